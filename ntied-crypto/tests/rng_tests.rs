@@ -0,0 +1,32 @@
+use ntied_crypto::{EphemeralKeyPair, PrivateKey};
+use rand::SeedableRng as _;
+use rand::rngs::StdRng;
+
+#[test]
+fn test_seeded_private_key_is_reproducible() {
+    let first = PrivateKey::generate_with_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+    let second = PrivateKey::generate_with_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+    assert_eq!(*first.to_pem().unwrap(), *second.to_pem().unwrap());
+    assert_eq!(
+        first.public_key().to_bytes().unwrap(),
+        second.public_key().to_bytes().unwrap()
+    );
+    let other = PrivateKey::generate_with_rng(&mut StdRng::seed_from_u64(7)).unwrap();
+    assert_ne!(*first.to_pem().unwrap(), *other.to_pem().unwrap());
+}
+
+#[test]
+fn test_seeded_ephemeral_key_pair_is_reproducible() {
+    let first = EphemeralKeyPair::generate_with_rng(&mut StdRng::seed_from_u64(42));
+    let second = EphemeralKeyPair::generate_with_rng(&mut StdRng::seed_from_u64(42));
+    assert_eq!(first.public_key_bytes(), second.public_key_bytes());
+    let other = EphemeralKeyPair::generate_with_rng(&mut StdRng::seed_from_u64(7));
+    assert_ne!(first.public_key_bytes(), other.public_key_bytes());
+}
+
+#[test]
+fn test_default_generation_is_random() {
+    let first = PrivateKey::generate().unwrap();
+    let second = PrivateKey::generate().unwrap();
+    assert_ne!(*first.to_pem().unwrap(), *second.to_pem().unwrap());
+}
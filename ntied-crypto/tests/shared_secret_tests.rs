@@ -0,0 +1,93 @@
+use ntied_crypto::{Cipher, EphemeralKeyPair, SharedSecret};
+
+const KEY: [u8; 32] = [42u8; 32];
+const NONCE: [u8; 12] = [1u8; 12];
+
+#[test]
+fn test_each_cipher_round_trips() {
+    for cipher in Cipher::preferred() {
+        let secret = SharedSecret::new(cipher, KEY);
+        assert_eq!(secret.cipher(), cipher);
+        let ciphertext = secret.encrypt_nonce(&NONCE, b"Hello, world!").unwrap();
+        assert_ne!(&ciphertext[..], b"Hello, world!");
+        let plaintext = secret.decrypt_nonce(&NONCE, &ciphertext).unwrap();
+        assert_eq!(plaintext, b"Hello, world!");
+    }
+}
+
+#[test]
+fn test_ciphers_are_not_interchangeable() {
+    let aes = SharedSecret::new(Cipher::Aes256Gcm, KEY);
+    let chacha = SharedSecret::new(Cipher::ChaCha20Poly1305, KEY);
+    let ciphertext = aes.encrypt_nonce(&NONCE, b"Hello, world!").unwrap();
+    assert!(chacha.decrypt_nonce(&NONCE, &ciphertext).is_err());
+    let ciphertext = chacha.encrypt_nonce(&NONCE, b"Hello, world!").unwrap();
+    assert!(aes.decrypt_nonce(&NONCE, &ciphertext).is_err());
+}
+
+#[test]
+fn test_ephemeral_exchange_with_cipher() {
+    let alice = EphemeralKeyPair::generate();
+    let bob = EphemeralKeyPair::generate();
+    let alice_secret = alice
+        .compute_shared_secret_with(bob.public_key_bytes(), Cipher::ChaCha20Poly1305)
+        .unwrap();
+    let bob_secret = bob
+        .compute_shared_secret_with(alice.public_key_bytes(), Cipher::ChaCha20Poly1305)
+        .unwrap();
+    let ciphertext = alice_secret.encrypt_nonce(&NONCE, b"Secret").unwrap();
+    assert_eq!(
+        bob_secret.decrypt_nonce(&NONCE, &ciphertext).unwrap(),
+        b"Secret"
+    );
+    let aes_secret = bob.compute_shared_secret(alice.public_key_bytes()).unwrap();
+    assert_eq!(aes_secret.cipher(), Cipher::Aes256Gcm);
+    assert!(aes_secret.decrypt_nonce(&NONCE, &ciphertext).is_err());
+}
+
+#[test]
+fn test_cipher_ids() {
+    for cipher in Cipher::preferred() {
+        assert_eq!(Cipher::from_u8(cipher.to_u8()), Some(cipher));
+    }
+    assert_eq!(Cipher::from_u8(0), None);
+    assert_eq!(Cipher::from_u8(255), None);
+}
+
+#[test]
+fn test_commitment_is_not_sent() {
+    let secret = SharedSecret::new(Cipher::Aes256Gcm, KEY);
+    let ciphertext = secret.encrypt_nonce(&NONCE, b"Hello, world!").unwrap();
+    assert_eq!(ciphertext.len(), b"Hello, world!".len() + 16);
+    // Other keys fail on the authentication tag
+    let other = SharedSecret::new(Cipher::Aes256Gcm, [43u8; 32]);
+    assert!(other.decrypt_nonce(&NONCE, &ciphertext).is_err());
+}
+
+#[test]
+fn test_bound_context_must_match() {
+    for cipher in Cipher::preferred() {
+        let secret = SharedSecret::new(cipher, KEY).bind(b"handshake");
+        let ciphertext = secret.encrypt_nonce(&NONCE, b"Hello, world!").unwrap();
+        let same = SharedSecret::new(cipher, KEY).bind(b"handshake");
+        assert_eq!(
+            same.decrypt_nonce(&NONCE, &ciphertext).unwrap(),
+            b"Hello, world!"
+        );
+        // A peer that saw other cipher offers can not open the packets
+        let other = SharedSecret::new(cipher, KEY).bind(b"downgraded");
+        assert!(other.decrypt_nonce(&NONCE, &ciphertext).is_err());
+        let unbound = SharedSecret::new(cipher, KEY);
+        assert!(unbound.decrypt_nonce(&NONCE, &ciphertext).is_err());
+    }
+}
+
+#[test]
+fn test_tampered_ciphertext_fails() {
+    let secret = SharedSecret::new(Cipher::ChaCha20Poly1305, KEY);
+    let mut ciphertext = secret.encrypt_nonce(&NONCE, b"Hello, world!").unwrap();
+    let last = ciphertext.len() - 1;
+    ciphertext[last] ^= 1;
+    assert!(secret.decrypt_nonce(&NONCE, &ciphertext).is_err());
+    assert!(secret.decrypt_nonce(&NONCE, &[0u8; 8]).is_err());
+}
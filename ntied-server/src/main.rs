@@ -2,6 +2,17 @@ use ntied_server::Server;
 use tracing_subscriber::layer::SubscriberExt as _;
 use tracing_subscriber::util::SubscriberInitExt as _;
 
+const USAGE: &str = "\
+Usage: ntied-server [--dual-stack] [--no-reflexive-addr] [--token TOKEN] [ADDR]
+
+  --dual-stack         Accept IPv4 and IPv6 clients on one socket
+  --no-reflexive-addr  Do not report the observed address to clients
+  --token TOKEN        Require TOKEN to register. The token is sent in cleartext,
+                       so it only keeps casual users out. Connect and pairing
+                       require registration and are gated too, the reflexive
+                       address stays open.
+";
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Initialize tracing subscriber with environment filter
@@ -12,11 +23,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
-    let addr = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| "0.0.0.0:39045".to_string());
-    tracing::info!(?addr, "Starting server");
-    let server = Server::new(&addr).await?;
+    let mut dual_stack = false;
+    let mut reflexive_addr = true;
+    let mut token = None;
+    let mut addr = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            "--dual-stack" => dual_stack = true,
+            "--no-reflexive-addr" => reflexive_addr = false,
+            "--token" => token = Some(args.next().ok_or("Missing value for --token")?),
+            _ => addr = Some(arg),
+        }
+    }
+    let server = if dual_stack {
+        let addr = addr.unwrap_or_else(|| "[::]:39045".to_string());
+        tracing::info!(?addr, "Starting dual-stack server");
+        Server::new_dual_stack(addr.parse()?).await?
+    } else {
+        let addr = addr.unwrap_or_else(|| "0.0.0.0:39045".to_string());
+        tracing::info!(?addr, "Starting server");
+        Server::new(&addr).await?
+    };
+    if token.is_some() {
+        tracing::info!("Registration requires a token");
+    }
+    let server = server.with_reflexive_addr(reflexive_addr).with_token(token);
     server.run().await?;
     Ok(())
 }
@@ -1,14 +1,20 @@
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use ntied_crypto::PublicKey;
 use ntied_transport::{
     Address, ServerConnectRequest, ServerConnectResponse, ServerErrorResponse,
-    ServerIncomingConnectionResponse, ServerRegisterRequest, ServerRegisterResponse, ServerRequest,
-    ServerResponse, ToAddress,
+    ServerIncomingConnectionResponse, ServerPairingCodeRequest, ServerPairingCodeResponse,
+    ServerReflexiveAddrRequest, ServerReflexiveAddrResponse, ServerRegisterRequest,
+    ServerRegisterResponse, ServerRequest, ServerResolvePairingCodeRequest,
+    ServerResolvePairingCodeResponse, ServerResponse, ServerUnregisterRequest, ToAddress,
 };
+use rand::Rng as _;
+use sha2::{Digest as _, Sha256};
+use socket2::{Domain, Protocol, Socket, Type};
+use subtle::ConstantTimeEq as _;
 use tokio::net::{ToSocketAddrs, UdpSocket};
 use tokio::sync::RwLock;
 use tokio::time::interval;
@@ -18,31 +24,79 @@ struct ClientInfo {
     addr: SocketAddr,
     public_key: Vec<u8>,
     address: Address,
+    // Issued on registration, required to unregister
+    session: [u8; 16],
     last_seen: Instant,
 }
 
+#[derive(Debug, Clone)]
+struct PairingEntry {
+    address: Address,
+    expires_at: Instant,
+}
+
 pub struct Server {
     socket: Arc<UdpSocket>,
     clients: Arc<RwLock<HashMap<Address, ClientInfo>>>,
+    // Normalized pairing code to the address it resolves to
+    pairing_codes: Arc<RwLock<HashMap<String, PairingEntry>>>,
+    pairing_code_ttl: Duration,
+    reflexive_addr: bool,
+    // Shared secret required for registration, open server if not set
+    token: Option<String>,
 }
 
 impl Server {
     const PACKET_SIZE: usize = 65536;
     const CLIENT_TIMEOUT: Duration = Duration::from_secs(32);
     const CLEANUP_INTERVAL: Duration = Duration::from_secs(10);
+    /// Default lifetime of pairing codes.
+    pub const PAIRING_CODE_TTL: Duration = Duration::from_secs(300);
+    // Unambiguous characters, without 0/O and 1/I
+    const PAIRING_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    const PAIRING_CODE_LEN: usize = 8;
 
     /// Create and bind a new coordination server
     pub async fn new(
         addr: impl ToSocketAddrs,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let socket = Arc::new(UdpSocket::bind(&addr).await?);
+        let socket = UdpSocket::bind(&addr).await?;
+        Self::from_socket(socket)
+    }
+
+    /// Create a server on an IPv6 address that also accepts IPv4 clients
+    pub async fn new_dual_stack(
+        addr: SocketAddr,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        if !addr.is_ipv6() {
+            return Err(format!("Dual-stack requires an IPv6 address, got {addr}").into());
+        }
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_only_v6(false)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&addr.into())?;
+        let socket = UdpSocket::from_std(socket.into())?;
+        Self::from_socket(socket)
+    }
+
+    fn from_socket(socket: UdpSocket) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         tracing::info!(addr = ?socket.local_addr()?, "Server started listening");
         Ok(Self {
-            socket,
+            socket: Arc::new(socket),
             clients: Arc::new(RwLock::new(HashMap::new())),
+            pairing_codes: Arc::new(RwLock::new(HashMap::new())),
+            pairing_code_ttl: Self::PAIRING_CODE_TTL,
+            reflexive_addr: true,
+            token: None,
         })
     }
 
+    /// Set lifetime of issued pairing codes
+    pub fn with_pairing_code_ttl(mut self, ttl: Duration) -> Self {
+        self.pairing_code_ttl = ttl;
+        self
+    }
+
     /// Get the server's socket address
     pub fn local_addr(&self) -> Result<SocketAddr, Box<dyn std::error::Error + Send + Sync>> {
         Ok(self.socket.local_addr()?)
@@ -60,6 +114,8 @@ impl Server {
                     continue;
                 }
             };
+            // IPv4 clients of a dual-stack socket are seen as IPv4-mapped addresses
+            let addr = SocketAddr::new(addr.ip().to_canonical(), addr.port());
             let data = &buf[..len];
             let request = match ServerRequest::deserialize(data) {
                 Ok(v) => v,
@@ -74,6 +130,20 @@ impl Server {
         }
     }
 
+    /// Enable or disable reporting observed addresses back to clients
+    pub fn with_reflexive_addr(mut self, enabled: bool) -> Self {
+        self.reflexive_addr = enabled;
+        self
+    }
+
+    /// Require clients to send the token on registration. Connect and pairing
+    /// requests need a registered client, so they are gated as well, while the
+    /// reflexive address is reported to anyone. The token travels in cleartext.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
     /// Handle incoming request
     async fn handle_request(
         &self,
@@ -90,10 +160,36 @@ impl Server {
             ServerRequest::Connect(req) => {
                 self.handle_connect(addr, req).await?;
             }
+            ServerRequest::Unregister(req) => {
+                self.handle_unregister(addr, req).await;
+            }
+            ServerRequest::PairingCode(req) => {
+                self.handle_pairing_code(addr, req).await?;
+            }
+            ServerRequest::ResolvePairingCode(req) => {
+                self.handle_resolve_pairing_code(addr, req).await?;
+            }
+            ServerRequest::ReflexiveAddr(req) => {
+                self.handle_reflexive_addr(addr, req).await?;
+            }
         }
         Ok(())
     }
 
+    /// Check the registration token, digests are compared so neither the bytes
+    /// nor the length leak through timing
+    fn is_valid_token(&self, token: Option<&str>) -> bool {
+        match (&self.token, token) {
+            (None, _) => true,
+            (Some(expected), Some(token)) => {
+                let expected = Sha256::digest(expected.as_bytes());
+                let token = Sha256::digest(token.as_bytes());
+                expected.ct_eq(&token).into()
+            }
+            (Some(_), None) => false,
+        }
+    }
+
     /// Handle client registration
     async fn handle_register(
         &self,
@@ -101,6 +197,18 @@ impl Server {
         req: ServerRegisterRequest,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         tracing::debug!(?addr, ?req.address, "Received registration request");
+        if !self.is_valid_token(req.token.as_deref()) {
+            tracing::warn!(?addr, ?req.address, "Invalid token in registration");
+            self.send_response(
+                addr,
+                ServerResponse::RegisterError(ServerErrorResponse {
+                    request_id: req.request_id,
+                    code: 4, // Invalid token
+                }),
+            )
+            .await?;
+            return Ok(());
+        }
         // Validate public key
         let public_key = match PublicKey::from_bytes(&req.public_key) {
             Ok(pk) => pk,
@@ -150,10 +258,13 @@ impl Server {
             .await?;
             return Ok(());
         }
+        let mut session = [0u8; 16];
+        rand::thread_rng().fill(&mut session);
         let client_info = ClientInfo {
             addr,
             public_key: req.public_key,
             address: req.address,
+            session,
             last_seen: Instant::now(),
         };
         {
@@ -169,6 +280,7 @@ impl Server {
             addr,
             ServerResponse::Register(ServerRegisterResponse {
                 request_id: req.request_id,
+                session,
             }),
         )
         .await?;
@@ -270,6 +382,152 @@ impl Server {
         Ok(())
     }
 
+    /// Issue a pairing code for the requesting client, replacing its previous code
+    async fn handle_pairing_code(
+        &self,
+        addr: SocketAddr,
+        req: ServerPairingCodeRequest,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(address) = self.registered_address(addr).await else {
+            tracing::warn!(?addr, "Pairing code request from unregistered client");
+            return self
+                .send_response(
+                    addr,
+                    ServerResponse::PairingCodeError(ServerErrorResponse {
+                        request_id: req.request_id,
+                        code: 20, // Not registered
+                    }),
+                )
+                .await;
+        };
+        let code = {
+            let mut codes = self.pairing_codes.write().await;
+            codes.retain(|_, entry| entry.address != address);
+            let code = loop {
+                let code = Self::generate_pairing_code();
+                if !codes.contains_key(&code) {
+                    break code;
+                }
+            };
+            codes.insert(
+                code.clone(),
+                PairingEntry {
+                    address,
+                    expires_at: Instant::now() + self.pairing_code_ttl,
+                },
+            );
+            code
+        };
+        tracing::info!(?address, "Pairing code issued");
+        self.send_response(
+            addr,
+            ServerResponse::PairingCode(ServerPairingCodeResponse {
+                request_id: req.request_id,
+                code: format!("{}-{}", &code[..4], &code[4..]),
+                expires_in: self
+                    .pairing_code_ttl
+                    .as_secs()
+                    .try_into()
+                    .unwrap_or(u32::MAX),
+            }),
+        )
+        .await
+    }
+
+    /// Resolve a pairing code, each code can be resolved once
+    async fn handle_resolve_pairing_code(
+        &self,
+        addr: SocketAddr,
+        req: ServerResolvePairingCodeRequest,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.registered_address(addr).await.is_none() {
+            tracing::warn!(
+                ?addr,
+                "Resolve pairing code request from unregistered client"
+            );
+            return self
+                .send_response(
+                    addr,
+                    ServerResponse::ResolvePairingCodeError(ServerErrorResponse {
+                        request_id: req.request_id,
+                        code: 20, // Not registered
+                    }),
+                )
+                .await;
+        }
+        let code = Self::normalize_pairing_code(&req.code);
+        let entry = self
+            .pairing_codes
+            .write()
+            .await
+            .remove(&code)
+            .filter(|entry| entry.expires_at > Instant::now());
+        let response = match entry {
+            Some(entry) => {
+                tracing::info!(?addr, address = ?entry.address, "Pairing code resolved");
+                ServerResponse::ResolvePairingCode(ServerResolvePairingCodeResponse {
+                    request_id: req.request_id,
+                    address: entry.address,
+                })
+            }
+            None => {
+                tracing::debug!(?addr, "Unknown or expired pairing code");
+                ServerResponse::ResolvePairingCodeError(ServerErrorResponse {
+                    request_id: req.request_id,
+                    code: 21, // Code not found or expired
+                })
+            }
+        };
+        self.send_response(addr, response).await
+    }
+
+    /// Report the source address of the request, like a STUN binding response
+    async fn handle_reflexive_addr(
+        &self,
+        addr: SocketAddr,
+        req: ServerReflexiveAddrRequest,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        tracing::debug!(?addr, "Received reflexive address request");
+        let response = if self.reflexive_addr {
+            ServerResponse::ReflexiveAddr(ServerReflexiveAddrResponse {
+                request_id: req.request_id,
+                addr,
+            })
+        } else {
+            ServerResponse::ReflexiveAddrError(ServerErrorResponse {
+                request_id: req.request_id,
+                code: 30, // Reporting disabled
+            })
+        };
+        self.send_response(addr, response).await
+    }
+
+    /// Address of the client registered from the socket address, refreshes its last seen time
+    async fn registered_address(&self, addr: SocketAddr) -> Option<Address> {
+        let mut clients = self.clients.write().await;
+        let client = clients.values_mut().find(|c| c.addr == addr)?;
+        client.last_seen = Instant::now();
+        Some(client.address)
+    }
+
+    fn generate_pairing_code() -> String {
+        let mut rng = rand::thread_rng();
+        (0..Self::PAIRING_CODE_LEN)
+            .map(|_| {
+                let index = rng.gen_range(0..Self::PAIRING_CODE_ALPHABET.len());
+                Self::PAIRING_CODE_ALPHABET[index] as char
+            })
+            .collect()
+    }
+
+    /// Drop separators and case, so "abcd-efgh" matches "ABCDEFGH"
+    fn normalize_pairing_code(code: &str) -> String {
+        code.chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    }
+
     /// Handle heartbeat message
     async fn handle_heartbeat(
         &self,
@@ -291,6 +549,18 @@ impl Server {
         }
     }
 
+    /// Handle client leaving the server, the session from its registration must match
+    async fn handle_unregister(&self, addr: SocketAddr, req: ServerUnregisterRequest) {
+        let mut clients = self.clients.write().await;
+        clients.retain(|address, client| {
+            let keep = client.addr != addr || !bool::from(client.session.ct_eq(&req.session));
+            if !keep {
+                tracing::info!(?address, ?addr, "Client unregistered");
+            }
+            keep
+        });
+    }
+
     /// Send response to a client
     async fn send_response(
         &self,
@@ -299,6 +569,12 @@ impl Server {
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         tracing::debug!(?addr, "Sending response to client");
         let data = response.serialize();
+        let addr = match addr.ip() {
+            IpAddr::V4(ip) if self.socket.local_addr()?.is_ipv6() => {
+                SocketAddr::new(IpAddr::V6(ip.to_ipv6_mapped()), addr.port())
+            }
+            _ => addr,
+        };
         self.socket.send_to(&data, addr).await?;
         Ok(())
     }
@@ -306,6 +582,7 @@ impl Server {
     /// Spawn cleanup task to remove inactive clients
     fn spawn_cleanup_task(&self) -> tokio::task::JoinHandle<()> {
         let clients = self.clients.clone();
+        let pairing_codes = self.pairing_codes.clone();
         tokio::spawn(async move {
             let mut interval = interval(Self::CLEANUP_INTERVAL);
             loop {
@@ -323,6 +600,11 @@ impl Server {
                     }
                     keep
                 });
+                drop(clients);
+                pairing_codes
+                    .write()
+                    .await
+                    .retain(|_, entry| entry.expires_at > now);
             }
         })
     }
@@ -9,6 +9,11 @@ impl<'a> Reader<'a> {
         Self { data }
     }
 
+    /// Whether all data was read.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     pub fn read_u8(&mut self) -> Result<u8, String> {
         if self.data.is_empty() {
             return Err("Unexpected end of data".to_string());
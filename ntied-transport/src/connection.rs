@@ -2,7 +2,7 @@ use std::net::SocketAddr;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
-use ntied_crypto::{EphemeralKeyPair, PublicKey, SharedSecret};
+use ntied_crypto::{Cipher, EphemeralKeyPair, PublicKey, SharedSecret};
 use rand::Rng as _;
 use tokio::sync::{Mutex as TokioMutex, mpsc};
 use tokio::task::JoinHandle;
@@ -67,7 +67,8 @@ impl Connection {
         peer_public_key: PublicKey,
         mut packet_rx: mpsc::Receiver<(SocketAddr, Packet)>,
     ) -> Result<Self, Error> {
-        let mut encryption_state = EncryptionState::new();
+        let mut encryption_state = EncryptionState::new(transport.replay_window_size());
+        let own_ciphers = transport.ciphers();
         let (data_tx, data_rx) = mpsc::channel(Self::MAX_PACKETS);
         let data_rx = TokioMutex::new(data_rx);
         let handshake_task = async {
@@ -80,17 +81,20 @@ impl Connection {
                         .expect("Failed to serialize public key");
                     let ephemeral_public_key =
                         encryption_state.ephemeral_keypair.public_key_bytes();
+                    let ciphers = cipher_ids(&own_ciphers);
                     let mut packet_bytes = Vec::new();
                     let mut packet_writer = Writer::new(&mut packet_bytes);
                     packet_writer.write_u32(source_id);
                     packet_writer.write_bytes(&public_key);
                     packet_writer.write_bytes(&ephemeral_public_key);
+                    packet_writer.write_bytes(&ciphers);
                     Packet::Handshake(HandshakePacket {
                         source_id,
                         public_key,
                         address: transport.address,
                         peer_address,
                         ephemeral_public_key,
+                        ciphers,
                         signature: transport.private_key.sign(packet_bytes),
                     })
                 };
@@ -102,7 +106,10 @@ impl Connection {
                 tokio::time::sleep(Self::HANDSHAKE_INTERVAL).await;
             }
         };
+        // The handshake goes first, the ack may already be buffered while
+        // the peer still waits for our handshake.
         let target_id = tokio::select! {
+            biased;
             _ = handshake_task => {
                 return Err("Handshake failed".into());
             },
@@ -127,6 +134,7 @@ impl Connection {
                         packet_writer.write_u32(handshake_ack_package.source_id);
                         packet_writer.write_bytes(&handshake_ack_package.public_key);
                         packet_writer.write_bytes(&handshake_ack_package.ephemeral_public_key);
+                        packet_writer.write_bytes(&handshake_ack_package.ciphers);
                         if !public_key
                             .verify(&packet_bytes, &handshake_ack_package.signature)
                             .unwrap_or(false)
@@ -138,11 +146,18 @@ impl Connection {
                             tracing::warn!("Invalid address in handshake ack");
                             return Err("Invalid address".into());
                         }
+                        let peer_ciphers = parse_cipher_ids(&handshake_ack_package.ciphers);
+                        let Some(cipher) = select_cipher(&own_ciphers, &peer_ciphers) else {
+                            tracing::warn!(?peer_ciphers, "No common cipher in handshake ack");
+                            return Err("No common cipher".into());
+                        };
+                        encryption_state.handshake =
+                            handshake_context(&cipher_ids(&own_ciphers), &handshake_ack_package.ciphers);
                         let shared_secret = match encryption_state
                             .ephemeral_keypair
-                            .compute_shared_secret(&handshake_ack_package.ephemeral_public_key)
+                            .compute_shared_secret_with(&handshake_ack_package.ephemeral_public_key, cipher)
                         {
-                            Ok(secret) => secret,
+                            Ok(secret) => secret.bind(&encryption_state.handshake),
                             Err(err) => {
                                 tracing::warn!(?err, "Failed to compute shared secret");
                                 return Err("Failed to compute shared secret".into());
@@ -191,7 +206,8 @@ impl Connection {
         peer_public_key: PublicKey,
         mut packet_rx: mpsc::Receiver<(SocketAddr, Packet)>,
     ) -> Result<Connection, Error> {
-        let mut encryption_state = EncryptionState::new();
+        let mut encryption_state = EncryptionState::new(transport.replay_window_size());
+        let own_ciphers = transport.ciphers();
         let (data_tx, data_rx) = mpsc::channel(Self::MAX_PACKETS);
         let data_rx = TokioMutex::new(data_rx);
         let handshake_ack_task = async {
@@ -204,12 +220,14 @@ impl Connection {
                         .expect("Failed to serialize public key");
                     let ephemeral_public_key =
                         encryption_state.ephemeral_keypair.public_key_bytes();
+                    let ciphers = cipher_ids(&own_ciphers);
                     let mut packet_bytes = Vec::new();
                     let mut packet_writer = Writer::new(&mut packet_bytes);
                     packet_writer.write_u32(target_id);
                     packet_writer.write_u32(source_id);
                     packet_writer.write_bytes(&public_key);
                     packet_writer.write_bytes(&ephemeral_public_key);
+                    packet_writer.write_bytes(&ciphers);
                     Packet::HandshakeAck(HandshakeAckPacket {
                         target_id,
                         source_id,
@@ -217,6 +235,7 @@ impl Connection {
                         address: transport.address,
                         peer_address,
                         ephemeral_public_key,
+                        ciphers,
                         signature: transport.private_key.sign(packet_bytes),
                     })
                 };
@@ -263,6 +282,7 @@ impl Connection {
                         packet_writer.write_u32(handshake_package.source_id);
                         packet_writer.write_bytes(&handshake_package.public_key);
                         packet_writer.write_bytes(&handshake_package.ephemeral_public_key);
+                        packet_writer.write_bytes(&handshake_package.ciphers);
                         if !public_key
                             .verify(&packet_bytes, &handshake_package.signature)
                             .unwrap_or(false)
@@ -274,11 +294,18 @@ impl Connection {
                             tracing::warn!("Invalid address in handshake ack");
                             return Err("Invalid address".into());
                         }
+                        let peer_ciphers = parse_cipher_ids(&handshake_package.ciphers);
+                        let Some(cipher) = select_cipher(&peer_ciphers, &own_ciphers) else {
+                            tracing::warn!(?peer_ciphers, "No common cipher in handshake");
+                            return Err("No common cipher".into());
+                        };
+                        encryption_state.handshake =
+                            handshake_context(&handshake_package.ciphers, &cipher_ids(&own_ciphers));
                         let shared_secret = match encryption_state
                             .ephemeral_keypair
-                            .compute_shared_secret(&handshake_package.ephemeral_public_key)
+                            .compute_shared_secret_with(&handshake_package.ephemeral_public_key, cipher)
                         {
-                            Ok(secret) => secret,
+                            Ok(secret) => secret.bind(&encryption_state.handshake),
                             Err(err) => {
                                 tracing::warn!(?err, "Failed to compute shared secret");
                                 return Err("Failed to compute shared secret".into());
@@ -374,6 +401,11 @@ impl Connection {
         &self.peer_public_key
     }
 
+    /// Cipher negotiated with the peer during the handshake.
+    pub fn cipher(&self) -> Cipher {
+        self.encryption_state.lock().unwrap().cipher()
+    }
+
     async fn main_loop(
         mut packet_rx: mpsc::Receiver<(SocketAddr, Packet)>,
         data_tx: mpsc::Sender<Vec<u8>>,
@@ -506,13 +538,20 @@ impl Connection {
                                         continue;
                                     }
                                 };
-                                match encrypted_msg.decrypt(shared_secret) {
+                                let decrypted = match encrypted_msg.decrypt(shared_secret) {
                                     Ok(msg) => msg,
                                     Err(err) => {
                                         tracing::warn!(?err, "Failed to decrypt message");
                                         continue;
                                     }
+                                };
+                                // Only authenticated packets may advance the replay window.
+                                let sequence = encrypted_msg.sequence();
+                                if !state.replay_window.accept(sequence) {
+                                    tracing::debug!(sequence, "Dropping replayed or stale packet");
+                                    continue;
                                 }
+                                decrypted
                             };
                             // Update last heartbeat
                             last_heartbeat = Instant::now();
@@ -544,9 +583,9 @@ impl Connection {
                                         let next_keypair = state.next_ephemeral_keypair.as_ref().unwrap();
                                         let next_public_key = next_keypair.public_key_bytes();
                                         let next_secret = match next_keypair
-                                            .compute_shared_secret(&rotate_msg.ephemeral_public_key)
+                                            .compute_shared_secret_with(&rotate_msg.ephemeral_public_key, state.cipher())
                                         {
-                                            Ok(secret) => secret,
+                                            Ok(secret) => secret.bind(&state.handshake),
                                             Err(err) => {
                                                 tracing::warn!(?err, "Failed to compute next shared secret");
                                                 continue;
@@ -596,9 +635,9 @@ impl Connection {
                                     if let Some(next_keypair) = &state.next_ephemeral_keypair {
                                         // Compute next shared secret
                                         let next_secret = match next_keypair
-                                            .compute_shared_secret(&rotate_ack_msg.ephemeral_public_key)
+                                            .compute_shared_secret_with(&rotate_ack_msg.ephemeral_public_key, state.cipher())
                                         {
-                                            Ok(secret) => secret,
+                                            Ok(secret) => secret.bind(&state.handshake),
                                             Err(err) => {
                                                 tracing::warn!(?err, "Failed to compute next shared secret");
                                                 continue;
@@ -651,10 +690,13 @@ struct EncryptionState {
     next_ephemeral_keypair: Option<EphemeralKeyPair>,
     next_shared_secret: Option<SharedSecret>,
     nonce_counter: u64,
+    replay_window: ReplayWindow,
+    // Cipher offers of both sides, every shared secret is bound to them
+    handshake: Vec<u8>,
 }
 
 impl EncryptionState {
-    fn new() -> Self {
+    fn new(replay_window_size: usize) -> Self {
         Self {
             epoch: EncryptionEpoch::new(0),
             ephemeral_keypair: EphemeralKeyPair::generate(),
@@ -662,9 +704,18 @@ impl EncryptionState {
             next_ephemeral_keypair: None,
             next_shared_secret: None,
             nonce_counter: 0,
+            replay_window: ReplayWindow::new(replay_window_size),
+            handshake: Vec::new(),
         }
     }
 
+    fn cipher(&self) -> Cipher {
+        self.shared_secret
+            .as_ref()
+            .map(SharedSecret::cipher)
+            .unwrap_or_default()
+    }
+
     fn generate_nonce(&mut self) -> [u8; 12] {
         let mut nonce = [0u8; 12];
         nonce[..8].copy_from_slice(&self.nonce_counter.to_le_bytes());
@@ -673,3 +724,93 @@ impl EncryptionState {
         nonce
     }
 }
+
+/// Picks the first cipher preferred by the connecting side that is also
+/// supported by the accepting side, so both peers agree on the result.
+pub fn select_cipher(connecting: &[Cipher], accepting: &[Cipher]) -> Option<Cipher> {
+    connecting.iter().find(|v| accepting.contains(v)).copied()
+}
+
+/// Context the shared secrets are bound to. Both peers select the cipher
+/// from the same signed offers, so a connection only works when neither
+/// offer was changed on the way and both selected the same cipher.
+fn handshake_context(connecting: &[u8], accepting: &[u8]) -> Vec<u8> {
+    let mut context = Vec::new();
+    let mut writer = Writer::new(&mut context);
+    writer.write_bytes(connecting);
+    writer.write_bytes(accepting);
+    context
+}
+
+fn cipher_ids(ciphers: &[Cipher]) -> Vec<u8> {
+    ciphers.iter().map(|v| v.to_u8()).collect()
+}
+
+/// Ciphers announced by the peer, unknown identifiers are skipped.
+fn parse_cipher_ids(ids: &[u8]) -> Vec<Cipher> {
+    ids.iter().filter_map(|v| Cipher::from_u8(*v)).collect()
+}
+
+/// Sliding window over received packet sequence numbers.
+///
+/// Remembers which of the last `size` sequence numbers were already seen, so
+/// duplicated packets are rejected while reordered ones inside the window are
+/// still accepted. Anything older than the window is rejected as stale.
+pub struct ReplayWindow {
+    highest: Option<u64>,
+    seen: Vec<bool>,
+}
+
+impl ReplayWindow {
+    pub const DEFAULT_SIZE: usize = 1024;
+
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "Replay window size must be positive");
+        Self {
+            highest: None,
+            seen: vec![false; size],
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Records the sequence number and returns whether the packet is fresh.
+    pub fn accept(&mut self, sequence: u64) -> bool {
+        let size = self.seen.len() as u64;
+        let slot = (sequence % size) as usize;
+        let highest = match self.highest {
+            Some(v) => v,
+            None => {
+                self.highest = Some(sequence);
+                self.seen[slot] = true;
+                return true;
+            }
+        };
+        if sequence > highest {
+            // Forget the slots reused by the sequence numbers we skip over.
+            let skipped = (sequence - highest).min(size);
+            for i in 0..skipped {
+                self.seen[((sequence - i) % size) as usize] = false;
+            }
+            self.highest = Some(sequence);
+            self.seen[slot] = true;
+            return true;
+        }
+        if highest - sequence >= size {
+            return false;
+        }
+        if self.seen[slot] {
+            return false;
+        }
+        self.seen[slot] = true;
+        true
+    }
+}
+
+impl Default for ReplayWindow {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE)
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::{Address, NatType};
+
+/// Outcome of a connection attempt to one endpoint of the peer.
+#[derive(Debug, Clone)]
+pub struct CandidateReport {
+    pub addr: SocketAddr,
+    /// Handshake time on success, the error otherwise.
+    pub result: Result<Duration, String>,
+}
+
+/// Steps of a connection attempt made by [`crate::Transport::diagnose`],
+/// the text form is meant to be pasted into bug reports.
+#[derive(Debug, Clone)]
+pub struct ConnectivityReport {
+    pub target: Address,
+    pub local_addr: SocketAddr,
+    /// Public address observed by the discovery backend.
+    pub reflexive_addr: Result<SocketAddr, String>,
+    /// Time the discovery backend took to find the peer.
+    pub lookup: Result<Duration, String>,
+    /// Endpoints of the peer in the order they were tried.
+    pub candidates: Vec<CandidateReport>,
+}
+
+impl ConnectivityReport {
+    /// Whether the discovery backend answered any request.
+    pub fn server_reachable(&self) -> bool {
+        self.reflexive_addr.is_ok() || self.lookup.is_ok()
+    }
+
+    pub fn nat_type(&self) -> Option<NatType> {
+        let reflexive_addr = self.reflexive_addr.as_ref().ok()?;
+        Some(NatType::classify(self.local_addr, *reflexive_addr))
+    }
+
+    /// Whether the handshake with any candidate succeeded.
+    pub fn connected(&self) -> bool {
+        self.candidates.iter().any(|v| v.result.is_ok())
+    }
+}
+
+impl fmt::Display for ConnectivityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Target: {}", self.target)?;
+        writeln!(f, "Local address: {}", self.local_addr)?;
+        match &self.reflexive_addr {
+            Ok(addr) => writeln!(f, "Reflexive address: {addr}")?,
+            Err(err) => writeln!(f, "Reflexive address: failed: {err}")?,
+        }
+        if let Some(nat_type) = self.nat_type() {
+            writeln!(f, "NAT: {}", nat_type.label())?;
+        }
+        let reachable = if self.server_reachable() { "yes" } else { "no" };
+        writeln!(f, "Server reachable: {reachable}")?;
+        match &self.lookup {
+            Ok(elapsed) => writeln!(f, "Lookup: ok in {}ms", elapsed.as_millis())?,
+            Err(err) => writeln!(f, "Lookup: failed: {err}")?,
+        }
+        for candidate in &self.candidates {
+            match &candidate.result {
+                Ok(elapsed) => writeln!(
+                    f,
+                    "Candidate {}: connected in {}ms",
+                    candidate.addr,
+                    elapsed.as_millis()
+                )?,
+                Err(err) => writeln!(f, "Candidate {}: failed: {err}", candidate.addr)?,
+            }
+        }
+        let result = if self.connected() {
+            "connected"
+        } else {
+            "not connected"
+        };
+        write!(f, "Result: {result}")
+    }
+}
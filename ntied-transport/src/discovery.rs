@@ -0,0 +1,98 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ntied_crypto::PublicKey;
+
+use crate::{Address, Error};
+
+/// Backend that makes peers findable by their address, such as the
+/// coordination server, a DHT, local network discovery or a static list.
+///
+/// Connection logic only relies on this trait, the transport uses the
+/// coordination server unless another backend is given to
+/// [`crate::Transport::bind_with_discovery`].
+#[async_trait]
+pub trait Discovery: Send + Sync {
+    /// Publishes the endpoint of `address`, `addr` is the local socket
+    /// address for backends that cannot observe the public one.
+    async fn announce(
+        &self,
+        address: Address,
+        public_key: &PublicKey,
+        addr: SocketAddr,
+    ) -> Result<(), Error>;
+
+    /// Finds the endpoint of the peer and tells it to expect a connection
+    /// identified by `source_id`.
+    async fn lookup(&self, address: Address, source_id: u32) -> Result<PeerInfo, Error>;
+
+    /// Waits for the next peer that looked us up.
+    async fn accept(&self) -> Result<PeerInfo, Error>;
+
+    /// Removes the announcement, peers can no longer find us.
+    async fn withdraw(&self) -> Result<(), Error>;
+
+    /// Requests a short-lived code that resolves to the own address.
+    async fn pairing_code(&self) -> Result<PairingCode, Error> {
+        Err("Pairing codes are not supported by discovery".into())
+    }
+
+    /// Resolves a pairing code to the address of the client that requested it.
+    async fn resolve_pairing_code(&self, _code: &str) -> Result<Address, Error> {
+        Err("Pairing codes are not supported by discovery".into())
+    }
+
+    /// Public address of this client as observed by the backend.
+    async fn reflexive_addr(&self) -> Result<SocketAddr, Error> {
+        Err("Reflexive address is not supported by discovery".into())
+    }
+}
+
+/// Endpoint of a peer returned by [`Discovery`].
+#[derive(Clone)]
+pub struct PeerInfo {
+    pub addr: SocketAddr,
+    pub address: Address,
+    pub public_key: PublicKey,
+    /// Connection id chosen by the peer, set for peers that looked us up.
+    pub source_id: Option<u32>,
+}
+
+/// Short-lived code resolving to the address of the client that requested it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairingCode {
+    pub code: String,
+    pub expires_in: Duration,
+}
+
+/// Rough NAT behaviour guessed from the local and the server-observed address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatType {
+    /// The server sees the local address, no translation happens.
+    Open,
+    /// The address is translated but the port is kept, hole punching usually works.
+    PortPreserving,
+    /// The port is remapped, hole punching may fail with symmetric NATs.
+    PortMapping,
+}
+
+impl NatType {
+    pub fn classify(local: SocketAddr, reflexive: SocketAddr) -> Self {
+        if local.port() != reflexive.port() {
+            Self::PortMapping
+        } else if local.ip() == reflexive.ip().to_canonical() {
+            Self::Open
+        } else {
+            Self::PortPreserving
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Open => "No NAT",
+            Self::PortPreserving => "Port-preserving NAT",
+            Self::PortMapping => "Port-mapping NAT",
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use ntied_crypto::PublicKey;
+use sha2::{Digest as _, Sha256};
+use tokio::net::UdpSocket;
+use tokio::sync::{Mutex as TokioMutex, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, timeout_at};
+
+use crate::byteio::{Reader, Writer};
+use crate::{Address, Discovery, Error, PairingCode, PeerInfo};
+
+/// Endpoint of a client published on the local network.
+#[derive(Clone)]
+pub struct LanRecord {
+    pub address: Address,
+    pub public_key: PublicKey,
+    /// Transport socket of the client.
+    pub addr: SocketAddr,
+    /// Socket receiving connection notifications of [`LanDiscovery`].
+    pub notify_addr: SocketAddr,
+}
+
+/// Directory of clients on the local network used by [`LanDiscovery`].
+#[async_trait]
+pub trait LanRegistry: Send + Sync {
+    async fn publish(&self, record: LanRecord) -> Result<(), Error>;
+
+    async fn unpublish(&self, address: Address) -> Result<(), Error>;
+
+    /// Finds the record of the address, `None` when nobody answered.
+    async fn resolve(&self, address: Address) -> Result<Option<LanRecord>, Error>;
+}
+
+/// [`LanRegistry`] announcing records as `_ntied._udp.local.` mDNS services.
+///
+/// The instance name is derived from the address because DNS names are
+/// case-insensitive, the address itself is carried in a TXT property.
+pub struct MdnsRegistry {
+    daemon: ServiceDaemon,
+    resolve_timeout: Duration,
+    // The daemon keeps one browse per service type
+    resolving: TokioMutex<()>,
+}
+
+impl MdnsRegistry {
+    const SERVICE_TYPE: &str = "_ntied._udp.local.";
+    const DEFAULT_RESOLVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            daemon: ServiceDaemon::new()?,
+            resolve_timeout: Self::DEFAULT_RESOLVE_TIMEOUT,
+            resolving: TokioMutex::new(()),
+        })
+    }
+
+    /// Set how long a lookup waits for an answer on the local network.
+    pub fn with_resolve_timeout(mut self, resolve_timeout: Duration) -> Self {
+        self.resolve_timeout = resolve_timeout;
+        self
+    }
+
+    fn instance_name(address: Address) -> String {
+        hex::encode(&Sha256::digest(address.as_bytes())[..16])
+    }
+
+    fn fullname(address: Address) -> String {
+        format!("{}.{}", Self::instance_name(address), Self::SERVICE_TYPE)
+    }
+
+    fn parse_record(info: &ServiceInfo, address: Address) -> Option<LanRecord> {
+        if info.get_property_val_str("address")? != address.to_string() {
+            return None;
+        }
+        let public_key = STANDARD
+            .decode(info.get_property_val_str("key")?)
+            .ok()
+            .and_then(|v| PublicKey::from_bytes(&v).ok())?;
+        let notify_port = info.get_property_val_str("notify")?.parse().ok()?;
+        let ip = info
+            .get_addresses()
+            .iter()
+            .min_by_key(|ip| ip.is_ipv6())
+            .copied()?;
+        Some(LanRecord {
+            address,
+            public_key,
+            addr: SocketAddr::new(ip, info.get_port()),
+            notify_addr: SocketAddr::new(ip, notify_port),
+        })
+    }
+}
+
+#[async_trait]
+impl LanRegistry for MdnsRegistry {
+    async fn publish(&self, record: LanRecord) -> Result<(), Error> {
+        let instance_name = Self::instance_name(record.address);
+        let properties = HashMap::from([
+            ("address".to_string(), record.address.to_string()),
+            (
+                "key".to_string(),
+                STANDARD.encode(record.public_key.to_bytes()?),
+            ),
+            ("notify".to_string(), record.notify_addr.port().to_string()),
+        ]);
+        let info = ServiceInfo::new(
+            Self::SERVICE_TYPE,
+            &instance_name,
+            &format!("{instance_name}.local."),
+            (),
+            record.addr.port(),
+            properties,
+        )?
+        .enable_addr_auto();
+        self.daemon.register(info)?;
+        Ok(())
+    }
+
+    async fn unpublish(&self, address: Address) -> Result<(), Error> {
+        self.daemon.unregister(&Self::fullname(address))?;
+        Ok(())
+    }
+
+    async fn resolve(&self, address: Address) -> Result<Option<LanRecord>, Error> {
+        let _resolving = self.resolving.lock().await;
+        let fullname = Self::fullname(address);
+        let events = self.daemon.browse(Self::SERVICE_TYPE)?;
+        let deadline = Instant::now() + self.resolve_timeout;
+        let mut record = None;
+        while let Ok(Ok(event)) = timeout_at(deadline, events.recv_async()).await {
+            if let ServiceEvent::ServiceResolved(info) = event
+                && info.get_fullname() == fullname
+            {
+                record = Self::parse_record(&info, address);
+                if record.is_some() {
+                    break;
+                }
+            }
+        }
+        if let Err(err) = self.daemon.stop_browse(Self::SERVICE_TYPE) {
+            tracing::debug!(?err, "Cannot stop mDNS browse");
+        }
+        Ok(record)
+    }
+}
+
+/// [`Discovery`] backend for peers on the same network.
+///
+/// Records are published through a [`LanRegistry`], a peer looking us up
+/// sends a notification datagram to our notify socket so that the
+/// connection can be accepted without a server. The lookup succeeds only
+/// after the peer has picked the notification up, otherwise the handshake
+/// could reach it before it expects the connection.
+pub struct LanDiscovery {
+    registry: Arc<dyn LanRegistry>,
+    socket: Arc<UdpSocket>,
+    own: Mutex<Option<LanRecord>>,
+    accept_rx: TokioMutex<mpsc::Receiver<(PeerInfo, SocketAddr)>>,
+    pending: Arc<Mutex<HashMap<u32, oneshot::Sender<()>>>>,
+    receiver_task: JoinHandle<()>,
+}
+
+impl LanDiscovery {
+    const NOTIFY_VERSION: u8 = 1;
+    const NOTIFY: u8 = 1;
+    const NOTIFY_ACK: u8 = 2;
+    const NOTIFY_TIMEOUT: Duration = Duration::from_secs(1);
+    const PACKET_SIZE: usize = 2048;
+
+    pub async fn new(registry: Arc<dyn LanRegistry>) -> Result<Self, Error> {
+        let socket = Arc::new(UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?);
+        let (accept_tx, accept_rx) = mpsc::channel(100);
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let receiver_task = tokio::spawn(Self::receiver_loop(
+            socket.clone(),
+            accept_tx,
+            pending.clone(),
+        ));
+        Ok(Self {
+            registry,
+            socket,
+            own: Mutex::new(None),
+            accept_rx: TokioMutex::new(accept_rx),
+            pending,
+            receiver_task,
+        })
+    }
+
+    /// Local discovery over mDNS.
+    pub async fn mdns() -> Result<Self, Error> {
+        Self::new(Arc::new(MdnsRegistry::new()?)).await
+    }
+
+    async fn receiver_loop(
+        socket: Arc<UdpSocket>,
+        accept_tx: mpsc::Sender<(PeerInfo, SocketAddr)>,
+        pending: Arc<Mutex<HashMap<u32, oneshot::Sender<()>>>>,
+    ) {
+        let mut buf = [0u8; Self::PACKET_SIZE];
+        loop {
+            let (len, addr) = match socket.recv_from(&mut buf).await {
+                Ok(v) => v,
+                Err(err) => {
+                    tracing::debug!(?err, "Cannot receive LAN notification");
+                    continue;
+                }
+            };
+            if let Some(source_id) = Self::parse_ack(&buf[..len]) {
+                if let Some(tx) = pending.lock().unwrap().remove(&source_id) {
+                    let _ = tx.send(());
+                }
+                continue;
+            }
+            let peer_info = match Self::parse_notification(&buf[..len], addr.ip()) {
+                Ok(v) => v,
+                Err(err) => {
+                    tracing::debug!(?addr, ?err, "Invalid LAN notification");
+                    continue;
+                }
+            };
+            tracing::debug!(peer_addr = ?peer_info.addr, "Received LAN connection notification");
+            if accept_tx.send((peer_info, addr)).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    fn notification(record: &LanRecord, source_id: u32) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        let mut writer = Writer::new(&mut bytes);
+        writer.write_u8(Self::NOTIFY_VERSION);
+        writer.write_u8(Self::NOTIFY);
+        writer.write_array(record.address.as_bytes());
+        writer.write_bytes(&record.public_key.to_bytes()?);
+        writer.write_u16(record.addr.port());
+        writer.write_u32(source_id);
+        Ok(bytes)
+    }
+
+    fn parse_notification(bytes: &[u8], ip: IpAddr) -> Result<PeerInfo, Error> {
+        let mut reader = Reader::new(bytes);
+        if reader.read_u8()? != Self::NOTIFY_VERSION {
+            return Err("Unsupported notification version".into());
+        }
+        if reader.read_u8()? != Self::NOTIFY {
+            return Err("Unexpected notification kind".into());
+        }
+        let address = Address::from_bytes(reader.read_array()?);
+        let public_key = PublicKey::from_bytes(&reader.read_bytes()?)?;
+        let port = reader.read_u16()?;
+        let source_id = reader.read_u32()?;
+        Ok(PeerInfo {
+            addr: SocketAddr::new(ip.to_canonical(), port),
+            address,
+            public_key,
+            source_id: Some(source_id),
+        })
+    }
+
+    fn ack(source_id: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut writer = Writer::new(&mut bytes);
+        writer.write_u8(Self::NOTIFY_VERSION);
+        writer.write_u8(Self::NOTIFY_ACK);
+        writer.write_u32(source_id);
+        bytes
+    }
+
+    fn parse_ack(bytes: &[u8]) -> Option<u32> {
+        let mut reader = Reader::new(bytes);
+        if reader.read_u8().ok()? != Self::NOTIFY_VERSION
+            || reader.read_u8().ok()? != Self::NOTIFY_ACK
+        {
+            return None;
+        }
+        reader.read_u32().ok()
+    }
+}
+
+#[async_trait]
+impl Discovery for LanDiscovery {
+    async fn announce(
+        &self,
+        address: Address,
+        public_key: &PublicKey,
+        addr: SocketAddr,
+    ) -> Result<(), Error> {
+        let record = LanRecord {
+            address,
+            public_key: public_key.clone(),
+            addr,
+            notify_addr: SocketAddr::new(addr.ip(), self.socket.local_addr()?.port()),
+        };
+        self.registry.publish(record.clone()).await?;
+        *self.own.lock().unwrap() = Some(record);
+        Ok(())
+    }
+
+    async fn lookup(&self, address: Address, source_id: u32) -> Result<PeerInfo, Error> {
+        let own = self.own.lock().unwrap().clone().ok_or("Not announced")?;
+        let record = self
+            .registry
+            .resolve(address)
+            .await?
+            .ok_or("Peer not found on local network")?;
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(source_id, ack_tx);
+        let notified = async {
+            self.socket
+                .send_to(&Self::notification(&own, source_id)?, record.notify_addr)
+                .await?;
+            tokio::time::timeout(Self::NOTIFY_TIMEOUT, ack_rx)
+                .await
+                .map_err(|_| "Peer did not answer on local network")?
+                .map_err(|_| "LAN discovery closed")?;
+            Ok::<_, Error>(())
+        }
+        .await;
+        self.pending.lock().unwrap().remove(&source_id);
+        notified?;
+        Ok(PeerInfo {
+            addr: record.addr,
+            address,
+            public_key: record.public_key,
+            source_id: None,
+        })
+    }
+
+    async fn accept(&self) -> Result<PeerInfo, Error> {
+        let (peer_info, notify_addr) = self
+            .accept_rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or("LAN discovery closed")?;
+        let source_id = peer_info.source_id.unwrap_or_default();
+        self.socket
+            .send_to(&Self::ack(source_id), notify_addr)
+            .await?;
+        Ok(peer_info)
+    }
+
+    async fn withdraw(&self) -> Result<(), Error> {
+        let own = self.own.lock().unwrap().take();
+        if let Some(own) = own {
+            self.registry.unpublish(own.address).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LanDiscovery {
+    fn drop(&mut self) {
+        self.receiver_task.abort();
+    }
+}
+
+/// Combines a local backend with a remote one, lookups try the direct
+/// local path first and fall back to the remote backend.
+pub struct LocalFirstDiscovery {
+    local: Arc<dyn Discovery>,
+    remote: Arc<dyn Discovery>,
+}
+
+impl LocalFirstDiscovery {
+    pub fn new(local: Arc<dyn Discovery>, remote: Arc<dyn Discovery>) -> Self {
+        Self { local, remote }
+    }
+}
+
+#[async_trait]
+impl Discovery for LocalFirstDiscovery {
+    async fn announce(
+        &self,
+        address: Address,
+        public_key: &PublicKey,
+        addr: SocketAddr,
+    ) -> Result<(), Error> {
+        self.remote.announce(address, public_key, addr).await?;
+        if let Err(err) = self.local.announce(address, public_key, addr).await {
+            tracing::warn!(?err, "Cannot announce on local network");
+        }
+        Ok(())
+    }
+
+    async fn lookup(&self, address: Address, source_id: u32) -> Result<PeerInfo, Error> {
+        match self.local.lookup(address, source_id).await {
+            Ok(peer_info) => {
+                tracing::debug!(?address, peer_addr = ?peer_info.addr, "Peer found locally");
+                Ok(peer_info)
+            }
+            Err(err) => {
+                tracing::trace!(?address, ?err, "Peer not found locally");
+                self.remote.lookup(address, source_id).await
+            }
+        }
+    }
+
+    async fn accept(&self) -> Result<PeerInfo, Error> {
+        tokio::select! {
+            v = self.local.accept() => v,
+            v = self.remote.accept() => v,
+        }
+    }
+
+    async fn withdraw(&self) -> Result<(), Error> {
+        if let Err(err) = self.local.withdraw().await {
+            tracing::warn!(?err, "Cannot withdraw from local network");
+        }
+        self.remote.withdraw().await
+    }
+
+    async fn pairing_code(&self) -> Result<PairingCode, Error> {
+        self.remote.pairing_code().await
+    }
+
+    async fn resolve_pairing_code(&self, code: &str) -> Result<Address, Error> {
+        self.remote.resolve_pairing_code(code).await
+    }
+
+    async fn reflexive_addr(&self) -> Result<SocketAddr, Error> {
+        self.remote.reflexive_addr().await
+    }
+}
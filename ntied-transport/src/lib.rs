@@ -2,15 +2,23 @@ pub mod byteio;
 
 mod address;
 mod connection;
+mod diagnostic;
+mod discovery;
+mod lan_discovery;
 mod packet;
+mod qos;
 mod server_connection;
 mod server_message;
 mod transport;
 
 pub use address::*;
 pub use connection::*;
+pub use diagnostic::*;
+pub use discovery::*;
+pub use lan_discovery::*;
 pub use packet::*;
+pub use qos::*;
 pub use server_message::*;
 pub use transport::*;
 
-pub(crate) use server_connection::*;
+pub(crate) use server_connection::ServerConnection;
@@ -135,6 +135,11 @@ impl EncryptedPacket {
         let message = DecryptedPacket::deserialize(&decrypted_payload)?;
         Ok(message)
     }
+
+    /// Sender sequence number carried in the first 8 bytes of the nonce.
+    pub fn sequence(&self) -> u64 {
+        u64::from_le_bytes(self.nonce[..8].try_into().unwrap())
+    }
 }
 
 pub enum DecryptedPacket {
@@ -200,6 +205,8 @@ pub struct HandshakePacket {
     pub address: Address,
     pub public_key: Vec<u8>,
     pub ephemeral_public_key: Vec<u8>,
+    /// Supported cipher identifiers in order of preference.
+    pub ciphers: Vec<u8>,
     pub signature: Vec<u8>,
 }
 
@@ -210,6 +217,7 @@ impl HandshakePacket {
         writer.write_array(self.address.as_bytes());
         writer.write_bytes(&self.public_key);
         writer.write_bytes(&self.ephemeral_public_key);
+        writer.write_bytes(&self.ciphers);
         writer.write_bytes(&self.signature);
     }
 
@@ -219,6 +227,7 @@ impl HandshakePacket {
         let address = Address::from_bytes(reader.read_array()?);
         let public_key = reader.read_bytes()?;
         let ephemeral_public_key = reader.read_bytes()?;
+        let ciphers = reader.read_bytes()?;
         let signature = reader.read_bytes()?;
         Ok(Self {
             source_id,
@@ -226,6 +235,7 @@ impl HandshakePacket {
             address,
             peer_address,
             ephemeral_public_key,
+            ciphers,
             signature,
         })
     }
@@ -238,6 +248,8 @@ pub struct HandshakeAckPacket {
     pub address: Address,
     pub public_key: Vec<u8>,
     pub ephemeral_public_key: Vec<u8>,
+    /// Supported cipher identifiers in order of preference.
+    pub ciphers: Vec<u8>,
     pub signature: Vec<u8>,
 }
 
@@ -249,6 +261,7 @@ impl HandshakeAckPacket {
         writer.write_array(self.address.as_bytes());
         writer.write_bytes(&self.public_key);
         writer.write_bytes(&self.ephemeral_public_key);
+        writer.write_bytes(&self.ciphers);
         writer.write_bytes(&self.signature);
     }
 
@@ -259,6 +272,7 @@ impl HandshakeAckPacket {
         let address = Address::from_bytes(reader.read_array()?);
         let public_key = reader.read_bytes()?;
         let ephemeral_public_key = reader.read_bytes()?;
+        let ciphers = reader.read_bytes()?;
         let signature = reader.read_bytes()?;
         Ok(Self {
             target_id,
@@ -267,6 +281,7 @@ impl HandshakeAckPacket {
             address,
             peer_address,
             ephemeral_public_key,
+            ciphers,
             signature,
         })
     }
@@ -0,0 +1,63 @@
+use tokio::net::UdpSocket;
+
+/// DiffServ class of outgoing packets, networks honoring it forward
+/// latency sensitive traffic first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TrafficClass {
+    #[default]
+    BestEffort,
+    /// Transfers that may yield to other traffic, CS1.
+    Bulk,
+    /// Call audio, Expedited Forwarding.
+    Audio,
+}
+
+impl TrafficClass {
+    /// DSCP code point of the class.
+    pub fn dscp(self) -> u8 {
+        match self {
+            Self::BestEffort => 0,
+            Self::Bulk => 8,
+            Self::Audio => 46,
+        }
+    }
+
+    /// Marks packets sent from the socket, returns false if the platform
+    /// or the address family does not support it.
+    pub(crate) fn apply(self, socket: &UdpSocket) -> bool {
+        // The ECN bits are left clear
+        let tos = u32::from(self.dscp()) << 2;
+        match socket.local_addr() {
+            Ok(addr) if addr.is_ipv4() => Self::set_tos(socket, tos),
+            _ => false,
+        }
+    }
+
+    #[cfg(not(any(
+        target_os = "fuchsia",
+        target_os = "redox",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "haiku"
+    )))]
+    fn set_tos(socket: &UdpSocket, tos: u32) -> bool {
+        match socket.set_tos(tos) {
+            Ok(()) => true,
+            Err(err) => {
+                tracing::debug!(?err, "Cannot set IP_TOS");
+                false
+            }
+        }
+    }
+
+    #[cfg(any(
+        target_os = "fuchsia",
+        target_os = "redox",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "haiku"
+    ))]
+    fn set_tos(_socket: &UdpSocket, _tos: u32) -> bool {
+        false
+    }
+}
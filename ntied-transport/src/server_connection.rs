@@ -4,20 +4,33 @@ use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use async_trait::async_trait;
 use ntied_crypto::PublicKey;
 use tokio::sync::{Mutex as TokioMutex, mpsc, oneshot};
 use tokio::task::JoinHandle;
 use tokio::time::timeout;
 
-use crate::{Address, Error, ServerRequest, ServerResponse, ToAddress, TransportInner};
+use crate::{
+    Address, Discovery, Error, PairingCode, PeerInfo, ServerErrorResponse,
+    ServerPairingCodeResponse, ServerReflexiveAddrResponse, ServerRequest,
+    ServerResolvePairingCodeResponse, ServerResponse, TransportInner,
+};
 
+/// Default [`Discovery`] backend, peers register on and are looked up through
+/// the coordination server.
 pub(crate) struct ServerConnection {
     transport: Arc<TransportInner>,
     server_addr: SocketAddr,
+    // Sent on registration to private servers
+    token: Option<String>,
     requests: Arc<Mutex<HashMap<u32, oneshot::Sender<ServerResponse>>>>,
     request_id: Arc<AtomicU32>,
     receiver_task: JoinHandle<()>,
-    heartbeat_task: JoinHandle<()>,
+    // Issued by the server on registration, proves the unregister request
+    session: Mutex<Option<[u8; 16]>>,
+    // Started by announce
+    heartbeat_task: Mutex<Option<JoinHandle<()>>>,
+    alive: Arc<AtomicBool>,
     accept_rx: TokioMutex<mpsc::Receiver<PeerInfo>>,
 }
 
@@ -25,12 +38,12 @@ impl ServerConnection {
     const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(8);
     const CONNECTION_TIMEOUT: Duration = Duration::from_secs(32);
 
-    pub(crate) async fn new(
+    pub(crate) fn new(
         transport: Arc<TransportInner>,
         server_addr: SocketAddr,
+        token: Option<String>,
         recv_rx: mpsc::Receiver<Vec<u8>>,
-        address: Address,
-    ) -> Result<Self, Error> {
+    ) -> Self {
         let requests = Arc::new(Mutex::new(HashMap::new()));
         let request_id = Arc::new(AtomicU32::new(0));
         let (accept_tx, accept_rx) = mpsc::channel(100);
@@ -42,129 +55,37 @@ impl ServerConnection {
             accept_tx,
             alive.clone(),
         ));
-        // Register with the server
-        let public_key = transport.private_key.public_key().to_bytes()?;
-        Self::register(
-            transport.clone(),
-            server_addr,
-            requests.clone(),
-            request_id.clone(),
-            address,
-            public_key,
-        )
-        .await?;
-        let heartbeat_task = tokio::spawn(Self::heartbeat_loop(
-            transport.clone(),
-            server_addr,
-            alive.clone(),
-        ));
-        Ok(Self {
+        Self {
             transport,
             server_addr,
+            token,
             requests,
             request_id,
             receiver_task,
-            heartbeat_task,
+            session: Mutex::new(None),
+            heartbeat_task: Mutex::new(None),
+            alive,
             accept_rx,
-        })
+        }
     }
 
-    pub async fn connect(
+    /// Sends the request and waits for the response with the same request id.
+    async fn request(
         &self,
-        address: impl ToAddress,
-        source_id: u32,
-    ) -> Result<PeerInfo, Error> {
-        let address = address.to_address()?;
-        tracing::debug!(?address, "Requesting for connection to peer");
-        let request_id = self.next_request_id();
-        let request = ServerRequest::Connect(crate::ServerConnectRequest {
-            request_id,
-            address,
-            source_id,
-        });
-        // Create a channel to receive the response
+        request_id: u32,
+        request: ServerRequest,
+    ) -> Result<ServerResponse, Error> {
         let (tx, rx) = oneshot::channel();
-        // Register the request with its request_id
         self.requests.lock().unwrap().insert(request_id, tx);
-        // Send the request to the server
         self.transport
             .socket
             .send_to(&request.serialize(), self.server_addr)
             .await?;
-        // Wait for the response with timeout
-        let response = timeout(Self::CONNECTION_TIMEOUT, rx)
-            .await
-            .map_err(|_| "Connection timeout")?
-            .map_err(|_| "Channel closed")?;
-        // Process the response
-        match response {
-            ServerResponse::Connect(resp) => {
-                tracing::trace!(
-                    peer_addr = ?resp.addr,
-                    peer_address = ?resp.address,
-                    "Received connect response from server",
-                );
-                let public_key = PublicKey::from_bytes(&resp.public_key)?;
-                Ok(PeerInfo {
-                    addr: resp.addr,
-                    address: resp.address,
-                    public_key,
-                    source_id: None,
-                })
-            }
-            ServerResponse::ConnectError(err) => {
-                Err(format!("Connect error: code {}", err.code).into())
-            }
-            _ => Err("Unexpected response type".into()),
-        }
-    }
-
-    pub async fn accept(&self) -> Result<PeerInfo, Error> {
-        self.accept_rx
-            .lock()
-            .await
-            .recv()
-            .await
-            .ok_or("Server connection closed".into())
-    }
-
-    async fn register(
-        transport: Arc<TransportInner>,
-        socket_addr: SocketAddr,
-        requests: Arc<Mutex<HashMap<u32, oneshot::Sender<ServerResponse>>>>,
-        request_id_counter: Arc<AtomicU32>,
-        address: Address,
-        public_key: Vec<u8>,
-    ) -> Result<(), Error> {
-        tracing::debug!("Registering with server");
-        let request_id = Self::next_request_id_static(&request_id_counter);
-        let request = ServerRequest::Register(crate::ServerRegisterRequest {
-            request_id,
-            public_key,
-            address,
-        });
-        // Create a channel to receive the response
-        let (tx, rx) = oneshot::channel();
-        // Register the request with its request_id
-        requests.lock().unwrap().insert(request_id, tx);
-        // Send the request to the server
-        transport
-            .socket
-            .send_to(&request.serialize(), socket_addr)
-            .await?;
-        // Wait for the response with timeout
         let response = timeout(Self::CONNECTION_TIMEOUT, rx)
             .await
-            .map_err(|_| "Register timeout")?
+            .map_err(|_| "Request timeout")?
             .map_err(|_| "Channel closed")?;
-        // Process the response
-        match response {
-            ServerResponse::Register(_) => Ok(()),
-            ServerResponse::RegisterError(err) => {
-                Err(format!("Register error: code {}", err.code).into())
-            }
-            _ => Err("Unexpected response type".into()),
-        }
+        Ok(response)
     }
 
     async fn receiver_loop(
@@ -265,6 +186,33 @@ impl ServerConnection {
                         tracing::warn!(request_id, "Received response with unknown request_id");
                     }
                 }
+                ServerResponse::PairingCode(ServerPairingCodeResponse { request_id, .. })
+                | ServerResponse::PairingCodeError(ServerErrorResponse { request_id, .. })
+                | ServerResponse::ResolvePairingCode(ServerResolvePairingCodeResponse {
+                    request_id,
+                    ..
+                })
+                | ServerResponse::ResolvePairingCodeError(ServerErrorResponse {
+                    request_id, ..
+                })
+                | ServerResponse::ReflexiveAddr(ServerReflexiveAddrResponse {
+                    request_id, ..
+                })
+                | ServerResponse::ReflexiveAddrError(ServerErrorResponse { request_id, .. }) => {
+                    let request_id = *request_id;
+                    let mut requests_guard = requests.lock().unwrap();
+                    if let Some(sender) = requests_guard.remove(&request_id) {
+                        tracing::debug!(request_id, "Routing response to waiting request");
+                        if sender.send(response).is_err() {
+                            tracing::warn!(
+                                request_id,
+                                "Failed to send response to dropped receiver"
+                            );
+                        }
+                    } else {
+                        tracing::warn!(request_id, "Received response with unknown request_id");
+                    }
+                }
                 ServerResponse::IncomingConnection(resp) => {
                     tracing::debug!(source_id = ?resp.source_id, peer_addr = ?resp.addr, "Received incoming connection notification");
                     let public_key = match PublicKey::from_bytes(&resp.public_key) {
@@ -315,12 +263,8 @@ impl ServerConnection {
     }
 
     fn next_request_id(&self) -> u32 {
-        Self::next_request_id_static(&self.request_id)
-    }
-
-    fn next_request_id_static(request_id: &AtomicU32) -> u32 {
         loop {
-            let id = request_id.fetch_add(1, Ordering::SeqCst);
+            let id = self.request_id.fetch_add(1, Ordering::SeqCst);
             if id != 0 {
                 return id;
             }
@@ -328,10 +272,163 @@ impl ServerConnection {
     }
 }
 
+#[async_trait]
+impl Discovery for ServerConnection {
+    async fn announce(
+        &self,
+        address: Address,
+        public_key: &PublicKey,
+        _addr: SocketAddr,
+    ) -> Result<(), Error> {
+        tracing::debug!("Registering with server");
+        let request_id = self.next_request_id();
+        let request = ServerRequest::Register(crate::ServerRegisterRequest {
+            request_id,
+            public_key: public_key.to_bytes()?,
+            address,
+            token: self.token.clone(),
+        });
+        match self.request(request_id, request).await? {
+            ServerResponse::Register(resp) => {
+                *self.session.lock().unwrap() = Some(resp.session);
+            }
+            ServerResponse::RegisterError(err) => {
+                return Err(format!("Register error: code {}", err.code).into());
+            }
+            _ => return Err("Unexpected response type".into()),
+        }
+        let heartbeat_task = tokio::spawn(Self::heartbeat_loop(
+            self.transport.clone(),
+            self.server_addr,
+            self.alive.clone(),
+        ));
+        if let Some(task) = self.heartbeat_task.lock().unwrap().replace(heartbeat_task) {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    async fn lookup(&self, address: Address, source_id: u32) -> Result<PeerInfo, Error> {
+        tracing::debug!(?address, "Requesting for connection to peer");
+        let request_id = self.next_request_id();
+        let request = ServerRequest::Connect(crate::ServerConnectRequest {
+            request_id,
+            address,
+            source_id,
+        });
+        // Create a channel to receive the response
+        let (tx, rx) = oneshot::channel();
+        // Register the request with its request_id
+        self.requests.lock().unwrap().insert(request_id, tx);
+        // Send the request to the server
+        self.transport
+            .socket
+            .send_to(&request.serialize(), self.server_addr)
+            .await?;
+        // Wait for the response with timeout
+        let response = timeout(Self::CONNECTION_TIMEOUT, rx)
+            .await
+            .map_err(|_| "Connection timeout")?
+            .map_err(|_| "Channel closed")?;
+        // Process the response
+        match response {
+            ServerResponse::Connect(resp) => {
+                tracing::trace!(
+                    peer_addr = ?resp.addr,
+                    peer_address = ?resp.address,
+                    "Received connect response from server",
+                );
+                let public_key = PublicKey::from_bytes(&resp.public_key)?;
+                Ok(PeerInfo {
+                    addr: resp.addr,
+                    address: resp.address,
+                    public_key,
+                    source_id: None,
+                })
+            }
+            ServerResponse::ConnectError(err) => {
+                Err(format!("Connect error: code {}", err.code).into())
+            }
+            _ => Err("Unexpected response type".into()),
+        }
+    }
+
+    async fn accept(&self) -> Result<PeerInfo, Error> {
+        self.accept_rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or("Server connection closed".into())
+    }
+
+    async fn pairing_code(&self) -> Result<PairingCode, Error> {
+        let request_id = self.next_request_id();
+        let request = ServerRequest::PairingCode(crate::ServerPairingCodeRequest { request_id });
+        match self.request(request_id, request).await? {
+            ServerResponse::PairingCode(resp) => Ok(PairingCode {
+                code: resp.code,
+                expires_in: Duration::from_secs(resp.expires_in.into()),
+            }),
+            ServerResponse::PairingCodeError(err) => {
+                Err(format!("Pairing code error: code {}", err.code).into())
+            }
+            _ => Err("Unexpected response type".into()),
+        }
+    }
+
+    async fn resolve_pairing_code(&self, code: &str) -> Result<Address, Error> {
+        let request_id = self.next_request_id();
+        let request = ServerRequest::ResolvePairingCode(crate::ServerResolvePairingCodeRequest {
+            request_id,
+            code: code.to_string(),
+        });
+        match self.request(request_id, request).await? {
+            ServerResponse::ResolvePairingCode(resp) => Ok(resp.address),
+            ServerResponse::ResolvePairingCodeError(err) => {
+                Err(format!("Resolve pairing code error: code {}", err.code).into())
+            }
+            _ => Err("Unexpected response type".into()),
+        }
+    }
+
+    async fn reflexive_addr(&self) -> Result<SocketAddr, Error> {
+        let request_id = self.next_request_id();
+        let request =
+            ServerRequest::ReflexiveAddr(crate::ServerReflexiveAddrRequest { request_id });
+        match self.request(request_id, request).await? {
+            ServerResponse::ReflexiveAddr(resp) => Ok(resp.addr),
+            ServerResponse::ReflexiveAddrError(err) => {
+                Err(format!("Reflexive address error: code {}", err.code).into())
+            }
+            _ => Err("Unexpected response type".into()),
+        }
+    }
+
+    /// Asks the server to forget the client, heartbeats are stopped.
+    async fn withdraw(&self) -> Result<(), Error> {
+        tracing::debug!("Unregistering from server");
+        if let Some(task) = self.heartbeat_task.lock().unwrap().take() {
+            task.abort();
+        }
+        let Some(session) = self.session.lock().unwrap().take() else {
+            return Ok(());
+        };
+        let request = ServerRequest::Unregister(crate::ServerUnregisterRequest { session });
+        self.transport
+            .socket
+            .send_to(&request.serialize(), self.server_addr)
+            .await?;
+        Ok(())
+    }
+}
+
 impl Drop for ServerConnection {
     fn drop(&mut self) {
         self.receiver_task.abort();
-        self.heartbeat_task.abort();
+        if let Some(task) = self.heartbeat_task.get_mut().unwrap().take() {
+            task.abort();
+        }
         // Clean up raw connection to server
         self.transport
             .raw_connections
@@ -340,10 +437,3 @@ impl Drop for ServerConnection {
             .remove(&self.server_addr);
     }
 }
-
-pub(crate) struct PeerInfo {
-    pub addr: SocketAddr,
-    pub address: Address,
-    pub public_key: PublicKey,
-    pub source_id: Option<u32>,
-}
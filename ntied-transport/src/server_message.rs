@@ -7,6 +7,13 @@ pub enum ServerRequest {
     Heartbeat,
     Register(ServerRegisterRequest),
     Connect(ServerConnectRequest),
+    /// Removes the client registered from the sender socket address with the same session.
+    Unregister(ServerUnregisterRequest),
+    /// Asks for a short-lived code other clients can resolve to the sender address.
+    PairingCode(ServerPairingCodeRequest),
+    ResolvePairingCode(ServerResolvePairingCodeRequest),
+    /// Asks for the socket address the server observes for the sender.
+    ReflexiveAddr(ServerReflexiveAddrRequest),
 }
 
 impl ServerRequest {
@@ -20,6 +27,9 @@ impl ServerRequest {
                 writer.write_u32(v.request_id);
                 writer.write_bytes(&v.public_key);
                 writer.write_array(v.address.as_bytes());
+                if let Some(token) = &v.token {
+                    writer.write_string(token);
+                }
             }
             ServerRequest::Connect(v) => {
                 writer.write_u8(2);
@@ -27,6 +37,23 @@ impl ServerRequest {
                 writer.write_array(v.address.as_bytes());
                 writer.write_u32(v.source_id);
             }
+            ServerRequest::Unregister(v) => {
+                writer.write_u8(3);
+                writer.write_array(&v.session);
+            }
+            ServerRequest::PairingCode(v) => {
+                writer.write_u8(4);
+                writer.write_u32(v.request_id);
+            }
+            ServerRequest::ResolvePairingCode(v) => {
+                writer.write_u8(5);
+                writer.write_u32(v.request_id);
+                writer.write_string(&v.code);
+            }
+            ServerRequest::ReflexiveAddr(v) => {
+                writer.write_u8(6);
+                writer.write_u32(v.request_id);
+            }
         }
         bytes
     }
@@ -41,10 +68,17 @@ impl ServerRequest {
                 let request_id = reader.read_u32()?;
                 let public_key = reader.read_bytes()?;
                 let address = Address::from_bytes(reader.read_array()?);
+                // Older clients do not send the token
+                let token = if reader.is_empty() {
+                    None
+                } else {
+                    Some(reader.read_string()?)
+                };
                 Ok(Self::Register(ServerRegisterRequest {
                     request_id,
                     public_key,
                     address,
+                    token,
                 }))
             }
             2 => {
@@ -57,6 +91,28 @@ impl ServerRequest {
                     source_id,
                 }))
             }
+            3 => {
+                let session = reader.read_array()?;
+                Ok(Self::Unregister(ServerUnregisterRequest { session }))
+            }
+            4 => {
+                let request_id = reader.read_u32()?;
+                Ok(Self::PairingCode(ServerPairingCodeRequest { request_id }))
+            }
+            5 => {
+                let request_id = reader.read_u32()?;
+                let code = reader.read_string()?;
+                Ok(Self::ResolvePairingCode(ServerResolvePairingCodeRequest {
+                    request_id,
+                    code,
+                }))
+            }
+            6 => {
+                let request_id = reader.read_u32()?;
+                Ok(Self::ReflexiveAddr(ServerReflexiveAddrRequest {
+                    request_id,
+                }))
+            }
             _ => Err("Unknown request type".into()),
         }
     }
@@ -66,6 +122,13 @@ pub struct ServerRegisterRequest {
     pub request_id: u32,
     pub public_key: Vec<u8>,
     pub address: Address,
+    /// Shared secret required by private servers.
+    pub token: Option<String>,
+}
+
+pub struct ServerUnregisterRequest {
+    /// Session issued in the register response.
+    pub session: [u8; 16],
 }
 
 pub struct ServerConnectRequest {
@@ -74,6 +137,19 @@ pub struct ServerConnectRequest {
     pub source_id: u32,
 }
 
+pub struct ServerPairingCodeRequest {
+    pub request_id: u32,
+}
+
+pub struct ServerResolvePairingCodeRequest {
+    pub request_id: u32,
+    pub code: String,
+}
+
+pub struct ServerReflexiveAddrRequest {
+    pub request_id: u32,
+}
+
 pub enum ServerResponse {
     Heartbeat,
     Register(ServerRegisterResponse),
@@ -81,6 +157,12 @@ pub enum ServerResponse {
     Connect(ServerConnectResponse),
     ConnectError(ServerErrorResponse),
     IncomingConnection(ServerIncomingConnectionResponse),
+    PairingCode(ServerPairingCodeResponse),
+    PairingCodeError(ServerErrorResponse),
+    ResolvePairingCode(ServerResolvePairingCodeResponse),
+    ResolvePairingCodeError(ServerErrorResponse),
+    ReflexiveAddr(ServerReflexiveAddrResponse),
+    ReflexiveAddrError(ServerErrorResponse),
 }
 
 impl ServerResponse {
@@ -92,6 +174,7 @@ impl ServerResponse {
             Self::Register(v) => {
                 writer.write_u8(1);
                 writer.write_u32(v.request_id);
+                writer.write_array(&v.session);
             }
             Self::RegisterError(v) => {
                 writer.write_u8(2);
@@ -117,6 +200,37 @@ impl ServerResponse {
                 writer.write_socket_addr(&response.addr);
                 writer.write_u32(response.source_id);
             }
+            Self::PairingCode(v) => {
+                writer.write_u8(6);
+                writer.write_u32(v.request_id);
+                writer.write_string(&v.code);
+                writer.write_u32(v.expires_in);
+            }
+            Self::PairingCodeError(v) => {
+                writer.write_u8(7);
+                writer.write_u32(v.request_id);
+                writer.write_u16(v.code);
+            }
+            Self::ResolvePairingCode(v) => {
+                writer.write_u8(8);
+                writer.write_u32(v.request_id);
+                writer.write_array(v.address.as_bytes());
+            }
+            Self::ResolvePairingCodeError(v) => {
+                writer.write_u8(9);
+                writer.write_u32(v.request_id);
+                writer.write_u16(v.code);
+            }
+            Self::ReflexiveAddr(v) => {
+                writer.write_u8(10);
+                writer.write_u32(v.request_id);
+                writer.write_socket_addr(&v.addr);
+            }
+            Self::ReflexiveAddrError(v) => {
+                writer.write_u8(11);
+                writer.write_u32(v.request_id);
+                writer.write_u16(v.code);
+            }
         }
         bytes
     }
@@ -129,7 +243,11 @@ impl ServerResponse {
         match reader.read_u8()? {
             1 => {
                 let request_id = reader.read_u32()?;
-                Ok(Self::Register(ServerRegisterResponse { request_id }))
+                let session = reader.read_array()?;
+                Ok(Self::Register(ServerRegisterResponse {
+                    request_id,
+                    session,
+                }))
             }
             2 => {
                 let request_id = reader.read_u32()?;
@@ -168,6 +286,56 @@ impl ServerResponse {
                     source_id,
                 }))
             }
+            6 => {
+                let request_id = reader.read_u32()?;
+                let code = reader.read_string()?;
+                let expires_in = reader.read_u32()?;
+                Ok(Self::PairingCode(ServerPairingCodeResponse {
+                    request_id,
+                    code,
+                    expires_in,
+                }))
+            }
+            7 => {
+                let request_id = reader.read_u32()?;
+                let code = reader.read_u16()?;
+                Ok(Self::PairingCodeError(ServerErrorResponse {
+                    request_id,
+                    code,
+                }))
+            }
+            8 => {
+                let request_id = reader.read_u32()?;
+                let address = Address::from_bytes(reader.read_array()?);
+                Ok(Self::ResolvePairingCode(ServerResolvePairingCodeResponse {
+                    request_id,
+                    address,
+                }))
+            }
+            9 => {
+                let request_id = reader.read_u32()?;
+                let code = reader.read_u16()?;
+                Ok(Self::ResolvePairingCodeError(ServerErrorResponse {
+                    request_id,
+                    code,
+                }))
+            }
+            10 => {
+                let request_id = reader.read_u32()?;
+                let addr = reader.read_socket_addr()?;
+                Ok(Self::ReflexiveAddr(ServerReflexiveAddrResponse {
+                    request_id,
+                    addr,
+                }))
+            }
+            11 => {
+                let request_id = reader.read_u32()?;
+                let code = reader.read_u16()?;
+                Ok(Self::ReflexiveAddrError(ServerErrorResponse {
+                    request_id,
+                    code,
+                }))
+            }
             _ => Err("Unknown response type".into()),
         }
     }
@@ -175,6 +343,8 @@ impl ServerResponse {
 
 pub struct ServerRegisterResponse {
     pub request_id: u32,
+    /// Random value the client proves itself with when unregistering.
+    pub session: [u8; 16],
 }
 
 pub struct ServerConnectResponse {
@@ -195,3 +365,21 @@ pub struct ServerIncomingConnectionResponse {
     pub addr: SocketAddr,
     pub source_id: u32,
 }
+
+pub struct ServerPairingCodeResponse {
+    pub request_id: u32,
+    pub code: String,
+    /// Seconds until the code expires.
+    pub expires_in: u32,
+}
+
+pub struct ServerResolvePairingCodeResponse {
+    pub request_id: u32,
+    pub address: Address,
+}
+
+pub struct ServerReflexiveAddrResponse {
+    pub request_id: u32,
+    /// Source address of the request as seen by the server.
+    pub addr: SocketAddr,
+}
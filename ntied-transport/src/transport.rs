@@ -1,33 +1,89 @@
 use std::collections::{HashMap, hash_map};
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
-use ntied_crypto::PrivateKey;
+use ntied_crypto::{Cipher, PrivateKey};
 use tokio::net::{ToSocketAddrs, UdpSocket};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::error::TrySendError;
 use tokio::task::JoinHandle;
+use tokio::time::timeout;
 
-use crate::{Address, Connection, Packet, ServerConnection};
+use crate::{
+    Address, CandidateReport, Connection, ConnectivityReport, Discovery, LocalFirstDiscovery,
+    Packet, PairingCode, ReplayWindow, ServerConnection, TrafficClass,
+};
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 
 pub struct Transport {
     inner: Arc<TransportInner>,
-    server_connection: ServerConnection,
+    discovery: Arc<dyn Discovery>,
 }
 
 impl Transport {
     const MAX_PACKETS: usize = 4;
     const PACKET_SIZE: usize = 65536;
+    const DIAGNOSTIC_STEP_TIMEOUT: Duration = Duration::from_secs(5);
 
+    /// Binds the socket and registers on the coordination server at `server_addr`.
     pub async fn bind(
         addr: impl ToSocketAddrs,
         address: Address,
         private_key: PrivateKey,
         server_addr: SocketAddr,
     ) -> Result<Self, Error> {
+        Self::bind_with_server_token(addr, address, private_key, server_addr, None).await
+    }
+
+    /// Like [`Transport::bind`], `token` is sent on registration to servers
+    /// that only accept clients knowing the shared secret.
+    pub async fn bind_with_server_token(
+        addr: impl ToSocketAddrs,
+        address: Address,
+        private_key: PrivateKey,
+        server_addr: SocketAddr,
+        token: Option<String>,
+    ) -> Result<Self, Error> {
+        let inner = Self::bind_inner(addr, address, private_key).await?;
+        let discovery = Self::server_discovery(&inner, server_addr, token);
+        Self::announce(inner, discovery).await
+    }
+
+    /// Like [`Transport::bind_with_server_token`], peers are looked up through
+    /// `local` first, the coordination server is used for peers not found there.
+    pub async fn bind_with_local_discovery(
+        addr: impl ToSocketAddrs,
+        address: Address,
+        private_key: PrivateKey,
+        server_addr: SocketAddr,
+        token: Option<String>,
+        local: Arc<dyn Discovery>,
+    ) -> Result<Self, Error> {
+        let inner = Self::bind_inner(addr, address, private_key).await?;
+        let remote = Self::server_discovery(&inner, server_addr, token);
+        let discovery = Arc::new(LocalFirstDiscovery::new(local, remote));
+        Self::announce(inner, discovery).await
+    }
+
+    /// Binds the socket and announces it through a custom discovery backend.
+    pub async fn bind_with_discovery(
+        addr: impl ToSocketAddrs,
+        address: Address,
+        private_key: PrivateKey,
+        discovery: Arc<dyn Discovery>,
+    ) -> Result<Self, Error> {
+        let inner = Self::bind_inner(addr, address, private_key).await?;
+        Self::announce(inner, discovery).await
+    }
+
+    async fn bind_inner(
+        addr: impl ToSocketAddrs,
+        address: Address,
+        private_key: PrivateKey,
+    ) -> Result<Arc<TransportInner>, Error> {
         let socket = Arc::new(UdpSocket::bind(addr).await?);
         let source_counter = Arc::new(AtomicU32::new(1));
         let raw_connections = Arc::new(RwLock::new(HashMap::new()));
@@ -39,40 +95,166 @@ impl Transport {
             connections.clone(),
             handshakes.clone(),
         ));
-        let inner = Arc::new(TransportInner {
+        Ok(Arc::new(TransportInner {
             socket,
             address,
             private_key,
             source_counter,
-            raw_connections: raw_connections.clone(),
+            replay_window_size: AtomicUsize::new(ReplayWindow::DEFAULT_SIZE),
+            ciphers: RwLock::new(Cipher::preferred()),
+            raw_connections,
             connections,
             handshakes,
             main_task,
-        });
+        }))
+    }
+
+    fn server_discovery(
+        inner: &Arc<TransportInner>,
+        server_addr: SocketAddr,
+        token: Option<String>,
+    ) -> Arc<dyn Discovery> {
         // TODO: Refactor this.
         let (server_tx, server_rx) = mpsc::channel(Self::MAX_PACKETS);
-        raw_connections
+        inner
+            .raw_connections
             .write()
             .unwrap()
             .insert(server_addr, server_tx);
-        let server_connection =
-            ServerConnection::new(inner.clone(), server_addr, server_rx, address).await?;
-        Ok(Self {
-            inner,
-            server_connection,
-        })
+        Arc::new(ServerConnection::new(
+            inner.clone(),
+            server_addr,
+            token,
+            server_rx,
+        ))
+    }
+
+    async fn announce(
+        inner: Arc<TransportInner>,
+        discovery: Arc<dyn Discovery>,
+    ) -> Result<Self, Error> {
+        let public_key = inner.private_key.public_key();
+        discovery
+            .announce(inner.address, &public_key, inner.socket.local_addr()?)
+            .await?;
+        Ok(Self { inner, discovery })
     }
 
     pub async fn connect(&self, address: Address) -> Result<Connection, Error> {
+        let (pending, packet_rx) = self.pending_source(address)?;
+        let source_id = pending.source_id;
+        let peer_info = self.discovery.lookup(address, source_id).await?;
+        let connection = Connection::connect(
+            self.inner.clone(),
+            source_id,
+            peer_info.addr,
+            peer_info.address,
+            peer_info.public_key,
+            packet_rx,
+        )
+        .await;
+        match connection {
+            Ok(v) => {
+                std::mem::forget(pending);
+                Ok(v)
+            }
+            Err(err) => {
+                tracing::trace!(
+                    source_id = source_id,
+                    peer_addr = ?peer_info.addr,
+                    peer_address = ?peer_info.address,
+                    "Dropping failed connection source id",
+                );
+                Err(err)
+            }
+        }
+    }
+
+    /// Runs the steps of [`Transport::connect`] one by one, logging and
+    /// reporting each of them. The connection is returned on success.
+    pub async fn diagnose(&self, address: Address) -> (ConnectivityReport, Option<Connection>) {
+        let mut report = ConnectivityReport {
+            target: address,
+            local_addr: self.local_addr(),
+            reflexive_addr: Err("Not requested".into()),
+            lookup: Err("Not requested".into()),
+            candidates: Vec::new(),
+        };
+        tracing::info!(peer_address = ?address, local_addr = ?report.local_addr, "Diagnostic started");
+        report.reflexive_addr =
+            match timeout(Self::DIAGNOSTIC_STEP_TIMEOUT, self.reflexive_addr()).await {
+                Ok(Ok(addr)) => Ok(addr),
+                Ok(Err(err)) => Err(err.to_string()),
+                Err(_) => Err("Timed out".into()),
+            };
+        tracing::info!(reflexive_addr = ?report.reflexive_addr, "Diagnostic reflexive address");
+        let (pending, packet_rx) = match self.pending_source(address) {
+            Ok(v) => v,
+            Err(err) => {
+                report.lookup = Err(err.to_string());
+                return (report, None);
+            }
+        };
+        let start = Instant::now();
+        let lookup = timeout(
+            Self::DIAGNOSTIC_STEP_TIMEOUT,
+            self.discovery.lookup(address, pending.source_id),
+        )
+        .await;
+        let peer_info = match lookup {
+            Ok(Ok(v)) => v,
+            Ok(Err(err)) => {
+                tracing::info!(?err, "Diagnostic lookup failed");
+                report.lookup = Err(err.to_string());
+                return (report, None);
+            }
+            Err(_) => {
+                tracing::info!("Diagnostic lookup timed out");
+                report.lookup = Err("Timed out".into());
+                return (report, None);
+            }
+        };
+        report.lookup = Ok(start.elapsed());
+        tracing::info!(peer_addr = ?peer_info.addr, elapsed = ?start.elapsed(), "Diagnostic lookup succeeded");
+        let start = Instant::now();
+        let connection = Connection::connect(
+            self.inner.clone(),
+            pending.source_id,
+            peer_info.addr,
+            peer_info.address,
+            peer_info.public_key,
+            packet_rx,
+        )
+        .await;
+        let (result, connection) = match connection {
+            Ok(v) => {
+                std::mem::forget(pending);
+                (Ok(start.elapsed()), Some(v))
+            }
+            Err(err) => (Err(err.to_string()), None),
+        };
+        tracing::info!(peer_addr = ?peer_info.addr, ?result, "Diagnostic handshake finished");
+        report.candidates.push(CandidateReport {
+            addr: peer_info.addr,
+            result,
+        });
+        (report, connection)
+    }
+
+    /// Registers the buffer for packets of a new outgoing connection.
+    fn pending_source(
+        &self,
+        address: Address,
+    ) -> Result<(PendingSource<'_>, mpsc::Receiver<(SocketAddr, Packet)>), Error> {
         let source_id = self.inner.source_counter.fetch_add(1, Ordering::SeqCst);
-        let peer_info = self.server_connection.connect(address, source_id).await?;
         let (packet_tx, packet_rx) = mpsc::channel(Self::MAX_PACKETS);
         tracing::trace!(
             source_id = source_id,
-            peer_addr = ?peer_info.addr,
-            peer_address = ?peer_info.address,
+            peer_address = ?address,
             "Creating connection buffer",
         );
+        // The buffer exists before the lookup, the peer may answer as soon
+        // as discovery notifies it.
         {
             let mut connections = self.inner.connections.write().unwrap();
             match connections.entry(source_id) {
@@ -84,41 +266,18 @@ impl Transport {
                 }
             }
         }
-        match Connection::connect(
-            self.inner.clone(),
+        // Removes the buffer if the attempt fails or is cancelled by
+        // dropping the future, the connection removes it once created.
+        let pending = PendingSource {
+            inner: &self.inner,
             source_id,
-            peer_info.addr,
-            peer_info.address,
-            peer_info.public_key,
-            packet_rx,
-        )
-        .await
-        {
-            Ok(v) => Ok(v),
-            Err(err) => {
-                tracing::trace!(
-                    source_id = source_id,
-                    peer_addr = ?peer_info.addr,
-                    peer_address = ?peer_info.address,
-                    "Dropping failed connection source id",
-                );
-                let mut connections = self.inner.connections.write().unwrap();
-                if connections.remove(&source_id).is_none() {
-                    tracing::error!(
-                        source_id = source_id,
-                        peer_addr = ?peer_info.addr,
-                        peer_address = ?peer_info.address,
-                        "Inconsistent connection drop: Connection not found",
-                    );
-                };
-                Err(err)
-            }
-        }
+        };
+        Ok((pending, packet_rx))
     }
 
     pub async fn accept(&self) -> Result<Connection, Error> {
         loop {
-            let peer_info = self.server_connection.accept().await?;
+            let peer_info = self.discovery.accept().await?;
             let source_id = self.inner.source_counter.fetch_add(1, Ordering::SeqCst);
             let target_id = peer_info.source_id.unwrap();
             let (packet_tx, packet_rx) = mpsc::channel(Self::MAX_PACKETS);
@@ -212,6 +371,26 @@ impl Transport {
         }
     }
 
+    /// Removes the announcement, peers can no longer connect.
+    pub async fn unregister(&self) -> Result<(), Error> {
+        self.discovery.withdraw().await
+    }
+
+    /// Requests a short-lived pairing code, peers can resolve it to our address once.
+    pub async fn pairing_code(&self) -> Result<PairingCode, Error> {
+        self.discovery.pairing_code().await
+    }
+
+    /// Resolves a pairing code issued to another client into its address.
+    pub async fn resolve_pairing_code(&self, code: &str) -> Result<Address, Error> {
+        self.discovery.resolve_pairing_code(code).await
+    }
+
+    /// Public address of this transport as observed by the server.
+    pub async fn reflexive_addr(&self) -> Result<SocketAddr, Error> {
+        self.discovery.reflexive_addr().await
+    }
+
     pub fn local_addr(&self) -> SocketAddr {
         self.inner.socket.local_addr().unwrap()
     }
@@ -220,6 +399,38 @@ impl Transport {
         self.inner.address
     }
 
+    /// Set the replay window size used by connections created after this call.
+    pub fn set_replay_window_size(&self, size: usize) {
+        assert!(size > 0, "Replay window size must be positive");
+        self.inner.replay_window_size.store(size, Ordering::Relaxed);
+    }
+
+    pub fn replay_window_size(&self) -> usize {
+        self.inner.replay_window_size()
+    }
+
+    /// Number of connections and connection attempts using the socket.
+    pub fn connection_count(&self) -> usize {
+        self.inner.connections.read().unwrap().len()
+    }
+
+    /// Set ciphers offered to peers in order of preference, used by
+    /// connections created after this call.
+    pub fn set_ciphers(&self, ciphers: Vec<Cipher>) {
+        assert!(!ciphers.is_empty(), "At least one cipher is required");
+        *self.inner.ciphers.write().unwrap() = ciphers;
+    }
+
+    pub fn ciphers(&self) -> Vec<Cipher> {
+        self.inner.ciphers()
+    }
+
+    /// Marks all packets sent from now on with the DSCP of `class`. Returns
+    /// false where marking is unsupported, packets are then sent unmarked.
+    pub fn set_traffic_class(&self, class: TrafficClass) -> bool {
+        class.apply(&self.inner.socket)
+    }
+
     async fn main_loop(
         socket: Arc<UdpSocket>,
         raw_connections: Arc<RwLock<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>>,
@@ -343,11 +554,31 @@ impl Transport {
     }
 }
 
+/// Connection buffer registered by [`Transport::connect`] before the
+/// connection exists.
+struct PendingSource<'a> {
+    inner: &'a TransportInner,
+    source_id: u32,
+}
+
+impl Drop for PendingSource<'_> {
+    fn drop(&mut self) {
+        tracing::trace!(source_id = self.source_id, "Dropping pending source id");
+        self.inner
+            .connections
+            .write()
+            .unwrap()
+            .remove(&self.source_id);
+    }
+}
+
 pub(crate) struct TransportInner {
     pub(crate) socket: Arc<UdpSocket>,
     pub(crate) address: Address,
     pub(crate) private_key: PrivateKey,
     source_counter: Arc<AtomicU32>,
+    replay_window_size: AtomicUsize,
+    ciphers: RwLock<Vec<Cipher>>,
     #[allow(unused)]
     pub(crate) raw_connections: Arc<RwLock<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>>,
     pub(crate) connections: Arc<RwLock<HashMap<u32, mpsc::Sender<(SocketAddr, Packet)>>>>,
@@ -355,6 +586,16 @@ pub(crate) struct TransportInner {
     main_task: JoinHandle<()>,
 }
 
+impl TransportInner {
+    pub(crate) fn replay_window_size(&self) -> usize {
+        self.replay_window_size.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn ciphers(&self) -> Vec<Cipher> {
+        self.ciphers.read().unwrap().clone()
+    }
+}
+
 impl Drop for TransportInner {
     fn drop(&mut self) {
         self.main_task.abort();
@@ -0,0 +1,339 @@
+use async_trait::async_trait;
+use ntied_crypto::{PrivateKey, PublicKey};
+use ntied_server::Server;
+use ntied_transport::{
+    Address, Discovery, Error, LanDiscovery, LanRecord, LanRegistry, PeerInfo, ToAddress, Transport,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Mutex as TokioMutex, mpsc};
+use tokio::time::{sleep, timeout};
+
+/// Announced endpoint and the channel notifying its owner about lookups.
+struct Entry {
+    addr: SocketAddr,
+    public_key: PublicKey,
+    notify: mpsc::Sender<PeerInfo>,
+}
+
+/// In-memory discovery shared by all peers of a test.
+#[derive(Default, Clone)]
+struct Directory {
+    entries: Arc<Mutex<HashMap<Address, Entry>>>,
+}
+
+struct MockDiscovery {
+    directory: Directory,
+    own: Mutex<Option<PeerInfo>>,
+    notify: mpsc::Sender<PeerInfo>,
+    accept_rx: TokioMutex<mpsc::Receiver<PeerInfo>>,
+}
+
+impl MockDiscovery {
+    fn new(directory: Directory) -> Self {
+        let (notify, accept_rx) = mpsc::channel(16);
+        Self {
+            directory,
+            own: Mutex::new(None),
+            notify,
+            accept_rx: TokioMutex::new(accept_rx),
+        }
+    }
+}
+
+#[async_trait]
+impl Discovery for MockDiscovery {
+    async fn announce(
+        &self,
+        address: Address,
+        public_key: &PublicKey,
+        addr: SocketAddr,
+    ) -> Result<(), Error> {
+        self.directory.entries.lock().unwrap().insert(
+            address,
+            Entry {
+                addr,
+                public_key: public_key.clone(),
+                notify: self.notify.clone(),
+            },
+        );
+        *self.own.lock().unwrap() = Some(PeerInfo {
+            addr,
+            address,
+            public_key: public_key.clone(),
+            source_id: None,
+        });
+        Ok(())
+    }
+
+    async fn lookup(&self, address: Address, source_id: u32) -> Result<PeerInfo, Error> {
+        let own = self.own.lock().unwrap().clone().ok_or("Not announced")?;
+        let (peer, notify) = {
+            let entries = self.directory.entries.lock().unwrap();
+            let entry = entries.get(&address).ok_or("Peer not found")?;
+            let peer = PeerInfo {
+                addr: entry.addr,
+                address,
+                public_key: entry.public_key.clone(),
+                source_id: None,
+            };
+            (peer, entry.notify.clone())
+        };
+        notify
+            .send(PeerInfo {
+                source_id: Some(source_id),
+                ..own
+            })
+            .await?;
+        Ok(peer)
+    }
+
+    async fn accept(&self) -> Result<PeerInfo, Error> {
+        self.accept_rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or("Discovery closed".into())
+    }
+
+    async fn withdraw(&self) -> Result<(), Error> {
+        if let Some(own) = self.own.lock().unwrap().take() {
+            self.directory.entries.lock().unwrap().remove(&own.address);
+        }
+        Ok(())
+    }
+}
+
+async fn new_transport(directory: &Directory) -> (Transport, Address) {
+    let private_key = PrivateKey::generate().unwrap();
+    let address = private_key.public_key().to_address().unwrap();
+    let discovery = Arc::new(MockDiscovery::new(directory.clone()));
+    let transport = Transport::bind_with_discovery("127.0.0.1:0", address, private_key, discovery)
+        .await
+        .unwrap();
+    (transport, address)
+}
+
+#[tokio::test]
+async fn test_lookup_returns_announced_endpoint() {
+    let directory = Directory::default();
+    let (transport, address) = new_transport(&directory).await;
+    let discovery = MockDiscovery::new(directory.clone());
+    let key = PrivateKey::generate().unwrap().public_key();
+    discovery
+        .announce(
+            key.to_address().unwrap(),
+            &key,
+            "127.0.0.1:1".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+    let peer = discovery.lookup(address, 7).await.unwrap();
+    assert_eq!(peer.address, address);
+    assert_eq!(peer.addr, transport.local_addr());
+    // Withdrawn peers can no longer be found
+    transport.unregister().await.unwrap();
+    assert!(discovery.lookup(address, 8).await.is_err());
+}
+
+#[tokio::test]
+async fn test_transports_connect_without_server() {
+    let directory = Directory::default();
+    let (transport1, address1) = new_transport(&directory).await;
+    let (transport2, address2) = new_transport(&directory).await;
+    let connect_task = tokio::spawn(async move { transport1.connect(address2).await.unwrap() });
+    let accept_task = tokio::spawn(async move { transport2.accept().await.unwrap() });
+    let connection1 = connect_task.await.unwrap();
+    let connection2 = accept_task.await.unwrap();
+    assert_eq!(*connection1.peer_address(), address2);
+    assert_eq!(*connection2.peer_address(), address1);
+}
+
+#[tokio::test]
+async fn test_server_queries_are_unsupported() {
+    let (transport, _) = new_transport(&Directory::default()).await;
+    assert!(transport.pairing_code().await.is_err());
+    assert!(transport.resolve_pairing_code("ABCD-EFGH").await.is_err());
+    assert!(transport.reflexive_addr().await.is_err());
+}
+
+#[tokio::test]
+async fn test_diagnostic_reports_discovery_steps() {
+    let directory = Directory::default();
+    let (transport1, _) = new_transport(&directory).await;
+    let (transport2, address2) = new_transport(&directory).await;
+    let accept_task = tokio::spawn(async move { transport2.accept().await.unwrap() });
+    let (report, connection) = transport1.diagnose(address2).await;
+    accept_task.await.unwrap();
+    assert!(connection.is_some());
+    assert_eq!(report.target, address2);
+    assert_eq!(report.local_addr, transport1.local_addr());
+    // The mock backend cannot observe the public address
+    assert!(report.reflexive_addr.is_err());
+    assert!(report.nat_type().is_none());
+    assert!(report.lookup.is_ok());
+    assert!(report.server_reachable());
+    assert_eq!(report.candidates.len(), 1);
+    assert!(report.candidates[0].result.is_ok());
+    assert!(report.connected());
+    assert!(report.to_string().contains("Result: connected"));
+    // Unknown peers fail at the lookup, nothing is tried
+    let unknown = PrivateKey::generate().unwrap().public_key();
+    let (report, connection) = transport1.diagnose(unknown.to_address().unwrap()).await;
+    assert!(connection.is_none());
+    assert!(report.lookup.is_err());
+    assert!(!report.server_reachable());
+    assert!(report.candidates.is_empty());
+    assert!(!report.connected());
+    assert_eq!(transport1.connection_count(), 1);
+}
+
+/// Loopback stand-in for mDNS, records are shared in memory.
+#[derive(Default)]
+struct LoopbackRegistry {
+    records: Mutex<HashMap<Address, LanRecord>>,
+    resolved: AtomicUsize,
+}
+
+#[async_trait]
+impl LanRegistry for LoopbackRegistry {
+    async fn publish(&self, record: LanRecord) -> Result<(), Error> {
+        self.records.lock().unwrap().insert(record.address, record);
+        Ok(())
+    }
+
+    async fn unpublish(&self, address: Address) -> Result<(), Error> {
+        self.records.lock().unwrap().remove(&address);
+        Ok(())
+    }
+
+    async fn resolve(&self, address: Address) -> Result<Option<LanRecord>, Error> {
+        let record = self.records.lock().unwrap().get(&address).cloned();
+        if record.is_some() {
+            self.resolved.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(record)
+    }
+}
+
+async fn lan_discovery(registry: &Arc<LoopbackRegistry>) -> Arc<LanDiscovery> {
+    Arc::new(LanDiscovery::new(registry.clone()).await.unwrap())
+}
+
+#[tokio::test]
+async fn test_lan_peer_is_discoverable() {
+    let registry = Arc::new(LoopbackRegistry::default());
+    let alice = lan_discovery(&registry).await;
+    let bob = lan_discovery(&registry).await;
+    let alice_key = PrivateKey::generate().unwrap().public_key();
+    let alice_address = alice_key.to_address().unwrap();
+    let alice_addr: SocketAddr = "127.0.0.1:40001".parse().unwrap();
+    alice
+        .announce(alice_address, &alice_key, alice_addr)
+        .await
+        .unwrap();
+    let bob_key = PrivateKey::generate().unwrap().public_key();
+    let bob_address = bob_key.to_address().unwrap();
+    bob.announce(bob_address, &bob_key, "127.0.0.1:40002".parse().unwrap())
+        .await
+        .unwrap();
+
+    // Alice is notified about the incoming connection
+    let accept = {
+        let alice = alice.clone();
+        tokio::spawn(async move { alice.accept().await.unwrap() })
+    };
+    let peer = bob.lookup(alice_address, 5).await.unwrap();
+    assert_eq!(peer.address, alice_address);
+    assert_eq!(peer.addr, alice_addr);
+    let incoming = timeout(Duration::from_secs(5), accept)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(incoming.address, bob_address);
+    assert_eq!(incoming.addr, "127.0.0.1:40002".parse().unwrap());
+    assert_eq!(incoming.source_id, Some(5));
+
+    alice.withdraw().await.unwrap();
+    assert!(bob.lookup(alice_address, 6).await.is_err());
+}
+
+#[tokio::test]
+async fn test_lan_is_preferred_over_server() {
+    let token = Some("secret".to_string());
+    let server = Server::new("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_token(token.clone());
+    let server_addr = server.local_addr().unwrap();
+    let server_task = tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+    let registry = Arc::new(LoopbackRegistry::default());
+    // The server token is still required with local discovery
+    let private_key = PrivateKey::generate().unwrap();
+    let address = private_key.public_key().to_address().unwrap();
+    let local = lan_discovery(&registry).await;
+    assert!(
+        Transport::bind_with_local_discovery(
+            "127.0.0.1:0",
+            address,
+            private_key,
+            server_addr,
+            None,
+            local,
+        )
+        .await
+        .is_err()
+    );
+    let mut transports = Vec::new();
+    for _ in 0..2 {
+        let private_key = PrivateKey::generate().unwrap();
+        let address = private_key.public_key().to_address().unwrap();
+        let local = lan_discovery(&registry).await;
+        let transport = Transport::bind_with_local_discovery(
+            "127.0.0.1:0",
+            address,
+            private_key,
+            server_addr,
+            token.clone(),
+            local,
+        )
+        .await
+        .unwrap();
+        transports.push((Arc::new(transport), address));
+    }
+    let (lan_peer, lan_address) = transports.pop().unwrap();
+    let (transport, address) = transports.pop().unwrap();
+
+    let peer = lan_peer.clone();
+    let accept_task = tokio::spawn(async move { peer.accept().await.unwrap() });
+    let connection = transport.connect(lan_address).await.unwrap();
+    let accepted = accept_task.await.unwrap();
+    assert_eq!(*connection.peer_address(), lan_address);
+    assert_eq!(*accepted.peer_address(), address);
+    assert_eq!(registry.resolved.load(Ordering::Relaxed), 1);
+
+    // Peers not on the local network are found through the server
+    let private_key = PrivateKey::generate().unwrap();
+    let remote_address = private_key.public_key().to_address().unwrap();
+    let remote = Transport::bind_with_server_token(
+        "127.0.0.1:0",
+        remote_address,
+        private_key,
+        server_addr,
+        token,
+    )
+    .await
+    .unwrap();
+    let accept_task = tokio::spawn(async move { remote.accept().await.unwrap() });
+    let connection = transport.connect(remote_address).await.unwrap();
+    let accepted = accept_task.await.unwrap();
+    assert_eq!(*connection.peer_address(), remote_address);
+    assert_eq!(*accepted.peer_address(), address);
+    assert_eq!(registry.resolved.load(Ordering::Relaxed), 1);
+    server_task.abort();
+}
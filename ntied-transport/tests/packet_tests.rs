@@ -12,6 +12,7 @@ fn test_handshake_message_serialization() {
     let address = Address::from_bytes([0u8; 33]);
     let peer_address = Address::from_bytes([1u8; 33]);
     let ephemeral_public_key = vec![6, 7, 8, 9, 10];
+    let ciphers = vec![2, 1];
     let signature = vec![11, 12, 13, 14, 15];
 
     let handshake = HandshakePacket {
@@ -20,6 +21,7 @@ fn test_handshake_message_serialization() {
         address,
         peer_address,
         ephemeral_public_key: ephemeral_public_key.clone(),
+        ciphers: ciphers.clone(),
         signature: signature.clone(),
     };
 
@@ -34,6 +36,7 @@ fn test_handshake_message_serialization() {
             assert_eq!(h.address, address);
             assert_eq!(h.peer_address, peer_address);
             assert_eq!(h.ephemeral_public_key, ephemeral_public_key);
+            assert_eq!(h.ciphers, ciphers);
             assert_eq!(h.signature, signature);
         }
         _ => panic!("Expected Handshake message"),
@@ -49,6 +52,7 @@ fn test_handshake_ack_message_serialization() {
     let address = Address::from_bytes([2u8; 33]);
     let peer_address = Address::from_bytes([3u8; 33]);
     let ephemeral_public_key = vec![21, 22, 23, 24, 25];
+    let ciphers = vec![1];
     let signature = vec![26, 27, 28, 29, 30];
 
     let handshake_ack = HandshakeAckPacket {
@@ -58,6 +62,7 @@ fn test_handshake_ack_message_serialization() {
         address,
         peer_address,
         ephemeral_public_key: ephemeral_public_key.clone(),
+        ciphers: ciphers.clone(),
         signature: signature.clone(),
     };
 
@@ -73,6 +78,7 @@ fn test_handshake_ack_message_serialization() {
             assert_eq!(h.address, address);
             assert_eq!(h.peer_address, peer_address);
             assert_eq!(h.ephemeral_public_key, ephemeral_public_key);
+            assert_eq!(h.ciphers, ciphers);
             assert_eq!(h.signature, signature);
         }
         _ => panic!("Expected HandshakeAck message"),
@@ -332,6 +338,7 @@ fn test_address_in_message() {
         address,
         peer_address,
         ephemeral_public_key: vec![74, 75, 76],
+        ciphers: vec![1, 2],
         signature: vec![77, 78, 79],
     };
 
@@ -383,6 +390,7 @@ fn test_message_type_discrimination() {
                 address: Address::from_bytes([4u8; 33]),
                 peer_address: Address::from_bytes([5u8; 33]),
                 ephemeral_public_key: vec![81],
+                ciphers: vec![1],
                 signature: vec![82],
             }),
             "Handshake",
@@ -395,6 +403,7 @@ fn test_message_type_discrimination() {
                 address: Address::from_bytes([6u8; 33]),
                 peer_address: Address::from_bytes([7u8; 33]),
                 ephemeral_public_key: vec![84],
+                ciphers: vec![2],
                 signature: vec![85],
             }),
             "HandshakeAck",
@@ -0,0 +1,63 @@
+use ntied_transport::ReplayWindow;
+
+/// Test that a duplicated packet is dropped
+#[test]
+fn test_duplicate_rejected() {
+    let mut window = ReplayWindow::new(64);
+    assert!(window.accept(0));
+    assert!(window.accept(1));
+    assert!(!window.accept(1));
+    assert!(!window.accept(0));
+}
+
+/// Test that reordered packets inside the window are accepted once
+#[test]
+fn test_reordered_accepted() {
+    let mut window = ReplayWindow::new(64);
+    assert!(window.accept(10));
+    assert!(window.accept(7));
+    assert!(window.accept(9));
+    assert!(window.accept(8));
+    assert!(window.accept(11));
+    for sequence in 7..=11 {
+        assert!(!window.accept(sequence), "sequence {sequence} replayed");
+    }
+}
+
+/// Test that packets older than the window are rejected
+#[test]
+fn test_stale_rejected() {
+    let mut window = ReplayWindow::new(16);
+    assert!(window.accept(100));
+    assert!(window.accept(85));
+    assert!(!window.accept(84));
+    assert!(!window.accept(0));
+}
+
+/// Test that a large jump forward forgets old slots without false positives
+#[test]
+fn test_large_jump() {
+    let mut window = ReplayWindow::new(8);
+    for sequence in 0..8 {
+        assert!(window.accept(sequence));
+    }
+    assert!(window.accept(1000));
+    // Fresh sequence numbers sharing slots with old ones are accepted.
+    assert!(window.accept(999));
+    assert!(window.accept(993));
+    assert!(!window.accept(992));
+    assert!(!window.accept(1000));
+}
+
+/// Test window size configuration
+#[test]
+fn test_window_size() {
+    assert_eq!(ReplayWindow::new(32).size(), 32);
+    assert_eq!(ReplayWindow::default().size(), ReplayWindow::DEFAULT_SIZE);
+}
+
+#[test]
+#[should_panic]
+fn test_zero_size_panics() {
+    ReplayWindow::new(0);
+}
@@ -1,7 +1,9 @@
 use ntied_transport::{
     Address, ServerConnectRequest, ServerConnectResponse, ServerErrorResponse,
-    ServerIncomingConnectionResponse, ServerRegisterRequest, ServerRegisterResponse, ServerRequest,
-    ServerResponse,
+    ServerIncomingConnectionResponse, ServerPairingCodeRequest, ServerPairingCodeResponse,
+    ServerReflexiveAddrRequest, ServerReflexiveAddrResponse, ServerRegisterRequest,
+    ServerRegisterResponse, ServerRequest, ServerResolvePairingCodeRequest,
+    ServerResolvePairingCodeResponse, ServerResponse, ServerUnregisterRequest,
 };
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
@@ -41,6 +43,7 @@ fn test_server_request_register() {
         request_id,
         public_key: public_key.clone(),
         address,
+        token: None,
     };
 
     let request = ServerRequest::Register(register_request);
@@ -52,6 +55,7 @@ fn test_server_request_register() {
             assert_eq!(r.request_id, request_id);
             assert_eq!(r.public_key, public_key);
             assert_eq!(r.address, address);
+            assert_eq!(r.token, None);
         }
         _ => panic!("Expected Register request"),
     }
@@ -114,7 +118,12 @@ fn test_server_response_heartbeat() {
 fn test_server_response_register() {
     let request_id = 11111u32;
 
-    let register_response = ServerRegisterResponse { request_id };
+    let session = [7u8; 16];
+
+    let register_response = ServerRegisterResponse {
+        request_id,
+        session,
+    };
 
     let response = ServerResponse::Register(register_response);
     let serialized = response.serialize();
@@ -123,11 +132,31 @@ fn test_server_response_register() {
     match deserialized {
         ServerResponse::Register(r) => {
             assert_eq!(r.request_id, request_id);
+            assert_eq!(r.session, session);
         }
         _ => panic!("Expected Register response"),
     }
 }
 
+/// Test serialization and deserialization of ServerRequest::Unregister
+#[test]
+fn test_server_request_unregister() {
+    let session = [9u8; 16];
+
+    let request = ServerRequest::Unregister(ServerUnregisterRequest { session });
+    let serialized = request.serialize();
+    let deserialized = ServerRequest::deserialize(&serialized).unwrap();
+
+    match deserialized {
+        ServerRequest::Unregister(r) => {
+            assert_eq!(r.session, session);
+        }
+        _ => panic!("Expected Unregister request"),
+    }
+    // Unregister without the session is rejected
+    assert!(ServerRequest::deserialize(&[3]).is_err());
+}
+
 /// Test serialization and deserialization of ServerResponse::RegisterError
 #[test]
 fn test_server_response_register_error() {
@@ -247,6 +276,7 @@ fn test_server_request_large_public_key() {
         request_id,
         public_key: large_public_key.clone(),
         address,
+        token: None,
     };
 
     let request = ServerRequest::Register(register_request);
@@ -298,6 +328,91 @@ fn test_server_response_with_ipv6() {
     }
 }
 
+/// Test serialization and deserialization of pairing code requests
+#[test]
+fn test_server_request_pairing_code() {
+    let request = ServerRequest::PairingCode(ServerPairingCodeRequest { request_id: 42 });
+    match ServerRequest::deserialize(&request.serialize()).unwrap() {
+        ServerRequest::PairingCode(r) => assert_eq!(r.request_id, 42),
+        _ => panic!("Expected PairingCode request"),
+    }
+
+    let request = ServerRequest::ResolvePairingCode(ServerResolvePairingCodeRequest {
+        request_id: 43,
+        code: "ABCD-EFGH".to_string(),
+    });
+    match ServerRequest::deserialize(&request.serialize()).unwrap() {
+        ServerRequest::ResolvePairingCode(r) => {
+            assert_eq!(r.request_id, 43);
+            assert_eq!(r.code, "ABCD-EFGH");
+        }
+        _ => panic!("Expected ResolvePairingCode request"),
+    }
+}
+
+/// Test serialization and deserialization of pairing code responses
+#[test]
+fn test_server_response_pairing_code() {
+    let response = ServerResponse::PairingCode(ServerPairingCodeResponse {
+        request_id: 42,
+        code: "ABCD-EFGH".to_string(),
+        expires_in: 300,
+    });
+    match ServerResponse::deserialize(&response.serialize()).unwrap() {
+        ServerResponse::PairingCode(r) => {
+            assert_eq!(r.request_id, 42);
+            assert_eq!(r.code, "ABCD-EFGH");
+            assert_eq!(r.expires_in, 300);
+        }
+        _ => panic!("Expected PairingCode response"),
+    }
+
+    let address = Address::from_bytes([7u8; 33]);
+    let response = ServerResponse::ResolvePairingCode(ServerResolvePairingCodeResponse {
+        request_id: 43,
+        address,
+    });
+    match ServerResponse::deserialize(&response.serialize()).unwrap() {
+        ServerResponse::ResolvePairingCode(r) => {
+            assert_eq!(r.request_id, 43);
+            assert_eq!(r.address, address);
+        }
+        _ => panic!("Expected ResolvePairingCode response"),
+    }
+
+    let response = ServerResponse::ResolvePairingCodeError(ServerErrorResponse {
+        request_id: 44,
+        code: 21,
+    });
+    match ServerResponse::deserialize(&response.serialize()).unwrap() {
+        ServerResponse::ResolvePairingCodeError(e) => {
+            assert_eq!(e.request_id, 44);
+            assert_eq!(e.code, 21);
+        }
+        _ => panic!("Expected ResolvePairingCodeError response"),
+    }
+}
+
+/// Test serialization and deserialization of ServerResponse::ReflexiveAddr
+#[test]
+fn test_server_response_reflexive_addr() {
+    let addr = SocketAddr::new(
+        IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        61000,
+    );
+    let response = ServerResponse::ReflexiveAddr(ServerReflexiveAddrResponse {
+        request_id: 77,
+        addr,
+    });
+    match ServerResponse::deserialize(&response.serialize()).unwrap() {
+        ServerResponse::ReflexiveAddr(r) => {
+            assert_eq!(r.request_id, 77);
+            assert_eq!(r.addr, addr);
+        }
+        _ => panic!("Expected ReflexiveAddr response"),
+    }
+}
+
 /// Test invalid request type deserialization
 #[test]
 fn test_server_request_invalid_type() {
@@ -348,6 +463,7 @@ fn test_server_request_type_discrimination() {
                 request_id: 1,
                 public_key: vec![1],
                 address: Address::from_bytes([1u8; 33]),
+                token: None,
             }),
             "Register",
         ),
@@ -359,6 +475,25 @@ fn test_server_request_type_discrimination() {
             }),
             "Connect",
         ),
+        (
+            ServerRequest::Unregister(ServerUnregisterRequest { session: [0; 16] }),
+            "Unregister",
+        ),
+        (
+            ServerRequest::PairingCode(ServerPairingCodeRequest { request_id: 3 }),
+            "PairingCode",
+        ),
+        (
+            ServerRequest::ResolvePairingCode(ServerResolvePairingCodeRequest {
+                request_id: 4,
+                code: "ABCD-EFGH".to_string(),
+            }),
+            "ResolvePairingCode",
+        ),
+        (
+            ServerRequest::ReflexiveAddr(ServerReflexiveAddrRequest { request_id: 5 }),
+            "ReflexiveAddr",
+        ),
     ];
 
     for (request, expected_type) in requests {
@@ -369,6 +504,10 @@ fn test_server_request_type_discrimination() {
             ServerRequest::Heartbeat => "Heartbeat",
             ServerRequest::Register(_) => "Register",
             ServerRequest::Connect(_) => "Connect",
+            ServerRequest::Unregister(_) => "Unregister",
+            ServerRequest::PairingCode(_) => "PairingCode",
+            ServerRequest::ResolvePairingCode(_) => "ResolvePairingCode",
+            ServerRequest::ReflexiveAddr(_) => "ReflexiveAddr",
         };
 
         assert_eq!(actual_type, expected_type);
@@ -381,7 +520,10 @@ fn test_server_response_type_discrimination() {
     let responses = vec![
         (ServerResponse::Heartbeat, "Heartbeat"),
         (
-            ServerResponse::Register(ServerRegisterResponse { request_id: 1 }),
+            ServerResponse::Register(ServerRegisterResponse {
+                request_id: 1,
+                session: [0; 16],
+            }),
             "Register",
         ),
         (
@@ -416,6 +558,49 @@ fn test_server_response_type_discrimination() {
             }),
             "IncomingConnection",
         ),
+        (
+            ServerResponse::PairingCode(ServerPairingCodeResponse {
+                request_id: 6,
+                code: "ABCD-EFGH".to_string(),
+                expires_in: 300,
+            }),
+            "PairingCode",
+        ),
+        (
+            ServerResponse::PairingCodeError(ServerErrorResponse {
+                request_id: 7,
+                code: 20,
+            }),
+            "PairingCodeError",
+        ),
+        (
+            ServerResponse::ResolvePairingCode(ServerResolvePairingCodeResponse {
+                request_id: 8,
+                address: Address::from_bytes([8u8; 33]),
+            }),
+            "ResolvePairingCode",
+        ),
+        (
+            ServerResponse::ResolvePairingCodeError(ServerErrorResponse {
+                request_id: 9,
+                code: 21,
+            }),
+            "ResolvePairingCodeError",
+        ),
+        (
+            ServerResponse::ReflexiveAddr(ServerReflexiveAddrResponse {
+                request_id: 10,
+                addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)), 61000),
+            }),
+            "ReflexiveAddr",
+        ),
+        (
+            ServerResponse::ReflexiveAddrError(ServerErrorResponse {
+                request_id: 11,
+                code: 30,
+            }),
+            "ReflexiveAddrError",
+        ),
     ];
 
     for (response, expected_type) in responses {
@@ -429,6 +614,12 @@ fn test_server_response_type_discrimination() {
             ServerResponse::Connect(_) => "Connect",
             ServerResponse::ConnectError(_) => "ConnectError",
             ServerResponse::IncomingConnection(_) => "IncomingConnection",
+            ServerResponse::PairingCode(_) => "PairingCode",
+            ServerResponse::PairingCodeError(_) => "PairingCodeError",
+            ServerResponse::ResolvePairingCode(_) => "ResolvePairingCode",
+            ServerResponse::ResolvePairingCodeError(_) => "ResolvePairingCodeError",
+            ServerResponse::ReflexiveAddr(_) => "ReflexiveAddr",
+            ServerResponse::ReflexiveAddrError(_) => "ReflexiveAddrError",
         };
 
         assert_eq!(actual_type, expected_type);
@@ -446,6 +637,7 @@ fn test_max_request_id_values() {
         request_id: max_request_id,
         public_key: vec![70, 71, 72],
         address,
+        token: None,
     };
 
     let request = ServerRequest::Register(register_request);
@@ -574,6 +766,7 @@ fn test_empty_public_key() {
         request_id,
         public_key: empty_public_key.clone(),
         address,
+        token: None,
     };
 
     let request = ServerRequest::Register(register_request);
@@ -680,6 +873,7 @@ fn test_serialization_consistency() {
         request_id: 999999,
         public_key: vec![1, 2, 3, 4, 5],
         address: Address::from_bytes([123u8; 33]),
+        token: None,
     });
 
     // Serialize multiple times and ensure consistency
@@ -743,6 +937,7 @@ fn test_maximum_public_key_size() {
         request_id: 12345,
         public_key: large_public_key.clone(),
         address: Address::from_bytes([0xCD; 33]),
+        token: None,
     };
 
     let request = ServerRequest::Register(register_request);
@@ -883,6 +1078,7 @@ fn test_sequential_request_ids() {
             request_id: i,
             public_key: vec![i as u8],
             address: Address::from_bytes([i as u8; 33]),
+            token: None,
         }));
     }
 
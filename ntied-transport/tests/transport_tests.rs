@@ -1,11 +1,15 @@
-use ntied_crypto::PrivateKey;
+use ntied_crypto::{Cipher, PrivateKey};
 use ntied_server::Server;
-use ntied_transport::{ToAddress, Transport};
+use ntied_transport::{
+    Address, NatType, ServerRegisterRequest, ServerRequest, ServerResponse,
+    ServerUnregisterRequest, ToAddress, TrafficClass, Transport, select_cipher,
+};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::net::UdpSocket;
 use tokio::task::JoinHandle;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
 
 fn init_tracing() {
     let _ = tracing_subscriber::fmt()
@@ -175,6 +179,336 @@ async fn test_long_connection() {
     server_task.abort();
 }
 
+#[test]
+fn test_select_cipher() {
+    let aes = Cipher::Aes256Gcm;
+    let chacha = Cipher::ChaCha20Poly1305;
+    assert_eq!(select_cipher(&[chacha, aes], &[aes, chacha]), Some(chacha));
+    assert_eq!(select_cipher(&[aes, chacha], &[chacha]), Some(chacha));
+    assert_eq!(select_cipher(&[aes], &[chacha]), None);
+}
+
+#[tokio::test]
+async fn test_cipher_negotiation() {
+    init_tracing();
+    let (server_addr, server_task) = create_server().await;
+    let private_key1 = PrivateKey::generate().unwrap();
+    let address1 = private_key1.public_key().to_address().unwrap();
+    let transport1 = Transport::bind("127.0.0.1:0", address1, private_key1, server_addr)
+        .await
+        .unwrap();
+    transport1.set_ciphers(vec![Cipher::ChaCha20Poly1305, Cipher::Aes256Gcm]);
+    let private_key2 = PrivateKey::generate().unwrap();
+    let address2 = private_key2.public_key().to_address().unwrap();
+    let transport2 = Transport::bind("127.0.0.1:0", address2, private_key2, server_addr)
+        .await
+        .unwrap();
+    transport2.set_ciphers(vec![Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305]);
+    let connect_task = tokio::spawn(async move { transport1.connect(address2).await.unwrap() });
+    let accept_task = tokio::spawn(async move { transport2.accept().await.unwrap() });
+    let connection1 = connect_task.await.unwrap();
+    let connection2 = accept_task.await.unwrap();
+    // Preference of the connecting side wins
+    assert_eq!(connection1.cipher(), Cipher::ChaCha20Poly1305);
+    assert_eq!(connection2.cipher(), Cipher::ChaCha20Poly1305);
+    connection1.send("hello").await.unwrap();
+    let message: String = connection2.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(message, "hello");
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_dual_stack_server() {
+    init_tracing();
+    let server = Server::new_dual_stack("[::]:0".parse().unwrap())
+        .await
+        .unwrap();
+    let port = server.local_addr().unwrap().port();
+    let server_task = tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+    // Clients of both families register and discover peers of the same family
+    connect_pair(
+        "[::1]:0",
+        SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], port)),
+    )
+    .await;
+    connect_pair("127.0.0.1:0", SocketAddr::from(([127, 0, 0, 1], port))).await;
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_ipv6_server() {
+    init_tracing();
+    let server = Server::new("[::1]:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let server_task = tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+    connect_pair("[::1]:0", server_addr).await;
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_dual_stack_requires_ipv6() {
+    assert!(
+        Server::new_dual_stack("127.0.0.1:0".parse().unwrap())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_pairing_code() {
+    init_tracing();
+    let (server_addr, server_task) = create_server().await;
+    let (transport1, address1) = new_transport(server_addr).await;
+    let (transport2, _) = new_transport(server_addr).await;
+    let pairing = transport1.pairing_code().await.unwrap();
+    assert_eq!(pairing.code.len(), 9);
+    assert_eq!(pairing.expires_in, Server::PAIRING_CODE_TTL);
+    // Codes are case-insensitive and can be typed without the separator
+    let typed = pairing.code.replace('-', "").to_lowercase();
+    assert_eq!(
+        transport2.resolve_pairing_code(&typed).await.unwrap(),
+        address1
+    );
+    // Codes are single-use
+    assert!(
+        transport2
+            .resolve_pairing_code(&pairing.code)
+            .await
+            .is_err()
+    );
+    assert!(transport2.resolve_pairing_code("AAAA-AAAA").await.is_err());
+    // A new code replaces the previous one
+    let first = transport1.pairing_code().await.unwrap();
+    let second = transport1.pairing_code().await.unwrap();
+    assert!(transport2.resolve_pairing_code(&first.code).await.is_err());
+    assert_eq!(
+        transport2.resolve_pairing_code(&second.code).await.unwrap(),
+        address1
+    );
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_pairing_code_expires() {
+    init_tracing();
+    let server = Server::new("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_pairing_code_ttl(Duration::from_millis(200));
+    let server_addr = server.local_addr().unwrap();
+    let server_task = tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+    let (transport1, _) = new_transport(server_addr).await;
+    let (transport2, _) = new_transport(server_addr).await;
+    let pairing = transport1.pairing_code().await.unwrap();
+    sleep(Duration::from_millis(300)).await;
+    assert!(
+        transport2
+            .resolve_pairing_code(&pairing.code)
+            .await
+            .is_err()
+    );
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_reflexive_addr() {
+    init_tracing();
+    let (server_addr, server_task) = create_server().await;
+    let (transport, _) = new_transport(server_addr).await;
+    // Without NAT the server observes the local socket address
+    let reflexive = transport.reflexive_addr().await.unwrap();
+    assert_eq!(reflexive, transport.local_addr());
+    assert_eq!(
+        NatType::classify(transport.local_addr(), reflexive),
+        NatType::Open
+    );
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_reflexive_addr_disabled() {
+    init_tracing();
+    let server = Server::new("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_reflexive_addr(false);
+    let server_addr = server.local_addr().unwrap();
+    let server_task = tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+    let (transport, _) = new_transport(server_addr).await;
+    assert!(transport.reflexive_addr().await.is_err());
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_server_token() {
+    init_tracing();
+    let server = Server::new("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_token(Some("secret".to_string()));
+    let server_addr = server.local_addr().unwrap();
+    let server_task = tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+    let bind = |token: Option<&str>| {
+        let private_key = PrivateKey::generate().unwrap();
+        let address = private_key.public_key().to_address().unwrap();
+        let token = token.map(str::to_string);
+        Transport::bind_with_server_token("127.0.0.1:0", address, private_key, server_addr, token)
+    };
+    assert!(bind(None).await.is_err());
+    assert!(bind(Some("wrong")).await.is_err());
+    // A wrong token of the same length is rejected as well
+    let err = bind(Some("secreT")).await.err().unwrap();
+    assert!(err.to_string().contains("code 4"), "{err}");
+    // Tokens sharing a prefix with the expected one do not match
+    assert!(bind(Some("secre")).await.is_err());
+    assert!(bind(Some("secret2")).await.is_err());
+    assert!(bind(Some("secret")).await.is_ok());
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_unregister_requires_session() {
+    init_tracing();
+    let (server_addr, server_task) = create_server().await;
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let request = |request: ServerRequest| {
+        let socket = &socket;
+        async move {
+            socket
+                .send_to(&request.serialize(), server_addr)
+                .await
+                .unwrap();
+            let mut buf = [0u8; 1024];
+            let len = timeout(Duration::from_millis(300), socket.recv(&mut buf))
+                .await
+                .ok()?
+                .unwrap();
+            Some(ServerResponse::deserialize(&buf[..len]).unwrap())
+        }
+    };
+    let public_key = PrivateKey::generate().unwrap().public_key();
+    let register = ServerRequest::Register(ServerRegisterRequest {
+        request_id: 1,
+        public_key: public_key.to_bytes().unwrap(),
+        address: public_key.to_address().unwrap(),
+        token: None,
+    });
+    let session = match request(register).await {
+        Some(ServerResponse::Register(resp)) => resp.session,
+        _ => panic!("Expected Register response"),
+    };
+    // A wrong session from the same socket address keeps the client
+    let mut forged = session;
+    forged[0] ^= 1;
+    socket
+        .send_to(
+            &ServerRequest::Unregister(ServerUnregisterRequest { session: forged }).serialize(),
+            server_addr,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        request(ServerRequest::Heartbeat).await,
+        Some(ServerResponse::Heartbeat)
+    ));
+    socket
+        .send_to(
+            &ServerRequest::Unregister(ServerUnregisterRequest { session }).serialize(),
+            server_addr,
+        )
+        .await
+        .unwrap();
+    assert!(request(ServerRequest::Heartbeat).await.is_none());
+    server_task.abort();
+}
+
+#[test]
+fn test_nat_type() {
+    let local: SocketAddr = "192.168.1.10:5000".parse().unwrap();
+    assert_eq!(
+        NatType::classify(local, "203.0.113.7:5000".parse().unwrap()),
+        NatType::PortPreserving
+    );
+    assert_eq!(
+        NatType::classify(local, "203.0.113.7:61000".parse().unwrap()),
+        NatType::PortMapping
+    );
+    assert_eq!(NatType::classify(local, local), NatType::Open);
+}
+
+#[tokio::test]
+async fn test_cancelled_connect_is_cleaned_up() {
+    init_tracing();
+    let (server_addr, server_task) = create_server().await;
+    let (transport1, _) = new_transport(server_addr).await;
+    // The peer never accepts, so the handshake is retried until cancelled.
+    let (_transport2, address2) = new_transport(server_addr).await;
+    let attempt = tokio::time::timeout(Duration::from_millis(300), transport1.connect(address2));
+    assert!(attempt.await.is_err());
+    assert_eq!(transport1.connection_count(), 0);
+    // Failed attempts are cleaned up as well.
+    let missing = PrivateKey::generate()
+        .unwrap()
+        .public_key()
+        .to_address()
+        .unwrap();
+    assert!(transport1.connect(missing).await.is_err());
+    assert_eq!(transport1.connection_count(), 0);
+    server_task.abort();
+}
+
+#[tokio::test]
+async fn test_traffic_class() {
+    init_tracing();
+    assert_eq!(TrafficClass::default(), TrafficClass::BestEffort);
+    assert_eq!(TrafficClass::Audio.dscp(), 46);
+    assert_eq!(TrafficClass::Bulk.dscp(), 8);
+    let (server_addr, server_task) = create_server().await;
+    let (transport, _) = new_transport(server_addr).await;
+    if cfg!(any(target_os = "linux", target_os = "macos")) {
+        assert!(transport.set_traffic_class(TrafficClass::Audio));
+        assert!(transport.set_traffic_class(TrafficClass::BestEffort));
+    } else {
+        // Unsupported platforms fall back to unmarked packets
+        transport.set_traffic_class(TrafficClass::Audio);
+    }
+    server_task.abort();
+}
+
+async fn new_transport(server_addr: SocketAddr) -> (Transport, Address) {
+    let private_key = PrivateKey::generate().unwrap();
+    let address = private_key.public_key().to_address().unwrap();
+    let transport = Transport::bind("127.0.0.1:0", address, private_key, server_addr)
+        .await
+        .unwrap();
+    (transport, address)
+}
+
+/// Binds two transports to `bind_addr` and connects them through the server.
+async fn connect_pair(bind_addr: &str, server_addr: SocketAddr) {
+    let private_key1 = PrivateKey::generate().unwrap();
+    let address1 = private_key1.public_key().to_address().unwrap();
+    let transport1 = Transport::bind(bind_addr, address1, private_key1, server_addr)
+        .await
+        .unwrap();
+    let private_key2 = PrivateKey::generate().unwrap();
+    let address2 = private_key2.public_key().to_address().unwrap();
+    let transport2 = Transport::bind(bind_addr, address2, private_key2, server_addr)
+        .await
+        .unwrap();
+    let connect_task = tokio::spawn(async move { transport1.connect(address2).await.unwrap() });
+    let accept_task = tokio::spawn(async move { transport2.accept().await.unwrap() });
+    let connection1 = connect_task.await.unwrap();
+    let connection2 = accept_task.await.unwrap();
+    assert_eq!(*connection1.peer_address(), address2);
+    assert_eq!(*connection2.peer_address(), address1);
+}
+
 async fn create_server() -> (
     SocketAddr,
     JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>>,
@@ -0,0 +1,284 @@
+use ntied_transport::{
+    Address, DataPacket, DecryptedPacket, EncryptedPacket, EncryptionEpoch, Error,
+    HandshakeAckPacket, HandshakePacket, HeartbeatPacket, Packet, RotatePacket,
+    ServerConnectRequest, ServerConnectResponse, ServerErrorResponse,
+    ServerIncomingConnectionResponse, ServerPairingCodeRequest, ServerPairingCodeResponse,
+    ServerReflexiveAddrRequest, ServerReflexiveAddrResponse, ServerRegisterRequest,
+    ServerRegisterResponse, ServerRequest, ServerResolvePairingCodeRequest,
+    ServerResolvePairingCodeResponse, ServerResponse, ServerUnregisterRequest,
+};
+use std::net::SocketAddr;
+
+/// Message with a byte encoding sent over the wire.
+trait Wire: Sized {
+    fn encode(&self) -> Vec<u8>;
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+impl Wire for ServerRequest {
+    fn encode(&self) -> Vec<u8> {
+        self.serialize()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Self::deserialize(bytes)
+    }
+}
+
+impl Wire for ServerResponse {
+    fn encode(&self) -> Vec<u8> {
+        self.serialize()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Self::deserialize(bytes)
+    }
+}
+
+impl Wire for Packet {
+    fn encode(&self) -> Vec<u8> {
+        self.serialize()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Self::deserialize(bytes)
+    }
+}
+
+impl Wire for DecryptedPacket {
+    fn encode(&self) -> Vec<u8> {
+        self.serialize()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Self::deserialize(bytes)
+    }
+}
+
+/// Checks that `value` survives encoding and decoding, returns its encoding.
+fn assert_roundtrip<T: Wire>(value: &T) -> Vec<u8> {
+    let bytes = value.encode();
+    let decoded = T::decode(&bytes).expect("Failed to decode encoded value");
+    assert_eq!(decoded.encode(), bytes, "Encoding changed after round trip");
+    bytes
+}
+
+/// Checks `value` against an encoding captured from a previous version.
+fn assert_golden<T: Wire>(value: &T, golden: &str) {
+    let bytes = assert_roundtrip(value);
+    assert_eq!(hex::encode(&bytes), golden, "Wire format changed");
+    let decoded = T::decode(&hex::decode(golden).unwrap()).expect("Failed to decode golden");
+    assert_eq!(decoded.encode(), bytes);
+}
+
+fn address(byte: u8) -> Address {
+    Address::from_bytes([byte; Address::LEN])
+}
+
+fn addr() -> SocketAddr {
+    "192.168.1.10:4000".parse().unwrap()
+}
+
+#[test]
+fn test_golden_server_requests() {
+    assert_golden(&ServerRequest::Heartbeat, "");
+    assert_golden(
+        &ServerRequest::Register(ServerRegisterRequest {
+            request_id: 1,
+            public_key: vec![1, 2, 3],
+            address: address(0xa1),
+            token: None,
+        }),
+        "01000000010003010203a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1",
+    );
+    assert_golden(
+        &ServerRequest::Register(ServerRegisterRequest {
+            request_id: 1,
+            public_key: vec![1, 2, 3],
+            address: address(0xa1),
+            token: Some("key".to_string()),
+        }),
+        "01000000010003010203a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a100036b6579",
+    );
+    assert_golden(
+        &ServerRequest::Connect(ServerConnectRequest {
+            request_id: 2,
+            address: address(0xb2),
+            source_id: 7,
+        }),
+        "0200000002b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b200000007",
+    );
+    assert_golden(
+        &ServerRequest::Unregister(ServerUnregisterRequest {
+            session: [0xc3; 16],
+        }),
+        "03c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3",
+    );
+    assert_golden(
+        &ServerRequest::PairingCode(ServerPairingCodeRequest { request_id: 3 }),
+        "0400000003",
+    );
+    assert_golden(
+        &ServerRequest::ResolvePairingCode(ServerResolvePairingCodeRequest {
+            request_id: 4,
+            code: "ABCD-EFGH".to_string(),
+        }),
+        "05000000040009414243442d45464748",
+    );
+    assert_golden(
+        &ServerRequest::ReflexiveAddr(ServerReflexiveAddrRequest { request_id: 5 }),
+        "0600000005",
+    );
+}
+
+#[test]
+fn test_golden_server_responses() {
+    let error = |request_id| ServerErrorResponse {
+        request_id,
+        code: 20,
+    };
+    assert_golden(&ServerResponse::Heartbeat, "");
+    assert_golden(
+        &ServerResponse::Register(ServerRegisterResponse {
+            request_id: 1,
+            session: [0xc1; 16],
+        }),
+        "0100000001c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1",
+    );
+    assert_golden(&ServerResponse::RegisterError(error(1)), "02000000010014");
+    assert_golden(
+        &ServerResponse::Connect(ServerConnectResponse {
+            request_id: 2,
+            public_key: vec![4, 5],
+            address: address(0xb2),
+            addr: addr(),
+        }),
+        "030000000200020405b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b204c0a8010a0fa0",
+    );
+    assert_golden(&ServerResponse::ConnectError(error(2)), "04000000020014");
+    assert_golden(
+        &ServerResponse::IncomingConnection(ServerIncomingConnectionResponse {
+            public_key: vec![6],
+            address: address(0xc3),
+            addr: "[2001:db8::1]:5000".parse().unwrap(),
+            source_id: 9,
+        }),
+        "05000106c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c30620010db8000000000000000000000001138800000009",
+    );
+    assert_golden(
+        &ServerResponse::PairingCode(ServerPairingCodeResponse {
+            request_id: 3,
+            code: "ABCD-EFGH".to_string(),
+            expires_in: 300,
+        }),
+        "06000000030009414243442d454647480000012c",
+    );
+    assert_golden(
+        &ServerResponse::PairingCodeError(error(3)),
+        "07000000030014",
+    );
+    assert_golden(
+        &ServerResponse::ResolvePairingCode(ServerResolvePairingCodeResponse {
+            request_id: 4,
+            address: address(0xd4),
+        }),
+        "0800000004d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4",
+    );
+    assert_golden(
+        &ServerResponse::ResolvePairingCodeError(error(4)),
+        "09000000040014",
+    );
+    assert_golden(
+        &ServerResponse::ReflexiveAddr(ServerReflexiveAddrResponse {
+            request_id: 5,
+            addr: addr(),
+        }),
+        "0a0000000504c0a8010a0fa0",
+    );
+    assert_golden(
+        &ServerResponse::ReflexiveAddrError(error(5)),
+        "0b000000050014",
+    );
+}
+
+#[test]
+fn test_golden_packets() {
+    assert_golden(
+        &Packet::Handshake(HandshakePacket {
+            source_id: 1,
+            peer_address: address(0xa1),
+            address: address(0xb2),
+            public_key: vec![1, 2],
+            ephemeral_public_key: vec![3, 4],
+            ciphers: vec![1],
+            signature: vec![5, 6],
+        }),
+        "0100000001a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2000201020002030400010100020506",
+    );
+    assert_golden(
+        &Packet::HandshakeAck(HandshakeAckPacket {
+            target_id: 1,
+            source_id: 2,
+            peer_address: address(0xb2),
+            address: address(0xa1),
+            public_key: vec![1, 2],
+            ephemeral_public_key: vec![3, 4],
+            ciphers: vec![1],
+            signature: vec![5, 6],
+        }),
+        "020000000100000002b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1000201020002030400010100020506",
+    );
+    assert_golden(
+        &Packet::Encrypted(EncryptedPacket {
+            target_id: 3,
+            epoch: EncryptionEpoch::new(1),
+            payload: vec![7, 8, 9],
+            nonce: [0x0c; 12],
+        }),
+        "810000000300030708090c0c0c0c0c0c0c0c0c0c0c0c",
+    );
+}
+
+#[test]
+fn test_golden_decrypted_packets() {
+    assert_golden(&DecryptedPacket::Heartbeat(HeartbeatPacket {}), "01");
+    assert_golden(&DecryptedPacket::HeartbeatAck(HeartbeatPacket {}), "02");
+    assert_golden(
+        &DecryptedPacket::Data(DataPacket {
+            data: vec![1, 2, 3],
+        }),
+        "030003010203",
+    );
+    let rotate = || RotatePacket {
+        ephemeral_public_key: vec![4, 5],
+        signature: vec![6],
+    };
+    assert_golden(&DecryptedPacket::Rotate(rotate()), "0400020405000106");
+    assert_golden(&DecryptedPacket::RotateAck(rotate()), "0500020405000106");
+}
+
+#[test]
+fn test_roundtrip_edge_values() {
+    assert_roundtrip(&ServerRequest::Register(ServerRegisterRequest {
+        request_id: u32::MAX,
+        public_key: Vec::new(),
+        address: address(0),
+        token: None,
+    }));
+    assert_roundtrip(&ServerResponse::ConnectError(ServerErrorResponse {
+        request_id: u32::MAX,
+        code: u16::MAX,
+    }));
+    assert_roundtrip(&ServerResponse::PairingCode(ServerPairingCodeResponse {
+        request_id: 0,
+        code: String::new(),
+        expires_in: u32::MAX,
+    }));
+    assert_roundtrip(&Packet::Encrypted(EncryptedPacket {
+        target_id: u32::MAX,
+        epoch: EncryptionEpoch::new(u8::MAX - 128),
+        payload: vec![0xff; 1200],
+        nonce: [0xff; 12],
+    }));
+}
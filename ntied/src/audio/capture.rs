@@ -1,12 +1,24 @@
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
+#[cfg(feature = "audio")]
+use anyhow::anyhow;
+#[cfg(feature = "audio")]
 use cpal::traits::{DeviceTrait as _, StreamTrait};
+#[cfg(feature = "audio")]
 use cpal::{Device, FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig};
 use tokio::sync::mpsc;
-use tokio::task::{JoinHandle, spawn_blocking};
+use tokio::task::JoinHandle;
+#[cfg(feature = "audio")]
+use tokio::task::spawn_blocking;
+
+#[cfg(not(feature = "audio"))]
+use super::Device;
+#[cfg(feature = "audio")]
+use super::{ExclusiveModeUnavailable, open_with_fallback};
+use super::{ShareMode, StreamBuffer};
 
 #[derive(Debug, Clone)]
 pub struct AudioFrame {
@@ -16,6 +28,17 @@ pub struct AudioFrame {
     pub timestamp: Instant,
 }
 
+impl AudioFrame {
+    /// Playback duration of the samples.
+    pub fn duration(&self) -> Duration {
+        let samples_per_second = self.sample_rate as u64 * self.channels.max(1) as u64;
+        if samples_per_second == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos(self.samples.len() as u64 * 1_000_000_000 / samples_per_second)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AudioConfig {
     pub sample_rate: u32,
@@ -38,24 +61,77 @@ impl AudioConfig {
     }
 }
 
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
 enum Command {
     Mute(bool),
 }
 
+/// Multipliers applied to captured samples.
+struct CaptureLevels {
+    /// Per-call volume, starts at the value passed to the stream
+    volume: AtomicU32,
+    /// Device gain, kept by the caller across calls
+    gain: AtomicU32,
+}
+
+impl CaptureLevels {
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    fn factor(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+            * f32::from_bits(self.gain.load(Ordering::Relaxed))
+    }
+}
+
 pub struct CaptureStream {
     command_tx: mpsc::Sender<Command>,
-    volume: Arc<AtomicU32>,
+    levels: Arc<CaptureLevels>,
     rx: mpsc::Receiver<AudioFrame>,
+    buffer: Arc<StreamBuffer>,
     task: JoinHandle<()>,
     sample_rate: u32,
     channels: u16,
+    share_mode: ShareMode,
 }
 
 impl CaptureStream {
     pub async fn new(device: Device, volume: f32) -> Result<Self> {
+        Self::with_share_mode(device, volume, ShareMode::Shared).await
+    }
+
+    /// Opens the device in `share_mode`, falls back to shared access if the
+    /// device cannot be opened exclusively.
+    #[cfg(feature = "audio")]
+    pub async fn with_share_mode(
+        device: Device,
+        volume: f32,
+        share_mode: ShareMode,
+    ) -> Result<Self> {
+        open_with_fallback(share_mode, |share_mode| {
+            Self::open(device.clone(), volume, share_mode)
+        })
+        .await
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub async fn with_share_mode(
+        device: Device,
+        _volume: f32,
+        _share_mode: ShareMode,
+    ) -> Result<Self> {
+        match device {}
+    }
+
+    #[cfg(feature = "audio")]
+    async fn open(device: Device, volume: f32, share_mode: ShareMode) -> Result<Self> {
+        if !share_mode.is_supported() {
+            return Err(ExclusiveModeUnavailable.into());
+        }
         let (command_tx, mut command_rx) = mpsc::channel(1);
         let (tx, rx) = mpsc::channel(100);
-        let volume = Arc::new(AtomicU32::new(f32::to_bits(volume)));
+        let levels = Arc::new(CaptureLevels {
+            volume: AtomicU32::new(volume.to_bits()),
+            gain: AtomicU32::new(1.0f32.to_bits()),
+        });
         let config = device
             .default_input_config()
             .map_err(|e| anyhow!("Failed to get default input config: {}", e))?;
@@ -80,8 +156,10 @@ impl CaptureStream {
         }
 
         let stream_config: StreamConfig = config.into();
+        let buffer = Arc::new(StreamBuffer::new(sample_rate, channels));
         let task = {
-            let volume = volume.clone();
+            let levels = levels.clone();
+            let buffer = buffer.clone();
             spawn_blocking(move || {
                 let stream = match sample_format {
                     SampleFormat::I8 => Self::build_input_stream::<i8>(
@@ -89,80 +167,90 @@ impl CaptureStream {
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::I16 => Self::build_input_stream::<i16>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::I32 => Self::build_input_stream::<i32>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::I64 => Self::build_input_stream::<i64>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::U8 => Self::build_input_stream::<u8>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::U16 => Self::build_input_stream::<u16>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::U32 => Self::build_input_stream::<u32>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::U64 => Self::build_input_stream::<u64>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::F32 => Self::build_input_stream::<f32>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     SampleFormat::F64 => Self::build_input_stream::<f64>(
                         &device,
                         &stream_config,
                         sample_rate,
                         channels,
-                        volume,
+                        levels,
                         tx,
+                        buffer,
                     ),
                     _ => {
                         tracing::error!("Unsupported sample format: {:?}", sample_format);
@@ -205,16 +293,20 @@ impl CaptureStream {
         };
         Ok(CaptureStream {
             command_tx,
-            volume,
+            levels,
             rx,
+            buffer,
             task,
             sample_rate,
             channels,
+            share_mode,
         })
     }
 
     pub async fn recv(&mut self) -> Option<AudioFrame> {
-        self.rx.recv().await
+        let frame = self.rx.recv().await?;
+        self.buffer.pop_frame(frame.samples.len());
+        Some(frame)
     }
 
     pub async fn set_mute(&mut self, mute: bool) {
@@ -224,13 +316,24 @@ impl CaptureStream {
     }
 
     pub async fn set_volume(&mut self, volume: f32) {
-        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+        self.levels
+            .volume
+            .store(volume.to_bits(), Ordering::Relaxed);
     }
 
     pub fn volume(&self) -> f32 {
-        f32::from_bits(self.volume.load(Ordering::Relaxed))
+        f32::from_bits(self.levels.volume.load(Ordering::Relaxed))
+    }
+
+    /// Set the device gain, multiplied with the volume.
+    pub fn set_gain(&self, gain: f32) {
+        self.levels.gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn gain(&self) -> f32 {
+        f32::from_bits(self.levels.gain.load(Ordering::Relaxed))
     }
- 
+
     pub fn sample_rate(&self) -> u32 {
         self.sample_rate
     }
@@ -239,13 +342,34 @@ impl CaptureStream {
         self.channels
     }
 
+    /// Access the device was opened with, shared after a fallback.
+    pub fn share_mode(&self) -> ShareMode {
+        self.share_mode
+    }
+
+    /// Captured frames not yet received.
+    pub fn buffered_frames(&self) -> usize {
+        self.buffer.buffered_frames()
+    }
+
+    /// Duration of captured audio not yet received.
+    pub fn estimated_latency_ms(&self) -> f32 {
+        self.buffer.estimated_latency_ms()
+    }
+
+    pub fn buffer(&self) -> Arc<StreamBuffer> {
+        self.buffer.clone()
+    }
+
+    #[cfg(feature = "audio")]
     fn build_input_stream<T>(
         device: &Device,
         config: &StreamConfig,
         sample_rate: u32,
         channels: u16,
-        volume: Arc<AtomicU32>,
+        levels: Arc<CaptureLevels>,
         tx: mpsc::Sender<AudioFrame>,
+        stream_buffer: Arc<StreamBuffer>,
     ) -> Result<Stream>
     where
         T: SizedSample + FromSample<f32>,
@@ -260,7 +384,7 @@ impl CaptureStream {
             if count % 100 == 0 {
                 tracing::debug!("Audio input callback #{}, samples: {}", count, data.len());
             }
-            let vol = f32::from_bits(volume.load(Ordering::Relaxed));
+            let vol = levels.factor();
             let mut buffer = sample_buffer.lock().unwrap();
             // Convert samples to f32 and apply volume
             for sample in data {
@@ -280,6 +404,8 @@ impl CaptureStream {
                 };
 
                 // Try to send, but don't block the audio thread
+                let samples = frame.samples.len();
+                stream_buffer.push_frame(samples);
                 match tx.try_send(frame) {
                     Ok(_) => {
                         if count % 100 == 0 {
@@ -287,9 +413,11 @@ impl CaptureStream {
                         }
                     }
                     Err(mpsc::error::TrySendError::Full(_)) => {
+                        stream_buffer.pop_frame(samples);
                         tracing::warn!("Audio frame dropped: channel full");
                     }
                     Err(mpsc::error::TrySendError::Closed(_)) => {
+                        stream_buffer.pop_frame(samples);
                         tracing::warn!("Audio channel closed, stopping capture");
                     }
                 }
@@ -0,0 +1,30 @@
+/// Convert interleaved samples between channel counts.
+///
+/// Mono is averaged from all channels and duplicated into all channels,
+/// other layouts keep the first channels and repeat them if needed.
+pub fn convert_channels(samples: &[f32], from: u16, to: u16) -> Vec<f32> {
+    if from == to || from == 0 || to == 0 {
+        return samples.to_vec();
+    }
+    if to == 1 {
+        return downmix_to_mono(samples, from);
+    }
+    let (from, to) = (from as usize, to as usize);
+    let frames = samples.len() / from;
+    let mut output = Vec::with_capacity(frames * to);
+    for frame in samples.chunks_exact(from) {
+        for ch in 0..to {
+            output.push(frame[ch % from]);
+        }
+    }
+    output
+}
+
+/// Downmix multi-channel audio to mono by averaging channels
+fn downmix_to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels as usize;
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
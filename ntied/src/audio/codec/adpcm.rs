@@ -1,17 +1,30 @@
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 use super::traits::{AudioDecoder, AudioEncoder, CodecFactory, CodecType};
 use crate::audio::AudioConfig;
 
-/// IMA ADPCM encoder/decoder for simple audio compression
+/// Coding scheme of ADPCM frames, both sides of a call must use the same one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AdpcmVariant {
+    /// IMA ADPCM with a step index header
+    #[default]
+    Ima,
+    /// Microsoft ADPCM with predictor coefficients and an adaptive delta
+    Microsoft,
+}
+
+/// ADPCM encoder/decoder for simple audio compression
 /// Provides 4:1 compression ratio (4 bits per sample vs 16 bits)
 /// Configuration: 48kHz, 1-2 channels (configurable), 20ms frames (960 samples/channel)
 pub struct AdpcmEncoder {
     channels: u16,
+    variant: AdpcmVariant,
     predictor_l: i32,
     step_index_l: i32,
     predictor_r: i32,
     step_index_r: i32,
+    ms_state: [MsChannel; 2],
 }
 
 /// IMA ADPCM step table
@@ -26,8 +39,101 @@ const STEP_TABLE: [i32; 89] = [
 /// Index adjustment table
 const INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
 
+/// Microsoft ADPCM delta adaptation table
+const MS_ADAPTATION_TABLE: [i32; 16] = [
+    230, 230, 230, 230, 307, 409, 512, 614, 768, 614, 512, 409, 307, 230, 230, 230,
+];
+
+/// Microsoft ADPCM predictor coefficient pairs
+const MS_COEF1: [i32; 7] = [256, 512, 0, 192, 240, 460, 392];
+const MS_COEF2: [i32; 7] = [0, -256, 0, 64, 0, -208, -232];
+
+/// Microsoft ADPCM header size of a channel: coefficient index, delta and two samples
+const MS_HEADER_SIZE: usize = 7;
+
+/// Microsoft ADPCM state of a channel
+#[derive(Debug, Clone, Copy)]
+struct MsChannel {
+    coef_index: usize,
+    delta: i32,
+    sample1: i32,
+    sample2: i32,
+}
+
+impl Default for MsChannel {
+    fn default() -> Self {
+        Self {
+            coef_index: 0,
+            delta: 16,
+            sample1: 0,
+            sample2: 0,
+        }
+    }
+}
+
+impl MsChannel {
+    fn predict(&self) -> i32 {
+        (self.sample1 * MS_COEF1[self.coef_index] + self.sample2 * MS_COEF2[self.coef_index]) >> 8
+    }
+
+    fn decode_nibble(&mut self, nibble: u8) -> i16 {
+        let signed = if nibble & 8 != 0 {
+            nibble as i32 - 16
+        } else {
+            nibble as i32
+        };
+        let sample = (self.predict() + signed * self.delta).clamp(-32768, 32767);
+        self.sample2 = self.sample1;
+        self.sample1 = sample;
+        self.delta = ((MS_ADAPTATION_TABLE[nibble as usize] * self.delta) >> 8).clamp(16, 32767);
+        sample as i16
+    }
+
+    fn encode_sample(&mut self, sample: i16) -> u8 {
+        let diff = sample as i32 - self.predict();
+        // Round to the nearest multiple of delta
+        let half = self.delta / 2;
+        let quantized = if diff >= 0 {
+            (diff + half) / self.delta
+        } else {
+            (diff - half) / self.delta
+        };
+        let nibble = (quantized.clamp(-8, 7) & 0x0F) as u8;
+        self.decode_nibble(nibble);
+        nibble
+    }
+
+    fn write_header(&self, output: &mut Vec<u8>) {
+        output.push(self.coef_index as u8);
+        output.extend_from_slice(&(self.delta as i16).to_le_bytes());
+        output.extend_from_slice(&(self.sample1 as i16).to_le_bytes());
+        output.extend_from_slice(&(self.sample2 as i16).to_le_bytes());
+    }
+
+    fn read_header(data: &[u8]) -> Result<Self> {
+        let coef_index = data[0] as usize;
+        if coef_index >= MS_COEF1.len() {
+            return Err(anyhow::anyhow!(
+                "Invalid ADPCM coefficient index {}",
+                coef_index
+            ));
+        }
+        Ok(Self {
+            coef_index,
+            delta: (i16::from_le_bytes([data[1], data[2]]) as i32).max(16),
+            sample1: i16::from_le_bytes([data[3], data[4]]) as i32,
+            sample2: i16::from_le_bytes([data[5], data[6]]) as i32,
+        })
+    }
+}
+
+fn to_i16(sample: f32) -> i16 {
+    // Convert f32 to i16 with proper clamping to avoid overflow
+    (sample.clamp(-0.999, 0.999) * 32767.0) as i16
+}
+
 impl AdpcmEncoder {
-    pub fn new(channels: u16) -> Result<Self> {
+    pub fn new(channels: u16, variant: AdpcmVariant) -> Result<Self> {
         if channels == 0 || channels > 2 {
             return Err(anyhow::anyhow!(
                 "ADPCM only supports 1-2 channels, got {}",
@@ -36,13 +142,67 @@ impl AdpcmEncoder {
         }
         Ok(Self {
             channels,
+            variant,
             predictor_l: 0,
             step_index_l: 0,
             predictor_r: 0,
             step_index_r: 0,
+            ms_state: [MsChannel::default(); 2],
         })
     }
 
+    pub fn variant(&self) -> AdpcmVariant {
+        self.variant
+    }
+
+    /// Encodes a frame with the Microsoft variant, each channel uses the
+    /// predictor coefficients giving the smallest error on the frame.
+    fn encode_ms(&mut self, samples: &[f32]) -> Vec<u8> {
+        let channels = self.channels as usize;
+        let mut output = Vec::with_capacity(MS_HEADER_SIZE * channels + samples.len() / 2);
+        for channel in 0..channels {
+            let channel_samples: Vec<i16> = samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .map(|&s| to_i16(s))
+                .collect();
+            let state = &mut self.ms_state[channel];
+            state.coef_index = (0..MS_COEF1.len())
+                .min_by_key(|&coef_index| {
+                    let mut trial = MsChannel {
+                        coef_index,
+                        ..*state
+                    };
+                    channel_samples
+                        .iter()
+                        .map(|&sample| {
+                            trial.encode_sample(sample);
+                            let error = (sample as i32 - trial.sample1) as i64;
+                            error * error
+                        })
+                        .sum::<i64>()
+                })
+                .unwrap_or(0);
+            state.write_header(&mut output);
+        }
+
+        // High nibble first
+        let mut encoded_byte = 0u8;
+        for (i, &sample) in samples.iter().enumerate() {
+            let nibble = self.ms_state[i % channels].encode_sample(to_i16(sample));
+            if i % 2 == 0 {
+                encoded_byte = nibble << 4;
+            } else {
+                output.push(encoded_byte | nibble);
+            }
+        }
+        if !samples.len().is_multiple_of(2) {
+            output.push(encoded_byte);
+        }
+        output
+    }
+
     fn encode_sample(&mut self, sample: i16, channel: u16) -> u8 {
         let (predictor, step_index) = if channel == 0 {
             (&mut self.predictor_l, &mut self.step_index_l)
@@ -107,6 +267,9 @@ impl AdpcmEncoder {
 
 impl AudioEncoder for AdpcmEncoder {
     fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>> {
+        if self.variant == AdpcmVariant::Microsoft {
+            return Ok(self.encode_ms(samples));
+        }
         // Expected: 960 samples/channel (20ms at 48kHz)
         // For stereo: 1920 samples total (interleaved L, R, L, R, ...)
         // Output size: 4 bits per sample = 0.5 bytes per sample
@@ -137,9 +300,7 @@ impl AudioEncoder for AdpcmEncoder {
         let mut nibble_count = 0;
 
         for (i, &sample) in samples.iter().enumerate() {
-            // Convert f32 to i16 with proper clamping to avoid overflow
-            let clamped = sample.clamp(-0.999, 0.999);
-            let sample_i16 = (clamped * 32767.0) as i16;
+            let sample_i16 = to_i16(sample);
 
             // Determine channel (for stereo: even indices = left, odd = right)
             let channel = if self.channels == 2 {
@@ -180,6 +341,7 @@ impl AudioEncoder for AdpcmEncoder {
         self.step_index_l = 0;
         self.predictor_r = 0;
         self.step_index_r = 0;
+        self.ms_state = [MsChannel::default(); 2];
         Ok(())
     }
 
@@ -192,9 +354,10 @@ impl AudioEncoder for AdpcmEncoder {
     }
 }
 
-/// IMA ADPCM decoder
+/// ADPCM decoder
 pub struct AdpcmDecoder {
     channels: u16,
+    variant: AdpcmVariant,
     predictor_l: i32,
     step_index_l: i32,
     predictor_r: i32,
@@ -204,7 +367,7 @@ pub struct AdpcmDecoder {
 }
 
 impl AdpcmDecoder {
-    pub fn new(channels: u16) -> Result<Self> {
+    pub fn new(channels: u16, variant: AdpcmVariant) -> Result<Self> {
         if channels == 0 || channels > 2 {
             return Err(anyhow::anyhow!(
                 "ADPCM only supports 1-2 channels, got {}",
@@ -215,6 +378,7 @@ impl AdpcmDecoder {
         let frame_size = 960 * channels as usize;
         Ok(Self {
             channels,
+            variant,
             predictor_l: 0,
             step_index_l: 0,
             predictor_r: 0,
@@ -224,6 +388,31 @@ impl AdpcmDecoder {
         })
     }
 
+    pub fn variant(&self) -> AdpcmVariant {
+        self.variant
+    }
+
+    fn decode_ms(&self, data: &[u8]) -> Result<Vec<f32>> {
+        let channels = self.channels as usize;
+        let header_size = MS_HEADER_SIZE * channels;
+        if data.len() < header_size {
+            return Err(anyhow::anyhow!("ADPCM data too short"));
+        }
+        let mut state = [MsChannel::default(); 2];
+        for (channel, state) in state.iter_mut().take(channels).enumerate() {
+            *state = MsChannel::read_header(&data[channel * MS_HEADER_SIZE..])?;
+        }
+        let mut samples = Vec::with_capacity((data.len() - header_size) * 2);
+        for byte in &data[header_size..] {
+            // High nibble first
+            for nibble in [byte >> 4, byte & 0x0F] {
+                let sample = state[samples.len() % channels].decode_nibble(nibble);
+                samples.push((sample as f32 / 32767.0).clamp(-1.0, 1.0));
+            }
+        }
+        Ok(samples)
+    }
+
     fn decode_nibble(&mut self, nibble: u8, channel: u16) -> i16 {
         let (predictor, step_index) = if channel == 0 {
             (&mut self.predictor_l, &mut self.step_index_l)
@@ -263,6 +452,12 @@ impl AdpcmDecoder {
 
 impl AudioDecoder for AdpcmDecoder {
     fn decode(&mut self, data: &[u8]) -> Result<Vec<f32>> {
+        if self.variant == AdpcmVariant::Microsoft {
+            let samples = self.decode_ms(data)?;
+            self.last_frame = samples.clone();
+            self.plc_count = 0;
+            return Ok(samples);
+        }
         let header_size = if self.channels == 2 { 8 } else { 4 };
         if data.len() < header_size {
             return Err(anyhow::anyhow!("ADPCM data too short"));
@@ -383,12 +578,18 @@ impl CodecFactory for AdpcmCodecFactory {
         true
     }
 
-    fn create_encoder(&self, _params: super::traits::CodecParams) -> Result<Box<dyn AudioEncoder>> {
-        Ok(Box::new(AdpcmEncoder::new(self.channels)?))
+    fn create_encoder(&self, params: super::traits::CodecParams) -> Result<Box<dyn AudioEncoder>> {
+        Ok(Box::new(AdpcmEncoder::new(
+            self.channels,
+            params.adpcm_variant,
+        )?))
     }
 
-    fn create_decoder(&self, _params: super::traits::CodecParams) -> Result<Box<dyn AudioDecoder>> {
-        Ok(Box::new(AdpcmDecoder::new(self.channels)?))
+    fn create_decoder(&self, params: super::traits::CodecParams) -> Result<Box<dyn AudioDecoder>> {
+        Ok(Box::new(AdpcmDecoder::new(
+            self.channels,
+            params.adpcm_variant,
+        )?))
     }
 }
 
@@ -398,8 +599,8 @@ mod tests {
 
     #[test]
     fn test_adpcm_encode_decode() {
-        let mut encoder = AdpcmEncoder::new(1).unwrap();
-        let mut decoder = AdpcmDecoder::new(1).unwrap();
+        let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+        let mut decoder = AdpcmDecoder::new(1, AdpcmVariant::Ima).unwrap();
 
         // Create test samples (960 samples for 20ms at 48kHz)
         let mut samples = Vec::new();
@@ -430,11 +631,11 @@ mod tests {
 
     #[test]
     fn test_adpcm_plc() {
-        let mut decoder = AdpcmDecoder::new(1).unwrap();
+        let mut decoder = AdpcmDecoder::new(1, AdpcmVariant::Ima).unwrap();
 
         // First decode a frame (960 samples for 20ms at 48kHz)
         let samples = vec![0.5; 960];
-        let mut encoder = AdpcmEncoder::new(1).unwrap();
+        let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
         let encoded = encoder.encode(&samples).unwrap();
         decoder.decode(&encoded).unwrap();
 
@@ -450,7 +651,7 @@ mod tests {
 
     #[test]
     fn test_adpcm_compression_ratio() {
-        let mut encoder = AdpcmEncoder::new(1).unwrap();
+        let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
 
         // ADPCM should provide 4:1 compression (4 bits per sample vs 16 bits)
         let samples = vec![0.5f32; 960];
@@ -472,8 +673,8 @@ mod tests {
 
     #[test]
     fn test_adpcm_step_adaptation() {
-        let mut encoder = AdpcmEncoder::new(1).unwrap();
-        let mut decoder = AdpcmDecoder::new(1).unwrap();
+        let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+        let mut decoder = AdpcmDecoder::new(1, AdpcmVariant::Ima).unwrap();
 
         // Test with increasing amplitude signal (960 samples for 20ms at 48kHz)
         let mut samples = Vec::new();
@@ -518,8 +719,8 @@ mod tests {
 
     #[test]
     fn test_adpcm_predictor_stability() {
-        let mut encoder = AdpcmEncoder::new(1).unwrap();
-        let mut decoder = AdpcmDecoder::new(1).unwrap();
+        let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+        let mut decoder = AdpcmDecoder::new(1, AdpcmVariant::Ima).unwrap();
 
         // Test with DC signal (constant value) (960 samples for 20ms at 48kHz)
         let samples = vec![0.3f32; 960];
@@ -550,8 +751,8 @@ mod tests {
 
     #[test]
     fn test_adpcm_pitch_detection_plc() {
-        let mut encoder = AdpcmEncoder::new(1).unwrap();
-        let mut decoder = AdpcmDecoder::new(1).unwrap();
+        let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+        let mut decoder = AdpcmDecoder::new(1, AdpcmVariant::Ima).unwrap();
 
         // Create a periodic signal (simulating voice pitch) (960 samples for 20ms at 48kHz)
         let pitch_period = 48usize; // ~1kHz at 48kHz sample rate
@@ -579,7 +780,7 @@ mod tests {
 
     #[test]
     fn test_adpcm_index_bounds() {
-        let mut encoder = AdpcmEncoder::new(1).unwrap();
+        let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
 
         // Test with rapidly changing signal that might stress index adaptation (320 samples for 20ms at 16kHz)
         let mut samples = Vec::new();
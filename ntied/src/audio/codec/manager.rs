@@ -1,9 +1,8 @@
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
-use anyhow::Result;
-use tokio::sync::RwLock;
+use anyhow::{Result, anyhow};
 
-use super::{CodecCapabilities, CodecType, NegotiatedCodec};
+use super::{CodecCapabilities, CodecParams, CodecType, NegotiatedCodec};
 
 /// Simplified codec manager for backward compatibility
 /// In new architecture, Encoder/Decoder handle codec management directly
@@ -22,24 +21,70 @@ impl CodecManager {
 
     /// Get codec capabilities
     pub async fn capabilities(&self) -> CodecCapabilities {
-        self.capabilities.read().await.clone()
+        self.capabilities.read().unwrap().clone()
     }
 
-    /// Create a codec offer (simplified - just returns default codec)
+    /// Codec used to encode outgoing audio, the first one of the capabilities
+    pub fn preferred_codec(&self) -> CodecType {
+        self.capabilities
+            .read()
+            .unwrap()
+            .codecs
+            .first()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Moves the codec to the front of the capabilities, returns false if
+    /// the codec is not available in this build
+    pub fn set_preferred_codec(&self, codec: CodecType) -> bool {
+        let mut capabilities = self.capabilities.write().unwrap();
+        let Some(pos) = capabilities.codecs.iter().position(|v| *v == codec) else {
+            return false;
+        };
+        capabilities.codecs[..=pos].rotate_right(1);
+        true
+    }
+
+    /// Limits the codecs to the given ones in preference order, returns false
+    /// if the list is empty or has a codec not available in this build
+    pub fn set_codecs(&self, codecs: &[CodecType]) -> bool {
+        let available = CodecType::available();
+        if codecs.is_empty() || codecs.iter().any(|v| !available.contains(v)) {
+            return false;
+        }
+        self.capabilities.write().unwrap().codecs = codecs.to_vec();
+        true
+    }
+
+    /// Create a codec offer with the default parameters of the preferred codec
     pub fn create_offer(&self) -> NegotiatedCodec {
+        self.create_offer_with_params(CodecParams::preset(self.preferred_codec()))
+    }
+
+    /// Create an offer of the preferred codec with the given parameters
+    pub fn create_offer_with_params(&self, params: CodecParams) -> NegotiatedCodec {
         NegotiatedCodec {
-            codec: CodecType::ADPCM,
-            params: super::CodecParams::adpcm(),
+            codec: self.preferred_codec(),
+            params,
             is_offerer: true,
         }
     }
 
-    /// Create a codec answer based on peer capabilities
-    pub fn create_answer(&self, _peer_caps: &CodecCapabilities) -> Result<NegotiatedCodec> {
-        // Simplified: just accept ADPCM
+    /// Create a codec answer with the most preferred codec the peer supports
+    pub fn create_answer(&self, peer_caps: &CodecCapabilities) -> Result<NegotiatedCodec> {
+        let codec = self
+            .capabilities
+            .read()
+            .unwrap()
+            .codecs
+            .iter()
+            .copied()
+            .find(|v| peer_caps.codecs.contains(v))
+            .ok_or_else(|| anyhow!("No common codec found"))?;
         Ok(NegotiatedCodec {
-            codec: CodecType::ADPCM,
-            params: super::CodecParams::adpcm(),
+            codec,
+            params: CodecParams::preset(codec),
             is_offerer: false,
         })
     }
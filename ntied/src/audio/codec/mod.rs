@@ -2,6 +2,7 @@ mod adpcm;
 mod manager;
 mod negotiation;
 mod raw;
+mod timing;
 mod traits;
 
 use anyhow::Result;
@@ -10,38 +11,29 @@ pub use adpcm::*;
 pub use manager::*;
 pub use negotiation::*;
 pub use raw::*;
+pub use timing::*;
 pub use traits::*;
 
-/// Create an encoder for the given codec type
-pub fn create_encoder(codec: CodecType, channels: u16) -> Result<Box<dyn AudioEncoder>> {
-    match codec {
-        CodecType::ADPCM => {
-            if channels == 0 || channels > 2 {
-                return Err(anyhow::anyhow!(
-                    "ADPCM supports 1-2 channels, got {}",
-                    channels
-                ));
-            }
-            Ok(Box::new(AdpcmEncoder::new(channels)?))
-        }
-        CodecType::Raw => Ok(Box::new(RawEncoder::new(channels)?)),
-    }
+/// Create an encoder for the given codec type and parameters, its
+/// stats include the average encoding time
+pub fn create_encoder(codec: CodecType, params: &CodecParams) -> Result<Box<dyn AudioEncoder>> {
+    params.validate(codec)?;
+    let encoder: Box<dyn AudioEncoder> = match codec {
+        CodecType::ADPCM => Box::new(AdpcmEncoder::new(params.channels, params.adpcm_variant)?),
+        CodecType::Raw => Box::new(RawEncoder::new(params.channels)?),
+    };
+    Ok(Box::new(TimedEncoder::new(encoder)))
 }
 
-/// Create a decoder for the given codec type
-pub fn create_decoder(codec: CodecType, channels: u16) -> Result<Box<dyn AudioDecoder>> {
-    match codec {
-        CodecType::ADPCM => {
-            if channels == 0 || channels > 2 {
-                return Err(anyhow::anyhow!(
-                    "ADPCM supports 1-2 channels, got {}",
-                    channels
-                ));
-            }
-            Ok(Box::new(AdpcmDecoder::new(channels)?))
-        }
-        CodecType::Raw => Ok(Box::new(RawDecoder::new(channels)?)),
-    }
+/// Create a decoder for the given codec type and parameters, its
+/// stats include the average decoding time
+pub fn create_decoder(codec: CodecType, params: &CodecParams) -> Result<Box<dyn AudioDecoder>> {
+    params.validate(codec)?;
+    let decoder: Box<dyn AudioDecoder> = match codec {
+        CodecType::ADPCM => Box::new(AdpcmDecoder::new(params.channels, params.adpcm_variant)?),
+        CodecType::Raw => Box::new(RawDecoder::new(params.channels)?),
+    };
+    Ok(Box::new(TimedDecoder::new(decoder)))
 }
 
 #[cfg(test)]
@@ -1,6 +1,9 @@
+use std::collections::HashMap;
+
 use anyhow::{Result, anyhow};
 
-use super::traits::{CodecCapabilities, CodecParams, CodecType, NegotiatedCodec};
+use super::adpcm::AdpcmVariant;
+use super::traits::{CodecCapabilities, CodecParams, CodecStats, CodecType, NegotiatedCodec};
 
 /// Negotiates codec selection between two peers
 pub struct CodecNegotiator {
@@ -89,6 +92,7 @@ impl CodecNegotiator {
             dtx,
             expected_packet_loss: 5,
             complexity: 10,
+            adpcm_variant: AdpcmVariant::default(),
         };
 
         Ok(NegotiatedCodec {
@@ -213,6 +217,7 @@ impl CodecNegotiator {
                 dtx: false,
                 expected_packet_loss: 0,
                 complexity: 0,
+                adpcm_variant: AdpcmVariant::default(),
             },
             _ => CodecParams::default(),
         }
@@ -235,6 +240,8 @@ pub struct AdaptiveCodecManager {
     negotiator: CodecNegotiator,
     current_codec: Option<NegotiatedCodec>,
     network_quality: NetworkQuality,
+    /// Average encode and decode time of a frame in microseconds
+    codec_costs: HashMap<CodecType, (f64, f64)>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -261,12 +268,41 @@ impl Default for NetworkQuality {
 }
 
 impl AdaptiveCodecManager {
+    /// Share of a 20ms frame a codec may spend encoding and decoding it
+    const MAX_CODEC_LOAD: f64 = 0.25;
+    const FRAME_TIME_US: f64 = 20_000.0;
+
     pub fn new(negotiator: CodecNegotiator) -> Self {
         Self {
             negotiator,
             current_codec: None,
             network_quality: NetworkQuality::default(),
+            codec_costs: HashMap::new(),
+        }
+    }
+
+    /// Update the measured CPU cost of the codec from its encoder or decoder stats
+    pub fn update_codec_stats(&mut self, codec: CodecType, stats: &CodecStats) {
+        let (encode, decode) = self.codec_costs.entry(codec).or_default();
+        if stats.frames_encoded > 0 {
+            *encode = stats.avg_encode_time_us;
         }
+        if stats.frames_decoded > 0 {
+            *decode = stats.avg_decode_time_us;
+        }
+    }
+
+    /// Average time in microseconds to encode and decode a frame, if measured
+    pub fn codec_cost(&self, codec: CodecType) -> Option<f64> {
+        self.codec_costs
+            .get(&codec)
+            .map(|(encode, decode)| encode + decode)
+    }
+
+    /// Check if the codec takes too much of the frame time on this device
+    pub fn is_too_expensive(&self, codec: CodecType) -> bool {
+        self.codec_cost(codec)
+            .is_some_and(|cost| cost > Self::FRAME_TIME_US * Self::MAX_CODEC_LOAD)
     }
 
     /// Update network quality metrics
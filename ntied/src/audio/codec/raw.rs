@@ -94,7 +94,6 @@ impl AudioDecoder for RawDecoder {
         }
         Ok(concealed)
     }
-    
 
     fn reset(&mut self) -> Result<()> {
         let frame_size = 960 * self.channels as usize;
@@ -0,0 +1,117 @@
+use std::time::Instant;
+
+use anyhow::Result;
+
+use super::traits::{AudioDecoder, AudioEncoder, CodecStats, CodecType};
+use crate::audio::AudioConfig;
+
+/// Running mean of `value` over `count` samples, `count` includes it.
+fn update_average(average: f64, count: u64, value: f64) -> f64 {
+    average + (value - average) / count as f64
+}
+
+/// Encoder measuring the time spent in the wrapped codec.
+pub struct TimedEncoder {
+    inner: Box<dyn AudioEncoder>,
+    stats: CodecStats,
+}
+
+impl TimedEncoder {
+    pub fn new(inner: Box<dyn AudioEncoder>) -> Self {
+        Self {
+            inner,
+            stats: CodecStats::default(),
+        }
+    }
+}
+
+impl AudioEncoder for TimedEncoder {
+    fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>> {
+        let start = Instant::now();
+        let data = self.inner.encode(samples)?;
+        let elapsed = start.elapsed().as_secs_f64() * 1e6;
+        self.stats.frames_encoded += 1;
+        self.stats.bytes_encoded += data.len() as u64;
+        self.stats.avg_encode_time_us = update_average(
+            self.stats.avg_encode_time_us,
+            self.stats.frames_encoded,
+            elapsed,
+        );
+        Ok(data)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.stats = CodecStats::default();
+        self.inner.reset()
+    }
+
+    fn codec_type(&self) -> CodecType {
+        self.inner.codec_type()
+    }
+
+    fn codec_config(&self) -> AudioConfig {
+        self.inner.codec_config()
+    }
+
+    fn stats(&self) -> CodecStats {
+        self.stats.clone()
+    }
+}
+
+/// Decoder measuring the time spent in the wrapped codec.
+pub struct TimedDecoder {
+    inner: Box<dyn AudioDecoder>,
+    stats: CodecStats,
+}
+
+impl TimedDecoder {
+    pub fn new(inner: Box<dyn AudioDecoder>) -> Self {
+        Self {
+            inner,
+            stats: CodecStats::default(),
+        }
+    }
+}
+
+impl AudioDecoder for TimedDecoder {
+    fn decode(&mut self, data: &[u8]) -> Result<Vec<f32>> {
+        let start = Instant::now();
+        let samples = match self.inner.decode(data) {
+            Ok(samples) => samples,
+            Err(err) => {
+                self.stats.decode_errors += 1;
+                return Err(err);
+            }
+        };
+        let elapsed = start.elapsed().as_secs_f64() * 1e6;
+        self.stats.frames_decoded += 1;
+        self.stats.bytes_decoded += data.len() as u64;
+        self.stats.avg_decode_time_us = update_average(
+            self.stats.avg_decode_time_us,
+            self.stats.frames_decoded,
+            elapsed,
+        );
+        Ok(samples)
+    }
+
+    fn conceal_packet_loss(&mut self) -> Result<Vec<f32>> {
+        self.inner.conceal_packet_loss()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.stats = CodecStats::default();
+        self.inner.reset()
+    }
+
+    fn codec_type(&self) -> CodecType {
+        self.inner.codec_type()
+    }
+
+    fn codec_config(&self) -> AudioConfig {
+        self.inner.codec_config()
+    }
+
+    fn stats(&self) -> CodecStats {
+        self.stats.clone()
+    }
+}
@@ -1,6 +1,7 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
 
+use super::adpcm::AdpcmVariant;
 use crate::audio::AudioConfig;
 
 /// Supported audio codec types
@@ -13,6 +14,21 @@ pub enum CodecType {
 }
 
 impl CodecType {
+    /// Codecs compiled into this build, by priority.
+    pub fn available() -> Vec<CodecType> {
+        let mut codecs = vec![CodecType::ADPCM, CodecType::Raw];
+        codecs.sort_by_key(|v| std::cmp::Reverse(v.priority()));
+        codecs
+    }
+
+    /// Human readable name of the codec.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CodecType::ADPCM => "ADPCM",
+            CodecType::Raw => "Raw PCM",
+        }
+    }
+
     /// Get the priority of this codec (higher is better)
     pub fn priority(&self) -> u8 {
         match self {
@@ -40,6 +56,19 @@ impl CodecType {
         // matches!(self, CodecType::Opus)
         false
     }
+
+    /// Get the sample rate in Hz this codec operates at
+    pub fn sample_rate(&self) -> u32 {
+        48000
+    }
+
+    /// Get the maximum bitrate in bps of a single channel
+    pub fn max_bitrate(&self) -> u32 {
+        match self {
+            CodecType::ADPCM => 192000, // 4 bits per sample
+            CodecType::Raw => 768000,   // 16 bits per sample
+        }
+    }
 }
 
 impl Default for CodecType {
@@ -49,7 +78,7 @@ impl Default for CodecType {
 }
 
 /// Parameters for configuring an audio codec
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CodecParams {
     /// Sample rate in Hz
     pub sample_rate: u32,
@@ -65,6 +94,8 @@ pub struct CodecParams {
     pub expected_packet_loss: u8,
     /// Complexity/quality trade-off (0-10, 10 = best quality)
     pub complexity: u8,
+    /// Coding scheme of the ADPCM codec
+    pub adpcm_variant: AdpcmVariant,
 }
 
 impl Default for CodecParams {
@@ -77,21 +108,96 @@ impl Default for CodecParams {
             dtx: true,
             expected_packet_loss: 5,
             complexity: 10,
+            adpcm_variant: AdpcmVariant::default(),
         }
     }
 }
 
 impl CodecParams {
+    /// Start building parameters for the codec from its preset
+    pub fn builder(codec: CodecType) -> CodecParamsBuilder {
+        let params = Self::preset(codec);
+        CodecParamsBuilder { codec, params }
+    }
+
+    /// Default parameters of the codec
+    pub fn preset(codec: CodecType) -> Self {
+        match codec {
+            CodecType::ADPCM => Self::adpcm(),
+            CodecType::Raw => Self::raw_mono(),
+        }
+    }
+
+    /// Check that the codec supports these parameters
+    pub fn validate(&self, codec: CodecType) -> Result<()> {
+        if self.channels == 0 || self.channels > 2 {
+            bail!("{:?} supports 1-2 channels, got {}", codec, self.channels);
+        }
+        if self.sample_rate != codec.sample_rate() {
+            bail!(
+                "{:?} supports only {}Hz, got {}Hz",
+                codec,
+                codec.sample_rate(),
+                self.sample_rate
+            );
+        }
+        let max_bitrate = codec.max_bitrate() * self.channels as u32;
+        if self.bitrate > max_bitrate {
+            bail!(
+                "{:?} supports up to {}bps for {} channels, got {}bps",
+                codec,
+                max_bitrate,
+                self.channels,
+                self.bitrate
+            );
+        }
+        if self.fec && !codec.supports_fec() {
+            bail!("{:?} does not support FEC", codec);
+        }
+        if self.dtx && !codec.supports_dtx() {
+            bail!("{:?} does not support DTX", codec);
+        }
+        if self.expected_packet_loss > 100 {
+            bail!(
+                "Packet loss hint must be 0-100, got {}",
+                self.expected_packet_loss
+            );
+        }
+        if self.adpcm_variant != AdpcmVariant::default() && codec != CodecType::ADPCM {
+            bail!("{:?} does not support ADPCM variants", codec);
+        }
+        if self.complexity > 10 {
+            bail!("Complexity must be 0-10, got {}", self.complexity);
+        }
+        Ok(())
+    }
+
     /// Create parameters for ADPCM codec (fixed)
     pub fn adpcm() -> Self {
         Self {
-            sample_rate: 16000,
+            sample_rate: 48000,
             channels: 1,
             bitrate: 32000,
             fec: false,
             dtx: false,
             expected_packet_loss: 5,
             complexity: 10,
+            adpcm_variant: AdpcmVariant::default(),
+        }
+    }
+
+    /// Create parameters tuned for speech: mono ADPCM
+    pub fn voice() -> Self {
+        Self::adpcm()
+    }
+
+    /// Create parameters tuned for music and system audio: stereo ADPCM,
+    /// mono sources are still sent as mono
+    pub fn music() -> Self {
+        Self {
+            channels: 2,
+            bitrate: 64000,
+            ..Self::adpcm()
         }
     }
 
@@ -105,6 +211,7 @@ impl CodecParams {
             dtx: false,
             expected_packet_loss: 0,
             complexity: 10,
+            adpcm_variant: AdpcmVariant::default(),
         }
     }
 
@@ -118,10 +225,66 @@ impl CodecParams {
             dtx: false,
             expected_packet_loss: 0,
             complexity: 10,
+            adpcm_variant: AdpcmVariant::default(),
         }
     }
 }
 
+/// Builder of [`CodecParams`] validated against a codec
+#[derive(Debug, Clone)]
+pub struct CodecParamsBuilder {
+    codec: CodecType,
+    params: CodecParams,
+}
+
+impl CodecParamsBuilder {
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.params.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn channels(mut self, channels: u16) -> Self {
+        self.params.channels = channels;
+        self
+    }
+
+    pub fn bitrate(mut self, bitrate: u32) -> Self {
+        self.params.bitrate = bitrate;
+        self
+    }
+
+    pub fn fec(mut self, fec: bool) -> Self {
+        self.params.fec = fec;
+        self
+    }
+
+    pub fn dtx(mut self, dtx: bool) -> Self {
+        self.params.dtx = dtx;
+        self
+    }
+
+    pub fn expected_packet_loss(mut self, expected_packet_loss: u8) -> Self {
+        self.params.expected_packet_loss = expected_packet_loss;
+        self
+    }
+
+    pub fn complexity(mut self, complexity: u8) -> Self {
+        self.params.complexity = complexity;
+        self
+    }
+
+    pub fn adpcm_variant(mut self, adpcm_variant: AdpcmVariant) -> Self {
+        self.params.adpcm_variant = adpcm_variant;
+        self
+    }
+
+    /// Validate and return the parameters
+    pub fn build(self) -> Result<CodecParams> {
+        self.params.validate(self.codec)?;
+        Ok(self.params)
+    }
+}
+
 /// Statistics about codec performance
 #[derive(Debug, Clone, Default)]
 pub struct CodecStats {
@@ -167,6 +330,11 @@ pub trait AudioEncoder: Send + Sync {
 
     /// Get codec audio configuration (sample rate and channels this codec expects)
     fn codec_config(&self) -> AudioConfig;
+
+    /// Get statistics collected since creation or the last reset
+    fn stats(&self) -> CodecStats {
+        CodecStats::default()
+    }
 }
 
 /// Trait for audio decoders
@@ -195,6 +363,11 @@ pub trait AudioDecoder: Send + Sync {
 
     /// Get codec audio configuration (sample rate and channels this codec produces)
     fn codec_config(&self) -> AudioConfig;
+
+    /// Get statistics collected since creation or the last reset
+    fn stats(&self) -> CodecStats {
+        CodecStats::default()
+    }
 }
 
 /// Factory for creating codec instances
@@ -243,7 +416,7 @@ pub struct CodecCapabilities {
 impl Default for CodecCapabilities {
     fn default() -> Self {
         Self {
-            codecs: vec![CodecType::ADPCM, CodecType::Raw],
+            codecs: CodecType::available(),
             sample_rates: vec![48000, 44100, 32000, 24000, 16000, 8000],
             max_channels: 2,
             max_bitrate: 510000,
@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Kind of audio content carried by a packet, a hint for the receiver.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentType {
+    #[default]
+    Speech,
+    Music,
+}
+
+/// Tells speech from music by the spectrum and loudness of recent frames.
+///
+/// Music is tonal, its spectrum has a few strong peaks, and keeps its level,
+/// speech alternates syllables with pauses. Until a full window is seen the
+/// content is reported as speech.
+pub struct ContentClassifier {
+    window: usize,
+    frames: VecDeque<FrameFeatures>,
+    current: ContentType,
+}
+
+struct FrameFeatures {
+    energy: f32,
+    // Spectral flatness, None for silent frames
+    flatness: Option<f32>,
+}
+
+impl ContentClassifier {
+    /// One second of 20 ms frames.
+    pub const DEFAULT_WINDOW: usize = 50;
+    const FFT_SIZE: usize = 512;
+    const SILENCE_ENERGY: f32 = 1e-6;
+    /// Tonal frames have flatness well below noise-like ones.
+    const MAX_MUSIC_FLATNESS: f32 = 0.1;
+    /// Relative spread of frame energies tolerated in music.
+    const MAX_MUSIC_MODULATION: f32 = 0.5;
+
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            frames: VecDeque::new(),
+            current: ContentType::Speech,
+        }
+    }
+
+    /// Content of the window ending with the given interleaved frame.
+    pub fn classify(&mut self, samples: &[f32], channels: u16) -> ContentType {
+        let mono = downmix(samples, channels);
+        let energy = mono.iter().map(|s| s * s).sum::<f32>() / mono.len().max(1) as f32;
+        let flatness = (energy > Self::SILENCE_ENERGY).then(|| spectral_flatness(&mono));
+        if self.frames.len() == self.window {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(FrameFeatures { energy, flatness });
+        if self.frames.len() == self.window {
+            self.current = self.decide();
+        }
+        self.current
+    }
+
+    pub fn current(&self) -> ContentType {
+        self.current
+    }
+
+    fn decide(&self) -> ContentType {
+        let tonal: Vec<f32> = self.frames.iter().filter_map(|f| f.flatness).collect();
+        // Mostly silent windows are left to the voice path
+        if tonal.len() * 2 < self.frames.len() {
+            return ContentType::Speech;
+        }
+        let flatness = tonal.iter().sum::<f32>() / tonal.len() as f32;
+        let count = self.frames.len() as f32;
+        let mean = self.frames.iter().map(|f| f.energy).sum::<f32>() / count;
+        let variance = self
+            .frames
+            .iter()
+            .map(|f| (f.energy - mean).powi(2))
+            .sum::<f32>()
+            / count;
+        let modulation = variance.sqrt() / mean;
+        if flatness < Self::MAX_MUSIC_FLATNESS && modulation < Self::MAX_MUSIC_MODULATION {
+            ContentType::Music
+        } else {
+            ContentType::Speech
+        }
+    }
+}
+
+impl Default for ContentClassifier {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+fn downmix(samples: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// Ratio of geometric to arithmetic mean of the power spectrum, close to 1
+/// for noise and close to 0 for a few pure tones.
+fn spectral_flatness(samples: &[f32]) -> f32 {
+    let size = samples.len().min(ContentClassifier::FFT_SIZE);
+    if size < 2 {
+        return 1.0;
+    }
+    let windowed: Vec<f32> = samples[..size]
+        .iter()
+        .enumerate()
+        .map(|(i, s)| s * (0.5 - 0.5 * (2.0 * PI * i as f32 / (size - 1) as f32).cos()))
+        .collect();
+    let (cos, sin): (Vec<f32>, Vec<f32>) = (0..size)
+        .map(|i| (2.0 * PI * i as f32 / size as f32).sin_cos())
+        .map(|(sin, cos)| (cos, sin))
+        .unzip();
+    let bins = size / 2;
+    let mut log_sum = 0.0f32;
+    let mut sum = 0.0f32;
+    for k in 1..=bins {
+        let (mut re, mut im) = (0.0f32, 0.0f32);
+        for (n, s) in windowed.iter().enumerate() {
+            let i = k * n % size;
+            re += s * cos[i];
+            im -= s * sin[i];
+        }
+        let power = re * re + im * im + 1e-12;
+        log_sum += power.ln();
+        sum += power;
+    }
+    (log_sum / bins as f32).exp() / (sum / bins as f32)
+}
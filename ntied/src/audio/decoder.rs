@@ -1,20 +1,140 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Instant;
+use std::time::Duration;
 
 use tokio::sync::{Mutex as TokioMutex, mpsc};
+use tokio::time::Instant;
 
 use crate::packet::AudioDataPacket;
 
-use super::codec::{CodecType, create_decoder};
-use super::{AudioConfig, AudioFrame, Resampler};
+use super::codec::{CodecParams, CodecType, create_decoder};
+use super::{AudioConfig, AudioFrame, ContentType, Resampler, convert_channels};
 
 /// Wrapper for buffered packet data in jitter buffer
 struct BufferedPacket {
     data: Vec<u8>,
+    content: ContentType,
+    // Arrival time
     timestamp: Instant,
 }
 
+/// Order in which the [`Decoder`] plays buffered packets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlayoutOrder {
+    /// By sequence number, a pause of the sender is played as a loss.
+    #[default]
+    Sequence,
+    /// By capture timestamp, frames keep the timing intended by the sender
+    /// including its pauses.
+    Timestamp,
+}
+
+/// Settings of the [`Decoder`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecoderConfig {
+    /// Concealed frames in a row after which the decoder outputs silence.
+    pub max_consecutive_plc: u32,
+    /// Share of the level removed from each concealed frame in a row,
+    /// on top of the codec's own concealment. Music fades at half the rate,
+    /// repeating a sustained tone is less audible than repeating a syllable.
+    pub plc_fade_rate: f32,
+    /// Packets buffered before the first frame is played, trades a short
+    /// delay at call start for a smooth onset. Playback starts anyway when
+    /// the first packet waited twice this long. Zero plays at once.
+    pub prebuffer_frames: u32,
+    pub playout_order: PlayoutOrder,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        Self {
+            // 500 ms of concealment
+            max_consecutive_plc: 25,
+            plc_fade_rate: 0.1,
+            // 60 ms
+            prebuffer_frames: 3,
+            playout_order: PlayoutOrder::default(),
+        }
+    }
+}
+
+/// Fades concealed frames of one outage and mutes long outages.
+struct PlcFade {
+    config: DecoderConfig,
+    consecutive: u32,
+    // Content of the last decoded packet
+    content: ContentType,
+}
+
+impl PlcFade {
+    fn new(config: DecoderConfig) -> Self {
+        Self {
+            config,
+            consecutive: 0,
+            content: ContentType::default(),
+        }
+    }
+
+    /// A frame was decoded, the outage is over.
+    fn reset(&mut self, content: ContentType) {
+        self.consecutive = 0;
+        self.content = content;
+    }
+
+    fn apply(&mut self, samples: &mut [f32]) {
+        self.consecutive = self.consecutive.saturating_add(1);
+        if self.consecutive > self.config.max_consecutive_plc {
+            samples.fill(0.0);
+            return;
+        }
+        let fade_rate = match self.content {
+            ContentType::Speech => self.config.plc_fade_rate,
+            ContentType::Music => self.config.plc_fade_rate / 2.0,
+        };
+        let gain = (1.0 - fade_rate.clamp(0.0, 1.0)).powi(self.consecutive as i32 - 1);
+        for sample in samples.iter_mut() {
+            *sample *= gain;
+        }
+    }
+}
+
+/// Schedules buffered packets by the capture timestamps of the sender.
+#[derive(Default)]
+struct TimestampPlayout {
+    // Timestamp of the last played packet
+    last: Option<u64>,
+    // Timestamp expected in the next frame slot
+    next: Option<u64>,
+}
+
+impl TimestampPlayout {
+    const FRAME_US: u64 = 20_000;
+    /// A packet not due yet is played anyway after waiting this long, the
+    /// playout fell behind the sender.
+    const MAX_WAIT: Duration = Duration::from_millis(200);
+
+    /// Takes the packet of the current frame slot, `None` if the slot has
+    /// no packet because it was lost or the sender paused.
+    fn take(&mut self, buffer: &mut BTreeMap<u64, BufferedPacket>) -> Option<BufferedPacket> {
+        // Packets older than the played ones arrived too late
+        if let Some(last) = self.last {
+            *buffer = buffer.split_off(&(last + 1));
+        }
+        let (&timestamp, packet) = buffer.first_key_value()?;
+        let due = self
+            .next
+            .is_none_or(|next| timestamp < next + Self::FRAME_US / 2);
+        if !due && packet.timestamp.elapsed() < Self::MAX_WAIT {
+            self.next = self.next.map(|next| next + Self::FRAME_US);
+            return None;
+        }
+        self.last = Some(timestamp);
+        self.next = Some(timestamp + Self::FRAME_US);
+        buffer.remove(&timestamp)
+    }
+}
+
 pub struct Decoder {
     tx: mpsc::Sender<AudioDataPacket>,
     rx: TokioMutex<mpsc::Receiver<AudioFrame>>,
@@ -22,17 +142,25 @@ pub struct Decoder {
     received_frames: Arc<AtomicU64>,
     sent_bytes: Arc<AtomicU64>,
     received_bytes: Arc<AtomicU64>,
+    decoded_frames: Arc<AtomicU64>,
+    plc_frames: Arc<AtomicU64>,
+    underruns: Arc<AtomicU64>,
+    // Average time of decoding a frame in nanoseconds
+    decode_time: Arc<AtomicU64>,
     task: tokio::task::JoinHandle<()>,
 }
 
 impl Decoder {
     const BUFFER_SIZE: usize = 100;
+    /// Number of generated frames between stats log records (5 seconds)
+    const STATS_INTERVAL: u64 = 250;
 
     /// Create a new decoder
     ///
     /// # Arguments
     /// * `target_config` - Audio configuration for the LOCAL playback device (speaker)
-    /// * `codec_type` - Codec used for decoding
+    /// * `codec_type` - Codec expected from the peer, the decoder switches to the
+    ///   codec of incoming packets if the peer prefers another one
     ///
     /// # Behavior
     /// The decoder determines codec channels dynamically from incoming AudioDataPacket.channels
@@ -40,6 +168,15 @@ impl Decoder {
     /// configuration without prior knowledge. The decoder converts from codec channels to
     /// target_config.channels for final playback on the LOCAL speaker.
     pub fn new(target_config: AudioConfig, codec_type: CodecType) -> Self {
+        Self::with_config(target_config, codec_type, DecoderConfig::default())
+    }
+
+    /// Create a new decoder with custom settings, see [`Decoder::new`].
+    pub fn with_config(
+        target_config: AudioConfig,
+        codec_type: CodecType,
+        config: DecoderConfig,
+    ) -> Self {
         let (frame_tx, rx) = mpsc::channel(Self::BUFFER_SIZE);
         let (tx, packet_rx) = mpsc::channel(Self::BUFFER_SIZE);
         let rx = TokioMutex::new(rx);
@@ -47,15 +184,24 @@ impl Decoder {
         let received_frames = Arc::new(AtomicU64::new(0));
         let sent_bytes = Arc::new(AtomicU64::new(0));
         let received_bytes = Arc::new(AtomicU64::new(0));
+        let decoded_frames = Arc::new(AtomicU64::new(0));
+        let plc_frames = Arc::new(AtomicU64::new(0));
+        let underruns = Arc::new(AtomicU64::new(0));
+        let decode_time = Arc::new(AtomicU64::new(0));
         let task = tokio::spawn(Self::main_loop(
             target_config,
             codec_type,
+            config,
             frame_tx,
             packet_rx,
             sent_packets.clone(),
             received_frames.clone(),
             sent_bytes.clone(),
             received_bytes.clone(),
+            decoded_frames.clone(),
+            plc_frames.clone(),
+            underruns.clone(),
+            decode_time.clone(),
         ));
         Self {
             tx,
@@ -64,6 +210,10 @@ impl Decoder {
             received_frames,
             sent_bytes,
             received_bytes,
+            decoded_frames,
+            plc_frames,
+            underruns,
+            decode_time,
             task,
         }
     }
@@ -84,12 +234,17 @@ impl Decoder {
     async fn main_loop(
         target_config: AudioConfig,
         codec_type: CodecType,
+        config: DecoderConfig,
         tx: mpsc::Sender<AudioFrame>,
         mut rx: mpsc::Receiver<AudioDataPacket>,
         sent_packets: Arc<AtomicU64>,
         received_frames: Arc<AtomicU64>,
         sent_bytes: Arc<AtomicU64>,
         received_bytes: Arc<AtomicU64>,
+        decoded_frames: Arc<AtomicU64>,
+        plc_frames: Arc<AtomicU64>,
+        underruns: Arc<AtomicU64>,
+        decode_time: Arc<AtomicU64>,
     ) {
         tracing::info!("Decoder main loop started");
         tracing::info!(
@@ -101,13 +256,18 @@ impl Decoder {
         // and determine the codec channels from the packet data
         let mut decoder: Option<Box<dyn super::codec::AudioDecoder>> = None;
         let mut resampler: Option<Resampler> = None;
+        let mut codec = codec_type;
         let mut current_codec_channels: Option<u16> = None;
 
         // Create jitter buffer - using a simple HashMap instead of JitterBuffer
-        // since JitterBuffer expects AudioFrame
-        use std::collections::BTreeMap;
-        let mut packet_buffer: BTreeMap<u32, BufferedPacket> = BTreeMap::new();
+        // since JitterBuffer expects AudioFrame, keyed by the sequence number
+        // or by the timestamp depending on the playout order
+        let mut packet_buffer: BTreeMap<u64, BufferedPacket> = BTreeMap::new();
         let mut next_sequence: u32 = 0;
+        let mut timestamp_playout = TimestampPlayout::default();
+        let mut plc_fade = PlcFade::new(config);
+        let mut prebuffering = config.prebuffer_frames > 0;
+        let max_prebuffer_wait = tokio::time::Duration::from_millis(40) * config.prebuffer_frames;
 
         // Frame generation loop
         let target_frame_size =
@@ -134,6 +294,12 @@ impl Decoder {
                         tracing::debug!("Decoder received packet #{}, seq: {}, size: {}, channels: {}", packet_count, packet.sequence, packet.data.len(), packet.channels);
                     }
 
+                    if packet.codec != codec {
+                        tracing::info!("Decoder: switching codec from {:?} to {:?}", codec, packet.codec);
+                        codec = packet.codec;
+                        current_codec_channels = None;
+                    }
+
                     // Check if we need to recreate decoder due to channel change
                     if current_codec_channels != Some(packet.channels) {
                         tracing::info!(
@@ -143,7 +309,10 @@ impl Decoder {
                         );
 
                         // Create new decoder with channels from packet
-                        decoder = match create_decoder(codec_type, packet.channels) {
+                        let params = CodecParams::builder(codec)
+                            .channels(packet.channels)
+                            .build();
+                        decoder = match params.and_then(|params| create_decoder(codec, &params)) {
                             Ok(dec) => Some(dec),
                             Err(e) => {
                                 tracing::error!("Failed to create decoder: {}", e);
@@ -181,8 +350,13 @@ impl Decoder {
                     }
 
                     // Store packet in buffer
-                    packet_buffer.insert(packet.sequence, BufferedPacket {
+                    let key = match config.playout_order {
+                        PlayoutOrder::Sequence => packet.sequence as u64,
+                        PlayoutOrder::Timestamp => packet.timestamp,
+                    };
+                    packet_buffer.insert(key, BufferedPacket {
                         data: packet.data,
+                        content: packet.content,
                         timestamp: Instant::now(),
                     });
                 }
@@ -201,20 +375,48 @@ impl Decoder {
 
                     let codec_config = dec.codec_config();
 
+                    // Hold playback until the buffer is filled at call start
+                    if prebuffering {
+                        let waited = packet_buffer
+                            .values()
+                            .map(|pkt| pkt.timestamp.elapsed())
+                            .max()
+                            .unwrap_or_default();
+                        if packet_buffer.len() < config.prebuffer_frames as usize
+                            && waited < max_prebuffer_wait
+                        {
+                            continue;
+                        }
+                        tracing::debug!(buffered = packet_buffer.len(), ?waited, "Decoder prebuffer filled");
+                        prebuffering = false;
+                    }
+
                     // Try to get packet from buffer
-                    let decoded_samples = if let Some(buffered_packet) = packet_buffer.remove(&next_sequence) {
+                    let next_packet = match config.playout_order {
+                        PlayoutOrder::Sequence => packet_buffer.remove(&(next_sequence as u64)),
+                        PlayoutOrder::Timestamp => timestamp_playout.take(&mut packet_buffer),
+                    };
+                    let decoded_samples = if let Some(buffered_packet) = next_packet {
                         // Decode the packet data
                         match dec.decode(&buffered_packet.data) {
                             Ok(samples) => {
                                 next_sequence = next_sequence.wrapping_add(1);
+                                decoded_frames.fetch_add(1, Ordering::Relaxed);
+                                let average = dec.stats().avg_decode_time_us;
+                                decode_time.store((average * 1000.0) as u64, Ordering::Relaxed);
+                                plc_fade.reset(buffered_packet.content);
                                 samples
                             }
                             Err(e) => {
                                 tracing::error!("Decoding failed: {}", e);
                                 next_sequence = next_sequence.wrapping_add(1);
                                 // Use PLC
+                                plc_frames.fetch_add(1, Ordering::Relaxed);
                                 match dec.conceal_packet_loss() {
-                                    Ok(plc_samples) => plc_samples,
+                                    Ok(mut plc_samples) => {
+                                        plc_fade.apply(&mut plc_samples);
+                                        plc_samples
+                                    }
                                     Err(e) => {
                                         tracing::error!("PLC failed: {}", e);
                                         // Generate silence
@@ -226,23 +428,31 @@ impl Decoder {
                             }
                         }
                     } else {
+                        if packet_buffer.is_empty() {
+                            underruns.fetch_add(1, Ordering::Relaxed);
+                        }
                         // No packet available - check if we should skip ahead
                         let now = Instant::now();
-                        let should_skip = packet_buffer.iter().next().map(|(&seq, pkt)| {
-                            seq > next_sequence && now.duration_since(pkt.timestamp).as_millis() > 100
-                        }).unwrap_or(false);
+                        let should_skip = config.playout_order == PlayoutOrder::Sequence
+                            && packet_buffer.iter().next().map(|(&seq, pkt)| {
+                                seq > next_sequence as u64 && now.duration_since(pkt.timestamp).as_millis() > 100
+                            }).unwrap_or(false);
 
                         if should_skip {
                             // Skip to next available packet
                             if let Some((&seq, _)) = packet_buffer.iter().next() {
                                 tracing::debug!("Skipping from sequence {} to {}", next_sequence, seq);
-                                next_sequence = seq;
+                                next_sequence = seq as u32;
                             }
                         }
 
                         // Use PLC for missing packet
+                        plc_frames.fetch_add(1, Ordering::Relaxed);
                         match dec.conceal_packet_loss() {
-                            Ok(plc_samples) => plc_samples,
+                            Ok(mut plc_samples) => {
+                                plc_fade.apply(&mut plc_samples);
+                                plc_samples
+                            }
                             Err(e) => {
                                 tracing::error!("PLC failed: {}", e);
                                 // Generate silence
@@ -267,24 +477,14 @@ impl Decoder {
                     };
 
                     // Channel conversion
-                    if codec_config.channels < target_config.channels {
-                        // Upmix mono to stereo
+                    if codec_config.channels != target_config.channels {
                         tracing::trace!(
-                            "Decoder upmixing {} -> {} channels, {} samples",
+                            "Decoder converting {} -> {} channels, {} samples",
                             codec_config.channels,
                             target_config.channels,
                             samples.len()
                         );
-                        samples = upmix_to_stereo(&samples);
-                    } else if codec_config.channels > target_config.channels {
-                        // Downmix stereo to mono
-                        tracing::trace!(
-                            "Decoder downmixing {} -> {} channels, {} samples",
-                            codec_config.channels,
-                            target_config.channels,
-                            samples.len()
-                        );
-                        samples = downmix_to_mono(&samples, codec_config.channels);
+                        samples = convert_channels(&samples, codec_config.channels, target_config.channels);
                     }
 
                     // Ensure we have the right frame size
@@ -310,6 +510,15 @@ impl Decoder {
                     if frame_count % 50 == 0 {
                         tracing::debug!("Decoder generated frame #{}, samples: {}", frame_count, frame.samples.len());
                     }
+                    if frame_count.is_multiple_of(Self::STATS_INTERVAL) {
+                        tracing::info!(
+                            "Decoder stats: decoded={}, plc={}, underruns={}, buffered={}",
+                            decoded_frames.load(Ordering::Relaxed),
+                            plc_frames.load(Ordering::Relaxed),
+                            underruns.load(Ordering::Relaxed),
+                            packet_buffer.len()
+                        );
+                    }
 
                     // Send frame
                     if let Err(e) = tx.send(frame).await {
@@ -335,6 +544,10 @@ impl Decoder {
             received_frames: self.received_frames.load(Ordering::Relaxed),
             sent_bytes: self.sent_bytes.load(Ordering::Relaxed),
             received_bytes: self.received_bytes.load(Ordering::Relaxed),
+            decoded_frames: self.decoded_frames.load(Ordering::Relaxed),
+            plc_frames: self.plc_frames.load(Ordering::Relaxed),
+            underruns: self.underruns.load(Ordering::Relaxed),
+            avg_decode_time: Duration::from_nanos(self.decode_time.load(Ordering::Relaxed)),
         }
     }
 }
@@ -345,73 +558,18 @@ impl Drop for Decoder {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct DecoderStats {
     pub sent_packets: u64,
     pub received_frames: u64,
     pub sent_bytes: u64,
     pub received_bytes: u64,
-}
-
-/// Downmix multi-channel audio to mono by averaging channels
-fn downmix_to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
-    if channels == 1 {
-        return samples.to_vec();
-    }
-
-    let channels = channels as usize;
-    let mono_samples = samples.len() / channels;
-    let mut output = Vec::with_capacity(mono_samples);
-
-    for i in 0..mono_samples {
-        let mut sum = 0.0;
-        for ch in 0..channels {
-            sum += samples[i * channels + ch];
-        }
-        output.push(sum / channels as f32);
-    }
-
-    output
-}
-
-/// Upmix mono audio to stereo by duplicating to both channels
-fn upmix_to_stereo(samples: &[f32]) -> Vec<f32> {
-    let mut output = Vec::with_capacity(samples.len() * 2);
-    for &sample in samples {
-        output.push(sample); // Left
-        output.push(sample); // Right
-    }
-    output
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_downmix_to_mono() {
-        // Stereo to mono
-        let stereo = vec![0.5, -0.5, 0.3, -0.3, 0.1, -0.1];
-        let mono = downmix_to_mono(&stereo, 2);
-        assert_eq!(mono.len(), 3);
-        assert!((mono[0] - 0.0).abs() < 1e-6);
-        assert!((mono[1] - 0.0).abs() < 1e-6);
-        assert!((mono[2] - 0.0).abs() < 1e-6);
-
-        // Already mono
-        let mono_input = vec![0.5, 0.3, 0.1];
-        let mono_output = downmix_to_mono(&mono_input, 1);
-        assert_eq!(mono_output, mono_input);
-    }
-
-    #[test]
-    fn test_upmix_to_stereo() {
-        let mono = vec![0.5, 0.3, 0.1];
-        let stereo = upmix_to_stereo(&mono);
-        assert_eq!(stereo.len(), 6);
-        assert_eq!(stereo[0], 0.5); // L
-        assert_eq!(stereo[1], 0.5); // R
-        assert_eq!(stereo[2], 0.3); // L
-        assert_eq!(stereo[3], 0.3); // R
-    }
+    /// Frames decoded from received packets.
+    pub decoded_frames: u64,
+    /// Frames generated by packet loss concealment.
+    pub plc_frames: u64,
+    /// Frames generated while no packets were buffered.
+    pub underruns: u64,
+    /// Average time the codec spent decoding a frame.
+    pub avg_decode_time: Duration,
 }
@@ -0,0 +1,10 @@
+use anyhow::Result;
+
+/// Audio device of builds without the `audio` feature, there are none.
+pub enum Device {}
+
+impl Device {
+    pub fn name(&self) -> Result<String> {
+        match *self {}
+    }
+}
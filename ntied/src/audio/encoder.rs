@@ -1,14 +1,17 @@
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use tokio::sync::{Mutex as TokioMutex, mpsc};
 use uuid::Uuid;
 
 use crate::packet::AudioDataPacket;
 
-use super::codec::{CodecType, create_encoder};
-use super::{AudioConfig, AudioFrame, Resampler};
+use super::codec::{CodecParams, CodecType, create_encoder};
+use super::{
+    AudioConfig, AudioFrame, ContentClassifier, Resampler, VadParams, VoiceActivityDetector,
+    convert_channels,
+};
 
 pub struct Encoder {
     tx: mpsc::Sender<AudioFrame>,
@@ -17,6 +20,9 @@ pub struct Encoder {
     received_packets: Arc<AtomicU64>,
     sent_bytes: Arc<AtomicU64>,
     received_bytes: Arc<AtomicU64>,
+    dtx_frames: Arc<AtomicU64>,
+    // Average time of encoding a frame in nanoseconds
+    encode_time: Arc<AtomicU64>,
     task: tokio::task::JoinHandle<()>,
 }
 
@@ -34,6 +40,34 @@ impl Encoder {
     /// This ensures the highest quality audio from the LOCAL microphone is preserved.
     /// The codec channel count is sent in AudioDataPacket.channels to inform the remote decoder.
     pub fn new(source_config: AudioConfig, codec_type: CodecType) -> Self {
+        let params = CodecParams {
+            channels: 2,
+            ..CodecParams::preset(codec_type)
+        };
+        Self::with_params(source_config, codec_type, params)
+    }
+
+    /// Create a new encoder using `params`, the channel count is capped
+    /// by the channels of the source.
+    pub fn with_params(
+        source_config: AudioConfig,
+        codec_type: CodecType,
+        params: CodecParams,
+    ) -> Self {
+        Self::with_vad(source_config, codec_type, params, None)
+    }
+
+    /// Create a new encoder using `params` with discontinuous transmission
+    /// if `vad` is set: frames without voice are not sent.
+    ///
+    /// Sequence numbers of sent packets stay contiguous, the receiver sees
+    /// the pause by timestamps instead of a packet loss.
+    pub fn with_vad(
+        source_config: AudioConfig,
+        codec_type: CodecType,
+        params: CodecParams,
+        vad: Option<VadParams>,
+    ) -> Self {
         let (tx, frame_rx) = mpsc::channel(Self::BUFFER_SIZE);
         let (packet_tx, rx) = mpsc::channel(Self::BUFFER_SIZE);
         let rx = TokioMutex::new(rx);
@@ -41,15 +75,21 @@ impl Encoder {
         let received_packets = Arc::new(AtomicU64::new(0));
         let sent_bytes = Arc::new(AtomicU64::new(0));
         let received_bytes = Arc::new(AtomicU64::new(0));
+        let dtx_frames = Arc::new(AtomicU64::new(0));
+        let encode_time = Arc::new(AtomicU64::new(0));
         let task = tokio::spawn(Self::main_loop(
             source_config,
             codec_type,
+            params,
+            vad,
             packet_tx,
             frame_rx,
             sent_frames.clone(),
             received_packets.clone(),
             sent_bytes.clone(),
             received_bytes.clone(),
+            dtx_frames.clone(),
+            encode_time.clone(),
         ));
         Self {
             tx,
@@ -58,6 +98,8 @@ impl Encoder {
             received_packets,
             sent_bytes,
             received_bytes,
+            dtx_frames,
+            encode_time,
             task,
         }
     }
@@ -78,22 +120,31 @@ impl Encoder {
     async fn main_loop(
         source_config: AudioConfig,
         codec_type: CodecType,
+        params: CodecParams,
+        vad: Option<VadParams>,
         tx: mpsc::Sender<AudioDataPacket>,
         mut rx: mpsc::Receiver<AudioFrame>,
         sent_frames: Arc<AtomicU64>,
         received_packets: Arc<AtomicU64>,
         sent_bytes: Arc<AtomicU64>,
         received_bytes: Arc<AtomicU64>,
+        dtx_frames: Arc<AtomicU64>,
+        encode_time: Arc<AtomicU64>,
     ) {
         // Create codec encoder
-        // Use source channels (support stereo for better quality)
-        let target_channels = source_config.channels.min(2); // Max 2 channels (stereo)
+        // Use source channels up to the channels of the params
+        let target_channels = source_config.channels.min(params.channels);
         tracing::info!(
             "Encoder: source has {} channels, using {} channels for codec",
             source_config.channels,
             target_channels
         );
-        let mut encoder = match create_encoder(codec_type, target_channels) {
+        let params = CodecParams {
+            channels: target_channels,
+            ..params
+        };
+        let params = params.validate(codec_type).map(|()| params);
+        let mut encoder = match params.and_then(|params| create_encoder(codec_type, &params)) {
             Ok(enc) => enc,
             Err(e) => {
                 tracing::error!("Failed to create encoder: {}", e);
@@ -134,34 +185,31 @@ impl Encoder {
         let mut sample_buffer = Vec::with_capacity(codec_frame_size * 2);
 
         let mut sequence: u32 = 0;
+        // Capture time of the first frame and the number of frames after it,
+        // frames skipped by DTX included
+        let mut start_time: Option<u64> = None;
+        let mut frame_index: u64 = 0;
+        let mut classifier = ContentClassifier::default();
+        let mut vad = vad.map(VoiceActivityDetector::new);
 
         while let Some(frame) = rx.recv().await {
             sent_frames.fetch_add(1, Ordering::Relaxed);
             received_bytes.fetch_add((frame.samples.len() * 4) as u64, Ordering::Relaxed);
 
             // Convert channels if needed
-            let mut samples = if source_config.channels > codec_config.channels {
-                // Downmix (e.g., stereo to mono)
-                tracing::debug!(
-                    "Encoder downmixing {} -> {} channels, {} samples",
-                    source_config.channels,
-                    codec_config.channels,
-                    frame.samples.len()
-                );
-                downmix_to_mono(&frame.samples, source_config.channels)
-            } else if source_config.channels < codec_config.channels {
-                // Upmix (e.g., mono to stereo)
+            if source_config.channels != codec_config.channels {
                 tracing::debug!(
-                    "Encoder upmixing {} -> {} channels, {} samples",
+                    "Encoder converting {} -> {} channels, {} samples",
                     source_config.channels,
                     codec_config.channels,
                     frame.samples.len()
                 );
-                upmix_to_stereo(&frame.samples)
-            } else {
-                // Channels match, no conversion needed
-                frame.samples
-            };
+            }
+            let mut samples = convert_channels(
+                &frame.samples,
+                source_config.channels,
+                codec_config.channels,
+            );
 
             // Resample if needed
             if let Some(ref mut resampler) = resampler {
@@ -180,10 +228,31 @@ impl Encoder {
             // Encode complete frames
             while sample_buffer.len() >= codec_frame_size {
                 let frame_samples: Vec<f32> = sample_buffer.drain(..codec_frame_size).collect();
+                let start_time = *start_time.get_or_insert_with(|| {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_micros() as u64
+                });
+                // Timestamps follow the audio clock, the receiver schedules
+                // playback by them without the jitter of encoding
+                let timestamp = start_time + frame_index * 20_000;
+                frame_index += 1;
+                let content = classifier.classify(&frame_samples, codec_config.channels);
+                if let Some(ref mut vad) = vad
+                    && !vad.is_active(&frame_samples)
+                {
+                    dtx_frames.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
 
                 // Encode
                 let encoded = match encoder.encode(&frame_samples) {
-                    Ok(data) => data,
+                    Ok(data) => {
+                        let average = encoder.stats().avg_encode_time_us;
+                        encode_time.store((average * 1000.0) as u64, Ordering::Relaxed);
+                        data
+                    }
                     Err(e) => {
                         tracing::error!("Encoding failed: {}", e);
                         // Send silence packet to maintain sequence
@@ -191,12 +260,6 @@ impl Encoder {
                     }
                 };
 
-                // Get current timestamp in microseconds
-                let timestamp = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_micros() as u64;
-
                 // Create packet
                 let packet = AudioDataPacket {
                     call_id: Uuid::nil(),
@@ -205,6 +268,7 @@ impl Encoder {
                     codec: codec_type,
                     channels: codec_config.channels,
                     data: encoded.clone(),
+                    content,
                 };
 
                 sent_bytes.fetch_add(encoded.len() as u64, Ordering::Relaxed);
@@ -229,6 +293,8 @@ impl Encoder {
             received_packets: self.received_packets.load(Ordering::Relaxed),
             sent_bytes: self.sent_bytes.load(Ordering::Relaxed),
             received_bytes: self.received_bytes.load(Ordering::Relaxed),
+            dtx_frames: self.dtx_frames.load(Ordering::Relaxed),
+            avg_encode_time: Duration::from_nanos(self.encode_time.load(Ordering::Relaxed)),
         }
     }
 }
@@ -245,67 +311,8 @@ pub struct EncoderStats {
     pub received_packets: u64,
     pub sent_bytes: u64,
     pub received_bytes: u64,
-}
-
-/// Downmix multi-channel audio to mono by averaging channels
-fn downmix_to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
-    if channels == 1 {
-        return samples.to_vec();
-    }
-
-    let channels = channels as usize;
-    let mono_samples = samples.len() / channels;
-    let mut output = Vec::with_capacity(mono_samples);
-
-    for i in 0..mono_samples {
-        let mut sum = 0.0;
-        for ch in 0..channels {
-            sum += samples[i * channels + ch];
-        }
-        output.push(sum / channels as f32);
-    }
-
-    output
-}
-
-/// Upmix mono audio to stereo by duplicating to both channels
-fn upmix_to_stereo(samples: &[f32]) -> Vec<f32> {
-    let mut output = Vec::with_capacity(samples.len() * 2);
-    for &sample in samples {
-        output.push(sample); // Left
-        output.push(sample); // Right
-    }
-    output
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_downmix_to_mono() {
-        // Stereo to mono
-        let stereo = vec![0.5, -0.5, 0.3, -0.3, 0.1, -0.1];
-        let mono = downmix_to_mono(&stereo, 2);
-        assert_eq!(mono.len(), 3);
-        assert!((mono[0] - 0.0).abs() < 1e-6);
-        assert!((mono[1] - 0.0).abs() < 1e-6);
-        assert!((mono[2] - 0.0).abs() < 1e-6);
-
-        // Already mono
-        let mono_input = vec![0.5, 0.3, 0.1];
-        let mono_output = downmix_to_mono(&mono_input, 1);
-        assert_eq!(mono_output, mono_input);
-    }
-
-    #[test]
-    fn test_upmix_to_stereo() {
-        let mono = vec![0.5, 0.3, 0.1];
-        let stereo = upmix_to_stereo(&mono);
-        assert_eq!(stereo.len(), 6);
-        assert_eq!(stereo[0], 0.5); // L
-        assert_eq!(stereo[1], 0.5); // R
-        assert_eq!(stereo[2], 0.3); // L
-        assert_eq!(stereo[3], 0.3); // R
-    }
+    /// Frames without voice that were not sent
+    pub dtx_frames: u64,
+    /// Average time the codec spent encoding a frame
+    pub avg_encode_time: Duration,
 }
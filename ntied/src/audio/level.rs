@@ -0,0 +1,96 @@
+use std::time::{Duration, Instant};
+
+use super::AudioFrame;
+
+/// Root mean square level of normalized samples, in range [0.0, 1.0].
+pub fn rms_level(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = samples.iter().map(|s| s * s).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+/// Detects voice in the microphone signal while the microphone is muted.
+///
+/// Speech is reported once the level stays above the threshold for long
+/// enough, after that the detector stays quiet for the cooldown period so
+/// the user is not nudged on every frame.
+pub struct MutedSpeechDetector {
+    /// Minimal RMS level treated as voice
+    threshold: f32,
+    /// Amount of voiced audio required to report speech
+    min_voice: Duration,
+    /// Minimal interval between two reports
+    cooldown: Duration,
+    /// Accumulated voiced audio, decays on quiet frames
+    voice: Duration,
+    last_report: Option<Instant>,
+}
+
+impl MutedSpeechDetector {
+    /// Roughly -34 dBFS, above background noise of a typical microphone.
+    pub const DEFAULT_THRESHOLD: f32 = 0.02;
+    pub const DEFAULT_MIN_VOICE: Duration = Duration::from_millis(300);
+    pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(10);
+
+    /// Create a new MutedSpeechDetector with default settings
+    pub fn new() -> Self {
+        Self::with_config(
+            Self::DEFAULT_THRESHOLD,
+            Self::DEFAULT_MIN_VOICE,
+            Self::DEFAULT_COOLDOWN,
+        )
+    }
+
+    pub fn with_config(threshold: f32, min_voice: Duration, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            min_voice,
+            cooldown,
+            voice: Duration::ZERO,
+            last_report: None,
+        }
+    }
+
+    /// Feeds a frame captured while muted, returns true if the user should
+    /// be told that they are muted.
+    pub fn process(&mut self, frame: &AudioFrame) -> bool {
+        let duration = frame_duration(frame);
+        if rms_level(&frame.samples) < self.threshold {
+            self.voice = self.voice.saturating_sub(duration);
+            return false;
+        }
+        self.voice += duration;
+        if self.voice < self.min_voice {
+            return false;
+        }
+        if let Some(last_report) = self.last_report
+            && frame.timestamp.duration_since(last_report) < self.cooldown
+        {
+            return false;
+        }
+        self.voice = Duration::ZERO;
+        self.last_report = Some(frame.timestamp);
+        true
+    }
+
+    /// Forgets accumulated voice, e.g. when the microphone is unmuted.
+    pub fn reset(&mut self) {
+        self.voice = Duration::ZERO;
+    }
+}
+
+impl Default for MutedSpeechDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn frame_duration(frame: &AudioFrame) -> Duration {
+    let rate = frame.sample_rate as u64 * frame.channels.max(1) as u64;
+    if rate == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_micros(frame.samples.len() as u64 * 1_000_000 / rate)
+}
@@ -0,0 +1,18 @@
+/// Level above which [`soft_clip`] starts compressing samples.
+pub const DEFAULT_LIMITER_THRESHOLD: f32 = 0.8;
+
+/// Soft-clipping limiter curve.
+///
+/// Samples within the threshold pass unchanged, louder ones are bent by a
+/// tanh curve that approaches but never exceeds 1.0. The curve and its slope
+/// are continuous at the threshold, so there is no audible knee.
+pub fn soft_clip(sample: f32, threshold: f32) -> f32 {
+    let threshold = threshold.clamp(0.0, 0.99);
+    let level = sample.abs();
+    if level <= threshold {
+        return sample;
+    }
+    let headroom = 1.0 - threshold;
+    let limited = threshold + headroom * ((level - threshold) / headroom).tanh();
+    limited.copysign(sample)
+}
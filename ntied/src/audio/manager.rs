@@ -1,11 +1,22 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
 use anyhow::{Result, anyhow};
+#[cfg(feature = "audio")]
 use cpal::Device;
+#[cfg(feature = "audio")]
 use cpal::traits::{DeviceTrait, HostTrait};
+use tokio::sync::mpsc;
+
+#[cfg(not(feature = "audio"))]
+use super::Device;
+use super::{is_loopback_device_name, play_test_tone_blocking};
 
 /// Simplified audio manager for device management
 pub struct AudioManager;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AudioDevice {
     pub name: String,
     pub is_default: bool,
@@ -18,94 +29,306 @@ pub enum DeviceType {
     Output,
 }
 
-impl AudioManager {
-    /// List available input devices
-    pub async fn list_input_devices() -> Result<Vec<AudioDevice>> {
-        tokio::task::spawn_blocking(|| {
-            let host = cpal::default_host();
-            let mut devices = Vec::new();
+/// The host has no device of the given type, e.g. a headless machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoAudioDevice(pub DeviceType);
 
-            let default_name = host.default_input_device().and_then(|d| d.name().ok());
+impl fmt::Display for NoAudioDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            DeviceType::Input => write!(f, "No audio input device available"),
+            DeviceType::Output => write!(f, "No audio output device available"),
+        }
+    }
+}
 
-            for device in host.input_devices()? {
-                if let Ok(name) = device.name() {
-                    let is_default = default_name.as_ref().map(|n| n == &name).unwrap_or(false);
+impl std::error::Error for NoAudioDevice {}
 
-                    devices.push(AudioDevice {
-                        name,
-                        is_default,
-                        device_type: DeviceType::Input,
-                    });
-                }
-            }
+/// Audio was requested from a build without the `audio` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioDisabled;
 
-            Ok::<Vec<AudioDevice>, anyhow::Error>(devices)
-        })
-        .await?
+impl fmt::Display for AudioDisabled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Audio is not supported by this build, calls are disabled"
+        )
+    }
+}
+
+impl std::error::Error for AudioDisabled {}
+
+/// Change of audio devices delivered by [`AudioManager::subscribe_device_changes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceChange {
+    Added(AudioDevice),
+    Removed(AudioDevice),
+    /// Another device became the default one, `None` if no device is left.
+    DefaultChanged(DeviceType, Option<String>),
+}
+
+/// Source of audio devices, [`CpalHost`] outside of tests.
+pub trait AudioHost: Send + Sync {
+    fn input_devices(&self) -> Result<Vec<Device>>;
+
+    fn output_devices(&self) -> Result<Vec<Device>>;
+
+    fn default_input_device(&self) -> Option<Device>;
+
+    fn default_output_device(&self) -> Option<Device>;
+
+    /// Input device recording what other applications play, `None` if the
+    /// platform does not expose one.
+    fn loopback_device(&self) -> Option<Device> {
+        self.input_devices()
+            .ok()?
+            .into_iter()
+            .find(|d| d.name().is_ok_and(|name| is_loopback_device_name(&name)))
+    }
+
+    /// Named devices of the given type with the default one marked.
+    fn list_devices(&self, device_type: DeviceType) -> Result<Vec<AudioDevice>> {
+        let (devices, default) = match device_type {
+            DeviceType::Input => (self.input_devices()?, self.default_input_device()),
+            DeviceType::Output => (self.output_devices()?, self.default_output_device()),
+        };
+        let default_name = default.and_then(|d| d.name().ok());
+        Ok(devices
+            .into_iter()
+            .filter_map(|d| d.name().ok())
+            .map(|name| AudioDevice {
+                is_default: default_name.as_ref() == Some(&name),
+                name,
+                device_type,
+            })
+            .collect())
+    }
+}
+
+/// Devices of the default cpal host, builds without the `audio` feature
+/// have none.
+pub struct CpalHost;
+
+#[cfg(not(feature = "audio"))]
+impl AudioHost for CpalHost {
+    fn input_devices(&self) -> Result<Vec<Device>> {
+        Err(AudioDisabled.into())
+    }
+
+    fn output_devices(&self) -> Result<Vec<Device>> {
+        Err(AudioDisabled.into())
+    }
+
+    fn default_input_device(&self) -> Option<Device> {
+        None
+    }
+
+    fn default_output_device(&self) -> Option<Device> {
+        None
+    }
+}
+
+#[cfg(feature = "audio")]
+impl AudioHost for CpalHost {
+    fn input_devices(&self) -> Result<Vec<Device>> {
+        Ok(cpal::default_host().input_devices()?.collect())
+    }
+
+    fn output_devices(&self) -> Result<Vec<Device>> {
+        Ok(cpal::default_host().output_devices()?.collect())
+    }
+
+    fn default_input_device(&self) -> Option<Device> {
+        cpal::default_host().default_input_device()
+    }
+
+    fn default_output_device(&self) -> Option<Device> {
+        cpal::default_host().default_output_device()
+    }
+}
+
+impl AudioManager {
+    /// Whether the build has the `audio` feature, calls need it.
+    pub fn is_supported() -> bool {
+        cfg!(feature = "audio")
+    }
+
+    /// List available input devices
+    pub async fn list_input_devices() -> Result<Vec<AudioDevice>> {
+        tokio::task::spawn_blocking(|| CpalHost.list_devices(DeviceType::Input)).await?
     }
 
     /// List available output devices
     pub async fn list_output_devices() -> Result<Vec<AudioDevice>> {
-        tokio::task::spawn_blocking(|| {
-            let host = cpal::default_host();
-            let mut devices = Vec::new();
+        tokio::task::spawn_blocking(|| CpalHost.list_devices(DeviceType::Output)).await?
+    }
 
-            let default_name = host.default_output_device().and_then(|d| d.name().ok());
+    /// Interval between polls of the device lists, cpal has no portable
+    /// notification about added or removed devices.
+    pub const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
-            for device in host.output_devices()? {
-                if let Ok(name) = device.name() {
-                    let is_default = default_name.as_ref().map(|n| n == &name).unwrap_or(false);
+    /// Subscribe to added and removed devices and to changes of the default
+    /// ones, the subscription ends when the receiver is dropped.
+    pub async fn subscribe_device_changes() -> Result<mpsc::Receiver<DeviceChange>> {
+        Self::subscribe_device_changes_from(Arc::new(CpalHost), Self::DEVICE_POLL_INTERVAL).await
+    }
 
-                    devices.push(AudioDevice {
-                        name,
-                        is_default,
-                        device_type: DeviceType::Output,
-                    });
+    /// Like [`Self::subscribe_device_changes`] with the given host polled
+    /// every `interval`.
+    pub async fn subscribe_device_changes_from(
+        host: Arc<dyn AudioHost>,
+        interval: Duration,
+    ) -> Result<mpsc::Receiver<DeviceChange>> {
+        let mut known = Self::poll_devices(host.clone()).await?;
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = tx.closed() => return,
                 }
+                let devices = match Self::poll_devices(host.clone()).await {
+                    Ok(v) => v,
+                    Err(err) => {
+                        tracing::warn!(?err, "Failed to list audio devices");
+                        continue;
+                    }
+                };
+                for change in device_changes(&known, &devices) {
+                    if tx.send(change).await.is_err() {
+                        return;
+                    }
+                }
+                known = devices;
             }
+        });
+        Ok(rx)
+    }
 
-            Ok::<Vec<AudioDevice>, anyhow::Error>(devices)
+    async fn poll_devices(host: Arc<dyn AudioHost>) -> Result<Vec<AudioDevice>> {
+        tokio::task::spawn_blocking(move || {
+            let mut devices = host.list_devices(DeviceType::Input)?;
+            devices.extend(host.list_devices(DeviceType::Output)?);
+            Ok(devices)
         })
         .await?
     }
 
     /// Get a specific input device by name, or default if name is None
     pub async fn get_input_device(device_name: Option<String>) -> Result<Device> {
-        tokio::task::spawn_blocking(move || {
-            let host = cpal::default_host();
+        Self::get_input_device_from(Arc::new(CpalHost), device_name).await
+    }
 
+    /// Get a specific output device by name, or default if name is None
+    pub async fn get_output_device(device_name: Option<String>) -> Result<Device> {
+        Self::get_output_device_from(Arc::new(CpalHost), device_name).await
+    }
+
+    /// Like [`Self::get_input_device`], fails with [`NoAudioDevice`] when
+    /// the host has no input devices at all.
+    pub async fn get_input_device_from(
+        host: Arc<dyn AudioHost>,
+        device_name: Option<String>,
+    ) -> Result<Device> {
+        tokio::task::spawn_blocking(move || {
             if let Some(name) = device_name {
                 tracing::info!("Getting input device: {}", name);
-                host.input_devices()?
+                let devices = host.input_devices()?;
+                if devices.is_empty() {
+                    return Err(NoAudioDevice(DeviceType::Input).into());
+                }
+                devices
+                    .into_iter()
                     .find(|d| d.name().ok() == Some(name.clone()))
                     .ok_or_else(|| anyhow!("Input device not found: {}", name))
             } else {
                 tracing::info!("Getting default input device");
                 host.default_input_device()
-                    .ok_or_else(|| anyhow!("No default input device"))
+                    .ok_or_else(|| NoAudioDevice(DeviceType::Input).into())
             }
         })
         .await?
     }
 
-    /// Get a specific output device by name, or default if name is None
-    pub async fn get_output_device(device_name: Option<String>) -> Result<Device> {
+    /// The loopback device of the host, see [`AudioHost::loopback_device`].
+    pub async fn get_loopback_device_from(host: Arc<dyn AudioHost>) -> Result<Device> {
         tokio::task::spawn_blocking(move || {
-            let host = cpal::default_host();
+            host.loopback_device()
+                .ok_or_else(|| anyhow!("No loopback device available"))
+        })
+        .await?
+    }
 
+    /// Like [`Self::get_output_device`], fails with [`NoAudioDevice`] when
+    /// the host has no output devices at all.
+    pub async fn get_output_device_from(
+        host: Arc<dyn AudioHost>,
+        device_name: Option<String>,
+    ) -> Result<Device> {
+        tokio::task::spawn_blocking(move || {
             if let Some(name) = device_name {
                 tracing::info!("Getting output device: {}", name);
-                host.output_devices()?
+                let devices = host.output_devices()?;
+                if devices.is_empty() {
+                    return Err(NoAudioDevice(DeviceType::Output).into());
+                }
+                devices
+                    .into_iter()
                     .find(|d| d.name().ok() == Some(name.clone()))
                     .ok_or_else(|| anyhow!("Output device not found: {}", name))
             } else {
                 tracing::info!("Getting default output device");
                 host.default_output_device()
-                    .ok_or_else(|| anyhow!("No default output device"))
+                    .ok_or_else(|| NoAudioDevice(DeviceType::Output).into())
             }
         })
         .await?
     }
+
+    /// Play a sine tone on the output device, the default one for `None`.
+    /// The tone has its own stream and leaves playback of a call untouched.
+    pub async fn play_test_tone(
+        device_name: Option<String>,
+        freq: f32,
+        duration: Duration,
+        volume: f32,
+    ) -> Result<()> {
+        let device = Self::get_output_device(device_name).await?;
+        tokio::task::spawn_blocking(move || {
+            play_test_tone_blocking(&device, freq, duration, volume)
+        })
+        .await?
+    }
+}
+
+/// Changes that turn the `old` device list into the `new` one.
+fn device_changes(old: &[AudioDevice], new: &[AudioDevice]) -> Vec<DeviceChange> {
+    let same =
+        |a: &AudioDevice, b: &AudioDevice| a.name == b.name && a.device_type == b.device_type;
+    let mut changes: Vec<_> = old
+        .iter()
+        .filter(|d| !new.iter().any(|n| same(d, n)))
+        .map(|d| DeviceChange::Removed(d.clone()))
+        .collect();
+    changes.extend(
+        new.iter()
+            .filter(|d| !old.iter().any(|o| same(d, o)))
+            .map(|d| DeviceChange::Added(d.clone())),
+    );
+    for device_type in [DeviceType::Input, DeviceType::Output] {
+        let default = |devices: &[AudioDevice]| {
+            devices
+                .iter()
+                .find(|d| d.device_type == device_type && d.is_default)
+                .map(|d| d.name.clone())
+        };
+        let default_name = default(new);
+        if default(old) != default_name {
+            changes.push(DeviceChange::DefaultChanged(device_type, default_name));
+        }
+    }
+    changes
 }
 
 #[cfg(test)]
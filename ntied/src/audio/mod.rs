@@ -1,19 +1,45 @@
 mod capture;
+mod channels;
 mod codec;
+mod content;
 mod decoder;
+#[cfg(not(feature = "audio"))]
+mod disabled;
 mod encoder;
 mod jitter_buffer;
+mod level;
+mod limiter;
 mod manager;
+mod pacer;
 mod playback;
 mod resampler;
 mod ringtone;
+mod share_mode;
+mod stream_buffer;
+mod system_audio;
+mod test_tone;
+mod vad;
+mod voice_message;
 
 pub use capture::*;
+pub use channels::*;
 pub use codec::*;
+pub use content::*;
 pub use decoder::*;
+#[cfg(not(feature = "audio"))]
+pub use disabled::*;
 pub use encoder::*;
 pub use jitter_buffer::*;
+pub use level::*;
+pub use limiter::*;
 pub use manager::*;
+pub use pacer::*;
 pub use playback::*;
 pub use resampler::*;
 pub use ringtone::*;
+pub use share_mode::*;
+pub use stream_buffer::*;
+pub use system_audio::*;
+pub use test_tone::*;
+pub use vad::*;
+pub use voice_message::*;
@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+
+use super::AudioFrame;
+
+/// Spreads bursts of captured frames to a steady cadence of one frame per
+/// frame duration before they reach the encoder.
+///
+/// Frames arriving after their slot are released at once. A burst is
+/// delayed by at most `max_delay`, beyond that the schedule is restarted
+/// so the pacer never adds more latency than that.
+#[derive(Debug)]
+pub struct FramePacer {
+    max_delay: Duration,
+    next_release: Option<Instant>,
+}
+
+impl FramePacer {
+    pub const DEFAULT_MAX_DELAY: Duration = Duration::from_millis(60);
+
+    pub fn new(max_delay: Duration) -> Self {
+        Self {
+            max_delay,
+            next_release: None,
+        }
+    }
+
+    /// Returns the time to release a frame of `duration` arriving at `now`.
+    pub fn schedule(&mut self, duration: Duration, now: Instant) -> Instant {
+        let release = match self.next_release {
+            Some(v) if v > now && v - now <= self.max_delay => v,
+            _ => now,
+        };
+        self.next_release = Some(release + duration);
+        release
+    }
+
+    /// Waits until the frame may be passed to the encoder.
+    pub async fn pace(&mut self, frame: &AudioFrame) {
+        let release = self.schedule(frame.duration(), Instant::now());
+        tokio::time::sleep_until(release.into()).await;
+    }
+
+    pub fn reset(&mut self) {
+        self.next_release = None;
+    }
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_DELAY)
+    }
+}
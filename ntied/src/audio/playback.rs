@@ -1,14 +1,23 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 use anyhow::{Result, anyhow};
+#[cfg(feature = "audio")]
 use cpal::traits::{DeviceTrait as _, StreamTrait};
+#[cfg(feature = "audio")]
 use cpal::{Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
 use tokio::sync::mpsc;
-use tokio::task::{JoinHandle, spawn_blocking};
+use tokio::task::JoinHandle;
+#[cfg(feature = "audio")]
+use tokio::task::spawn_blocking;
 
-use super::AudioFrame;
+#[cfg(not(feature = "audio"))]
+use super::Device;
+use super::{AudioFrame, ShareMode, StreamBuffer};
+#[cfg(feature = "audio")]
+use super::{DEFAULT_LIMITER_THRESHOLD, ExclusiveModeUnavailable, open_with_fallback, soft_clip};
 
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
 enum Command {
     Mute(bool),
 }
@@ -16,17 +25,52 @@ enum Command {
 pub struct PlaybackStream {
     command_tx: mpsc::Sender<Command>,
     volume: Arc<AtomicU32>,
+    limiter: Arc<AtomicBool>,
     tx: mpsc::Sender<AudioFrame>,
+    buffer: Arc<StreamBuffer>,
     task: JoinHandle<()>,
     sample_rate: u32,
     channels: u16,
+    share_mode: ShareMode,
 }
 
 impl PlaybackStream {
     pub async fn new(device: Device, volume: f32) -> Result<Self> {
+        Self::with_share_mode(device, volume, ShareMode::Shared).await
+    }
+
+    /// Opens the device in `share_mode`, falls back to shared access if the
+    /// device cannot be opened exclusively.
+    #[cfg(feature = "audio")]
+    pub async fn with_share_mode(
+        device: Device,
+        volume: f32,
+        share_mode: ShareMode,
+    ) -> Result<Self> {
+        open_with_fallback(share_mode, |share_mode| {
+            Self::open(device.clone(), volume, share_mode)
+        })
+        .await
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub async fn with_share_mode(
+        device: Device,
+        _volume: f32,
+        _share_mode: ShareMode,
+    ) -> Result<Self> {
+        match device {}
+    }
+
+    #[cfg(feature = "audio")]
+    async fn open(device: Device, volume: f32, share_mode: ShareMode) -> Result<Self> {
+        if !share_mode.is_supported() {
+            return Err(ExclusiveModeUnavailable.into());
+        }
         let (command_tx, mut command_rx) = mpsc::channel(1);
         let (tx, mut rx) = mpsc::channel::<AudioFrame>(100);
         let volume = Arc::new(AtomicU32::new(f32::to_bits(volume)));
+        let limiter = Arc::new(AtomicBool::new(true));
         let config = device
             .default_output_config()
             .map_err(|e| anyhow!("Failed to get default output config: {}", e))?;
@@ -51,8 +95,11 @@ impl PlaybackStream {
         }
 
         let stream_config: StreamConfig = config.into();
+        let buffer = Arc::new(StreamBuffer::new(sample_rate, channels));
         let task = {
             let volume = volume.clone();
+            let limiter = limiter.clone();
+            let buffer = buffer.clone();
             spawn_blocking(move || {
                 // Ring buffer for playback samples
                 let buffer_size = ((sample_rate as usize) * channels as usize) / 5; // 200ms buffer for better stability
@@ -60,12 +107,14 @@ impl PlaybackStream {
                     buffer_size,
                 )));
                 let ring_buffer_clone = ring_buffer.clone();
+                let stream_buffer = buffer.clone();
                 // Spawn task to receive audio frames and fill the buffer
                 let runtime = tokio::runtime::Handle::current();
                 let buffer_fill_task = runtime.spawn(async move {
                     let mut frame_count = 0u64;
                     tracing::info!("Playback buffer fill task started");
                     while let Some(frame) = rx.recv().await {
+                        stream_buffer.unqueue_frame();
                         frame_count += 1;
                         if frame_count % 100 == 0 {
                             tracing::debug!(
@@ -80,6 +129,7 @@ impl PlaybackStream {
                             // If buffer is full, drop oldest samples
                             if buffer.len() >= buffer_size {
                                 buffer.remove(0);
+                                stream_buffer.consume_samples(1);
                             }
                             buffer.push(sample);
                         }
@@ -95,60 +145,80 @@ impl PlaybackStream {
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::I16 => Self::build_output_stream::<i16>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::I32 => Self::build_output_stream::<i32>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::I64 => Self::build_output_stream::<i64>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::U8 => Self::build_output_stream::<u8>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::U16 => Self::build_output_stream::<u16>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::U32 => Self::build_output_stream::<u32>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::U64 => Self::build_output_stream::<u64>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::F32 => Self::build_output_stream::<f32>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     SampleFormat::F64 => Self::build_output_stream::<f64>(
                         &device,
                         &stream_config,
                         ring_buffer.clone(),
                         volume,
+                        limiter,
+                        buffer,
                     ),
                     _ => {
                         tracing::error!("Unsupported sample format: {:?}", sample_format);
@@ -198,24 +268,34 @@ impl PlaybackStream {
         Ok(PlaybackStream {
             command_tx,
             volume,
+            limiter,
             tx,
+            buffer,
             task,
             sample_rate,
             channels,
+            share_mode,
         })
     }
 
     pub async fn send(&mut self, frame: AudioFrame) -> Result<()> {
-        self.tx
-            .send(frame)
-            .await
-            .map_err(|_| anyhow!("Failed to send audio frame: channel closed"))
+        let samples = frame.samples.len();
+        self.buffer.push_frame(samples);
+        self.tx.send(frame).await.map_err(|_| {
+            self.buffer.pop_frame(samples);
+            anyhow!("Failed to send audio frame: channel closed")
+        })
     }
 
     pub fn try_send(&mut self, frame: AudioFrame) -> Result<()> {
-        self.tx.try_send(frame).map_err(|e| match e {
-            mpsc::error::TrySendError::Full(_) => anyhow!("Audio buffer full"),
-            mpsc::error::TrySendError::Closed(_) => anyhow!("Audio channel closed"),
+        let samples = frame.samples.len();
+        self.buffer.push_frame(samples);
+        self.tx.try_send(frame).map_err(|e| {
+            self.buffer.pop_frame(samples);
+            match e {
+                mpsc::error::TrySendError::Full(_) => anyhow!("Audio buffer full"),
+                mpsc::error::TrySendError::Closed(_) => anyhow!("Audio channel closed"),
+            }
         })
     }
 
@@ -237,19 +317,51 @@ impl PlaybackStream {
         self.channels
     }
 
+    /// Access the device was opened with, shared after a fallback.
+    pub fn share_mode(&self) -> ShareMode {
+        self.share_mode
+    }
+
     pub fn volume(&self) -> f32 {
         f32::from_bits(self.volume.load(Ordering::Relaxed))
     }
 
+    /// Enable soft clipping of samples after the volume is applied, on by
+    /// default so volumes above 1.0 do not clip harshly.
+    pub fn set_limiter(&self, enabled: bool) {
+        self.limiter.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_limiter_enabled(&self) -> bool {
+        self.limiter.load(Ordering::Relaxed)
+    }
+
     pub fn get_buffer_space(&self) -> usize {
         self.tx.capacity()
     }
 
+    /// Frames sent but not yet moved to the device buffer.
+    pub fn buffered_frames(&self) -> usize {
+        self.buffer.buffered_frames()
+    }
+
+    /// Time until a frame sent now starts playing.
+    pub fn estimated_latency_ms(&self) -> f32 {
+        self.buffer.estimated_latency_ms()
+    }
+
+    pub fn buffer(&self) -> Arc<StreamBuffer> {
+        self.buffer.clone()
+    }
+
+    #[cfg(feature = "audio")]
     fn build_output_stream<T>(
         device: &Device,
         config: &StreamConfig,
         ring_buffer: Arc<std::sync::Mutex<Vec<f32>>>,
         volume: Arc<AtomicU32>,
+        limiter: Arc<AtomicBool>,
+        stream_buffer: Arc<StreamBuffer>,
     ) -> Result<Stream>
     where
         T: SizedSample + FromSample<f32>,
@@ -266,6 +378,15 @@ impl PlaybackStream {
                 );
             }
             let vol = f32::from_bits(volume.load(Ordering::Relaxed));
+            let limit = limiter.load(Ordering::Relaxed);
+            let output = |sample: f32| {
+                let sample = sample * vol;
+                if limit {
+                    T::from_sample(soft_clip(sample, DEFAULT_LIMITER_THRESHOLD))
+                } else {
+                    T::from_sample(sample)
+                }
+            };
             let mut buffer = ring_buffer.lock().unwrap();
 
             // Process in chunks for better performance
@@ -282,21 +403,23 @@ impl PlaybackStream {
             if samples_available >= samples_needed {
                 // Fast path: we have enough samples
                 for (i, sample) in data.iter_mut().enumerate() {
-                    *sample = T::from_sample(buffer[i] * vol);
+                    *sample = output(buffer[i]);
                 }
                 buffer.drain(0..samples_needed);
+                stream_buffer.consume_samples(samples_needed);
             } else if samples_available > 0 {
                 // Partial buffer: play what we have, then silence
                 // DO NOT stretch - stretching changes pitch!
                 for (i, sample) in data.iter_mut().enumerate() {
                     if i < samples_available {
-                        *sample = T::from_sample(buffer[i] * vol);
+                        *sample = output(buffer[i]);
                     } else {
                         // Fill remainder with silence to avoid pitch shift
                         *sample = T::from_sample(0.0);
                     }
                 }
                 buffer.clear();
+                stream_buffer.consume_samples(samples_available);
 
                 // Log underrun for debugging
                 tracing::trace!(
@@ -1,25 +1,96 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
+#[cfg(feature = "audio")]
+use anyhow::anyhow;
+#[cfg(feature = "audio")]
 use cpal::traits::{DeviceTrait as _, HostTrait as _, StreamTrait};
+#[cfg(feature = "audio")]
 use cpal::{Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
 use tokio::task::{JoinHandle, spawn_blocking};
 
+#[cfg(not(feature = "audio"))]
+use super::AudioDisabled;
+
+/// Dual-tone ringtone pattern: 2 seconds of ring, 4 seconds of silence.
+pub struct RingtoneSignal {
+    sample_rate: f32,
+    sample_clock: f32,
+}
+
+impl RingtoneSignal {
+    // Dual-tone frequencies (similar to phone ringtone)
+    const FREQ1: f32 = 480.0;
+    const FREQ2: f32 = 620.0;
+    const RING_DURATION: f32 = 2.0;
+    const SILENCE_DURATION: f32 = 4.0;
+    // Amplitude of each tone at volume 1.0
+    const LEVEL: f32 = 0.15;
+    const FADE_DURATION: f32 = 0.05;
+
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as f32,
+            sample_clock: 0.0,
+        }
+    }
+
+    /// Writes the next frames of the pattern scaled by `volume`, the same
+    /// value goes to all channels.
+    pub fn fill(&mut self, data: &mut [f32], channels: usize, volume: f32) {
+        let pattern_duration = Self::RING_DURATION + Self::SILENCE_DURATION;
+        for frame in data.chunks_mut(channels) {
+            let time = self.sample_clock / self.sample_rate;
+            let pattern_time = time % pattern_duration;
+            let value = if pattern_time < Self::RING_DURATION {
+                let t = time * 2.0 * std::f32::consts::PI;
+                let mixed = ((Self::FREQ1 * t).sin() + (Self::FREQ2 * t).sin()) * Self::LEVEL;
+                // Apply fade in/out to avoid clicks
+                let fade_in = (pattern_time / Self::FADE_DURATION).min(1.0);
+                let fade_out =
+                    ((Self::RING_DURATION - pattern_time) / Self::FADE_DURATION).min(1.0);
+                mixed * fade_in * fade_out * volume
+            } else {
+                0.0
+            };
+            frame.fill(value);
+            self.sample_clock = (self.sample_clock + 1.0) % (self.sample_rate * pattern_duration);
+        }
+    }
+}
+
 /// Ringtone player that generates and plays a dual-tone ringtone pattern
 pub struct RingtonePlayer {
     is_playing: Arc<AtomicBool>,
+    // Bits of the f32 volume, independent of the call playback volume
+    volume: Arc<AtomicU32>,
     task: Option<JoinHandle<()>>,
 }
 
 impl RingtonePlayer {
+    pub const DEFAULT_VOLUME: f32 = 1.0;
+    /// The loudest ringtone still does not clip.
+    pub const MAX_VOLUME: f32 = 3.0;
+
     pub fn new() -> Self {
         Self {
             is_playing: Arc::new(AtomicBool::new(false)),
+            volume: Arc::new(AtomicU32::new(Self::DEFAULT_VOLUME.to_bits())),
             task: None,
         }
     }
 
+    /// Set the ringtone volume multiplier, applied to a ringing tone at once.
+    pub fn set_volume(&self, volume: f32) {
+        let volume = volume.clamp(0.0, Self::MAX_VOLUME);
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
     /// Start playing the ringtone
     pub fn start(&mut self) -> Result<()> {
         if self.is_playing.load(Ordering::Relaxed) {
@@ -29,9 +100,10 @@ impl RingtonePlayer {
         self.is_playing.store(true, Ordering::Relaxed);
 
         let is_playing = self.is_playing.clone();
+        let volume = self.volume.clone();
 
         self.task = Some(spawn_blocking(move || {
-            if let Err(e) = Self::play_ringtone_blocking(is_playing) {
+            if let Err(e) = Self::play_ringtone_blocking(is_playing, volume) {
                 tracing::error!("Failed to play ringtone: {}", e);
             }
         }));
@@ -53,7 +125,13 @@ impl RingtonePlayer {
         self.is_playing.load(Ordering::Relaxed)
     }
 
-    fn play_ringtone_blocking(is_playing: Arc<AtomicBool>) -> Result<()> {
+    #[cfg(not(feature = "audio"))]
+    fn play_ringtone_blocking(_is_playing: Arc<AtomicBool>, _volume: Arc<AtomicU32>) -> Result<()> {
+        Err(AudioDisabled.into())
+    }
+
+    #[cfg(feature = "audio")]
+    fn play_ringtone_blocking(is_playing: Arc<AtomicBool>, volume: Arc<AtomicU32>) -> Result<()> {
         let host = cpal::default_host();
         let device = host
             .default_output_device()
@@ -77,36 +155,66 @@ impl RingtonePlayer {
         let stream_config: StreamConfig = config.into();
 
         let stream = match sample_format {
-            SampleFormat::I8 => {
-                Self::build_ringtone_stream::<i8>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::I16 => {
-                Self::build_ringtone_stream::<i16>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::I32 => {
-                Self::build_ringtone_stream::<i32>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::I64 => {
-                Self::build_ringtone_stream::<i64>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::U8 => {
-                Self::build_ringtone_stream::<u8>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::U16 => {
-                Self::build_ringtone_stream::<u16>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::U32 => {
-                Self::build_ringtone_stream::<u32>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::U64 => {
-                Self::build_ringtone_stream::<u64>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::F32 => {
-                Self::build_ringtone_stream::<f32>(&device, &stream_config, is_playing.clone())
-            }
-            SampleFormat::F64 => {
-                Self::build_ringtone_stream::<f64>(&device, &stream_config, is_playing.clone())
-            }
+            SampleFormat::I8 => Self::build_ringtone_stream::<i8>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::I16 => Self::build_ringtone_stream::<i16>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::I32 => Self::build_ringtone_stream::<i32>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::I64 => Self::build_ringtone_stream::<i64>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::U8 => Self::build_ringtone_stream::<u8>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::U16 => Self::build_ringtone_stream::<u16>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::U32 => Self::build_ringtone_stream::<u32>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::U64 => Self::build_ringtone_stream::<u64>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::F32 => Self::build_ringtone_stream::<f32>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
+            SampleFormat::F64 => Self::build_ringtone_stream::<f64>(
+                &device,
+                &stream_config,
+                is_playing.clone(),
+                volume.clone(),
+            ),
             _ => {
                 return Err(anyhow!("Unsupported sample format: {:?}", sample_format));
             }
@@ -126,27 +234,19 @@ impl RingtonePlayer {
         Ok(())
     }
 
+    #[cfg(feature = "audio")]
     fn build_ringtone_stream<T>(
         device: &Device,
         config: &StreamConfig,
         is_playing: Arc<AtomicBool>,
+        volume: Arc<AtomicU32>,
     ) -> Result<Stream>
     where
         T: SizedSample + FromSample<f32>,
     {
-        let sample_rate = config.sample_rate.0 as f32;
         let channels = config.channels as usize;
-
-        // Dual-tone frequencies (similar to phone ringtone)
-        let freq1 = 480.0; // Hz
-        let freq2 = 620.0; // Hz
-
-        // Ring pattern: 2 seconds on, 4 seconds off
-        let ring_duration = 2.0; // seconds
-        let silence_duration = 4.0; // seconds
-        let pattern_duration = ring_duration + silence_duration;
-
-        let mut sample_clock = 0f32;
+        let mut signal = RingtoneSignal::new(config.sample_rate.0);
+        let mut buffer = Vec::new();
 
         let data_fn = move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
             if !is_playing.load(Ordering::Relaxed) {
@@ -156,38 +256,11 @@ impl RingtonePlayer {
                 }
                 return;
             }
-
-            for frame in data.chunks_mut(channels) {
-                let time = sample_clock / sample_rate;
-                let pattern_time = time % pattern_duration;
-
-                let value = if pattern_time < ring_duration {
-                    // Generate dual-tone signal
-                    let t = time * 2.0 * std::f32::consts::PI;
-                    let tone1 = (freq1 * t).sin();
-                    let tone2 = (freq2 * t).sin();
-
-                    // Mix the two tones and apply envelope
-                    let mixed = (tone1 + tone2) * 0.15; // Reduced volume to 15%
-
-                    // Apply fade in/out to avoid clicks
-                    let fade_duration = 0.05; // 50ms fade
-                    let fade_in = (pattern_time / fade_duration).min(1.0);
-                    let fade_out = ((ring_duration - pattern_time) / fade_duration).min(1.0);
-                    let envelope = fade_in * fade_out;
-
-                    mixed * envelope
-                } else {
-                    // Silence
-                    0.0
-                };
-
-                // Write the same value to all channels
-                for sample in frame.iter_mut() {
-                    *sample = T::from_sample(value);
-                }
-
-                sample_clock = (sample_clock + 1.0) % (sample_rate * pattern_duration);
+            buffer.resize(data.len(), 0.0);
+            let volume = f32::from_bits(volume.load(Ordering::Relaxed));
+            signal.fill(&mut buffer, channels, volume);
+            for (sample, value) in data.iter_mut().zip(&buffer) {
+                *sample = T::from_sample(*value);
             }
         };
 
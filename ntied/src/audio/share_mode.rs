@@ -0,0 +1,59 @@
+use std::fmt;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Access to an audio device requested by call streams.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShareMode {
+    /// The device is mixed with other applications.
+    #[default]
+    Shared,
+    /// The device is used by this application only, with lower latency.
+    Exclusive,
+}
+
+impl ShareMode {
+    /// Whether streams can be opened in this mode.
+    ///
+    /// cpal opens every device in shared mode, exclusive access is refused
+    /// until the backend provides it.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Shared => true,
+            Self::Exclusive => false,
+        }
+    }
+}
+
+/// Exclusive access to an audio device was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExclusiveModeUnavailable;
+
+impl fmt::Display for ExclusiveModeUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Exclusive access to the audio device is not available")
+    }
+}
+
+impl std::error::Error for ExclusiveModeUnavailable {}
+
+/// Opens a stream with `open` in `mode`. If exclusive access fails the
+/// stream is opened again in shared mode, errors of shared access are
+/// returned as is.
+pub async fn open_with_fallback<T, F, Fut>(mode: ShareMode, mut open: F) -> Result<T>
+where
+    F: FnMut(ShareMode) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    match mode {
+        ShareMode::Shared => open(ShareMode::Shared).await,
+        ShareMode::Exclusive => match open(ShareMode::Exclusive).await {
+            Ok(stream) => Ok(stream),
+            Err(err) => {
+                tracing::warn!(%err, "Cannot open audio device exclusively, using shared mode");
+                open(ShareMode::Shared).await
+            }
+        },
+    }
+}
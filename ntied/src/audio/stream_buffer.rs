@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Audio queued inside a capture or playback stream.
+///
+/// Frames are counted while they wait in the stream channel, samples are
+/// counted until they reach the consumer or the output device.
+#[derive(Debug)]
+pub struct StreamBuffer {
+    sample_rate: u32,
+    channels: u16,
+    frames: AtomicUsize,
+    samples: AtomicUsize,
+}
+
+impl StreamBuffer {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            frames: AtomicUsize::new(0),
+            samples: AtomicUsize::new(0),
+        }
+    }
+
+    /// A frame of interleaved samples entered the stream.
+    pub fn push_frame(&self, samples: usize) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.samples.fetch_add(samples, Ordering::Relaxed);
+    }
+
+    /// A frame left the stream together with its samples.
+    pub fn pop_frame(&self, samples: usize) {
+        saturating_sub(&self.frames, 1);
+        saturating_sub(&self.samples, samples);
+    }
+
+    /// A frame left the channel, its samples are still pending in the
+    /// device buffer.
+    pub fn unqueue_frame(&self) {
+        saturating_sub(&self.frames, 1);
+    }
+
+    /// Samples played by the device or dropped on overflow.
+    pub fn consume_samples(&self, samples: usize) {
+        saturating_sub(&self.samples, samples);
+    }
+
+    pub fn buffered_frames(&self) -> usize {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    pub fn buffered_samples(&self) -> usize {
+        self.samples.load(Ordering::Relaxed)
+    }
+
+    /// Time needed to drain all buffered samples.
+    pub fn estimated_latency_ms(&self) -> f32 {
+        let rate = self.sample_rate as f32 * self.channels.max(1) as f32;
+        if rate == 0.0 {
+            return 0.0;
+        }
+        self.buffered_samples() as f32 * 1000.0 / rate
+    }
+
+    pub fn stats(&self) -> StreamBufferStats {
+        StreamBufferStats {
+            buffered_frames: self.buffered_frames(),
+            estimated_latency_ms: self.estimated_latency_ms(),
+        }
+    }
+}
+
+fn saturating_sub(value: &AtomicUsize, delta: usize) {
+    let _ = value.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+        Some(v.saturating_sub(delta))
+    });
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StreamBufferStats {
+    pub buffered_frames: usize,
+    pub estimated_latency_ms: f32,
+}
+
+/// Buffering of the capture and playback streams of the current call.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StreamStats {
+    pub capture: StreamBufferStats,
+    pub playback: StreamBufferStats,
+}
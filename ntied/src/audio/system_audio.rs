@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{AudioConfig, AudioFrame, CaptureStream, Resampler, convert_channels};
+
+/// How the system audio is combined with the microphone during screen share.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SystemAudioMode {
+    /// Both the microphone and the system audio are sent.
+    #[default]
+    Mix,
+    /// Only the system audio is sent.
+    Replace,
+}
+
+/// Source of the audio played by other applications.
+#[async_trait]
+pub trait LoopbackSource: Send {
+    fn config(&self) -> AudioConfig;
+
+    /// Next captured frame, `None` after the source is closed.
+    async fn recv(&mut self) -> Option<AudioFrame>;
+}
+
+#[async_trait]
+impl LoopbackSource for CaptureStream {
+    fn config(&self) -> AudioConfig {
+        AudioConfig::new(self.sample_rate(), self.channels())
+    }
+
+    async fn recv(&mut self) -> Option<AudioFrame> {
+        CaptureStream::recv(self).await
+    }
+}
+
+/// Whether the input device records the output of other applications, as
+/// PulseAudio monitors and "Stereo Mix" of Windows do.
+pub fn is_loopback_device_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["monitor", "loopback", "stereo mix", "what u hear"]
+        .iter()
+        .any(|v| name.contains(v))
+}
+
+/// Combines the system audio with microphone frames.
+///
+/// System audio is converted to the microphone format and queued, every
+/// microphone frame takes the same number of queued samples. Missing
+/// samples are silence, the queue is limited so the delay does not grow.
+pub struct SystemAudioMixer {
+    mode: SystemAudioMode,
+    source_config: AudioConfig,
+    target_config: AudioConfig,
+    resampler: Option<Resampler>,
+    samples: VecDeque<f32>,
+}
+
+impl SystemAudioMixer {
+    const MAX_DELAY: Duration = Duration::from_millis(200);
+
+    /// Mixer of the system audio in `source_config` into microphone frames
+    /// in `target_config`.
+    pub fn new(
+        mode: SystemAudioMode,
+        source_config: AudioConfig,
+        target_config: AudioConfig,
+    ) -> Result<Self> {
+        let resampler = if source_config.sample_rate != target_config.sample_rate {
+            Some(Resampler::new(
+                source_config.sample_rate,
+                target_config.sample_rate,
+                target_config.channels,
+            )?)
+        } else {
+            None
+        };
+        Ok(Self {
+            mode,
+            source_config,
+            target_config,
+            resampler,
+            samples: VecDeque::new(),
+        })
+    }
+
+    pub fn mode(&self) -> SystemAudioMode {
+        self.mode
+    }
+
+    /// Queues a frame of the system audio.
+    pub fn push(&mut self, frame: &AudioFrame) {
+        let mut samples = convert_channels(
+            &frame.samples,
+            self.source_config.channels,
+            self.target_config.channels,
+        );
+        if let Some(ref mut resampler) = self.resampler {
+            samples = match resampler.resample(&samples) {
+                Ok(resampled) => resampled,
+                Err(err) => {
+                    tracing::warn!(%err, "Cannot resample system audio");
+                    return;
+                }
+            };
+        }
+        self.samples.extend(samples);
+        let max_samples = (self.target_config.sample_rate as u128 * Self::MAX_DELAY.as_millis()
+            / 1000) as usize
+            * self.target_config.channels as usize;
+        let excess = self.samples.len().saturating_sub(max_samples);
+        self.samples.drain(..excess);
+    }
+
+    /// Combines queued system audio with the microphone frame.
+    pub fn mix(&mut self, frame: &mut AudioFrame) {
+        let count = frame.samples.len().min(self.samples.len());
+        let system = self.samples.drain(..count);
+        match self.mode {
+            SystemAudioMode::Mix => {
+                for (sample, system) in frame.samples.iter_mut().zip(system) {
+                    *sample = (*sample + system).clamp(-1.0, 1.0);
+                }
+            }
+            SystemAudioMode::Replace => {
+                let mut samples: Vec<f32> = system.collect();
+                samples.resize(frame.samples.len(), 0.0);
+                frame.samples = samples;
+            }
+        }
+    }
+}
+
+/// Moves frames of `source` to the mixer until the source is closed.
+pub async fn forward_system_audio(
+    mut source: Box<dyn LoopbackSource>,
+    mixer: Arc<Mutex<SystemAudioMixer>>,
+) {
+    while let Some(frame) = source.recv().await {
+        mixer.lock().unwrap().push(&frame);
+    }
+    tracing::debug!("System audio source closed");
+}
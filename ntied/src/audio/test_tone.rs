@@ -0,0 +1,109 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use anyhow::Result;
+#[cfg(feature = "audio")]
+use anyhow::anyhow;
+#[cfg(feature = "audio")]
+use cpal::traits::{DeviceTrait as _, StreamTrait as _};
+#[cfg(feature = "audio")]
+use cpal::{Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+
+#[cfg(not(feature = "audio"))]
+use super::Device;
+
+/// Fade at both ends of the tone against clicks.
+const FADE: Duration = Duration::from_millis(10);
+
+/// Mono samples of a sine tone, `volume` is the peak amplitude in 0.0..=1.0.
+pub fn test_tone_samples(freq: f32, duration: Duration, volume: f32, sample_rate: u32) -> Vec<f32> {
+    let volume = volume.clamp(0.0, 1.0);
+    let len = (duration.as_secs_f32() * sample_rate as f32) as usize;
+    let fade = ((FADE.as_secs_f32() * sample_rate as f32) as usize)
+        .min(len / 2)
+        .max(1);
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / sample_rate as f32;
+            let envelope = (i.min(len - 1 - i) as f32 / fade as f32).min(1.0);
+            (2.0 * PI * freq * t).sin() * volume * envelope
+        })
+        .collect()
+}
+
+/// Play the tone on the device and wait until it is over.
+#[cfg(not(feature = "audio"))]
+pub(crate) fn play_test_tone_blocking(
+    device: &Device,
+    _freq: f32,
+    _duration: Duration,
+    _volume: f32,
+) -> Result<()> {
+    match *device {}
+}
+
+/// Play the tone on the device and wait until it is over.
+#[cfg(feature = "audio")]
+pub(crate) fn play_test_tone_blocking(
+    device: &Device,
+    freq: f32,
+    duration: Duration,
+    volume: f32,
+) -> Result<()> {
+    let config = device
+        .default_output_config()
+        .map_err(|e| anyhow!("Failed to get default output config: {}", e))?;
+    let sample_format = config.sample_format();
+    let stream_config: StreamConfig = config.into();
+    let samples = test_tone_samples(freq, duration, volume, stream_config.sample_rate.0);
+    tracing::info!(
+        "Test tone playback: {} Hz for {:?} at {} Hz, format: {:?}",
+        freq,
+        duration,
+        stream_config.sample_rate.0,
+        sample_format
+    );
+
+    let stream = match sample_format {
+        SampleFormat::I8 => build_tone_stream::<i8>(device, &stream_config, samples),
+        SampleFormat::I16 => build_tone_stream::<i16>(device, &stream_config, samples),
+        SampleFormat::I32 => build_tone_stream::<i32>(device, &stream_config, samples),
+        SampleFormat::I64 => build_tone_stream::<i64>(device, &stream_config, samples),
+        SampleFormat::U8 => build_tone_stream::<u8>(device, &stream_config, samples),
+        SampleFormat::U16 => build_tone_stream::<u16>(device, &stream_config, samples),
+        SampleFormat::U32 => build_tone_stream::<u32>(device, &stream_config, samples),
+        SampleFormat::U64 => build_tone_stream::<u64>(device, &stream_config, samples),
+        SampleFormat::F32 => build_tone_stream::<f32>(device, &stream_config, samples),
+        SampleFormat::F64 => build_tone_stream::<f64>(device, &stream_config, samples),
+        _ => return Err(anyhow!("Unsupported sample format: {:?}", sample_format)),
+    }?;
+    stream
+        .play()
+        .map_err(|e| anyhow!("Failed to play stream: {}", e))?;
+    // Leave time for the device buffer to drain
+    std::thread::sleep(duration + Duration::from_millis(100));
+    Ok(())
+}
+
+#[cfg(feature = "audio")]
+fn build_tone_stream<T>(device: &Device, config: &StreamConfig, samples: Vec<f32>) -> Result<Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let mut samples = samples.into_iter();
+    let data_fn = move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+        for frame in data.chunks_mut(channels) {
+            let value = samples.next().unwrap_or(0.0);
+            for sample in frame.iter_mut() {
+                *sample = T::from_sample(value);
+            }
+        }
+    };
+    let err_fn = |err| {
+        tracing::error!("Test tone stream error: {}", err);
+    };
+    device
+        .build_output_stream(config, data_fn, err_fn, None)
+        .map_err(|e| anyhow!("Failed to build test tone stream: {}", e))
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use super::rms_level;
+
+/// Sensitivity of the voice activity detection used for DTX.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VadParams {
+    /// Minimal RMS level of a voiced frame, in range [0.0, 1.0]
+    pub energy_threshold: f32,
+    /// Quiet frames still sent after the last voiced one, so pauses
+    /// between words and breaths do not close the stream
+    pub hangover_frames: u32,
+}
+
+impl VadParams {
+    /// Roughly -40 dBFS, quiet speech is above and the background noise of
+    /// a typical microphone is below.
+    pub const DEFAULT_ENERGY_THRESHOLD: f32 = 0.01;
+    /// 300 ms of 20 ms frames, longer than a breath between words.
+    pub const DEFAULT_HANGOVER_FRAMES: u32 = 15;
+}
+
+impl Default for VadParams {
+    fn default() -> Self {
+        Self {
+            energy_threshold: Self::DEFAULT_ENERGY_THRESHOLD,
+            hangover_frames: Self::DEFAULT_HANGOVER_FRAMES,
+        }
+    }
+}
+
+/// Decides which frames carry voice and must be sent.
+///
+/// A voiced frame is sent at once, so the starts of words are not clipped,
+/// after it the stream stays open for the hangover frames.
+pub struct VoiceActivityDetector {
+    params: VadParams,
+    // Quiet frames left to send before the stream closes
+    hangover: u32,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(params: VadParams) -> Self {
+        Self {
+            params,
+            hangover: 0,
+        }
+    }
+
+    pub fn params(&self) -> VadParams {
+        self.params
+    }
+
+    /// Registers the next frame, returns true if it should be sent.
+    pub fn is_active(&mut self, samples: &[f32]) -> bool {
+        if rms_level(samples) >= self.params.energy_threshold {
+            self.hangover = self.params.hangover_frames;
+            return true;
+        }
+        if self.hangover > 0 {
+            self.hangover -= 1;
+            return true;
+        }
+        false
+    }
+}
+
+impl Default for VoiceActivityDetector {
+    fn default() -> Self {
+        Self::new(VadParams::default())
+    }
+}
@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use hound::{SampleFormat, WavReader};
+
+use crate::models::{Base64, VoiceMessage};
+
+use super::{AudioConfig, CodecParams, CodecType, Resampler, convert_channels, create_encoder};
+
+/// Longest audio file accepted as a voice message.
+pub const MAX_VOICE_DURATION: Duration = Duration::from_secs(60);
+
+const MIN_SAMPLE_RATE: u32 = 8000;
+const MAX_SAMPLE_RATE: u32 = 192000;
+
+/// Reads interleaved samples of a WAV file with 8 to 32 bit integer or
+/// 32 bit float PCM, mono or stereo.
+pub fn read_wav<R: Read>(reader: R) -> Result<(AudioConfig, Vec<f32>)> {
+    let reader = WavReader::new(reader).map_err(|err| anyhow!("Invalid WAV file: {err}"))?;
+    let spec = reader.spec();
+    if spec.channels == 0 || spec.channels > 2 {
+        bail!(
+            "Only mono and stereo WAV files are supported, got {} channels",
+            spec.channels
+        );
+    }
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&spec.sample_rate) {
+        bail!("Unsupported sample rate: {}Hz", spec.sample_rate);
+    }
+    let frames = reader.duration();
+    if u64::from(frames) * 1000 / u64::from(spec.sample_rate)
+        > MAX_VOICE_DURATION.as_millis() as u64
+    {
+        bail!(
+            "Audio is too long: {}s, at most {}s are allowed",
+            frames / spec.sample_rate,
+            MAX_VOICE_DURATION.as_secs()
+        );
+    }
+    let samples = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, 32) => reader.into_samples::<f32>().collect::<Result<Vec<_>, _>>(),
+        (SampleFormat::Int, bits @ 8..=32) => {
+            let scale = 1.0 / (1u64 << (bits - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|v| v.map(|v| v as f32 * scale))
+                .collect()
+        }
+        (format, bits) => bail!("Unsupported WAV format: {bits} bit {format:?}"),
+    }
+    .map_err(|err| anyhow!("Cannot read WAV file: {err}"))?;
+    if samples.is_empty() {
+        bail!("Audio is empty");
+    }
+    Ok((AudioConfig::new(spec.sample_rate, spec.channels), samples))
+}
+
+/// Resamples the audio to mono at the codec rate and encodes it. Audio
+/// that does not fit into one message is split into several parts.
+pub fn encode_voice(config: AudioConfig, samples: &[f32]) -> Result<Vec<VoiceMessage>> {
+    let codec = CodecType::ADPCM;
+    let params = CodecParams::voice();
+    let mono = convert_channels(samples, config.channels, 1);
+    let samples = if config.sample_rate == params.sample_rate {
+        mono
+    } else {
+        Resampler::new(config.sample_rate, params.sample_rate, 1)?.resample(&mono)?
+    };
+    if samples.is_empty() {
+        bail!("Audio is empty");
+    }
+    let mut encoder = create_encoder(codec, &params)?;
+    let frame_len =
+        (params.sample_rate as u128 * VoiceMessage::FRAME_DURATION.as_millis() / 1000) as usize;
+    let part = |frames, len| VoiceMessage {
+        codec,
+        sample_rate: params.sample_rate,
+        duration_ms: (len as u64 * 1000 / u64::from(params.sample_rate)).max(1) as u32,
+        frames,
+    };
+    let mut parts = Vec::new();
+    let mut frames = Vec::new();
+    let (mut data_len, mut len) = (0, 0);
+    for chunk in samples.chunks(frame_len) {
+        let mut frame = chunk.to_vec();
+        frame.resize(frame_len, 0.0);
+        let data = encoder.encode(&frame)?;
+        if !frames.is_empty() && data_len + data.len() > VoiceMessage::MAX_DATA_LEN {
+            parts.push(part(std::mem::take(&mut frames), len));
+            (data_len, len) = (0, 0);
+        }
+        data_len += data.len();
+        len += chunk.len();
+        frames.push(Base64(data));
+    }
+    parts.push(part(frames, len));
+    Ok(parts)
+}
+
+/// Reads a WAV file and encodes it as a voice message.
+pub fn import_wav(path: &Path) -> Result<Vec<VoiceMessage>> {
+    let file = File::open(path).map_err(|err| anyhow!("Cannot open {}: {err}", path.display()))?;
+    let (config, samples) = read_wav(BufReader::new(file))?;
+    encode_voice(config, &samples)
+}
@@ -0,0 +1,97 @@
+use std::io::Cursor;
+
+use anyhow::anyhow;
+use image::imageops::FilterType;
+use image::io::{Limits, Reader as ImageReader};
+use image::{DynamicImage, ImageFormat, RgbaImage};
+
+/// Side of the square avatar image in pixels.
+pub const AVATAR_SIZE: u32 = 96;
+
+/// Maximum size of an encoded avatar that is stored or exchanged with contacts.
+pub const MAX_AVATAR_BYTES: usize = 32 * 1024;
+
+/// Maximum size of a source image picked by the user.
+pub const MAX_SOURCE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Maximum width and height of a source image.
+const MAX_SOURCE_DIMENSION: u32 = 8192;
+
+/// Maximum width and height of an avatar received from a contact. Senders
+/// normalize avatars, so anything larger is not worth decoding.
+const MAX_REMOTE_DIMENSION: u32 = AVATAR_SIZE;
+
+/// Decodes an image, crops it to a square, downscales it to [`AVATAR_SIZE`]
+/// and encodes it as PNG.
+pub fn normalize_avatar(data: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+    if data.len() > MAX_SOURCE_BYTES {
+        return Err(anyhow!("Avatar image is too large"));
+    }
+    normalize_image(decode(data, MAX_SOURCE_DIMENSION)?)
+}
+
+fn normalize_image(image: DynamicImage) -> Result<Vec<u8>, anyhow::Error> {
+    let side = image.width().min(image.height());
+    let image = if side > AVATAR_SIZE {
+        image.resize_to_fill(AVATAR_SIZE, AVATAR_SIZE, FilterType::Triangle)
+    } else {
+        let x = (image.width() - side) / 2;
+        let y = (image.height() - side) / 2;
+        image.crop_imm(x, y, side, side)
+    };
+    let mut encoded = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Png)
+        .map_err(|e| anyhow!("Failed to encode avatar: {}", e))?;
+    if encoded.len() > MAX_AVATAR_BYTES {
+        return Err(anyhow!("Avatar image is too large"));
+    }
+    Ok(encoded)
+}
+
+/// Validates an avatar received from a contact.
+///
+/// Avatars above [`MAX_AVATAR_BYTES`], larger than [`AVATAR_SIZE`] or that
+/// cannot be decoded are dropped, the rest are normalized.
+pub fn sanitize_avatar(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() > MAX_AVATAR_BYTES {
+        tracing::debug!(len = data.len(), "Dropping oversized avatar");
+        return None;
+    }
+    match decode(data, MAX_REMOTE_DIMENSION).and_then(normalize_image) {
+        Ok(avatar) => Some(avatar),
+        Err(err) => {
+            tracing::debug!(?err, "Dropping invalid avatar");
+            None
+        }
+    }
+}
+
+/// Decodes an avatar and makes pixels outside of the inscribed circle transparent.
+pub fn circle_avatar(data: &[u8]) -> Result<RgbaImage, anyhow::Error> {
+    let mut image = decode(data, MAX_SOURCE_DIMENSION)?.to_rgba8();
+    let (width, height) = image.dimensions();
+    let radius = width.min(height) as f32 / 2.0;
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let dx = x as f32 + 0.5 - cx;
+        let dy = y as f32 + 0.5 - cy;
+        // Anti-alias the edge over one pixel
+        let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+        pixel[3] = (pixel[3] as f32 * coverage) as u8;
+    }
+    Ok(image)
+}
+
+fn decode(data: &[u8], max_dimension: u32) -> Result<DynamicImage, anyhow::Error> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| anyhow!("Failed to read avatar: {}", e))?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(max_dimension);
+    limits.max_image_height = Some(max_dimension);
+    reader.limits(limits);
+    reader
+        .decode()
+        .map_err(|e| anyhow!("Failed to decode avatar: {}", e))
+}
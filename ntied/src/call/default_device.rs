@@ -0,0 +1,34 @@
+/// Reaction of a call to a new system default audio device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefaultDeviceAction {
+    /// The call switches to the new default device.
+    Switch(String),
+    /// The user is asked whether to switch.
+    Offer(String),
+}
+
+impl DefaultDeviceAction {
+    /// Decides how a call using the `current` device, `None` for the default
+    /// one at the call start, reacts to `new_default`.
+    pub fn decide(
+        current: Option<&str>,
+        new_default: Option<&str>,
+        follow_default: bool,
+    ) -> Option<Self> {
+        let name = new_default?;
+        if current == Some(name) {
+            return None;
+        }
+        Some(if follow_default {
+            Self::Switch(name.to_string())
+        } else {
+            Self::Offer(name.to_string())
+        })
+    }
+
+    pub fn device_name(&self) -> &str {
+        match self {
+            Self::Switch(name) | Self::Offer(name) => name,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+
+use crate::audio::{AudioDisabled, CodecType, DeviceType, NoAudioDevice};
+
+/// Why a call is over, passed to [`super::CallListener::on_call_ended`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallEndReason {
+    /// We hung up.
+    LocalHangup,
+    /// The peer hung up.
+    RemoteHangup,
+    /// The call was rejected by either side.
+    Rejected,
+    /// The peer did not answer in time.
+    NoAnswer,
+    /// The connection to the peer was lost and did not come back.
+    NetworkError,
+    /// The peer is already in another call.
+    Busy,
+    /// The call could not start without an audio device.
+    NoAudioDevice(DeviceType),
+    /// The build has no audio support.
+    AudioDisabled,
+    /// The peers share no audio codec, with the codecs of each side.
+    CodecMismatch {
+        local: Vec<CodecType>,
+        remote: Vec<CodecType>,
+    },
+}
+
+impl fmt::Display for CallEndReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallEndReason::LocalHangup => write!(f, "Call ended"),
+            CallEndReason::RemoteHangup => write!(f, "Remote ended call"),
+            CallEndReason::Rejected => write!(f, "Call rejected"),
+            CallEndReason::NoAnswer => write!(f, "No answer"),
+            CallEndReason::NetworkError => write!(f, "Connection lost"),
+            CallEndReason::Busy => write!(f, "Busy"),
+            CallEndReason::NoAudioDevice(device_type) => NoAudioDevice(*device_type).fmt(f),
+            CallEndReason::AudioDisabled => AudioDisabled.fmt(f),
+            CallEndReason::CodecMismatch { local, remote } => write!(
+                f,
+                "No common audio codec, supported here: {}, by the peer: {}",
+                codec_names(local),
+                codec_names(remote)
+            ),
+        }
+    }
+}
+
+fn codec_names(codecs: &[CodecType]) -> String {
+    if codecs.is_empty() {
+        return "none".to_string();
+    }
+    let names: Vec<_> = codecs.iter().map(|v| v.name()).collect();
+    names.join(", ")
+}
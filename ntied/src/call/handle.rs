@@ -1,11 +1,12 @@
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use ntied_transport::Address;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::contact::ContactHandle;
+use crate::audio::CodecParams;
+use crate::contact::{ContactHandle, Usage};
 
 use super::CallListener;
 
@@ -14,10 +15,51 @@ pub enum CallState {
     Idle,
     Calling,
     Ringing,
+    /// Accepted, waiting for the first frame of the peer to play.
+    ConnectingAudio,
     Connected,
+    OnHold,
     Ended,
 }
 
+impl CallState {
+    /// Whether the call was accepted and is not held or ended.
+    pub fn is_established(&self) -> bool {
+        matches!(self, Self::ConnectingAudio | Self::Connected)
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Calling => "calling",
+            Self::Ringing => "ringing",
+            Self::ConnectingAudio => "connecting_audio",
+            Self::Connected => "connected",
+            Self::OnHold => "on_hold",
+            Self::Ended => "ended",
+        }
+    }
+}
+
+/// What the call carries, selects the tuning of the audio codec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CallMode {
+    /// Conversation, speech tuned parameters.
+    #[default]
+    Voice,
+    /// Screen sharing with system audio, music tuned parameters.
+    Presentation,
+}
+
+impl CallMode {
+    pub fn codec_params(self) -> CodecParams {
+        match self {
+            Self::Voice => CodecParams::voice(),
+            Self::Presentation => CodecParams::music(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct CallHandle {
     call_id: Uuid,
@@ -26,6 +68,8 @@ pub struct CallHandle {
     contact_handle: ContactHandle,
     state: Arc<RwLock<CallState>>,
     is_muted: Arc<AtomicBool>,
+    mode: Arc<Mutex<CallMode>>,
+    usage_start: Usage,
     listener: Arc<dyn CallListener>,
 }
 
@@ -37,6 +81,7 @@ impl CallHandle {
         contact_handle: ContactHandle,
         listener: Arc<dyn CallListener>,
     ) -> Self {
+        let usage_start = contact_handle.call_usage();
         Self {
             call_id,
             peer_address,
@@ -44,6 +89,8 @@ impl CallHandle {
             contact_handle,
             state: Arc::new(RwLock::new(CallState::Idle)),
             is_muted: Arc::new(AtomicBool::new(false)),
+            mode: Arc::new(Mutex::new(CallMode::default())),
+            usage_start,
             listener,
         }
     }
@@ -60,10 +107,28 @@ impl CallHandle {
         self.is_incoming
     }
 
+    pub fn with_mode(self, mode: CallMode) -> Self {
+        self.set_mode(mode);
+        self
+    }
+
+    pub fn mode(&self) -> CallMode {
+        *self.mode.lock().unwrap()
+    }
+
+    pub(super) fn set_mode(&self, mode: CallMode) {
+        *self.mode.lock().unwrap() = mode;
+    }
+
     pub fn contact_handle(&self) -> ContactHandle {
         self.contact_handle.clone()
     }
 
+    /// Bytes of call packets exchanged since the call was created.
+    pub fn usage(&self) -> Usage {
+        self.contact_handle.call_usage().since(self.usage_start)
+    }
+
     pub async fn get_state(&self) -> CallState {
         self.state.read().await.clone()
     }
@@ -73,16 +138,22 @@ impl CallHandle {
         *current_state = state.clone();
 
         // Notify listener of state change
-        let state_str = match state {
-            CallState::Idle => "idle",
-            CallState::Calling => "calling",
-            CallState::Ringing => "ringing",
-            CallState::Connected => "connected",
-            CallState::Ended => "ended",
-        };
         self.listener
-            .on_call_state_changed(self.peer_address, state_str)
+            .on_call_state_changed(self.peer_address, state.as_str())
+            .await;
+    }
+
+    /// Changes the state only if it is still `from`, returns whether it changed.
+    pub(super) async fn advance_state(&self, from: CallState, to: CallState) -> bool {
+        let mut current_state = self.state.write().await;
+        if *current_state != from {
+            return false;
+        }
+        *current_state = to.clone();
+        self.listener
+            .on_call_state_changed(self.peer_address, to.as_str())
             .await;
+        true
     }
 
     pub async fn toggle_mute(&self) -> Result<bool, anyhow::Error> {
@@ -1,6 +1,10 @@
 use async_trait::async_trait;
 use ntied_transport::Address;
 
+use super::{AudioDirection, CallEndReason, CallQuality, DefaultDeviceAction};
+use crate::audio::{CodecType, DeviceType};
+use crate::contact::Usage;
+
 #[async_trait]
 pub trait CallListener: Send + Sync {
     async fn on_incoming_call(&self, address: Address);
@@ -9,10 +13,36 @@ pub trait CallListener: Send + Sync {
     async fn on_call_rejected(&self, address: Address);
     /// Called when call is connected. is_muted indicates initial microphone state (always false for new calls)
     async fn on_call_connected(&self, address: Address);
-    async fn on_call_ended(&self, address: Address, reason: &str);
+    async fn on_call_ended(&self, address: Address, reason: CallEndReason);
     async fn on_call_state_changed(&self, address: Address, state: &str);
     async fn on_audio_data_received(&self, address: Address, data: Vec<u8>);
     async fn on_video_frame_received(&self, address: Address, frame: Vec<u8>);
+    /// Called when a second call arrives during an active call and call waiting is enabled.
+    async fn on_call_waiting(&self, address: Address);
+    async fn on_call_held(&self, address: Address);
+    async fn on_call_resumed(&self, address: Address);
+    /// Called when the waiting or held call is over.
+    async fn on_call_waiting_ended(&self, address: Address, reason: CallEndReason);
+    /// Called when voice is detected while the microphone is muted, throttled.
+    async fn on_speaking_while_muted(&self, address: Address);
+    /// Called before `on_call_ended` with bytes used by the call.
+    async fn on_call_summary(&self, address: Address, usage: Usage);
+    /// Called when the audio codec of the call is negotiated.
+    async fn on_call_codec(&self, address: Address, codec: CodecType);
+    /// Called when the quality bucket of incoming audio changes.
+    async fn on_call_quality(&self, address: Address, quality: CallQuality);
+    /// Called when audio of a connected call flows only in one direction.
+    async fn on_one_way_audio_detected(&self, address: Address, direction: AudioDirection);
+    /// Called when audio flows both ways again after a detection.
+    async fn on_one_way_audio_resolved(&self, address: Address);
+    /// Called when the system default device changed during the call, after
+    /// the call switched to it or to offer the switch.
+    async fn on_default_device_changed(
+        &self,
+        address: Address,
+        device_type: DeviceType,
+        action: DefaultDeviceAction,
+    );
 }
 
 pub struct StubListener;
@@ -24,8 +54,25 @@ impl CallListener for StubListener {
     async fn on_call_accepted(&self, _address: Address) {}
     async fn on_call_rejected(&self, _address: Address) {}
     async fn on_call_connected(&self, _address: Address) {}
-    async fn on_call_ended(&self, _address: Address, _reason: &str) {}
+    async fn on_call_ended(&self, _address: Address, _reason: CallEndReason) {}
     async fn on_call_state_changed(&self, _address: Address, _state: &str) {}
     async fn on_audio_data_received(&self, _address: Address, _data: Vec<u8>) {}
     async fn on_video_frame_received(&self, _address: Address, _frame: Vec<u8>) {}
+    async fn on_call_waiting(&self, _address: Address) {}
+    async fn on_call_held(&self, _address: Address) {}
+    async fn on_call_resumed(&self, _address: Address) {}
+    async fn on_call_waiting_ended(&self, _address: Address, _reason: CallEndReason) {}
+    async fn on_speaking_while_muted(&self, _address: Address) {}
+    async fn on_call_summary(&self, _address: Address, _usage: Usage) {}
+    async fn on_call_codec(&self, _address: Address, _codec: CodecType) {}
+    async fn on_call_quality(&self, _address: Address, _quality: CallQuality) {}
+    async fn on_one_way_audio_detected(&self, _address: Address, _direction: AudioDirection) {}
+    async fn on_one_way_audio_resolved(&self, _address: Address) {}
+    async fn on_default_device_changed(
+        &self,
+        _address: Address,
+        _device_type: DeviceType,
+        _action: DefaultDeviceAction,
+    ) {
+    }
 }
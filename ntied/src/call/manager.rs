@@ -1,33 +1,45 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
-use ntied_transport::Address;
+use ntied_transport::{Address, TrafficClass};
 use tokio::sync::{Mutex as TokioMutex, RwLock};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 use crate::audio::{
-    AudioConfig, AudioManager, CaptureStream, CodecManager, CodecType, Decoder, Encoder,
-    PlaybackStream,
+    AudioConfig, AudioDisabled, AudioHost, AudioManager, CaptureStream, CodecCapabilities,
+    CodecManager, CodecParams, CodecType, CpalHost, Decoder, DecoderStats, DeviceChange,
+    DeviceType, Encoder, FramePacer, LoopbackSource as _, MutedSpeechDetector, NetworkQuality,
+    NoAudioDevice, PlaybackStream, ShareMode, StreamBuffer, StreamStats, SystemAudioMixer,
+    SystemAudioMode, forward_system_audio,
 };
-use crate::contact::{ContactHandle, ContactManager};
+use crate::contact::{ContactHandle, ContactManager, Usage};
 use crate::packet::{
     AudioDataPacket, CallAcceptPacket, CallEndPacket, CallPacket, CallRejectPacket,
-    CallStartPacket, CodecAnswerPacket, CodecOfferPacket, VideoDataPacket,
+    CallStartPacket, CodecAnswerPacket, CodecMismatchPacket, CodecOfferPacket, VideoDataPacket,
 };
 
-use super::{CallHandle, CallListener, CallState, StubListener};
+use super::{
+    CallEndReason, CallHandle, CallListener, CallMode, CallQuality, CallState, DefaultDeviceAction,
+    LossEstimator, OneWayAudioDetector, OneWayAudioEvent, ReconnectEvent, ReconnectGrace,
+    StubListener, play_call_audio,
+};
 
 /// Audio state for the active call - only one can exist at a time
 struct AudioState {
     decoder: Arc<Decoder>,
     capture_stream: Arc<TokioMutex<CaptureStream>>,
     playback_stream: Arc<TokioMutex<PlaybackStream>>,
+    capture_buffer: Arc<StreamBuffer>,
+    playback_buffer: Arc<StreamBuffer>,
     capture_task: JoinHandle<()>,
     playback_task: JoinHandle<()>,
     encoder_task: JoinHandle<()>,
+    // Capture of the system audio during screen share
+    system_audio_task: Option<JoinHandle<()>>,
     input_device_name: Option<String>,
     output_device_name: Option<String>,
     codec_type: CodecType,
@@ -38,21 +50,59 @@ impl Drop for AudioState {
         self.capture_task.abort();
         self.playback_task.abort();
         self.encoder_task.abort();
+        if let Some(task) = &self.system_audio_task {
+            task.abort();
+        }
         tracing::debug!("Audio state dropped - all tasks aborted");
     }
 }
 
+/// Network estimate of the active call.
+struct QualityState {
+    loss: LossEstimator,
+    window_start: Instant,
+    // Only the loss is measured, the rest keeps the defaults
+    quality: NetworkQuality,
+    // Quality bucket last passed to the listener
+    reported: Option<CallQuality>,
+}
+
 pub struct CallManager {
     contact_manager: Arc<ContactManager>,
     active_calls: Arc<RwLock<HashMap<Address, CallHandle>>>,
     current_call: Arc<RwLock<Option<CallHandle>>>,
+    // Second call that is either waiting for an answer or on hold
+    secondary_call: Arc<RwLock<Option<CallHandle>>>,
+    call_waiting: AtomicBool,
+    frame_pacing: Arc<AtomicBool>,
+    follow_default_device: AtomicBool,
+    qos_marking: AtomicBool,
     listener: Arc<dyn CallListener>,
     polling_tasks: Arc<TokioMutex<HashMap<Address, JoinHandle<()>>>>,
     audio_state: Arc<TokioMutex<Option<AudioState>>>,
     codec_manager: Arc<CodecManager>,
+    quality_state: Mutex<QualityState>,
+    reconnect: Mutex<ReconnectGrace>,
+    one_way: Mutex<OneWayAudioDetector>,
+    share_mode: Mutex<ShareMode>,
+    // System audio sent during screen share, disabled if None
+    system_audio: Mutex<Option<SystemAudioMode>>,
+    // Microphone gain kept across calls, f32 bits
+    input_gain: AtomicU32,
+    audio_host: Mutex<Arc<dyn AudioHost>>,
+    // Contacts whose calls are answered without user action
+    auto_answer: Mutex<HashSet<Address>>,
+    auto_answer_delay: Mutex<Duration>,
 }
 
 impl CallManager {
+    const QUALITY_WINDOW: Duration = Duration::from_secs(1);
+    const RECONNECT_GRACE: Duration = Duration::from_secs(10);
+    /// Ringing time before a call is answered automatically.
+    pub const AUTO_ANSWER_DELAY: Duration = Duration::from_secs(3);
+    /// Upper bound of the microphone gain, 400%.
+    pub const MAX_INPUT_GAIN: f32 = 4.0;
+
     pub fn new(contact_manager: Arc<ContactManager>) -> Arc<Self> {
         Self::with_listener(contact_manager, Arc::new(StubListener))
     }
@@ -67,10 +117,29 @@ impl CallManager {
             contact_manager,
             active_calls: Arc::new(RwLock::new(HashMap::new())),
             current_call: Arc::new(RwLock::new(None)),
+            secondary_call: Arc::new(RwLock::new(None)),
+            call_waiting: AtomicBool::new(false),
+            frame_pacing: Arc::new(AtomicBool::new(false)),
+            follow_default_device: AtomicBool::new(false),
+            qos_marking: AtomicBool::new(false),
             listener,
             polling_tasks: Arc::new(TokioMutex::new(HashMap::new())),
             audio_state: Arc::new(TokioMutex::new(None)),
             codec_manager,
+            quality_state: Mutex::new(QualityState {
+                loss: LossEstimator::default(),
+                window_start: Instant::now(),
+                quality: NetworkQuality::default(),
+                reported: None,
+            }),
+            reconnect: Mutex::new(ReconnectGrace::new(Self::RECONNECT_GRACE)),
+            one_way: Mutex::new(OneWayAudioDetector::default()),
+            share_mode: Mutex::new(ShareMode::default()),
+            system_audio: Mutex::new(None),
+            input_gain: AtomicU32::new(1.0f32.to_bits()),
+            audio_host: Mutex::new(Arc::new(CpalHost)),
+            auto_answer: Mutex::new(HashSet::new()),
+            auto_answer_delay: Mutex::new(Self::AUTO_ANSWER_DELAY),
         });
 
         // Start main polling coordinator task
@@ -80,8 +149,174 @@ impl CallManager {
         manager
     }
 
+    /// Enable or disable call waiting. When disabled, a second incoming call
+    /// during an active call is rejected as busy.
+    pub fn set_call_waiting(&self, enabled: bool) {
+        self.call_waiting.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_call_waiting_enabled(&self) -> bool {
+        self.call_waiting.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable releasing captured frames to the encoder at a
+    /// steady cadence, for devices that deliver audio in bursts. Applies to
+    /// the current call at once.
+    pub fn set_frame_pacing(&self, enabled: bool) {
+        self.frame_pacing.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_frame_pacing_enabled(&self) -> bool {
+        self.frame_pacing.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable switching the call to a new system default device
+    /// at once, otherwise the switch is offered to the user.
+    pub fn set_follow_default_device(&self, enabled: bool) {
+        self.follow_default_device.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_follow_default_device_enabled(&self) -> bool {
+        self.follow_default_device.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable marking packets as Expedited Forwarding while a
+    /// call has audio. The socket is shared, so messages sent during the
+    /// call are marked too. Applies to the current call at once.
+    pub async fn set_qos_marking(&self, enabled: bool) {
+        self.qos_marking.store(enabled, Ordering::Relaxed);
+        let class = if enabled && self.audio_state.lock().await.is_some() {
+            TrafficClass::Audio
+        } else {
+            TrafficClass::BestEffort
+        };
+        self.contact_manager.set_traffic_class(class).await;
+    }
+
+    pub fn is_qos_marking_enabled(&self) -> bool {
+        self.qos_marking.load(Ordering::Relaxed)
+    }
+
+    /// Set how long a call survives its contact being disconnected.
+    pub fn set_reconnect_grace(&self, grace: Duration) {
+        self.reconnect.lock().unwrap().set_grace(grace);
+    }
+
+    pub fn reconnect_grace(&self) -> Duration {
+        self.reconnect.lock().unwrap().grace()
+    }
+
+    /// Access to audio devices requested by the next calls, shared access
+    /// is used where exclusive one is not available.
+    pub fn set_share_mode(&self, mode: ShareMode) {
+        *self.share_mode.lock().unwrap() = mode;
+    }
+
+    pub fn share_mode(&self) -> ShareMode {
+        *self.share_mode.lock().unwrap()
+    }
+
+    /// Send the system audio in the next presentations, `None` sends only
+    /// the microphone. Requires a loopback device, see
+    /// [`AudioHost::loopback_device`].
+    pub fn set_system_audio(&self, mode: Option<SystemAudioMode>) {
+        *self.system_audio.lock().unwrap() = mode;
+    }
+
+    pub fn system_audio(&self) -> Option<SystemAudioMode> {
+        *self.system_audio.lock().unwrap()
+    }
+
+    /// Answer calls from the contact automatically after a short delay.
+    /// Callers are expected to enable it for verified contacts only.
+    pub fn set_auto_answer(&self, address: Address, enabled: bool) {
+        let mut auto_answer = self.auto_answer.lock().unwrap();
+        if enabled {
+            auto_answer.insert(address);
+        } else {
+            auto_answer.remove(&address);
+        }
+    }
+
+    pub fn is_auto_answer(&self, address: Address) -> bool {
+        self.auto_answer.lock().unwrap().contains(&address)
+    }
+
+    /// Set the ringing time before a call is answered automatically.
+    pub fn set_auto_answer_delay(&self, delay: Duration) {
+        *self.auto_answer_delay.lock().unwrap() = delay;
+    }
+
+    /// Replace the source of audio devices used by calls.
+    pub fn set_audio_host(&self, host: Arc<dyn AudioHost>) {
+        *self.audio_host.lock().unwrap() = host;
+    }
+
+    fn audio_host(&self) -> Arc<dyn AudioHost> {
+        self.audio_host.lock().unwrap().clone()
+    }
+
+    /// Set the microphone gain, it is kept across calls unlike the capture
+    /// volume and applies to the current call immediately.
+    pub async fn set_input_gain(&self, gain: f32) {
+        let gain = gain.clamp(0.0, Self::MAX_INPUT_GAIN);
+        self.input_gain.store(gain.to_bits(), Ordering::Relaxed);
+        let audio = self.audio_state.lock().await;
+        if let Some(state) = audio.as_ref() {
+            state.capture_stream.lock().await.set_gain(gain);
+        }
+    }
+
+    pub fn input_gain(&self) -> f32 {
+        f32::from_bits(self.input_gain.load(Ordering::Relaxed))
+    }
+
+    /// Last network estimate of the call.
+    pub fn network_quality(&self) -> NetworkQuality {
+        self.quality_state.lock().unwrap().quality
+    }
+
+    pub fn call_quality(&self) -> CallQuality {
+        CallQuality::from_network(&self.network_quality())
+    }
+
+    /// Tracks loss of incoming audio and updates the estimate once per second.
+    /// Returns the quality bucket when it differs from the last returned one.
+    fn record_audio_sequence(&self, sequence: u32) -> Option<CallQuality> {
+        let mut state = self.quality_state.lock().unwrap();
+        state.loss.record(sequence);
+        if state.window_start.elapsed() < Self::QUALITY_WINDOW {
+            return None;
+        }
+        state.window_start = Instant::now();
+        state.quality.packet_loss = state.loss.take_loss()?;
+        let bucket = CallQuality::from_network(&state.quality);
+        if state.reported == Some(bucket) {
+            return None;
+        }
+        state.reported = Some(bucket);
+        Some(bucket)
+    }
+
+    fn reset_network_quality(&self) {
+        let mut state = self.quality_state.lock().unwrap();
+        state.loss = LossEstimator::default();
+        state.window_start = Instant::now();
+        state.quality.packet_loss = 0.0;
+        state.reported = None;
+    }
+
     pub async fn start_call(&self, address: Address) -> Result<CallHandle, anyhow::Error> {
-        tracing::info!("Starting call to address: {}", address);
+        self.start_call_with_mode(address, CallMode::Voice).await
+    }
+
+    /// Starts a call with the codec tuned for `mode`.
+    pub async fn start_call_with_mode(
+        &self,
+        address: Address,
+        mode: CallMode,
+    ) -> Result<CallHandle, anyhow::Error> {
+        tracing::info!("Starting {:?} call to address: {}", mode, address);
 
         // Check if already in a call
         let current = self.current_call.read().await;
@@ -91,6 +326,21 @@ impl CallManager {
         }
         drop(current);
 
+        // Fail early instead of ringing the peer when audio can not start
+        if let Err(err) = self.check_audio_devices().await {
+            tracing::warn!(?err, "Cannot start call - audio is unavailable");
+            let reason = if err.is::<AudioDisabled>() {
+                CallEndReason::AudioDisabled
+            } else {
+                let device_type = err
+                    .downcast_ref::<NoAudioDevice>()
+                    .map_or(DeviceType::Input, |e| e.0);
+                CallEndReason::NoAudioDevice(device_type)
+            };
+            self.listener.on_call_ended(address, reason).await;
+            return Err(err);
+        }
+
         // Get contact handle
         let contact_handle = self.contact_manager.connect_contact(address).await;
         if !contact_handle.is_connected() {
@@ -107,7 +357,8 @@ impl CallManager {
             false, // outgoing
             contact_handle.clone(),
             self.listener.clone(),
-        );
+        )
+        .with_mode(mode);
 
         // Store call handle
         let mut calls = self.active_calls.write().await;
@@ -128,7 +379,9 @@ impl CallManager {
         })?;
 
         // Send codec offer
-        let codec_offer = self.codec_manager.create_offer();
+        let codec_offer = self
+            .codec_manager
+            .create_offer_with_params(mode.codec_params());
         let offer_packet = CallPacket::CodecOffer(CodecOfferPacket {
             call_id,
             capabilities: self.codec_manager.capabilities().await,
@@ -161,7 +414,7 @@ impl CallManager {
     }
 
     async fn handle_incoming_call(
-        &self,
+        self: &Arc<Self>,
         address: Address,
         packet: CallStartPacket,
     ) -> Result<(), anyhow::Error> {
@@ -171,10 +424,30 @@ impl CallManager {
             packet.call_id,
         );
 
+        if !AudioManager::is_supported() {
+            tracing::warn!(
+                "Rejecting incoming call from {}, audio is not supported by this build",
+                address
+            );
+            self.reject_incoming_call(address, packet.call_id).await?;
+            self.listener
+                .on_call_ended(address, CallEndReason::AudioDisabled)
+                .await;
+            return Ok(());
+        }
+
         // Check if already in a call
         let current = self.current_call.read().await;
         if let Some(existing_call) = current.as_ref() {
             let state = existing_call.get_state().await;
+            if state.is_established()
+                && existing_call.peer_address() != address
+                && self.is_call_waiting_enabled()
+                && self.secondary_call.read().await.is_none()
+            {
+                drop(current);
+                return self.handle_waiting_call(address, packet).await;
+            }
             if state != CallState::Idle && state != CallState::Ended {
                 tracing::warn!(
                     "Already in a call with state {:?}, rejecting incoming call from {}",
@@ -221,9 +494,234 @@ impl CallManager {
         // Notify listener
         self.listener.on_incoming_call(address).await;
 
+        if self.is_auto_answer(address) {
+            let manager = self.clone();
+            tokio::spawn(async move { manager.auto_answer_call(address, packet.call_id).await });
+        }
+
         Ok(())
     }
 
+    /// Accept the call after the auto-answer delay unless it was answered,
+    /// rejected or replaced in the meantime.
+    async fn auto_answer_call(&self, address: Address, call_id: Uuid) {
+        let delay = *self.auto_answer_delay.lock().unwrap();
+        tokio::time::sleep(delay).await;
+        let Some(call) = self.get_current_call().await else {
+            return;
+        };
+        if call.call_id() != call_id
+            || call.get_state().await != CallState::Ringing
+            || !self.is_auto_answer(address)
+        {
+            return;
+        }
+        tracing::info!("Auto-answering call from {}", address);
+        if let Err(err) = self.accept_call(address).await {
+            tracing::warn!(?err, "Failed to auto-answer call");
+        }
+    }
+
+    async fn handle_waiting_call(
+        &self,
+        address: Address,
+        packet: CallStartPacket,
+    ) -> Result<(), anyhow::Error> {
+        tracing::info!("Call from {} is waiting", address);
+
+        let contact_handle = self.contact_manager.connect_contact(address).await;
+        if !contact_handle.is_connected() {
+            return Err(anyhow!("Contact is not connected"));
+        }
+
+        let call_handle = CallHandle::new(
+            packet.call_id,
+            address,
+            true, // incoming
+            contact_handle,
+            self.listener.clone(),
+        );
+
+        let mut calls = self.active_calls.write().await;
+        calls.insert(address, call_handle.clone());
+        drop(calls);
+
+        let mut secondary = self.secondary_call.write().await;
+        *secondary = Some(call_handle.clone());
+        drop(secondary);
+
+        call_handle.set_state(CallState::Ringing).await;
+
+        self.listener.on_call_waiting(address).await;
+
+        Ok(())
+    }
+
+    /// Put the current call on hold and answer the waiting call.
+    pub async fn accept_waiting_call(&self, address: Address) -> Result<(), anyhow::Error> {
+        tracing::info!("Accepting waiting call from {}", address);
+
+        let waiting = self
+            .secondary_call
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow!("No waiting call"))?;
+        if waiting.peer_address() != address {
+            return Err(anyhow!("Waiting call is not from {}", address));
+        }
+        if waiting.get_state().await != CallState::Ringing {
+            return Err(anyhow!("Call from {} is not waiting", address));
+        }
+
+        let held_address = self.hold_current_call().await?;
+
+        // The waiting call becomes current, the held one takes its slot
+        let mut current = self.current_call.write().await;
+        let mut secondary = self.secondary_call.write().await;
+        std::mem::swap(&mut *current, &mut *secondary);
+        drop(secondary);
+        drop(current);
+
+        self.listener.on_call_held(held_address).await;
+
+        self.accept_call(address).await
+    }
+
+    /// Reject the waiting call, the current call is not affected.
+    pub async fn reject_waiting_call(&self, address: Address) -> Result<(), anyhow::Error> {
+        tracing::info!("Rejecting waiting call from {}", address);
+
+        let call_handle = self
+            .take_secondary_call(address)
+            .await
+            .ok_or_else(|| anyhow!("No waiting call from {}", address))?;
+
+        let packet = CallPacket::Reject(CallRejectPacket {
+            call_id: call_handle.call_id(),
+        });
+        call_handle
+            .contact_handle()
+            .send_call_packet(packet)
+            .await
+            .map_err(|e| anyhow!("Failed to send reject packet: {}", e))?;
+
+        self.listener
+            .on_call_waiting_ended(address, CallEndReason::Rejected)
+            .await;
+
+        Ok(())
+    }
+
+    /// Put the current call on hold and resume the held one.
+    pub async fn swap_calls(&self) -> Result<(), anyhow::Error> {
+        let held = self
+            .secondary_call
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow!("No held call"))?;
+        if held.get_state().await != CallState::OnHold {
+            return Err(anyhow!("Call with {} is not on hold", held.peer_address()));
+        }
+
+        let held_address = self.hold_current_call().await?;
+
+        let mut current = self.current_call.write().await;
+        let mut secondary = self.secondary_call.write().await;
+        std::mem::swap(&mut *current, &mut *secondary);
+        drop(secondary);
+        drop(current);
+
+        self.listener.on_call_held(held_address).await;
+
+        self.resume_current_call().await
+    }
+
+    pub async fn get_secondary_call(&self) -> Option<CallHandle> {
+        self.secondary_call.read().await.clone()
+    }
+
+    /// Stops audio of the current call and marks it as held.
+    async fn hold_current_call(&self) -> Result<Address, anyhow::Error> {
+        let current = self.current_call.read().await;
+        let call_handle = current.as_ref().ok_or_else(|| anyhow!("No current call"))?;
+        let state = call_handle.get_state().await;
+        if !state.is_established() {
+            return Err(anyhow!("Current call is not connected: {:?}", state));
+        }
+        let address = call_handle.peer_address();
+        call_handle.set_state(CallState::OnHold).await;
+        drop(current);
+
+        let mut audio = self.audio_state.lock().await;
+        if audio.take().is_some() {
+            tracing::debug!("Audio state stopped for held call with {}", address);
+        }
+
+        Ok(address)
+    }
+
+    /// Restarts audio of the current call after it was held.
+    async fn resume_current_call(&self) -> Result<(), anyhow::Error> {
+        let current = self.current_call.read().await;
+        let call_handle = current.as_ref().ok_or_else(|| anyhow!("No current call"))?;
+        let address = call_handle.peer_address();
+        drop(current);
+
+        self.listener.on_call_resumed(address).await;
+
+        self.connect_call_audio().await;
+
+        tracing::info!("Call with {} resumed", address);
+        Ok(())
+    }
+
+    /// Removes the waiting or held call with the address.
+    async fn take_secondary_call(&self, address: Address) -> Option<CallHandle> {
+        let mut secondary = self.secondary_call.write().await;
+        if secondary.as_ref().map(|c| c.peer_address()) != Some(address) {
+            return None;
+        }
+        let call_handle = secondary.take()?;
+        drop(secondary);
+
+        call_handle.set_state(CallState::Ended).await;
+
+        let mut calls = self.active_calls.write().await;
+        calls.remove(&address);
+        drop(calls);
+
+        Some(call_handle)
+    }
+
+    /// Makes the waiting or held call current once the current call is over.
+    async fn promote_secondary_call(&self) {
+        let mut current = self.current_call.write().await;
+        if current.is_some() {
+            return;
+        }
+        let Some(call_handle) = self.secondary_call.write().await.take() else {
+            return;
+        };
+        *current = Some(call_handle.clone());
+        drop(current);
+
+        match call_handle.get_state().await {
+            CallState::Ringing => {
+                self.listener
+                    .on_incoming_call(call_handle.peer_address())
+                    .await;
+            }
+            CallState::OnHold => {
+                if let Err(e) = self.resume_current_call().await {
+                    tracing::error!("Failed to resume held call: {}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+
     pub async fn accept_call(&self, address: Address) -> Result<(), anyhow::Error> {
         tracing::info!("Accepting call from {}", address);
 
@@ -241,6 +739,7 @@ impl CallManager {
 
         let call_id = call_handle.call_id();
         let contact_handle = call_handle.contact_handle().clone();
+        let mode = call_handle.mode();
 
         drop(current);
 
@@ -252,7 +751,9 @@ impl CallManager {
             .map_err(|e| anyhow!("Failed to send accept packet: {}", e))?;
 
         // Send codec offer
-        let codec_offer = self.codec_manager.create_offer();
+        let codec_offer = self
+            .codec_manager
+            .create_offer_with_params(mode.codec_params());
         let offer_packet = CallPacket::CodecOffer(CodecOfferPacket {
             call_id,
             capabilities: self.codec_manager.capabilities().await,
@@ -265,17 +766,7 @@ impl CallManager {
             .map_err(|e| anyhow!("Failed to send codec offer: {}", e))?;
 
         // Start audio for this call
-        if let Err(e) = self.start_audio_for_call().await {
-            tracing::error!("Failed to start audio for call: {}", e);
-        } else {
-            tracing::debug!("Audio started successfully for accepted call");
-        }
-
-        let current = self.current_call.read().await;
-        if let Some(call_handle) = current.as_ref() {
-            call_handle.set_state(CallState::Connected).await;
-        }
-        drop(current);
+        self.connect_call_audio().await;
 
         // Notify listener that call was accepted and is now connected
         self.listener.on_call_accepted(address).await;
@@ -313,7 +804,11 @@ impl CallManager {
 
         // Notify listener
         self.listener.on_call_rejected(address).await;
-        self.listener.on_call_ended(address, "Call rejected").await;
+        self.listener
+            .on_call_ended(address, CallEndReason::Rejected)
+            .await;
+
+        self.promote_secondary_call().await;
 
         tracing::info!("Call rejected from {}", address);
         Ok(())
@@ -340,6 +835,19 @@ impl CallManager {
     pub async fn end_call(&self, address: Address) -> Result<(), anyhow::Error> {
         tracing::info!("Ending call with {}", address);
 
+        if let Some(call_handle) = self.take_secondary_call(address).await {
+            let packet = CallPacket::End(CallEndPacket {
+                call_id: call_handle.call_id(),
+            });
+            if let Err(e) = call_handle.contact_handle().send_call_packet(packet).await {
+                tracing::warn!("Failed to send end packet: {}", e);
+            }
+            self.listener
+                .on_call_waiting_ended(address, CallEndReason::LocalHangup)
+                .await;
+            return Ok(());
+        }
+
         let current = self.current_call.read().await;
         let call_handle = current.as_ref().ok_or_else(|| anyhow!("No current call"))?;
 
@@ -357,17 +865,40 @@ impl CallManager {
         if let Err(e) = contact_handle.send_call_packet(packet).await {
             tracing::warn!("Failed to send end packet: {}", e);
         }
+        let usage = self.call_bytes().await;
 
         // Cleanup
         self.cleanup_call(address).await;
 
         // Notify listener
-        self.listener.on_call_ended(address, "Call ended").await;
+        if let Some(usage) = usage {
+            self.listener.on_call_summary(address, usage).await;
+        }
+        self.listener
+            .on_call_ended(address, CallEndReason::LocalHangup)
+            .await;
+
+        self.promote_secondary_call().await;
 
         tracing::info!("Call ended with {}", address);
         Ok(())
     }
 
+    /// End the secondary and the current call, the peers receive the end packet.
+    pub async fn shutdown(&self) {
+        for call in [
+            self.get_secondary_call().await,
+            self.get_current_call().await,
+        ] {
+            let Some(call) = call else {
+                continue;
+            };
+            if let Err(e) = self.end_call(call.peer_address()).await {
+                tracing::warn!("Failed to end call on shutdown: {}", e);
+            }
+        }
+    }
+
     async fn handle_call_accepted(
         &self,
         address: Address,
@@ -378,13 +909,10 @@ impl CallManager {
         let current = self.current_call.read().await;
         if let Some(call_handle) = current.as_ref() {
             if call_handle.peer_address() == address {
-                call_handle.set_state(CallState::Connected).await;
                 drop(current);
 
                 // Start audio for this call
-                if let Err(e) = self.start_audio_for_call().await {
-                    tracing::error!("Failed to start audio for call: {}", e);
-                }
+                self.connect_call_audio().await;
 
                 self.listener.on_call_connected(address).await;
             }
@@ -402,7 +930,10 @@ impl CallManager {
 
         self.cleanup_call(address).await;
         self.listener.on_call_rejected(address).await;
-        self.listener.on_call_ended(address, "Call rejected").await;
+        self.listener
+            .on_call_ended(address, CallEndReason::Rejected)
+            .await;
+        self.promote_secondary_call().await;
 
         Ok(())
     }
@@ -414,10 +945,25 @@ impl CallManager {
     ) -> Result<(), anyhow::Error> {
         tracing::info!("Call ended by {}", address);
 
+        if self.take_secondary_call(address).await.is_some() {
+            self.listener
+                .on_call_waiting_ended(address, CallEndReason::RemoteHangup)
+                .await;
+            return Ok(());
+        }
+
+        let usage = match self.get_current_call().await {
+            Some(call) if call.peer_address() == address => Some(call.usage()),
+            _ => None,
+        };
         self.cleanup_call(address).await;
+        if let Some(usage) = usage {
+            self.listener.on_call_summary(address, usage).await;
+        }
         self.listener
-            .on_call_ended(address, "Remote ended call")
+            .on_call_ended(address, CallEndReason::RemoteHangup)
             .await;
+        self.promote_secondary_call().await;
 
         Ok(())
     }
@@ -452,7 +998,22 @@ impl CallManager {
         drop(current);
 
         // Create answer based on their capabilities
-        let answer = self.codec_manager.create_answer(&packet.capabilities)?;
+        let answer = match self.codec_manager.create_answer(&packet.capabilities) {
+            Ok(answer) => answer,
+            Err(e) => {
+                tracing::warn!("Codec negotiation with {} failed: {}", address, e);
+                let mismatch_packet = CallPacket::CodecMismatch(CodecMismatchPacket {
+                    call_id,
+                    codecs: self.codec_manager.capabilities().await.codecs,
+                });
+                if let Err(e) = contact_handle.send_call_packet(mismatch_packet).await {
+                    tracing::warn!("Failed to send codec mismatch packet: {}", e);
+                }
+                self.end_codec_mismatch(address, packet.capabilities.codecs)
+                    .await;
+                return Ok(());
+            }
+        };
 
         // Send codec answer
         let answer_packet = CallPacket::CodecAnswer(CodecAnswerPacket {
@@ -466,6 +1027,7 @@ impl CallManager {
             .map_err(|e| anyhow!("Failed to send codec answer: {}", e))?;
 
         tracing::info!("Codec negotiation complete, using: {:?}", answer.codec);
+        self.listener.on_call_codec(address, answer.codec).await;
 
         Ok(())
     }
@@ -481,10 +1043,51 @@ impl CallManager {
             packet.negotiated_codec.codec
         );
 
-        // Just log it - codec is already set when creating AudioState
+        // Codec is already set when creating AudioState, only report it
+        let current = self.current_call.read().await;
+        let is_current = current
+            .as_ref()
+            .is_some_and(|c| c.peer_address() == address && c.call_id() == packet.call_id);
+        drop(current);
+        if is_current {
+            self.listener
+                .on_call_codec(address, packet.negotiated_codec.codec)
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn handle_codec_mismatch(
+        &self,
+        address: Address,
+        packet: CodecMismatchPacket,
+    ) -> Result<(), anyhow::Error> {
+        let is_current = self
+            .get_current_call()
+            .await
+            .is_some_and(|c| c.peer_address() == address && c.call_id() == packet.call_id);
+        if !is_current {
+            return Ok(());
+        }
+        tracing::warn!("Peer {} shares no codec with us", address);
+        self.end_codec_mismatch(address, packet.codecs).await;
         Ok(())
     }
 
+    /// Ends the current call with the peer that supports none of our codecs.
+    async fn end_codec_mismatch(&self, address: Address, remote: Vec<CodecType>) {
+        let usage = self.call_bytes().await;
+        self.cleanup_call(address).await;
+        if let Some(usage) = usage {
+            self.listener.on_call_summary(address, usage).await;
+        }
+        let local = self.codec_manager.capabilities().await.codecs;
+        self.listener
+            .on_call_ended(address, CallEndReason::CodecMismatch { local, remote })
+            .await;
+        self.promote_secondary_call().await;
+    }
+
     async fn handle_audio_data(
         &self,
         address: Address,
@@ -500,6 +1103,9 @@ impl CallManager {
             return Ok(());
         }
         drop(current);
+        if let Some(quality) = self.record_audio_sequence(packet.sequence) {
+            self.listener.on_call_quality(address, quality).await;
+        }
 
         // Get audio state and send packet to decoder
         let audio = self.audio_state.lock().await;
@@ -558,6 +1164,13 @@ impl CallManager {
             if audio.take().is_some() {
                 tracing::debug!("Audio state stopped for address {}", address);
             }
+            drop(audio);
+            if self.contact_manager.traffic_class() != TrafficClass::BestEffort {
+                self.contact_manager
+                    .set_traffic_class(TrafficClass::BestEffort)
+                    .await;
+            }
+            self.reset_network_quality();
         }
 
         let mut calls = self.active_calls.write().await;
@@ -576,6 +1189,27 @@ impl CallManager {
         self.current_call.read().await.clone()
     }
 
+    /// Bytes used by the current call.
+    pub async fn call_bytes(&self) -> Option<Usage> {
+        let current = self.current_call.read().await;
+        current.as_ref().map(|call| call.usage())
+    }
+
+    /// Playback counters of the current call audio.
+    pub async fn decoder_stats(&self) -> Option<DecoderStats> {
+        let audio = self.audio_state.lock().await;
+        audio.as_ref().map(|state| state.decoder.stats())
+    }
+
+    /// Audio buffered by the capture and playback devices of the current call.
+    pub async fn stream_stats(&self) -> Option<StreamStats> {
+        let audio = self.audio_state.lock().await;
+        audio.as_ref().map(|state| StreamStats {
+            capture: state.capture_buffer.stats(),
+            playback: state.playback_buffer.stats(),
+        })
+    }
+
     pub async fn is_in_call(&self) -> bool {
         let current = self.current_call.read().await;
         if let Some(call) = current.as_ref() {
@@ -646,6 +1280,52 @@ impl CallManager {
         Ok(())
     }
 
+    /// Reacts to a change of audio devices during a call. A new system
+    /// default device is switched to or offered to the listener, returns
+    /// what was done.
+    pub async fn handle_device_change(
+        &self,
+        change: &DeviceChange,
+    ) -> Result<Option<DefaultDeviceAction>, anyhow::Error> {
+        let DeviceChange::DefaultChanged(device_type, new_default) = change else {
+            return Ok(None);
+        };
+        let Some(address) = self
+            .current_call
+            .read()
+            .await
+            .as_ref()
+            .map(|c| c.peer_address())
+        else {
+            return Ok(None);
+        };
+        let current = match self.audio_state.lock().await.as_ref() {
+            Some(state) => match device_type {
+                DeviceType::Input => state.input_device_name.clone(),
+                DeviceType::Output => state.output_device_name.clone(),
+            },
+            None => return Ok(None),
+        };
+        let Some(action) = DefaultDeviceAction::decide(
+            current.as_deref(),
+            new_default.as_deref(),
+            self.is_follow_default_device_enabled(),
+        ) else {
+            return Ok(None);
+        };
+        if let DefaultDeviceAction::Switch(name) = &action {
+            tracing::info!(?device_type, name, "Following the system default device");
+            match device_type {
+                DeviceType::Input => self.switch_input_device(Some(name.clone())).await?,
+                DeviceType::Output => self.switch_output_device(Some(name.clone())).await?,
+            }
+        }
+        self.listener
+            .on_default_device_changed(address, *device_type, action.clone())
+            .await;
+        Ok(Some(action))
+    }
+
     pub async fn switch_output_device(
         &self,
         device_name: Option<String>,
@@ -682,6 +1362,90 @@ impl CallManager {
         Ok(())
     }
 
+    /// Codecs available in this build in preference order.
+    pub async fn codec_capabilities(&self) -> CodecCapabilities {
+        self.codec_manager.capabilities().await
+    }
+
+    pub fn preferred_codec(&self) -> CodecType {
+        self.codec_manager.preferred_codec()
+    }
+
+    /// Sets the codec of outgoing audio of the next calls, returns false if
+    /// the codec is not available in this build.
+    pub fn set_preferred_codec(&self, codec: CodecType) -> bool {
+        self.codec_manager.set_preferred_codec(codec)
+    }
+
+    /// Limits the codecs offered in the next calls, in preference order.
+    /// Returns false if the list is empty or has a codec not available in
+    /// this build.
+    pub fn set_codecs(&self, codecs: &[CodecType]) -> bool {
+        self.codec_manager.set_codecs(codecs)
+    }
+
+    /// Mode of the current call.
+    pub async fn call_mode(&self) -> Option<CallMode> {
+        let current = self.current_call.read().await;
+        current.as_ref().map(|v| v.mode())
+    }
+
+    /// Codec parameters of the current call.
+    pub async fn codec_params(&self) -> Option<CodecParams> {
+        self.call_mode().await.map(CallMode::codec_params)
+    }
+
+    /// Switches the current call to `mode`, the encoder is recreated with
+    /// the parameters of the mode and the codec is renegotiated.
+    pub async fn set_call_mode(&self, mode: CallMode) -> Result<(), anyhow::Error> {
+        let current = self.current_call.read().await;
+        let call_handle = current.as_ref().ok_or_else(|| anyhow!("No active call"))?;
+        if call_handle.mode() == mode {
+            return Ok(());
+        }
+        call_handle.set_mode(mode);
+
+        let call_id = call_handle.call_id();
+        let contact_handle = call_handle.contact_handle().clone();
+        let call_handle_clone = call_handle.clone();
+        drop(current);
+
+        let mut audio = self.audio_state.lock().await;
+        if let Some(old_state) = audio.take() {
+            let input_device_name = old_state.input_device_name.clone();
+            let output_device_name = old_state.output_device_name.clone();
+            let codec_type = old_state.codec_type;
+
+            drop(old_state);
+            drop(audio);
+
+            tracing::info!("Switching call mode to {:?}", mode);
+            self.create_audio_state(
+                call_id,
+                codec_type,
+                input_device_name,
+                output_device_name,
+                contact_handle.clone(),
+                call_handle_clone,
+            )
+            .await?;
+        }
+
+        let codec_offer = self
+            .codec_manager
+            .create_offer_with_params(mode.codec_params());
+        let offer_packet = CallPacket::CodecOffer(CodecOfferPacket {
+            call_id,
+            capabilities: self.codec_manager.capabilities().await,
+            preferred_codec: codec_offer,
+        });
+        contact_handle
+            .send_call_packet(offer_packet)
+            .await
+            .map_err(|e| anyhow!("Failed to send codec offer: {}", e))?;
+        Ok(())
+    }
+
     pub async fn set_playback_volume(&self, volume: f32) -> Result<(), anyhow::Error> {
         let audio = self.audio_state.lock().await;
         if let Some(state) = audio.as_ref() {
@@ -726,6 +1490,22 @@ impl CallManager {
         }
     }
 
+    /// Starts audio of the current call, the call stays connecting until the
+    /// first frame of the peer plays. Without audio there is nothing to wait
+    /// for and the call is connected right away.
+    async fn connect_call_audio(&self) {
+        let Some(call_handle) = self.get_current_call().await else {
+            return;
+        };
+        call_handle.set_state(CallState::ConnectingAudio).await;
+        if let Err(e) = self.start_audio_for_call().await {
+            tracing::error!("Failed to start audio for call: {}", e);
+            call_handle
+                .advance_state(CallState::ConnectingAudio, CallState::Connected)
+                .await;
+        }
+    }
+
     async fn start_audio_for_call(&self) -> Result<(), anyhow::Error> {
         tracing::info!("=== Starting audio for call ===");
 
@@ -744,8 +1524,8 @@ impl CallManager {
             peer_address
         );
 
-        // Use default codec (ADPCM)
-        let codec_type = CodecType::ADPCM;
+        // Peers decode any codec of the build, send with the preferred one
+        let codec_type = self.codec_manager.preferred_codec();
 
         // Create audio state with default devices
         self.create_audio_state(
@@ -762,6 +1542,15 @@ impl CallManager {
         Ok(())
     }
 
+    async fn check_audio_devices(&self) -> Result<(), anyhow::Error> {
+        if !AudioManager::is_supported() {
+            return Err(AudioDisabled.into());
+        }
+        AudioManager::get_input_device_from(self.audio_host(), None).await?;
+        AudioManager::get_output_device_from(self.audio_host(), None).await?;
+        Ok(())
+    }
+
     async fn create_audio_state(
         &self,
         call_id: Uuid,
@@ -775,32 +1564,45 @@ impl CallManager {
 
         // Get audio devices
         tracing::debug!("Getting audio input device: {:?}", input_device_name);
-        let input_device = AudioManager::get_input_device(input_device_name.clone()).await?;
+        let input_device =
+            AudioManager::get_input_device_from(self.audio_host(), input_device_name.clone())
+                .await?;
         tracing::debug!("Getting audio output device: {:?}", output_device_name);
-        let output_device = AudioManager::get_output_device(output_device_name.clone()).await?;
+        let output_device =
+            AudioManager::get_output_device_from(self.audio_host(), output_device_name.clone())
+                .await?;
 
         // Create capture stream
         tracing::debug!("Creating capture stream");
-        let capture_stream = CaptureStream::new(input_device, 1.0).await?;
+        let share_mode = self.share_mode();
+        let capture_stream = CaptureStream::with_share_mode(input_device, 1.0, share_mode).await?;
         let source_config =
             AudioConfig::new(capture_stream.sample_rate(), capture_stream.channels());
+        let capture_buffer = capture_stream.buffer();
+        capture_stream.set_gain(self.input_gain());
+        let capture_share_mode = capture_stream.share_mode();
         let capture_stream = Arc::new(TokioMutex::new(capture_stream));
         tracing::info!(
-            "Capture stream created: {}Hz, {} channels",
+            "Capture stream created: {}Hz, {} channels, {:?} mode",
             source_config.sample_rate,
-            source_config.channels
+            source_config.channels,
+            capture_share_mode
         );
 
         // Create playback stream
         tracing::debug!("Creating playback stream");
-        let playback_stream = PlaybackStream::new(output_device, 1.0).await?;
+        let playback_stream =
+            PlaybackStream::with_share_mode(output_device, 1.0, share_mode).await?;
         let target_config =
             AudioConfig::new(playback_stream.sample_rate(), playback_stream.channels());
+        let playback_buffer = playback_stream.buffer();
+        let playback_share_mode = playback_stream.share_mode();
         let playback_stream = Arc::new(TokioMutex::new(playback_stream));
         tracing::info!(
-            "Playback stream created: {}Hz, {} channels",
+            "Playback stream created: {}Hz, {} channels, {:?} mode",
             target_config.sample_rate,
-            target_config.channels
+            target_config.channels,
+            playback_share_mode
         );
 
         // ===== AUDIO CHANNEL CONVERSION ARCHITECTURE =====
@@ -819,7 +1621,7 @@ impl CallManager {
         // 4. Decoder responsibilities:
         //    - Input: AudioDataPacket from REMOTE peer (with channels field)
         //    - Decodes using AudioDataPacket.channels (from REMOTE source)
-        //    - Converts to target_config.channels (LOCAL speaker)
+        //    - Converts to target_config.channels (LOCAL speaker) with convert_channels
         //    - Handles dynamic channel changes from remote peer
         //
         // SUPPORTED USE CASES (Remote → Local):
@@ -845,7 +1647,11 @@ impl CallManager {
         );
 
         // Encoder: Uses LOCAL microphone config to determine encoding
-        let encoder = Arc::new(Encoder::new(source_config, codec_type));
+        let encoder = Arc::new(Encoder::with_params(
+            source_config,
+            codec_type,
+            call_handle.mode().codec_params(),
+        ));
 
         // Decoder: Will determine codec channels from REMOTE peer's packets
         // Only needs to know LOCAL speaker config for final output conversion
@@ -860,13 +1666,26 @@ impl CallManager {
             target_config.channels
         );
 
+        // System audio is mixed into captured frames during screen share
+        let system_audio = match self.system_audio() {
+            Some(mode) if call_handle.mode() == CallMode::Presentation => {
+                self.start_system_audio(mode, source_config).await
+            }
+            _ => None,
+        };
+        let (system_mixer, system_audio_task) = system_audio.unzip();
+
         // Start capture task: capture -> encoder
         let encoder_clone = encoder.clone();
         let capture_stream_for_task = capture_stream.clone();
         let call_handle_for_capture = call_handle.clone();
+        let listener = self.listener.clone();
+        let frame_pacing = self.frame_pacing.clone();
         let capture_task = tokio::spawn(async move {
             tracing::info!("Capture task started");
             let mut frame_count = 0u64;
+            let mut muted_speech = MutedSpeechDetector::new();
+            let mut pacer = FramePacer::default();
             loop {
                 let frame = {
                     let mut stream = capture_stream_for_task.lock().await;
@@ -888,7 +1707,25 @@ impl CallManager {
                         if frame_count % 100 == 0 {
                             tracing::debug!("Microphone muted, sending silence");
                         }
+                        // Check the signal before it is silenced
+                        if muted_speech.process(&frame) {
+                            listener
+                                .on_speaking_while_muted(call_handle_for_capture.peer_address())
+                                .await;
+                        }
                         frame.samples = vec![0.0f32; frame.samples.len()];
+                    } else {
+                        muted_speech.reset();
+                    }
+
+                    if let Some(ref mixer) = system_mixer {
+                        mixer.lock().unwrap().mix(&mut frame);
+                    }
+
+                    if frame_pacing.load(Ordering::Relaxed) {
+                        pacer.pace(&frame).await;
+                    } else {
+                        pacer.reset();
                     }
 
                     if let Err(e) = encoder_clone.send_frame(frame).await {
@@ -932,34 +1769,25 @@ impl CallManager {
         // Start playback task: decoder -> playback
         let decoder_clone = decoder.clone();
         let playback_stream_for_task = playback_stream.clone();
+        let playback_call_handle = call_handle.clone();
         let playback_task = tokio::spawn(async move {
-            tracing::info!("Playback task started");
-            let mut frame_count = 0u64;
-            while let Some(frame) = decoder_clone.recv_frame().await {
-                frame_count += 1;
-                if frame_count % 100 == 0 {
-                    tracing::debug!(
-                        "Playing audio frame #{}, samples: {}",
-                        frame_count,
-                        frame.samples.len()
-                    );
-                }
-                let mut stream = playback_stream_for_task.lock().await;
-                if let Err(e) = stream.send(frame).await {
-                    tracing::error!("Failed to send frame to playback: {}", e);
-                    break;
-                }
-            }
-            tracing::warn!("Playback task ended after {} frames", frame_count);
+            play_call_audio(&decoder_clone, &playback_call_handle, |frame| {
+                let stream = playback_stream_for_task.clone();
+                async move { stream.lock().await.send(frame).await }
+            })
+            .await;
         });
 
         let audio_state = AudioState {
             decoder,
             capture_stream,
             playback_stream,
+            capture_buffer,
+            playback_buffer,
             capture_task,
             playback_task,
             encoder_task,
+            system_audio_task,
             input_device_name,
             output_device_name,
             codec_type,
@@ -967,10 +1795,50 @@ impl CallManager {
 
         let mut audio = self.audio_state.lock().await;
         *audio = Some(audio_state);
+        drop(audio);
+
+        if self.is_qos_marking_enabled()
+            && !self
+                .contact_manager
+                .set_traffic_class(TrafficClass::Audio)
+                .await
+        {
+            tracing::debug!("Call packets are sent unmarked");
+        }
 
         Ok(())
     }
 
+    /// Starts the capture of the system audio mixed into frames of
+    /// `target_config`, `None` if the platform has no loopback device.
+    async fn start_system_audio(
+        &self,
+        mode: SystemAudioMode,
+        target_config: AudioConfig,
+    ) -> Option<(Arc<Mutex<SystemAudioMixer>>, JoinHandle<()>)> {
+        let source = match AudioManager::get_loopback_device_from(self.audio_host()).await {
+            Ok(device) => CaptureStream::new(device, 1.0).await,
+            Err(err) => Err(err),
+        };
+        let source = match source {
+            Ok(source) => source,
+            Err(err) => {
+                tracing::warn!(%err, "System audio is not available, sending the microphone only");
+                return None;
+            }
+        };
+        let mixer = match SystemAudioMixer::new(mode, source.config(), target_config) {
+            Ok(mixer) => Arc::new(Mutex::new(mixer)),
+            Err(err) => {
+                tracing::warn!(%err, "Cannot mix system audio");
+                return None;
+            }
+        };
+        tracing::info!("Sending system audio, {:?} mode", mode);
+        let task = tokio::spawn(forward_system_audio(Box::new(source), mixer.clone()));
+        Some((mixer, task))
+    }
+
     async fn manage_polling_tasks(self: Arc<Self>) {
         // Check contacts every second to start/stop polling tasks
         let mut interval = tokio::time::interval(Duration::from_secs(1));
@@ -978,6 +1846,9 @@ impl CallManager {
         loop {
             interval.tick().await;
 
+            self.check_call_connection().await;
+            self.check_one_way_audio().await;
+
             // Get current contacts
             let contacts = self.contact_manager.list_contacts().await;
             let mut tasks = self.polling_tasks.lock().await;
@@ -1021,6 +1892,80 @@ impl CallManager {
         }
     }
 
+    /// Ends the current call when its contact stays disconnected longer than
+    /// the reconnect grace period, restarts the audio when it reconnects in time.
+    async fn check_call_connection(&self) {
+        let Some(call) = self.get_current_call().await else {
+            return;
+        };
+        if !call.get_state().await.is_established() {
+            return;
+        }
+        let address = call.peer_address();
+        let connected = call.contact_handle().is_connected();
+        let event =
+            self.reconnect
+                .lock()
+                .unwrap()
+                .update(call.call_id(), connected, Instant::now());
+        match event {
+            ReconnectEvent::None => {}
+            ReconnectEvent::Interrupted => {
+                tracing::warn!(
+                    "Call with {} lost connection, waiting for reconnect",
+                    address
+                );
+            }
+            ReconnectEvent::Resumed => {
+                tracing::info!("Call with {} resumed after reconnect", address);
+                if let Err(e) = self.start_audio_for_call().await {
+                    tracing::error!("Failed to restart audio for call: {}", e);
+                }
+            }
+            ReconnectEvent::Expired => {
+                tracing::warn!("Call with {} did not reconnect in time", address);
+                let usage = call.usage();
+                self.cleanup_call(address).await;
+                self.listener.on_call_summary(address, usage).await;
+                self.listener
+                    .on_call_ended(address, CallEndReason::NetworkError)
+                    .await;
+                self.promote_secondary_call().await;
+            }
+        }
+    }
+
+    /// Warns when call packets flow only in one direction for a while.
+    async fn check_one_way_audio(&self) {
+        let Some(call) = self.get_current_call().await else {
+            return;
+        };
+        if !call.get_state().await.is_established()
+            || self.reconnect.lock().unwrap().is_interrupted()
+        {
+            return;
+        }
+        let address = call.peer_address();
+        let event =
+            self.one_way
+                .lock()
+                .unwrap()
+                .update(call.call_id(), call.usage(), Instant::now());
+        match event {
+            None => {}
+            Some(OneWayAudioEvent::Detected(direction)) => {
+                tracing::warn!("Call with {} has one-way audio: {:?}", address, direction);
+                self.listener
+                    .on_one_way_audio_detected(address, direction)
+                    .await;
+            }
+            Some(OneWayAudioEvent::Resolved) => {
+                tracing::info!("Call with {} has two-way audio again", address);
+                self.listener.on_one_way_audio_resolved(address).await;
+            }
+        }
+    }
+
     async fn poll_contact_packets(
         self: Arc<Self>,
         address: Address,
@@ -1071,7 +2016,7 @@ impl CallManager {
     }
 
     async fn process_call_packet(
-        &self,
+        self: &Arc<Self>,
         address: Address,
         packet: CallPacket,
     ) -> Result<(), anyhow::Error> {
@@ -1084,6 +2029,11 @@ impl CallManager {
             CallPacket::VideoData(p) => self.handle_video_frame(address, p).await,
             CallPacket::CodecOffer(p) => self.handle_codec_offer(address, p).await,
             CallPacket::CodecAnswer(p) => self.handle_codec_answer(address, p).await,
+            CallPacket::CodecMismatch(p) => self.handle_codec_mismatch(address, p).await,
+            CallPacket::Unknown(p) => {
+                tracing::debug!(tag = p.tag, "Ignoring unsupported call packet");
+                Ok(())
+            }
         }
     }
 }
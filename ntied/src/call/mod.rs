@@ -1,7 +1,19 @@
+mod default_device;
+mod end_reason;
 mod handle;
 mod listener;
 mod manager;
+mod one_way;
+mod playback;
+mod quality;
+mod reconnect;
 
+pub use default_device::*;
+pub use end_reason::*;
 pub use handle::*;
 pub use listener::*;
 pub use manager::*;
+pub use one_way::*;
+pub use playback::*;
+pub use quality::*;
+pub use reconnect::*;
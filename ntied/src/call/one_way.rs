@@ -0,0 +1,116 @@
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::contact::Usage;
+
+/// Direction of call audio that stopped flowing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioDirection {
+    /// Nothing is sent, the peer most likely can not hear us.
+    Outbound,
+    /// Nothing is received from the peer.
+    Inbound,
+}
+
+impl AudioDirection {
+    /// Short hint shown to the user.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            AudioDirection::Outbound => {
+                "Your audio is not reaching the peer, check microphone mute or firewall"
+            }
+            AudioDirection::Inbound => "No audio from the peer, ask them to check mute or firewall",
+        }
+    }
+}
+
+/// Change reported by [`OneWayAudioDetector::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OneWayAudioEvent {
+    /// One direction has been silent for the whole window while the other flowed.
+    Detected(AudioDirection),
+    /// Traffic flows both ways again after a detection.
+    Resolved,
+}
+
+/// Detects calls where packets flow only in one direction.
+#[derive(Debug, Clone)]
+pub struct OneWayAudioDetector {
+    window: Duration,
+    call_id: Option<Uuid>,
+    last: Usage,
+    last_sent: Option<Instant>,
+    last_received: Option<Instant>,
+    reported: Option<AudioDirection>,
+}
+
+impl OneWayAudioDetector {
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(5);
+
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            call_id: None,
+            last: Usage::default(),
+            last_sent: None,
+            last_received: None,
+            reported: None,
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    /// Applies call usage counters observed at `now`, a new call id starts
+    /// tracking from scratch. Each condition is reported once.
+    pub fn update(
+        &mut self,
+        call_id: Uuid,
+        usage: Usage,
+        now: Instant,
+    ) -> Option<OneWayAudioEvent> {
+        if self.call_id != Some(call_id) {
+            self.call_id = Some(call_id);
+            self.last = usage;
+            self.last_sent = Some(now);
+            self.last_received = Some(now);
+            self.reported = None;
+            return None;
+        }
+        if usage.bytes_sent > self.last.bytes_sent {
+            self.last_sent = Some(now);
+        }
+        if usage.bytes_received > self.last.bytes_received {
+            self.last_received = Some(now);
+        }
+        self.last = usage;
+        let stalled = |since: Option<Instant>| {
+            since.is_some_and(|since| now.duration_since(since) >= self.window)
+        };
+        let direction = match (stalled(self.last_sent), stalled(self.last_received)) {
+            (true, false) => Some(AudioDirection::Outbound),
+            (false, true) => Some(AudioDirection::Inbound),
+            _ => None,
+        };
+        if direction == self.reported {
+            return None;
+        }
+        self.reported = direction;
+        Some(match direction {
+            Some(direction) => OneWayAudioEvent::Detected(direction),
+            None => OneWayAudioEvent::Resolved,
+        })
+    }
+}
+
+impl Default for OneWayAudioDetector {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
@@ -0,0 +1,41 @@
+use std::future::Future;
+
+use crate::audio::{AudioFrame, Decoder};
+
+use super::{CallHandle, CallState};
+
+/// Plays decoded frames of the peer until the decoder or the output stops,
+/// returns the number of played frames. The call moves from connecting audio
+/// to connected once the first frame plays.
+pub async fn play_call_audio<F, Fut>(
+    decoder: &Decoder,
+    call_handle: &CallHandle,
+    mut play: F,
+) -> u64
+where
+    F: FnMut(AudioFrame) -> Fut,
+    Fut: Future<Output = Result<(), anyhow::Error>>,
+{
+    tracing::info!("Playback task started");
+    let mut frame_count = 0u64;
+    while let Some(frame) = decoder.recv_frame().await {
+        let samples = frame.samples.len();
+        if let Err(e) = play(frame).await {
+            tracing::error!("Failed to send frame to playback: {}", e);
+            break;
+        }
+        frame_count += 1;
+        if frame_count == 1
+            && call_handle
+                .advance_state(CallState::ConnectingAudio, CallState::Connected)
+                .await
+        {
+            tracing::info!("First audio frame played, call is connected");
+        }
+        if frame_count.is_multiple_of(100) {
+            tracing::debug!("Playing audio frame #{}, samples: {}", frame_count, samples);
+        }
+    }
+    tracing::warn!("Playback task ended after {} frames", frame_count);
+    frame_count
+}
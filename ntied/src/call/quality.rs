@@ -0,0 +1,61 @@
+use crate::audio::NetworkQuality;
+
+/// Coarse call quality shown next to the codec in the call overlay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallQuality {
+    Good,
+    Fair,
+    Poor,
+}
+
+impl CallQuality {
+    /// Buckets a network estimate, the worst of loss, RTT and jitter wins.
+    pub fn from_network(quality: &NetworkQuality) -> Self {
+        if quality.packet_loss >= 8.0 || quality.rtt >= 400.0 || quality.jitter >= 80.0 {
+            CallQuality::Poor
+        } else if quality.packet_loss >= 2.0 || quality.rtt >= 150.0 || quality.jitter >= 30.0 {
+            CallQuality::Fair
+        } else {
+            CallQuality::Good
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CallQuality::Good => "Good",
+            CallQuality::Fair => "Fair",
+            CallQuality::Poor => "Poor",
+        }
+    }
+}
+
+/// Estimates loss of incoming audio packets from gaps in sequence numbers.
+#[derive(Debug, Default)]
+pub struct LossEstimator {
+    first: Option<u32>,
+    highest: u32,
+    received: u64,
+}
+
+impl LossEstimator {
+    pub fn record(&mut self, sequence: u32) {
+        match self.first {
+            None => {
+                self.first = Some(sequence);
+                self.highest = sequence;
+            }
+            Some(_) if sequence > self.highest => self.highest = sequence,
+            Some(_) => {}
+        }
+        self.received += 1;
+    }
+
+    /// Returns loss percentage since the previous call and starts a new window.
+    pub fn take_loss(&mut self) -> Option<f32> {
+        let first = self.first.take()?;
+        let expected = (self.highest - first) as u64 + 1;
+        let lost = expected.saturating_sub(self.received);
+        self.received = 0;
+        Some(lost as f32 * 100.0 / expected as f32)
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+/// Change of the call connection reported by [`ReconnectGrace::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconnectEvent {
+    None,
+    /// The contact disconnected, the call waits for a reconnect.
+    Interrupted,
+    /// The contact reconnected within the grace period.
+    Resumed,
+    /// The contact did not reconnect in time, the call should end.
+    Expired,
+}
+
+/// Keeps a call alive while its contact reconnects after a brief drop.
+#[derive(Debug, Clone)]
+pub struct ReconnectGrace {
+    grace: Duration,
+    call_id: Option<Uuid>,
+    disconnected_since: Option<Instant>,
+}
+
+impl ReconnectGrace {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            call_id: None,
+            disconnected_since: None,
+        }
+    }
+
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    pub fn set_grace(&mut self, grace: Duration) {
+        self.grace = grace;
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.disconnected_since.is_some()
+    }
+
+    /// Applies the contact connection state of the call observed at `now`,
+    /// a new call id starts tracking from scratch.
+    pub fn update(&mut self, call_id: Uuid, connected: bool, now: Instant) -> ReconnectEvent {
+        if self.call_id != Some(call_id) {
+            self.call_id = Some(call_id);
+            self.disconnected_since = None;
+        }
+        match (connected, self.disconnected_since) {
+            (true, None) => ReconnectEvent::None,
+            (true, Some(_)) => {
+                self.disconnected_since = None;
+                ReconnectEvent::Resumed
+            }
+            (false, None) => {
+                self.disconnected_since = Some(now);
+                ReconnectEvent::Interrupted
+            }
+            (false, Some(since)) if now.duration_since(since) > self.grace => {
+                self.disconnected_since = None;
+                ReconnectEvent::Expired
+            }
+            (false, Some(_)) => ReconnectEvent::None,
+        }
+    }
+}
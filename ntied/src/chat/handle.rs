@@ -3,22 +3,23 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::anyhow;
+use ntied_crypto::{PrivateKey, PublicKey};
 use ntied_transport::Address;
 use rand::Rng as _;
 use tokio::sync::{Mutex as TokioMutex, mpsc};
 use tokio::task::JoinHandle;
 use tokio::time::{Instant, sleep_until};
-use tokio_sqlite::Value;
 use uuid::Uuid;
 
-use crate::contact::ContactHandle;
-use crate::models::{ColumnIndex, Contact, DateTime, Message, MessageKind};
+use crate::contact::{ContactHandle, Features};
+use crate::models::{Contact, DateTime, HistoryPage, Message, MessageKind, VoiceMessage};
 use crate::packet::{
     ChatConflictPacket, ChatMessageAckPacket, ChatMessageKind, ChatMessagePacket, ChatPacket,
+    ChatSessionAcceptPacket, ChatSessionInitPacket, SealedChatPacket,
 };
-use crate::storage::Storage;
+use crate::storage::MessageStore;
 
-use super::ChatListener;
+use super::{ArchiveOptions, ChatListener, PendingSession, RatchetSession};
 
 #[derive(Clone)]
 pub struct ChatHandle {
@@ -27,30 +28,41 @@ pub struct ChatHandle {
 
 impl ChatHandle {
     const MAX_PACKETS: usize = 4;
+    /// Messages sent within the window after the first one share a packet.
+    const BATCH_WINDOW: Duration = Duration::from_millis(50);
+    const MAX_BATCH_MESSAGES: usize = 32;
+    /// Batches stay below the transport datagram size, larger messages
+    /// are sent alone.
+    const MAX_BATCH_LEN: usize = 48 * 1024;
 
     pub fn new(
         contact_handle: ContactHandle,
         contact: Contact,
-        storage: Arc<TokioMutex<Storage>>,
+        private_key: PrivateKey,
+        store: Arc<dyn MessageStore>,
+        archive_options: Arc<Mutex<ArchiveOptions>>,
         listener: Arc<dyn ChatListener>,
     ) -> Self {
         let contact = Arc::new(Mutex::new(contact));
         let (command_tx, command_rx) = mpsc::channel(Self::MAX_PACKETS);
         let (recv_tx, recv_rx) = mpsc::channel(Self::MAX_PACKETS);
         let recv_rx = TokioMutex::new(recv_rx);
-        let main_task = tokio::spawn(Self::main_loop(
-            contact_handle.clone(),
-            contact.clone(),
-            storage.clone(),
+        let main_task = ChatHandleTask {
+            contact_handle: contact_handle.clone(),
+            contact: contact.clone(),
+            private_key,
+            store: store.clone(),
+            archive_options,
             command_rx,
             recv_tx,
-            listener.clone(),
-        ));
+            listener,
+        };
+        let main_task = tokio::spawn(main_task.run());
         Self {
             inner: Arc::new(ChatHandleInner {
                 contact_handle,
                 contact,
-                storage,
+                store,
                 command_tx,
                 recv_rx,
                 main_task,
@@ -72,27 +84,203 @@ impl ChatHandle {
         &self.inner.contact_handle
     }
 
+    pub fn is_archived(&self) -> bool {
+        self.inner.contact.lock().unwrap().archived
+    }
+
+    /// Persist the archive flag of the contact.
+    pub async fn set_archived(&self, archived: bool) -> Result<(), anyhow::Error> {
+        let mut contact = self.contact();
+        if contact.archived == archived {
+            return Ok(());
+        }
+        contact.archived = archived;
+        let contact = self.inner.store.update_contact(contact).await?;
+        *self.inner.contact.lock().unwrap() = contact;
+        Ok(())
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.inner.contact.lock().unwrap().muted
+    }
+
+    /// Persist the mute flag of the contact.
+    pub async fn set_muted(&self, muted: bool) -> Result<(), anyhow::Error> {
+        let mut contact = self.contact();
+        if contact.muted == muted {
+            return Ok(());
+        }
+        contact.muted = muted;
+        let contact = self.inner.store.update_contact(contact).await?;
+        *self.inner.contact.lock().unwrap() = contact;
+        Ok(())
+    }
+
+    pub fn is_auto_answer(&self) -> bool {
+        self.inner.contact.lock().unwrap().auto_answer
+    }
+
+    /// Persist the auto-answer flag, only verified contacts can have it.
+    pub async fn set_auto_answer(&self, auto_answer: bool) -> Result<(), anyhow::Error> {
+        let mut contact = self.contact();
+        if contact.auto_answer == auto_answer {
+            return Ok(());
+        }
+        if auto_answer && !contact.verified {
+            return Err(anyhow!("Only verified contacts can be auto-answered"));
+        }
+        contact.auto_answer = auto_answer;
+        let contact = self.inner.store.update_contact(contact).await?;
+        *self.inner.contact.lock().unwrap() = contact;
+        Ok(())
+    }
+
+    /// Fingerprint of the contact identity key.
+    pub fn fingerprint(&self) -> String {
+        self.inner.contact.lock().unwrap().public_key.fingerprint()
+    }
+
+    /// Persist the verified flag of the contact.
+    pub async fn set_verified(&self, verified: bool) -> Result<(), anyhow::Error> {
+        let mut contact = self.contact();
+        contact.verified = verified;
+        contact.auto_answer &= verified;
+        let contact = self.inner.store.update_contact(contact).await?;
+        *self.inner.contact.lock().unwrap() = contact;
+        Ok(())
+    }
+
+    /// Position among pinned chats, `None` for chats that are not pinned.
+    pub fn pin_order(&self) -> Option<i64> {
+        let contact = self.inner.contact.lock().unwrap();
+        contact.pinned.then_some(contact.pin_order)
+    }
+
+    /// Persist the pin flag and order of the contact.
+    pub async fn set_pin_order(&self, pin_order: Option<i64>) -> Result<(), anyhow::Error> {
+        let mut contact = self.contact();
+        contact.pinned = pin_order.is_some();
+        contact.pin_order = pin_order.unwrap_or(0);
+        let contact = self.inner.store.update_contact(contact).await?;
+        *self.inner.contact.lock().unwrap() = contact;
+        Ok(())
+    }
+
     pub async fn send_message(&self, kind: MessageKind) -> Result<Message, anyhow::Error> {
+        self.send(kind, None).await
+    }
+
+    /// Sends several messages at once, contacts supporting
+    /// [`Features::MESSAGE_BATCH`] receive them in one packet. Every
+    /// message keeps its own id and the order is preserved.
+    pub async fn send_batch(&self, kinds: Vec<MessageKind>) -> Result<Vec<Message>, anyhow::Error> {
+        self.send_all(kinds, None).await
+    }
+
+    /// Sends a text of any length, texts longer than
+    /// [`MessageKind::MAX_TEXT_LEN`] are sent as several messages in order.
+    /// Only the first message quotes `reply_to`.
+    pub async fn send_text(
+        &self,
+        text: &str,
+        reply_to: Option<i64>,
+    ) -> Result<Vec<Message>, anyhow::Error> {
+        if let Some(reply_to) = reply_to {
+            self.check_reply(reply_to).await?;
+        }
+        let kinds = MessageKind::split_text(text)
+            .into_iter()
+            .map(|v| MessageKind::Text(v.to_string()))
+            .collect();
+        self.send_all(kinds, reply_to).await
+    }
+
+    /// Sends the parts of a voice message in order, see
+    /// [`crate::audio::import_wav`].
+    pub async fn send_voice(
+        &self,
+        parts: Vec<VoiceMessage>,
+    ) -> Result<Vec<Message>, anyhow::Error> {
+        self.send_batch(parts.into_iter().map(MessageKind::Voice).collect())
+            .await
+    }
+
+    /// Send a message quoting an earlier message of this chat. Contacts not
+    /// supporting [`Features::SIGNED_MESSAGE`] get it without the quote.
+    pub async fn send_reply(
+        &self,
+        kind: MessageKind,
+        reply_to: i64,
+    ) -> Result<Message, anyhow::Error> {
+        self.check_reply(reply_to).await?;
+        self.send(kind, Some(reply_to)).await
+    }
+
+    async fn check_reply(&self, reply_to: i64) -> Result<(), anyhow::Error> {
         let contact_id = self.inner.contact.lock().unwrap().id;
-        let message_id = Uuid::now_v7();
-        let message = Message {
-            id: 0,
-            contact_id,
-            message_id,
-            log_id: None,
-            incoming: false,
-            kind,
-            create_time: DateTime::now(),
-            receive_time: None,
-            read_time: None,
-        };
-        let message = Self::create_message(&self.inner.storage.as_ref(), message).await?;
+        match self.inner.store.get_message_by_id(reply_to).await? {
+            Some(v) if v.contact_id == contact_id => Ok(()),
+            _ => Err(anyhow!("Replied message not found")),
+        }
+    }
+
+    async fn send(
+        &self,
+        kind: MessageKind,
+        reply_to: Option<i64>,
+    ) -> Result<Message, anyhow::Error> {
+        let mut messages = self.send_all(vec![kind], reply_to).await?;
+        Ok(messages.remove(0))
+    }
+
+    /// Stores the messages and enqueues them for sending, only the first
+    /// message quotes `reply_to`.
+    async fn send_all(
+        &self,
+        kinds: Vec<MessageKind>,
+        reply_to: Option<i64>,
+    ) -> Result<Vec<Message>, anyhow::Error> {
+        if kinds.is_empty() {
+            return Err(anyhow!("No messages to send"));
+        }
+        for kind in &kinds {
+            match kind {
+                MessageKind::Text(text) if text.len() > MessageKind::MAX_TEXT_LEN => {
+                    return Err(anyhow!(
+                        "Message is too long: {} bytes, at most {} are allowed",
+                        text.len(),
+                        MessageKind::MAX_TEXT_LEN
+                    ));
+                }
+                MessageKind::Text(_) => {}
+                MessageKind::Voice(voice) => voice.validate()?,
+            }
+        }
+        let contact_id = self.inner.contact.lock().unwrap().id;
+        let mut messages = Vec::with_capacity(kinds.len());
+        for (i, kind) in kinds.into_iter().enumerate() {
+            let message = Message {
+                id: 0,
+                contact_id,
+                message_id: Uuid::now_v7(),
+                log_id: None,
+                incoming: false,
+                kind,
+                create_time: DateTime::now(),
+                receive_time: None,
+                read_time: None,
+                verified: true,
+                signature: None,
+                reply_to: reply_to.filter(|_| i == 0),
+            };
+            messages.push(self.inner.store.create_message(message).await?);
+        }
         self.inner
             .command_tx
-            .send(HandleCommand::SendMessage(message.clone()))
+            .send(HandleCommand::SendMessages(messages.clone()))
             .await
             .map_err(|_| anyhow::Error::msg("Handle is broken"))?;
-        Ok(message)
+        Ok(messages)
     }
 
     pub async fn recv_message(&self) -> Result<Message, anyhow::Error> {
@@ -109,49 +297,66 @@ impl ChatHandle {
     /// Load chat history for this contact.
     pub async fn load_history(&self, limit: usize) -> Result<Vec<Message>, anyhow::Error> {
         let contact_id = self.inner.contact.lock().unwrap().id;
-        let columns = Message::columns();
-        let query = format!(
-            "SELECT {} FROM \"message\" \
-             WHERE \"contact_id\" = ?1 \
-             ORDER BY CASE WHEN \"log_id\" IS NULL THEN 0 ELSE 1 END, \"log_id\" DESC, \"id\" DESC \
-             LIMIT ?2",
-            Self::format_columns(columns)
-        );
-        let mut values = Vec::<tokio_sqlite::Value>::new();
-        values.push(tokio_sqlite::Value::Integer(contact_id));
-        values.push(tokio_sqlite::Value::Integer(limit as i64));
-        let mut storage = self.inner.storage.lock().await;
-        let conn = storage.connection().await;
-        let mut rows = conn.query(query, values).await?;
-        let mut result = Vec::new();
-        while let Some(row) = rows.next().await {
-            let row = row?;
-            let values = row.into_values();
-            let message = Message::from_values(values, columns)?;
-            result.push(message);
-        }
-        result.reverse();
-        Ok(result)
+        self.inner.store.load_history(contact_id, limit).await
     }
 
-    async fn main_loop(
-        contact_handle: ContactHandle,
-        contact: Arc<Mutex<Contact>>,
-        storage: Arc<TokioMutex<Storage>>,
-        mut command_rx: mpsc::Receiver<HandleCommand>,
-        recv_tx: mpsc::Sender<Message>,
-        listener: Arc<dyn ChatListener>,
-    ) {
+    /// Load a page of chat history preceding the message with id `before_id`.
+    /// Loads the latest page when `before_id` is `None`.
+    pub async fn load_history_before(
+        &self,
+        before_id: Option<i64>,
+        limit: usize,
+    ) -> Result<HistoryPage, anyhow::Error> {
+        let contact_id = self.inner.contact.lock().unwrap().id;
+        self.inner
+            .store
+            .load_history_before(contact_id, before_id, limit)
+            .await
+    }
+
+    fn next_tick() -> Instant {
+        Instant::now() + Duration::from_millis(rand::thread_rng().gen_range(1000..5000))
+    }
+}
+
+struct ChatHandleTask {
+    contact_handle: ContactHandle,
+    contact: Arc<Mutex<Contact>>,
+    private_key: PrivateKey,
+    store: Arc<dyn MessageStore>,
+    archive_options: Arc<Mutex<ArchiveOptions>>,
+    command_rx: mpsc::Receiver<HandleCommand>,
+    recv_tx: mpsc::Sender<Message>,
+    listener: Arc<dyn ChatListener>,
+}
+
+impl ChatHandleTask {
+    async fn run(self) {
+        let Self {
+            contact_handle,
+            contact,
+            private_key,
+            store,
+            archive_options,
+            mut command_rx,
+            recv_tx,
+            listener,
+        } = self;
         let contact_id = contact.lock().unwrap().id;
         let contact_address = contact_handle.address();
         let mut pending_messages = VecDeque::<Uuid>::new();
-        let mut pending_message_ack = None::<Uuid>;
-        let mut head_log_id = Self::get_head_log_id(storage.as_ref(), contact_id)
-            .await
-            .unwrap();
-        let mut next_tick = Self::next_tick();
+        // Sent messages in log order, the front one is acked next
+        let mut pending_acks = VecDeque::<Uuid>::new();
+        let mut head_log_id = store.get_head_log_id(contact_id).await.unwrap();
+        let supported = {
+            let contact = contact.lock().unwrap();
+            RatchetSession::is_supported(&private_key, &contact.public_key)
+        };
+        let mut session = ChatSession::load(&store, contact_id, supported).await;
+        let mut next_tick = ChatHandle::next_tick();
+        let mut batch_deadline = None::<Instant>;
         // Restore pending outgoing messages (incoming = 0, log_id IS NULL)
-        match Self::get_pending_message_ids(storage.as_ref(), contact_id).await {
+        match store.get_pending_message_ids(contact_id).await {
             Ok(ids) => {
                 for id in ids {
                     pending_messages.push_back(id);
@@ -170,28 +375,52 @@ impl ChatHandle {
                         None => return,
                     };
                     match command {
-                        HandleCommand::SendMessage(message) => {
-                            tracing::debug!("Registering new pending message");
-                            if pending_message_ack.is_none() && pending_messages.is_empty() {
-                                pending_message_ack = Some(message.message_id);
-                                let log_id = head_log_id.unwrap_or(0) + 1;
-                                let kind = match message.kind {
-                                    MessageKind::Text(text) => ChatMessageKind::Text(text),
-                                };
-                                let packet = ChatMessagePacket {
-                                    message_id: message.message_id,
-                                    log_id,
-                                    kind,
-                                };
-                                if let Err(err) = contact_handle.send_chat_packet(ChatPacket::Message(packet)).await {
-                                    tracing::warn!(?err, "Failed to send chat packet");
+                        HandleCommand::SendMessages(messages) => {
+                            tracing::debug!(count = messages.len(), "Registering new pending messages");
+                            for message in messages {
+                                // The message may already be restored from storage
+                                if !pending_acks.contains(&message.message_id) && !pending_messages.contains(&message.message_id) {
+                                    pending_messages.push_back(message.message_id);
                                 }
+                            }
+                            if !pending_acks.is_empty() || batch_deadline.is_some() {
                                 continue;
                             }
-                            pending_messages.push_back(message.message_id);
+                            if contact_handle.features().contains(Features::MESSAGE_BATCH) {
+                                batch_deadline = Some(Instant::now() + ChatHandle::BATCH_WINDOW);
+                            } else {
+                                Self::send_pending(&contact_handle, &store, &private_key, head_log_id, &mut pending_acks, &mut pending_messages, &mut session).await;
+                            }
                         }
                     }
                 }
+                profile = contact_handle.recv_profile_update() => {
+                    let profile = match profile {
+                        Ok(v) => v,
+                        Err(err) => {
+                            tracing::error!(?err, "Failed to receive profile update");
+                            continue;
+                        }
+                    };
+                    let mut new_contact = contact.lock().unwrap().clone();
+                    let avatar = profile.avatar.map(|v| v.0);
+                    if new_contact.name == profile.name && new_contact.avatar == avatar {
+                        continue;
+                    }
+                    tracing::debug!("Updating contact profile");
+                    // Local name set by the user is kept as is
+                    new_contact.name = profile.name;
+                    new_contact.avatar = avatar;
+                    let new_contact = match store.update_contact(new_contact).await {
+                        Ok(v) => v,
+                        Err(err) => {
+                            tracing::error!(?err, "Failed to update contact");
+                            continue;
+                        }
+                    };
+                    *contact.lock().unwrap() = new_contact.clone();
+                    listener.on_contact_updated(contact_address, new_contact).await;
+                }
                 packet = contact_handle.recv_chat_packet() => {
                     let packet = match packet {
                         Ok(v) => v,
@@ -200,350 +429,637 @@ impl ChatHandle {
                             continue;
                         }
                     };
-                    match packet {
-                        ChatPacket::Message(message_packet) => {
-                            tracing::debug!("Received new message");
-                            match Self::get_message(storage.as_ref(), message_packet.message_id).await {
-                                Ok(Some(_)) => {
-                                    tracing::debug!("Sending message ack");
-                                    let packet = ChatMessageAckPacket {
+                    let sealed = matches!(packet, ChatPacket::Sealed(_));
+                    let packet = match packet {
+                        ChatPacket::SessionInit(init_packet) => {
+                            let public_key = contact.lock().unwrap().public_key.clone();
+                            if session.accept(&contact_handle, &store, &private_key, &public_key, init_packet).await {
+                                Self::send_pending(&contact_handle, &store, &private_key, head_log_id, &mut pending_acks, &mut pending_messages, &mut session).await;
+                            }
+                            continue;
+                        }
+                        ChatPacket::SessionAccept(accept_packet) => {
+                            let public_key = contact.lock().unwrap().public_key.clone();
+                            if session.complete(&store, &private_key, &public_key, accept_packet).await {
+                                Self::send_pending(&contact_handle, &store, &private_key, head_log_id, &mut pending_acks, &mut pending_messages, &mut session).await;
+                            }
+                            continue;
+                        }
+                        ChatPacket::Sealed(sealed_packet) => {
+                            match session.open(&contact_handle, &store, &private_key, sealed_packet).await {
+                                Some(v) => v,
+                                None => continue,
+                            }
+                        }
+                        v => v,
+                    };
+                    // Packets of a batch are handled in order, their replies are batched too
+                    let packets = match packet {
+                        ChatPacket::Batch(v) => v,
+                        v => vec![v],
+                    };
+                    let mut replies = Vec::new();
+                    for packet in packets {
+                        let signed = matches!(packet, ChatPacket::SignedMessage(_));
+                        match packet {
+                            ChatPacket::Message(message_packet) | ChatPacket::SignedMessage(message_packet) => {
+                                tracing::debug!("Received new message");
+                                if !signed && contact_handle.features().contains(Features::SIGNED_MESSAGE) {
+                                    tracing::warn!(message_id = ?message_packet.message_id, "Rejecting unsigned message");
+                                    continue;
+                                }
+                                if !sealed && session.is_used(&contact_handle) {
+                                    tracing::warn!(message_id = ?message_packet.message_id, "Rejecting message sent without session");
+                                    continue;
+                                }
+                                // The sender assigns message ids, a retransmitted message is only acked again
+                                match store.get_message(message_packet.message_id).await {
+                                    Ok(Some(existing)) => {
+                                        let contact_id = contact.lock().unwrap().id;
+                                        let log_id = match existing.log_id {
+                                            Some(v) if existing.incoming && existing.contact_id == contact_id => v,
+                                            _ => {
+                                                tracing::warn!(message_id = ?message_packet.message_id, "Ignoring message with foreign id");
+                                                continue;
+                                            }
+                                        };
+                                        tracing::debug!(message_id = ?message_packet.message_id, "Sending ack for duplicate message");
+                                        // Ack with the stored log_id so both sides keep the same log
+                                        let packet = ChatMessageAckPacket {
+                                            message_id: message_packet.message_id,
+                                            log_id,
+                                        };
+                                        replies.push(ChatPacket::MessageAck(packet));
+                                        continue;
+                                    },
+                                    Ok(None) => {}
+                                    Err(err) => {
+                                        tracing::error!(?err, message_id = ?message_packet.message_id, "Failed to get message");
+                                        continue;
+                                    }
+                                };
+                                tracing::trace!(message_id = ?message_packet.message_id, "Check message log_id");
+                                if !pending_acks.is_empty() || !head_log_id.map(|v| v + 1 == message_packet.log_id).unwrap_or(true) {
+                                    tracing::debug!(log_id = message_packet.log_id, "Rejecting message with incorrect log_id");
+                                    let packet = ChatConflictPacket {
                                         message_id: message_packet.message_id,
-                                        log_id: message_packet.log_id,
                                     };
-                                    if let Err(err) = contact_handle.send_chat_packet(ChatPacket::MessageAck(packet)).await {
-                                        tracing::warn!(?err, "Failed to send chat packet");
-                                    }
+                                    replies.push(ChatPacket::Conflict(packet));
                                     continue;
-                                },
-                                Ok(None) => {}
-                                Err(err) => {
-                                    tracing::error!(?err, message_id = ?message_packet.message_id, "Failed to get message");
+                                }
+                                // Forged messages are neither stored nor acknowledged,
+                                // messages of contacts without signatures are kept unverified
+                                let public_key = contact.lock().unwrap().public_key.clone();
+                                let verified = signed && message_packet.verify(&public_key);
+                                if signed && !verified {
+                                    tracing::warn!(message_id = ?message_packet.message_id, "Rejecting message with invalid signature");
                                     continue;
                                 }
-                            };
-                            tracing::trace!(message_id = ?message_packet.message_id, "Check message log_id");
-                            if pending_message_ack.is_some() || !head_log_id.map(|v| v + 1 == message_packet.log_id).unwrap_or(true) {
-                                tracing::debug!(log_id = message_packet.log_id, "Rejecting message with incorrect log_id");
-                                let packet = ChatConflictPacket {
+                                let contact_id = contact.lock().unwrap().id;
+                                let reply_to = match message_packet.reply_to {
+                                    Some(id) => Self::reply_local_id(&store, contact_id, id).await,
+                                    None => None,
+                                };
+                                let kind = match message_packet.kind {
+                                    ChatMessageKind::Text(text) => MessageKind::Text(text),
+                                    ChatMessageKind::Voice(voice) => MessageKind::Voice(voice),
+                                };
+                                // Message ids carry the sender clock, history keeps the log order
+                                let receive_time = DateTime::now();
+                                let message = Message {
+                                    id: 0,
                                     message_id: message_packet.message_id,
+                                    log_id: Some(message_packet.log_id),
+                                    contact_id,
+                                    incoming: true,
+                                    kind,
+                                    create_time: DateTime::from_uuid(&message_packet.message_id)
+                                        .unwrap_or(receive_time),
+                                    receive_time: Some(receive_time),
+                                    read_time: None,
+                                    verified,
+                                    signature: signed.then_some(message_packet.signature),
+                                    reply_to,
                                 };
-                                if let Err(err) = contact_handle.send_chat_packet(ChatPacket::Conflict(packet)).await {
-                                    tracing::warn!(?err, "Failed to send chat packet");
-                                }
-                                continue;
-                            }
-                            let kind = match message_packet.kind {
-                                ChatMessageKind::Text(text) => MessageKind::Text(text),
-                            };
-                            let message = Message {
-                                id: 0,
-                                message_id: message_packet.message_id,
-                                log_id: Some(message_packet.log_id),
-                                contact_id: contact.lock().unwrap().id,
-                                incoming: true,
-                                kind,
-                                create_time: DateTime::now(),
-                                receive_time: Some(DateTime::now()),
-                                read_time: None,
-                            };
-                            tracing::trace!(message_id = ?message.message_id, "Save message in storage");
-                            let message = match Self::create_message(storage.as_ref(), message).await {
-                                Ok(v) => v,
-                                Err(err) => {
-                                    tracing::error!(?err, "Failed to create message");
-                                    continue;
+                                if message.has_clock_skew() {
+                                    tracing::warn!(
+                                        message_id = ?message.message_id,
+                                        skew = ?message.clock_skew(),
+                                        "Sender clock differs from local clock",
+                                    );
                                 }
-                            };
-                            tracing::trace!(log_id = ?message.log_id, "Update chat head");
-                            head_log_id = message.log_id;
-                            assert!(head_log_id.is_some());
-                            listener.on_incoming_message(contact_address, message.clone()).await;
-                            tracing::debug!("Sending message ack");
-                            let packet = ChatMessageAckPacket {
-                                message_id: message.message_id,
-                                log_id: message_packet.log_id,
-                            };
-                            if let Err(err) = recv_tx.try_send(message.clone()) {
-                                tracing::error!(?err, "Failed to notify recv message");
-                            }
-                            if let Err(err) = contact_handle.send_chat_packet(ChatPacket::MessageAck(packet)).await {
-                                tracing::warn!(?err, "Failed to send chat packet");
-                            }
-                        }
-                        ChatPacket::MessageAck(message_ack_packet) => {
-                            tracing::debug!("Received message ack");
-                            match pending_message_ack {
-                                Some(message_id) => {
-                                    tracing::trace!(?message_id, "Check message_id for ack");
-                                    if message_id != message_ack_packet.message_id {
-                                        tracing::debug!(message_id = ?message_ack_packet.message_id, "Rejected message ack");
+                                tracing::trace!(message_id = ?message.message_id, "Save message in storage");
+                                let message = match store.create_message(message).await {
+                                    Ok(v) => v,
+                                    Err(err) => {
+                                        tracing::error!(?err, "Failed to create message");
                                         continue;
                                     }
-                                    tracing::trace!(?message_id, "Fetch message content");
-                                    let message = match Self::get_message(storage.as_ref(), message_ack_packet.message_id).await {
-                                        Ok(Some(v)) => v,
-                                        Ok(None) => {
-                                            tracing::debug!(?message_id, "Message not found");
-                                            pending_message_ack.take();
+                                };
+                                tracing::trace!(log_id = ?message.log_id, "Update chat head");
+                                head_log_id = message.log_id;
+                                assert!(head_log_id.is_some());
+                                let unarchive = archive_options.lock().unwrap().unarchive_on_message
+                                    && contact.lock().unwrap().archived;
+                                if unarchive {
+                                    tracing::debug!("Unarchiving chat on new message");
+                                    let mut new_contact = contact.lock().unwrap().clone();
+                                    new_contact.archived = false;
+                                    match store.update_contact(new_contact).await {
+                                        Ok(new_contact) => {
+                                            *contact.lock().unwrap() = new_contact.clone();
+                                            listener.on_contact_updated(contact_address, new_contact).await;
+                                        }
+                                        Err(err) => tracing::error!(?err, "Failed to unarchive contact"),
+                                    }
+                                }
+                                listener.on_incoming_message(contact_address, message.clone()).await;
+                                if !contact.lock().unwrap().muted {
+                                    listener.on_message_notification(contact_address, message.clone()).await;
+                                }
+                                tracing::debug!("Sending message ack");
+                                let packet = ChatMessageAckPacket {
+                                    message_id: message.message_id,
+                                    log_id: message_packet.log_id,
+                                };
+                                if let Err(err) = recv_tx.try_send(message.clone()) {
+                                    tracing::error!(?err, "Failed to notify recv message");
+                                }
+                                replies.push(ChatPacket::MessageAck(packet));
+                            }
+                            ChatPacket::MessageAck(message_ack_packet) => {
+                                tracing::debug!("Received message ack");
+                                match pending_acks.front().copied() {
+                                    Some(message_id) => {
+                                        tracing::trace!(?message_id, "Check message_id for ack");
+                                        if message_id != message_ack_packet.message_id {
+                                            tracing::debug!(message_id = ?message_ack_packet.message_id, "Rejected message ack");
                                             continue;
-                                        },
-                                        Err(err) => {
-                                            tracing::error!(?err, ?message_id, "Failed to get message");
+                                        }
+                                        tracing::trace!(?message_id, "Fetch message content");
+                                        let message = match store.get_message(message_ack_packet.message_id).await {
+                                            Ok(Some(v)) => v,
+                                            Ok(None) => {
+                                                tracing::debug!(?message_id, "Message not found");
+                                                pending_acks.pop_front();
+                                                continue;
+                                            },
+                                            Err(err) => {
+                                                tracing::error!(?err, ?message_id, "Failed to get message");
+                                                continue;
+                                            }
+                                        };
+                                        tracing::trace!(?message_id, "Check message already confirmed");
+                                        if message.log_id == Some(message_ack_packet.log_id) {
+                                            tracing::debug!("Message already confirmed");
+                                            pending_acks.pop_front();
                                             continue;
                                         }
-                                    };
-                                    tracing::trace!(?message_id, "Check message already confirmed");
-                                    if message.log_id == Some(message_ack_packet.log_id) {
-                                        tracing::debug!("Message already confirmed");
-                                        continue;
+                                        tracing::trace!(?message_id, "Check message log_id");
+                                        if !head_log_id.map(|v| v + 1 == message_ack_packet.log_id).unwrap_or(true) {
+                                            tracing::warn!(
+                                                log_id = message_ack_packet.log_id,
+                                                "Rejecting message ack with incorrect log_id",
+                                            );
+                                            continue;
+                                        }
+                                        let mut new_message = message.clone();
+                                        new_message.log_id = Some(message_ack_packet.log_id);
+                                        new_message.receive_time = Some(DateTime::now());
+                                        tracing::trace!(?message_id, "Update message status");
+                                        let new_message = match store.update_message(new_message).await {
+                                            Ok(v) => v,
+                                            Err(err) => {
+                                                tracing::error!(?err, "Failed to update message");
+                                                continue;
+                                            }
+                                        };
+                                        tracing::trace!(log_id = new_message.log_id, "Update chat head");
+                                        pending_acks.pop_front();
+                                        head_log_id = new_message.log_id;
+                                        assert!(head_log_id.is_some());
+                                        listener.on_outgoing_message(contact_address, new_message).await;
                                     }
-                                    tracing::trace!(?message_id, "Check message log_id");
-                                    if !head_log_id.map(|v| v + 1 == message_ack_packet.log_id).unwrap_or(true) {
-                                        tracing::warn!(
-                                            log_id = message_ack_packet.log_id,
-                                            "Rejecting message ack with incorrect log_id",
+                                    None => {
+                                        tracing::debug!(
+                                            message_id = ?message_ack_packet.message_id,
+                                            "Ignored message ack for non sent message",
                                         );
-                                        continue;
                                     }
-                                    let mut new_message = message.clone();
-                                    new_message.log_id = Some(message_ack_packet.log_id);
-                                    new_message.receive_time = Some(DateTime::now());
-                                    tracing::trace!(?message_id, "Update message status");
-                                    let new_message = match Self::update_message(storage.as_ref(), new_message).await {
-                                        Ok(v) => v,
-                                        Err(err) => {
-                                            tracing::error!(?err, "Failed to update message");
-                                            continue;
-                                        }
-                                    };
-                                    tracing::trace!(log_id = new_message.log_id, "Update chat head");
-                                    pending_message_ack.take();
-                                    head_log_id = new_message.log_id;
-                                    assert!(head_log_id.is_some());
-                                    listener.on_outgoing_message(contact_address, new_message).await;
-                                }
-                                None => {
-                                    tracing::debug!(
-                                        message_id = ?message_ack_packet.message_id,
-                                        "Ignored message ack for non sent message",
-                                    );
                                 }
                             }
-                        }
-                        ChatPacket::Conflict(conflict_packet) => {
-                            tracing::debug!("Received conflict");
-                            match pending_message_ack {
-                                Some(message_id) => {
-                                    tracing::trace!(?message_id, "Check message_id for conflict");
-                                    if message_id != conflict_packet.message_id {
-                                        tracing::debug!(message_id = ?conflict_packet.message_id, "Rejected message conflict");
-                                        continue;
+                            ChatPacket::Conflict(conflict_packet) => {
+                                tracing::debug!("Received conflict");
+                                match pending_acks.front().copied() {
+                                    Some(message_id) => {
+                                        tracing::trace!(?message_id, "Check message_id for conflict");
+                                        if message_id != conflict_packet.message_id {
+                                            tracing::debug!(message_id = ?conflict_packet.message_id, "Rejected message conflict");
+                                            continue;
+                                        }
+                                        tracing::debug!(?message_id, "Accepted message conflict");
+                                        // Later messages of the batch are rejected too
+                                        while let Some(v) = pending_acks.pop_back() {
+                                            pending_messages.push_front(v);
+                                        }
+                                    }
+                                    None => {
+                                        tracing::debug!(
+                                            message_id = ?conflict_packet.message_id,
+                                            "Ignored message conflict for non sent message",
+                                        );
                                     }
-                                    tracing::debug!(?message_id, "Accepted message conflict");
-                                    pending_messages.push_front(message_id);
-                                    pending_message_ack.take();
-                                }
-                                None => {
-                                    tracing::debug!(
-                                        message_id = ?conflict_packet.message_id,
-                                        "Ignored message conflict for non sent message",
-                                    );
                                 }
                             }
+                            ChatPacket::Batch(_) => {
+                                tracing::warn!("Ignoring nested chat batch");
+                            }
+                            ChatPacket::SessionInit(_)
+                            | ChatPacket::SessionAccept(_)
+                            | ChatPacket::Sealed(_) => {
+                                tracing::warn!("Ignoring nested session packet");
+                            }
                         }
                     }
-                }
-                _ = sleep_until(next_tick) => {
-                    next_tick = Self::next_tick();
-                    let message_id = if let Some(v) = pending_message_ack {
-                        v
-                    } else if let Some(v) = pending_messages.pop_front() {
-                        pending_message_ack = Some(v);
-                        v
-                    } else {
-                        continue;
-                    };
-                    let message = match Self::get_message(storage.as_ref(), message_id).await {
-                        Ok(Some(v)) => v,
-                        Ok(None) => {
-                            tracing::debug!(?message_id, "Message not found");
-                            // Ignore message.
-                            pending_message_ack.take();
-                            continue;
-                        }
-                        Err(err) => {
-                            tracing::error!(?err, ?message_id, "Failed to get message");
-                            continue;
-                        }
-                    };
-                    let log_id = head_log_id.unwrap_or(0) + 1;
-                    let kind = match message.kind {
-                        MessageKind::Text(text) => ChatMessageKind::Text(text),
+                    let packet = match replies.len() {
+                        0 => continue,
+                        1 => replies.remove(0),
+                        _ => ChatPacket::Batch(replies),
                     };
-                    let packet = ChatMessagePacket {
-                        message_id,
-                        log_id,
-                        kind,
-                    };
-                    if let Err(err) = contact_handle.send_chat_packet(ChatPacket::Message(packet)).await {
+                    if let Err(err) = contact_handle.send_chat_packet(packet).await {
                         tracing::warn!(?err, "Failed to send chat packet");
                     }
                 }
+                _ = sleep_until(batch_deadline.unwrap_or(next_tick)), if batch_deadline.is_some() => {
+                    batch_deadline = None;
+                    Self::send_pending(&contact_handle, &store, &private_key, head_log_id, &mut pending_acks, &mut pending_messages, &mut session).await;
+                }
+                _ = sleep_until(next_tick) => {
+                    next_tick = ChatHandle::next_tick();
+                    batch_deadline = None;
+                    Self::send_pending(&contact_handle, &store, &private_key, head_log_id, &mut pending_acks, &mut pending_messages, &mut session).await;
+                }
             }
         }
     }
 
-    async fn get_head_log_id(
-        storage: &TokioMutex<Storage>,
-        contact_id: i64,
-    ) -> Result<Option<u64>, anyhow::Error> {
-        let query = "SELECT MAX(\"log_id\") FROM \"message\" WHERE \"log_id\" IS NOT NULL AND \"contact_id\" = ?1";
-        let mut values = Vec::<Value>::new();
-        values.push(contact_id.into());
-        let mut storage = storage.lock().await;
-        let connection = storage.connection().await;
-        match connection.query_row(query, values).await? {
-            Some(row) => {
-                let values = row.into_values();
-                assert_eq!(values.len(), 1);
-                match values.first().unwrap() {
-                    Value::Integer(i) if *i >= 0 => return Ok(Some(*i as u64)),
-                    Value::Null => return Ok(None),
-                    v => return Err(anyhow!("Failed to parse log_id from value: {v:?}")),
+    /// Sends the messages waiting for an ack again, or the next pending
+    /// messages once all are acked. Contacts supporting
+    /// [`Features::MESSAGE_BATCH`] get several messages in one packet.
+    /// Contacts supporting [`Features::MESSAGE_RATCHET`] get them sealed,
+    /// they wait for the session to be accepted.
+    async fn send_pending(
+        contact_handle: &ContactHandle,
+        store: &Arc<dyn MessageStore>,
+        private_key: &PrivateKey,
+        head_log_id: Option<u64>,
+        pending_acks: &mut VecDeque<Uuid>,
+        pending_messages: &mut VecDeque<Uuid>,
+        session: &mut ChatSession,
+    ) {
+        let max_messages = if contact_handle.features().contains(Features::MESSAGE_BATCH) {
+            ChatHandle::MAX_BATCH_MESSAGES
+        } else {
+            1
+        };
+        let mut unsent = std::mem::take(pending_acks);
+        if unsent.is_empty() {
+            let count = pending_messages.len().min(max_messages);
+            unsent.extend(pending_messages.drain(..count));
+        }
+        let mut packets = Vec::new();
+        let mut batch_len = 0;
+        while let Some(message_id) = unsent.pop_front() {
+            let message = match store.get_message(message_id).await {
+                Ok(Some(v)) if v.log_id.is_none() => v,
+                Ok(Some(_)) => {
+                    tracing::debug!(?message_id, "Message already confirmed");
+                    continue;
                 }
+                Ok(None) => {
+                    tracing::debug!(?message_id, "Message not found");
+                    continue;
+                }
+                Err(err) => {
+                    tracing::error!(?err, ?message_id, "Failed to get message");
+                    unsent.push_front(message_id);
+                    break;
+                }
+            };
+            let log_id = head_log_id.unwrap_or(0) + 1 + packets.len() as u64;
+            let reply_to = Self::reply_message_id(store, message.reply_to).await;
+            let kind = match message.kind {
+                MessageKind::Text(text) => ChatMessageKind::Text(text),
+                MessageKind::Voice(voice) => ChatMessageKind::Voice(voice),
+            };
+            // Contacts without signed messages get neither the reply nor the signature
+            let packet = ChatMessagePacket::new(message_id, log_id, kind, reply_to, private_key);
+            let packet = if contact_handle.features().contains(Features::SIGNED_MESSAGE) {
+                ChatPacket::SignedMessage(packet)
+            } else {
+                ChatPacket::Message(packet)
+            };
+            let len = bincode::serialized_size(&packet).unwrap_or(u64::MAX) as usize;
+            if !packets.is_empty()
+                && (packets.len() == max_messages || batch_len + len > ChatHandle::MAX_BATCH_LEN)
+            {
+                unsent.push_front(message_id);
+                break;
             }
-            None => Ok(None),
+            batch_len += len;
+            pending_acks.push_back(message_id);
+            packets.push(packet);
+        }
+        // Messages left out are sent once the batch is acked
+        while let Some(message_id) = unsent.pop_back() {
+            pending_messages.push_front(message_id);
+        }
+        let packet = match packets.len() {
+            0 => return,
+            1 => packets.remove(0),
+            _ => ChatPacket::Batch(packets),
+        };
+        let packet = if session.is_used(contact_handle) {
+            match session.seal(store, private_key, packet).await {
+                Some(v) => v,
+                None => return,
+            }
+        } else {
+            packet
+        };
+        if let Err(err) = contact_handle.send_chat_packet(packet).await {
+            tracing::warn!(?err, "Failed to send chat packet");
         }
     }
 
-    async fn get_pending_message_ids(
-        storage: &TokioMutex<Storage>,
-        contact_id: i64,
-    ) -> Result<Vec<Uuid>, anyhow::Error> {
-        let query = "SELECT \"message_id\" FROM \"message\" \
-                     WHERE \"contact_id\" = ?1 AND \"incoming\" = 0 AND \"log_id\" IS NULL \
-                     ORDER BY \"id\" ASC";
-        let mut storage = storage.lock().await;
-        let conn = storage.connection().await;
-        let mut rows = conn.query(query, vec![Value::Integer(contact_id)]).await?;
-        let mut result = Vec::new();
-        while let Some(row) = rows.next().await {
-            let row = row?;
-            if let Some(Value::Text(s)) = row.into_values().into_iter().next() {
-                if let Ok(uuid) = Uuid::parse_str(&s) {
-                    result.push(uuid);
-                }
+    /// Network id of the replied message, replies to deleted messages are
+    /// sent as regular messages.
+    async fn reply_message_id(
+        store: &Arc<dyn MessageStore>,
+        reply_to: Option<i64>,
+    ) -> Option<Uuid> {
+        match store.get_message_by_id(reply_to?).await {
+            Ok(message) => message.map(|v| v.message_id),
+            Err(err) => {
+                tracing::error!(?err, "Failed to get replied message");
+                None
             }
         }
-        Ok(result)
     }
 
-    async fn get_message(
-        storage: &TokioMutex<Storage>,
+    /// Local id of the replied message, unknown messages are ignored.
+    async fn reply_local_id(
+        store: &Arc<dyn MessageStore>,
+        contact_id: i64,
         message_id: Uuid,
-    ) -> Result<Option<Message>, anyhow::Error> {
-        let columns = Message::columns();
-        let query = format!(
-            "SELECT {} FROM \"message\" WHERE \"message_id\" = ?1 LIMIT 1",
-            Self::format_columns(columns)
-        );
-        let mut values = Vec::<Value>::new();
-        values.push(message_id.to_string().into());
-        let mut storage = storage.lock().await;
-        let connection = storage.connection().await;
-        match connection.query_row(query, values).await? {
-            Some(row) => {
-                let values = row.into_values();
-                let message = Message::from_values(values, columns)?;
-                Ok(Some(message))
+    ) -> Option<i64> {
+        match store.get_message(message_id).await {
+            Ok(Some(message)) if message.contact_id == contact_id => Some(message.id),
+            Ok(_) => {
+                tracing::debug!(?message_id, "Replied message not found");
+                None
+            }
+            Err(err) => {
+                tracing::error!(?err, "Failed to get replied message");
+                None
             }
-            None => Ok(None),
         }
     }
+}
 
-    async fn create_message(
-        storage: &TokioMutex<Storage>,
-        mut message: Message,
-    ) -> Result<Message, anyhow::Error> {
-        let columns = Self::columns_without_id(Message::columns(), "id");
-        let values = message.values(&columns);
-        let query = format!(
-            "INSERT INTO \"message\" ({}) VALUES ({})",
-            Self::format_columns(&columns),
-            Self::format_values(&values),
-        );
-        let mut storage = storage.lock().await;
-        let connection = storage.connection().await;
-        let status = connection.execute(query, values).await?;
-        message.id = status
-            .last_insert_id()
-            .ok_or(anyhow!("Cannot retrieve contact id"))?;
-        Ok(message)
-    }
-
-    async fn update_message(
-        storage: &TokioMutex<Storage>,
-        message: Message,
-    ) -> Result<Message, anyhow::Error> {
-        let query =
-            "UPDATE \"message\" SET \"log_id\" = ?1, \"receive_time\" = ?2 WHERE \"id\" = ?3";
-        let mut storage = storage.lock().await;
-        let connection = storage.connection().await;
-        let mut values = Vec::<Value>::new();
-        values.push(message.log_id.map(|v| v as i64).into());
-        values.push(message.receive_time.map(|v| v.0.timestamp_micros()).into());
-        values.push(message.id.into());
-        let status = connection.execute(query, values).await?;
-        if status.rows_affected() != 1 {
-            return Err(anyhow!("Cannot delete contact"));
+/// End-to-end session with a contact supporting [`Features::MESSAGE_RATCHET`].
+struct ChatSession {
+    contact_id: i64,
+    // Identities of different schemes send messages without a session
+    supported: bool,
+    session: Option<RatchetSession>,
+    // Own init waiting for the accept of the contact
+    pending: Option<PendingSession>,
+    // Id of the replaced session, its packets may still arrive
+    previous: Option<Vec<u8>>,
+}
+
+impl ChatSession {
+    async fn load(store: &Arc<dyn MessageStore>, contact_id: i64, supported: bool) -> Self {
+        let session = match store.get_chat_session(contact_id).await {
+            Ok(Some(state)) => match RatchetSession::from_bytes(&state) {
+                Ok(v) => Some(v),
+                Err(err) => {
+                    tracing::error!(?err, "Failed to decode chat session");
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(err) => {
+                tracing::error!(?err, "Failed to load chat session");
+                None
+            }
+        };
+        Self {
+            contact_id,
+            supported,
+            session,
+            pending: None,
+            previous: None,
         }
-        Ok(message)
     }
 
-    fn columns_without_id(columns: &ColumnIndex, id_name: &str) -> ColumnIndex {
-        let mut result = ColumnIndex::builder();
-        for name in columns.columns() {
-            if name != id_name {
-                result.add(name);
+    /// Whether messages to the contact are sealed with the session.
+    fn is_used(&self, contact_handle: &ContactHandle) -> bool {
+        self.supported
+            && contact_handle
+                .features()
+                .contains(Features::MESSAGE_RATCHET)
+    }
+
+    async fn save(&self, store: &Arc<dyn MessageStore>) {
+        let state = match self.session.as_ref().map(|v| v.to_bytes()).transpose() {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!(?err, "Failed to encode chat session");
+                return;
             }
+        };
+        if let Err(err) = store.set_chat_session(self.contact_id, state).await {
+            tracing::error!(?err, "Failed to save chat session");
         }
-        result.build()
     }
 
-    fn format_columns(columns: &ColumnIndex) -> String {
-        let mut result = String::new();
-        for column in columns.columns() {
-            if !result.is_empty() {
-                result.push_str(", ");
+    /// Init packet of the own pending session, started if there is none.
+    fn init_packet(&mut self, private_key: &PrivateKey) -> ChatPacket {
+        let pending = self
+            .pending
+            .get_or_insert_with(|| PendingSession::new(private_key));
+        ChatPacket::SessionInit(pending.init_packet())
+    }
+
+    /// Seals the packet with the session, without a session the init packet
+    /// is returned instead and the packet is sent again once it is accepted.
+    async fn seal(
+        &mut self,
+        store: &Arc<dyn MessageStore>,
+        private_key: &PrivateKey,
+        packet: ChatPacket,
+    ) -> Option<ChatPacket> {
+        let Some(session) = self.session.as_mut() else {
+            tracing::debug!("Starting chat session");
+            return Some(self.init_packet(private_key));
+        };
+        let plaintext = match bincode::serialize(&packet) {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!(?err, "Failed to encode chat packet");
+                return None;
             }
-            result.push('"');
-            result.push_str(&column);
-            result.push('"');
-        }
-        result
+        };
+        let (header, ciphertext) = match session.encrypt(&plaintext) {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!(?err, "Failed to seal chat packet");
+                return None;
+            }
+        };
+        let packet = SealedChatPacket {
+            session: session.id().to_vec(),
+            header,
+            ciphertext,
+        };
+        // The used key must not be used again after a restart
+        self.save(store).await;
+        Some(ChatPacket::Sealed(packet))
     }
 
-    fn format_values(values: &[Value]) -> String {
-        let mut result = String::new();
-        for i in 0..values.len() {
-            if !result.is_empty() {
-                result.push_str(", ");
+    /// Opens the sealed packet. A packet of an unknown session means that
+    /// the session was lost or replaced here, a new one is started.
+    async fn open(
+        &mut self,
+        contact_handle: &ContactHandle,
+        store: &Arc<dyn MessageStore>,
+        private_key: &PrivateKey,
+        packet: SealedChatPacket,
+    ) -> Option<ChatPacket> {
+        let session = match self.session.as_mut() {
+            Some(v) if v.id() == packet.session.as_slice() => v,
+            _ if self.previous.as_deref() == Some(packet.session.as_slice()) => {
+                tracing::debug!("Ignoring packet of previous chat session");
+                return None;
+            }
+            _ => {
+                if self.pending.is_none() {
+                    tracing::debug!("Received packet of unknown chat session");
+                    let init_packet = self.init_packet(private_key);
+                    if let Err(err) = contact_handle.send_chat_packet(init_packet).await {
+                        tracing::warn!(?err, "Failed to send chat packet");
+                    }
+                }
+                return None;
+            }
+        };
+        let plaintext = match session.decrypt(&packet.header, &packet.ciphertext) {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::warn!(?err, "Failed to open sealed chat packet");
+                return None;
+            }
+        };
+        self.save(store).await;
+        match bincode::deserialize(&plaintext) {
+            Ok(v) => Some(v),
+            Err(err) => {
+                tracing::warn!(?err, "Failed to decode sealed chat packet");
+                None
             }
-            result.push('?');
-            result.push_str(&(i + 1).to_string());
         }
-        result
     }
 
-    fn next_tick() -> Instant {
-        Instant::now() + Duration::from_millis(rand::thread_rng().gen_range(1000..5000))
+    /// Accepts the session started by the contact, returns whether the
+    /// session is new. When both sides start a session at once the one with
+    /// the smaller init key is kept.
+    async fn accept(
+        &mut self,
+        contact_handle: &ContactHandle,
+        store: &Arc<dyn MessageStore>,
+        private_key: &PrivateKey,
+        public_key: &PublicKey,
+        packet: ChatSessionInitPacket,
+    ) -> bool {
+        if let Some(pending) = &self.pending
+            && pending.init_key() < packet.init_key.as_slice()
+        {
+            tracing::debug!("Ignoring chat session init, own one is kept");
+            return false;
+        }
+        let started = match &self.session {
+            // The accept was lost, it is sent again
+            Some(session) if session.id() == packet.init_key.as_slice() => false,
+            _ => match RatchetSession::accept(private_key, public_key, &packet) {
+                Ok(session) => {
+                    tracing::debug!("Accepted chat session");
+                    self.replace(session);
+                    self.pending = None;
+                    self.save(store).await;
+                    true
+                }
+                Err(err) => {
+                    tracing::warn!(?err, "Failed to accept chat session");
+                    return false;
+                }
+            },
+        };
+        let Some(packet) = self.session.as_ref().map(|v| v.accept_packet(private_key)) else {
+            return started;
+        };
+        if let Err(err) = contact_handle
+            .send_chat_packet(ChatPacket::SessionAccept(packet))
+            .await
+        {
+            tracing::warn!(?err, "Failed to send chat packet");
+        }
+        started
+    }
+
+    fn replace(&mut self, session: RatchetSession) {
+        if let Some(previous) = self.session.replace(session) {
+            self.previous = Some(previous.id().to_vec());
+        }
+    }
+
+    /// Completes the own pending session, returns whether it is done.
+    async fn complete(
+        &mut self,
+        store: &Arc<dyn MessageStore>,
+        private_key: &PrivateKey,
+        public_key: &PublicKey,
+        packet: ChatSessionAcceptPacket,
+    ) -> bool {
+        let Some(pending) = self
+            .pending
+            .as_ref()
+            .filter(|v| v.init_key() == packet.init_key.as_slice())
+        else {
+            tracing::debug!("Ignoring accept of unknown chat session");
+            return false;
+        };
+        match pending.complete(private_key, public_key, &packet) {
+            Ok(session) => {
+                tracing::debug!("Chat session is established");
+                self.replace(session);
+                self.pending = None;
+                self.save(store).await;
+                true
+            }
+            Err(err) => {
+                tracing::warn!(?err, "Failed to complete chat session");
+                false
+            }
+        }
     }
 }
 
 struct ChatHandleInner {
     contact_handle: ContactHandle,
     contact: Arc<Mutex<Contact>>,
-    storage: Arc<TokioMutex<Storage>>,
+    store: Arc<dyn MessageStore>,
     command_tx: mpsc::Sender<HandleCommand>,
     recv_rx: TokioMutex<mpsc::Receiver<Message>>,
     main_task: JoinHandle<()>,
@@ -556,5 +1072,5 @@ impl Drop for ChatHandleInner {
 }
 
 enum HandleCommand {
-    SendMessage(Message),
+    SendMessages(Vec<Message>),
 }
@@ -1,13 +1,18 @@
 use async_trait::async_trait;
 use ntied_transport::Address;
 
-use crate::models::Message;
+use crate::models::{Contact, Message};
 
 #[async_trait]
 pub trait ChatListener: Send + Sync {
     async fn on_incoming_message(&self, address: Address, message: Message);
 
+    /// Called after `on_incoming_message` unless the contact is muted.
+    async fn on_message_notification(&self, address: Address, message: Message);
+
     async fn on_outgoing_message(&self, address: Address, message: Message);
+
+    async fn on_contact_updated(&self, address: Address, contact: Contact);
 }
 
 pub(super) struct StubListener;
@@ -19,8 +24,18 @@ impl ChatListener for StubListener {
         _ = message;
     }
 
+    async fn on_message_notification(&self, address: Address, message: Message) {
+        _ = address;
+        _ = message;
+    }
+
     async fn on_outgoing_message(&self, address: Address, message: Message) {
         _ = address;
         _ = message;
     }
+
+    async fn on_contact_updated(&self, address: Address, contact: Contact) {
+        _ = address;
+        _ = contact;
+    }
 }
@@ -1,23 +1,57 @@
 use std::collections::{HashMap, hash_map};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use anyhow::{Context as _, anyhow};
-use ntied_crypto::PublicKey;
-use ntied_transport::Address;
-use tokio::sync::Mutex as TokioMutex;
-use tokio_sqlite::Value;
+use anyhow::anyhow;
+use ntied_crypto::{PrivateKey, PublicKey};
+use ntied_transport::{Address, ToAddress as _};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex as TokioMutex, Notify};
+use tokio::task::JoinHandle;
 
 use crate::contact::ContactManager;
-use crate::models::{ColumnIndex, Contact, DateTime};
+use crate::models::{Base64, Contact, DateTime};
 use crate::packet::ContactProfile;
-use crate::storage::Storage;
+use crate::storage::{MessageStore, SqliteStore, Storage};
 
 use super::{ChatHandle, ChatListener, StubListener};
 
+/// Behavior of archived chats.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveOptions {
+    /// Move the chat back to the main list when a new message arrives.
+    pub unarchive_on_message: bool,
+    /// Count unread messages of archived chats.
+    pub unread_badges: bool,
+}
+
+/// Automatic deletion of old messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionOptions {
+    /// Delete messages older than this number of days, all messages are kept if not set.
+    pub max_age_days: Option<u32>,
+}
+
+impl RetentionOptions {
+    /// Messages created before the returned time are expired.
+    pub fn expire_before(&self, now: DateTime) -> Option<DateTime> {
+        let days = self.max_age_days?;
+        Some(DateTime(now.0 - chrono::Duration::days(days.into())))
+    }
+}
+
 pub struct ChatManager {
-    storage: Arc<TokioMutex<Storage>>,
+    store: Arc<dyn MessageStore>,
     contact_manager: Arc<ContactManager>,
+    private_key: PrivateKey,
     chats: Arc<TokioMutex<HashMap<Address, ChatHandle>>>,
+    archive_options: Arc<Mutex<ArchiveOptions>>,
+    retention_options: Arc<Mutex<RetentionOptions>>,
+    // Wakes the sweep up when retention options change
+    sweep_notify: Arc<Notify>,
+    sweep_task: JoinHandle<()>,
     listener: Arc<dyn ChatListener>,
 }
 
@@ -37,31 +71,209 @@ impl ChatManager {
     where
         L: ChatListener + 'static,
     {
-        Self::create_tables(storage.as_ref()).await?;
-        let contacts = Self::get_contacts(storage.as_ref()).await?;
+        let store = SqliteStore::new(storage);
+        store.create_tables().await?;
+        Self::with_store(Arc::new(store), contact_manager, listener).await
+    }
+
+    /// Create a chat manager on top of an arbitrary message store.
+    pub async fn with_store<L>(
+        store: Arc<dyn MessageStore>,
+        contact_manager: Arc<ContactManager>,
+        listener: Arc<L>,
+    ) -> Result<Self, anyhow::Error>
+    where
+        L: ChatListener + 'static,
+    {
+        let contacts = store.get_contacts().await?;
+        let private_key = contact_manager.private_key().clone();
+        let archive_options = Arc::new(Mutex::new(ArchiveOptions::default()));
         let mut chats = HashMap::new();
         for contact in contacts {
             let address = contact.address;
             let public_key = contact.public_key.clone();
             let profile = ContactProfile {
                 name: contact.name.clone(),
+                avatar: contact.avatar.clone().map(Base64),
             };
             let contact_handle = contact_manager
                 .add_contact(address, public_key, profile)
                 .await;
-            let handle =
-                ChatHandle::new(contact_handle, contact, storage.clone(), listener.clone());
+            let handle = ChatHandle::new(
+                contact_handle,
+                contact,
+                private_key.clone(),
+                store.clone(),
+                archive_options.clone(),
+                listener.clone(),
+            );
             chats.insert(address, handle);
         }
         let chats = Arc::new(TokioMutex::new(chats));
+        let retention_options = Arc::new(Mutex::new(RetentionOptions::default()));
+        let sweep_notify = Arc::new(Notify::new());
+        let sweep_task = tokio::spawn(Self::sweep_loop(
+            store.clone(),
+            retention_options.clone(),
+            sweep_notify.clone(),
+        ));
         Ok(Self {
-            storage,
+            store,
             contact_manager,
+            private_key,
             chats,
+            archive_options,
+            retention_options,
+            sweep_notify,
+            sweep_task,
             listener,
         })
     }
 
+    /// Interval between sweeps of expired messages.
+    const SWEEP_INTERVAL: Duration = Duration::from_secs(3600);
+
+    async fn sweep_loop(
+        store: Arc<dyn MessageStore>,
+        options: Arc<Mutex<RetentionOptions>>,
+        notify: Arc<Notify>,
+    ) {
+        loop {
+            let options = *options.lock().unwrap();
+            if let Err(err) = Self::sweep(&store, options).await {
+                tracing::error!(?err, "Failed to delete expired messages");
+            }
+            _ = tokio::time::timeout(Self::SWEEP_INTERVAL, notify.notified()).await;
+        }
+    }
+
+    async fn sweep(
+        store: &Arc<dyn MessageStore>,
+        options: RetentionOptions,
+    ) -> Result<u64, anyhow::Error> {
+        let Some(time) = options.expire_before(DateTime::now()) else {
+            return Ok(0);
+        };
+        let count = store.delete_messages_before(time).await?;
+        if count > 0 {
+            tracing::debug!(count, "Deleted expired messages");
+        }
+        Ok(count)
+    }
+
+    pub fn archive_options(&self) -> ArchiveOptions {
+        *self.archive_options.lock().unwrap()
+    }
+
+    pub fn set_archive_options(&self, options: ArchiveOptions) {
+        *self.archive_options.lock().unwrap() = options;
+    }
+
+    pub fn retention_options(&self) -> RetentionOptions {
+        *self.retention_options.lock().unwrap()
+    }
+
+    /// Apply new retention options, expired messages are deleted right away.
+    pub fn set_retention_options(&self, options: RetentionOptions) {
+        *self.retention_options.lock().unwrap() = options;
+        self.sweep_notify.notify_one();
+    }
+
+    /// Delete messages expired under the current retention options, returns
+    /// the number of deleted messages.
+    pub async fn sweep_expired_messages(&self) -> Result<u64, anyhow::Error> {
+        Self::sweep(&self.store, self.retention_options()).await
+    }
+
+    /// Hide the chat from the main list or bring it back, history is kept.
+    pub async fn set_archived(
+        &self,
+        address: Address,
+        archived: bool,
+    ) -> Result<(), anyhow::Error> {
+        let handle = self
+            .get_contact_chat(address)
+            .await
+            .ok_or(anyhow!("Contact chat not found"))?;
+        handle.set_archived(archived).await
+    }
+
+    /// Silence notifications and ringing of the contact, unread counts are kept.
+    pub async fn set_muted(&self, address: Address, muted: bool) -> Result<(), anyhow::Error> {
+        let handle = self
+            .get_contact_chat(address)
+            .await
+            .ok_or(anyhow!("Contact chat not found"))?;
+        handle.set_muted(muted).await
+    }
+
+    /// Whether the contact is muted, unknown contacts are not.
+    pub async fn is_muted(&self, address: Address) -> bool {
+        self.get_contact_chat(address)
+            .await
+            .is_some_and(|handle| handle.is_muted())
+    }
+
+    /// Answer calls of the verified contact without user action.
+    pub async fn set_auto_answer(
+        &self,
+        address: Address,
+        auto_answer: bool,
+    ) -> Result<(), anyhow::Error> {
+        let handle = self
+            .get_contact_chat(address)
+            .await
+            .ok_or(anyhow!("Contact chat not found"))?;
+        handle.set_auto_answer(auto_answer).await
+    }
+
+    /// Pin the chat after already pinned ones or unpin it, returns the new
+    /// pin order.
+    pub async fn set_pinned(
+        &self,
+        address: Address,
+        pinned: bool,
+    ) -> Result<Option<i64>, anyhow::Error> {
+        let chats = self.chats.lock().await;
+        let handle = chats
+            .get(&address)
+            .ok_or(anyhow!("Contact chat not found"))?;
+        let pin_order = if pinned {
+            if let Some(order) = handle.pin_order() {
+                return Ok(Some(order));
+            }
+            let last = chats.values().filter_map(|c| c.pin_order()).max();
+            Some(last.map_or(0, |v| v + 1))
+        } else {
+            None
+        };
+        handle.set_pin_order(pin_order).await?;
+        Ok(pin_order)
+    }
+
+    /// Fingerprint of the own identity key to compare with contacts.
+    pub fn own_fingerprint(&self) -> String {
+        self.private_key.public_key().fingerprint()
+    }
+
+    /// Mark the contact key as confirmed out-of-band or drop the mark.
+    pub async fn set_verified(
+        &self,
+        address: Address,
+        verified: bool,
+    ) -> Result<(), anyhow::Error> {
+        let handle = self
+            .get_contact_chat(address)
+            .await
+            .ok_or(anyhow!("Contact chat not found"))?;
+        handle.set_verified(verified).await
+    }
+
+    /// Wait for pending storage writes and flush them to disk.
+    pub async fn flush(&self) -> Result<(), anyhow::Error> {
+        self.store.flush().await
+    }
+
     pub async fn list_contact_chats(&self) -> Vec<ChatHandle> {
         let mut result = Vec::new();
         let chats = self.chats.lock().await;
@@ -85,19 +297,30 @@ impl ChatManager {
                 return Ok(entry.get().clone());
             }
             hash_map::Entry::Vacant(entry) => {
+                // Avatar comes from the profile received at handshake
+                let avatar = contact_handle.profile().and_then(|p| p.avatar).map(|a| a.0);
                 let contact = Contact {
                     id: 0,
                     address,
                     public_key: public_key.clone(),
                     name,
                     local_name,
+                    avatar,
+                    archived: false,
+                    pinned: false,
+                    pin_order: 0,
+                    verified: false,
+                    muted: false,
+                    auto_answer: false,
                     create_time: DateTime::now(),
                 };
-                let contact = self.create_contact(contact).await?;
+                let contact = self.store.create_contact(contact).await?;
                 let handle = ChatHandle::new(
                     contact_handle,
                     contact,
-                    self.storage.clone(),
+                    self.private_key.clone(),
+                    self.store.clone(),
+                    self.archive_options.clone(),
                     self.listener.clone(),
                 );
                 entry.insert(handle.clone());
@@ -111,166 +334,69 @@ impl ChatManager {
         chats.get(&address).cloned()
     }
 
-    pub async fn remove_contact_chat(&self, address: Address) -> Result<(), anyhow::Error> {
+    /// Move the chat to the new identity key announced by the contact.
+    /// History is preserved, the contact is reachable by the new address.
+    pub async fn rotate_contact_key(
+        &self,
+        address: Address,
+        public_key: PublicKey,
+    ) -> Result<ChatHandle, anyhow::Error> {
+        let new_address = public_key
+            .to_address()
+            .map_err(|e| anyhow!("Invalid public key: {}", e))?;
         let mut chats = self.chats.lock().await;
-        if let hash_map::Entry::Occupied(entry) = chats.entry(address) {
-            self.delete_contact(entry.get().contact().id).await?;
-            entry.remove();
+        let old_handle = chats
+            .get(&address)
+            .cloned()
+            .ok_or(anyhow!("Contact chat not found"))?;
+        let mut contact = old_handle.contact();
+        contact.address = new_address;
+        contact.public_key = public_key.clone();
+        if contact.verified {
+            tracing::warn!(%address, %new_address, "Key of verified contact changed");
+            contact.verified = false;
+            contact.auto_answer = false;
         }
+        let contact = self.store.update_contact(contact).await?;
+        chats.remove(&address);
+        let profile = old_handle
+            .contact_handle()
+            .profile()
+            .unwrap_or_else(|| ContactProfile {
+                name: contact.name.clone(),
+                avatar: contact.avatar.clone().map(Base64),
+            });
+        drop(old_handle);
         self.contact_manager.remove_contact(address).await;
-        Ok(())
-    }
-
-    async fn get_contacts(storage: &TokioMutex<Storage>) -> Result<Vec<Contact>, anyhow::Error> {
-        let columns = Contact::columns();
-        let query = format!(
-            "SELECT {} FROM \"contact\" ORDER BY \"id\"",
-            Self::format_columns(columns)
-        );
-        let mut storage = storage.lock().await;
-        let connection = storage.connection().await;
-        let mut rows = connection.query(query, vec![]).await?;
-        assert_eq!(columns.columns(), rows.columns());
-        let mut result = Vec::new();
-        while let Some(row) = rows.next().await {
-            let row = row?;
-            let values = row.into_values();
-            let contact = Contact::from_values(values, columns)?;
-            result.push(contact);
-        }
-        Ok(result)
-    }
-
-    async fn create_contact(&self, mut contact: Contact) -> Result<Contact, anyhow::Error> {
-        let columns = Self::columns_without_id(Contact::columns(), "id");
-        let values = contact.values(&columns);
-        let query = format!(
-            "INSERT INTO \"contact\" ({}) VALUES ({})",
-            Self::format_columns(&columns),
-            Self::format_values(&values),
+        let contact_handle = self
+            .contact_manager
+            .add_rotated_contact(new_address, public_key, profile)
+            .await;
+        let handle = ChatHandle::new(
+            contact_handle,
+            contact,
+            self.private_key.clone(),
+            self.store.clone(),
+            self.archive_options.clone(),
+            self.listener.clone(),
         );
-        let mut storage = self.storage.lock().await;
-        let connection = storage.connection().await;
-        let status = connection.execute(query, values).await?;
-        contact.id = status
-            .last_insert_id()
-            .ok_or(anyhow!("Cannot retrieve contact id"))?;
-        Ok(contact)
+        chats.insert(new_address, handle.clone());
+        Ok(handle)
     }
 
-    async fn delete_contact(&self, id: i64) -> Result<(), anyhow::Error> {
-        let query = "DELETE FROM \"contact\" WHERE \"id\" = ?1";
-        let mut storage = self.storage.lock().await;
-        let connection = storage.connection().await;
-        let status = connection.execute(query, vec![Value::Integer(id)]).await?;
-        if status.rows_affected() != 1 {
-            return Err(anyhow!("Cannot delete contact"));
+    pub async fn remove_contact_chat(&self, address: Address) -> Result<(), anyhow::Error> {
+        let mut chats = self.chats.lock().await;
+        if let hash_map::Entry::Occupied(entry) = chats.entry(address) {
+            self.store.delete_contact(entry.get().contact().id).await?;
+            entry.remove();
         }
+        self.contact_manager.remove_contact(address).await;
         Ok(())
     }
+}
 
-    async fn create_tables(storage: &TokioMutex<Storage>) -> Result<(), anyhow::Error> {
-        let mut storage = storage.lock().await;
-        let conn = storage.connection().await;
-
-        conn.execute("PRAGMA foreign_keys = ON", Vec::<Value>::new())
-            .await
-            .context("Failed to enable foreign keys")?;
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS \"config\" (
-                \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,
-                \"key\" TEXT NOT NULL UNIQUE,
-                \"value\" TEXT NOT NULL
-            )",
-            Vec::new(),
-        )
-        .await
-        .context("Failed to create config table")?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS \"contact\" (
-                    \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,
-                    \"address\" TEXT NOT NULL UNIQUE,
-                    \"public_key\" BLOB NOT NULL,
-                    \"name\" TEXT NOT NULL,
-                    \"local_name\" TEXT,
-                    \"create_time\" BIGINT NOT NULL
-                )",
-            Vec::<Value>::new(),
-        )
-        .await
-        .context("Failed to create contact table")?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS \"message\" (
-                    \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,
-                    \"contact_id\" INTEGER NOT NULL,
-                    \"message_id\" TEXT NOT NULL UNIQUE,
-                    \"log_id\" INTEGER,
-                    \"incoming\" INTEGER NOT NULL,
-                    \"kind\" TEXT NOT NULL,
-                    \"content\" TEXT NOT NULL,
-                    \"create_time\" BIGINT NOT NULL,
-                    \"receive_time\" BIGINT,
-                    \"read_time\" BIGINT,
-                    FOREIGN KEY (\"contact_id\") REFERENCES \"contact\" (\"id\") ON DELETE CASCADE
-                )",
-            Vec::<Value>::new(),
-        )
-        .await
-        .context("Failed to create message table")?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS message__contact_id_log_id_idx
-                 ON \"message\" (\"contact_id\", \"log_id\")",
-            Vec::<Value>::new(),
-        )
-        .await
-        .context("Failed to create message__contact_id_log_id_idx index")?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS message__contact_id_create_time_idx
-                 ON \"message\" (\"contact_id\", \"create_time\")",
-            Vec::<Value>::new(),
-        )
-        .await
-        .context("Failed to create message__contact_id_create_time_idx index")?;
-
-        Ok(())
-    }
-
-    fn columns_without_id(columns: &ColumnIndex, id_name: &str) -> ColumnIndex {
-        let mut result = ColumnIndex::builder();
-        for name in columns.columns() {
-            if name != id_name {
-                result.add(name);
-            }
-        }
-        result.build()
-    }
-
-    fn format_columns(columns: &ColumnIndex) -> String {
-        let mut result = String::new();
-        for column in columns.columns() {
-            if !result.is_empty() {
-                result.push_str(", ");
-            }
-            result.push('"');
-            result.push_str(&column);
-            result.push('"');
-        }
-        result
-    }
-
-    fn format_values(values: &[Value]) -> String {
-        let mut result = String::new();
-        for i in 0..values.len() {
-            if !result.is_empty() {
-                result.push_str(", ");
-            }
-            result.push('?');
-            result.push_str(&(i + 1).to_string());
-        }
-        result
+impl Drop for ChatManager {
+    fn drop(&mut self) {
+        self.sweep_task.abort();
     }
 }
@@ -1,7 +1,11 @@
 mod handle;
 mod listener;
 mod manager;
+mod order;
+mod ratchet;
 
 pub use handle::*;
 pub use listener::*;
 pub use manager::*;
+pub use order::*;
+pub use ratchet::*;
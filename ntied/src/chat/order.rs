@@ -0,0 +1,29 @@
+use std::cmp::Ordering;
+
+/// Sort key of a chat in the chat list.
+///
+/// Pinned chats come first in pin order, the rest follow by recent
+/// activity with idle chats last.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChatOrder {
+    pub pin_order: Option<i64>,
+    /// Id of the latest message, ids grow with every stored message.
+    pub last_activity: Option<i64>,
+}
+
+impl Ord for ChatOrder {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.pin_order, other.pin_order) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => other.last_activity.cmp(&self.last_activity),
+        }
+    }
+}
+
+impl PartialOrd for ChatOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::VecDeque;
+
+use anyhow::anyhow;
+use ntied_crypto::{Cipher, PrivateKey, PublicKey, SharedSecret, X25519Key, derive_secret};
+use serde::{Deserialize, Serialize};
+
+use crate::packet::{ChatSessionAcceptPacket, ChatSessionInitPacket, RatchetHeader};
+
+/// Own half of a session started with a [`ChatSessionInitPacket`], it is
+/// completed by the accept of the contact.
+#[derive(Clone)]
+pub struct PendingSession {
+    ephemeral_key: X25519Key,
+    init_key: Vec<u8>,
+    // Signature of the init key by the own identity
+    signature: Vec<u8>,
+}
+
+impl PendingSession {
+    /// Starts a session with a new ephemeral key signed by the identity.
+    pub fn new(identity_key: &PrivateKey) -> Self {
+        let ephemeral_key = X25519Key::generate();
+        let init_key = ephemeral_key.public_key().to_vec();
+        let signature = identity_key.sign(init_signed_data(&init_key));
+        Self {
+            ephemeral_key,
+            init_key,
+            signature,
+        }
+    }
+
+    /// Ephemeral public key sent to the contact.
+    pub fn init_key(&self) -> &[u8] {
+        &self.init_key
+    }
+
+    pub fn init_packet(&self) -> ChatSessionInitPacket {
+        ChatSessionInitPacket {
+            init_key: self.init_key.clone(),
+            signature: self.signature.clone(),
+        }
+    }
+
+    /// Creates the session from the accept of the contact, the accept must
+    /// be signed by the identity of the contact.
+    pub fn complete(
+        &self,
+        identity_key: &PrivateKey,
+        peer_key: &PublicKey,
+        packet: &ChatSessionAcceptPacket,
+    ) -> Result<RatchetSession, anyhow::Error> {
+        if packet.init_key != self.init_key {
+            return Err(anyhow!("Accept of another session"));
+        }
+        let data = accept_signed_data(&packet.init_key, &packet.ratchet_key);
+        if !peer_key.verify(data, &packet.signature).unwrap_or(false) {
+            return Err(anyhow!("Invalid signature of session accept"));
+        }
+        let root_key =
+            RatchetSession::root_key(&self.init_key, &identity_secret(identity_key, peer_key)?);
+        let mut session = RatchetSession {
+            id: self.init_key.clone(),
+            root_key,
+            own_key: self.ephemeral_key.clone(),
+            remote_key: None,
+            send_chain: Chain::default(),
+            recv_chain: None,
+            prev_count: 0,
+            skipped: VecDeque::new(),
+        };
+        session.ratchet_step(&packet.ratchet_key)?;
+        Ok(session)
+    }
+}
+
+/// Double ratchet session of a chat with a contact.
+///
+/// Every sealed packet is encrypted with its own key taken from a symmetric
+/// chain, used keys are dropped, so a later state does not open earlier
+/// packets. The chains are restarted from a new ECDH secret each time the
+/// direction of the conversation changes, so a leaked state stops opening
+/// packets after a round trip.
+#[derive(Clone)]
+pub struct RatchetSession {
+    id: Vec<u8>,
+    root_key: [u8; 32],
+    own_key: X25519Key,
+    remote_key: Option<Vec<u8>>,
+    send_chain: Chain,
+    recv_chain: Option<Chain>,
+    prev_count: u32,
+    // Keys of packets not received yet, oldest first
+    skipped: VecDeque<SkippedKey>,
+}
+
+impl RatchetSession {
+    /// Packets of one chain that may be lost or reordered.
+    pub const MAX_SKIP: u32 = 1000;
+    const MAX_SKIPPED_KEYS: usize = 2000;
+    const CIPHER: Cipher = Cipher::ChaCha20Poly1305;
+    const ROOT_CONTEXT: &[u8] = b"ntied-ratchet-root";
+    const CHAIN_CONTEXT: &[u8] = b"ntied-ratchet-chain";
+    const MESSAGE_CONTEXT: &[u8] = b"ntied-ratchet-message";
+    const PACKET_CONTEXT: &[u8] = b"ntied-ratchet-packet";
+    const INIT_CONTEXT: &[u8] = b"ntied-ratchet-init";
+    const ACCEPT_CONTEXT: &[u8] = b"ntied-ratchet-accept";
+
+    /// Whether a session can be set up between the identities, identities
+    /// of different schemes have no common secret.
+    pub fn is_supported(identity_key: &PrivateKey, peer_key: &PublicKey) -> bool {
+        identity_key.scheme() == peer_key.scheme()
+    }
+
+    /// Accepts the session initiated by the contact, the init must be signed
+    /// by the identity of the contact. The first ratchet key of the returned
+    /// session is sent back with [`RatchetSession::accept_packet`].
+    pub fn accept(
+        identity_key: &PrivateKey,
+        peer_key: &PublicKey,
+        packet: &ChatSessionInitPacket,
+    ) -> Result<Self, anyhow::Error> {
+        let init_key = packet.init_key.as_slice();
+        if !peer_key
+            .verify(init_signed_data(init_key), &packet.signature)
+            .unwrap_or(false)
+        {
+            return Err(anyhow!("Invalid signature of session init"));
+        }
+        let root_key = Self::root_key(init_key, &identity_secret(identity_key, peer_key)?);
+        let own_key = X25519Key::generate();
+        let (root_key, chain_key) =
+            Self::next_root_key(&root_key, &diffie_hellman(&own_key, init_key)?);
+        Ok(Self {
+            id: init_key.to_vec(),
+            root_key,
+            own_key,
+            remote_key: Some(init_key.to_vec()),
+            send_chain: Chain::new(chain_key),
+            recv_chain: None,
+            prev_count: 0,
+            skipped: VecDeque::new(),
+        })
+    }
+
+    /// Init key of the session.
+    pub fn id(&self) -> &[u8] {
+        &self.id
+    }
+
+    /// Current own ratchet public key.
+    pub fn ratchet_key(&self) -> Vec<u8> {
+        self.own_key.public_key().to_vec()
+    }
+
+    /// Accept of the session signed by the identity.
+    pub fn accept_packet(&self, identity_key: &PrivateKey) -> ChatSessionAcceptPacket {
+        let ratchet_key = self.ratchet_key();
+        let signature = identity_key.sign(accept_signed_data(&self.id, &ratchet_key));
+        ChatSessionAcceptPacket {
+            init_key: self.id.clone(),
+            ratchet_key,
+            signature,
+        }
+    }
+
+    /// Encrypts the packet with the next key of the sending chain.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<(RatchetHeader, Vec<u8>), anyhow::Error> {
+        let header = RatchetHeader {
+            ratchet_key: self.ratchet_key(),
+            prev_count: self.prev_count,
+            count: self.send_chain.count,
+        };
+        let message_key = self.send_chain.next_message_key();
+        let ciphertext = Self::seal(&message_key, &header, plaintext)?;
+        Ok((header, ciphertext))
+    }
+
+    /// Decrypts the packet, the session is not changed on failure.
+    pub fn decrypt(
+        &mut self,
+        header: &RatchetHeader,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let mut session = self.clone();
+        let plaintext = session.decrypt_inner(header, ciphertext)?;
+        *self = session;
+        Ok(plaintext)
+    }
+
+    fn decrypt_inner(
+        &mut self,
+        header: &RatchetHeader,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let skipped = self
+            .skipped
+            .iter()
+            .position(|v| v.ratchet_key == header.ratchet_key && v.count == header.count);
+        if let Some(pos) = skipped {
+            let key = self.skipped.remove(pos).unwrap();
+            return Self::open(&key.message_key, header, ciphertext);
+        }
+        if self.remote_key.as_ref() != Some(&header.ratchet_key) {
+            self.skip_keys(header.prev_count)?;
+            self.ratchet_step(&header.ratchet_key)?;
+        }
+        self.skip_keys(header.count)?;
+        let chain = self
+            .recv_chain
+            .as_mut()
+            .ok_or(anyhow!("Receiving chain is not started"))?;
+        if header.count < chain.count {
+            return Err(anyhow!("Packet key is already used"));
+        }
+        let message_key = chain.next_message_key();
+        Self::open(&message_key, header, ciphertext)
+    }
+
+    /// Keeps keys of the receiving chain up to the packet number.
+    fn skip_keys(&mut self, until: u32) -> Result<(), anyhow::Error> {
+        let Some(chain) = self.recv_chain.as_mut() else {
+            return Ok(());
+        };
+        if until > chain.count + Self::MAX_SKIP {
+            return Err(anyhow!("Too many skipped packets"));
+        }
+        let ratchet_key = self.remote_key.clone().unwrap_or_default();
+        while chain.count < until {
+            let count = chain.count;
+            let message_key = chain.next_message_key();
+            self.skipped.push_back(SkippedKey {
+                ratchet_key: ratchet_key.clone(),
+                count,
+                message_key,
+            });
+        }
+        while self.skipped.len() > Self::MAX_SKIPPED_KEYS {
+            self.skipped.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Restarts both chains with the new ratchet key of the contact.
+    fn ratchet_step(&mut self, ratchet_key: &[u8]) -> Result<(), anyhow::Error> {
+        let (root_key, chain_key) =
+            Self::next_root_key(&self.root_key, &diffie_hellman(&self.own_key, ratchet_key)?);
+        self.recv_chain = Some(Chain::new(chain_key));
+        self.own_key = X25519Key::generate();
+        let (root_key, chain_key) =
+            Self::next_root_key(&root_key, &diffie_hellman(&self.own_key, ratchet_key)?);
+        self.root_key = root_key;
+        self.prev_count = self.send_chain.count;
+        self.send_chain = Chain::new(chain_key);
+        self.remote_key = Some(ratchet_key.to_vec());
+        Ok(())
+    }
+
+    fn root_key(init_key: &[u8], identity_secret: &[u8]) -> [u8; 32] {
+        derive_secret(init_key, identity_secret, Self::ROOT_CONTEXT)
+    }
+
+    fn next_root_key(root_key: &[u8; 32], secret: &[u8]) -> ([u8; 32], [u8; 32]) {
+        let output: [u8; 64] = derive_secret(root_key, secret, Self::ROOT_CONTEXT);
+        let (root_key, chain_key) = output.split_at(32);
+        (root_key.try_into().unwrap(), chain_key.try_into().unwrap())
+    }
+
+    /// Key and nonce of the packet, bound to its header.
+    fn packet_key(
+        message_key: &[u8; 32],
+        header: &RatchetHeader,
+    ) -> Result<(SharedSecret, [u8; 12]), anyhow::Error> {
+        let header = bincode::serialize(header)?;
+        let output: [u8; 44] = derive_secret(message_key, &header, Self::PACKET_CONTEXT);
+        let (key, nonce) = output.split_at(32);
+        Ok((
+            SharedSecret::new(Self::CIPHER, key.try_into().unwrap()),
+            nonce.try_into().unwrap(),
+        ))
+    }
+
+    fn seal(
+        message_key: &[u8; 32],
+        header: &RatchetHeader,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let (secret, nonce) = Self::packet_key(message_key, header)?;
+        secret
+            .encrypt_nonce(&nonce, plaintext)
+            .map_err(|e| anyhow!("Failed to encrypt packet: {}", e))
+    }
+
+    fn open(
+        message_key: &[u8; 32],
+        header: &RatchetHeader,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let (secret, nonce) = Self::packet_key(message_key, header)?;
+        secret
+            .decrypt_nonce(&nonce, ciphertext)
+            .map_err(|e| anyhow!("Failed to decrypt packet: {}", e))
+    }
+
+    /// Serializes the session to be stored.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let state = StoredSession {
+            id: self.id.clone(),
+            root_key: self.root_key,
+            own_key: self.own_key.to_bytes(),
+            remote_key: self.remote_key.clone(),
+            send_chain: self.send_chain.clone(),
+            recv_chain: self.recv_chain.clone(),
+            prev_count: self.prev_count,
+            skipped: self.skipped.iter().cloned().collect(),
+        };
+        Ok(bincode::serialize(&state)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        let state: StoredSession = bincode::deserialize(bytes)?;
+        Ok(Self {
+            id: state.id,
+            root_key: state.root_key,
+            own_key: X25519Key::from_bytes(state.own_key),
+            remote_key: state.remote_key,
+            send_chain: state.send_chain,
+            recv_chain: state.recv_chain,
+            prev_count: state.prev_count,
+            skipped: state.skipped.into(),
+        })
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Chain {
+    key: [u8; 32],
+    count: u32,
+}
+
+impl Chain {
+    fn new(key: [u8; 32]) -> Self {
+        Self { key, count: 0 }
+    }
+
+    /// Moves the chain forward, the previous chain key is forgotten.
+    fn next_message_key(&mut self) -> [u8; 32] {
+        let message_key = derive_secret(&self.key, &[], RatchetSession::MESSAGE_CONTEXT);
+        self.key = derive_secret(&self.key, &[], RatchetSession::CHAIN_CONTEXT);
+        self.count += 1;
+        message_key
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SkippedKey {
+    ratchet_key: Vec<u8>,
+    count: u32,
+    message_key: [u8; 32],
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    id: Vec<u8>,
+    root_key: [u8; 32],
+    own_key: [u8; 32],
+    remote_key: Option<Vec<u8>>,
+    send_chain: Chain,
+    recv_chain: Option<Chain>,
+    prev_count: u32,
+    skipped: Vec<SkippedKey>,
+}
+
+fn diffie_hellman(private_key: &X25519Key, public_key: &[u8]) -> Result<[u8; 32], anyhow::Error> {
+    private_key
+        .diffie_hellman(public_key)
+        .map_err(|e| anyhow!("Failed to compute shared secret: {}", e))
+}
+
+/// Secret of both identities mixed into the root key.
+fn identity_secret(
+    identity_key: &PrivateKey,
+    peer_key: &PublicKey,
+) -> Result<[u8; 32], anyhow::Error> {
+    identity_key
+        .diffie_hellman(peer_key)
+        .map_err(|e| anyhow!("Failed to compute identity secret: {}", e))
+}
+
+fn init_signed_data(init_key: &[u8]) -> Vec<u8> {
+    [RatchetSession::INIT_CONTEXT, init_key].concat()
+}
+
+fn accept_signed_data(init_key: &[u8], ratchet_key: &[u8]) -> Vec<u8> {
+    let mut data = RatchetSession::ACCEPT_CONTEXT.to_vec();
+    data.extend_from_slice(&(init_key.len() as u32).to_be_bytes());
+    data.extend_from_slice(init_key);
+    data.extend_from_slice(ratchet_key);
+    data
+}
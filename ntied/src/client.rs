@@ -0,0 +1,244 @@
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use ntied_transport::Address;
+use tokio::sync::{Mutex as TokioMutex, mpsc};
+
+use crate::audio::import_wav;
+use crate::call::{CallHandle, CallManager};
+use crate::chat::ChatManager;
+use crate::config::ConfigManager;
+use crate::contact::{ConnectionStatus, ContactManager};
+use crate::models::Message;
+use crate::storage::Storage;
+use crate::ui::{UiEvent, UiEventListener};
+
+/// Contact, chat and call managers of one profile with a single stream of
+/// events, for frontends other than the bundled GUI.
+///
+/// Events must be read with [`Client::next_event`], the managers wait when
+/// the stream is full.
+pub struct Client {
+    storage: Arc<TokioMutex<Storage>>,
+    contact_manager: Arc<ContactManager>,
+    chat_manager: Arc<ChatManager>,
+    call_manager: Arc<CallManager>,
+    event_rx: TokioMutex<mpsc::Receiver<UiEvent>>,
+}
+
+impl Client {
+    const EVENT_CAPACITY: usize = 100;
+
+    /// Creates a new profile in `path` with a fresh identity key.
+    pub async fn create(
+        path: &Path,
+        password: &str,
+        name: String,
+        server_addr: SocketAddr,
+    ) -> Result<Self, anyhow::Error> {
+        std::fs::create_dir_all(path)?;
+        let storage = Storage::create(path, password).await?;
+        let storage = Arc::new(TokioMutex::new(storage));
+        let cfg = ConfigManager::new(storage.clone());
+        cfg.init_account(name, password).await?;
+        cfg.set_server_addr(server_addr).await?;
+        Self::start(storage, password).await
+    }
+
+    /// Unlocks the existing profile in `path`.
+    pub async fn open(path: &Path, password: &str) -> Result<Self, anyhow::Error> {
+        let storage = Storage::open(path, password)
+            .await
+            .map_err(|err| anyhow!("Failed to unlock: {err}"))?;
+        Self::start(Arc::new(TokioMutex::new(storage)), password).await
+    }
+
+    async fn start(
+        storage: Arc<TokioMutex<Storage>>,
+        password: &str,
+    ) -> Result<Self, anyhow::Error> {
+        let cfg = ConfigManager::new(storage.clone());
+        let (event_tx, event_rx) = mpsc::channel(Self::EVENT_CAPACITY);
+        let listener = Arc::new(UiEventListener::new(event_tx));
+        let contact_manager = Arc::new(
+            ContactManager::with_server_token(
+                cfg.get_server_addr().await?,
+                cfg.get_server_token().await?,
+                cfg.get_private_key(password).await?,
+                cfg.get_profile().await?,
+                listener.clone(),
+            )
+            .await,
+        );
+        contact_manager.set_wake_recovery(cfg.get_wake_recovery().await.unwrap_or(true));
+        contact_manager.set_focus_refresh(cfg.get_focus_refresh().await.unwrap_or(true));
+        contact_manager.set_auto_accept_keys(cfg.get_auto_accept_keys().await.unwrap_or_default());
+        contact_manager
+            .set_blocked_addresses(cfg.get_blocked_addresses().await.unwrap_or_default());
+        contact_manager
+            .set_pending_key_rotations(cfg.get_pending_key_rotations().await.unwrap_or_default());
+        let chat_manager = Arc::new(
+            ChatManager::with_listener(storage.clone(), contact_manager.clone(), listener.clone())
+                .await?,
+        );
+        chat_manager.set_archive_options(cfg.get_archive_options().await.unwrap_or_default());
+        chat_manager.set_retention_options(cfg.get_retention_options().await.unwrap_or_default());
+        let call_manager = CallManager::with_listener(contact_manager.clone(), listener);
+        call_manager.set_call_waiting(cfg.get_call_waiting().await.unwrap_or_default());
+        call_manager.set_frame_pacing(cfg.get_frame_pacing().await.unwrap_or_default());
+        call_manager
+            .set_follow_default_device(cfg.get_follow_default_device().await.unwrap_or_default());
+        call_manager
+            .set_qos_marking(cfg.get_qos_marking().await.unwrap_or_default())
+            .await;
+        call_manager.set_preferred_codec(cfg.get_preferred_codec().await.unwrap_or_default());
+        call_manager.set_share_mode(cfg.get_audio_share_mode().await.unwrap_or_default());
+        call_manager.set_system_audio(cfg.get_system_audio().await.unwrap_or_default());
+        Ok(Self {
+            storage,
+            contact_manager,
+            chat_manager,
+            call_manager,
+            event_rx: TokioMutex::new(event_rx),
+        })
+    }
+
+    pub fn storage(&self) -> &Arc<TokioMutex<Storage>> {
+        &self.storage
+    }
+
+    pub fn contact_manager(&self) -> &Arc<ContactManager> {
+        &self.contact_manager
+    }
+
+    pub fn chat_manager(&self) -> &Arc<ChatManager> {
+        &self.chat_manager
+    }
+
+    pub fn call_manager(&self) -> &Arc<CallManager> {
+        &self.call_manager
+    }
+
+    pub fn own_address(&self) -> Address {
+        self.contact_manager.get_own_address()
+    }
+
+    /// Waits for the next event, `None` after the managers are gone.
+    ///
+    /// A chat is created for contacts that accepted our request, as the GUI
+    /// does, so messages can be sent to them right after the event.
+    /// Connections to the server are recorded in the recent sessions.
+    pub async fn next_event(&self) -> Option<UiEvent> {
+        let event = self.event_rx.lock().await.recv().await?;
+        if let UiEvent::ContactAccepted { name, address, .. } = &event
+            && let Err(err) = self.add_accepted_chat(address, name.clone()).await
+        {
+            tracing::error!(?err, "Cannot add contact chat");
+        }
+        if let UiEvent::ConnectionStatus(ConnectionStatus::Connected) = &event
+            && let Err(err) = ConfigManager::new(self.storage.clone())
+                .record_connection(&self.contact_manager)
+                .await
+        {
+            tracing::error!(?err, "Cannot record session");
+        }
+        Some(event)
+    }
+
+    /// Sends a contact request to the address.
+    pub async fn request_contact(&self, address: Address) {
+        self.contact_manager.connect_contact(address).await;
+    }
+
+    /// Accepts an incoming contact request and creates the chat.
+    pub async fn accept_contact(&self, address: Address) -> Result<(), anyhow::Error> {
+        let handle = self.contact_manager.connect_contact(address).await;
+        handle
+            .accept()
+            .await
+            .map_err(|err| anyhow!("Cannot accept contact: {err}"))?;
+        let name = handle
+            .profile()
+            .map(|v| v.name)
+            .unwrap_or_else(|| address.to_string());
+        self.add_accepted_chat(&address.to_string(), name).await
+    }
+
+    pub async fn reject_contact(&self, address: Address) -> Result<(), anyhow::Error> {
+        let handle = self.contact_manager.connect_contact(address).await;
+        handle
+            .reject()
+            .await
+            .map_err(|err| anyhow!("Cannot reject contact: {err}"))
+    }
+
+    /// Sends the text, long texts are split into several messages.
+    pub async fn send_message(
+        &self,
+        address: Address,
+        text: String,
+    ) -> Result<Vec<Message>, anyhow::Error> {
+        let chat = self
+            .chat_manager
+            .get_contact_chat(address)
+            .await
+            .ok_or_else(|| anyhow!("No chat with {address}"))?;
+        chat.send_text(&text, None).await
+    }
+
+    /// Sends a WAV file as a voice message, long audio is sent as several
+    /// messages.
+    pub async fn send_voice_file(
+        &self,
+        address: Address,
+        path: &Path,
+    ) -> Result<Vec<Message>, anyhow::Error> {
+        let chat = self
+            .chat_manager
+            .get_contact_chat(address)
+            .await
+            .ok_or_else(|| anyhow!("No chat with {address}"))?;
+        let path = path.to_path_buf();
+        let parts = tokio::task::spawn_blocking(move || import_wav(&path)).await??;
+        chat.send_voice(parts).await
+    }
+
+    pub async fn start_call(&self, address: Address) -> Result<CallHandle, anyhow::Error> {
+        self.call_manager.start_call(address).await
+    }
+
+    pub async fn answer_call(&self, address: Address) -> Result<(), anyhow::Error> {
+        self.call_manager.accept_call(address).await
+    }
+
+    pub async fn decline_call(&self, address: Address) -> Result<(), anyhow::Error> {
+        self.call_manager.reject_call(address).await
+    }
+
+    pub async fn end_call(&self, address: Address) -> Result<(), anyhow::Error> {
+        self.call_manager.end_call(address).await
+    }
+
+    /// Stops the calls and removes the registration on the server.
+    pub async fn shutdown(&self) -> Result<(), anyhow::Error> {
+        self.call_manager.shutdown().await;
+        self.chat_manager.flush().await?;
+        self.contact_manager.shutdown().await
+    }
+
+    async fn add_accepted_chat(&self, address: &str, name: String) -> Result<(), anyhow::Error> {
+        let address: Address = address
+            .parse()
+            .map_err(|err| anyhow!("Invalid address {address}: {err}"))?;
+        let handle = self.contact_manager.connect_contact(address).await;
+        let public_key = handle
+            .public_key()
+            .ok_or_else(|| anyhow!("Unknown public key of {address}"))?;
+        self.chat_manager
+            .add_contact_chat(address, public_key, name, None)
+            .await?;
+        Ok(())
+    }
+}
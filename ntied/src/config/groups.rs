@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use ntied_transport::Address;
+use serde::{Deserialize, Serialize};
+
+/// User-defined contact groups shown in the chat list.
+///
+/// Contacts without a group belong to [`ContactGroups::DEFAULT`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContactGroups {
+    groups: Vec<String>,
+    // Contact address to group name
+    members: BTreeMap<String, String>,
+}
+
+impl ContactGroups {
+    pub const DEFAULT: &str = "Contacts";
+
+    /// User-defined groups in creation order, without the default one.
+    pub fn groups(&self) -> &[String] {
+        &self.groups
+    }
+
+    pub fn has_group(&self, name: &str) -> bool {
+        self.groups.iter().any(|g| g == name)
+    }
+
+    /// Create a new empty group, the name is trimmed.
+    pub fn add_group(&mut self, name: &str) -> Result<(), anyhow::Error> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("Group name is empty"));
+        }
+        if name == Self::DEFAULT || self.has_group(name) {
+            return Err(anyhow!("Group '{}' already exists", name));
+        }
+        self.groups.push(name.to_string());
+        Ok(())
+    }
+
+    /// Delete the group, its contacts move to the default group.
+    pub fn remove_group(&mut self, name: &str) -> bool {
+        let len = self.groups.len();
+        self.groups.retain(|g| g != name);
+        self.members.retain(|_, g| g != name);
+        self.groups.len() != len
+    }
+
+    /// Move the contact to the group, assigning to the default group
+    /// removes the contact from its current group.
+    pub fn assign(&mut self, address: Address, group: &str) -> Result<(), anyhow::Error> {
+        if group == Self::DEFAULT {
+            self.unassign(address);
+            return Ok(());
+        }
+        if !self.has_group(group) {
+            return Err(anyhow!("Group '{}' does not exist", group));
+        }
+        self.members.insert(address.to_string(), group.to_string());
+        Ok(())
+    }
+
+    /// Remove the contact from its group, returns whether it had one.
+    pub fn unassign(&mut self, address: Address) -> bool {
+        self.members.remove(&address.to_string()).is_some()
+    }
+
+    /// Group of the contact, the default group for unassigned contacts.
+    pub fn group_of(&self, address: Address) -> &str {
+        self.members
+            .get(&address.to_string())
+            .map(String::as_str)
+            .unwrap_or(Self::DEFAULT)
+    }
+}
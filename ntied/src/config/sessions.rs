@@ -0,0 +1,29 @@
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::DateTime;
+
+/// Connection of this profile to the server, kept in the local log of
+/// recent sessions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub server_addr: SocketAddr,
+    // Address of this endpoint as seen by the server, `None` if unknown
+    pub public_addr: Option<SocketAddr>,
+    pub connect_time: DateTime,
+}
+
+impl SessionRecord {
+    /// Number of sessions kept in the log, older ones are dropped.
+    pub const MAX_RECENT: usize = 20;
+
+    /// Session connected to `server_addr` right now.
+    pub fn new(server_addr: SocketAddr, public_addr: Option<SocketAddr>) -> Self {
+        Self {
+            server_addr,
+            public_addr,
+            connect_time: DateTime::now(),
+        }
+    }
+}
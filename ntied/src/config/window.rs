@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// Size and position of the main window and width of the chat list panel,
+/// restored after unlocking.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    // Top-left corner, `None` until the window was moved
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub sidebar_width: f32,
+}
+
+impl WindowGeometry {
+    pub const MIN_SIZE: f32 = 400.0;
+    pub const MIN_SIDEBAR_WIDTH: f32 = 240.0;
+    pub const MAX_SIDEBAR_WIDTH: f32 = 600.0;
+
+    /// Chat list panel width limited to the allowed range.
+    pub fn clamp_sidebar_width(width: f32) -> f32 {
+        if width.is_finite() {
+            width.clamp(Self::MIN_SIDEBAR_WIDTH, Self::MAX_SIDEBAR_WIDTH)
+        } else {
+            Self::default().sidebar_width
+        }
+    }
+
+    /// Geometry with values that can not be applied replaced by defaults.
+    pub fn sanitized(self) -> Self {
+        let default = Self::default();
+        let size = |v: f32, default: f32| {
+            if v.is_finite() && v >= Self::MIN_SIZE {
+                v
+            } else {
+                default
+            }
+        };
+        Self {
+            width: size(self.width, default.width),
+            height: size(self.height, default.height),
+            x: self.x.filter(|v| v.is_finite()),
+            y: self.y.filter(|v| v.is_finite()),
+            sidebar_width: Self::clamp_sidebar_width(self.sidebar_width),
+        }
+    }
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            width: 1024.0,
+            height: 768.0,
+            x: None,
+            y: None,
+            sidebar_width: 320.0,
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use ntied_crypto::PublicKey;
+use ntied_transport::Address;
+
+use serde::{Deserialize, Serialize};
+
+use crate::packet::{ContactKeyRotationPacket, ContactProfile};
+
+/// Version of the peer protocol implemented by this client.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Optional protocol features, exchanged with contacts when a connection is
+/// established. Bits unknown to this version are dropped by negotiation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Features(u32);
+
+impl Features {
+    /// Forward error correction of call audio.
+    pub const FEC: Features = Features(1 << 0);
+    /// Microsoft ADPCM audio codec variant.
+    pub const ADPCM_MICROSOFT: Features = Features(1 << 1);
+    /// Video frames in calls.
+    pub const VIDEO: Features = Features(1 << 2);
+    /// Opaque payloads of extensions, older clients drop them as unknown.
+    pub const APP_PACKETS: Features = Features(1 << 3);
+    /// Chat messages sent within a short window share one packet.
+    pub const MESSAGE_BATCH: Features = Features(1 << 4);
+    /// Chat messages encrypted with a per-contact double ratchet session.
+    pub const MESSAGE_RATCHET: Features = Features(1 << 5);
+    /// Pings answered by the contact to check that the connection is alive.
+    pub const CONNECTION_PROBE: Features = Features(1 << 6);
+    /// Chat messages carrying a reply and the signature of the sender.
+    pub const SIGNED_MESSAGE: Features = Features(1 << 7);
+
+    pub const fn empty() -> Self {
+        Features(0)
+    }
+
+    /// Features supported by this client.
+    pub const fn all() -> Self {
+        Features(
+            Self::FEC.0
+                | Self::ADPCM_MICROSOFT.0
+                | Self::VIDEO.0
+                | Self::APP_PACKETS.0
+                | Self::MESSAGE_BATCH.0
+                | Self::MESSAGE_RATCHET.0
+                | Self::CONNECTION_PROBE.0
+                | Self::SIGNED_MESSAGE.0,
+        )
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Features(bits)
+    }
+
+    pub const fn contains(&self, other: Features) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersection(&self, other: Features) -> Self {
+        Features(self.0 & other.0)
+    }
+
+    pub const fn union(&self, other: Features) -> Self {
+        Features(self.0 | other.0)
+    }
+}
+
+/// Protocol version and features agreed with a connected contact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Negotiated {
+    pub version: u32,
+    pub features: Features,
+}
+
+impl Negotiated {
+    /// Lowest of both versions and features supported by both sides.
+    pub fn new(own_features: Features, peer_version: u32, peer_features: Features) -> Self {
+        Self {
+            version: PROTOCOL_VERSION.min(peer_version),
+            features: own_features.intersection(peer_features),
+        }
+    }
+}
+
+/// Own state shared by the contact manager with all contact handles.
+pub(super) struct LocalPeer {
+    pub(super) profile: Mutex<ContactProfile>,
+    pub(super) features: Mutex<Features>,
+    /// Keys whose contact requests are accepted without asking.
+    pub(super) auto_accept: Mutex<HashMap<Address, PublicKey>>,
+    /// Announcements of a new own identity key not yet acknowledged, by contact.
+    pub(super) key_rotations: Mutex<HashMap<Address, ContactKeyRotationPacket>>,
+}
+
+impl LocalPeer {
+    pub(super) fn new(profile: ContactProfile) -> Self {
+        Self {
+            profile: Mutex::new(profile),
+            features: Mutex::new(Features::all()),
+            auto_accept: Mutex::new(HashMap::new()),
+            key_rotations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a request from `address` is accepted without asking. The
+    /// address is derived from the allowlisted key, so the transport only
+    /// lets the holder of that key connect from it.
+    pub(super) fn is_auto_accepted(&self, address: Address) -> bool {
+        self.auto_accept.lock().unwrap().contains_key(&address)
+    }
+}
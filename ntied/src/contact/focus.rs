@@ -0,0 +1,41 @@
+use std::time::{Duration, Instant};
+
+/// Detects the user returning to the app, the window regains focus after
+/// being in the background longer than the idle threshold.
+#[derive(Debug)]
+pub struct FocusTracker {
+    idle: Duration,
+    unfocused_at: Option<Instant>,
+}
+
+impl FocusTracker {
+    pub const DEFAULT_IDLE: Duration = Duration::from_secs(30);
+
+    /// Creates a tracker, focus regained after `idle` in the background is
+    /// a return.
+    pub fn new(idle: Duration) -> Self {
+        Self {
+            idle,
+            unfocused_at: None,
+        }
+    }
+
+    /// Registers the window losing focus at `now`.
+    pub fn unfocus(&mut self, now: Instant) {
+        self.unfocused_at.get_or_insert(now);
+    }
+
+    /// Registers the window gaining focus at `now`, returns true if it was
+    /// in the background for at least the idle threshold.
+    pub fn focus(&mut self, now: Instant) -> bool {
+        self.unfocused_at
+            .take()
+            .is_some_and(|v| now.saturating_duration_since(v) >= self.idle)
+    }
+}
+
+impl Default for FocusTracker {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_IDLE)
+    }
+}
@@ -3,15 +3,20 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use ntied_crypto::PublicKey;
-use ntied_transport::{Address, Connection, Error, Transport};
-use tokio::sync::{Mutex as TokioMutex, RwLock as TokioRwLock, mpsc, oneshot};
+use ntied_transport::{Address, Connection, Error, ToAddress as _, Transport};
+use tokio::sync::{Mutex as TokioMutex, RwLock as TokioRwLock, broadcast, mpsc, oneshot};
 
+use crate::avatar::sanitize_avatar;
+use crate::models::Base64;
 use crate::packet::{
-    CallPacket, ChatPacket, ContactAcceptPacket, ContactPacket, ContactProfile,
-    ContactRejectPacket, ContactRequestPacket, Packet,
+    AppPacket, CallPacket, ChatPacket, ContactAcceptPacket, ContactHelloPacket,
+    ContactKeyRotationAckPacket, ContactKeyRotationPacket, ContactPacket, ContactPingPacket,
+    ContactProfile, ContactProfileUpdatePacket, ContactRejectPacket, ContactRequestPacket, Packet,
 };
 
 use super::ContactListener;
+use super::features::{Features, LocalPeer, Negotiated, PROTOCOL_VERSION};
+use super::usage::{Usage, UsageCounter};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ContactStatus {
@@ -29,13 +34,15 @@ pub struct ContactHandle {
 
 impl ContactHandle {
     const MAX_PACKETS: usize = 4;
+    /// Payloads kept for a slow raw subscriber before it lags.
+    const MAX_RAW_PACKETS: usize = 64;
 
     pub(super) fn new_accepted(
         transport: Arc<TokioRwLock<Option<Arc<Transport>>>>,
         address: Address,
         public_key: PublicKey,
         profile: ContactProfile,
-        own_profile: ContactProfile,
+        local: Arc<LocalPeer>,
         own_address: Address,
         listener: Arc<dyn ContactListener>,
     ) -> Self {
@@ -43,11 +50,17 @@ impl ContactHandle {
         let status = Arc::new(Mutex::new(ContactStatus::Accepted));
         let connected = Arc::new(AtomicBool::new(false));
         let profile = Arc::new(Mutex::new(Some(profile)));
+        let intro = Arc::new(Mutex::new(None));
         let (command_tx, command_rx) = mpsc::channel(Self::MAX_PACKETS);
         let (chat_packet_tx, chat_packet_rx) = mpsc::channel(Self::MAX_PACKETS);
         let chat_packet_rx = TokioMutex::new(chat_packet_rx);
         let (call_packet_tx, call_packet_rx) = mpsc::channel(Self::MAX_PACKETS);
         let call_packet_rx = TokioMutex::new(call_packet_rx);
+        let (profile_update_tx, profile_update_rx) = mpsc::channel(Self::MAX_PACKETS);
+        let profile_update_rx = TokioMutex::new(profile_update_rx);
+        let (raw_packet_tx, _) = broadcast::channel(Self::MAX_RAW_PACKETS);
+        let usage = Arc::new(UsageCounter::default());
+        let negotiated = Arc::new(Mutex::new(None));
         let main_task = ContactHandleTask {
             transport,
             connection: None,
@@ -56,12 +69,17 @@ impl ContactHandle {
             status: status.clone(),
             connected: connected.clone(),
             profile: profile.clone(),
-            own_profile,
+            intro: intro.clone(),
+            usage: usage.clone(),
+            negotiated: negotiated.clone(),
+            local,
             own_address,
             listener,
             command_rx,
             chat_packet_tx,
             call_packet_tx,
+            profile_update_tx,
+            raw_packet_tx: raw_packet_tx.clone(),
         };
         let main_task = tokio::spawn(main_task.run());
         Self {
@@ -71,9 +89,14 @@ impl ContactHandle {
                 status,
                 connected,
                 profile,
+                intro,
+                usage,
+                negotiated,
                 command_tx,
                 chat_packet_rx,
                 call_packet_rx,
+                profile_update_rx,
+                raw_packet_tx,
                 main_task,
             }),
         }
@@ -82,7 +105,8 @@ impl ContactHandle {
     pub(super) fn new_outgoing(
         transport: Arc<TokioRwLock<Option<Arc<Transport>>>>,
         address: Address,
-        own_profile: ContactProfile,
+        intro: Option<String>,
+        local: Arc<LocalPeer>,
         own_address: Address,
         listener: Arc<dyn ContactListener>,
     ) -> Self {
@@ -90,11 +114,17 @@ impl ContactHandle {
         let status = Arc::new(Mutex::new(ContactStatus::PendingOutgoing));
         let connected = Arc::new(AtomicBool::new(false));
         let profile = Arc::new(Mutex::new(None));
+        let intro = Arc::new(Mutex::new(intro));
         let (command_tx, command_rx) = mpsc::channel(Self::MAX_PACKETS);
         let (chat_packet_tx, chat_packet_rx) = mpsc::channel(Self::MAX_PACKETS);
         let chat_packet_rx = TokioMutex::new(chat_packet_rx);
         let (call_packet_tx, call_packet_rx) = mpsc::channel(Self::MAX_PACKETS);
         let call_packet_rx = TokioMutex::new(call_packet_rx);
+        let (profile_update_tx, profile_update_rx) = mpsc::channel(Self::MAX_PACKETS);
+        let profile_update_rx = TokioMutex::new(profile_update_rx);
+        let (raw_packet_tx, _) = broadcast::channel(Self::MAX_RAW_PACKETS);
+        let usage = Arc::new(UsageCounter::default());
+        let negotiated = Arc::new(Mutex::new(None));
         let main_task = ContactHandleTask {
             transport,
             connection: None,
@@ -103,12 +133,17 @@ impl ContactHandle {
             status: status.clone(),
             connected: connected.clone(),
             profile: profile.clone(),
-            own_profile,
+            intro: intro.clone(),
+            usage: usage.clone(),
+            negotiated: negotiated.clone(),
+            local,
             own_address,
             listener,
             command_rx,
             chat_packet_tx,
             call_packet_tx,
+            profile_update_tx,
+            raw_packet_tx: raw_packet_tx.clone(),
         };
         let main_task = tokio::spawn(main_task.run());
         Self {
@@ -118,9 +153,14 @@ impl ContactHandle {
                 status,
                 connected,
                 profile,
+                intro,
+                usage,
+                negotiated,
                 command_tx,
                 chat_packet_rx,
                 call_packet_rx,
+                profile_update_rx,
+                raw_packet_tx,
                 main_task,
             }),
         }
@@ -130,7 +170,7 @@ impl ContactHandle {
         transport: Arc<TokioRwLock<Option<Arc<Transport>>>>,
         connection: Connection,
         address: Address,
-        own_profile: ContactProfile,
+        local: Arc<LocalPeer>,
         own_address: Address,
         listener: Arc<dyn ContactListener>,
     ) -> Self {
@@ -138,11 +178,17 @@ impl ContactHandle {
         let status = Arc::new(Mutex::new(ContactStatus::PendingIncoming));
         let connected = Arc::new(AtomicBool::new(true));
         let profile = Arc::new(Mutex::new(None));
+        let intro = Arc::new(Mutex::new(None));
         let (command_tx, command_rx) = mpsc::channel(Self::MAX_PACKETS);
         let (chat_packet_tx, chat_packet_rx) = mpsc::channel(Self::MAX_PACKETS);
         let chat_packet_rx = TokioMutex::new(chat_packet_rx);
         let (call_packet_tx, call_packet_rx) = mpsc::channel(Self::MAX_PACKETS);
         let call_packet_rx = TokioMutex::new(call_packet_rx);
+        let (profile_update_tx, profile_update_rx) = mpsc::channel(Self::MAX_PACKETS);
+        let profile_update_rx = TokioMutex::new(profile_update_rx);
+        let (raw_packet_tx, _) = broadcast::channel(Self::MAX_RAW_PACKETS);
+        let usage = Arc::new(UsageCounter::default());
+        let negotiated = Arc::new(Mutex::new(None));
         let main_task = ContactHandleTask {
             transport,
             connection: Some(connection),
@@ -151,12 +197,17 @@ impl ContactHandle {
             status: status.clone(),
             connected: connected.clone(),
             profile: profile.clone(),
-            own_profile,
+            intro: intro.clone(),
+            usage: usage.clone(),
+            negotiated: negotiated.clone(),
+            local,
             own_address,
             listener,
             command_rx,
             chat_packet_tx,
             call_packet_tx,
+            profile_update_tx,
+            raw_packet_tx: raw_packet_tx.clone(),
         };
         let main_task = tokio::spawn(main_task.run());
         Self {
@@ -166,9 +217,14 @@ impl ContactHandle {
                 status,
                 connected,
                 profile,
+                intro,
+                usage,
+                negotiated,
                 command_tx,
                 chat_packet_rx,
                 call_packet_rx,
+                profile_update_rx,
+                raw_packet_tx,
                 main_task,
             }),
         }
@@ -193,6 +249,23 @@ impl ContactHandle {
         profile.clone()
     }
 
+    /// Intro note of the pending request, ours for outgoing requests and
+    /// the peer's for incoming ones. Dropped once the request is answered.
+    pub fn intro(&self) -> Option<String> {
+        self.inner.intro.lock().unwrap().clone()
+    }
+
+    /// Protocol version and features agreed on the current connection,
+    /// none until the contact has answered the hello packet.
+    pub fn negotiated(&self) -> Option<Negotiated> {
+        *self.inner.negotiated.lock().unwrap()
+    }
+
+    /// Features supported by both sides, empty before negotiation.
+    pub fn features(&self) -> Features {
+        self.negotiated().map(|v| v.features).unwrap_or_default()
+    }
+
     pub fn get_name(&self) -> Option<String> {
         self.profile().map(|p| p.name)
     }
@@ -201,6 +274,28 @@ impl ContactHandle {
         self.inner.connected.load(Ordering::Relaxed)
     }
 
+    /// Bytes exchanged with the contact since the handle was created.
+    pub fn usage(&self) -> Usage {
+        self.inner.usage.usage()
+    }
+
+    /// Bytes of call packets exchanged with the contact.
+    pub fn call_usage(&self) -> Usage {
+        self.inner.usage.call_usage()
+    }
+
+    /// Whether the task driving the contact state is still alive.
+    pub fn is_running(&self) -> bool {
+        !self.inner.main_task.is_finished()
+    }
+
+    /// Stops the contact task, an in-flight connection attempt is dropped
+    /// with it.
+    pub(super) fn abort(&self) {
+        self.inner.main_task.abort();
+        self.inner.connected.store(false, Ordering::SeqCst);
+    }
+
     pub async fn accept(&self) -> Result<(), Error> {
         let (tx, rx) = oneshot::channel();
         self.inner
@@ -223,6 +318,26 @@ impl ContactHandle {
         Ok(())
     }
 
+    /// Drops the connection with an accepted contact and establishes a new one.
+    pub async fn reconnect(&self) -> Result<(), Error> {
+        self.inner
+            .command_tx
+            .send(HandleCommand::Reconnect)
+            .await
+            .map_err(|_| "Handle is broken".into())
+    }
+
+    /// Checks that the connection with an accepted contact is alive, a
+    /// connection without an answer in time is closed and established
+    /// again. Connections of contacts without probe support are renewed.
+    pub async fn probe(&self) -> Result<(), Error> {
+        self.inner
+            .command_tx
+            .send(HandleCommand::Probe)
+            .await
+            .map_err(|_| "Handle is broken".into())
+    }
+
     pub async fn send_chat_packet(&self, packet: ChatPacket) -> Result<(), Error> {
         self.inner
             .command_tx
@@ -259,6 +374,54 @@ impl ContactHandle {
             .ok_or("Handle is broken".into())
     }
 
+    /// Sends an opaque payload of an extension to the contact, delivered to
+    /// its [`Self::subscribe_raw`] subscribers.
+    ///
+    /// Contacts without [`Features::APP_PACKETS`] drop the payload.
+    pub async fn send_raw(&self, data: Vec<u8>) -> Result<(), Error> {
+        self.inner
+            .command_tx
+            .send(HandleCommand::SendAppPacket(AppPacket { data }))
+            .await
+            .map_err(|_| "Handle is broken".into())
+    }
+
+    /// Subscribes to opaque payloads of extensions sent by the contact,
+    /// payloads received before the subscription are not delivered.
+    pub fn subscribe_raw(&self) -> broadcast::Receiver<Vec<u8>> {
+        self.inner.raw_packet_tx.subscribe()
+    }
+
+    /// Sends the current own profile to the contact if it is connected.
+    pub(super) async fn send_profile_update(&self) -> Result<(), Error> {
+        self.inner
+            .command_tx
+            .send(HandleCommand::SendProfileUpdate)
+            .await
+            .map_err(|_| "Handle is broken".into())
+    }
+
+    /// Sends the pending announcement of a new own identity key if the
+    /// contact is connected.
+    pub(super) async fn send_key_rotation(&self) -> Result<(), Error> {
+        self.inner
+            .command_tx
+            .send(HandleCommand::SendKeyRotation)
+            .await
+            .map_err(|_| "Handle is broken".into())
+    }
+
+    /// Waits for a profile update sent by the contact.
+    pub async fn recv_profile_update(&self) -> Result<ContactProfile, Error> {
+        self.inner
+            .profile_update_rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or("Handle is broken".into())
+    }
+
     pub(super) async fn set_connection(&self, connection: Connection) -> Result<(), Error> {
         self.inner
             .command_tx
@@ -274,9 +437,14 @@ struct ContactHandleInner {
     status: Arc<Mutex<ContactStatus>>,
     connected: Arc<AtomicBool>,
     profile: Arc<Mutex<Option<ContactProfile>>>,
+    intro: Arc<Mutex<Option<String>>>,
+    usage: Arc<UsageCounter>,
+    negotiated: Arc<Mutex<Option<Negotiated>>>,
     command_tx: mpsc::Sender<HandleCommand>,
     chat_packet_rx: TokioMutex<mpsc::Receiver<ChatPacket>>,
     call_packet_rx: TokioMutex<mpsc::Receiver<CallPacket>>,
+    profile_update_rx: TokioMutex<mpsc::Receiver<ContactProfile>>,
+    raw_packet_tx: broadcast::Sender<Vec<u8>>,
     main_task: tokio::task::JoinHandle<()>,
 }
 
@@ -290,8 +458,13 @@ enum HandleCommand {
     Accept { tx: oneshot::Sender<()> },
     Reject { tx: oneshot::Sender<()> },
     SetConnection(Connection),
+    Reconnect,
+    Probe,
     SendChatPacket(ChatPacket),
     SendCallPacket(CallPacket),
+    SendAppPacket(AppPacket),
+    SendProfileUpdate,
+    SendKeyRotation,
 }
 
 struct ContactHandleTask {
@@ -302,21 +475,33 @@ struct ContactHandleTask {
     status: Arc<Mutex<ContactStatus>>,
     connected: Arc<AtomicBool>,
     profile: Arc<Mutex<Option<ContactProfile>>>,
-    own_profile: ContactProfile,
+    intro: Arc<Mutex<Option<String>>>,
+    usage: Arc<UsageCounter>,
+    negotiated: Arc<Mutex<Option<Negotiated>>>,
+    local: Arc<LocalPeer>,
     own_address: Address,
     listener: Arc<dyn ContactListener>,
     command_rx: mpsc::Receiver<HandleCommand>,
     chat_packet_tx: mpsc::Sender<ChatPacket>,
     call_packet_tx: mpsc::Sender<CallPacket>,
+    profile_update_tx: mpsc::Sender<ContactProfile>,
+    raw_packet_tx: broadcast::Sender<Vec<u8>>,
 }
 
 impl ContactHandleTask {
     const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+    const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
 
     pub async fn run(mut self) {
         loop {
             let current_status = *self.status.lock().unwrap();
             tracing::debug!(?current_status, "Enter contact status state");
+            if !matches!(
+                current_status,
+                ContactStatus::PendingIncoming | ContactStatus::PendingOutgoing
+            ) {
+                self.intro.lock().unwrap().take();
+            }
             match current_status {
                 ContactStatus::PendingIncoming => self.pending_incoming_loop().await,
                 ContactStatus::PendingOutgoing => self.pending_outgoing_loop().await,
@@ -340,14 +525,7 @@ impl ContactHandleTask {
                 command = self.command_rx.recv() => match command {
                     Some(v) => match v {
                         HandleCommand::Accept { tx } => {
-                            let packet = Packet::Contact(ContactPacket::Accept(ContactAcceptPacket {
-                                profile: self.own_profile.clone(),
-                            }));
-                            let bytes = bincode::serialize(&packet).unwrap();
-                            tracing::debug!("Sending accept packet");
-                            if let Err(err) = connection_mut.send(bytes).await {
-                                tracing::error!(?err, "Failed to send accept packet");
-                            }
+                            send_accept(&self.local, connection_mut).await;
                             *self.status.lock().unwrap() = ContactStatus::Accepted;
                             // if let Err(err) = self.event_tx.try_send(ContactEvent::Accepted { address: self.address }) {
                             //     tracing::error!(?err, "Failed to send accepted event");
@@ -391,11 +569,21 @@ impl ContactHandleTask {
                 },
                 packet = connection_mut.recv() => match packet {
                     Ok(packet) => {
-                        match bincode::deserialize::<Packet>(&packet) {
-                            Ok(Packet::Contact(ContactPacket::Request(ContactRequestPacket { profile }))) => {
+                        match Packet::decode(&packet) {
+                            Ok(Packet::Contact(ContactPacket::Request(ContactRequestPacket { profile, intro }))) => {
                                 tracing::debug!("Received contact request from {:?}", self.address);
+                                let profile = sanitize_profile(profile);
+                                let intro = intro.as_deref().and_then(sanitize_intro);
                                 *self.profile.lock().unwrap() = Some(profile.clone());
-                                self.listener.on_contact_incoming(self.address, profile).await;
+                                *self.intro.lock().unwrap() = intro.clone();
+                                if self.local.is_auto_accepted(self.address) {
+                                    tracing::debug!("Auto-accepting contact request");
+                                    send_accept(&self.local, connection_mut).await;
+                                    *self.status.lock().unwrap() = ContactStatus::Accepted;
+                                    self.listener.on_contact_accepted(self.address, profile).await;
+                                    return;
+                                }
+                                self.listener.on_contact_incoming(self.address, profile, intro).await;
                             }
                             Ok(Packet::Contact(ContactPacket::Reject(ContactRejectPacket { }))) => {
                                 tracing::debug!("Received contact reject packet");
@@ -403,6 +591,30 @@ impl ContactHandleTask {
                                 self.listener.on_contact_rejected(self.address).await;
                                 return;
                             }
+                            Ok(Packet::Contact(ContactPacket::KeyRotation(rotation_packet))) => {
+                                // Sent from the new identity of a contact that rotated its key
+                                tracing::debug!("Received key rotation packet from new identity");
+                                let peer_key = connection_mut.peer_public_key().to_bytes().ok();
+                                match rotation_packet.verify_previous() {
+                                    Ok((old_key, public_key)) if public_key.to_bytes().ok() == peer_key => {
+                                        match old_key.to_address() {
+                                            Ok(old_address) => {
+                                                send_key_rotation_ack(connection_mut, &self.usage, &rotation_packet).await;
+                                                self.listener.on_contact_key_rotated(old_address, public_key).await;
+                                            }
+                                            Err(err) => {
+                                                tracing::warn!(?err, "Rejected key rotation packet");
+                                            }
+                                        }
+                                    }
+                                    Ok(_) => {
+                                        tracing::warn!("Rejected key rotation packet for another key");
+                                    }
+                                    Err(err) => {
+                                        tracing::warn!(?err, "Rejected key rotation packet");
+                                    }
+                                }
+                            }
                             Ok(packet) => {
                                 tracing::warn!(?packet, "Unexpected packet in pending incoming state");
                             }
@@ -429,7 +641,8 @@ impl ContactHandleTask {
             .as_mut()
             .expect("Unexpected connection state");
         let packet = Packet::Contact(ContactPacket::Request(ContactRequestPacket {
-            profile: self.own_profile.clone(),
+            profile: self.local.profile.lock().unwrap().clone(),
+            intro: self.intro.lock().unwrap().clone(),
         }));
         let bytes = bincode::serialize(&packet).unwrap();
         tracing::debug!("Sending contact request packet");
@@ -484,9 +697,10 @@ impl ContactHandleTask {
                 },
                 packet = connection_mut.recv() => match packet {
                     Ok(packet) => {
-                        match bincode::deserialize::<Packet>(&packet) {
+                        match Packet::decode(&packet) {
                             Ok(Packet::Contact(ContactPacket::Accept(ContactAcceptPacket { profile }))) => {
                                 tracing::debug!("Received contact accept packet");
+                                let profile = sanitize_profile(profile);
                                 *self.profile.lock().unwrap() = Some(profile.clone());
                                 *self.status.lock().unwrap() = ContactStatus::Accepted;
                                 self.listener.on_contact_accepted(self.address, profile).await;
@@ -514,7 +728,8 @@ impl ContactHandleTask {
                 _ = tokio::time::sleep(Duration::from_secs(1)) => {
                     // Send contact request periodically
                     let packet = Packet::Contact(ContactPacket::Request(ContactRequestPacket {
-                        profile: self.own_profile.clone(),
+                        profile: self.local.profile.lock().unwrap().clone(),
+                        intro: self.intro.lock().unwrap().clone(),
                     }));
                     let bytes = bincode::serialize(&packet).unwrap();
                     tracing::debug!("Sending contact request packet");
@@ -586,8 +801,30 @@ impl ContactHandleTask {
             .connection
             .as_mut()
             .expect("Unexpected connection state");
+        let packet = hello_packet(&self.local);
+        if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
+            tracing::error!(?err, "Failed to send hello packet");
+        }
+        // The profile could change while the contact was offline
+        let packet = Packet::Contact(ContactPacket::ProfileUpdate(ContactProfileUpdatePacket {
+            profile: self.local.profile.lock().unwrap().clone(),
+        }));
+        if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
+            tracing::error!(?err, "Failed to send profile update packet");
+        }
+        // The own key could be rotated while the contact was offline
+        send_key_rotation(&self.local, &self.usage, self.address, connection_mut).await;
+        // Nonce and deadline of the unanswered ping
+        let mut probe: Option<(u64, tokio::time::Instant)> = None;
+        let mut probe_nonce = 0;
         loop {
+            let probe_deadline = probe.map_or_else(tokio::time::Instant::now, |(_, v)| v);
             tokio::select! {
+                _ = tokio::time::sleep_until(probe_deadline), if probe.is_some() => {
+                    tracing::info!("Contact did not answer ping, connection is stale");
+                    self.close_connection().await;
+                    return;
+                }
                 v = self.command_rx.recv() => {
                     let command = match v {
                         Some(v) => v,
@@ -596,20 +833,37 @@ impl ContactHandleTask {
                     match command {
                         HandleCommand::SendChatPacket(chat_packet) => {
                             let packet = Packet::Chat(chat_packet);
-                            let bytes = bincode::serialize(&packet).unwrap();
                             tracing::debug!("Send packet");
-                            if let Err(err) = connection_mut.send(bytes).await {
+                            if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
                                 tracing::error!(?err, "Failed to send packet");
                             }
                         }
                         HandleCommand::SendCallPacket(call_packet) => {
                             let packet = Packet::Call(call_packet);
-                            let bytes = bincode::serialize(&packet).unwrap();
                             tracing::debug!("Send packet");
-                            if let Err(err) = connection_mut.send(bytes).await {
+                            if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
                                 tracing::error!(?err, "Failed to send packet");
                             }
                         }
+                        HandleCommand::SendAppPacket(app_packet) => {
+                            let packet = Packet::App(app_packet);
+                            tracing::debug!("Send app packet");
+                            if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
+                                tracing::error!(?err, "Failed to send app packet");
+                            }
+                        }
+                        HandleCommand::SendProfileUpdate => {
+                            let packet = Packet::Contact(ContactPacket::ProfileUpdate(ContactProfileUpdatePacket {
+                                profile: self.local.profile.lock().unwrap().clone(),
+                            }));
+                            tracing::debug!("Sending profile update packet");
+                            if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
+                                tracing::error!(?err, "Failed to send profile update packet");
+                            }
+                        }
+                        HandleCommand::SendKeyRotation => {
+                            send_key_rotation(&self.local, &self.usage, self.address, connection_mut).await;
+                        }
                         HandleCommand::SetConnection(connection) => {
                             if self.own_address.to_string() < connection.peer_address().to_string() {
                                 tracing::debug!("Discard incoming connection");
@@ -617,8 +871,42 @@ impl ContactHandleTask {
                             }
                             tracing::debug!("Replace connection");
                             *connection_mut = connection;
+                            *self.negotiated.lock().unwrap() = None;
+                            probe = None;
+                            let packet = hello_packet(&self.local);
+                            if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
+                                tracing::error!(?err, "Failed to send hello packet");
+                            }
+                            send_key_rotation(&self.local, &self.usage, self.address, connection_mut).await;
                             continue;
                         }
+                        HandleCommand::Reconnect => {
+                            tracing::debug!("Reconnecting to peer");
+                            self.close_connection().await;
+                            return;
+                        }
+                        HandleCommand::Probe => {
+                            let supported = self
+                                .negotiated
+                                .lock()
+                                .unwrap()
+                                .is_some_and(|v| v.features.contains(Features::CONNECTION_PROBE));
+                            if !supported {
+                                tracing::debug!("Contact does not answer pings, reconnecting");
+                                self.close_connection().await;
+                                return;
+                            }
+                            if probe.is_some() {
+                                continue;
+                            }
+                            probe_nonce += 1;
+                            let packet = Packet::Contact(ContactPacket::Ping(ContactPingPacket { nonce: probe_nonce }));
+                            tracing::debug!("Sending ping packet");
+                            if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
+                                tracing::error!(?err, "Failed to send ping packet");
+                            }
+                            probe = Some((probe_nonce, tokio::time::Instant::now() + Self::PROBE_TIMEOUT));
+                        }
                         _ => {
                             tracing::debug!("Ignoring command");
                         }
@@ -626,22 +914,74 @@ impl ContactHandleTask {
                 },
                 packet = connection_mut.recv() => match packet {
                     Ok(packet) => {
-                        match bincode::deserialize::<Packet>(&packet) {
-                            Ok(Packet::Contact(ContactPacket::Request(ContactRequestPacket { profile }))) => {
+                        let len = packet.len() as u64;
+                        let packet = Packet::decode(&packet);
+                        self.usage.add_received(len, matches!(packet, Ok(Packet::Call(_))));
+                        match packet {
+                            Ok(Packet::Contact(ContactPacket::Request(ContactRequestPacket { profile, .. }))) => {
                                 tracing::debug!("Received contact request packet");
-                                *self.profile.lock().unwrap() = Some(profile);
+                                *self.profile.lock().unwrap() = Some(sanitize_profile(profile));
                                 let packet = Packet::Contact(ContactPacket::Accept(ContactAcceptPacket {
-                                    profile: self.own_profile.clone(),
+                                    profile: self.local.profile.lock().unwrap().clone(),
                                 }));
-                                let bytes = bincode::serialize(&packet).unwrap();
                                 tracing::debug!("Sending contact accept packet");
-                                if let Err(err) = connection_mut.send(bytes).await {
+                                if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
                                     tracing::error!(?err, "Failed to send contact accept packet");
                                 }
                             }
                             Ok(Packet::Contact(ContactPacket::Accept(ContactAcceptPacket { profile }))) => {
                                 tracing::debug!("Received contact accept packet");
-                                *self.profile.lock().unwrap() = Some(profile);
+                                *self.profile.lock().unwrap() = Some(sanitize_profile(profile));
+                            }
+                            Ok(Packet::Contact(ContactPacket::ProfileUpdate(ContactProfileUpdatePacket { profile }))) => {
+                                tracing::debug!("Received profile update packet");
+                                let profile = sanitize_profile(profile);
+                                *self.profile.lock().unwrap() = Some(profile.clone());
+                                if let Err(err) = self.profile_update_tx.try_send(profile) {
+                                    tracing::warn!(?err, "Received profile update is lost");
+                                }
+                            }
+                            Ok(Packet::Contact(ContactPacket::KeyRotation(rotation_packet))) => {
+                                tracing::debug!("Received key rotation packet");
+                                let old_key = connection_mut.peer_public_key().clone();
+                                match rotation_packet.verify(&old_key) {
+                                    Ok(public_key) => {
+                                        send_key_rotation_ack(connection_mut, &self.usage, &rotation_packet).await;
+                                        self.listener.on_contact_key_rotated(self.address, public_key).await;
+                                    }
+                                    Err(err) => {
+                                        tracing::warn!(?err, "Rejected key rotation packet");
+                                    }
+                                }
+                            }
+                            Ok(Packet::Contact(ContactPacket::KeyRotationAck(ContactKeyRotationAckPacket { public_key }))) => {
+                                tracing::debug!("Received key rotation ack packet");
+                                let acked = {
+                                    let mut key_rotations = self.local.key_rotations.lock().unwrap();
+                                    let pending = key_rotations.get(&self.address).is_some_and(|v| v.public_key == public_key);
+                                    pending && key_rotations.remove(&self.address).is_some()
+                                };
+                                if acked {
+                                    self.listener.on_key_rotation_delivered(self.address).await;
+                                }
+                            }
+                            Ok(Packet::Contact(ContactPacket::Hello(ContactHelloPacket { version, features }))) => {
+                                let own_features = *self.local.features.lock().unwrap();
+                                let negotiated = Negotiated::new(own_features, version, Features::from_bits(features));
+                                tracing::debug!(?negotiated, "Received hello packet");
+                                *self.negotiated.lock().unwrap() = Some(negotiated);
+                            }
+                            Ok(Packet::Contact(ContactPacket::Ping(ContactPingPacket { nonce }))) => {
+                                let packet = Packet::Contact(ContactPacket::Pong(ContactPingPacket { nonce }));
+                                if let Err(err) = send_counted(connection_mut, &self.usage, &packet).await {
+                                    tracing::error!(?err, "Failed to send pong packet");
+                                }
+                            }
+                            Ok(Packet::Contact(ContactPacket::Pong(ContactPingPacket { nonce }))) => {
+                                if probe.is_some_and(|(v, _)| v == nonce) {
+                                    tracing::debug!("Received pong packet");
+                                    probe = None;
+                                }
                             }
                             Ok(Packet::Chat(chat_packet)) => {
                                 if let Err(err) = self.chat_packet_tx.try_send(chat_packet) {
@@ -653,6 +993,11 @@ impl ContactHandleTask {
                                     tracing::warn!(?err, "Received call packet is lost");
                                 }
                             }
+                            Ok(Packet::App(AppPacket { data })) => {
+                                if self.raw_packet_tx.send(data).is_err() {
+                                    tracing::debug!("Received app packet without subscribers");
+                                }
+                            }
                             Ok(packet) => {
                                 tracing::warn!(?packet, "Unexpected packet in accepted state");
                             }
@@ -766,8 +1111,101 @@ impl ContactHandleTask {
         if let Some(connection) = self.connection.take() {
             drop(connection);
             self.connected.store(false, Ordering::SeqCst);
+            *self.negotiated.lock().unwrap() = None;
             tracing::info!("Connection closed");
             self.listener.on_contact_disconnected(self.address).await;
         }
     }
 }
+
+/// Announces own protocol version and features to the contact.
+fn hello_packet(local: &LocalPeer) -> Packet {
+    Packet::Contact(ContactPacket::Hello(ContactHelloPacket {
+        version: PROTOCOL_VERSION,
+        features: local.features.lock().unwrap().bits(),
+    }))
+}
+
+/// Sends the pending announcement of a new own identity key to the contact.
+/// It is kept until the contact acknowledges it, so a lost packet is sent
+/// again with the next connection.
+async fn send_key_rotation(
+    local: &LocalPeer,
+    usage: &UsageCounter,
+    address: Address,
+    connection: &Connection,
+) {
+    let Some(rotation_packet) = local.key_rotations.lock().unwrap().get(&address).cloned() else {
+        return;
+    };
+    let packet = Packet::Contact(ContactPacket::KeyRotation(rotation_packet));
+    tracing::debug!("Sending key rotation packet");
+    if let Err(err) = send_counted(connection, usage, &packet).await {
+        tracing::error!(?err, "Failed to send key rotation packet");
+    }
+}
+
+/// Confirms a verified key rotation of the contact.
+async fn send_key_rotation_ack(
+    connection: &Connection,
+    usage: &UsageCounter,
+    rotation_packet: &ContactKeyRotationPacket,
+) {
+    let packet = Packet::Contact(ContactPacket::KeyRotationAck(ContactKeyRotationAckPacket {
+        public_key: rotation_packet.public_key.clone(),
+    }));
+    tracing::debug!("Sending key rotation ack packet");
+    if let Err(err) = send_counted(connection, usage, &packet).await {
+        tracing::error!(?err, "Failed to send key rotation ack packet");
+    }
+}
+
+/// Drops or downscales the avatar of a profile received from a contact.
+/// Makes the intro note of a contact request a single line of at most
+/// [`ContactRequestPacket::MAX_INTRO_LEN`] characters, `None` if nothing
+/// is left.
+pub fn sanitize_intro(intro: &str) -> Option<String> {
+    let intro = intro
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let intro: String = intro
+        .chars()
+        .take(ContactRequestPacket::MAX_INTRO_LEN)
+        .collect();
+    let intro = intro.trim_end();
+    (!intro.is_empty()).then(|| intro.to_string())
+}
+
+fn sanitize_profile(mut profile: ContactProfile) -> ContactProfile {
+    profile.avatar = profile
+        .avatar
+        .and_then(|avatar| sanitize_avatar(&avatar.0))
+        .map(Base64);
+    profile
+}
+
+async fn send_accept(local: &LocalPeer, connection: &mut Connection) {
+    let packet = Packet::Contact(ContactPacket::Accept(ContactAcceptPacket {
+        profile: local.profile.lock().unwrap().clone(),
+    }));
+    let bytes = bincode::serialize(&packet).unwrap();
+    tracing::debug!("Sending accept packet");
+    if let Err(err) = connection.send(bytes).await {
+        tracing::error!(?err, "Failed to send accept packet");
+    }
+}
+
+/// Sends the packet and accounts its size in `usage`.
+async fn send_counted(
+    connection: &Connection,
+    usage: &UsageCounter,
+    packet: &Packet,
+) -> Result<(), Error> {
+    let bytes = bincode::serialize(packet).unwrap();
+    let len = bytes.len() as u64;
+    connection.send(bytes).await?;
+    usage.add_sent(len, matches!(packet, Packet::Call(_)));
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use ntied_crypto::PublicKey;
 use ntied_transport::Address;
 
 use crate::packet::ContactProfile;
@@ -7,17 +8,43 @@ use crate::packet::ContactProfile;
 pub trait ContactListener: Send + Sync {
     async fn on_server_connected(&self);
 
+    /// Called when the server connection is lost or a connection attempt
+    /// failed, another attempt follows after a delay.
+    async fn on_server_reconnecting(&self);
+
     async fn on_server_disconnected(&self);
 
     async fn on_contact_connected(&self, address: Address);
 
     async fn on_contact_disconnected(&self, addres: Address);
 
-    async fn on_contact_incoming(&self, address: Address, profile: ContactProfile);
+    async fn on_contact_incoming(
+        &self,
+        address: Address,
+        profile: ContactProfile,
+        intro: Option<String>,
+    );
+
+    /// Called instead of `on_contact_incoming` when the address keeps sending
+    /// requests, `count` is the total number of requests received.
+    async fn on_contact_incoming_repeated(
+        &self,
+        address: Address,
+        profile: ContactProfile,
+        intro: Option<String>,
+        count: u32,
+    );
 
     async fn on_contact_accepted(&self, address: Address, profile: ContactProfile);
 
     async fn on_contact_rejected(&self, address: Address);
+
+    /// Called when the contact proved that it switched to a new identity key.
+    async fn on_contact_key_rotated(&self, address: Address, public_key: PublicKey);
+
+    /// Called when the contact acknowledged the announcement of a new own
+    /// identity key.
+    async fn on_key_rotation_delivered(&self, address: Address);
 }
 
 pub(super) struct StubListener;
@@ -26,15 +53,36 @@ pub(super) struct StubListener;
 impl ContactListener for StubListener {
     async fn on_server_connected(&self) {}
 
+    async fn on_server_reconnecting(&self) {}
+
     async fn on_server_disconnected(&self) {}
 
     async fn on_contact_connected(&self, _address: Address) {}
 
     async fn on_contact_disconnected(&self, _address: Address) {}
 
-    async fn on_contact_incoming(&self, _address: Address, _profile: ContactProfile) {}
+    async fn on_contact_incoming(
+        &self,
+        _address: Address,
+        _profile: ContactProfile,
+        _intro: Option<String>,
+    ) {
+    }
+
+    async fn on_contact_incoming_repeated(
+        &self,
+        _address: Address,
+        _profile: ContactProfile,
+        _intro: Option<String>,
+        _count: u32,
+    ) {
+    }
 
     async fn on_contact_accepted(&self, _address: Address, _profile: ContactProfile) {}
 
     async fn on_contact_rejected(&self, _address: Address) {}
+
+    async fn on_contact_key_rotated(&self, _address: Address, _public_key: PublicKey) {}
+
+    async fn on_key_rotation_delivered(&self, _address: Address) {}
 }
@@ -1,17 +1,23 @@
 use std::collections::{HashMap, hash_map};
 use std::net::SocketAddr;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
 use ntied_crypto::{PrivateKey, PublicKey};
-use ntied_transport::{Address, ToAddress, Transport};
+use ntied_transport::{Address, ConnectivityReport, ToAddress, TrafficClass, Transport};
 use tokio::sync::{Mutex as TokioMutex, RwLock as TokioRwLock, mpsc};
 use tokio::task::JoinHandle;
 
-use crate::packet::ContactProfile;
+use crate::packet::{ContactKeyRotationPacket, ContactProfile};
 
-use super::{ContactHandle, ContactListener, StubListener};
+use super::features::{Features, LocalPeer};
+use super::throttle::ThrottledListener;
+use super::{
+    ContactHandle, ContactListener, ContactStatus, PresenceSchedule, RequestThrottle, StubListener,
+    Usage, WakeDetector, sanitize_intro,
+};
 
 #[derive(Clone, Debug)]
 pub struct ContactInfo {
@@ -20,19 +26,41 @@ pub struct ContactInfo {
     pub name: String,
 }
 
+/// State of the connection to the server.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Connected,
+    /// Connection is lost or failed, waiting before the next attempt.
+    Reconnecting,
+    #[default]
+    Disconnected,
+}
+
 pub struct ContactManager {
     transport: Arc<TokioRwLock<Option<Arc<Transport>>>>,
     private_key: PrivateKey,
-    own_profile: ContactProfile,
+    local: Arc<LocalPeer>,
     contacts: Arc<TokioMutex<HashMap<Address, ContactHandle>>>,
-    connected: Arc<AtomicBool>,
+    status: Arc<Mutex<ConnectionStatus>>,
+    // Kept across reconnects, applied to each new transport
+    traffic_class: Arc<Mutex<TrafficClass>>,
+    wake_recovery: Arc<AtomicBool>,
+    focus_refresh: AtomicBool,
     command_tx: mpsc::Sender<ManagerCommand>,
     accept_rx: TokioMutex<mpsc::Receiver<Address>>,
     main_task: JoinHandle<()>,
     listener: Arc<dyn ContactListener>,
+    throttle: Arc<Mutex<RequestThrottle>>,
+    presence: Arc<Mutex<PresenceSchedule>>,
 }
 
 impl ContactManager {
+    /// Delay before announcing presence, stored contacts are added meanwhile.
+    const PRESENCE_DELAY: Duration = Duration::from_millis(250);
+    const PRESENCE_TIMEOUT: Duration = Duration::from_secs(3);
+    const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+    const CANCEL_TIMEOUT: Duration = Duration::from_secs(1);
+
     pub async fn new(
         server_addr: SocketAddr,
         private_key: PrivateKey,
@@ -53,41 +81,73 @@ impl ContactManager {
         own_profile: ContactProfile,
         listener: Arc<L>,
     ) -> Self
+    where
+        L: ContactListener + 'static,
+    {
+        Self::with_server_token(server_addr, None, private_key, own_profile, listener).await
+    }
+
+    /// Like [`ContactManager::with_listener`], `server_token` is sent on
+    /// registration to private servers.
+    pub async fn with_server_token<L>(
+        server_addr: SocketAddr,
+        server_token: Option<String>,
+        private_key: PrivateKey,
+        own_profile: ContactProfile,
+        listener: Arc<L>,
+    ) -> Self
     where
         L: ContactListener + 'static,
     {
         // let (event_tx, event_rx) = mpsc::channel(100);
         // let event_rx = TokioMutex::new(event_rx);
+        let local = Arc::new(LocalPeer::new(own_profile));
+        let throttle = Arc::new(Mutex::new(RequestThrottle::default()));
+        let presence = Arc::new(Mutex::new(PresenceSchedule::default()));
+        let listener: Arc<dyn ContactListener> =
+            Arc::new(ThrottledListener::new(listener, throttle.clone()));
         let contacts = Arc::new(TokioMutex::new(HashMap::new()));
         let transport = Arc::new(TokioRwLock::new(None));
-        let connected = Arc::new(AtomicBool::new(false));
+        let status = Arc::new(Mutex::new(ConnectionStatus::default()));
+        let traffic_class = Arc::new(Mutex::new(TrafficClass::default()));
+        let wake_recovery = Arc::new(AtomicBool::new(true));
         let (command_tx, command_rx) = mpsc::channel(1);
         let (accept_tx, accept_rx) = mpsc::channel(1);
         let accept_rx = TokioMutex::new(accept_rx);
         let main_task = tokio::spawn(Self::main_loop(
             server_addr,
+            server_token,
             private_key.clone(),
             transport.clone(),
             contacts.clone(),
-            connected.clone(),
+            status.clone(),
+            traffic_class.clone(),
+            wake_recovery.clone(),
             // event_tx.clone(),
             command_rx,
             accept_tx,
-            own_profile.clone(),
+            local.clone(),
             listener.clone(),
+            throttle.clone(),
+            presence.clone(),
         ));
         Self {
             transport,
             private_key,
-            own_profile,
+            local,
             contacts,
-            connected,
+            status,
+            traffic_class,
+            wake_recovery,
+            focus_refresh: AtomicBool::new(true),
             // event_tx,
             // event_rx,
             command_tx,
             accept_rx,
             main_task,
             listener,
+            throttle,
+            presence,
         }
     }
 
@@ -95,6 +155,41 @@ impl ContactManager {
         self.private_key.public_key().to_address().unwrap()
     }
 
+    /// Identity key used to sign outgoing chat messages.
+    pub(crate) fn private_key(&self) -> &PrivateKey {
+        &self.private_key
+    }
+
+    pub fn own_profile(&self) -> ContactProfile {
+        self.local.profile.lock().unwrap().clone()
+    }
+
+    /// Features offered to contacts on new connections, all by default.
+    pub fn features(&self) -> Features {
+        *self.local.features.lock().unwrap()
+    }
+
+    /// Limit features offered to contacts, applies to connections
+    /// established afterwards.
+    pub fn set_features(&self, features: Features) {
+        *self.local.features.lock().unwrap() = features.intersection(Features::all());
+    }
+
+    /// Replace the own profile and send it to all connected contacts.
+    /// Contacts that are offline receive it once they reconnect.
+    pub async fn update_own_profile(&self, profile: ContactProfile) {
+        *self.local.profile.lock().unwrap() = profile;
+        for contact in self.list_contacts().await {
+            if !contact.is_connected() {
+                continue;
+            }
+            if let Err(err) = contact.send_profile_update().await {
+                let address = contact.address();
+                tracing::warn!(?address, ?err, "Failed to send profile update");
+            }
+        }
+    }
+
     pub async fn add_contact(
         &self,
         address: Address,
@@ -110,7 +205,7 @@ impl ContactManager {
                     address,
                     public_key,
                     profile,
-                    self.own_profile.clone(),
+                    self.local.clone(),
                     self.private_key.public_key().to_address().unwrap(),
                     self.listener.clone(),
                 );
@@ -120,7 +215,49 @@ impl ContactManager {
         }
     }
 
+    /// Same as [`ContactManager::add_contact`] for the new identity of a
+    /// contact that rotated its key. The announcement could come from the
+    /// new identity, its connection is left as a pending request which is
+    /// replaced.
+    pub async fn add_rotated_contact(
+        &self,
+        address: Address,
+        public_key: PublicKey,
+        profile: ContactProfile,
+    ) -> ContactHandle {
+        let mut contacts = self.contacts.lock().await;
+        if let Some(handle) = contacts.get(&address)
+            && handle.status() != ContactStatus::PendingIncoming
+        {
+            return handle.clone();
+        }
+        let handle = ContactHandle::new_accepted(
+            self.transport.clone(),
+            address,
+            public_key,
+            profile,
+            self.local.clone(),
+            self.private_key.public_key().to_address().unwrap(),
+            self.listener.clone(),
+        );
+        if let Some(request) = contacts.insert(address, handle.clone()) {
+            request.abort();
+        }
+        handle
+    }
+
     pub async fn connect_contact(&self, address: Address) -> ContactHandle {
+        self.connect_contact_with_intro(address, None).await
+    }
+
+    /// Same as [`ContactManager::connect_contact`], a new request carries
+    /// the intro note shown to the peer. The note of an existing contact is
+    /// not changed.
+    pub async fn connect_contact_with_intro(
+        &self,
+        address: Address,
+        intro: Option<&str>,
+    ) -> ContactHandle {
         let mut contacts = self.contacts.lock().await;
         match contacts.entry(address) {
             hash_map::Entry::Occupied(entry) => entry.get().clone(),
@@ -128,7 +265,8 @@ impl ContactManager {
                 let handle = ContactHandle::new_outgoing(
                     self.transport.clone(),
                     address,
-                    self.own_profile.clone(),
+                    intro.and_then(sanitize_intro),
+                    self.local.clone(),
                     self.private_key.public_key().to_address().unwrap(),
                     self.listener.clone(),
                 );
@@ -138,11 +276,172 @@ impl ContactManager {
         }
     }
 
+    /// Withdraws an outgoing contact request, the connection attempt is
+    /// aborted and the contact is forgotten. A peer that already got the
+    /// request is told it was cancelled. Returns false if there is no
+    /// pending outgoing request to the address.
+    pub async fn cancel_connect(&self, address: Address) -> bool {
+        let mut contacts = self.contacts.lock().await;
+        if contacts.get(&address).map(|v| v.status()) != Some(ContactStatus::PendingOutgoing) {
+            return false;
+        }
+        let handle = contacts.remove(&address).unwrap();
+        drop(contacts);
+        let connected = handle.is_connected();
+        if connected
+            && tokio::time::timeout(Self::CANCEL_TIMEOUT, handle.reject())
+                .await
+                .is_err()
+        {
+            tracing::warn!(?address, "Timed out withdrawing contact request");
+        }
+        handle.abort();
+        if connected {
+            self.listener.on_contact_disconnected(address).await;
+        }
+        true
+    }
+
+    /// Announce a new own identity key to all accepted contacts. Connected
+    /// contacts get it now, the others when they connect next time. It is
+    /// sent again on every connection until the contact acknowledges it, see
+    /// [`ContactManager::pending_key_rotations`].
+    pub async fn announce_key_rotation(&self, packet: ContactKeyRotationPacket) {
+        let contacts: Vec<_> = self
+            .list_contacts()
+            .await
+            .into_iter()
+            .filter(|v| v.status() == ContactStatus::Accepted)
+            .collect();
+        {
+            let mut key_rotations = self.local.key_rotations.lock().unwrap();
+            for contact in &contacts {
+                key_rotations.insert(contact.address(), packet.clone());
+            }
+        }
+        for contact in contacts {
+            if !contact.is_connected() {
+                continue;
+            }
+            if let Err(err) = contact.send_key_rotation().await {
+                let address = contact.address();
+                tracing::warn!(?address, ?err, "Failed to send key rotation");
+            }
+        }
+    }
+
+    /// Replace the unacknowledged key rotation announcements, used to
+    /// restore the persisted ones.
+    pub fn set_pending_key_rotations(
+        &self,
+        key_rotations: HashMap<Address, ContactKeyRotationPacket>,
+    ) {
+        *self.local.key_rotations.lock().unwrap() = key_rotations;
+    }
+
+    /// Key rotation announcements not yet acknowledged, by contact.
+    pub fn pending_key_rotations(&self) -> HashMap<Address, ContactKeyRotationPacket> {
+        self.local.key_rotations.lock().unwrap().clone()
+    }
+
     pub async fn remove_contact(&self, address: Address) -> Option<ContactHandle> {
         let mut contacts = self.contacts.lock().await;
         contacts.remove(&address)
     }
 
+    /// Drop requests and connections from the address and forget its
+    /// pending request.
+    pub async fn block_address(&self, address: Address) -> Option<ContactHandle> {
+        self.throttle.lock().unwrap().block(address);
+        self.remove_contact(address).await
+    }
+
+    pub fn unblock_address(&self, address: Address) -> bool {
+        self.throttle.lock().unwrap().unblock(&address)
+    }
+
+    pub fn is_blocked(&self, address: Address) -> bool {
+        self.throttle.lock().unwrap().is_blocked(&address)
+    }
+
+    /// Replace the blocked addresses, used to restore the persisted list.
+    pub fn set_blocked_addresses(&self, addresses: Vec<Address>) {
+        let mut throttle = self.throttle.lock().unwrap();
+        for address in throttle.blocked() {
+            throttle.unblock(&address);
+        }
+        for address in addresses {
+            throttle.block(address);
+        }
+    }
+
+    pub fn blocked_addresses(&self) -> Vec<Address> {
+        self.throttle.lock().unwrap().blocked()
+    }
+
+    /// Replace the keys whose contact requests are accepted without asking,
+    /// for contacts that exchanged keys out-of-band.
+    pub fn set_auto_accept_keys(&self, keys: Vec<PublicKey>) {
+        let keys = keys
+            .into_iter()
+            .filter_map(|v| Some((v.to_address().ok()?, v)))
+            .collect();
+        *self.local.auto_accept.lock().unwrap() = keys;
+    }
+
+    pub fn add_auto_accept_key(&self, public_key: PublicKey) -> Result<(), anyhow::Error> {
+        let address = public_key
+            .to_address()
+            .map_err(|err| anyhow!("Invalid public key: {err}"))?;
+        self.local
+            .auto_accept
+            .lock()
+            .unwrap()
+            .insert(address, public_key);
+        Ok(())
+    }
+
+    pub fn remove_auto_accept_key(&self, public_key: &PublicKey) -> bool {
+        match public_key.to_address() {
+            Ok(address) => self
+                .local
+                .auto_accept
+                .lock()
+                .unwrap()
+                .remove(&address)
+                .is_some(),
+            Err(_) => false,
+        }
+    }
+
+    pub fn auto_accept_keys(&self) -> Vec<PublicKey> {
+        self.local
+            .auto_accept
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_auto_accepted(&self, public_key: &PublicKey) -> bool {
+        match public_key.to_address() {
+            Ok(address) => self.local.is_auto_accepted(address),
+            Err(_) => false,
+        }
+    }
+
+    /// Bytes exchanged with the contact during this session.
+    pub async fn usage(&self, address: Address) -> Option<Usage> {
+        let contacts = self.contacts.lock().await;
+        contacts.get(&address).map(|contact| contact.usage())
+    }
+
+    /// Number of presence announcements sent to the contact during this session.
+    pub fn presence_attempts(&self, address: Address) -> u32 {
+        self.presence.lock().unwrap().attempts(address)
+    }
+
     pub async fn list_contacts(&self) -> Vec<ContactHandle> {
         let mut result = Vec::new();
         let contacts = self.contacts.lock().await;
@@ -169,27 +468,155 @@ impl ContactManager {
         Ok(())
     }
 
+    /// Reconnects to the server sending the new registration token.
+    pub async fn change_server_token(&self, token: Option<String>) -> Result<(), anyhow::Error> {
+        self.command_tx
+            .send(ManagerCommand::ChangeServerToken(token))
+            .await
+            .map_err(|err| anyhow!("Cannot change server token: {err}"))?;
+        Ok(())
+    }
+
     pub fn is_connected(&self) -> bool {
-        self.connected.load(Ordering::Relaxed)
+        self.connection_status() == ConnectionStatus::Connected
+    }
+
+    pub fn connection_status(&self) -> ConnectionStatus {
+        *self.status.lock().unwrap()
+    }
+
+    /// Marks packets sent to the server and contacts with the DSCP of
+    /// `class`. Returns false if marking is unsupported or there is no
+    /// transport yet, the class is applied after every reconnect anyway.
+    pub async fn set_traffic_class(&self, class: TrafficClass) -> bool {
+        *self.traffic_class.lock().unwrap() = class;
+        match self.transport.read().await.as_ref() {
+            Some(transport) => transport.set_traffic_class(class),
+            None => false,
+        }
+    }
+
+    pub fn traffic_class(&self) -> TrafficClass {
+        *self.traffic_class.lock().unwrap()
+    }
+
+    /// Whether the server registration and contact connections are renewed
+    /// after the system wakes from sleep, enabled by default.
+    pub fn set_wake_recovery(&self, enabled: bool) {
+        self.wake_recovery.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_wake_recovery_enabled(&self) -> bool {
+        self.wake_recovery.load(Ordering::Relaxed)
+    }
+
+    /// Whether contact connections are refreshed when the user returns to
+    /// the app, see [`ContactManager::refresh_contacts`]. Enabled by default.
+    pub fn set_focus_refresh(&self, enabled: bool) {
+        self.focus_refresh.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_focus_refresh_enabled(&self) -> bool {
+        self.focus_refresh.load(Ordering::Relaxed)
+    }
+
+    /// Pings online contacts and connects to offline ones without waiting
+    /// for their backoff. Connections left unanswered are closed, so the
+    /// contact is shown offline until a new connection is established.
+    pub async fn refresh_contacts(&self) {
+        let handles = self.list_contacts().await;
+        for handle in handles {
+            if handle.status() == ContactStatus::Accepted
+                && handle.is_connected()
+                && let Err(err) = handle.probe().await
+            {
+                tracing::warn!(address = ?handle.address(), ?err, "Failed to probe contact");
+            }
+        }
+        let Some(transport) = self.transport.read().await.clone() else {
+            return;
+        };
+        self.presence.lock().unwrap().clear_backoff();
+        Self::connect_offline(&transport, &self.contacts, &self.presence).await;
+    }
+
+    /// Public address of this client as observed by the server, used to
+    /// guess the NAT type with [`ntied_transport::NatType::classify`].
+    pub async fn reflexive_addr(&self) -> Result<SocketAddr, anyhow::Error> {
+        let transport = self
+            .transport
+            .read()
+            .await
+            .clone()
+            .ok_or(anyhow!("Not connected to server"))?;
+        transport
+            .reflexive_addr()
+            .await
+            .map_err(|err| anyhow!("Cannot get reflexive address: {err}"))
+    }
+
+    /// Runs a connection attempt to `address` step by step and reports
+    /// the outcome. A connection to a contact is handed to its handle.
+    pub async fn diagnose(&self, address: Address) -> Result<ConnectivityReport, anyhow::Error> {
+        let transport = self
+            .transport
+            .read()
+            .await
+            .clone()
+            .ok_or(anyhow!("Not connected to server"))?;
+        let (report, connection) = transport.diagnose(address).await;
+        tracing::info!(%address, "Connectivity diagnostic:\n{report}");
+        if let Some(connection) = connection {
+            let handle = self.contacts.lock().await.get(&address).cloned();
+            if let Some(handle) = handle
+                && let Err(err) = handle.set_connection(connection).await
+            {
+                tracing::debug!(?err, "Cannot pass diagnostic connection to contact");
+            }
+        }
+        Ok(report)
+    }
+
+    /// Stop reconnecting to the server and remove the registration on it.
+    pub async fn shutdown(&self) -> Result<(), anyhow::Error> {
+        self.main_task.abort();
+        *self.status.lock().unwrap() = ConnectionStatus::Disconnected;
+        let transport = self.transport.write().await.take();
+        if let Some(transport) = transport {
+            transport
+                .unregister()
+                .await
+                .map_err(|err| anyhow!("Cannot unregister from server: {err}"))?;
+        }
+        Ok(())
     }
 
     async fn main_loop(
         mut server_addr: SocketAddr,
+        mut server_token: Option<String>,
         private_key: PrivateKey,
         transport: Arc<TokioRwLock<Option<Arc<Transport>>>>,
         contacts: Arc<TokioMutex<HashMap<Address, ContactHandle>>>,
-        connected: Arc<AtomicBool>,
+        status: Arc<Mutex<ConnectionStatus>>,
+        traffic_class: Arc<Mutex<TrafficClass>>,
+        wake_recovery: Arc<AtomicBool>,
         // event_tx: mpsc::Sender<ContactEvent>,
         mut command_rx: mpsc::Receiver<ManagerCommand>,
         accept_tx: mpsc::Sender<Address>,
-        own_profile: ContactProfile,
+        local: Arc<LocalPeer>,
         listener: Arc<dyn ContactListener>,
+        throttle: Arc<Mutex<RequestThrottle>>,
+        presence: Arc<Mutex<PresenceSchedule>>,
     ) {
         let own_address = private_key.public_key().to_address().unwrap();
+        let mut wake = WakeDetector::default();
+        let mut resumed = false;
         loop {
-            if connected.swap(false, Ordering::SeqCst) {
+            let was_connected = *status.lock().unwrap() == ConnectionStatus::Connected;
+            if was_connected {
                 tracing::debug!("Server connection is lost");
-                listener.on_server_disconnected().await;
+                Self::set_status(&status, ConnectionStatus::Reconnecting);
+                listener.on_server_reconnecting().await;
             }
             loop {
                 match command_rx.try_recv() {
@@ -197,38 +624,77 @@ impl ContactManager {
                         ManagerCommand::ChangeServerAddr(addr) => {
                             server_addr = addr;
                         }
+                        ManagerCommand::ChangeServerToken(token) => {
+                            server_token = token;
+                        }
                     },
                     Err(mpsc::error::TryRecvError::Empty) => break,
                     Err(mpsc::error::TryRecvError::Disconnected) => {
                         tracing::debug!("Stopping main loop");
+                        Self::set_status(&status, ConnectionStatus::Disconnected);
+                        listener.on_server_disconnected().await;
                         return;
                     }
                 }
             }
             tracing::debug!(?server_addr, "Connecting to server");
-            let transport_arc =
-                match Transport::bind("0.0.0.0:0", own_address, private_key.clone(), server_addr)
-                    .await
-                {
-                    Ok(v) => Arc::new(v),
-                    Err(err) => {
-                        tracing::error!(?err, "Failed to connect to server");
-                        continue;
+            let bind_addr = if server_addr.is_ipv6() {
+                "[::]:0"
+            } else {
+                "0.0.0.0:0"
+            };
+            let transport_arc = match Transport::bind_with_server_token(
+                bind_addr,
+                own_address,
+                private_key.clone(),
+                server_addr,
+                server_token.clone(),
+            )
+            .await
+            {
+                Ok(v) => Arc::new(v),
+                Err(err) => {
+                    tracing::error!(?err, "Failed to connect to server");
+                    if Self::set_status(&status, ConnectionStatus::Reconnecting)
+                        != ConnectionStatus::Reconnecting
+                    {
+                        listener.on_server_reconnecting().await;
                     }
-                };
+                    // Rejected registrations fail without waiting for a timeout
+                    tokio::time::sleep(Self::RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
             tracing::debug!("Connected to server");
+            let class = *traffic_class.lock().unwrap();
+            if class != TrafficClass::default() && !transport_arc.set_traffic_class(class) {
+                tracing::debug!(?class, "Traffic class is not supported");
+            }
             {
                 let mut transport_guard = transport.write().await;
                 *transport_guard = Some(transport_arc.clone());
             }
-            connected.store(true, Ordering::SeqCst);
+            Self::set_status(&status, ConnectionStatus::Connected);
             listener.on_server_connected().await;
+            let presence_task = tokio::spawn(Self::announce_presence(
+                transport_arc.clone(),
+                contacts.clone(),
+                presence.clone(),
+                std::mem::take(&mut resumed),
+            ));
+            wake.reset();
+            let mut wake_ticker = tokio::time::interval(wake.interval());
+            wake_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
             loop {
                 tokio::select! {
                     v = transport_arc.accept() => {
                         match v {
                             Ok(connection) => {
                                 let address = *connection.peer_address();
+                                if throttle.lock().unwrap().is_blocked(&address) {
+                                    tracing::debug!(?address, "Dropping connection from blocked address");
+                                    continue;
+                                }
                                 let mut contacts_guard = contacts.lock().await;
                                 match contacts_guard.entry(address) {
                                     hash_map::Entry::Occupied(entry) => {
@@ -243,7 +709,7 @@ impl ContactManager {
                                             transport.clone(),
                                             connection,
                                             address,
-                                            own_profile.clone(),
+                                            local.clone(),
                                             own_address,
                                             listener.clone(),
                                         );
@@ -258,11 +724,18 @@ impl ContactManager {
                             }
                             Err(err) => {
                                 tracing::error!(?err, "Failed to accept connection");
-                                listener.on_server_disconnected().await;
                                 break;
                             }
                         }
                     }
+                    _ = wake_ticker.tick() => {
+                        let now = tokio::time::Instant::now().into_std();
+                        if wake.tick(now) && wake_recovery.load(Ordering::Relaxed) {
+                            tracing::info!("Wake from sleep detected, reconnecting to server");
+                            resumed = true;
+                            break;
+                        }
+                    }
                     v = command_rx.recv() => {
                         match v {
                             Some(v) => match v {
@@ -271,15 +744,105 @@ impl ContactManager {
                                     server_addr = addr;
                                     break;
                                 }
+                                ManagerCommand::ChangeServerToken(token) => {
+                                    tracing::debug!("Changing server token");
+                                    server_token = token;
+                                    break;
+                                }
                             },
                             None => {
                                 tracing::debug!("Stopping main loop");
+                                presence_task.abort();
+                                Self::set_status(&status, ConnectionStatus::Disconnected);
+                                listener.on_server_disconnected().await;
                                 return;
                             }
                         }
                     }
                 }
             }
+            presence_task.abort();
+        }
+    }
+
+    /// Replaces the connection status, returns the previous one.
+    fn set_status(status: &Mutex<ConnectionStatus>, value: ConnectionStatus) -> ConnectionStatus {
+        std::mem::replace(&mut *status.lock().unwrap(), value)
+    }
+
+    /// Connects to offline stored contacts one by one, so both sides see
+    /// each other online without waiting for a reconnect.
+    ///
+    /// After a wake from sleep connections of online contacts are stale,
+    /// they are renewed and unreachable contacts are tried again at once.
+    async fn announce_presence(
+        transport: Arc<Transport>,
+        contacts: Arc<TokioMutex<HashMap<Address, ContactHandle>>>,
+        presence: Arc<Mutex<PresenceSchedule>>,
+        resumed: bool,
+    ) {
+        if resumed {
+            presence.lock().unwrap().clear_backoff();
+            let handles: Vec<_> = contacts.lock().await.values().cloned().collect();
+            for handle in handles {
+                if handle.status() == ContactStatus::Accepted
+                    && handle.is_connected()
+                    && let Err(err) = handle.reconnect().await
+                {
+                    tracing::warn!(address = ?handle.address(), ?err, "Failed to reconnect contact");
+                }
+            }
+        }
+        tokio::time::sleep(Self::PRESENCE_DELAY).await;
+        Self::connect_offline(&transport, &contacts, &presence).await;
+    }
+
+    /// Connects to offline accepted contacts not backed off by the schedule.
+    async fn connect_offline(
+        transport: &Transport,
+        contacts: &TokioMutex<HashMap<Address, ContactHandle>>,
+        presence: &Mutex<PresenceSchedule>,
+    ) {
+        let handles: Vec<_> = contacts.lock().await.values().cloned().collect();
+        for handle in handles {
+            if handle.status() != ContactStatus::Accepted || handle.is_connected() {
+                continue;
+            }
+            let address = handle.address();
+            if !presence
+                .lock()
+                .unwrap()
+                .try_announce(address, Instant::now())
+            {
+                tracing::debug!(?address, "Skipping presence for unreachable contact");
+                continue;
+            }
+            let result =
+                tokio::time::timeout(Self::PRESENCE_TIMEOUT, transport.connect(address)).await;
+            match result {
+                Ok(Ok(connection)) => {
+                    presence.lock().unwrap().record_success(address);
+                    if let Err(err) = handle.set_connection(connection).await {
+                        tracing::warn!(?address, ?err, "Failed to set connection");
+                    }
+                }
+                Ok(Err(err)) => {
+                    tracing::debug!(?address, ?err, "Contact is unreachable");
+                    presence
+                        .lock()
+                        .unwrap()
+                        .record_failure(address, Instant::now());
+                }
+                Err(_) => {
+                    tracing::debug!(?address, "Presence announcement timed out");
+                    presence
+                        .lock()
+                        .unwrap()
+                        .record_failure(address, Instant::now());
+                }
+            }
+            let interval = presence.lock().unwrap().interval();
+            tokio::time::sleep(interval).await;
         }
     }
 }
@@ -292,4 +855,5 @@ impl Drop for ContactManager {
 
 enum ManagerCommand {
     ChangeServerAddr(SocketAddr),
+    ChangeServerToken(Option<String>),
 }
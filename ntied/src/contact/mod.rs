@@ -1,7 +1,19 @@
+mod features;
+mod focus;
 mod handle;
 mod listener;
 mod manager;
+mod presence;
+mod throttle;
+mod usage;
+mod wake;
 
+pub use features::{Features, Negotiated, PROTOCOL_VERSION};
+pub use focus::FocusTracker;
 pub use handle::*;
 pub use listener::*;
 pub use manager::*;
+pub use presence::PresenceSchedule;
+pub use throttle::{RequestThrottle, RequestVerdict};
+pub use usage::Usage;
+pub use wake::WakeDetector;
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ntied_transport::Address;
+
+/// Paces presence announcements to stored contacts and backs off from
+/// contacts that were unreachable.
+#[derive(Debug)]
+pub struct PresenceSchedule {
+    interval: Duration,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    contacts: HashMap<Address, PresenceEntry>,
+}
+
+#[derive(Debug, Default)]
+struct PresenceEntry {
+    attempts: u32,
+    failures: u32,
+    retry_after: Option<Instant>,
+}
+
+impl PresenceSchedule {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(50);
+    pub const DEFAULT_BACKOFF: Duration = Duration::from_secs(30);
+    pub const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+    pub fn new(interval: Duration, base_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            interval,
+            base_backoff,
+            max_backoff,
+            contacts: HashMap::new(),
+        }
+    }
+
+    /// Delay between announcements to different contacts.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Registers an announcement at `now` unless the contact is backed off.
+    pub fn try_announce(&mut self, address: Address, now: Instant) -> bool {
+        let entry = self.contacts.entry(address).or_default();
+        if entry
+            .retry_after
+            .is_some_and(|retry_after| now < retry_after)
+        {
+            return false;
+        }
+        entry.attempts += 1;
+        true
+    }
+
+    pub fn record_success(&mut self, address: Address) {
+        let entry = self.contacts.entry(address).or_default();
+        entry.failures = 0;
+        entry.retry_after = None;
+    }
+
+    /// Doubles the backoff of the contact up to the maximum.
+    pub fn record_failure(&mut self, address: Address, now: Instant) {
+        let entry = self.contacts.entry(address).or_default();
+        let backoff = self
+            .base_backoff
+            .saturating_mul(1 << entry.failures.min(16))
+            .min(self.max_backoff);
+        entry.failures += 1;
+        entry.retry_after = Some(now + backoff);
+    }
+
+    /// Forgets failures of all contacts, e.g. after the network changed.
+    pub fn clear_backoff(&mut self) {
+        for entry in self.contacts.values_mut() {
+            entry.failures = 0;
+            entry.retry_after = None;
+        }
+    }
+
+    /// Number of announcements to the contact.
+    pub fn attempts(&self, address: Address) -> u32 {
+        self.contacts
+            .get(&address)
+            .map_or(0, |entry| entry.attempts)
+    }
+}
+
+impl Default for PresenceSchedule {
+    fn default() -> Self {
+        Self::new(
+            Self::DEFAULT_INTERVAL,
+            Self::DEFAULT_BACKOFF,
+            Self::MAX_BACKOFF,
+        )
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ntied_crypto::PublicKey;
+use ntied_transport::Address;
+
+use crate::packet::ContactProfile;
+
+use super::ContactListener;
+
+/// Decision about an incoming contact request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestVerdict {
+    /// First request from the address, it should be shown.
+    Notify,
+    /// Repeated request within the window, it is merged into the pending one.
+    Coalesce,
+    /// Repeated requests after the window, `count` is the total number of requests.
+    Repeat { count: u32 },
+    /// Request from a blocked address.
+    Drop,
+}
+
+/// Coalesces repeated contact requests from the same address and drops
+/// requests from blocked addresses.
+#[derive(Debug)]
+pub struct RequestThrottle {
+    window: Duration,
+    requests: HashMap<Address, RequestEntry>,
+    blocked: HashSet<Address>,
+}
+
+#[derive(Debug)]
+struct RequestEntry {
+    window_start: Instant,
+    last_request: Instant,
+    count: u32,
+}
+
+impl RequestThrottle {
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(30);
+
+    // Requests idle for this many windows are forgotten
+    const IDLE_WINDOWS: u32 = 10;
+
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            requests: HashMap::new(),
+            blocked: HashSet::new(),
+        }
+    }
+
+    /// Registers a request from the address received at `now`.
+    pub fn check(&mut self, address: Address, now: Instant) -> RequestVerdict {
+        if self.blocked.contains(&address) {
+            return RequestVerdict::Drop;
+        }
+        let idle = self.window * Self::IDLE_WINDOWS;
+        self.requests
+            .retain(|_, entry| now.saturating_duration_since(entry.last_request) < idle);
+        let Some(entry) = self.requests.get_mut(&address) else {
+            self.requests.insert(
+                address,
+                RequestEntry {
+                    window_start: now,
+                    last_request: now,
+                    count: 1,
+                },
+            );
+            return RequestVerdict::Notify;
+        };
+        entry.count = entry.count.saturating_add(1);
+        entry.last_request = now;
+        if now.duration_since(entry.window_start) < self.window {
+            return RequestVerdict::Coalesce;
+        }
+        entry.window_start = now;
+        RequestVerdict::Repeat { count: entry.count }
+    }
+
+    /// Forgets requests from the address once it is accepted.
+    pub fn forget(&mut self, address: &Address) {
+        self.requests.remove(address);
+    }
+
+    pub fn block(&mut self, address: Address) {
+        self.requests.remove(&address);
+        self.blocked.insert(address);
+    }
+
+    pub fn unblock(&mut self, address: &Address) -> bool {
+        self.blocked.remove(address)
+    }
+
+    pub fn is_blocked(&self, address: &Address) -> bool {
+        self.blocked.contains(address)
+    }
+
+    /// Blocked addresses in no particular order.
+    pub fn blocked(&self) -> Vec<Address> {
+        self.blocked.iter().copied().collect()
+    }
+
+    /// Number of addresses with remembered requests.
+    pub fn pending_len(&self) -> usize {
+        self.requests.len()
+    }
+}
+
+impl Default for RequestThrottle {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+/// Listener that passes incoming requests through [`RequestThrottle`].
+pub(super) struct ThrottledListener {
+    inner: Arc<dyn ContactListener>,
+    throttle: Arc<Mutex<RequestThrottle>>,
+}
+
+impl ThrottledListener {
+    pub(super) fn new(
+        inner: Arc<dyn ContactListener>,
+        throttle: Arc<Mutex<RequestThrottle>>,
+    ) -> Self {
+        Self { inner, throttle }
+    }
+}
+
+#[async_trait]
+impl ContactListener for ThrottledListener {
+    async fn on_server_connected(&self) {
+        self.inner.on_server_connected().await
+    }
+
+    async fn on_server_reconnecting(&self) {
+        self.inner.on_server_reconnecting().await
+    }
+
+    async fn on_server_disconnected(&self) {
+        self.inner.on_server_disconnected().await
+    }
+
+    async fn on_contact_connected(&self, address: Address) {
+        self.inner.on_contact_connected(address).await
+    }
+
+    async fn on_contact_disconnected(&self, address: Address) {
+        self.inner.on_contact_disconnected(address).await
+    }
+
+    async fn on_contact_incoming(
+        &self,
+        address: Address,
+        profile: ContactProfile,
+        intro: Option<String>,
+    ) {
+        let verdict = self.throttle.lock().unwrap().check(address, Instant::now());
+        match verdict {
+            RequestVerdict::Notify => {
+                self.inner
+                    .on_contact_incoming(address, profile, intro)
+                    .await
+            }
+            RequestVerdict::Repeat { count } => {
+                self.inner
+                    .on_contact_incoming_repeated(address, profile, intro, count)
+                    .await
+            }
+            RequestVerdict::Coalesce => {
+                tracing::debug!(?address, "Coalescing repeated contact request");
+            }
+            RequestVerdict::Drop => {
+                tracing::debug!(?address, "Dropping contact request from blocked address");
+            }
+        }
+    }
+
+    async fn on_contact_incoming_repeated(
+        &self,
+        address: Address,
+        profile: ContactProfile,
+        intro: Option<String>,
+        count: u32,
+    ) {
+        self.inner
+            .on_contact_incoming_repeated(address, profile, intro, count)
+            .await
+    }
+
+    async fn on_contact_accepted(&self, address: Address, profile: ContactProfile) {
+        self.throttle.lock().unwrap().forget(&address);
+        self.inner.on_contact_accepted(address, profile).await
+    }
+
+    async fn on_contact_rejected(&self, address: Address) {
+        self.inner.on_contact_rejected(address).await
+    }
+
+    async fn on_contact_key_rotated(&self, address: Address, public_key: PublicKey) {
+        self.inner.on_contact_key_rotated(address, public_key).await
+    }
+
+    async fn on_key_rotation_delivered(&self, address: Address) {
+        self.inner.on_key_rotation_delivered(address).await
+    }
+}
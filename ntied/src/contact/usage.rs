@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of bytes exchanged with a contact.
+///
+/// Sizes of serialized packets passed to the transport are counted,
+/// encryption and UDP overhead is not included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+impl Usage {
+    pub fn total(&self) -> u64 {
+        self.bytes_sent + self.bytes_received
+    }
+
+    /// Bytes exchanged after the `earlier` snapshot was taken.
+    pub fn since(&self, earlier: Usage) -> Usage {
+        Usage {
+            bytes_sent: self.bytes_sent.saturating_sub(earlier.bytes_sent),
+            bytes_received: self.bytes_received.saturating_sub(earlier.bytes_received),
+        }
+    }
+}
+
+/// Counters of a contact handle, call packets are also counted separately.
+#[derive(Default)]
+pub(super) struct UsageCounter {
+    sent: AtomicU64,
+    received: AtomicU64,
+    call_sent: AtomicU64,
+    call_received: AtomicU64,
+}
+
+impl UsageCounter {
+    pub(super) fn add_sent(&self, bytes: u64, call: bool) {
+        self.sent.fetch_add(bytes, Ordering::Relaxed);
+        if call {
+            self.call_sent.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    pub(super) fn add_received(&self, bytes: u64, call: bool) {
+        self.received.fetch_add(bytes, Ordering::Relaxed);
+        if call {
+            self.call_received.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    pub(super) fn usage(&self) -> Usage {
+        Usage {
+            bytes_sent: self.sent.load(Ordering::Relaxed),
+            bytes_received: self.received.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(super) fn call_usage(&self) -> Usage {
+        Usage {
+            bytes_sent: self.call_sent.load(Ordering::Relaxed),
+            bytes_received: self.call_received.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+
+/// Detects a wake from suspend by a gap between periodic ticks much longer
+/// than the tick interval, connections made before the gap are stale.
+#[derive(Debug)]
+pub struct WakeDetector {
+    interval: Duration,
+    threshold: Duration,
+    last_tick: Option<Instant>,
+}
+
+impl WakeDetector {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+    pub const DEFAULT_THRESHOLD: Duration = Duration::from_secs(10);
+
+    /// Creates a detector ticking every `interval`, a tick late by more
+    /// than `threshold` is a wake.
+    pub fn new(interval: Duration, threshold: Duration) -> Self {
+        Self {
+            interval,
+            threshold,
+            last_tick: None,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Registers a tick at `now`, returns true if the time since the
+    /// previous tick shows the system was suspended.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let last_tick = self.last_tick.replace(now);
+        last_tick.is_some_and(|v| now.saturating_duration_since(v) > self.interval + self.threshold)
+    }
+
+    /// Forgets the previous tick, the next one starts a new measurement.
+    pub fn reset(&mut self) {
+        self.last_tick = None;
+    }
+}
+
+impl Default for WakeDetector {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_INTERVAL, Self::DEFAULT_THRESHOLD)
+    }
+}
@@ -0,0 +1,147 @@
+//! Redacted text bundle with the state of the application for bug reports
+
+use std::fmt::{self, Write as _};
+
+use crate::audio::{CodecType, DecoderStats};
+use crate::contact::ConnectionStatus;
+use crate::logs::LogLine;
+
+/// Replacement of removed content.
+const REDACTED: &str = "[redacted]";
+/// Length of an address in its text form.
+const ADDRESS_LEN: usize = 44;
+/// Characters of an address kept to tell addresses apart.
+const ADDRESS_PREFIX_LEN: usize = 6;
+
+/// State of the application collected for a bug report.
+///
+/// The text form never contains private keys, message contents or the given
+/// secrets, contact addresses are shortened.
+#[derive(Debug, Clone)]
+pub struct DiagnosticsBundle {
+    /// Version of the application with the platform.
+    pub version: String,
+    pub connection: ConnectionStatus,
+    /// Text of the last connectivity diagnostic.
+    pub connectivity: Option<String>,
+    /// Codecs in preference order.
+    pub codecs: Vec<CodecType>,
+    /// Playback counters of the current call.
+    pub call_stats: Option<DecoderStats>,
+    pub logs: Vec<LogLine>,
+    /// Values replaced wherever they appear, like the server token.
+    pub secrets: Vec<String>,
+}
+
+impl DiagnosticsBundle {
+    pub fn new(connection: ConnectionStatus) -> Self {
+        Self {
+            version: format!(
+                "{} ({} {})",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ),
+            connection,
+            connectivity: None,
+            codecs: Vec::new(),
+            call_stats: None,
+            logs: Vec::new(),
+            secrets: Vec::new(),
+        }
+    }
+
+    fn write_sections(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "== Version ==")?;
+        writeln!(out, "{}", self.version)?;
+        writeln!(out)?;
+        writeln!(out, "== Connection ==")?;
+        writeln!(out, "Server: {:?}", self.connection)?;
+        writeln!(out)?;
+        writeln!(out, "== Connectivity ==")?;
+        writeln!(out, "{}", self.connectivity.as_deref().unwrap_or("Not run"))?;
+        writeln!(out)?;
+        writeln!(out, "== Codecs ==")?;
+        let codecs: Vec<_> = self.codecs.iter().map(|v| v.name()).collect();
+        writeln!(out, "{}", codecs.join(", "))?;
+        writeln!(out)?;
+        writeln!(out, "== Call ==")?;
+        match &self.call_stats {
+            Some(stats) => {
+                writeln!(out, "Received packets: {}", stats.sent_packets)?;
+                writeln!(out, "Decoded frames: {}", stats.decoded_frames)?;
+                writeln!(out, "Concealed frames: {}", stats.plc_frames)?;
+                writeln!(out, "Underruns: {}", stats.underruns)?;
+                writeln!(
+                    out,
+                    "Average decode time: {}us",
+                    stats.avg_decode_time.as_micros()
+                )?;
+            }
+            None => writeln!(out, "No active call")?,
+        }
+        writeln!(out)?;
+        writeln!(out, "== Logs ==")?;
+        for line in &self.logs {
+            writeln!(out, "{line}")?;
+        }
+        Ok(())
+    }
+
+    /// Removes private keys and secrets from the text, shortens addresses.
+    fn redact(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut in_private_key = false;
+        for line in text.lines() {
+            if line.contains("PRIVATE KEY") {
+                // PEM blocks are replaced as a whole
+                if !in_private_key {
+                    result.push_str(REDACTED);
+                    result.push('\n');
+                }
+                in_private_key = !line.contains("-----END");
+                continue;
+            }
+            if in_private_key {
+                continue;
+            }
+            let mut line = line.to_string();
+            for secret in self.secrets.iter().filter(|v| !v.is_empty()) {
+                line = line.replace(secret.as_str(), REDACTED);
+            }
+            result.push_str(&shorten_addresses(&line));
+            result.push('\n');
+        }
+        result
+    }
+}
+
+impl fmt::Display for DiagnosticsBundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = String::new();
+        self.write_sections(&mut text)?;
+        f.write_str(self.redact(&text).trim_end())
+    }
+}
+
+/// Keeps the first characters of the words looking like addresses.
+fn shorten_addresses(line: &str) -> String {
+    let is_address_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find(is_address_char) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_address_char(c)).unwrap_or(rest.len());
+        let word = &rest[..end];
+        if word.len() == ADDRESS_LEN {
+            result.push_str(&word[..ADDRESS_PREFIX_LEN]);
+            result.push_str("...");
+        } else {
+            result.push_str(word);
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,204 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context as _, anyhow};
+use ntied_transport::Address;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use tokio::task::JoinHandle;
+
+use crate::client::Client;
+
+/// Command accepted by the headless client, one per input line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HeadlessCommand {
+    /// `send <address> <text>`
+    Send { address: Address, text: String },
+    /// `voice <address> <path>`, sends a WAV file as a voice message.
+    Voice { address: Address, path: PathBuf },
+    /// `request <address>`, sends a contact request.
+    Request { address: Address },
+    /// `accept <address>`, accepts an incoming contact request.
+    Accept { address: Address },
+    /// `reject <address>`, rejects an incoming contact request.
+    Reject { address: Address },
+    /// `answer <address>`
+    Answer { address: Address },
+    /// `decline <address>`
+    Decline { address: Address },
+    /// `hangup <address>`
+    Hangup { address: Address },
+    /// `diagnose <address>`, tries to connect and reports each step.
+    Diagnose { address: Address },
+    /// `contacts`
+    Contacts,
+    /// `quit`
+    Quit,
+}
+
+impl HeadlessCommand {
+    pub fn parse(line: &str) -> Result<Self, anyhow::Error> {
+        let line = line.trim();
+        let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim_start();
+        let address = || -> Result<Address, anyhow::Error> {
+            let value = args.split_whitespace().next().context("Missing address")?;
+            value
+                .parse()
+                .map_err(|err| anyhow!("Invalid address {value}: {err}"))
+        };
+        match name {
+            "send" => {
+                let (_, text) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let text = text.trim();
+                if text.is_empty() {
+                    return Err(anyhow!("Missing message text"));
+                }
+                Ok(Self::Send {
+                    address: address()?,
+                    text: text.to_string(),
+                })
+            }
+            "voice" => {
+                let (_, path) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let path = path.trim();
+                if path.is_empty() {
+                    return Err(anyhow!("Missing file path"));
+                }
+                Ok(Self::Voice {
+                    address: address()?,
+                    path: PathBuf::from(path),
+                })
+            }
+            "request" => Ok(Self::Request {
+                address: address()?,
+            }),
+            "accept" => Ok(Self::Accept {
+                address: address()?,
+            }),
+            "reject" => Ok(Self::Reject {
+                address: address()?,
+            }),
+            "answer" => Ok(Self::Answer {
+                address: address()?,
+            }),
+            "decline" => Ok(Self::Decline {
+                address: address()?,
+            }),
+            "hangup" => Ok(Self::Hangup {
+                address: address()?,
+            }),
+            "diagnose" => Ok(Self::Diagnose {
+                address: address()?,
+            }),
+            "contacts" => Ok(Self::Contacts),
+            "quit" => Ok(Self::Quit),
+            _ => Err(anyhow!("Unknown command: {name}")),
+        }
+    }
+}
+
+/// Client running without the GUI, driven by text commands.
+pub struct Headless {
+    client: Arc<Client>,
+    event_task: JoinHandle<()>,
+}
+
+impl Headless {
+    /// Wraps the client, its events are written to the log.
+    pub fn new(client: Arc<Client>) -> Self {
+        let event_task = tokio::spawn(Self::log_events(client.clone()));
+        Self { client, event_task }
+    }
+
+    /// Unlocks the profile in `path` and starts the managers.
+    pub async fn open(path: &Path, password: &str) -> Result<Self, anyhow::Error> {
+        Ok(Self::new(Arc::new(Client::open(path, password).await?)))
+    }
+
+    pub fn client(&self) -> &Arc<Client> {
+        &self.client
+    }
+
+    /// Executes commands from `input` until `quit` or end of input, replies
+    /// are written to `output` as `ok ...` or `error ...` lines.
+    pub async fn run<R, W>(&self, input: R, mut output: W) -> Result<(), anyhow::Error>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = input.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let command = HeadlessCommand::parse(&line);
+            if command.as_ref().is_ok_and(|v| *v == HeadlessCommand::Quit) {
+                break;
+            }
+            let reply = match command {
+                Ok(command) => self.execute(command).await,
+                Err(err) => Err(err),
+            };
+            let reply = match reply {
+                Ok(text) if text.is_empty() => "ok\n".to_string(),
+                Ok(text) => format!("ok {text}\n"),
+                Err(err) => format!("error {err}\n"),
+            };
+            output.write_all(reply.as_bytes()).await?;
+            output.flush().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn execute(&self, command: HeadlessCommand) -> Result<String, anyhow::Error> {
+        match command {
+            HeadlessCommand::Send { address, text } => {
+                let messages = self.client.send_message(address, text).await?;
+                let ids: Vec<_> = messages.iter().map(|v| v.message_id.to_string()).collect();
+                return Ok(ids.join(" "));
+            }
+            HeadlessCommand::Voice { address, path } => {
+                let messages = self.client.send_voice_file(address, &path).await?;
+                let ids: Vec<_> = messages.iter().map(|v| v.message_id.to_string()).collect();
+                return Ok(ids.join(" "));
+            }
+            HeadlessCommand::Request { address } => self.client.request_contact(address).await,
+            HeadlessCommand::Accept { address } => self.client.accept_contact(address).await?,
+            HeadlessCommand::Reject { address } => self.client.reject_contact(address).await?,
+            HeadlessCommand::Answer { address } => self.client.answer_call(address).await?,
+            HeadlessCommand::Decline { address } => self.client.decline_call(address).await?,
+            HeadlessCommand::Hangup { address } => self.client.end_call(address).await?,
+            HeadlessCommand::Diagnose { address } => {
+                let report = self.client.contact_manager().diagnose(address).await?;
+                // Replies are single lines
+                return Ok(report.to_string().replace('\n', "; "));
+            }
+            HeadlessCommand::Contacts => {
+                let mut contacts: Vec<_> = self
+                    .client
+                    .chat_manager()
+                    .list_contact_chats()
+                    .await
+                    .iter()
+                    .map(|v| v.address().to_string())
+                    .collect();
+                contacts.sort();
+                return Ok(contacts.join(" "));
+            }
+            HeadlessCommand::Quit => {}
+        }
+        Ok(String::new())
+    }
+
+    /// Stops the calls and removes the registration on the server.
+    pub async fn shutdown(&self) -> Result<(), anyhow::Error> {
+        self.event_task.abort();
+        self.client.shutdown().await
+    }
+
+    async fn log_events(client: Arc<Client>) {
+        while let Some(event) = client.next_event().await {
+            tracing::info!(?event, "Event");
+        }
+    }
+}
@@ -1,7 +1,12 @@
 pub mod audio;
+pub mod avatar;
 pub mod call;
 pub mod chat;
+pub mod client;
 pub mod contact;
+pub mod diagnostics;
+pub mod headless;
+pub mod logs;
 pub mod models;
 pub mod packet;
 pub mod storage;
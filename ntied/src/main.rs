@@ -1,17 +1,37 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use iced::window::{Icon, Settings, icon};
-use ntied::ui::ChatApp;
+use ntied::headless::Headless;
+use ntied::logs::LogBuffer;
+use ntied::ui::{AppContext, ChatApp};
+use tokio::io::{AsyncBufReadExt as _, BufReader};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::prelude::*;
 
 fn main() -> iced::Result {
+    let headless = std::env::args().skip(1).any(|v| v == "--headless");
+    // Stdout is reserved for command replies in headless mode
+    let writer = if headless {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "ntied=debug,iced=warn,ntied_transport=info".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
+        .with(LogBuffer::global().layer())
         .init();
+    if headless {
+        let runtime = tokio::runtime::Runtime::new().expect("Cannot start runtime");
+        if let Err(err) = runtime.block_on(run_headless()) {
+            eprintln!("{err:#}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     iced::application(ChatApp::title, ChatApp::update, ChatApp::view)
         .theme(ChatApp::theme)
         .window(Settings {
@@ -19,9 +39,28 @@ fn main() -> iced::Result {
             ..Default::default()
         })
         .subscription(ChatApp::subscription)
+        .exit_on_close_request(false)
         .run_with(ChatApp::new)
 }
 
+/// Runs the client without the GUI, commands are read from stdin. The
+/// password is taken from `NTIED_PASSWORD` or the first input line.
+async fn run_headless() -> Result<(), anyhow::Error> {
+    let mut input = BufReader::new(tokio::io::stdin());
+    let password = match std::env::var("NTIED_PASSWORD") {
+        Ok(v) => v,
+        Err(_) => {
+            let mut line = String::new();
+            input.read_line(&mut line).await?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+    let headless = Headless::open(&AppContext::get_data_dir()?, &password).await?;
+    let result = headless.run(input, tokio::io::stdout()).await;
+    headless.shutdown().await?;
+    result
+}
+
 fn window_icon() -> Option<Icon> {
     const ICON_DATA: &[u8] = include_bytes!("../assets/ntied-icon.png");
     let image = image::load_from_memory(ICON_DATA).ok()?;
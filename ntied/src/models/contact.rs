@@ -4,8 +4,8 @@ use ntied_transport::Address;
 use tokio_sqlite::Value;
 
 use super::{
-    ColumnIndex, DateTime, value_as_address, value_as_bytes, value_as_datetime, value_as_i64,
-    value_as_string, value_as_string_opt,
+    ColumnIndex, DateTime, value_as_address, value_as_bool, value_as_bytes, value_as_bytes_opt,
+    value_as_datetime, value_as_i64, value_as_string, value_as_string_opt,
 };
 
 #[derive(Clone)]
@@ -19,6 +19,19 @@ pub struct Contact {
     pub local_name: Option<String>,
     // Name obtained from the remote contact.
     pub name: String,
+    // PNG avatar obtained from the remote contact.
+    pub avatar: Option<Vec<u8>>,
+    // Archived chats are hidden from the main chat list.
+    pub archived: bool,
+    // Pinned chats are listed first, ordered by pin order.
+    pub pinned: bool,
+    pub pin_order: i64,
+    // Fingerprint of the key was confirmed by the user out-of-band.
+    pub verified: bool,
+    // Muted contacts do not pop notifications or ring.
+    pub muted: bool,
+    // Calls of the contact are answered without user action, verified only.
+    pub auto_answer: bool,
     pub create_time: DateTime,
 }
 
@@ -31,6 +44,13 @@ impl Contact {
                 .add("public_key")
                 .add("local_name")
                 .add("name")
+                .add("avatar")
+                .add("archived")
+                .add("pinned")
+                .add("pin_order")
+                .add("verified")
+                .add("muted")
+                .add("auto_answer")
                 .add("create_time")
                 .build();
         }
@@ -48,6 +68,13 @@ impl Contact {
         );
         columns.set_value(&mut values, "local_name", self.local_name.clone());
         columns.set_value(&mut values, "name", self.name.clone());
+        columns.set_value(&mut values, "avatar", self.avatar.clone());
+        columns.set_value(&mut values, "archived", self.archived);
+        columns.set_value(&mut values, "pinned", self.pinned);
+        columns.set_value(&mut values, "pin_order", self.pin_order);
+        columns.set_value(&mut values, "verified", self.verified);
+        columns.set_value(&mut values, "muted", self.muted);
+        columns.set_value(&mut values, "auto_answer", self.auto_answer);
         columns.set_value(
             &mut values,
             "create_time",
@@ -66,6 +93,13 @@ impl Contact {
             .map_err(anyhow::Error::msg)?,
             local_name: value_as_string_opt(columns.get_value(&values, "local_name").unwrap())?,
             name: value_as_string(columns.get_value(&values, "name").unwrap())?,
+            avatar: value_as_bytes_opt(columns.get_value(&values, "avatar").unwrap())?,
+            archived: value_as_bool(columns.get_value(&values, "archived").unwrap())?,
+            pinned: value_as_bool(columns.get_value(&values, "pinned").unwrap())?,
+            pin_order: value_as_i64(columns.get_value(&values, "pin_order").unwrap())?,
+            verified: value_as_bool(columns.get_value(&values, "verified").unwrap())?,
+            muted: value_as_bool(columns.get_value(&values, "muted").unwrap())?,
+            auto_answer: value_as_bool(columns.get_value(&values, "auto_answer").unwrap())?,
             create_time: value_as_datetime(columns.get_value(&values, "create_time").unwrap())?,
         })
     }
@@ -1,12 +1,17 @@
+use std::time::Duration;
+
 use anyhow::anyhow;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use tokio_sqlite::Value;
 use uuid::Uuid;
 
+use crate::audio::CodecType;
+
 use super::{
-    ColumnIndex, DateTime, value_as_bool, value_as_datetime, value_as_datetime_opt, value_as_i64,
-    value_as_string, value_as_u64_opt, value_as_uuid,
+    Base64, ColumnIndex, DateTime, value_as_bool, value_as_bytes_opt, value_as_datetime,
+    value_as_datetime_opt, value_as_i64, value_as_i64_opt, value_as_string, value_as_u64_opt,
+    value_as_uuid,
 };
 
 #[derive(Debug, Clone)]
@@ -17,12 +22,45 @@ pub struct Message {
     pub log_id: Option<u64>,
     pub incoming: bool,
     pub kind: MessageKind,
+    /// Time by the sender clock, it may be wrong for incoming messages.
     pub create_time: DateTime,
+    /// Local time the message was received or its delivery was confirmed.
     pub receive_time: Option<DateTime>,
     pub read_time: Option<DateTime>,
+    /// Whether the signature of an incoming message matched the contact key.
+    /// Outgoing messages are always verified, incoming ones with a wrong
+    /// signature are rejected, so only messages of contacts sending no
+    /// signatures are unverified.
+    pub verified: bool,
+    /// Signature of an incoming message made by the sender identity key,
+    /// `None` for outgoing messages and messages received before
+    /// signatures were stored.
+    pub signature: Option<Vec<u8>>,
+    /// Id of the message this one replies to, it may point to a deleted message.
+    pub reply_to: Option<i64>,
 }
 
 impl Message {
+    /// Difference between sender and local clocks considered a wrong clock.
+    pub const MAX_CLOCK_SKEW: chrono::TimeDelta = chrono::TimeDelta::minutes(5);
+
+    /// How far the sender clock was ahead of the local one when an incoming
+    /// message was received, negative if it was behind.
+    pub fn clock_skew(&self) -> Option<chrono::TimeDelta> {
+        if !self.incoming {
+            return None;
+        }
+        self.receive_time
+            .map(|receive_time| self.create_time.0 - receive_time.0)
+    }
+
+    /// Whether the sender clock differs from the local one by more than
+    /// [`Message::MAX_CLOCK_SKEW`].
+    pub fn has_clock_skew(&self) -> bool {
+        self.clock_skew()
+            .is_some_and(|skew| skew.abs() > Self::MAX_CLOCK_SKEW)
+    }
+
     pub fn columns() -> &'static ColumnIndex {
         lazy_static! {
             static ref COLUMNS: ColumnIndex = ColumnIndex::builder()
@@ -36,6 +74,9 @@ impl Message {
                 .add("create_time")
                 .add("receive_time")
                 .add("read_time")
+                .add("verified")
+                .add("signature")
+                .add("reply_to")
                 .build();
         }
         &COLUMNS
@@ -65,6 +106,9 @@ impl Message {
             "read_time",
             self.read_time.map(|v| v.0.timestamp_micros()),
         );
+        columns.set_value(&mut values, "verified", self.verified);
+        columns.set_value(&mut values, "signature", self.signature.clone());
+        columns.set_value(&mut values, "reply_to", self.reply_to);
         values
     }
 
@@ -84,32 +128,145 @@ impl Message {
                 columns.get_value(&values, "receive_time").unwrap(),
             )?,
             read_time: value_as_datetime_opt(columns.get_value(&values, "read_time").unwrap())?,
+            verified: value_as_bool(columns.get_value(&values, "verified").unwrap())?,
+            signature: value_as_bytes_opt(columns.get_value(&values, "signature").unwrap())?,
+            reply_to: value_as_i64_opt(columns.get_value(&values, "reply_to").unwrap())?,
         })
     }
 }
 
+/// Slice of chat history returned by cursor-based loading.
+#[derive(Debug, Clone)]
+pub struct HistoryPage {
+    /// Messages in chronological order.
+    pub messages: Vec<Message>,
+    /// Whether there are older messages before the first one in the page.
+    pub has_more: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MessageKind {
     Text(String),
+    Voice(VoiceMessage),
 }
 
 impl MessageKind {
+    /// Maximum length of a text message in bytes, a message is sent in a
+    /// single packet.
+    pub const MAX_TEXT_LEN: usize = 16 * 1024;
+
+    /// Splits a text into parts of at most [`MessageKind::MAX_TEXT_LEN`]
+    /// bytes, preferably after a line break or a space.
+    pub fn split_text(text: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let mut rest = text;
+        while rest.len() > Self::MAX_TEXT_LEN {
+            let mut end = Self::MAX_TEXT_LEN;
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            let head = &rest[..end];
+            let end = head
+                .rfind('\n')
+                .map(|v| v + 1)
+                .or_else(|| {
+                    head.char_indices()
+                        .rev()
+                        .find(|(_, c)| c.is_whitespace())
+                        .map(|(v, c)| v + c.len_utf8())
+                })
+                .filter(|&v| v > Self::MAX_TEXT_LEN / 2)
+                .unwrap_or(end);
+            parts.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        if !rest.is_empty() || parts.is_empty() {
+            parts.push(rest);
+        }
+        parts
+    }
+
     pub fn name(&self) -> &str {
         match self {
             Self::Text(_) => "text",
+            Self::Voice(_) => "voice",
         }
     }
 
     pub fn content(&self) -> String {
         match self {
             Self::Text(text) => text.clone(),
+            Self::Voice(voice) => serde_json::to_string(voice).unwrap(),
         }
     }
 
     pub fn parse(kind: String, content: String) -> Result<Self, anyhow::Error> {
         match kind.as_str() {
             "text" => Ok(Self::Text(content)),
+            "voice" => serde_json::from_str(&content)
+                .map(Self::Voice)
+                .map_err(|e| anyhow!("Failed to parse voice message: {}", e)),
             _ => Err(anyhow!("Unknown message kind: {}", kind)),
         }
     }
+
+    /// Text shown for the message in the chat and notifications.
+    pub fn display_text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Voice(voice) => {
+                format!("Voice message ({:.1} s)", voice.duration().as_secs_f32())
+            }
+        }
+    }
+}
+
+/// Encoded mono audio sent as a chat message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceMessage {
+    pub codec: CodecType,
+    pub sample_rate: u32,
+    /// Length of the audio in milliseconds, the last frame is padded with
+    /// silence.
+    pub duration_ms: u32,
+    /// Frames of [`VoiceMessage::FRAME_DURATION`] encoded one by one.
+    pub frames: Vec<Base64>,
+}
+
+impl VoiceMessage {
+    pub const FRAME_DURATION: Duration = Duration::from_millis(20);
+    /// Maximum size of encoded frames of one message, a message is sent in
+    /// a single packet.
+    pub const MAX_DATA_LEN: usize = 40 * 1024;
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms.into())
+    }
+
+    /// Total size of encoded frames in bytes.
+    pub fn data_len(&self) -> usize {
+        self.frames.iter().map(|v| v.0.len()).sum()
+    }
+
+    /// Checks that the frames fit into a packet and cover the duration.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.frames.is_empty() || self.duration_ms == 0 {
+            return Err(anyhow!("Voice message is empty"));
+        }
+        if self.data_len() > Self::MAX_DATA_LEN {
+            return Err(anyhow!(
+                "Voice message is too large: {} bytes, at most {} are allowed",
+                self.data_len(),
+                Self::MAX_DATA_LEN
+            ));
+        }
+        if self.duration() > Self::FRAME_DURATION * self.frames.len() as u32 {
+            return Err(anyhow!(
+                "Voice message of {} frames cannot last {} ms",
+                self.frames.len(),
+                self.duration_ms
+            ));
+        }
+        Ok(())
+    }
 }
@@ -17,6 +17,12 @@ impl DateTime {
     pub fn now() -> Self {
         Self(chrono::Utc::now())
     }
+
+    /// Creation time embedded in a v7 UUID.
+    pub fn from_uuid(id: &Uuid) -> Option<Self> {
+        let (secs, nanos) = id.get_timestamp()?.to_unix();
+        chrono::DateTime::from_timestamp(secs.try_into().ok()?, nanos).map(Self)
+    }
 }
 
 impl Serialize for DateTime {
@@ -242,6 +248,13 @@ pub(super) fn value_as_u64_opt(v: &Value) -> Result<Option<u64>, anyhow::Error>
     }
 }
 
+pub(super) fn value_as_i64_opt(v: &Value) -> Result<Option<i64>, anyhow::Error> {
+    match v {
+        Value::Null => Ok(None),
+        v => value_as_i64(v).map(Some),
+    }
+}
+
 pub(super) fn value_as_string_opt(v: &Value) -> Result<Option<String>, anyhow::Error> {
     match v {
         Value::Null => Ok(None),
@@ -249,6 +262,13 @@ pub(super) fn value_as_string_opt(v: &Value) -> Result<Option<String>, anyhow::E
     }
 }
 
+pub(super) fn value_as_bytes_opt(v: &Value) -> Result<Option<Vec<u8>>, anyhow::Error> {
+    match v {
+        Value::Null => Ok(None),
+        v => value_as_bytes(v).map(Some),
+    }
+}
+
 pub(super) fn value_as_datetime_opt(v: &Value) -> Result<Option<DateTime>, anyhow::Error> {
     match v {
         Value::Null => Ok(None),
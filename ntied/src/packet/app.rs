@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Payload of an extension built on top of the contact channel.
+///
+/// The namespace is reserved for applications embedding ntied, the chat and
+/// call managers never consume these packets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AppPacket {
+    pub data: Vec<u8>,
+}
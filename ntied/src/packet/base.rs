@@ -1,10 +1,42 @@
 use serde::{Deserialize, Serialize};
 
-use super::{CallPacket, ChatPacket, ContactPacket};
+use super::{AppPacket, CallPacket, ChatPacket, ContactPacket, UnknownCallPacket};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Packet {
     Contact(ContactPacket),
     Chat(ChatPacket),
     Call(CallPacket),
+    /// Opaque payloads of extensions, see [`AppPacket`].
+    App(AppPacket),
+}
+
+impl Packet {
+    // Bincode variant index of `Packet::Call`
+    const CALL_TAG: u32 = 2;
+
+    /// Decodes a packet received from a contact.
+    ///
+    /// Call packets with a variant added by a newer client are decoded as
+    /// [`CallPacket::Unknown`] instead of failing.
+    pub fn decode(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        let err = match bincode::deserialize(bytes) {
+            Ok(packet) => return Ok(packet),
+            Err(err) => err,
+        };
+        let tag = |offset: usize| {
+            bytes
+                .get(offset..offset + 4)
+                .map(|v| u32::from_le_bytes(v.try_into().unwrap()))
+        };
+        match (tag(0), tag(4)) {
+            (Some(Self::CALL_TAG), Some(tag)) if tag >= CallPacket::KNOWN_VARIANTS => {
+                Ok(Packet::Call(CallPacket::Unknown(UnknownCallPacket {
+                    tag,
+                    data: bytes[8..].to_vec(),
+                })))
+            }
+            _ => Err(err),
+        }
+    }
 }
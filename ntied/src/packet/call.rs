@@ -1,4 +1,4 @@
-use crate::audio::{CodecCapabilities, CodecType, NegotiatedCodec};
+use crate::audio::{CodecCapabilities, CodecType, ContentType, NegotiatedCodec};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -12,6 +12,23 @@ pub enum CallPacket {
     VideoData(VideoDataPacket),
     CodecOffer(CodecOfferPacket),
     CodecAnswer(CodecAnswerPacket),
+    /// The offer shares no codec with the sender, the call is over.
+    CodecMismatch(CodecMismatchPacket),
+    /// Packet of a newer protocol version, it is never sent.
+    #[serde(skip)]
+    Unknown(UnknownCallPacket),
+}
+
+impl CallPacket {
+    /// Number of variants known to this version, `Unknown` excluded.
+    pub const KNOWN_VARIANTS: u32 = 9;
+}
+
+/// Call packet with an unrecognized variant tag and its raw payload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownCallPacket {
+    pub tag: u32,
+    pub data: Vec<u8>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -38,10 +55,12 @@ pub struct CallEndPacket {
 pub struct AudioDataPacket {
     pub call_id: Uuid,
     pub sequence: u32,    // Sequence number for packet ordering
-    pub timestamp: u64,   // Unix timestamp in microseconds
+    pub timestamp: u64,   // Capture time, Unix timestamp in microseconds
     pub codec: CodecType, // Codec used for encoding
     pub channels: u16,    // Number of channels (e.g., 1 for mono)
     pub data: Vec<u8>,    // Encoded audio data
+    /// Speech or music hint of the sender's classifier.
+    pub content: ContentType,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -57,6 +76,13 @@ pub struct CodecAnswerPacket {
     pub negotiated_codec: NegotiatedCodec,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CodecMismatchPacket {
+    pub call_id: Uuid,
+    /// Codecs of the sender in preference order.
+    pub codecs: Vec<CodecType>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct VideoDataPacket {
     pub call_id: Uuid,
@@ -1,11 +1,29 @@
+use ntied_crypto::{PrivateKey, PublicKey};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::models::VoiceMessage;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ChatPacket {
-    Message(ChatMessagePacket),
+    /// Message of contacts not supporting
+    /// [`crate::contact::Features::SIGNED_MESSAGE`], sent without the reply
+    /// and the signature.
+    Message(#[serde(with = "plain_message")] ChatMessagePacket),
     MessageAck(ChatMessageAckPacket),
     Conflict(ChatConflictPacket),
+    /// Several packets sent at once, handled in order. Only sent to
+    /// contacts supporting [`crate::contact::Features::MESSAGE_BATCH`].
+    Batch(Vec<ChatPacket>),
+    /// Starts an end-to-end session, the session packets are only sent to
+    /// contacts supporting [`crate::contact::Features::MESSAGE_RATCHET`].
+    SessionInit(ChatSessionInitPacket),
+    SessionAccept(ChatSessionAcceptPacket),
+    /// Message packets encrypted with the end-to-end session.
+    Sealed(SealedChatPacket),
+    /// Message with a reply and a signature, only sent to contacts
+    /// supporting [`crate::contact::Features::SIGNED_MESSAGE`].
+    SignedMessage(ChatMessagePacket),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -13,11 +31,67 @@ pub struct ChatMessagePacket {
     pub message_id: Uuid,
     pub log_id: u64,
     pub kind: ChatMessageKind,
+    /// Id of the message this one replies to.
+    pub reply_to: Option<Uuid>,
+    /// Signature of the message made by the sender identity key.
+    pub signature: Vec<u8>,
+}
+
+impl ChatMessagePacket {
+    const CONTEXT: &[u8] = b"ntied-chat-message";
+
+    /// Creates a packet signed by `key`.
+    ///
+    /// The signature does not cover `log_id`, it changes when the message is resent.
+    pub fn new(
+        message_id: Uuid,
+        log_id: u64,
+        kind: ChatMessageKind,
+        reply_to: Option<Uuid>,
+        key: &PrivateKey,
+    ) -> Self {
+        let signature = key.sign(Self::signed_message(message_id, &kind, reply_to));
+        Self {
+            message_id,
+            log_id,
+            kind,
+            reply_to,
+            signature,
+        }
+    }
+
+    /// Checks that the message is signed by `key`.
+    pub fn verify(&self, key: &PublicKey) -> bool {
+        let message = Self::signed_message(self.message_id, &self.kind, self.reply_to);
+        key.verify(message, &self.signature).unwrap_or(false)
+    }
+
+    fn signed_message(message_id: Uuid, kind: &ChatMessageKind, reply_to: Option<Uuid>) -> Vec<u8> {
+        let (tag, content): (&[u8], _) = match kind {
+            ChatMessageKind::Text(text) => (b"text", text.as_bytes().to_vec()),
+            ChatMessageKind::Voice(voice) => (b"voice", bincode::serialize(voice).unwrap()),
+        };
+        let mut message = Self::CONTEXT.to_vec();
+        message.extend_from_slice(message_id.as_bytes());
+        message.extend_from_slice(tag);
+        // Content is length prefixed, so it cannot run into the reply
+        message.extend_from_slice(&(content.len() as u32).to_be_bytes());
+        message.extend_from_slice(&content);
+        match reply_to {
+            Some(reply_to) => {
+                message.push(1);
+                message.extend_from_slice(reply_to.as_bytes());
+            }
+            None => message.push(0),
+        }
+        message
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ChatMessageKind {
     Text(String),
+    Voice(VoiceMessage),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -30,3 +104,88 @@ pub struct ChatMessageAckPacket {
 pub struct ChatConflictPacket {
     pub message_id: Uuid,
 }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatSessionInitPacket {
+    /// Ephemeral public key of the initiator, identifies the session.
+    pub init_key: Vec<u8>,
+    /// Signature of the init key by the identity of the initiator.
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatSessionAcceptPacket {
+    pub init_key: Vec<u8>,
+    /// First ratchet public key of the accepting side.
+    pub ratchet_key: Vec<u8>,
+    /// Signature of both keys by the identity of the accepting side.
+    pub signature: Vec<u8>,
+}
+
+/// Position of a sealed packet in the sending chain of the session.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RatchetHeader {
+    /// Current ratchet public key of the sender.
+    pub ratchet_key: Vec<u8>,
+    /// Number of packets sealed in the previous sending chain.
+    pub prev_count: u32,
+    /// Number of the packet in the current sending chain.
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SealedChatPacket {
+    /// Init key of the session.
+    pub session: Vec<u8>,
+    pub header: RatchetHeader,
+    /// Encrypted bincode of a [`ChatPacket`].
+    pub ciphertext: Vec<u8>,
+}
+
+/// Layout of [`ChatPacket::Message`], the fields of [`ChatMessagePacket`]
+/// known to clients before signed messages.
+mod plain_message {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use uuid::Uuid;
+
+    use super::{ChatMessageKind, ChatMessagePacket};
+
+    #[derive(Serialize)]
+    struct PlainRef<'a> {
+        message_id: Uuid,
+        log_id: u64,
+        kind: &'a ChatMessageKind,
+    }
+
+    #[derive(Deserialize)]
+    struct Plain {
+        message_id: Uuid,
+        log_id: u64,
+        kind: ChatMessageKind,
+    }
+
+    pub fn serialize<S: Serializer>(
+        packet: &ChatMessagePacket,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        PlainRef {
+            message_id: packet.message_id,
+            log_id: packet.log_id,
+            kind: &packet.kind,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ChatMessagePacket, D::Error> {
+        let packet = Plain::deserialize(deserializer)?;
+        Ok(ChatMessagePacket {
+            message_id: packet.message_id,
+            log_id: packet.log_id,
+            kind: packet.kind,
+            reply_to: None,
+            signature: Vec::new(),
+        })
+    }
+}
@@ -1,15 +1,38 @@
+use anyhow::anyhow;
+use ntied_crypto::{PrivateKey, PublicKey};
 use serde::{Deserialize, Serialize};
 
+use crate::models::Base64;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ContactPacket {
     Request(ContactRequestPacket),
     Accept(ContactAcceptPacket),
     Reject(ContactRejectPacket),
+    ProfileUpdate(ContactProfileUpdatePacket),
+    KeyRotation(ContactKeyRotationPacket),
+    Hello(ContactHelloPacket),
+    Ping(ContactPingPacket),
+    Pong(ContactPingPacket),
+    KeyRotationAck(ContactKeyRotationAckPacket),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContactRequestPacket {
     pub profile: ContactProfile,
+    /// Short note from the sender shown with the request. Appended after
+    /// the profile only when set, so older clients still read the request.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "trailing_option"
+    )]
+    pub intro: Option<String>,
+}
+
+impl ContactRequestPacket {
+    /// Maximum length of the intro note in characters.
+    pub const MAX_INTRO_LEN: usize = 200;
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -20,7 +43,113 @@ pub struct ContactAcceptPacket {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContactRejectPacket {}
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContactProfileUpdatePacket {
+    pub profile: ContactProfile,
+}
+
+/// Protocol version and feature bits, sent first on every connection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContactHelloPacket {
+    pub version: u32,
+    pub features: u32,
+}
+
+/// Probe of the connection liveness, a pong echoes the nonce of the ping.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContactPingPacket {
+    pub nonce: u64,
+}
+
+/// Announces a new identity key, signed by the previous key to prove continuity.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContactKeyRotationPacket {
+    /// New public key in DER format.
+    pub public_key: Vec<u8>,
+    /// Signature of the continuity message made by the old key.
+    pub signature: Vec<u8>,
+    /// Old public key in DER format, lets contacts verify announcements
+    /// sent from the new identity. Left out of the encoding when unset,
+    /// older clients ignore it.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "trailing_option"
+    )]
+    pub previous_key: Option<Vec<u8>>,
+}
+
+impl ContactKeyRotationPacket {
+    const CONTEXT: &[u8] = b"ntied-key-rotation";
+
+    /// Creates a packet announcing `new_key` signed by `old_key`.
+    pub fn new(old_key: &PrivateKey, new_key: &PublicKey) -> Result<Self, anyhow::Error> {
+        let public_key = new_key.to_bytes().map_err(|e| anyhow!(e))?;
+        let message = Self::continuity_message(&old_key.public_key(), &public_key)?;
+        Ok(Self {
+            signature: old_key.sign(message),
+            public_key,
+            previous_key: Some(old_key.public_key().to_bytes().map_err(|e| anyhow!(e))?),
+        })
+    }
+
+    /// Returns the new public key if the packet is signed by `old_key`.
+    pub fn verify(&self, old_key: &PublicKey) -> Result<PublicKey, anyhow::Error> {
+        let message = Self::continuity_message(old_key, &self.public_key)?;
+        if !old_key
+            .verify(message, &self.signature)
+            .map_err(|e| anyhow!(e))?
+        {
+            return Err(anyhow!("Invalid key rotation signature"));
+        }
+        PublicKey::from_bytes(&self.public_key).map_err(|e| anyhow!(e))
+    }
+
+    /// Returns the old and the new public keys if the packet is signed by
+    /// the old key it carries.
+    pub fn verify_previous(&self) -> Result<(PublicKey, PublicKey), anyhow::Error> {
+        let previous_key = self
+            .previous_key
+            .as_ref()
+            .ok_or(anyhow!("Key rotation without previous key"))?;
+        let old_key = PublicKey::from_bytes(previous_key).map_err(|e| anyhow!(e))?;
+        let new_key = self.verify(&old_key)?;
+        Ok((old_key, new_key))
+    }
+
+    fn continuity_message(old_key: &PublicKey, new_key: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        let old_key = old_key.to_bytes().map_err(|e| anyhow!(e))?;
+        let mut message = Self::CONTEXT.to_vec();
+        message.extend_from_slice(&(old_key.len() as u32).to_be_bytes());
+        message.extend_from_slice(&old_key);
+        message.extend_from_slice(new_key);
+        Ok(message)
+    }
+}
+
+/// Confirms that a key rotation was received, the announcement is resent
+/// on every connection until it is acknowledged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContactKeyRotationAckPacket {
+    /// New public key of the acknowledged announcement in DER format.
+    pub public_key: Vec<u8>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContactProfile {
     pub name: String,
+    /// PNG avatar image, see [`crate::avatar`]. Profiles of older clients
+    /// end before it and are read without an avatar.
+    #[serde(default, deserialize_with = "trailing_option")]
+    pub avatar: Option<Base64>,
+}
+
+/// Decodes an optional field at the end of a packet as `None` when the
+/// sender left it out.
+fn trailing_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer).ok().flatten())
 }
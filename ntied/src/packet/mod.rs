@@ -1,8 +1,10 @@
+mod app;
 mod base;
 mod call;
 mod chat;
 mod contact;
 
+pub use app::*;
 pub use base::*;
 pub use call::*;
 pub use chat::*;
@@ -64,6 +64,10 @@ impl Storage {
         })
     }
 
+    /// Rekey the storage only, use [`ConfigManager::change_password`] to
+    /// keep the private keys readable with the new password.
+    ///
+    /// [`ConfigManager::change_password`]: crate::config::ConfigManager::change_password
     pub async fn change_password(
         &mut self,
         password: &str,
@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::models::{Contact, DateTime, HistoryPage, Message};
+
+use super::{ConfigStore, MessageStore};
+
+/// Volatile store that keeps everything in memory.
+///
+/// Mirrors the constraints of [`SqliteStore`](super::SqliteStore): contact
+/// addresses and message ids are unique and deleting a contact deletes its
+/// messages.
+#[derive(Default)]
+pub struct MemoryStore {
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    config: HashMap<String, String>,
+    contacts: BTreeMap<i64, Contact>,
+    messages: BTreeMap<i64, Message>,
+    chat_sessions: HashMap<i64, Vec<u8>>,
+    last_contact_id: i64,
+    last_message_id: i64,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ConfigStore for MemoryStore {
+    async fn get_config(&self, key: &str) -> Result<Option<String>, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.config.get(key).cloned())
+    }
+
+    async fn set_config(&self, key: &str, value: String) -> Result<(), anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.config.insert(key.to_owned(), value);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageStore for MemoryStore {
+    async fn get_contacts(&self) -> Result<Vec<Contact>, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.contacts.values().cloned().collect())
+    }
+
+    async fn create_contact(&self, mut contact: Contact) -> Result<Contact, anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        if state
+            .contacts
+            .values()
+            .any(|v| v.address == contact.address)
+        {
+            return Err(anyhow!("Contact already exists"));
+        }
+        state.last_contact_id += 1;
+        contact.id = state.last_contact_id;
+        state.contacts.insert(contact.id, contact.clone());
+        Ok(contact)
+    }
+
+    async fn update_contact(&self, contact: Contact) -> Result<Contact, anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        if state
+            .contacts
+            .values()
+            .any(|v| v.address == contact.address && v.id != contact.id)
+        {
+            return Err(anyhow!("Contact already exists"));
+        }
+        let stored = state
+            .contacts
+            .get_mut(&contact.id)
+            .ok_or(anyhow!("Cannot update contact"))?;
+        stored.address = contact.address;
+        stored.public_key = contact.public_key.clone();
+        stored.name = contact.name.clone();
+        stored.local_name = contact.local_name.clone();
+        stored.avatar = contact.avatar.clone();
+        stored.archived = contact.archived;
+        stored.pinned = contact.pinned;
+        stored.pin_order = contact.pin_order;
+        stored.verified = contact.verified;
+        stored.muted = contact.muted;
+        stored.auto_answer = contact.auto_answer;
+        Ok(contact)
+    }
+
+    async fn delete_contact(&self, id: i64) -> Result<(), anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.contacts.remove(&id).is_none() {
+            return Err(anyhow!("Cannot delete contact"));
+        }
+        state.messages.retain(|_, v| v.contact_id != id);
+        state.chat_sessions.remove(&id);
+        Ok(())
+    }
+
+    async fn get_message(&self, message_id: Uuid) -> Result<Option<Message>, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .messages
+            .values()
+            .find(|v| v.message_id == message_id)
+            .cloned())
+    }
+
+    async fn get_message_by_id(&self, id: i64) -> Result<Option<Message>, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.messages.get(&id).cloned())
+    }
+
+    async fn create_message(&self, mut message: Message) -> Result<Message, anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        if !state.contacts.contains_key(&message.contact_id) {
+            return Err(anyhow!("Contact not found"));
+        }
+        if state
+            .messages
+            .values()
+            .any(|v| v.message_id == message.message_id)
+        {
+            return Err(anyhow!("Message already exists"));
+        }
+        state.last_message_id += 1;
+        message.id = state.last_message_id;
+        state.messages.insert(message.id, message.clone());
+        Ok(message)
+    }
+
+    async fn update_message(&self, message: Message) -> Result<Message, anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        let stored = state
+            .messages
+            .get_mut(&message.id)
+            .ok_or(anyhow!("Cannot update message"))?;
+        stored.log_id = message.log_id;
+        stored.receive_time = message.receive_time;
+        Ok(message)
+    }
+
+    async fn load_history_before(
+        &self,
+        contact_id: i64,
+        before_id: Option<i64>,
+        limit: usize,
+    ) -> Result<HistoryPage, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        // Same order as SQLite: confirmed messages by log_id, then pending ones.
+        let history_key = |v: &Message| (v.log_id.is_none(), v.log_id, v.id);
+        let before_key = match before_id {
+            Some(before_id) => {
+                let message = state
+                    .messages
+                    .get(&before_id)
+                    .filter(|v| v.contact_id == contact_id)
+                    .ok_or(anyhow!("Message not found"))?;
+                Some(history_key(message))
+            }
+            None => None,
+        };
+        let mut messages: Vec<_> = state
+            .messages
+            .values()
+            .filter(|v| v.contact_id == contact_id)
+            .filter(|v| before_key.is_none_or(|key| history_key(v) < key))
+            .cloned()
+            .collect();
+        messages.sort_by_key(history_key);
+        let skip = messages.len().saturating_sub(limit);
+        messages.drain(..skip);
+        Ok(HistoryPage {
+            messages,
+            has_more: skip > 0,
+        })
+    }
+
+    async fn get_head_log_id(&self, contact_id: i64) -> Result<Option<u64>, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .messages
+            .values()
+            .filter(|v| v.contact_id == contact_id)
+            .filter_map(|v| v.log_id)
+            .max())
+    }
+
+    async fn get_pending_message_ids(&self, contact_id: i64) -> Result<Vec<Uuid>, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .messages
+            .values()
+            .filter(|v| v.contact_id == contact_id && !v.incoming && v.log_id.is_none())
+            .map(|v| v.message_id)
+            .collect())
+    }
+
+    async fn get_chat_session(&self, contact_id: i64) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.chat_sessions.get(&contact_id).cloned())
+    }
+
+    async fn set_chat_session(
+        &self,
+        contact_id: i64,
+        session: Option<Vec<u8>>,
+    ) -> Result<(), anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        if !state.contacts.contains_key(&contact_id) {
+            return Err(anyhow!("Contact not found"));
+        }
+        match session {
+            Some(v) => state.chat_sessions.insert(contact_id, v),
+            None => state.chat_sessions.remove(&contact_id),
+        };
+        Ok(())
+    }
+
+    async fn delete_messages_before(&self, time: DateTime) -> Result<u64, anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        let mut heads = HashMap::new();
+        for message in state.messages.values() {
+            if let Some(log_id) = message.log_id {
+                let head = heads.entry(message.contact_id).or_insert(log_id);
+                *head = (*head).max(log_id);
+            }
+        }
+        let count = state.messages.len();
+        state.messages.retain(|_, v| {
+            v.create_time.0 >= time.0
+                || v.log_id.is_none_or(|log_id| log_id >= heads[&v.contact_id])
+        });
+        Ok((count - state.messages.len()) as u64)
+    }
+}
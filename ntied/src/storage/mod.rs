@@ -1,3 +1,9 @@
 mod base;
+mod memory;
+mod sqlite;
+mod store;
 
 pub use base::*;
+pub use memory::*;
+pub use sqlite::*;
+pub use store::*;
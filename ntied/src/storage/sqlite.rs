@@ -0,0 +1,643 @@
+use std::sync::Arc;
+
+use anyhow::{Context as _, anyhow};
+use async_trait::async_trait;
+use tokio::sync::Mutex as TokioMutex;
+use tokio_sqlite::{Connection, Value};
+use uuid::Uuid;
+
+use crate::models::{ColumnIndex, Contact, DateTime, HistoryPage, Message};
+
+use super::{ConfigStore, MessageStore, Storage};
+
+/// Store backed by the encrypted SQLite database of [`Storage`].
+pub struct SqliteStore {
+    storage: Arc<TokioMutex<Storage>>,
+}
+
+impl SqliteStore {
+    /// Create a new SqliteStore. Does not perform I/O.
+    pub fn new(storage: Arc<TokioMutex<Storage>>) -> Self {
+        Self { storage }
+    }
+
+    pub fn storage(&self) -> &Arc<TokioMutex<Storage>> {
+        &self.storage
+    }
+
+    /// Create all tables and indexes used by the store.
+    pub async fn create_tables(&self) -> Result<(), anyhow::Error> {
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+
+        conn.execute("PRAGMA foreign_keys = ON", Vec::<Value>::new())
+            .await
+            .context("Failed to enable foreign keys")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS \"config\" (
+                \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,
+                \"key\" TEXT NOT NULL UNIQUE,
+                \"value\" TEXT NOT NULL
+            )",
+            Vec::new(),
+        )
+        .await
+        .context("Failed to create config table")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS \"contact\" (
+                    \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,
+                    \"address\" TEXT NOT NULL UNIQUE,
+                    \"public_key\" BLOB NOT NULL,
+                    \"name\" TEXT NOT NULL,
+                    \"local_name\" TEXT,
+                    \"avatar\" BLOB,
+                    \"archived\" INTEGER NOT NULL DEFAULT 0,
+                    \"pinned\" INTEGER NOT NULL DEFAULT 0,
+                    \"pin_order\" INTEGER NOT NULL DEFAULT 0,
+                    \"verified\" INTEGER NOT NULL DEFAULT 0,
+                    \"muted\" INTEGER NOT NULL DEFAULT 0,
+                    \"auto_answer\" INTEGER NOT NULL DEFAULT 0,
+                    \"create_time\" BIGINT NOT NULL
+                )",
+            Vec::<Value>::new(),
+        )
+        .await
+        .context("Failed to create contact table")?;
+        // Databases created before avatars were introduced lack the column
+        if !Self::has_column(conn, "contact", "avatar").await? {
+            conn.execute(
+                "ALTER TABLE \"contact\" ADD COLUMN \"avatar\" BLOB",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add contact avatar column")?;
+        }
+        if !Self::has_column(conn, "contact", "archived").await? {
+            conn.execute(
+                "ALTER TABLE \"contact\" ADD COLUMN \"archived\" INTEGER NOT NULL DEFAULT 0",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add contact archived column")?;
+        }
+        if !Self::has_column(conn, "contact", "pinned").await? {
+            conn.execute(
+                "ALTER TABLE \"contact\" ADD COLUMN \"pinned\" INTEGER NOT NULL DEFAULT 0",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add contact pinned column")?;
+        }
+        if !Self::has_column(conn, "contact", "pin_order").await? {
+            conn.execute(
+                "ALTER TABLE \"contact\" ADD COLUMN \"pin_order\" INTEGER NOT NULL DEFAULT 0",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add contact pin order column")?;
+        }
+        if !Self::has_column(conn, "contact", "verified").await? {
+            conn.execute(
+                "ALTER TABLE \"contact\" ADD COLUMN \"verified\" INTEGER NOT NULL DEFAULT 0",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add contact verified column")?;
+        }
+        if !Self::has_column(conn, "contact", "muted").await? {
+            conn.execute(
+                "ALTER TABLE \"contact\" ADD COLUMN \"muted\" INTEGER NOT NULL DEFAULT 0",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add contact muted column")?;
+        }
+        if !Self::has_column(conn, "contact", "auto_answer").await? {
+            conn.execute(
+                "ALTER TABLE \"contact\" ADD COLUMN \"auto_answer\" INTEGER NOT NULL DEFAULT 0",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add contact auto answer column")?;
+        }
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS \"message\" (
+                    \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,
+                    \"contact_id\" INTEGER NOT NULL,
+                    \"message_id\" TEXT NOT NULL UNIQUE,
+                    \"log_id\" INTEGER,
+                    \"incoming\" INTEGER NOT NULL,
+                    \"kind\" TEXT NOT NULL,
+                    \"content\" TEXT NOT NULL,
+                    \"create_time\" BIGINT NOT NULL,
+                    \"receive_time\" BIGINT,
+                    \"read_time\" BIGINT,
+                    \"verified\" INTEGER NOT NULL DEFAULT 1,
+                    \"signature\" BLOB,
+                    \"reply_to\" INTEGER,
+                    FOREIGN KEY (\"contact_id\") REFERENCES \"contact\" (\"id\") ON DELETE CASCADE
+                )",
+            Vec::<Value>::new(),
+        )
+        .await
+        .context("Failed to create message table")?;
+        // Messages received before signatures were checked are unverified
+        if !Self::has_column(conn, "message", "verified").await? {
+            conn.execute(
+                "ALTER TABLE \"message\" ADD COLUMN \"verified\" INTEGER NOT NULL DEFAULT 1",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add message verified column")?;
+            conn.execute(
+                "UPDATE \"message\" SET \"verified\" = 0 WHERE \"incoming\" = 1",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to mark received messages unverified")?;
+        }
+        if !Self::has_column(conn, "message", "reply_to").await? {
+            conn.execute(
+                "ALTER TABLE \"message\" ADD COLUMN \"reply_to\" INTEGER",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add message reply column")?;
+        }
+        if !Self::has_column(conn, "message", "signature").await? {
+            conn.execute(
+                "ALTER TABLE \"message\" ADD COLUMN \"signature\" BLOB",
+                Vec::<Value>::new(),
+            )
+            .await
+            .context("Failed to add message signature column")?;
+        }
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS \"chat_session\" (
+                    \"contact_id\" INTEGER PRIMARY KEY,
+                    \"state\" BLOB NOT NULL,
+                    FOREIGN KEY (\"contact_id\") REFERENCES \"contact\" (\"id\") ON DELETE CASCADE
+                )",
+            Vec::<Value>::new(),
+        )
+        .await
+        .context("Failed to create chat session table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS message__contact_id_log_id_idx
+                 ON \"message\" (\"contact_id\", \"log_id\")",
+            Vec::<Value>::new(),
+        )
+        .await
+        .context("Failed to create message__contact_id_log_id_idx index")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS message__contact_id_create_time_idx
+                 ON \"message\" (\"contact_id\", \"create_time\")",
+            Vec::<Value>::new(),
+        )
+        .await
+        .context("Failed to create message__contact_id_create_time_idx index")?;
+
+        Ok(())
+    }
+
+    async fn ensure_config_table(&self) -> Result<(), anyhow::Error> {
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS \"config\" (
+                \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,
+                \"key\" TEXT NOT NULL UNIQUE,
+                \"value\" TEXT NOT NULL
+            )",
+            Vec::<Value>::new(),
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to create config table: {}", e))?;
+        Ok(())
+    }
+
+    async fn has_column(
+        conn: &mut Connection,
+        table: &str,
+        column: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let mut rows = conn
+            .query(
+                format!("PRAGMA table_info(\"{table}\")"),
+                Vec::<Value>::new(),
+            )
+            .await
+            .with_context(|| format!("Failed to read {table} table info"))?;
+        let mut found = false;
+        while let Some(row) = rows.next().await {
+            let values = row?.into_values();
+            if matches!(values.get(1), Some(Value::Text(name)) if name == column) {
+                found = true;
+            }
+        }
+        Ok(found)
+    }
+
+    fn columns_without_id(columns: &ColumnIndex, id_name: &str) -> ColumnIndex {
+        let mut result = ColumnIndex::builder();
+        for name in columns.columns() {
+            if name != id_name {
+                result.add(name);
+            }
+        }
+        result.build()
+    }
+
+    fn format_columns(columns: &ColumnIndex) -> String {
+        let mut result = String::new();
+        for column in columns.columns() {
+            if !result.is_empty() {
+                result.push_str(", ");
+            }
+            result.push('"');
+            result.push_str(&column);
+            result.push('"');
+        }
+        result
+    }
+
+    fn format_values(values: &[Value]) -> String {
+        let mut result = String::new();
+        for i in 0..values.len() {
+            if !result.is_empty() {
+                result.push_str(", ");
+            }
+            result.push('?');
+            result.push_str(&(i + 1).to_string());
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl ConfigStore for SqliteStore {
+    async fn get_config(&self, key: &str) -> Result<Option<String>, anyhow::Error> {
+        self.ensure_config_table().await?;
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+        let row = conn
+            .query_row(
+                "SELECT \"value\" FROM \"config\" WHERE \"key\" = ?1 LIMIT 1",
+                vec![Value::Text(key.to_string())],
+            )
+            .await
+            .map_err(|e| anyhow!("Failed to query config '{}': {}", key, e))?;
+        match row {
+            Some(row) => {
+                let mut values = row.into_values();
+                match values.pop() {
+                    Some(Value::Text(s)) => Ok(Some(s)),
+                    Some(other) => Err(anyhow!("Unexpected value type: {:?}", other)),
+                    None => Ok(None),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set_config(&self, key: &str, value: String) -> Result<(), anyhow::Error> {
+        self.ensure_config_table().await?;
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+        // Try UPDATE first
+        let update_status = conn
+            .execute(
+                "UPDATE \"config\" SET \"value\" = ?1 WHERE \"key\" = ?2",
+                vec![Value::Text(value.clone()), Value::Text(key.to_string())],
+            )
+            .await
+            .map_err(|e| anyhow!("Failed to update config '{}': {}", key, e))?;
+        if update_status.rows_affected() == 0 {
+            // No row updated; perform INSERT
+            conn.execute(
+                "INSERT INTO \"config\" (\"key\", \"value\") VALUES (?1, ?2)",
+                vec![Value::Text(key.to_string()), Value::Text(value)],
+            )
+            .await
+            .map_err(|e| anyhow!("Failed to insert config '{}': {}", key, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageStore for SqliteStore {
+    async fn get_contacts(&self) -> Result<Vec<Contact>, anyhow::Error> {
+        let columns = Contact::columns();
+        let query = format!(
+            "SELECT {} FROM \"contact\" ORDER BY \"id\"",
+            Self::format_columns(columns)
+        );
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        let mut rows = connection.query(query, vec![]).await?;
+        assert_eq!(columns.columns(), rows.columns());
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            let values = row.into_values();
+            let contact = Contact::from_values(values, columns)?;
+            result.push(contact);
+        }
+        Ok(result)
+    }
+
+    async fn create_contact(&self, mut contact: Contact) -> Result<Contact, anyhow::Error> {
+        let columns = Self::columns_without_id(Contact::columns(), "id");
+        let values = contact.values(&columns);
+        let query = format!(
+            "INSERT INTO \"contact\" ({}) VALUES ({})",
+            Self::format_columns(&columns),
+            Self::format_values(&values),
+        );
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        let status = connection.execute(query, values).await?;
+        contact.id = status
+            .last_insert_id()
+            .ok_or(anyhow!("Cannot retrieve contact id"))?;
+        Ok(contact)
+    }
+
+    async fn update_contact(&self, contact: Contact) -> Result<Contact, anyhow::Error> {
+        let query = "UPDATE \"contact\" SET \"address\" = ?1, \"public_key\" = ?2, \"name\" = ?3, \"local_name\" = ?4, \"avatar\" = ?5, \"archived\" = ?6, \"pinned\" = ?7, \"pin_order\" = ?8, \"verified\" = ?9, \"muted\" = ?10, \"auto_answer\" = ?11 WHERE \"id\" = ?12";
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        let mut values = Vec::<Value>::new();
+        values.push(contact.address.to_string().into());
+        values.push(
+            contact
+                .public_key
+                .to_bytes()
+                .map_err(|e| anyhow!(e))?
+                .into(),
+        );
+        values.push(contact.name.clone().into());
+        values.push(contact.local_name.clone().into());
+        values.push(contact.avatar.clone().into());
+        values.push(contact.archived.into());
+        values.push(contact.pinned.into());
+        values.push(contact.pin_order.into());
+        values.push(contact.verified.into());
+        values.push(contact.muted.into());
+        values.push(contact.auto_answer.into());
+        values.push(contact.id.into());
+        let status = connection.execute(query, values).await?;
+        if status.rows_affected() != 1 {
+            return Err(anyhow!("Cannot update contact"));
+        }
+        Ok(contact)
+    }
+
+    async fn delete_contact(&self, id: i64) -> Result<(), anyhow::Error> {
+        let query = "DELETE FROM \"contact\" WHERE \"id\" = ?1";
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        let status = connection.execute(query, vec![Value::Integer(id)]).await?;
+        if status.rows_affected() != 1 {
+            return Err(anyhow!("Cannot delete contact"));
+        }
+        Ok(())
+    }
+
+    async fn get_message(&self, message_id: Uuid) -> Result<Option<Message>, anyhow::Error> {
+        let columns = Message::columns();
+        let query = format!(
+            "SELECT {} FROM \"message\" WHERE \"message_id\" = ?1 LIMIT 1",
+            Self::format_columns(columns)
+        );
+        let values: Vec<Value> = vec![message_id.to_string().into()];
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        match connection.query_row(query, values).await? {
+            Some(row) => {
+                let values = row.into_values();
+                let message = Message::from_values(values, columns)?;
+                Ok(Some(message))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_message_by_id(&self, id: i64) -> Result<Option<Message>, anyhow::Error> {
+        let columns = Message::columns();
+        let query = format!(
+            "SELECT {} FROM \"message\" WHERE \"id\" = ?1 LIMIT 1",
+            Self::format_columns(columns)
+        );
+        let values: Vec<Value> = vec![id.into()];
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        match connection.query_row(query, values).await? {
+            Some(row) => {
+                let values = row.into_values();
+                let message = Message::from_values(values, columns)?;
+                Ok(Some(message))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn create_message(&self, mut message: Message) -> Result<Message, anyhow::Error> {
+        let columns = Self::columns_without_id(Message::columns(), "id");
+        let values = message.values(&columns);
+        let query = format!(
+            "INSERT INTO \"message\" ({}) VALUES ({})",
+            Self::format_columns(&columns),
+            Self::format_values(&values),
+        );
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        let status = connection.execute(query, values).await?;
+        message.id = status
+            .last_insert_id()
+            .ok_or(anyhow!("Cannot retrieve message id"))?;
+        Ok(message)
+    }
+
+    async fn update_message(&self, message: Message) -> Result<Message, anyhow::Error> {
+        let query =
+            "UPDATE \"message\" SET \"log_id\" = ?1, \"receive_time\" = ?2 WHERE \"id\" = ?3";
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        let values: Vec<Value> = vec![
+            message.log_id.map(|v| v as i64).into(),
+            message.receive_time.map(|v| v.0.timestamp_micros()).into(),
+            message.id.into(),
+        ];
+        let status = connection.execute(query, values).await?;
+        if status.rows_affected() != 1 {
+            return Err(anyhow!("Cannot update message"));
+        }
+        Ok(message)
+    }
+
+    async fn load_history_before(
+        &self,
+        contact_id: i64,
+        before_id: Option<i64>,
+        limit: usize,
+    ) -> Result<HistoryPage, anyhow::Error> {
+        let columns = Message::columns();
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+        let mut values = Vec::<Value>::new();
+        values.push(Value::Integer(contact_id));
+        values.push(Value::Integer(limit as i64 + 1));
+        let condition = match before_id {
+            Some(before_id) => {
+                let row = conn
+                    .query_row(
+                        "SELECT \"log_id\" FROM \"message\" WHERE \"id\" = ?1 AND \"contact_id\" = ?2",
+                        vec![Value::Integer(before_id), Value::Integer(contact_id)],
+                    )
+                    .await?
+                    .ok_or(anyhow!("Message not found"))?;
+                let log_id = row.into_values().into_iter().next();
+                values.push(Value::Integer(before_id));
+                match log_id {
+                    Some(Value::Integer(log_id)) => {
+                        values.push(Value::Integer(log_id));
+                        "AND (\"log_id\" < ?4 OR (\"log_id\" = ?4 AND \"id\" < ?3))"
+                    }
+                    // Pending messages go after all confirmed ones.
+                    _ => "AND (\"log_id\" IS NOT NULL OR \"id\" < ?3)",
+                }
+            }
+            None => "",
+        };
+        let query = format!(
+            "SELECT {} FROM \"message\" \
+             WHERE \"contact_id\" = ?1 {} \
+             ORDER BY CASE WHEN \"log_id\" IS NULL THEN 0 ELSE 1 END, \"log_id\" DESC, \"id\" DESC \
+             LIMIT ?2",
+            Self::format_columns(columns),
+            condition,
+        );
+        let mut rows = conn.query(query, values).await?;
+        let mut messages = Vec::new();
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            let values = row.into_values();
+            let message = Message::from_values(values, columns)?;
+            messages.push(message);
+        }
+        let has_more = messages.len() > limit;
+        messages.truncate(limit);
+        messages.reverse();
+        Ok(HistoryPage { messages, has_more })
+    }
+
+    async fn get_head_log_id(&self, contact_id: i64) -> Result<Option<u64>, anyhow::Error> {
+        let query = "SELECT MAX(\"log_id\") FROM \"message\" WHERE \"log_id\" IS NOT NULL AND \"contact_id\" = ?1";
+        let values: Vec<Value> = vec![contact_id.into()];
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        match connection.query_row(query, values).await? {
+            Some(row) => {
+                let values = row.into_values();
+                assert_eq!(values.len(), 1);
+                match values.first().unwrap() {
+                    Value::Integer(i) if *i >= 0 => Ok(Some(*i as u64)),
+                    Value::Null => Ok(None),
+                    v => Err(anyhow!("Failed to parse log_id from value: {v:?}")),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_pending_message_ids(&self, contact_id: i64) -> Result<Vec<Uuid>, anyhow::Error> {
+        let query = "SELECT \"message_id\" FROM \"message\" \
+                     WHERE \"contact_id\" = ?1 AND \"incoming\" = 0 AND \"log_id\" IS NULL \
+                     ORDER BY \"id\" ASC";
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+        let mut rows = conn.query(query, vec![Value::Integer(contact_id)]).await?;
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            if let Some(Value::Text(s)) = row.into_values().into_iter().next()
+                && let Ok(uuid) = Uuid::parse_str(&s)
+            {
+                result.push(uuid);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn get_chat_session(&self, contact_id: i64) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        let query = "SELECT \"state\" FROM \"chat_session\" WHERE \"contact_id\" = ?1 LIMIT 1";
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+        match conn
+            .query_row(query, vec![Value::Integer(contact_id)])
+            .await?
+        {
+            Some(row) => match row.into_values().pop() {
+                Some(Value::Blob(state)) => Ok(Some(state)),
+                Some(other) => Err(anyhow!("Unexpected value type: {:?}", other)),
+                None => Ok(None),
+            },
+            None => Ok(None),
+        }
+    }
+
+    async fn set_chat_session(
+        &self,
+        contact_id: i64,
+        session: Option<Vec<u8>>,
+    ) -> Result<(), anyhow::Error> {
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+        match session {
+            Some(state) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO \"chat_session\" (\"contact_id\", \"state\") \
+                     VALUES (?1, ?2)",
+                    vec![Value::Integer(contact_id), Value::Blob(state)],
+                )
+                .await?;
+            }
+            None => {
+                conn.execute(
+                    "DELETE FROM \"chat_session\" WHERE \"contact_id\" = ?1",
+                    vec![Value::Integer(contact_id)],
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_messages_before(&self, time: DateTime) -> Result<u64, anyhow::Error> {
+        let query = "DELETE FROM \"message\" WHERE \"create_time\" < ?1 AND \"log_id\" < \
+                     (SELECT MAX(\"log_id\") FROM \"message\" AS \"head\" \
+                     WHERE \"head\".\"contact_id\" = \"message\".\"contact_id\")";
+        let mut storage = self.storage.lock().await;
+        let connection = storage.connection().await;
+        let status = connection
+            .execute(query, vec![Value::Integer(time.0.timestamp_micros())])
+            .await?;
+        Ok(status.rows_affected() as u64)
+    }
+
+    async fn flush(&self) -> Result<(), anyhow::Error> {
+        let mut storage = self.storage.lock().await;
+        let conn = storage.connection().await;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", Vec::<Value>::new())
+            .await
+            .context("Failed to checkpoint database")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::models::{Contact, DateTime, HistoryPage, Message};
+
+/// Key-value settings used by [`ConfigManager`](crate::config::ConfigManager).
+#[async_trait]
+pub trait ConfigStore: Send + Sync {
+    async fn get_config(&self, key: &str) -> Result<Option<String>, anyhow::Error>;
+
+    async fn set_config(&self, key: &str, value: String) -> Result<(), anyhow::Error>;
+}
+
+/// Contacts and chat history used by [`ChatManager`](crate::chat::ChatManager).
+#[async_trait]
+pub trait MessageStore: Send + Sync {
+    /// List all contacts ordered by id.
+    async fn get_contacts(&self) -> Result<Vec<Contact>, anyhow::Error>;
+
+    /// Insert a contact and return it with the assigned id.
+    async fn create_contact(&self, contact: Contact) -> Result<Contact, anyhow::Error>;
+
+    /// Persist all fields of an existing contact except `create_time`.
+    async fn update_contact(&self, contact: Contact) -> Result<Contact, anyhow::Error>;
+
+    /// Delete a contact together with its messages.
+    async fn delete_contact(&self, id: i64) -> Result<(), anyhow::Error>;
+
+    async fn get_message(&self, message_id: Uuid) -> Result<Option<Message>, anyhow::Error>;
+
+    /// Find a message by its local id.
+    async fn get_message_by_id(&self, id: i64) -> Result<Option<Message>, anyhow::Error>;
+
+    /// Insert a message and return it with the assigned id.
+    async fn create_message(&self, message: Message) -> Result<Message, anyhow::Error>;
+
+    /// Persist `log_id` and `receive_time` of an existing message.
+    async fn update_message(&self, message: Message) -> Result<Message, anyhow::Error>;
+
+    /// Load up to `limit` latest messages of the contact in chronological order.
+    /// Pending outgoing messages (without `log_id`) are considered the latest.
+    async fn load_history(
+        &self,
+        contact_id: i64,
+        limit: usize,
+    ) -> Result<Vec<Message>, anyhow::Error> {
+        let page = self.load_history_before(contact_id, None, limit).await?;
+        Ok(page.messages)
+    }
+
+    /// Load up to `limit` messages that precede the message with id `before_id`
+    /// in history order, or the latest messages when `before_id` is `None`.
+    async fn load_history_before(
+        &self,
+        contact_id: i64,
+        before_id: Option<i64>,
+        limit: usize,
+    ) -> Result<HistoryPage, anyhow::Error>;
+
+    /// Largest confirmed `log_id` of the contact chat.
+    async fn get_head_log_id(&self, contact_id: i64) -> Result<Option<u64>, anyhow::Error>;
+
+    /// Outgoing messages of the contact that are not confirmed yet, oldest first.
+    async fn get_pending_message_ids(&self, contact_id: i64) -> Result<Vec<Uuid>, anyhow::Error>;
+
+    /// Delete confirmed messages created before `time`, returns the number of
+    /// deleted messages. The latest confirmed message of each chat is kept as
+    /// it holds the head of the message log.
+    async fn delete_messages_before(&self, time: DateTime) -> Result<u64, anyhow::Error>;
+
+    /// Serialized end-to-end session of the contact chat.
+    async fn get_chat_session(&self, contact_id: i64) -> Result<Option<Vec<u8>>, anyhow::Error>;
+
+    /// Replace the end-to-end session of the contact chat, `None` deletes it.
+    /// The session is deleted together with the contact.
+    async fn set_chat_session(
+        &self,
+        contact_id: i64,
+        session: Option<Vec<u8>>,
+    ) -> Result<(), anyhow::Error>;
+
+    /// Wait for writes in progress and move them to persistent storage.
+    async fn flush(&self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
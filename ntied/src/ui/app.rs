@@ -6,20 +6,24 @@ use std::sync::Arc;
 use anyhow::Context as _;
 use iced::futures::sink::SinkExt as _;
 use iced::keyboard::{self, key::Named};
-use iced::{Element, Subscription, Task, Theme, stream};
+use iced::{Element, Subscription, Task, Theme, event, stream, window};
+use ntied_crypto::PublicKey;
 use tokio::sync::{Mutex as TokioMutex, mpsc};
 
 use crate::DEFAULT_SERVER;
-use crate::audio::RingtonePlayer;
+use crate::audio::{AudioManager, CodecType, DeviceChange, RingtonePlayer, ShareMode};
 use crate::call::CallManager;
 use crate::chat::ChatManager;
-use crate::contact::ContactManager;
+use crate::config::{ConfigManager, WindowGeometry};
+use crate::contact::{ConnectionStatus, ContactManager, FocusTracker};
+use crate::logs::LogBuffer;
 use crate::packet::ContactProfile;
 use crate::storage::Storage;
 use crate::ui::UiEvent;
 use crate::ui::core::{Screen, ScreenCommand};
 use crate::ui::screens::{
-    ChatListMessage, ChatListScreen, InitScreen, SettingsScreen, UnlockScreen,
+    ChatListMessage, ChatListScreen, InitScreen, LogsScreen, SettingsMessage, SettingsScreen,
+    UnlockScreen,
 };
 use crate::ui::theme::ThemePreference;
 
@@ -28,8 +32,9 @@ use super::core::ScreenType;
 enum CurrentScreen {
     Unlock(UnlockScreen),
     Init(InitScreen),
-    Chats(ChatListScreen),
+    Chats(Box<ChatListScreen>),
     Settings(SettingsScreen),
+    Logs(LogsScreen),
 }
 
 pub struct AppContext {
@@ -40,6 +45,9 @@ pub struct AppContext {
     pub call_manager: Option<Arc<CallManager>>,
     pub profile: Option<ContactProfile>,
     pub server_addr: Option<SocketAddr>,
+    pub server_token: Option<String>,
+    // Connection status is sent again by screens, only new connections are recorded
+    pub server_connected: bool,
     pub ui_event_tx: mpsc::Sender<UiEvent>,
     pub ui_event_rx: Arc<TokioMutex<mpsc::Receiver<UiEvent>>>,
     pub pending_add_addr: Option<String>,
@@ -47,10 +55,17 @@ pub struct AppContext {
     pub pending_compose_text: Option<String>,
     pub ringtone_player: Arc<TokioMutex<RingtonePlayer>>,
     pub theme: ThemePreference,
+    // Current window geometry, saved on close
+    pub window_geometry: WindowGeometry,
+    // Saved geometry applied when the chats screen is shown
+    pub pending_window_geometry: Option<WindowGeometry>,
+    // Messages of a chat kept in memory by the chats screen
+    pub message_history_limit: usize,
     // Call state preservation
     pub active_call_address: Option<String>,
     pub active_call_name: Option<String>,
-    pub active_call_state: Option<String>, // "calling", "ringing", or "connected"
+    // "calling", "ringing", "connecting_audio" or "connected"
+    pub active_call_state: Option<String>,
     pub incoming_call_address: Option<String>,
     pub incoming_call_name: Option<String>,
 }
@@ -67,6 +82,8 @@ impl AppContext {
             call_manager: None,
             profile: None,
             server_addr: None,
+            server_token: None,
+            server_connected: false,
             ui_event_tx,
             ui_event_rx: Arc::new(TokioMutex::new(ui_event_rx)),
             pending_add_addr: None,
@@ -74,6 +91,9 @@ impl AppContext {
             pending_compose_text: None,
             ringtone_player: Arc::new(TokioMutex::new(RingtonePlayer::new())),
             theme: ThemePreference::default(),
+            window_geometry: WindowGeometry::default(),
+            pending_window_geometry: None,
+            message_history_limit: ConfigManager::DEFAULT_HISTORY_LIMIT,
             active_call_address: None,
             active_call_name: None,
             active_call_state: None,
@@ -82,7 +102,7 @@ impl AppContext {
         }
     }
 
-    fn get_data_dir() -> Result<PathBuf, anyhow::Error> {
+    pub fn get_data_dir() -> Result<PathBuf, anyhow::Error> {
         // Check for custom profile directory from environment variable
         if let Ok(custom_dir) = std::env::var("NTIED_PROFILE_DIR") {
             let path = PathBuf::from(custom_dir);
@@ -110,6 +130,9 @@ pub struct ChatApp {
     screen: CurrentScreen,
     ctx: AppContext,
     theme: Theme,
+    shutting_down: bool,
+    // Contacts are refreshed when the window is focused after a while
+    focus: FocusTracker,
 }
 
 impl ChatApp {
@@ -162,6 +185,20 @@ impl ChatApp {
                     None
                 }
             }
+            ScreenType::Settings { .. } => self.ctx.storage.clone().map(|storage| {
+                Task::perform(
+                    async move { ConfigManager::new(storage).recent_sessions().await },
+                    |result| match result {
+                        Ok(sessions) => {
+                            AppMessage::Settings(SettingsMessage::RecentSessionsLoaded(sessions))
+                        }
+                        Err(err) => {
+                            tracing::warn!(?err, "Failed to load recent sessions");
+                            AppMessage::Tick
+                        }
+                    },
+                )
+            }),
             _ => None,
         };
 
@@ -174,6 +211,11 @@ impl ChatApp {
             } => {
                 let mut screen = ChatListScreen::new(Some(own_name.clone()));
                 screen.set_identity(own_name, own_address);
+                if let Some(geometry) = self.ctx.pending_window_geometry {
+                    self.ctx.window_geometry = geometry;
+                }
+                screen.set_sidebar_width(self.ctx.window_geometry.sidebar_width);
+                screen.set_history_limit(self.ctx.message_history_limit);
 
                 // Restore call state if exists
                 screen.restore_call_state(
@@ -188,11 +230,21 @@ impl ChatApp {
                 let ui_tx = self.ctx.ui_event_tx.clone();
                 let cm_for_list = self.ctx.chat_manager.clone();
                 let contact_mgr = self.ctx.contact_manager.clone();
+                let storage = self.ctx.storage.clone();
                 tokio::spawn(async move {
                     // Send transport connection status
                     if let Some(ref cm) = contact_mgr {
-                        let is_connected = cm.is_connected();
-                        let _ = ui_tx.send(UiEvent::TransportConnected(is_connected)).await;
+                        let status = cm.connection_status();
+                        let _ = ui_tx.send(UiEvent::ConnectionStatus(status)).await;
+                    }
+                    // Send contact groups
+                    if let Some(storage) = storage {
+                        match ConfigManager::new(storage).get_contact_groups().await {
+                            Ok(groups) => {
+                                let _ = ui_tx.send(UiEvent::ContactGroupsLoaded(groups)).await;
+                            }
+                            Err(err) => tracing::warn!(?err, "Failed to load contact groups"),
+                        }
                     }
                     // Send contacts list
                     if let Some(cm) = cm_for_list {
@@ -202,6 +254,12 @@ impl ChatApp {
                                 .send(UiEvent::ContactAccepted {
                                     address: contact.address.to_string(),
                                     name: contact.local_name.unwrap_or(contact.name),
+                                    avatar: contact.avatar,
+                                    archived: contact.archived,
+                                    pin_order: contact.pinned.then_some(contact.pin_order),
+                                    verified: contact.verified,
+                                    muted: contact.muted,
+                                    auto_answer: contact.auto_answer,
                                 })
                                 .await;
                             let _ = ui_tx
@@ -213,11 +271,104 @@ impl ChatApp {
                         }
                     }
                 });
-                CurrentScreen::Chats(screen)
-            }
-            ScreenType::Settings { server_addr } => {
-                CurrentScreen::Settings(SettingsScreen::new(server_addr).with_theme(self.ctx.theme))
+                CurrentScreen::Chats(Box::new(screen))
             }
+            ScreenType::Settings { server_addr } => CurrentScreen::Settings(
+                SettingsScreen::new(server_addr)
+                    .with_theme(self.ctx.theme)
+                    .with_wake_recovery(
+                        self.ctx
+                            .contact_manager
+                            .as_ref()
+                            .is_none_or(|cm| cm.is_wake_recovery_enabled()),
+                    )
+                    .with_focus_refresh(
+                        self.ctx
+                            .contact_manager
+                            .as_ref()
+                            .is_none_or(|cm| cm.is_focus_refresh_enabled()),
+                    )
+                    .with_call_waiting(
+                        self.ctx
+                            .call_manager
+                            .as_ref()
+                            .is_some_and(|cm| cm.is_call_waiting_enabled()),
+                    )
+                    .with_frame_pacing(
+                        self.ctx
+                            .call_manager
+                            .as_ref()
+                            .is_some_and(|cm| cm.is_frame_pacing_enabled()),
+                    )
+                    .with_follow_default_device(
+                        self.ctx
+                            .call_manager
+                            .as_ref()
+                            .is_some_and(|cm| cm.is_follow_default_device_enabled()),
+                    )
+                    .with_qos_marking(
+                        self.ctx
+                            .call_manager
+                            .as_ref()
+                            .is_some_and(|cm| cm.is_qos_marking_enabled()),
+                    )
+                    .with_archive_options(
+                        self.ctx
+                            .chat_manager
+                            .as_ref()
+                            .map(|cm| cm.archive_options())
+                            .unwrap_or_default(),
+                    )
+                    .with_retention_options(
+                        self.ctx
+                            .chat_manager
+                            .as_ref()
+                            .map(|cm| cm.retention_options())
+                            .unwrap_or_default(),
+                    )
+                    .with_input_gain(
+                        self.ctx
+                            .call_manager
+                            .as_ref()
+                            .map_or(1.0, |cm| cm.input_gain()),
+                    )
+                    .with_message_history_limit(self.ctx.message_history_limit)
+                    .with_ringtone_volume(
+                        self.ctx
+                            .ringtone_player
+                            .try_lock()
+                            .map_or(RingtonePlayer::DEFAULT_VOLUME, |v| v.volume()),
+                    )
+                    .with_codecs(
+                        CodecType::available(),
+                        self.ctx
+                            .call_manager
+                            .as_ref()
+                            .map_or_else(CodecType::default, |cm| cm.preferred_codec()),
+                    )
+                    .with_share_mode(
+                        self.ctx
+                            .call_manager
+                            .as_ref()
+                            .map_or_else(ShareMode::default, |cm| cm.share_mode()),
+                    )
+                    .with_system_audio(
+                        self.ctx
+                            .call_manager
+                            .as_ref()
+                            .and_then(|cm| cm.system_audio()),
+                    )
+                    .with_server_token(self.ctx.server_token.clone())
+                    .with_blocked_addresses(
+                        self.ctx
+                            .contact_manager
+                            .as_ref()
+                            .map(|cm| cm.blocked_addresses())
+                            .unwrap_or_default(),
+                    )
+                    .with_profile(self.ctx.profile.as_ref()),
+            ),
+            ScreenType::Logs => CurrentScreen::Logs(LogsScreen::new(LogBuffer::global().clone())),
         };
 
         let focus_task = if let CurrentScreen::Init(screen) = &mut self.screen {
@@ -229,19 +380,49 @@ impl ChatApp {
             None
         };
 
-        match (sync_task, focus_task) {
-            (Some(a), Some(b)) => Task::batch(vec![a, b]),
-            (Some(a), None) => a,
-            (None, Some(b)) => b,
-            (None, None) => Task::none(),
-        }
+        // Saved geometry is applied once after unlocking
+        let restore_task = self.ctx.pending_window_geometry.take().map(|geometry| {
+            window::get_latest().and_then(move |id| {
+                let resize = window::resize(id, iced::Size::new(geometry.width, geometry.height));
+                match geometry.x.zip(geometry.y) {
+                    Some((x, y)) => {
+                        Task::batch([resize, window::move_to(id, iced::Point::new(x, y))])
+                    }
+                    None => resize,
+                }
+            })
+        });
+
+        Task::batch(sync_task.into_iter().chain(focus_task).chain(restore_task))
+    }
+}
+
+/// Upper bound of the shutdown, the app exits even if the server is unreachable.
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Ends active calls, flushes storage and unregisters from the server,
+/// called when the main window is closed.
+pub async fn shutdown(
+    call_manager: Option<Arc<CallManager>>,
+    chat_manager: Option<Arc<ChatManager>>,
+    contact_manager: Option<Arc<ContactManager>>,
+) {
+    if let Some(calls) = call_manager {
+        calls.shutdown().await;
+    }
+    if let Some(chats) = chat_manager
+        && let Err(err) = chats.flush().await
+    {
+        tracing::error!(?err, "Cannot flush storage");
+    }
+    if let Some(contacts) = contact_manager
+        && let Err(err) = contacts.shutdown().await
+    {
+        tracing::error!(?err, "Cannot unregister from server");
     }
 }
 
-fn handle_tab_press(
-    key: keyboard::Key,
-    modifiers: keyboard::Modifiers,
-) -> Option<AppMessage> {
+fn handle_tab_press(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<AppMessage> {
     match key {
         keyboard::Key::Named(Named::Tab) => Some(AppMessage::FocusInitField {
             reverse: modifiers.shift(),
@@ -256,10 +437,21 @@ pub enum AppMessage {
     Unlock(crate::ui::screens::UnlockMessage),
     Init(crate::ui::screens::InitMessage),
     ChatList(crate::ui::screens::ChatListMessage),
-    Settings(crate::ui::screens::SettingsMessage),
+    Settings(SettingsMessage),
+    Logs(crate::ui::screens::LogsMessage),
     // UI events from subscription
     UiEvent(UiEvent),
     FocusInitField { reverse: bool },
+    // Audio devices were added, removed or the default one changed
+    AudioDeviceChanged(DeviceChange),
+    // Window geometry changes
+    WindowMoved(iced::Point),
+    WindowResized(iced::Size),
+    WindowFocused,
+    WindowUnfocused,
+    // Window close flow
+    CloseRequested,
+    ShutdownFinished,
     Tick,
 }
 
@@ -270,8 +462,16 @@ impl std::fmt::Debug for AppMessage {
             AppMessage::Init(_) => write!(f, "Init(<msg>)"),
             AppMessage::ChatList(_) => write!(f, "ChatList(<msg>)"),
             AppMessage::Settings(_) => write!(f, "Settings(<msg>)"),
+            AppMessage::Logs(_) => write!(f, "Logs(<msg>)"),
             AppMessage::UiEvent(_) => write!(f, "UiEvent(<event>)"),
             AppMessage::FocusInitField { .. } => write!(f, "InitTab"),
+            AppMessage::AudioDeviceChanged(change) => write!(f, "AudioDeviceChanged({change:?})"),
+            AppMessage::WindowMoved(position) => write!(f, "WindowMoved({position:?})"),
+            AppMessage::WindowResized(size) => write!(f, "WindowResized({size:?})"),
+            AppMessage::WindowFocused => write!(f, "WindowFocused"),
+            AppMessage::WindowUnfocused => write!(f, "WindowUnfocused"),
+            AppMessage::CloseRequested => write!(f, "CloseRequested"),
+            AppMessage::ShutdownFinished => write!(f, "ShutdownFinished"),
             AppMessage::Tick => write!(f, "Tick"),
         }
     }
@@ -300,6 +500,8 @@ impl ChatApp {
                 screen,
                 ctx,
                 theme,
+                shutting_down: false,
+                focus: FocusTracker::default(),
             },
             focus_task,
         )
@@ -327,11 +529,52 @@ impl ChatApp {
             TypeId::of::<UiEvent>(),
             ui_event_sub,
         ));
+        // Refresh audio devices while audio settings are open and follow
+        // the default devices during calls
+        if let CurrentScreen::Chats(screen) = &self.screen
+            && (screen.is_audio_settings_open() || screen.get_active_call_address().is_some())
+        {
+            let device_sub = stream::channel(10, |mut output| async move {
+                let mut rx = match AudioManager::subscribe_device_changes().await {
+                    Ok(rx) => rx,
+                    Err(err) => {
+                        tracing::warn!(?err, "Failed to watch audio devices");
+                        return;
+                    }
+                };
+                while let Some(change) = rx.recv().await {
+                    tracing::debug!(?change, "Audio device change");
+                    if output
+                        .send(AppMessage::AudioDeviceChanged(change))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+            subscriptions.push(Subscription::run_with_id(
+                TypeId::of::<DeviceChange>(),
+                device_sub,
+            ));
+        }
         // Keep tick subscription for compatibility
         subscriptions.push(
             iced::time::every(std::time::Duration::from_millis(250)).map(|_| AppMessage::Tick),
         );
         subscriptions.push(keyboard::on_key_press(handle_tab_press));
+        subscriptions.push(window::close_requests().map(|_| AppMessage::CloseRequested));
+        subscriptions.push(event::listen_with(|event, _, _| match event {
+            iced::Event::Window(window::Event::Moved(position)) => {
+                Some(AppMessage::WindowMoved(position))
+            }
+            iced::Event::Window(window::Event::Resized(size)) => {
+                Some(AppMessage::WindowResized(size))
+            }
+            iced::Event::Window(window::Event::Focused) => Some(AppMessage::WindowFocused),
+            iced::Event::Window(window::Event::Unfocused) => Some(AppMessage::WindowUnfocused),
+            _ => None,
+        }));
         Subscription::batch(subscriptions)
     }
 
@@ -341,6 +584,7 @@ impl ChatApp {
             CurrentScreen::Init(_) => "ntied: Init".to_string(),
             CurrentScreen::Chats(_) => "ntied".to_string(),
             CurrentScreen::Settings(_) => "ntied: Settings".to_string(),
+            CurrentScreen::Logs(_) => "ntied: Logs".to_string(),
         }
     }
 
@@ -371,13 +615,28 @@ impl ChatApp {
                     }
                 }
 
+                // Contacts that lost verification are no longer auto-answered
+                if let UiEvent::ContactKeyRotated { address, .. }
+                | UiEvent::ContactRemoved { address } = &event
+                    && let Some(calls) = &self.ctx.call_manager
+                    && let Ok(address) = address.parse()
+                {
+                    calls.set_auto_answer(address, false);
+                }
+
                 // Process specific UI events that need app-level handling
                 match event {
-                    UiEvent::IncomingCall { address: _ } => {
-                        // Start ringtone
+                    UiEvent::IncomingCall { address } => {
+                        // Start ringtone unless the caller is muted
                         let ringtone = self.ctx.ringtone_player.clone();
+                        let chats = self.ctx.chat_manager.clone();
                         return Task::perform(
                             async move {
+                                if let (Some(chats), Ok(address)) = (chats, address.parse())
+                                    && chats.is_muted(address).await
+                                {
+                                    return;
+                                }
                                 let mut player = ringtone.lock().await;
                                 if let Err(e) = player.start() {
                                     tracing::error!("Failed to start ringtone: {}", e);
@@ -386,6 +645,12 @@ impl ChatApp {
                             |_| AppMessage::Tick,
                         );
                     }
+                    UiEvent::MessageNotification { .. } => window::get_latest().and_then(|id| {
+                        window::request_user_attention(
+                            id,
+                            Some(window::UserAttention::Informational),
+                        )
+                    }),
                     UiEvent::CallAccepted { .. }
                     | UiEvent::CallRejected { .. }
                     | UiEvent::CallConnected { .. }
@@ -400,7 +665,31 @@ impl ChatApp {
                             |_| AppMessage::Tick,
                         );
                     }
-                    UiEvent::ContactAccepted { name, address } => {
+                    UiEvent::ConnectionStatus(status) => {
+                        let connected = status == ConnectionStatus::Connected;
+                        let was_connected =
+                            std::mem::replace(&mut self.ctx.server_connected, connected);
+                        if !connected || was_connected {
+                            return Task::none();
+                        }
+                        let (Some(storage), Some(contacts)) =
+                            (self.ctx.storage.clone(), self.ctx.contact_manager.clone())
+                        else {
+                            return Task::none();
+                        };
+                        Task::perform(
+                            async move {
+                                if let Err(err) = ConfigManager::new(storage)
+                                    .record_connection(&contacts)
+                                    .await
+                                {
+                                    tracing::error!(?err, "Cannot record session");
+                                }
+                            },
+                            |_| AppMessage::Tick,
+                        )
+                    }
+                    UiEvent::ContactAccepted { name, address, .. } => {
                         let chats = self.ctx.chat_manager.clone();
                         let contacts = self.ctx.contact_manager.clone();
                         return Task::perform(
@@ -426,6 +715,44 @@ impl ChatApp {
                             |_| AppMessage::Tick,
                         );
                     }
+                    UiEvent::ContactKeyRotated {
+                        address,
+                        public_key,
+                        ..
+                    } => {
+                        let chats = self.ctx.chat_manager.clone();
+                        Task::perform(
+                            async move {
+                                if let Some(chats) = chats
+                                    && let Ok(address) = address.parse()
+                                    && let Ok(public_key) = PublicKey::from_bytes(&public_key)
+                                    && let Err(err) =
+                                        chats.rotate_contact_key(address, public_key).await
+                                {
+                                    tracing::error!(?err, "Cannot rotate contact key");
+                                }
+                            },
+                            |_| AppMessage::Tick,
+                        )
+                    }
+                    UiEvent::KeyRotationDelivered { .. } => {
+                        let (Some(storage), Some(contacts)) =
+                            (self.ctx.storage.clone(), self.ctx.contact_manager.clone())
+                        else {
+                            return Task::none();
+                        };
+                        Task::perform(
+                            async move {
+                                if let Err(err) = ConfigManager::new(storage)
+                                    .set_pending_key_rotations(&contacts.pending_key_rotations())
+                                    .await
+                                {
+                                    tracing::error!(?err, "Cannot save pending key rotations");
+                                }
+                            },
+                            |_| AppMessage::Tick,
+                        )
+                    }
                     UiEvent::ContactRemoved { address } => {
                         let chats = self.ctx.chat_manager.clone();
                         return Task::perform(
@@ -465,6 +792,10 @@ impl ChatApp {
                 let cmd = s.update(msg, &mut self.ctx);
                 self.handle_screen_command(cmd, AppMessage::Settings)
             }
+            (CurrentScreen::Logs(l), AppMessage::Logs(msg)) => {
+                let cmd = l.update(msg, &mut self.ctx);
+                self.handle_screen_command(cmd, AppMessage::Logs)
+            }
             (_, AppMessage::FocusInitField { reverse }) => match &mut self.screen {
                 CurrentScreen::Init(screen) => {
                     let cmd = screen.handle_tab_navigation(reverse);
@@ -472,9 +803,92 @@ impl ChatApp {
                 }
                 _ => Task::none(),
             },
+            (_, AppMessage::AudioDeviceChanged(change)) => {
+                let mut tasks = Vec::new();
+                if let CurrentScreen::Chats(screen) = &self.screen
+                    && screen.is_audio_settings_open()
+                {
+                    tasks.push(Task::done(AppMessage::ChatList(
+                        ChatListMessage::AudioDevicesChanged,
+                    )));
+                }
+                if let Some(calls) = self.ctx.call_manager.clone() {
+                    tasks.push(Task::perform(
+                        async move {
+                            if let Err(err) = calls.handle_device_change(&change).await {
+                                tracing::warn!(?err, "Failed to follow the default audio device");
+                            }
+                        },
+                        |_| AppMessage::Tick,
+                    ));
+                }
+                Task::batch(tasks)
+            }
+            (_, AppMessage::WindowMoved(position)) => {
+                self.ctx.window_geometry.x = Some(position.x);
+                self.ctx.window_geometry.y = Some(position.y);
+                Task::none()
+            }
+            (_, AppMessage::WindowResized(size)) => {
+                self.ctx.window_geometry.width = size.width;
+                self.ctx.window_geometry.height = size.height;
+                Task::none()
+            }
+            (_, AppMessage::WindowUnfocused) => {
+                self.focus.unfocus(std::time::Instant::now());
+                Task::none()
+            }
+            (_, AppMessage::WindowFocused) => {
+                if !self.focus.focus(std::time::Instant::now()) {
+                    return Task::none();
+                }
+                match self.ctx.contact_manager.clone() {
+                    Some(contacts) if contacts.is_focus_refresh_enabled() => {
+                        tracing::debug!("Window focused after idle, refreshing contacts");
+                        Task::perform(async move { contacts.refresh_contacts().await }, |_| {
+                            AppMessage::Tick
+                        })
+                    }
+                    _ => Task::none(),
+                }
+            }
+            (_, AppMessage::CloseRequested) => {
+                if self.shutting_down {
+                    return Task::none();
+                }
+                self.shutting_down = true;
+                let calls = self.ctx.call_manager.clone();
+                let chats = self.ctx.chat_manager.clone();
+                let contacts = self.ctx.contact_manager.clone();
+                let storage = self.ctx.storage.clone();
+                let geometry = self.ctx.window_geometry;
+                Task::perform(
+                    async move {
+                        if let Some(storage) = storage
+                            && let Err(err) = ConfigManager::new(storage)
+                                .set_window_geometry(geometry)
+                                .await
+                        {
+                            tracing::warn!(?err, "Failed to save window geometry");
+                        }
+                        let shutdown = shutdown(calls, chats, contacts);
+                        if tokio::time::timeout(SHUTDOWN_TIMEOUT, shutdown)
+                            .await
+                            .is_err()
+                        {
+                            tracing::warn!("Shutdown timed out");
+                        }
+                    },
+                    |_| AppMessage::ShutdownFinished,
+                )
+            }
+            (_, AppMessage::ShutdownFinished) => iced::exit(),
             // Tick: now mostly for compatibility, UI events handled via subscription
             (_, AppMessage::Tick) => {
                 // UI events are now handled through AppMessage::UiEvent
+                if let CurrentScreen::Logs(screen) = &mut self.screen {
+                    screen.refresh();
+                }
                 Task::none()
             }
             // Ignore unmatched pairs
@@ -488,6 +902,7 @@ impl ChatApp {
             CurrentScreen::Init(i) => i.view(&self.theme).map(AppMessage::Init),
             CurrentScreen::Chats(c) => c.view(&self.theme).map(AppMessage::ChatList),
             CurrentScreen::Settings(s) => s.view(&self.theme).map(AppMessage::Settings),
+            CurrentScreen::Logs(l) => l.view(&self.theme).map(AppMessage::Logs),
         }
     }
 
@@ -0,0 +1,67 @@
+//! Round avatar widget with an initials fallback
+
+use iced::widget::{container, image, text};
+use iced::{Color, Element, Length};
+
+use crate::avatar::circle_avatar;
+
+/// Background colors for avatars without an image.
+const PALETTE: [Color; 6] = [
+    Color::from_rgb(0.90, 0.45, 0.40),
+    Color::from_rgb(0.95, 0.65, 0.30),
+    Color::from_rgb(0.45, 0.70, 0.40),
+    Color::from_rgb(0.30, 0.65, 0.80),
+    Color::from_rgb(0.50, 0.50, 0.85),
+    Color::from_rgb(0.75, 0.45, 0.75),
+];
+
+/// Builds an image handle with the avatar clipped to a circle.
+pub fn avatar_handle(data: &[u8]) -> Option<image::Handle> {
+    match circle_avatar(data) {
+        Ok(image) => {
+            let (width, height) = image.dimensions();
+            Some(image::Handle::from_rgba(width, height, image.into_raw()))
+        }
+        Err(err) => {
+            tracing::warn!(?err, "Cannot decode avatar");
+            None
+        }
+    }
+}
+
+/// Round avatar of the given size, falls back to initials on a color derived from the name.
+pub fn avatar<'a, M: 'a>(name: &str, handle: Option<&image::Handle>, size: f32) -> Element<'a, M> {
+    if let Some(handle) = handle {
+        return image(handle.clone())
+            .width(Length::Fixed(size))
+            .height(Length::Fixed(size))
+            .into();
+    }
+    let background = PALETTE[name.bytes().map(usize::from).sum::<usize>() % PALETTE.len()];
+    container(text(initials(name)).size(size * 0.4).color(Color::WHITE))
+        .center_x(Length::Fixed(size))
+        .center_y(Length::Fixed(size))
+        .style(move |_| container::Style {
+            background: Some(iced::Background::Color(background)),
+            border: iced::Border {
+                radius: (size / 2.0).into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+fn initials(name: &str) -> String {
+    let initials: String = name
+        .split_whitespace()
+        .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()))
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect();
+    if initials.is_empty() {
+        "?".to_string()
+    } else {
+        initials
+    }
+}
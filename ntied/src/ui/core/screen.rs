@@ -27,6 +27,8 @@ pub enum ScreenType {
     },
     /// Settings screen
     Settings { server_addr: String },
+    /// Recent log lines
+    Logs,
 }
 
 /// Base trait for all application screens
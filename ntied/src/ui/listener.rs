@@ -1,19 +1,24 @@
 use async_trait::async_trait;
-use ntied_transport::Address;
+use ntied_crypto::PublicKey;
+use ntied_transport::{Address, ToAddress as _};
 use tokio::sync::mpsc;
 
-use crate::call::CallListener;
+use crate::audio::{CodecType, DeviceType};
+use crate::call::{AudioDirection, CallEndReason, CallListener, CallQuality, DefaultDeviceAction};
 use crate::chat::ChatListener;
-use crate::contact::ContactListener;
-use crate::models::{Message, MessageKind};
+use crate::config::ContactGroups;
+use crate::contact::{ConnectionStatus, ContactListener, Usage};
+use crate::models::{Contact, DateTime, Message};
 use crate::packet::ContactProfile;
 
 #[derive(Clone, Debug)]
 pub enum UiEvent {
-    TransportConnected(bool),
+    ConnectionStatus(ConnectionStatus),
     IncomingRequest {
         name: String,
         address: String,
+        intro: Option<String>,
+        count: u32,
     },
     OutgoingRequest {
         address: String,
@@ -21,10 +26,34 @@ pub enum UiEvent {
     ContactAccepted {
         name: String,
         address: String,
+        avatar: Option<Vec<u8>>,
+        archived: bool,
+        pin_order: Option<i64>,
+        verified: bool,
+        muted: bool,
+        auto_answer: bool,
+    },
+    ContactUpdated {
+        address: String,
+        name: String,
+        avatar: Option<Vec<u8>>,
+        archived: bool,
+        pin_order: Option<i64>,
+        verified: bool,
+        muted: bool,
+        auto_answer: bool,
     },
     ContactRemoved {
         address: String,
     },
+    ContactKeyRotated {
+        address: String,
+        new_address: String,
+        public_key: Vec<u8>,
+    },
+    KeyRotationDelivered {
+        address: String,
+    },
     ContactConnection {
         address: String,
         connected: bool,
@@ -34,16 +63,35 @@ pub enum UiEvent {
         address: String,
         incoming: bool,
         text: String,
+        verified: bool,
+        reply_to: Option<i64>,
+        // Time by the sender clock
+        create_time: DateTime,
+        clock_skewed: bool,
     },
     MessageSent {
         id: i64,
         address: String,
         text: String,
+        reply_to: Option<i64>,
+        create_time: DateTime,
     },
     MessageDelivered {
         id: i64,
         address: String,
     },
+    // Text was not sent, `id` is a local negative id of the failed bubble
+    MessageFailed {
+        id: i64,
+        address: String,
+        text: String,
+        reply_to: Option<i64>,
+        error: String,
+    },
+    // Incoming message from a contact that is not muted
+    MessageNotification {
+        address: String,
+    },
     // Call events
     IncomingCall {
         address: String,
@@ -62,12 +110,56 @@ pub enum UiEvent {
     },
     CallEnded {
         address: String,
-        reason: String,
+        reason: CallEndReason,
     },
     CallStateChanged {
         address: String,
         state: String,
     },
+    // Call waiting events
+    CallWaiting {
+        address: String,
+    },
+    CallHeld {
+        address: String,
+    },
+    CallResumed {
+        address: String,
+    },
+    CallWaitingEnded {
+        address: String,
+        reason: CallEndReason,
+    },
+    SpeakingWhileMuted {
+        address: String,
+    },
+    CallSummary {
+        address: String,
+        bytes_sent: u64,
+        bytes_received: u64,
+    },
+    CallCodec {
+        address: String,
+        codec: String,
+    },
+    CallQuality {
+        address: String,
+        quality: CallQuality,
+    },
+    OneWayAudioDetected {
+        address: String,
+        direction: AudioDirection,
+    },
+    OneWayAudioResolved {
+        address: String,
+    },
+    // System default device changed during a call
+    DefaultDeviceChanged {
+        address: String,
+        device_type: DeviceType,
+        action: DefaultDeviceAction,
+    },
+    ContactGroupsLoaded(ContactGroups),
 }
 
 pub struct UiEventListener {
@@ -83,14 +175,32 @@ impl UiEventListener {
 #[async_trait]
 impl ContactListener for UiEventListener {
     async fn on_server_connected(&self) {
-        if let Err(err) = self.tx.send(UiEvent::TransportConnected(true)).await {
-            tracing::error!(?err, "Cannot send UI event: TransportConnected");
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::ConnectionStatus(ConnectionStatus::Connected))
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: ConnectionStatus");
+        }
+    }
+
+    async fn on_server_reconnecting(&self) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::ConnectionStatus(ConnectionStatus::Reconnecting))
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: ConnectionStatus");
         }
     }
 
     async fn on_server_disconnected(&self) {
-        if let Err(err) = self.tx.send(UiEvent::TransportConnected(false)).await {
-            tracing::error!(?err, "Cannot send UI event: TransportConnected");
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::ConnectionStatus(ConnectionStatus::Disconnected))
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: ConnectionStatus");
         }
     }
 
@@ -120,12 +230,40 @@ impl ContactListener for UiEventListener {
         }
     }
 
-    async fn on_contact_incoming(&self, address: Address, profile: ContactProfile) {
+    async fn on_contact_incoming(
+        &self,
+        address: Address,
+        profile: ContactProfile,
+        intro: Option<String>,
+    ) {
         if let Err(err) = self
             .tx
             .send(UiEvent::IncomingRequest {
                 name: profile.name,
                 address: address.to_string(),
+                intro,
+                count: 1,
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: IncomingRequest");
+        }
+    }
+
+    async fn on_contact_incoming_repeated(
+        &self,
+        address: Address,
+        profile: ContactProfile,
+        intro: Option<String>,
+        count: u32,
+    ) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::IncomingRequest {
+                name: profile.name,
+                address: address.to_string(),
+                intro,
+                count,
             })
             .await
         {
@@ -139,6 +277,12 @@ impl ContactListener for UiEventListener {
             .send(UiEvent::ContactAccepted {
                 name: profile.name,
                 address: address.to_string(),
+                avatar: profile.avatar.map(|a| a.0),
+                archived: false,
+                pin_order: None,
+                verified: false,
+                muted: false,
+                auto_answer: false,
             })
             .await
         {
@@ -157,6 +301,39 @@ impl ContactListener for UiEventListener {
             tracing::error!(?err, "Cannot send UI event: ContactRemoved");
         }
     }
+
+    async fn on_contact_key_rotated(&self, address: Address, public_key: PublicKey) {
+        let (new_address, public_key) = match (public_key.to_address(), public_key.to_bytes()) {
+            (Ok(new_address), Ok(public_key)) => (new_address, public_key),
+            _ => {
+                tracing::error!(%address, "Invalid rotated public key");
+                return;
+            }
+        };
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::ContactKeyRotated {
+                address: address.to_string(),
+                new_address: new_address.to_string(),
+                public_key,
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: ContactKeyRotated");
+        }
+    }
+
+    async fn on_key_rotation_delivered(&self, address: Address) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::KeyRotationDelivered {
+                address: address.to_string(),
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: KeyRotationDelivered");
+        }
+    }
 }
 
 #[async_trait]
@@ -221,12 +398,12 @@ impl CallListener for UiEventListener {
         }
     }
 
-    async fn on_call_ended(&self, address: Address, reason: &str) {
+    async fn on_call_ended(&self, address: Address, reason: CallEndReason) {
         if let Err(err) = self
             .tx
             .send(UiEvent::CallEnded {
                 address: address.to_string(),
-                reason: reason.to_string(),
+                reason,
             })
             .await
         {
@@ -254,14 +431,158 @@ impl CallListener for UiEventListener {
     async fn on_video_frame_received(&self, _address: Address, _frame: Vec<u8>) {
         // TODO: Display video frame
     }
+
+    async fn on_call_waiting(&self, address: Address) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::CallWaiting {
+                address: address.to_string(),
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: CallWaiting");
+        }
+    }
+
+    async fn on_call_held(&self, address: Address) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::CallHeld {
+                address: address.to_string(),
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: CallHeld");
+        }
+    }
+
+    async fn on_call_resumed(&self, address: Address) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::CallResumed {
+                address: address.to_string(),
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: CallResumed");
+        }
+    }
+
+    async fn on_call_waiting_ended(&self, address: Address, reason: CallEndReason) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::CallWaitingEnded {
+                address: address.to_string(),
+                reason,
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: CallWaitingEnded");
+        }
+    }
+
+    async fn on_speaking_while_muted(&self, address: Address) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::SpeakingWhileMuted {
+                address: address.to_string(),
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: SpeakingWhileMuted");
+        }
+    }
+
+    async fn on_call_summary(&self, address: Address, usage: Usage) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::CallSummary {
+                address: address.to_string(),
+                bytes_sent: usage.bytes_sent,
+                bytes_received: usage.bytes_received,
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: CallSummary");
+        }
+    }
+
+    async fn on_call_codec(&self, address: Address, codec: CodecType) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::CallCodec {
+                address: address.to_string(),
+                codec: format!("{:?}", codec),
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: CallCodec");
+        }
+    }
+
+    async fn on_call_quality(&self, address: Address, quality: CallQuality) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::CallQuality {
+                address: address.to_string(),
+                quality,
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: CallQuality");
+        }
+    }
+
+    async fn on_one_way_audio_detected(&self, address: Address, direction: AudioDirection) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::OneWayAudioDetected {
+                address: address.to_string(),
+                direction,
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: OneWayAudioDetected");
+        }
+    }
+
+    async fn on_one_way_audio_resolved(&self, address: Address) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::OneWayAudioResolved {
+                address: address.to_string(),
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: OneWayAudioResolved");
+        }
+    }
+
+    async fn on_default_device_changed(
+        &self,
+        address: Address,
+        device_type: DeviceType,
+        action: DefaultDeviceAction,
+    ) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::DefaultDeviceChanged {
+                address: address.to_string(),
+                device_type,
+                action,
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: DefaultDeviceChanged");
+        }
+    }
 }
 
 #[async_trait]
 impl ChatListener for UiEventListener {
     async fn on_incoming_message(&self, address: Address, message: Message) {
-        let text = match message.kind {
-            MessageKind::Text(s) => s,
-        };
+        let clock_skewed = message.has_clock_skew();
+        let text = message.kind.display_text();
         if let Err(err) = self
             .tx
             .send(UiEvent::NewMessage {
@@ -269,6 +590,10 @@ impl ChatListener for UiEventListener {
                 address: address.to_string(),
                 incoming: true,
                 text,
+                verified: message.verified,
+                reply_to: message.reply_to,
+                create_time: message.create_time,
+                clock_skewed,
             })
             .await
         {
@@ -276,6 +601,18 @@ impl ChatListener for UiEventListener {
         }
     }
 
+    async fn on_message_notification(&self, address: Address, _message: Message) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::MessageNotification {
+                address: address.to_string(),
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: MessageNotification");
+        }
+    }
+
     async fn on_outgoing_message(&self, address: Address, message: Message) {
         if let Err(err) = self
             .tx
@@ -288,4 +625,23 @@ impl ChatListener for UiEventListener {
             tracing::error!(?err, "Cannot send UI event: MessageDelivered");
         }
     }
+
+    async fn on_contact_updated(&self, address: Address, contact: Contact) {
+        if let Err(err) = self
+            .tx
+            .send(UiEvent::ContactUpdated {
+                address: address.to_string(),
+                name: contact.local_name.unwrap_or(contact.name),
+                avatar: contact.avatar,
+                archived: contact.archived,
+                pin_order: contact.pinned.then_some(contact.pin_order),
+                verified: contact.verified,
+                muted: contact.muted,
+                auto_answer: contact.auto_answer,
+            })
+            .await
+        {
+            tracing::error!(?err, "Cannot send UI event: ContactUpdated");
+        }
+    }
 }
@@ -0,0 +1,177 @@
+//! Inline markdown of chat messages: `*bold*`, `_italic_` and `` `code` ``
+
+use iced::widget::{rich_text, span, text};
+use iced::{Element, Font, Theme, font};
+
+use crate::ui::theme::colors;
+
+/// Formatting applied to a span of text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SpanStyle {
+    pub bold: bool,
+    pub italic: bool,
+    // Inline code, shown in a monospace font
+    pub code: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+/// Splits the text into styled spans.
+///
+/// A marker only opens a span when it is followed by a non-space character and
+/// a matching marker preceded by a non-space character comes later, markers
+/// that do not pair up are kept as typed. Underscores inside words do not
+/// count as markers, code spans are not formatted further and a backslash
+/// keeps the next marker literal.
+pub fn parse_inline(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    parse_into(text, SpanStyle::default(), &mut spans);
+    spans
+}
+
+fn parse_into(text: &str, style: SpanStyle, spans: &mut Vec<Span>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut literal = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && chars.get(i + 1).is_some_and(|&n| is_marker(n)) {
+            literal.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c == '`'
+            && let Some(end) = find_code_end(&chars, i)
+        {
+            push_span(spans, std::mem::take(&mut literal), style);
+            let code: String = chars[i + 1..end].iter().collect();
+            push_span(
+                spans,
+                code,
+                SpanStyle {
+                    code: true,
+                    ..style
+                },
+            );
+            i = end + 1;
+            continue;
+        }
+        if (c == '*' && !style.bold || c == '_' && !style.italic)
+            && let Some(end) = find_closing(&chars, i)
+        {
+            push_span(spans, std::mem::take(&mut literal), style);
+            let inner: String = chars[i + 1..end].iter().collect();
+            let inner_style = match c {
+                '*' => SpanStyle {
+                    bold: true,
+                    ..style
+                },
+                _ => SpanStyle {
+                    italic: true,
+                    ..style
+                },
+            };
+            parse_into(&inner, inner_style, spans);
+            i = end + 1;
+            continue;
+        }
+        literal.push(c);
+        i += 1;
+    }
+    push_span(spans, literal, style);
+}
+
+fn is_marker(c: char) -> bool {
+    matches!(c, '*' | '_' | '`' | '\\')
+}
+
+/// Position of the backtick closing a non-empty code span opened at `start`.
+fn find_code_end(chars: &[char], start: usize) -> Option<usize> {
+    let end = start + 1 + chars[start + 1..].iter().position(|&c| c == '`')?;
+    (end > start + 1).then_some(end)
+}
+
+/// Position of the marker closing the span opened at `start`, code spans in
+/// between are skipped.
+fn find_closing(chars: &[char], start: usize) -> Option<usize> {
+    let marker = chars[start];
+    let is_word = |i: Option<usize>| {
+        i.and_then(|i| chars.get(i))
+            .is_some_and(|c| c.is_alphanumeric())
+    };
+    if chars.get(start + 1).is_none_or(|c| c.is_whitespace())
+        || marker == '_' && is_word(start.checked_sub(1))
+    {
+        return None;
+    }
+    let mut i = start + 2;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '`' => {
+                if let Some(end) = find_code_end(chars, i) {
+                    i = end;
+                }
+            }
+            c if c == marker
+                && !chars[i - 1].is_whitespace()
+                && !(marker == '_' && is_word(Some(i + 1))) =>
+            {
+                return Some(i);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Appends text to the spans, merging it with the last span of the same style.
+fn push_span(spans: &mut Vec<Span>, text: String, style: SpanStyle) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(&text),
+        _ => spans.push(Span { text, style }),
+    }
+}
+
+/// Renders the message text with its inline formatting.
+pub fn message_text<'a, Message: Clone + 'static>(
+    content: &str,
+    size: u16,
+    theme: &Theme,
+) -> Element<'a, Message> {
+    let code_background = colors::background_strong(theme);
+    let spans: Vec<text::Span<'a, Message, Font>> = parse_inline(content)
+        .into_iter()
+        .map(|s| {
+            let mut font = if s.style.code {
+                Font::MONOSPACE
+            } else {
+                Font::DEFAULT
+            };
+            if s.style.bold {
+                font.weight = font::Weight::Bold;
+            }
+            if s.style.italic {
+                font.style = font::Style::Italic;
+            }
+            let item = span(s.text).font(font);
+            if s.style.code {
+                item.background(code_background)
+            } else {
+                item
+            }
+        })
+        .collect();
+    rich_text(spans)
+        .size(size)
+        .color(colors::text_primary(theme))
+        .into()
+}
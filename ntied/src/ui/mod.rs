@@ -1,4 +1,7 @@
+pub mod avatar;
 pub mod core;
+pub mod markdown;
+pub mod qr;
 pub mod screens;
 pub mod theme;
 
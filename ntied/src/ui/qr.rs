@@ -0,0 +1,26 @@
+use iced::widget::svg;
+use qrcodegen::{QrCode, QrCodeEcc};
+
+/// Width of the quiet zone around the code in modules.
+const BORDER: i32 = 4;
+
+/// QR code of the text as an SVG document, `None` if the text does not fit.
+pub fn qr_svg(text: &str) -> Option<String> {
+    let code = QrCode::encode_text(text, QrCodeEcc::Medium).ok()?;
+    let mut path = String::new();
+    for y in 0..code.size() {
+        for x in 0..code.size() {
+            if code.get_module(x, y) {
+                path.push_str(&format!("M{},{}h1v1h-1z", x + BORDER, y + BORDER));
+            }
+        }
+    }
+    let size = code.size() + BORDER * 2;
+    Some(format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {size} {size}" shape-rendering="crispEdges"><rect width="{size}" height="{size}" fill="#FFFFFF"/><path d="{path}" fill="#000000"/></svg>"##
+    ))
+}
+
+pub fn qr_handle(text: &str) -> Option<svg::Handle> {
+    qr_svg(text).map(|svg| svg::Handle::from_memory(svg.into_bytes()))
+}
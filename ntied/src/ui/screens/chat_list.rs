@@ -1,19 +1,47 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
 
 use iced::widget::{
-    Space, button, column, container, row, scrollable, slider, stack, svg, text, text_input,
+    Space, button, column, container, image, mouse_area, pick_list, row, scrollable, slider, stack,
+    svg, text, text_input,
 };
-use iced::{Alignment, Color, Element, Length, Padding, Task, Theme, clipboard};
-
+use iced::{Alignment, Color, Element, Length, Padding, Task, Theme, clipboard, mouse};
+use tokio::sync::{Mutex as TokioMutex, mpsc};
+
+use crate::audio::DeviceType;
+use crate::call::{AudioDirection, CallQuality, DefaultDeviceAction};
+use crate::chat::{ChatManager, ChatOrder};
+use crate::config::{ConfigManager, ContactGroups, WindowGeometry};
+use crate::contact::ConnectionStatus;
+use crate::models::{DateTime, Message};
+use crate::packet::ContactRequestPacket;
+use crate::storage::Storage;
+use crate::ui::avatar::{avatar, avatar_handle};
 use crate::ui::core::{Screen, ScreenCommand, ScreenType};
+use crate::ui::markdown::message_text;
+use crate::ui::qr::qr_handle;
 use crate::ui::theme::{colors, styles};
 use crate::ui::{AppContext, UiEvent};
 
 // SVG Icons
+/// Number of messages loaded per history request.
+const HISTORY_PAGE_SIZE: usize = 50;
+/// Chats worth of messages kept in memory before idle chats are evicted.
+const HISTORY_MEMORY_CHATS: usize = 4;
+/// Reference tone played from the audio settings, A4 at -10 dBFS.
+const TEST_TONE_FREQ: f32 = 440.0;
+const TEST_TONE_DURATION: std::time::Duration = std::time::Duration::from_secs(1);
+const TEST_TONE_VOLUME: f32 = 0.316;
+
 const COPY_ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
     <path d="M16 1H4c-1.1 0-2 .9-2 2v14h2V3h12V1zm3 4H8c-1.1 0-2 .9-2 2v14c0 1.1.9 2 2 2h11c1.1 0 2-.9 2-2V7c0-1.1-.9-2-2-2zm0 16H8V7h11v14z"/>
 </svg>"#;
 
+const QR_ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
+    <path d="M3 11h8V3H3v8zm2-6h4v4H5V5zM3 21h8v-8H3v8zm2-6h4v4H5v-4zM13 3v8h8V3h-8zm6 6h-4V5h4v4zM13 13h2v2h-2zM15 15h2v2h-2zM13 17h2v2h-2zM17 17h2v2h-2zM19 19h2v2h-2zM15 19h2v2h-2zM17 13h2v2h-2zM19 15h2v2h-2z"/>
+</svg>"#;
+
 const ADD_ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
     <path d="M19 13h-6v6h-2v-6H5v-2h6V5h2v6h6v2z"/>
 </svg>"#;
@@ -38,6 +66,10 @@ const SPEAKER_ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0
     <path d="M3 9v6h4l5 5V4L7 9H3zm13.5 3c0-1.77-1.02-3.29-2.5-4.03v8.05c1.48-.73 2.5-2.25 2.5-4.02zM14 3.23v2.06c2.89.86 5 3.54 5 6.71s-2.11 5.85-5 6.71v2.06c4.01-.91 7-4.49 7-8.77s-2.99-7.86-7-8.77z"/>
 </svg>"#;
 
+const SHIELD_ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
+    <path d="M12 1 3 5v6c0 5.55 3.84 10.74 9 12 5.16-1.26 9-6.45 9-12V5l-9-4zm-2 16-4-4 1.41-1.41L10 14.17l6.59-6.59L18 9l-8 8z"/>
+</svg>"#;
+
 const MIC_ON_ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor">
     <path d="M12 14c1.66 0 3-1.34 3-3V5c0-1.66-1.34-3-3-3S9 3.34 9 5v6c0 1.66 1.34 3 3 3z"/>
     <path d="M17 11c0 2.76-2.24 5-5 5s-5-2.24-5-5H5c0 3.53 2.61 6.43 6 6.92V21h2v-3.08c3.39-.49 6-3.39 6-6.92h-2z"/>
@@ -51,30 +83,53 @@ const MIC_OFF_ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0
 pub enum ChatListMessage {
     SelectChat(String),
     CopyOwnAddress,
+    ClearCopyConfirmation(u64),
+    ShowOwnAddress,
+    HideOwnAddress,
     CopyPeerAddress(String),
+    CopyMessage(i64),     // message text only
+    CopyMessageLine(i64), // "name • time: text"
+    RetryMessage(i64),    // sends the failed message again
     AcceptIncoming(String),
     RejectIncoming(String),
+    BlockIncoming(String),
     CancelOutgoing(String),
     ShowAddContactModal,
     HideAddContactModal,
     AddContactInputChanged(String),
+    AddContactIntroChanged(String),
     AddContactSubmit,
     ComposeChanged(String),
     SendMessage,
     OpenSettings,
     Logout,
     ClearError,
+    DismissCallSummary,
     // Call messages
     StartVoiceCall(String),
+    CallFailed(String),
     AcceptCall(String),
     RejectCall(String),
     HangupCall(String),
+    AcceptWaitingCall(String),
+    RejectWaitingCall(String),
+    SwapCalls,
     ToggleMute,
     ShowAudioSettings,
     HideAudioSettings,
     SelectInputDevice(String),
+    // Audio devices were added or removed while audio settings are open
+    AudioDevicesChanged,
     SelectOutputDevice(String),
+    // Keep the devices of the call after the system default changed
+    DismissDeviceOffer,
     SpeakerVolumeChanged(f32),
+    // Play a reference tone on the selected speaker
+    PlayTestTone,
+    // Resizing of the chat list panel
+    DividerDragStarted,
+    DividerDragged(iced::Point),
+    DividerDragEnded,
     MicrophoneVolumeChanged(f32),
     DevicesLoaded(Vec<(String, bool)>, Vec<(String, bool)>), // (name, is_default)
     DevicesLoadedWithCurrent(
@@ -88,6 +143,36 @@ pub enum ChatListMessage {
     ContactOperationComplete(Result<(), String>),
     MessageSent(Result<i64, String>),
     DeviceSwitchComplete(Result<(), String>),
+    // Chat history paging
+    MessagesScrolled(f32), // relative vertical offset of the messages list
+    HistoryLoaded {
+        address: String,
+        messages: Vec<Message>,
+        has_more: bool,
+    },
+    // Contact groups
+    ToggleGroup(String),
+    AssignGroup(String, String), // (address, group)
+    NewGroupNameChanged(String),
+    CreateGroup,
+    DeleteGroup(String),
+    // Archived chats
+    ToggleArchived,
+    SetArchived(String, bool),   // (address, archived)
+    SetMuted(String, bool),      // (address, muted)
+    SetAutoAnswer(String, bool), // (address, auto_answer)
+    // Replies
+    ReplyTo(i64),
+    CancelReply,
+    ScrollToMessage(i64),
+    // Contact verification
+    ShowVerifyDialog(String),
+    VerifyDialogLoaded(Box<VerifyDialog>),
+    HideVerifyDialog,
+    SetVerified(String, bool), // (address, verified)
+    // Pinned chats
+    SetPinned(String, bool),         // (address, pinned)
+    PinChanged(String, Option<i64>), // (address, pin order)
     // State synchronization messages
     SyncCallState, // Request to sync state with CallManager
     CallStateSynced {
@@ -101,10 +186,65 @@ pub enum ChatListMessage {
     Noop,              // For operations that don't need result handling
 }
 
+/// Contact groups and the archived section of the left panel.
+#[derive(Default)]
+struct GroupsState {
+    groups: ContactGroups,
+    collapsed: HashSet<String>,
+    new_group_name: String,
+    // Whether the archived section is expanded
+    show_archived: bool,
+    // Count unread messages of archived chats
+    archived_unread_badges: bool,
+}
+
+/// Brief "copied" confirmation shown after copying the own address.
+///
+/// Each copy starts a new generation so an earlier timer does not hide
+/// the confirmation of a later copy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CopyConfirmation {
+    generation: u64,
+    visible: bool,
+}
+
+impl CopyConfirmation {
+    pub const DURATION: Duration = Duration::from_millis(1500);
+
+    /// Shows the confirmation, returns the generation to clear it with.
+    pub fn show(&mut self) -> u64 {
+        self.generation += 1;
+        self.visible = true;
+        self.generation
+    }
+
+    /// Hides the confirmation unless it was shown again since `generation`.
+    pub fn clear(&mut self, generation: u64) {
+        if generation == self.generation {
+            self.visible = false;
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+/// Fingerprints compared in the verify contact dialog.
+#[derive(Clone, Debug)]
+pub struct VerifyDialog {
+    address: String,
+    own_fingerprint: String,
+    contact_fingerprint: String,
+}
+
 #[derive(Clone, Debug)]
 struct PendingIncoming {
     name: String,
     address: String,
+    intro: Option<String>,
+    // Number of requests received from the address
+    count: u32,
 }
 
 #[derive(Clone, Debug)]
@@ -116,8 +256,26 @@ struct PendingOutgoing {
 struct ContactSummary {
     name: String,
     address: String,
+    avatar: Option<image::Handle>,
     connected: bool,
     last_message: Option<String>,
+    archived: bool,
+    unread: u32,
+    pin_order: Option<i64>,
+    verified: bool,
+    muted: bool,
+    auto_answer: bool,
+    // Id of the latest message
+    last_activity: Option<i64>,
+}
+
+impl ContactSummary {
+    fn order(&self) -> ChatOrder {
+        ChatOrder {
+            pin_order: self.pin_order,
+            last_activity: self.last_activity,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -125,6 +283,11 @@ struct CallInfo {
     address: String,
     name: String,
     state: CallState,
+    // Negotiated audio codec and quality of incoming audio, unknown until reported
+    codec: Option<String>,
+    quality: Option<CallQuality>,
+    // Direction without traffic while the other one flows
+    one_way: Option<AudioDirection>,
 }
 
 #[derive(Clone, Debug)]
@@ -133,49 +296,112 @@ struct IncomingCallInfo {
     name: String,
 }
 
+#[derive(Clone, Debug)]
+struct WaitingCallInfo {
+    address: String,
+    name: String,
+    // Held call that was connected before, otherwise not answered yet
+    held: bool,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 enum CallState {
     Calling,
     Ringing,
+    ConnectingAudio,
     Connected,
 }
 
+impl CallState {
+    /// State after the call was answered, the audio may already be playing.
+    fn answered(&self) -> Self {
+        match self {
+            Self::Calling | Self::Ringing => Self::ConnectingAudio,
+            state => state.clone(),
+        }
+    }
+}
+
+/// Delivery state of a message bubble.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MessageStatus {
+    /// Outgoing message is stored and waits for the contact to confirm it.
+    Pending,
+    Delivered,
+    /// Outgoing message was not sent, with the error.
+    Failed(String),
+}
+
 #[derive(Clone, Debug)]
 struct MessageItem {
     id: i64,
     text: String,
     is_mine: bool,
-    delivered: bool,
+    status: MessageStatus,
+    // Signature of an incoming message matched the contact key
+    verified: bool,
+    // Time by the sender clock
     timestamp: String,
+    // Sender clock differs from the local one, the timestamp may be wrong
+    clock_skewed: bool,
+    // Id of the quoted message
+    reply_to: Option<i64>,
 }
 
 pub struct ChatListScreen {
     own_name: String,
     own_address: String,
-    transport_connected: bool,
+    own_address_qr: Option<svg::Handle>,
+    address_copied: CopyConfirmation,
+    show_own_address: bool,
+    connection_status: ConnectionStatus,
     incoming_pending: Vec<PendingIncoming>,
     outgoing_pending: Vec<PendingOutgoing>,
     contacts: Vec<ContactSummary>,
     selected_chat: Option<String>,
     messages_by_addr: HashMap<String, Vec<MessageItem>>,
     show_add_contact_modal: bool,
+    verify_dialog: Option<Box<VerifyDialog>>,
     add_contact_addr: String,
+    // Intro note sent with the contact request
+    add_contact_intro: String,
     add_contact_error: Option<String>,
     compose_text: String,
+    // Message quoted by the composed reply
+    replying_to: Option<i64>,
+    // Last local id given to a message that failed to send, counts down
+    last_failed_id: i64,
     global_error: Option<String>,
     should_scroll_to_end: bool,
     messages_scrollable_id: scrollable::Id,
+    // Whether older history of the selected chat can be loaded
+    history_has_more: bool,
+    history_loading: bool,
+    // Messages of a chat kept in memory, older ones are loaded on scroll
+    history_limit: usize,
     // Call state
     active_call: Option<CallInfo>,
     incoming_call: Option<IncomingCallInfo>,
+    // Boxed to keep the screen size small
+    waiting_call: Option<Box<WaitingCallInfo>>,
+    // Data used by the last ended call
+    call_summary: Option<String>,
+    groups: Box<GroupsState>,
 
     // Audio settings
     show_audio_settings: bool,
+    // Width of the chat list panel, changed by dragging the divider
+    sidebar_width: f32,
+    dragging_divider: bool,
     is_muted: bool,
+    // Voice was detected while muted, cleared when mute is toggled
+    muted_nudge: bool,
     available_input_devices: Vec<String>,
     available_output_devices: Vec<String>,
     selected_input_device: Option<String>,
     selected_output_device: Option<String>,
+    // New system default device offered for the call
+    device_offer: Option<(DeviceType, String)>,
     speaker_volume: f32,    // 0.0 to 2.0, default 1.0 (100%)
     microphone_volume: f32, // 0.0 to 2.0, default 1.0 (100%)
 }
@@ -185,27 +411,44 @@ impl ChatListScreen {
         Self {
             own_name: profile_name.unwrap_or_else(|| "Me".to_string()),
             own_address: String::new(),
-            transport_connected: false,
+            own_address_qr: None,
+            address_copied: CopyConfirmation::default(),
+            show_own_address: false,
+            connection_status: ConnectionStatus::Disconnected,
             incoming_pending: Vec::new(),
             outgoing_pending: Vec::new(),
             contacts: Vec::new(),
             selected_chat: None,
             messages_by_addr: HashMap::new(),
             show_add_contact_modal: false,
+            verify_dialog: None,
             add_contact_addr: String::new(),
+            add_contact_intro: String::new(),
             add_contact_error: None,
             compose_text: String::new(),
+            replying_to: None,
+            last_failed_id: 0,
             global_error: None,
             should_scroll_to_end: false,
             messages_scrollable_id: scrollable::Id::unique(),
+            history_has_more: false,
+            history_loading: false,
+            history_limit: ConfigManager::DEFAULT_HISTORY_LIMIT,
             active_call: None,
             incoming_call: None,
+            waiting_call: None,
+            call_summary: None,
+            groups: Box::default(),
             show_audio_settings: false,
+            sidebar_width: WindowGeometry::default().sidebar_width,
+            dragging_divider: false,
             is_muted: false,
+            muted_nudge: false,
             available_input_devices: Vec::new(),
             available_output_devices: Vec::new(),
             selected_input_device: None,
             selected_output_device: None,
+            device_offer: None,
             speaker_volume: 1.0,
             microphone_volume: 1.0,
         }
@@ -213,9 +456,64 @@ impl ChatListScreen {
 
     pub fn set_identity(&mut self, name: String, address: String) {
         self.own_name = name;
+        self.own_address_qr = qr_handle(&address);
         self.own_address = address;
     }
 
+    pub fn set_sidebar_width(&mut self, width: f32) {
+        self.sidebar_width = WindowGeometry::clamp_sidebar_width(width);
+    }
+
+    pub fn sidebar_width(&self) -> f32 {
+        self.sidebar_width
+    }
+
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit.clamp(
+            ConfigManager::MIN_HISTORY_LIMIT,
+            ConfigManager::MAX_HISTORY_LIMIT,
+        );
+    }
+
+    /// Appends the message to the chat, dropping the oldest in-memory
+    /// messages over the limit, they stay in storage.
+    fn push_message(&mut self, address: &str, item: MessageItem) {
+        let entry = self
+            .messages_by_addr
+            .entry(address.to_string())
+            .or_default();
+        entry.push(item);
+        if entry.len() > self.history_limit {
+            let excess = entry.len() - self.history_limit;
+            entry.drain(..excess);
+            if self.selected_chat.as_deref() == Some(address) {
+                self.history_has_more = true;
+            }
+        }
+        self.evict_idle_chats();
+    }
+
+    /// Drops messages of the largest chats that are not open when the total
+    /// exceeds the memory budget, their history is loaded again on select.
+    fn evict_idle_chats(&mut self) {
+        let budget = self.history_limit * HISTORY_MEMORY_CHATS;
+        let mut total: usize = self.messages_by_addr.values().map(Vec::len).sum();
+        while total > budget {
+            let largest = self
+                .messages_by_addr
+                .iter()
+                .filter(|(addr, _)| self.selected_chat.as_ref() != Some(*addr))
+                .max_by_key(|(_, list)| list.len())
+                .map(|(addr, _)| addr.clone());
+            let Some(address) = largest else {
+                break;
+            };
+            if let Some(list) = self.messages_by_addr.remove(&address) {
+                total -= list.len();
+            }
+        }
+    }
+
     pub fn set_error(&mut self, msg: impl Into<String>) {
         self.global_error = Some(msg.into());
     }
@@ -224,7 +522,158 @@ impl ChatListScreen {
         &self.compose_text
     }
 
+    pub fn is_audio_settings_open(&self) -> bool {
+        self.show_audio_settings
+    }
+
+    /// New system default device offered to the current call.
+    pub fn device_offer(&self) -> Option<(DeviceType, &str)> {
+        self.device_offer
+            .as_ref()
+            .map(|(device_type, name)| (*device_type, name.as_str()))
+    }
+
+    pub fn selected_output_device(&self) -> Option<&str> {
+        self.selected_output_device.as_deref()
+    }
+
+    /// Load audio devices keeping the currently selected ones if they still exist.
+    fn load_audio_devices(&self) -> Task<ChatListMessage> {
+        let keep_current_input = self.selected_input_device.clone();
+        let keep_current_output = self.selected_output_device.clone();
+        Task::perform(
+            async move {
+                let input_devices = crate::audio::AudioManager::list_input_devices()
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|d| (d.name, d.is_default))
+                    .collect::<Vec<_>>();
+                let output_devices = crate::audio::AudioManager::list_output_devices()
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|d| (d.name, d.is_default))
+                    .collect::<Vec<_>>();
+                (
+                    input_devices,
+                    output_devices,
+                    keep_current_input,
+                    keep_current_output,
+                )
+            },
+            |(input, output, current_input, current_output)| {
+                ChatListMessage::DevicesLoadedWithCurrent(
+                    input,
+                    output,
+                    current_input,
+                    current_output,
+                )
+            },
+        )
+    }
+
+    /// Delivery state of the message with the id in the chat with the address.
+    pub fn message_status(&self, address: &str, id: i64) -> Option<MessageStatus> {
+        self.messages_by_addr
+            .get(address)?
+            .iter()
+            .find(|m| m.id == id)
+            .map(|m| m.status.clone())
+    }
+
+    /// Removes the failed message bubble, returns its text and quoted message
+    /// to send them again.
+    pub fn take_failed_message(&mut self, address: &str, id: i64) -> Option<(String, Option<i64>)> {
+        let list = self.messages_by_addr.get_mut(address)?;
+        let pos = list
+            .iter()
+            .position(|m| m.id == id && matches!(m.status, MessageStatus::Failed(_)))?;
+        let item = list.remove(pos);
+        Some((item.text, item.reply_to))
+    }
+
+    /// Sends the text to the chat with the address, returns the events to
+    /// show the sent messages or a bubble with `failed_id` on error.
+    pub async fn send_text(
+        chats: Option<Arc<ChatManager>>,
+        address: String,
+        text: String,
+        reply_to: Option<i64>,
+        failed_id: i64,
+    ) -> Vec<UiEvent> {
+        let result = async {
+            let chats = chats.ok_or_else(|| anyhow::anyhow!("Not connected"))?;
+            let addr = address
+                .parse::<ntied_transport::Address>()
+                .map_err(|err| anyhow::anyhow!("Invalid address {address}: {err}"))?;
+            let handle = chats
+                .get_contact_chat(addr)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("No chat with {address}"))?;
+            handle.send_text(&text, reply_to).await
+        }
+        .await;
+        match result {
+            Ok(messages) => messages
+                .into_iter()
+                .map(|message| {
+                    let text = message.kind.display_text();
+                    UiEvent::MessageSent {
+                        id: message.id,
+                        address: address.clone(),
+                        text,
+                        reply_to: message.reply_to,
+                        create_time: message.create_time,
+                    }
+                })
+                .collect(),
+            Err(err) => {
+                tracing::warn!(?err, "Failed to send message");
+                vec![UiEvent::MessageFailed {
+                    id: failed_id,
+                    address,
+                    text,
+                    reply_to,
+                    error: err.to_string(),
+                }]
+            }
+        }
+    }
+
+    fn next_failed_id(&mut self) -> i64 {
+        self.last_failed_id -= 1;
+        self.last_failed_id
+    }
+
+    fn send_text_task(
+        chats: Option<Arc<ChatManager>>,
+        ui_tx: mpsc::Sender<UiEvent>,
+        address: String,
+        text: String,
+        reply_to: Option<i64>,
+        failed_id: i64,
+    ) -> Task<ChatListMessage> {
+        Task::perform(
+            async move {
+                for event in Self::send_text(chats, address, text, reply_to, failed_id).await {
+                    let _ = ui_tx.send(event).await;
+                }
+                ChatListMessage::Noop
+            },
+            |msg| msg,
+        )
+    }
+
     // Methods to save/restore call state for preservation across screen switches
+    /// Intro note of the pending incoming request from the address.
+    pub fn incoming_request_intro(&self, address: &str) -> Option<&str> {
+        self.incoming_pending
+            .iter()
+            .find(|p| p.address == address)
+            .and_then(|p| p.intro.as_deref())
+    }
+
     pub fn get_active_call_address(&self) -> Option<String> {
         self.active_call.as_ref().map(|c| c.address.clone())
     }
@@ -237,6 +686,7 @@ impl ChatListScreen {
         self.active_call.as_ref().map(|c| match c.state {
             CallState::Calling => "calling".to_string(),
             CallState::Ringing => "ringing".to_string(),
+            CallState::ConnectingAudio => "connecting_audio".to_string(),
             CallState::Connected => "connected".to_string(),
         })
     }
@@ -264,6 +714,7 @@ impl ChatListScreen {
             let state = match state_str.as_str() {
                 "calling" => CallState::Calling,
                 "ringing" => CallState::Ringing,
+                "connecting_audio" => CallState::ConnectingAudio,
                 "connected" => CallState::Connected,
                 _ => CallState::Calling,
             };
@@ -271,6 +722,9 @@ impl ChatListScreen {
                 address,
                 name,
                 state,
+                codec: None,
+                quality: None,
+                one_way: None,
             });
         }
 
@@ -282,14 +736,32 @@ impl ChatListScreen {
 
     pub fn apply_event(&mut self, event: UiEvent) {
         match event {
-            UiEvent::TransportConnected(connected) => {
-                self.transport_connected = connected;
+            UiEvent::ConnectionStatus(status) => {
+                self.connection_status = status;
             }
 
-            UiEvent::IncomingRequest { name, address } => {
-                if !self.incoming_pending.iter().any(|p| p.address == address) {
-                    self.incoming_pending
-                        .push(PendingIncoming { name, address });
+            UiEvent::IncomingRequest {
+                name,
+                address,
+                intro,
+                count,
+            } => {
+                match self
+                    .incoming_pending
+                    .iter_mut()
+                    .find(|p| p.address == address)
+                {
+                    Some(pending) => {
+                        pending.name = name;
+                        pending.intro = intro.or(pending.intro.take());
+                        pending.count = pending.count.max(count);
+                    }
+                    None => self.incoming_pending.push(PendingIncoming {
+                        name,
+                        address,
+                        intro,
+                        count,
+                    }),
                 }
             }
 
@@ -299,18 +771,58 @@ impl ChatListScreen {
                 }
             }
 
-            UiEvent::ContactAccepted { address, name } => {
+            UiEvent::ContactAccepted {
+                address,
+                name,
+                avatar,
+                archived,
+                pin_order,
+                verified,
+                muted,
+                auto_answer,
+            } => {
                 self.incoming_pending.retain(|p| p.address != address);
                 self.outgoing_pending.retain(|p| p.address != address);
                 if !self.contacts.iter().any(|c| c.address == address) {
                     self.contacts.push(ContactSummary {
                         name,
                         address: address.clone(),
+                        avatar: avatar.as_deref().and_then(avatar_handle),
                         connected: true,
                         last_message: None,
+                        archived,
+                        unread: 0,
+                        pin_order,
+                        verified,
+                        muted,
+                        auto_answer,
+                        last_activity: None,
                     });
                 }
             }
+            UiEvent::ContactUpdated {
+                address,
+                name,
+                avatar,
+                archived,
+                pin_order,
+                verified,
+                muted,
+                auto_answer,
+            } => {
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == address) {
+                    c.name = name.clone();
+                    c.avatar = avatar.as_deref().and_then(avatar_handle);
+                    c.archived = archived;
+                    c.pin_order = pin_order;
+                    c.verified = verified;
+                    c.muted = muted;
+                    c.auto_answer = auto_answer;
+                }
+                if let Some(call) = self.active_call.as_mut().filter(|c| c.address == address) {
+                    call.name = name;
+                }
+            }
             UiEvent::ContactRemoved { address } => {
                 self.incoming_pending.retain(|p| p.address != address);
                 self.outgoing_pending.retain(|p| p.address != address);
@@ -325,6 +837,32 @@ impl ChatListScreen {
                     self.selected_chat = None;
                 }
             }
+            UiEvent::ContactKeyRotated {
+                address,
+                new_address,
+                ..
+            } => {
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == address) {
+                    c.address = new_address.clone();
+                    c.connected = false;
+                    // Mirrors ChatManager::rotate_contact_key
+                    if c.verified {
+                        c.verified = false;
+                        c.auto_answer = false;
+                        let name = if c.name.is_empty() { &address } else { &c.name };
+                        self.global_error = Some(format!(
+                            "Security key of verified contact {} changed, verify it again",
+                            name
+                        ));
+                    }
+                }
+                if let Some(messages) = self.messages_by_addr.remove(&address) {
+                    self.messages_by_addr.insert(new_address.clone(), messages);
+                }
+                if self.selected_chat.as_ref() == Some(&address) {
+                    self.selected_chat = Some(new_address);
+                }
+            }
 
             UiEvent::ContactConnection { address, connected } => {
                 if let Some(c) = self.contacts.iter_mut().find(|c| c.address == address) {
@@ -337,49 +875,65 @@ impl ChatListScreen {
                 address,
                 incoming,
                 text,
+                verified,
+                reply_to,
+                create_time,
+                clock_skewed,
             } => {
                 let entry = self.messages_by_addr.entry(address.clone()).or_default();
+                let item = MessageItem {
+                    id,
+                    text: text.clone(),
+                    is_mine: !incoming,
+                    status: MessageStatus::Delivered,
+                    verified,
+                    timestamp: format_message_time(create_time),
+                    clock_skewed,
+                    reply_to,
+                };
                 if let Some(pos) = entry.iter_mut().position(|m| m.id == id) {
-                    entry[pos] = MessageItem {
-                        id,
-                        text: text.clone(),
-                        is_mine: !incoming,
-                        delivered: true,
-                        timestamp: "12:34".to_string(),
-                    }
+                    entry[pos] = item;
                 } else {
-                    entry.push(MessageItem {
-                        id,
-                        text: text.clone(),
-                        is_mine: !incoming,
-                        delivered: true,
-                        timestamp: "12:34".to_string(),
-                    });
+                    self.push_message(&address, item);
                 }
+                let selected = self.selected_chat.as_ref() == Some(&address);
                 if let Some(c) = self.contacts.iter_mut().find(|c| c.address == address) {
                     c.last_message = Some(text);
+                    c.last_activity = c.last_activity.max(Some(id));
+                    if incoming && !selected && (!c.archived || self.groups.archived_unread_badges)
+                    {
+                        c.unread += 1;
+                    }
                 }
-                if self
-                    .selected_chat
-                    .as_ref()
-                    .map(|a| a == &address)
-                    .unwrap_or(false)
-                {
+                if selected {
                     self.should_scroll_to_end = true;
                 }
             }
-            UiEvent::MessageSent { id, address, text } => {
+            UiEvent::MessageSent {
+                id,
+                address,
+                text,
+                reply_to,
+                create_time,
+            } => {
                 let entry = self.messages_by_addr.entry(address.clone()).or_default();
                 if entry.iter_mut().position(|m| m.id == id).is_none() {
-                    entry.push(MessageItem {
-                        id: id,
-                        text: text.clone(),
-                        is_mine: true,
-                        delivered: false,
-                        timestamp: "12:34".to_string(),
-                    });
+                    self.push_message(
+                        &address,
+                        MessageItem {
+                            id,
+                            text: text.clone(),
+                            is_mine: true,
+                            status: MessageStatus::Pending,
+                            verified: true,
+                            timestamp: format_message_time(create_time),
+                            clock_skewed: false,
+                            reply_to,
+                        },
+                    );
                     if let Some(c) = self.contacts.iter_mut().find(|c| c.address == address) {
                         c.last_message = Some(text);
+                        c.last_activity = c.last_activity.max(Some(id));
                     }
                 }
                 if self
@@ -394,7 +948,7 @@ impl ChatListScreen {
             UiEvent::MessageDelivered { id, address } => {
                 if let Some(list) = self.messages_by_addr.get_mut(&address) {
                     if let Some(pos) = list.iter_mut().position(|m| m.id == id) {
-                        list[pos].delivered = true;
+                        list[pos].status = MessageStatus::Delivered;
                     }
                 }
                 if self
@@ -406,6 +960,40 @@ impl ChatListScreen {
                     self.should_scroll_to_end = true;
                 }
             }
+            UiEvent::MessageFailed {
+                id,
+                address,
+                text,
+                reply_to,
+                error,
+            } => {
+                let entry = self.messages_by_addr.entry(address.clone()).or_default();
+                let status = MessageStatus::Failed(error);
+                if let Some(item) = entry.iter_mut().find(|m| m.id == id) {
+                    item.status = status;
+                } else {
+                    self.push_message(
+                        &address,
+                        MessageItem {
+                            id,
+                            text,
+                            is_mine: true,
+                            status,
+                            verified: true,
+                            timestamp: format_message_time(DateTime::now()),
+                            clock_skewed: false,
+                            reply_to,
+                        },
+                    );
+                }
+                if self.selected_chat.as_ref() == Some(&address) {
+                    self.should_scroll_to_end = true;
+                }
+            }
+            // Unread counts are updated on NewMessage, the app requests attention
+            UiEvent::MessageNotification { .. } => {}
+            // The app persists the remaining announcements
+            UiEvent::KeyRotationDelivered { .. } => {}
 
             // Call events
             UiEvent::IncomingCall { address } => {
@@ -417,6 +1005,14 @@ impl ChatListScreen {
                     .map(|c| c.name.clone())
                     .unwrap_or_else(|| address.clone());
 
+                // Waiting call is promoted once the current call is over
+                if self
+                    .waiting_call
+                    .as_ref()
+                    .is_some_and(|c| c.address == address)
+                {
+                    self.waiting_call = None;
+                }
                 self.incoming_call = Some(IncomingCallInfo { address, name });
             }
 
@@ -434,13 +1030,16 @@ impl ChatListScreen {
                     address: address.clone(),
                     name,
                     state: CallState::Calling,
+                    codec: None,
+                    quality: None,
+                    one_way: None,
                 });
             }
 
             UiEvent::CallAccepted { address } => {
                 if let Some(call) = &mut self.active_call {
                     if call.address == address {
-                        call.state = CallState::Connected;
+                        call.state = call.state.answered();
                     }
                 }
             }
@@ -473,15 +1072,23 @@ impl ChatListScreen {
                     .unwrap_or(false)
                 {
                     let incoming = self.incoming_call.take().unwrap();
+                    let state = self
+                        .active_call
+                        .as_ref()
+                        .filter(|c| c.address == address)
+                        .map_or(CallState::ConnectingAudio, |c| c.state.answered());
                     self.active_call = Some(CallInfo {
                         address: incoming.address.clone(),
                         name: incoming.name.clone(),
-                        state: CallState::Connected,
+                        state,
+                        codec: None,
+                        quality: None,
+                        one_way: None,
                     });
                 } else if let Some(call) = &mut self.active_call {
                     // Update existing active call to connected
                     if call.address == address {
-                        call.state = CallState::Connected;
+                        call.state = call.state.answered();
                     }
                 }
             }
@@ -494,6 +1101,7 @@ impl ChatListScreen {
                     .unwrap_or(false)
                 {
                     self.active_call = None;
+                    self.device_offer = None;
                     self.speaker_volume = 1.0;
                     self.microphone_volume = 1.0;
                     self.is_muted = false;
@@ -511,11 +1119,154 @@ impl ChatListScreen {
                 }
             }
 
-            UiEvent::CallStateChanged {
-                address: _,
-                state: _,
+            UiEvent::CallStateChanged { address, state } => {
+                if let Some(call) = self.active_call.as_mut().filter(|c| c.address == address) {
+                    match state.as_str() {
+                        "connecting_audio" => call.state = CallState::ConnectingAudio,
+                        "connected" => call.state = CallState::Connected,
+                        _ => {}
+                    }
+                }
+            }
+
+            UiEvent::CallWaiting { address } => {
+                let name = self
+                    .contacts
+                    .iter()
+                    .find(|c| c.address == address)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| address.clone());
+                self.waiting_call = Some(Box::new(WaitingCallInfo {
+                    address,
+                    name,
+                    held: false,
+                }));
+            }
+
+            UiEvent::CallHeld { address } => {
+                if let Some(held) = self.active_call.take_if(|c| c.address == address) {
+                    // The other call takes the place of the held one
+                    if let Some(other) = self.waiting_call.take() {
+                        self.active_call = Some(CallInfo {
+                            address: other.address,
+                            name: other.name,
+                            state: if other.held {
+                                CallState::Connected
+                            } else {
+                                CallState::Ringing
+                            },
+                            codec: None,
+                            quality: None,
+                            one_way: None,
+                        });
+                    }
+                    self.waiting_call = Some(Box::new(WaitingCallInfo {
+                        address: held.address,
+                        name: held.name,
+                        held: true,
+                    }));
+                }
+            }
+
+            UiEvent::CallResumed { address } => {
+                // The audio restarts after the event, its state changes follow
+                if let Some(call) = self.active_call.as_mut().filter(|c| c.address == address) {
+                    call.state = CallState::ConnectingAudio;
+                } else if let Some(held) = self.waiting_call.take_if(|c| c.address == address) {
+                    self.active_call = Some(CallInfo {
+                        address: held.address,
+                        name: held.name,
+                        state: CallState::ConnectingAudio,
+                        codec: None,
+                        quality: None,
+                        one_way: None,
+                    });
+                }
+            }
+
+            UiEvent::CallWaitingEnded { address, reason: _ } => {
+                if self
+                    .waiting_call
+                    .as_ref()
+                    .is_some_and(|c| c.address == address)
+                {
+                    self.waiting_call = None;
+                }
+            }
+            UiEvent::SpeakingWhileMuted { address } => {
+                if self.is_muted
+                    && self
+                        .active_call
+                        .as_ref()
+                        .is_some_and(|c| c.address == address)
+                {
+                    self.muted_nudge = true;
+                }
+            }
+            UiEvent::CallCodec { address, codec } => {
+                if let Some(call) = self.active_call.as_mut().filter(|c| c.address == address) {
+                    call.codec = Some(codec);
+                }
+            }
+            UiEvent::CallQuality { address, quality } => {
+                if let Some(call) = self.active_call.as_mut().filter(|c| c.address == address) {
+                    call.quality = Some(quality);
+                }
+            }
+            UiEvent::OneWayAudioDetected { address, direction } => {
+                if let Some(call) = self.active_call.as_mut().filter(|c| c.address == address) {
+                    call.one_way = Some(direction);
+                }
+            }
+            UiEvent::OneWayAudioResolved { address } => {
+                if let Some(call) = self.active_call.as_mut().filter(|c| c.address == address) {
+                    call.one_way = None;
+                }
+            }
+            UiEvent::DefaultDeviceChanged {
+                address,
+                device_type,
+                action,
+            } => {
+                if self
+                    .active_call
+                    .as_ref()
+                    .is_none_or(|c| c.address != address)
+                {
+                    return;
+                }
+                match action {
+                    DefaultDeviceAction::Switch(name) => {
+                        match device_type {
+                            DeviceType::Input => self.selected_input_device = Some(name),
+                            DeviceType::Output => self.selected_output_device = Some(name),
+                        }
+                        self.device_offer = None;
+                    }
+                    DefaultDeviceAction::Offer(name) => {
+                        self.device_offer = Some((device_type, name));
+                    }
+                }
+            }
+            UiEvent::CallSummary {
+                address,
+                bytes_sent,
+                bytes_received,
             } => {
-                // Handle state changes if needed
+                let name = self
+                    .contacts
+                    .iter()
+                    .find(|c| c.address == address && !c.name.is_empty())
+                    .map(|c| c.name.clone())
+                    .unwrap_or(address);
+                self.call_summary = Some(format!(
+                    "Call with {} ended, {} of data used",
+                    name,
+                    format_bytes(bytes_sent + bytes_received)
+                ));
+            }
+            UiEvent::ContactGroupsLoaded(groups) => {
+                self.groups.groups = groups;
             }
         }
     }
@@ -523,23 +1274,72 @@ impl ChatListScreen {
     fn update_internal(&mut self, message: ChatListMessage) -> Task<ChatListMessage> {
         match message {
             ChatListMessage::SelectChat(addr) => {
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == addr) {
+                    c.unread = 0;
+                }
                 self.selected_chat = Some(addr.clone());
                 self.should_scroll_to_end = true;
                 // Clear message composition when switching chats
                 self.compose_text.clear();
+                self.replying_to = None;
                 // Trigger scroll to bottom
                 scrollable::snap_to(
                     self.messages_scrollable_id.clone(),
                     scrollable::RelativeOffset::END,
                 )
             }
-            ChatListMessage::CopyOwnAddress => clipboard::write(self.own_address.clone()),
+            ChatListMessage::CopyOwnAddress => {
+                let generation = self.address_copied.show();
+                Task::batch([
+                    clipboard::write(self.own_address.clone()),
+                    Task::perform(tokio::time::sleep(CopyConfirmation::DURATION), move |_| {
+                        ChatListMessage::ClearCopyConfirmation(generation)
+                    }),
+                ])
+            }
+            ChatListMessage::ClearCopyConfirmation(generation) => {
+                self.address_copied.clear(generation);
+                Task::none()
+            }
+            ChatListMessage::ShowOwnAddress => {
+                self.show_own_address = true;
+                Task::none()
+            }
+            ChatListMessage::HideOwnAddress => {
+                self.show_own_address = false;
+                Task::none()
+            }
             ChatListMessage::CopyPeerAddress(addr) => clipboard::write(addr),
+            ChatListMessage::CopyMessage(id) => match self.selected_message(id) {
+                Some(msg) => clipboard::write(msg.text.clone()),
+                None => Task::none(),
+            },
+            ChatListMessage::CopyMessageLine(id) => {
+                let Some(msg) = self.selected_message(id) else {
+                    return Task::none();
+                };
+                let name = if msg.is_mine {
+                    self.own_name.as_str()
+                } else {
+                    self.selected_chat
+                        .as_ref()
+                        .and_then(|addr| self.contacts.iter().find(|c| &c.address == addr))
+                        .map(|c| {
+                            if c.name.is_empty() {
+                                &c.address
+                            } else {
+                                &c.name
+                            }
+                        })
+                        .map_or("", String::as_str)
+                };
+                clipboard::write(format_message_line(name, &msg.timestamp, &msg.text))
+            }
             ChatListMessage::AcceptIncoming(addr) => {
                 self.incoming_pending.retain(|p| p.address != addr);
                 Task::none()
             }
-            ChatListMessage::RejectIncoming(addr) => {
+            ChatListMessage::RejectIncoming(addr) | ChatListMessage::BlockIncoming(addr) => {
                 self.incoming_pending.retain(|p| p.address != addr);
                 Task::none()
             }
@@ -550,6 +1350,7 @@ impl ChatListScreen {
             ChatListMessage::ShowAddContactModal => {
                 self.show_add_contact_modal = true;
                 self.add_contact_addr.clear();
+                self.add_contact_intro.clear();
                 self.add_contact_error = None;
                 Task::none()
             }
@@ -563,6 +1364,13 @@ impl ChatListScreen {
                 self.global_error = None;
                 Task::none()
             }
+            ChatListMessage::AddContactIntroChanged(value) => {
+                self.add_contact_intro = value
+                    .chars()
+                    .take(ContactRequestPacket::MAX_INTRO_LEN)
+                    .collect();
+                Task::none()
+            }
             ChatListMessage::AddContactSubmit => {
                 self.add_contact_error = Self::validate_address(&self.add_contact_addr);
                 if self.add_contact_error.is_none() {
@@ -573,6 +1381,7 @@ impl ChatListScreen {
                             .push(PendingOutgoing { address: addr });
                     }
                     self.add_contact_addr.clear();
+                    self.add_contact_intro.clear();
                     self.show_add_contact_modal = false;
                 }
                 Task::none()
@@ -587,6 +1396,7 @@ impl ChatListScreen {
                     if !text.is_empty() {
                         // Clear the compose text first
                         self.compose_text.clear();
+                        self.replying_to = None;
                         // Don't add to local state here - let the event system handle it
                         self.should_scroll_to_end = true;
                         // Parent component should handle actual sending
@@ -604,10 +1414,123 @@ impl ChatListScreen {
                 Task::none()
             }
             ChatListMessage::Logout => Task::none(),
+            // Sent by the update with the app context
+            ChatListMessage::RetryMessage(_) => Task::none(),
             ChatListMessage::ClearError => {
                 self.global_error = None;
                 Task::none()
             }
+            ChatListMessage::CallFailed(reason) => {
+                self.global_error = Some(reason);
+                Task::none()
+            }
+            ChatListMessage::ShowVerifyDialog(_) => Task::none(),
+            ChatListMessage::VerifyDialogLoaded(dialog) => {
+                self.verify_dialog = Some(dialog);
+                Task::none()
+            }
+            ChatListMessage::HideVerifyDialog => {
+                self.verify_dialog = None;
+                Task::none()
+            }
+            ChatListMessage::SetVerified(addr, verified) => {
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == addr) {
+                    c.verified = verified;
+                    c.auto_answer &= verified;
+                }
+                self.verify_dialog = None;
+                Task::none()
+            }
+            ChatListMessage::ReplyTo(id) => {
+                self.replying_to = Some(id);
+                Task::none()
+            }
+            ChatListMessage::CancelReply => {
+                self.replying_to = None;
+                Task::none()
+            }
+            ChatListMessage::ScrollToMessage(id) => {
+                let Some(messages) = self
+                    .selected_chat
+                    .as_ref()
+                    .and_then(|addr| self.messages_by_addr.get(addr))
+                else {
+                    return Task::none();
+                };
+                let Some(pos) = messages.iter().position(|m| m.id == id) else {
+                    return Task::none();
+                };
+                // Messages have different heights, the offset is approximate
+                let y = pos as f32 / messages.len().saturating_sub(1).max(1) as f32;
+                scrollable::snap_to(
+                    self.messages_scrollable_id.clone(),
+                    scrollable::RelativeOffset { x: 0.0, y },
+                )
+            }
+            ChatListMessage::DismissCallSummary => {
+                self.call_summary = None;
+                Task::none()
+            }
+            ChatListMessage::ToggleGroup(name) => {
+                if !self.groups.collapsed.remove(&name) {
+                    self.groups.collapsed.insert(name);
+                }
+                Task::none()
+            }
+            ChatListMessage::AssignGroup(addr, group) => {
+                if let Ok(address) = addr.parse::<ntied_transport::Address>()
+                    && let Err(err) = self.groups.groups.assign(address, &group)
+                {
+                    self.set_error(err.to_string());
+                }
+                Task::none()
+            }
+            ChatListMessage::NewGroupNameChanged(name) => {
+                self.groups.new_group_name = name;
+                Task::none()
+            }
+            ChatListMessage::CreateGroup => {
+                let name = std::mem::take(&mut self.groups.new_group_name);
+                if let Err(err) = self.groups.groups.add_group(&name) {
+                    self.set_error(err.to_string());
+                }
+                Task::none()
+            }
+            ChatListMessage::DeleteGroup(name) => {
+                self.groups.groups.remove_group(&name);
+                self.groups.collapsed.remove(&name);
+                Task::none()
+            }
+            ChatListMessage::ToggleArchived => {
+                self.groups.show_archived = !self.groups.show_archived;
+                Task::none()
+            }
+            ChatListMessage::SetArchived(addr, archived) => {
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == addr) {
+                    c.archived = archived;
+                }
+                Task::none()
+            }
+            ChatListMessage::SetMuted(addr, muted) => {
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == addr) {
+                    c.muted = muted;
+                }
+                Task::none()
+            }
+            ChatListMessage::SetAutoAnswer(addr, auto_answer) => {
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == addr) {
+                    c.auto_answer = auto_answer && c.verified;
+                }
+                Task::none()
+            }
+            // Pin order is assigned by the chat manager, see PinChanged
+            ChatListMessage::SetPinned(..) => Task::none(),
+            ChatListMessage::PinChanged(addr, pin_order) => {
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == addr) {
+                    c.pin_order = pin_order;
+                }
+                Task::none()
+            }
             // Call messages - these will be handled by the parent app
             ChatListMessage::StartVoiceCall(_addr) => {
                 // Don't update UI state here - wait for OutgoingCall event from backend
@@ -621,6 +1544,9 @@ impl ChatListScreen {
                             address: incoming.address.clone(),
                             name: incoming.name.clone(),
                             state: CallState::Ringing, // Keep in Ringing until CallConnected event
+                            codec: None,
+                            quality: None,
+                            one_way: None,
                         });
                         // Don't clear incoming_call yet - wait for CallConnected event
                     }
@@ -657,6 +1583,20 @@ impl ChatListScreen {
                 }
                 Task::none()
             }
+            ChatListMessage::AcceptWaitingCall(_) | ChatListMessage::SwapCalls => {
+                // Wait for CallHeld and CallConnected events from CallManager
+                Task::none()
+            }
+            ChatListMessage::RejectWaitingCall(addr) => {
+                if self
+                    .waiting_call
+                    .as_ref()
+                    .is_some_and(|c| c.address == addr)
+                {
+                    self.waiting_call = None;
+                }
+                Task::none()
+            }
             ChatListMessage::ToggleMute => {
                 // Don't update state here - wait for MuteToggled message from CallManager
                 Task::none()
@@ -664,58 +1604,33 @@ impl ChatListScreen {
             ChatListMessage::MuteToggled(is_muted) => {
                 // Update state based on actual CallManager state
                 self.is_muted = is_muted;
+                self.muted_nudge = false;
                 Task::none()
             }
             ChatListMessage::ShowAudioSettings => {
                 self.show_audio_settings = true;
-                // Load audio devices when opening settings
-                // Keep the currently selected devices if they exist
-                let keep_current_input = self.selected_input_device.clone();
-                let keep_current_output = self.selected_output_device.clone();
-                Task::perform(
-                    async move {
-                        let input_devices = crate::audio::AudioManager::list_input_devices()
-                            .await
-                            .unwrap_or_default()
-                            .into_iter()
-                            .map(|d| (d.name, d.is_default))
-                            .collect::<Vec<_>>();
-                        let output_devices = crate::audio::AudioManager::list_output_devices()
-                            .await
-                            .unwrap_or_default()
-                            .into_iter()
-                            .map(|d| (d.name, d.is_default))
-                            .collect::<Vec<_>>();
-                        (
-                            input_devices,
-                            output_devices,
-                            keep_current_input,
-                            keep_current_output,
-                        )
-                    },
-                    |(input, output, current_input, current_output)| {
-                        ChatListMessage::DevicesLoadedWithCurrent(
-                            input,
-                            output,
-                            current_input,
-                            current_output,
-                        )
-                    },
-                )
+                self.load_audio_devices()
             }
+            ChatListMessage::AudioDevicesChanged => self.load_audio_devices(),
             ChatListMessage::HideAudioSettings => {
                 self.show_audio_settings = false;
                 Task::none()
             }
+            ChatListMessage::DismissDeviceOffer => {
+                self.device_offer = None;
+                Task::none()
+            }
             ChatListMessage::SelectInputDevice(device) => {
                 // Update UI immediately to show selection
                 self.selected_input_device = Some(device.clone());
+                self.device_offer = None;
                 // The actual device switch happens in the parent app layer
                 Task::none()
             }
             ChatListMessage::SelectOutputDevice(device) => {
                 // Update UI immediately to show selection
                 self.selected_output_device = Some(device.clone());
+                self.device_offer = None;
                 // The actual device switch happens in the parent app layer
                 Task::none()
             }
@@ -769,6 +1684,9 @@ impl ChatListScreen {
                     .map(|(name, _)| name.clone())
                     .collect();
 
+                // Devices in use that were unplugged during a call
+                let mut failover = Vec::new();
+
                 // Use the current selection if it exists and is in the list
                 if let Some(current) = current_input {
                     if self.available_input_devices.contains(&current) {
@@ -780,6 +1698,11 @@ impl ChatListScreen {
                             .find(|(_, is_default)| *is_default)
                             .map(|(name, _)| name.clone())
                             .or_else(|| self.available_input_devices.first().cloned());
+                        if let Some(device) = self.selected_input_device.clone()
+                            && self.active_call.is_some()
+                        {
+                            failover.push(Task::done(ChatListMessage::SelectInputDevice(device)));
+                        }
                     }
                 } else {
                     // No current selection, use default
@@ -800,6 +1723,11 @@ impl ChatListScreen {
                             .find(|(_, is_default)| *is_default)
                             .map(|(name, _)| name.clone())
                             .or_else(|| self.available_output_devices.first().cloned());
+                        if let Some(device) = self.selected_output_device.clone()
+                            && self.active_call.is_some()
+                        {
+                            failover.push(Task::done(ChatListMessage::SelectOutputDevice(device)));
+                        }
                     }
                 } else {
                     self.selected_output_device = output_devices
@@ -808,13 +1736,57 @@ impl ChatListScreen {
                         .map(|(name, _)| name.clone())
                         .or_else(|| self.available_output_devices.first().cloned());
                 }
-                Task::none()
+                Task::batch(failover)
             }
             // Handle async operation results
             ChatListMessage::CallOperationComplete(_) => Task::none(),
             ChatListMessage::ContactOperationComplete(_) => Task::none(),
             ChatListMessage::MessageSent(_) => Task::none(),
             ChatListMessage::DeviceSwitchComplete(_) => Task::none(),
+            // The tone is played at the parent level
+            ChatListMessage::PlayTestTone => Task::none(),
+            ChatListMessage::DividerDragStarted => {
+                self.dragging_divider = true;
+                Task::none()
+            }
+            ChatListMessage::DividerDragged(position) => {
+                if self.dragging_divider {
+                    self.set_sidebar_width(position.x);
+                }
+                Task::none()
+            }
+            ChatListMessage::DividerDragEnded => {
+                self.dragging_divider = false;
+                Task::none()
+            }
+            // History paging is started at the parent level, it needs the chat manager
+            ChatListMessage::MessagesScrolled(_) => Task::none(),
+            ChatListMessage::HistoryLoaded {
+                address,
+                messages,
+                has_more,
+            } => {
+                self.history_loading = false;
+                if self.selected_chat.as_deref() == Some(address.as_str()) {
+                    self.history_has_more = has_more;
+                }
+                let entry = self.messages_by_addr.entry(address.clone()).or_default();
+                let older: Vec<_> = messages
+                    .into_iter()
+                    .filter(|message| !entry.iter().any(|m| m.id == message.id))
+                    .map(history_item)
+                    .collect();
+                entry.splice(0..0, older);
+                if let Some(c) = self.contacts.iter_mut().find(|c| c.address == address) {
+                    if c.last_message.is_none() {
+                        c.last_message = entry.last().map(|m| m.text.clone());
+                    }
+                    c.last_activity = c.last_activity.max(entry.last().map(|m| m.id));
+                }
+                // The open chat may grow past the limit while scrolling back
+                self.evict_idle_chats();
+                Task::none()
+            }
             // State synchronization
             ChatListMessage::SyncCallState => {
                 // This message is handled at the parent level (app.rs)
@@ -852,27 +1824,40 @@ impl ChatListScreen {
         let divider = container(Space::with_width(1))
             .height(Length::Fill)
             .style(move |t: &Theme| styles::divider(t));
+        // Wider grab area around the visible line
+        let divider = mouse_area(container(divider).padding([0, 2]))
+            .on_press(ChatListMessage::DividerDragStarted)
+            .interaction(mouse::Interaction::ResizingHorizontally);
+
+        let panels = row![left_panel, divider, right_panel]
+            .spacing(0)
+            .align_y(Alignment::Start);
+        // Track the pointer over the whole screen while the divider is dragged
+        let panels = if self.dragging_divider {
+            mouse_area(panels)
+                .on_move(ChatListMessage::DividerDragged)
+                .on_release(ChatListMessage::DividerDragEnded)
+                .interaction(mouse::Interaction::ResizingHorizontally)
+                .into()
+        } else {
+            Element::from(panels)
+        };
 
-        let main_content = container(
-            row![left_panel, divider, right_panel]
-                .spacing(0)
-                .align_y(Alignment::Start),
-        )
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .style(move |t: &Theme| container::Style {
-            background: Some(iced::Background::Color(colors::background_base(t))),
-            ..Default::default()
-        });
+        let main_content = container(panels)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(move |t: &Theme| container::Style {
+                background: Some(iced::Background::Color(colors::background_base(t))),
+                ..Default::default()
+            });
 
         // Check for active call first (highest priority)
         let main_element: Element<'a, ChatListMessage> = main_content.into();
 
         if let Some(call) = &self.active_call {
             let call_overlay = self.build_active_call_overlay(call.clone(), main_element, theme);
-            // Check if we need to show add contact modal on top of call overlay
-            if self.show_add_contact_modal {
-                let modal = self.build_add_contact_modal(theme);
+            // Check if we need to show a modal on top of call overlay
+            if let Some(modal) = self.build_modal(theme) {
                 return stack![call_overlay, modal].into();
             }
             return call_overlay;
@@ -882,33 +1867,40 @@ impl ChatListScreen {
         if let Some(incoming) = &self.incoming_call {
             let incoming_overlay =
                 self.build_incoming_call_overlay(incoming.clone(), main_element, theme);
-            // Check if we need to show add contact modal on top of incoming call overlay
-            if self.show_add_contact_modal {
-                let modal = self.build_add_contact_modal(theme);
+            // Check if we need to show a modal on top of incoming call overlay
+            if let Some(modal) = self.build_modal(theme) {
                 return stack![incoming_overlay, modal].into();
             }
             return incoming_overlay;
         }
 
         // Use stack to properly layer modal over main content
+        match self.build_modal(theme) {
+            Some(modal) => stack![main_element, modal].into(),
+            None => main_element,
+        }
+    }
+
+    fn build_modal(&self, theme: &Theme) -> Option<Element<'_, ChatListMessage>> {
         if self.show_add_contact_modal {
-            let modal = self.build_add_contact_modal(theme);
-            stack![main_element, modal].into()
-        } else {
-            main_element
+            return Some(self.build_add_contact_modal(theme));
         }
+        if self.show_own_address {
+            return Some(self.build_own_address_dialog(theme));
+        }
+        let dialog = self.verify_dialog.as_ref()?;
+        Some(self.build_verify_dialog(dialog, theme))
     }
 
     fn build_left_panel(&self, theme: &Theme) -> Element<'_, ChatListMessage> {
         // Connection status circle
-        let transport_connected = self.transport_connected;
-        let status_circle = container(Space::new(12, 12)).style(move |t: &Theme| {
-            if transport_connected {
-                styles::status_connected(t)
-            } else {
-                styles::status_disconnected(t)
-            }
-        });
+        let connection_status = self.connection_status;
+        let status_circle =
+            container(Space::new(12, 12)).style(move |t: &Theme| match connection_status {
+                ConnectionStatus::Connected => styles::status_connected(t),
+                ConnectionStatus::Reconnecting => styles::status_reconnecting(t),
+                ConnectionStatus::Disconnected => styles::status_disconnected(t),
+            });
 
         // Account name with truncation
         let name_container = container(
@@ -930,6 +1922,13 @@ impl ChatListScreen {
                 color: Some(icon_color),
             });
 
+        let qr_icon = svg::Svg::new(svg::Handle::from_memory(QR_ICON.as_bytes().to_vec()))
+            .width(Length::Fixed(16.0))
+            .height(Length::Fixed(16.0))
+            .style(move |_theme, _status| svg::Style {
+                color: Some(icon_color),
+            });
+
         let addr_text = container(
             text(&self.own_address)
                 .size(11)
@@ -938,15 +1937,30 @@ impl ChatListScreen {
                 .wrapping(text::Wrapping::None)
                 .width(Length::Shrink),
         )
-        .width(Length::Fixed(240.0))
+        .width(Length::Fixed(216.0))
         .height(Length::Fixed(16.0))
         .clip(true);
 
+        // The whole row copies the address, the icon turns into a confirmation
+        let copy_state: Element<'_, ChatListMessage> = if self.address_copied.is_visible() {
+            text("Copied")
+                .size(11)
+                .color(colors::text_secondary(theme))
+                .into()
+        } else {
+            copy_icon.into()
+        };
+        let copy_row =
+            button(row![addr_text, Space::with_width(4), copy_state].align_y(Alignment::Center))
+                .on_press(ChatListMessage::CopyOwnAddress)
+                .padding(4)
+                .style(move |t: &Theme, status| styles::button_icon(t, status));
+
         let addr_row = row![
-            addr_text,
+            copy_row,
             Space::with_width(4),
-            button(copy_icon)
-                .on_press(ChatListMessage::CopyOwnAddress)
+            button(qr_icon)
+                .on_press(ChatListMessage::ShowOwnAddress)
                 .padding(4)
                 .style(move |t: &Theme, status| styles::button_icon(t, status)),
         ]
@@ -973,10 +1987,10 @@ impl ChatListScreen {
                 .style(move |t: &Theme| styles::divider(t)),
             body
         ]
-        .width(Length::Fixed(320.0))
+        .width(Length::Fixed(self.sidebar_width))
         .spacing(0);
         container(panel)
-            .width(Length::Fixed(320.0))
+            .width(Length::Fixed(self.sidebar_width))
             .height(Length::Fill)
             .style(move |t: &Theme| container::Style {
                 background: Some(iced::Background::Color(colors::background_base(t))),
@@ -1081,6 +2095,33 @@ impl ChatListScreen {
             .into()
     }
 
+    /// Compact codec name with a colored quality dot.
+    fn build_call_quality<'a>(
+        &self,
+        call: &CallInfo,
+        theme: &Theme,
+    ) -> Element<'a, ChatListMessage> {
+        let mut indicator = row![].spacing(4).align_y(Alignment::Center);
+        if let Some(codec) = &call.codec {
+            indicator = indicator.push(
+                text(codec.clone())
+                    .size(11)
+                    .color(colors::text_secondary(theme)),
+            );
+        }
+        if let Some(quality) = call.quality {
+            let color = match quality {
+                CallQuality::Good => colors::text_success(theme),
+                CallQuality::Fair => colors::text_warning(theme),
+                CallQuality::Poor => colors::text_error(theme),
+            };
+            indicator = indicator
+                .push(container(Space::new(8, 8)).style(move |_| styles::indicator_dot(color)))
+                .push(text(quality.label()).size(11).color(color));
+        }
+        indicator.into()
+    }
+
     fn build_active_call_overlay<'a>(
         &self,
         call: CallInfo,
@@ -1102,6 +2143,8 @@ impl ChatListScreen {
 
             CallState::Ringing => ("Ringing...", colors::text_secondary(theme)),
 
+            CallState::ConnectingAudio => ("Connecting audio...", colors::text_secondary(theme)),
+
             CallState::Connected => ("Connected", colors::text_success(theme)),
         };
 
@@ -1116,6 +2159,7 @@ impl ChatListScreen {
             color: Some(icon_color),
         });
 
+        let call_quality = self.build_call_quality(&call, theme);
         let left_block = row![
             phone_icon,
             Space::with_width(8),
@@ -1126,11 +2170,23 @@ impl ChatListScreen {
                     mic_icon,
                     Space::with_width(4),
                     text(call.name).size(16),
+                    Space::with_width(8),
+                    call_quality,
                 ]
                 .align_y(Alignment::Center),
-                text(call.address.clone())
-                    .size(11)
-                    .color(colors::text_secondary(theme)),
+                if self.is_muted && self.muted_nudge {
+                    text("You're muted, unmute to be heard")
+                        .size(11)
+                        .color(colors::text_error(theme))
+                } else if let Some(direction) = call.one_way {
+                    text(direction.hint())
+                        .size(11)
+                        .color(colors::text_warning(theme))
+                } else {
+                    text(call.address.clone())
+                        .size(11)
+                        .color(colors::text_secondary(theme))
+                },
             ]
             .spacing(2)
         ]
@@ -1194,7 +2250,100 @@ impl ChatListScreen {
         .padding(Padding::from([8, 12]))
         .style(move |t: &Theme| styles::panel_header(t));
 
-        let base_content = column![top_bar, background];
+        let waiting_bar: Element<'a, ChatListMessage> = match &self.waiting_call {
+            Some(waiting) => {
+                let (label, actions) = if waiting.held {
+                    (
+                        "On hold",
+                        row![
+                            button(text("Swap").size(12))
+                                .on_press(ChatListMessage::SwapCalls)
+                                .padding(Padding::from([4, 8]))
+                                .style(button::primary),
+                            button(text("End").size(12).color(Color::WHITE))
+                                .on_press(ChatListMessage::HangupCall(waiting.address.clone()))
+                                .padding(Padding::from([4, 8]))
+                                .style(button::danger),
+                        ],
+                    )
+                } else {
+                    (
+                        "Call waiting",
+                        row![
+                            button(text("Hold & Accept").size(12).color(Color::WHITE))
+                                .on_press(ChatListMessage::AcceptWaitingCall(
+                                    waiting.address.clone()
+                                ))
+                                .padding(Padding::from([4, 8]))
+                                .style(button::success),
+                            button(text("Reject").size(12).color(Color::WHITE))
+                                .on_press(ChatListMessage::RejectWaitingCall(
+                                    waiting.address.clone()
+                                ))
+                                .padding(Padding::from([4, 8]))
+                                .style(button::danger),
+                        ],
+                    )
+                };
+                container(
+                    row![
+                        text(label).size(12).color(colors::text_secondary(theme)),
+                        Space::with_width(8),
+                        text(waiting.name.clone()).size(14),
+                        Space::with_width(Length::Fill),
+                        actions.spacing(4),
+                    ]
+                    .align_y(Alignment::Center),
+                )
+                .width(Length::Fill)
+                .padding(Padding::from([6, 12]))
+                .style(move |t: &Theme| styles::panel_header(t))
+                .into()
+            }
+            None => Space::with_height(0).into(),
+        };
+
+        let device_bar: Element<'a, ChatListMessage> = match &self.device_offer {
+            Some((device_type, name)) => {
+                let (label, select) = match device_type {
+                    DeviceType::Input => (
+                        "New default microphone",
+                        ChatListMessage::SelectInputDevice(name.clone()),
+                    ),
+                    DeviceType::Output => (
+                        "New default speaker",
+                        ChatListMessage::SelectOutputDevice(name.clone()),
+                    ),
+                };
+                container(
+                    row![
+                        text(label).size(12).color(colors::text_secondary(theme)),
+                        Space::with_width(8),
+                        text(name.clone()).size(14),
+                        Space::with_width(Length::Fill),
+                        row![
+                            button(text("Switch").size(12))
+                                .on_press(select)
+                                .padding(Padding::from([4, 8]))
+                                .style(button::primary),
+                            button(text("Keep current").size(12))
+                                .on_press(ChatListMessage::DismissDeviceOffer)
+                                .padding(Padding::from([4, 8]))
+                                .style(button::secondary),
+                        ]
+                        .spacing(4),
+                    ]
+                    .align_y(Alignment::Center),
+                )
+                .width(Length::Fill)
+                .padding(Padding::from([6, 12]))
+                .style(move |t: &Theme| styles::panel_header(t))
+                .into()
+            }
+            None => Space::with_height(0).into(),
+        };
+
+        let base_content = column![top_bar, waiting_bar, device_bar, background];
 
         if self.show_audio_settings {
             // Create audio settings panel
@@ -1328,7 +2477,12 @@ impl ChatListScreen {
                         .size(12)
                         .width(Length::Fixed(48.0))
                 ]
-                .align_y(Alignment::Center)
+                .align_y(Alignment::Center),
+                Space::with_height(4),
+                button(text("Play test tone").size(12))
+                    .on_press(ChatListMessage::PlayTestTone)
+                    .padding([4, 8])
+                    .style(button::secondary)
             ]
             .spacing(4);
 
@@ -1392,6 +2546,17 @@ impl ChatListScreen {
                 } else {
                     Element::from(Space::with_height(0))
                 },
+                container(
+                    text("Intro Note (optional)")
+                        .size(14)
+                        .color(colors::text_secondary(theme))
+                )
+                .padding(Padding::ZERO.bottom(4)),
+                text_input("Hi, it's Alice from work", &self.add_contact_intro)
+                    .on_input(ChatListMessage::AddContactIntroChanged)
+                    .on_submit(ChatListMessage::AddContactSubmit)
+                    .padding(10)
+                    .size(14),
                 Space::with_height(16),
                 row![
                     Space::with_width(Length::Fill),
@@ -1434,6 +2599,158 @@ impl ChatListScreen {
         .into()
     }
 
+    fn build_own_address_dialog(&self, theme: &Theme) -> Element<'_, ChatListMessage> {
+        let qr: Element<'_, ChatListMessage> = match &self.own_address_qr {
+            Some(handle) => svg::Svg::new(handle.clone())
+                .width(Length::Fixed(240.0))
+                .height(Length::Fixed(240.0))
+                .into(),
+            None => text("The address is too long for a QR code")
+                .size(13)
+                .color(colors::text_secondary(theme))
+                .into(),
+        };
+        let copy_label = if self.address_copied.is_visible() {
+            "Copied"
+        } else {
+            "Copy"
+        };
+        let modal_dialog = container(
+            column![
+                row![
+                    text("Your Address")
+                        .size(20)
+                        .color(colors::text_primary(theme)),
+                    Space::with_width(Length::Fill),
+                    button(text("×").size(24))
+                        .on_press(ChatListMessage::HideOwnAddress)
+                        .padding(4)
+                        .style(button::text)
+                ]
+                .align_y(Alignment::Center),
+                text("Share the address or let your contact scan the code.")
+                    .size(13)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                container(qr).center_x(Length::Fill),
+                Space::with_height(8),
+                text(&self.own_address)
+                    .size(14)
+                    .font(iced::Font::MONOSPACE)
+                    .color(colors::text_primary(theme)),
+                Space::with_height(8),
+                row![
+                    Space::with_width(Length::Fill),
+                    button(text("Close").size(14))
+                        .on_press(ChatListMessage::HideOwnAddress)
+                        .padding([8, 16])
+                        .style(button::secondary),
+                    button(text(copy_label).size(14))
+                        .on_press(ChatListMessage::CopyOwnAddress)
+                        .padding([8, 16])
+                        .style(button::primary),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8),
+        )
+        .width(Length::Fixed(480.0))
+        .padding(24)
+        .style(move |t: &Theme| styles::card(t));
+
+        container(
+            container(modal_dialog)
+                .center_x(Length::Fill)
+                .center_y(Length::Fill)
+                .width(Length::Fill)
+                .height(Length::Fill),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(move |t: &Theme| styles::modal_overlay(t))
+        .into()
+    }
+
+    fn build_verify_dialog<'a>(
+        &'a self,
+        dialog: &'a VerifyDialog,
+        theme: &Theme,
+    ) -> Element<'a, ChatListMessage> {
+        let verified = self
+            .contacts
+            .iter()
+            .any(|c| c.address == dialog.address && c.verified);
+        let fingerprint = |label, value: &'a str| {
+            column![
+                text(label).size(14).color(colors::text_secondary(theme)),
+                text(value).size(16).font(iced::Font::MONOSPACE),
+            ]
+            .spacing(4)
+        };
+        let mut actions = row![Space::with_width(Length::Fill)].spacing(8);
+        if verified {
+            actions = actions.push(
+                button(text("Remove verification").size(14))
+                    .on_press(ChatListMessage::SetVerified(dialog.address.clone(), false))
+                    .padding([8, 16])
+                    .style(button::secondary),
+            );
+        }
+        actions = actions.push(
+            button(text("Cancel").size(14))
+                .on_press(ChatListMessage::HideVerifyDialog)
+                .padding([8, 16])
+                .style(button::secondary),
+        );
+        if !verified {
+            actions = actions.push(
+                button(text("They match").size(14))
+                    .on_press(ChatListMessage::SetVerified(dialog.address.clone(), true))
+                    .padding([8, 16])
+                    .style(button::primary),
+            );
+        }
+        let modal_dialog = container(
+            column![
+                row![
+                    text("Verify Contact")
+                        .size(20)
+                        .color(colors::text_primary(theme)),
+                    Space::with_width(Length::Fill),
+                    button(text("×").size(24))
+                        .on_press(ChatListMessage::HideVerifyDialog)
+                        .padding(4)
+                        .style(button::text)
+                ]
+                .align_y(Alignment::Center),
+                text("Compare both fingerprints with your contact in person or over a trusted channel.")
+                    .size(13)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                fingerprint("Your fingerprint", &dialog.own_fingerprint),
+                fingerprint("Their fingerprint", &dialog.contact_fingerprint),
+                Space::with_height(8),
+                actions,
+            ]
+            .spacing(8),
+        )
+        .width(Length::Fixed(480.0))
+        .padding(24)
+        .style(move |t: &Theme| styles::card(t));
+
+        container(
+            container(modal_dialog)
+                .center_x(Length::Fill)
+                .center_y(Length::Fill)
+                .width(Length::Fill)
+                .height(Length::Fill),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(move |t: &Theme| styles::modal_overlay(t))
+        .into()
+    }
+
     fn build_incoming_pending(&self, theme: &Theme) -> Element<'_, ChatListMessage> {
         if self.incoming_pending.is_empty() {
             return Space::with_height(0).into();
@@ -1450,13 +2767,26 @@ impl ChatListScreen {
                 .padding(Padding::from([4, 8]))
                 .style(button::danger);
 
-            let row = column![
+            let block_btn = button(text("Block").size(12))
+                .on_press(ChatListMessage::BlockIncoming(p.address.clone()))
+                .padding(Padding::from([4, 8]))
+                .style(button::secondary);
+
+            let name = if p.count > 1 {
+                format!("{} ({} requests)", p.name, p.count)
+            } else {
+                p.name.clone()
+            };
+
+            let mut row = column![
                 row![
-                    text(&p.name).size(14),
+                    text(name).size(14),
                     Space::with_width(Length::Fill),
                     accept_btn,
                     Space::with_width(4),
-                    reject_btn
+                    reject_btn,
+                    Space::with_width(4),
+                    block_btn
                 ]
                 .align_y(Alignment::Center),
                 text(&p.address)
@@ -1465,6 +2795,13 @@ impl ChatListScreen {
                     .color(colors::text_secondary(theme))
             ]
             .spacing(2);
+            if let Some(intro) = &p.intro {
+                row = row.push(
+                    text(format!("\u{201c}{intro}\u{201d}"))
+                        .size(12)
+                        .color(colors::text_primary(theme)),
+                );
+            }
 
             col = col.push(
                 container(row)
@@ -1515,79 +2852,241 @@ impl ChatListScreen {
     }
 
     fn build_contacts_list(&self, theme: &Theme) -> Element<'_, ChatListMessage> {
+        let groups = &self.groups.groups;
+        if groups.groups().is_empty() {
+            let col = column(
+                self.sorted_contacts()
+                    .into_iter()
+                    .filter(|c| !c.archived)
+                    .map(|c| self.build_contact_item(c, theme)),
+            );
+            return column![
+                col.spacing(6),
+                self.build_archived_section(theme),
+                self.build_new_group_row()
+            ]
+            .spacing(10)
+            .into();
+        }
+        let names = groups
+            .groups()
+            .iter()
+            .map(String::as_str)
+            .chain([ContactGroups::DEFAULT]);
         let mut col = column![].spacing(6);
-        for c in &self.contacts {
-            let connected = c.connected;
-            let success_bg = colors::success_bg(theme);
-            let success_border = colors::success_border(theme);
-            let divider_color = colors::divider(theme);
-            let status_circle = container(Space::new(8, 8)).style(move |_t: &Theme| {
-                if connected {
-                    container::Style {
-                        background: Some(iced::Background::Color(success_bg)),
-                        border: iced::Border {
-                            color: success_border,
-                            width: 1.0,
-                            radius: 4.0.into(),
-                        },
-                        ..Default::default()
-                    }
-                } else {
-                    container::Style {
-                        background: Some(iced::Background::Color(Color::TRANSPARENT)),
+        for name in names {
+            let members: Vec<_> = self
+                .sorted_contacts()
+                .into_iter()
+                .filter(|c| !c.archived && self.group_of(&c.address) == name)
+                .collect();
+            let collapsed = self.groups.collapsed.contains(name);
+            let mut header = row![
+                text(if collapsed { ">" } else { "v" })
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                text(name).size(13).color(colors::text_secondary(theme)),
+                text(format!("({})", members.len()))
+                    .size(12)
+                    .color(colors::text_muted(theme)),
+                Space::with_width(Length::Fill),
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center);
+            if name != ContactGroups::DEFAULT {
+                header = header.push(
+                    button(text("Delete").size(11))
+                        .on_press(ChatListMessage::DeleteGroup(name.to_string()))
+                        .padding([2, 6])
+                        .style(button::text),
+                );
+            }
+            col = col.push(
+                button(header)
+                    .on_press(ChatListMessage::ToggleGroup(name.to_string()))
+                    .padding([4, 8])
+                    .width(Length::Fill)
+                    .style(button::text),
+            );
+            if !collapsed {
+                for c in members {
+                    col = col.push(self.build_contact_item(c, theme));
+                }
+            }
+        }
+        column![
+            col,
+            self.build_archived_section(theme),
+            self.build_new_group_row()
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    /// Collapsible section with archived chats, hidden while there are none.
+    fn build_archived_section(&self, theme: &Theme) -> Element<'_, ChatListMessage> {
+        let archived: Vec<_> = self
+            .sorted_contacts()
+            .into_iter()
+            .filter(|c| c.archived)
+            .collect();
+        if archived.is_empty() {
+            return Space::with_height(0).into();
+        }
+        let expanded = self.groups.show_archived;
+        let header = row![
+            text(if expanded { "v" } else { ">" })
+                .size(12)
+                .color(colors::text_secondary(theme)),
+            text("Archived")
+                .size(13)
+                .color(colors::text_secondary(theme)),
+            text(format!("({})", archived.len()))
+                .size(12)
+                .color(colors::text_muted(theme)),
+        ]
+        .spacing(6)
+        .align_y(Alignment::Center);
+        let mut col = column![
+            button(header)
+                .on_press(ChatListMessage::ToggleArchived)
+                .padding([4, 8])
+                .width(Length::Fill)
+                .style(button::text)
+        ]
+        .spacing(6);
+        if expanded {
+            for c in archived {
+                col = col.push(self.build_contact_item(c, theme));
+            }
+        }
+        col.into()
+    }
+
+    fn build_contact_item<'a>(
+        &'a self,
+        c: &'a ContactSummary,
+        theme: &Theme,
+    ) -> Element<'a, ChatListMessage> {
+        let connected = c.connected;
+        let success_bg = colors::success_bg(theme);
+        let success_border = colors::success_border(theme);
+        let divider_color = colors::divider(theme);
+        let status_circle = container(Space::new(8, 8)).style(move |_t: &Theme| {
+            if connected {
+                container::Style {
+                    background: Some(iced::Background::Color(success_bg)),
+                    border: iced::Border {
+                        color: success_border,
+                        width: 1.0,
+                        radius: 4.0.into(),
+                    },
+                    ..Default::default()
+                }
+            } else {
+                container::Style {
+                    background: Some(iced::Background::Color(Color::TRANSPARENT)),
+                    border: iced::Border {
+                        color: divider_color,
+                        width: 1.0,
+                        radius: 4.0.into(),
+                    },
+                    ..Default::default()
+                }
+            }
+        });
+
+        let display_name = if c.name.is_empty() {
+            &c.address
+        } else {
+            &c.name
+        };
+
+        let mut title_row = row![text(display_name).size(14)]
+            .spacing(6)
+            .align_y(Alignment::Center);
+        if let Some(badge) = verification_badge(c.verified, 14.0, theme) {
+            title_row = title_row.push(badge);
+        }
+        title_row = title_row.push(Space::with_width(Length::Fill));
+        if c.unread > 0 {
+            let badge_bg = colors::primary(theme);
+            title_row = title_row.push(
+                container(text(c.unread.to_string()).size(10).color(Color::WHITE))
+                    .padding([1, 6])
+                    .style(move |_t: &Theme| container::Style {
+                        background: Some(iced::Background::Color(badge_bg)),
                         border: iced::Border {
-                            color: divider_color,
-                            width: 1.0,
-                            radius: 4.0.into(),
+                            radius: 8.0.into(),
+                            ..Default::default()
                         },
                         ..Default::default()
-                    }
-                }
-            });
+                    }),
+            );
+        }
+        let mut content = column![title_row.push(status_circle)].spacing(2);
 
-            let display_name = if c.name.is_empty() {
-                &c.address
+        if let Some(last_msg) = &c.last_message {
+            let truncated = if last_msg.chars().count() > 30 {
+                format!("{}...", &last_msg.chars().take(30).collect::<String>())
             } else {
-                &c.name
+                last_msg.clone()
             };
+            content = content.push(text(truncated).size(11).color(colors::text_muted(theme)));
+        }
 
-            let mut content = column![
-                row![
-                    text(display_name).size(14),
-                    Space::with_width(Length::Fill),
-                    status_circle
-                ]
-                .align_y(Alignment::Center)
-            ]
-            .spacing(2);
+        let is_selected = self.selected_chat.as_ref() == Some(&c.address);
+        let addr = c.address.clone();
 
-            if let Some(last_msg) = &c.last_message {
-                let truncated = if last_msg.chars().count() > 30 {
-                    format!("{}...", &last_msg.chars().take(30).collect::<String>())
-                } else {
-                    last_msg.clone()
-                };
-                content = content.push(text(truncated).size(11).color(colors::text_muted(theme)));
-            }
+        let button_style = if is_selected {
+            button::primary
+        } else {
+            button::secondary
+        };
+
+        let content = row![avatar(display_name, c.avatar.as_ref(), 32.0), content]
+            .spacing(8)
+            .align_y(Alignment::Center);
+
+        button(content)
+            .on_press(ChatListMessage::SelectChat(addr))
+            .padding(8)
+            .width(Length::Fill)
+            .style(button_style)
+            .into()
+    }
 
-            let is_selected = self.selected_chat.as_ref() == Some(&c.address);
-            let addr = c.address.clone();
+    fn build_new_group_row(&self) -> Element<'_, ChatListMessage> {
+        let name = &self.groups.new_group_name;
+        row![
+            text_input("New group", name)
+                .on_input(ChatListMessage::NewGroupNameChanged)
+                .on_submit(ChatListMessage::CreateGroup)
+                .size(13)
+                .padding(6),
+            button(text("Add").size(13))
+                .on_press_maybe((!name.trim().is_empty()).then_some(ChatListMessage::CreateGroup))
+                .padding([6, 10])
+                .style(button::secondary),
+        ]
+        .spacing(6)
+        .align_y(Alignment::Center)
+        .into()
+    }
 
-            let button_style = if is_selected {
-                button::primary
-            } else {
-                button::secondary
-            };
+    /// Contacts in chat list order, pinned chats first.
+    fn sorted_contacts(&self) -> Vec<&ContactSummary> {
+        let mut contacts: Vec<_> = self.contacts.iter().collect();
+        contacts.sort_by_key(|c| c.order());
+        contacts
+    }
 
-            col = col.push(
-                button(content)
-                    .on_press(ChatListMessage::SelectChat(addr))
-                    .padding(8)
-                    .width(Length::Fill)
-                    .style(button_style),
-            );
+    /// Group of the contact with the given address string.
+    fn group_of(&self, address: &str) -> &str {
+        match address.parse::<ntied_transport::Address>() {
+            Ok(address) => self.groups.groups.group_of(address),
+            Err(_) => ContactGroups::DEFAULT,
         }
-        col.into()
     }
 
     fn build_right_panel<'a>(&'a self, theme: &'a Theme) -> Element<'a, ChatListMessage> {
@@ -1617,6 +3116,23 @@ impl ChatListScreen {
         .width(Length::Fill)
         .height(Length::Fill);
 
+        if let Some(summary) = &self.call_summary {
+            col = col.push(
+                container(
+                    row![
+                        text(summary).size(13).color(colors::text_secondary(theme)),
+                        Space::with_width(Length::Fill),
+                        button(text("Dismiss").size(12))
+                            .on_press(ChatListMessage::DismissCallSummary)
+                            .padding([4, 8])
+                            .style(button::text),
+                    ]
+                    .align_y(Alignment::Center),
+                )
+                .padding(8)
+                .width(Length::Fill),
+            );
+        }
         if let Some(err) = &self.global_error {
             col = col.push(
                 container(text(err).color(colors::text_error(theme)))
@@ -1631,12 +3147,17 @@ impl ChatListScreen {
     }
 
     fn build_chat_header<'a>(&'a self, theme: &'a Theme) -> Element<'a, ChatListMessage> {
-        let (name, address, connected) = match self.selected_chat.as_ref() {
+        let (name, address, connected, avatar_image) = match self.selected_chat.as_ref() {
             Some(addr) => {
                 if let Some(c) = self.contacts.iter().find(|c| &c.address == addr) {
-                    (c.name.clone(), c.address.clone(), c.connected)
+                    (
+                        c.name.clone(),
+                        c.address.clone(),
+                        c.connected,
+                        c.avatar.as_ref(),
+                    )
                 } else {
-                    ("".to_string(), addr.clone(), false)
+                    ("".to_string(), addr.clone(), false, None)
                 }
             }
             None => return Space::with_height(0).into(),
@@ -1647,6 +3168,7 @@ impl ChatListScreen {
         } else {
             name
         };
+        let avatar_view = avatar(&display_name, avatar_image, 40.0);
 
         let is_connected = connected;
         let success_bg = colors::success_bg(theme);
@@ -1692,10 +3214,100 @@ impl ChatListScreen {
             color: Some(icon_color),
         });
 
-        let mut title_row_items = vec![
-            text(display_name).size(18).into(),
-            Space::with_width(Length::Fill).into(),
-        ];
+        let verified = self
+            .contacts
+            .iter()
+            .find(|c| c.address == address)
+            .is_some_and(|c| c.verified);
+        let mut title_row_items = vec![text(display_name).size(18).into()];
+        if let Some(badge) = verification_badge(verified, 18.0, theme) {
+            title_row_items.push(Space::with_width(6).into());
+            title_row_items.push(badge);
+        }
+        title_row_items.push(Space::with_width(Length::Fill).into());
+        title_row_items.push(
+            button(text(if verified { "Verified" } else { "Verify" }).size(12))
+                .on_press(ChatListMessage::ShowVerifyDialog(address.clone()))
+                .padding([4, 8])
+                .style(button::text)
+                .into(),
+        );
+
+        let pinned = self
+            .contacts
+            .iter()
+            .any(|c| c.address == address && c.pin_order.is_some());
+        title_row_items.push(
+            button(text(if pinned { "Unpin" } else { "Pin" }).size(12))
+                .on_press(ChatListMessage::SetPinned(address.clone(), !pinned))
+                .padding([4, 8])
+                .style(button::text)
+                .into(),
+        );
+        let archived = self
+            .contacts
+            .iter()
+            .any(|c| c.address == address && c.archived);
+        title_row_items.push(
+            button(text(if archived { "Unarchive" } else { "Archive" }).size(12))
+                .on_press(ChatListMessage::SetArchived(address.clone(), !archived))
+                .padding([4, 8])
+                .style(button::text)
+                .into(),
+        );
+        let muted = self
+            .contacts
+            .iter()
+            .any(|c| c.address == address && c.muted);
+        title_row_items.push(
+            button(text(if muted { "Unmute" } else { "Mute" }).size(12))
+                .on_press(ChatListMessage::SetMuted(address.clone(), !muted))
+                .padding([4, 8])
+                .style(button::text)
+                .into(),
+        );
+        // Only verified contacts can be auto-answered
+        if let Some(c) = self
+            .contacts
+            .iter()
+            .find(|c| c.address == address && c.verified)
+        {
+            let label = if c.auto_answer {
+                "Auto-answer: on"
+            } else {
+                "Auto-answer: off"
+            };
+            title_row_items.push(
+                button(text(label).size(12))
+                    .on_press(ChatListMessage::SetAutoAnswer(
+                        address.clone(),
+                        !c.auto_answer,
+                    ))
+                    .padding([4, 8])
+                    .style(button::text)
+                    .into(),
+            );
+        }
+        title_row_items.push(Space::with_width(12).into());
+
+        // Move the contact between groups once any group exists
+        let groups = &self.groups.groups;
+        if !groups.groups().is_empty() {
+            let options: Vec<String> = std::iter::once(ContactGroups::DEFAULT.to_string())
+                .chain(groups.groups().iter().cloned())
+                .collect();
+            let current = self.group_of(&address).to_string();
+            let addr = address.clone();
+            title_row_items.push(
+                pick_list(options, Some(current), move |group| {
+                    ChatListMessage::AssignGroup(addr.clone(), group)
+                })
+                .text_size(12)
+                .padding([4, 8])
+                .into(),
+            );
+            title_row_items.push(Space::with_width(12).into());
+        }
 
         // Add call button only if connected
         if connected {
@@ -1751,7 +3363,9 @@ impl ChatListScreen {
         .align_y(Alignment::Center)
         .spacing(0);
 
-        let header_content = column![title_row, addr_row].spacing(4);
+        let header_content = row![avatar_view, column![title_row, addr_row].spacing(4)]
+            .spacing(12)
+            .align_y(Alignment::Center);
 
         container(header_content)
             .width(Length::Fill)
@@ -1768,16 +3382,67 @@ impl ChatListScreen {
 
         let mut col = column![].spacing(10);
 
-        for msg in msgs {
+        for msg in msgs.iter().cloned() {
             let is_mine = msg.is_mine;
-            let delivered = msg.delivered;
-            let bubble_content = column![
-                text(msg.text).size(14).color(colors::text_primary(theme)),
+            let status = msg.status.clone();
+            let mut footer = row![
                 text(msg.timestamp)
                     .size(10)
                     .color(colors::text_muted(theme))
             ]
-            .spacing(4);
+            .spacing(6);
+            if !msg.verified {
+                footer = footer.push(
+                    text("Unverified message")
+                        .size(10)
+                        .color(colors::text_error(theme)),
+                );
+            }
+            if msg.clock_skewed {
+                footer = footer.push(
+                    text("Sender clock is off")
+                        .size(10)
+                        .color(colors::text_warning(theme)),
+                );
+            }
+            if let MessageStatus::Failed(error) = &msg.status {
+                footer = footer.push(
+                    text(format!("Not sent: {error}"))
+                        .size(10)
+                        .color(colors::text_error(theme)),
+                );
+                footer = footer.push(
+                    button(text("Retry").size(10))
+                        .on_press(ChatListMessage::RetryMessage(msg.id))
+                        .padding(0)
+                        .style(button::text),
+                );
+            }
+            let footer = row![
+                footer,
+                Space::with_width(Length::Fill),
+                button(text("Copy").size(10))
+                    .on_press(ChatListMessage::CopyMessage(msg.id))
+                    .padding(0)
+                    .style(button::text),
+                button(text("Copy line").size(10))
+                    .on_press(ChatListMessage::CopyMessageLine(msg.id))
+                    .padding(0)
+                    .style(button::text),
+                button(text("Reply").size(10))
+                    .on_press(ChatListMessage::ReplyTo(msg.id))
+                    .padding(0)
+                    .style(button::text),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center);
+            let mut bubble_content = column![].spacing(4);
+            if let Some(reply_to) = msg.reply_to {
+                bubble_content = bubble_content.push(self.build_quote(&msgs, reply_to, theme));
+            }
+            let bubble_content = bubble_content
+                .push(message_text(&msg.text, 14, theme))
+                .push(footer);
 
             let bubble =
                 container(bubble_content)
@@ -1785,18 +3450,19 @@ impl ChatListScreen {
                     .max_width(400)
                     .style(move |t: &Theme| {
                         let (bg_color, border_color) = if is_mine {
-                            if delivered {
-                                // Delivered outgoing
-                                (
+                            match status {
+                                MessageStatus::Delivered => (
                                     colors::message_outgoing_bg(t),
                                     colors::message_outgoing_border(t),
-                                )
-                            } else {
-                                // Pending outgoing
-                                (
+                                ),
+                                MessageStatus::Pending => (
                                     colors::message_pending_bg(t),
                                     colors::message_pending_border(t),
-                                )
+                                ),
+                                MessageStatus::Failed(_) => (
+                                    colors::message_failed_bg(t),
+                                    colors::message_failed_border(t),
+                                ),
                             }
                         } else {
                             // Incoming
@@ -1836,7 +3502,8 @@ impl ChatListScreen {
 
         let sc = scrollable(col.padding(16))
             .height(Length::Fill)
-            .id(self.messages_scrollable_id.clone());
+            .id(self.messages_scrollable_id.clone())
+            .on_scroll(|viewport| ChatListMessage::MessagesScrolled(viewport.relative_offset().y));
 
         container(sc)
             .height(Length::Fill)
@@ -1874,19 +3541,140 @@ impl ChatListScreen {
             .width(Length::Fill)
             .on_submit(ChatListMessage::SendMessage);
 
-        container(
-            row![input, Space::with_width(8), send_btn]
-                .align_y(Alignment::Center)
-                .padding(12),
-        )
-        .width(Length::Fill)
-        .style(move |t: &Theme| container::Style {
-            background: Some(iced::Background::Color(colors::background_weak(t))),
+        let mut composer = column![].padding(12).spacing(8);
+        if let Some(reply_to) = self.replying_to {
+            let msgs = self
+                .selected_chat
+                .as_ref()
+                .and_then(|addr| self.messages_by_addr.get(addr))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            composer = composer.push(
+                row![
+                    self.build_quote(msgs, reply_to, theme),
+                    Space::with_width(Length::Fill),
+                    button(text("Cancel").size(11))
+                        .on_press(ChatListMessage::CancelReply)
+                        .padding([2, 6])
+                        .style(button::text),
+                ]
+                .align_y(Alignment::Center),
+            );
+        }
+        composer =
+            composer.push(row![input, Space::with_width(8), send_btn].align_y(Alignment::Center));
+
+        container(composer)
+            .width(Length::Fill)
+            .style(move |t: &Theme| container::Style {
+                background: Some(iced::Background::Color(colors::background_weak(t))),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    /// Message of the selected chat with the given id.
+    fn selected_message(&self, id: i64) -> Option<&MessageItem> {
+        let addr = self.selected_chat.as_ref()?;
+        self.messages_by_addr.get(addr)?.iter().find(|m| m.id == id)
+    }
+
+    /// Compact quote of a replied message, clicking it scrolls to the original.
+    fn build_quote<'a>(
+        &self,
+        msgs: &[MessageItem],
+        reply_to: i64,
+        theme: &Theme,
+    ) -> Element<'a, ChatListMessage> {
+        let accent = colors::primary(theme);
+        let bar = container(Space::new(2, 14)).style(move |_t: &Theme| container::Style {
+            background: Some(iced::Background::Color(accent)),
             ..Default::default()
-        })
+        });
+        // Deleted messages and ones not loaded yet cannot be shown
+        let Some(original) = msgs.iter().find(|m| m.id == reply_to) else {
+            return row![
+                bar,
+                text("Message unavailable")
+                    .size(11)
+                    .color(colors::text_muted(theme))
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center)
+            .into();
+        };
+        let snippet = if original.text.chars().count() > 40 {
+            format!("{}...", original.text.chars().take(40).collect::<String>())
+        } else {
+            original.text.clone()
+        };
+        button(
+            row![
+                bar,
+                text(snippet).size(11).color(colors::text_secondary(theme))
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center),
+        )
+        .on_press(ChatListMessage::ScrollToMessage(reply_to))
+        .padding(0)
+        .style(button::text)
         .into()
     }
 
+    fn save_groups(
+        storage: Option<Arc<TokioMutex<Storage>>>,
+        groups: ContactGroups,
+    ) -> Task<ChatListMessage> {
+        Task::perform(
+            async move {
+                if let Some(storage) = storage
+                    && let Err(err) = ConfigManager::new(storage)
+                        .set_contact_groups(&groups)
+                        .await
+                {
+                    tracing::error!(?err, "Failed to save contact groups");
+                }
+                ChatListMessage::Noop
+            },
+            |msg| msg,
+        )
+    }
+
+    fn load_history_page(
+        chats: Option<Arc<ChatManager>>,
+        address: String,
+        before_id: Option<i64>,
+    ) -> Task<ChatListMessage> {
+        Task::perform(
+            async move {
+                let mut messages = Vec::new();
+                let mut has_more = false;
+                if let Some(chats) = chats
+                    && let Ok(addr) = address.parse::<ntied_transport::Address>()
+                    && let Some(handle) = chats.get_contact_chat(addr).await
+                {
+                    match handle
+                        .load_history_before(before_id, HISTORY_PAGE_SIZE)
+                        .await
+                    {
+                        Ok(page) => {
+                            messages = page.messages;
+                            has_more = page.has_more;
+                        }
+                        Err(err) => tracing::warn!(?err, "Failed to load chat history"),
+                    }
+                }
+                ChatListMessage::HistoryLoaded {
+                    address,
+                    messages,
+                    has_more,
+                }
+            },
+            |msg| msg,
+        )
+    }
+
     fn validate_address(s: &str) -> Option<String> {
         let trimmed = s.trim();
         if trimmed.is_empty() {
@@ -1926,13 +3714,15 @@ impl Screen for ChatListScreen {
                 // Handle add contact with async operation
                 let addr_str = ctx.pending_add_addr.clone().unwrap_or_default();
                 if !addr_str.is_empty() {
+                    let intro = self.add_contact_intro.clone();
                     let cm = ctx.contact_manager.clone();
                     let ui_tx = ctx.ui_event_tx.clone();
                     let add_contact_cmd = Task::perform(
                         async move {
                             if let Ok(address) = addr_str.parse::<ntied_transport::Address>() {
                                 if let Some(cm) = cm {
-                                    let _ = cm.connect_contact(address).await;
+                                    let _ =
+                                        cm.connect_contact_with_intro(address, Some(&intro)).await;
                                 }
                                 let _ = ui_tx
                                     .send(crate::ui::UiEvent::OutgoingRequest {
@@ -1965,8 +3755,9 @@ impl Screen for ChatListScreen {
                             {
                                 let handle = cm.connect_contact(address).await;
                                 let _ = handle.accept().await;
-                                let name = handle
-                                    .profile()
+                                let profile = handle.profile();
+                                let avatar = profile.as_ref().and_then(|p| p.avatar.clone());
+                                let name = profile
                                     .map(|p| p.name)
                                     .unwrap_or_else(|| address.to_string());
                                 if let Some(pk) = handle.public_key() {
@@ -1978,6 +3769,12 @@ impl Screen for ChatListScreen {
                                     .send(crate::ui::UiEvent::ContactAccepted {
                                         address: addr_str_async.clone(),
                                         name,
+                                        avatar: avatar.map(|a| a.0),
+                                        archived: false,
+                                        pin_order: None,
+                                        verified: false,
+                                        muted: false,
+                                        auto_answer: false,
                                     })
                                     .await;
                             }
@@ -2019,6 +3816,37 @@ impl Screen for ChatListScreen {
                     self.update_internal(ChatListMessage::RejectIncoming(addr_str.clone()));
                 return ScreenCommand::Message(Task::batch(vec![ui_cmd, reject_cmd]));
             }
+            ChatListMessage::BlockIncoming(ref addr_str) => {
+                let cm = ctx.contact_manager.clone();
+                let storage = ctx.storage.clone();
+                let ui_tx = ctx.ui_event_tx.clone();
+                let addr_str_async = addr_str.clone();
+                let block_cmd = Task::perform(
+                    async move {
+                        if let Some(cm) = cm
+                            && let Ok(address) = addr_str_async.parse::<ntied_transport::Address>()
+                        {
+                            cm.block_address(address).await;
+                            if let Some(storage) = storage
+                                && let Err(err) = ConfigManager::new(storage)
+                                    .set_blocked_addresses(&cm.blocked_addresses())
+                                    .await
+                            {
+                                tracing::warn!(?err, "Failed to save blocked addresses");
+                            }
+                            let _ = ui_tx
+                                .send(crate::ui::UiEvent::ContactRemoved {
+                                    address: addr_str_async.clone(),
+                                })
+                                .await;
+                        }
+                        ChatListMessage::Noop
+                    },
+                    |msg| msg,
+                );
+                let ui_cmd = self.update_internal(ChatListMessage::BlockIncoming(addr_str.clone()));
+                ScreenCommand::Message(Task::batch(vec![ui_cmd, block_cmd]))
+            }
             ChatListMessage::CancelOutgoing(ref addr_str) => {
                 // Handle cancel outgoing with async operation
                 let cm = ctx.contact_manager.clone();
@@ -2030,8 +3858,7 @@ impl Screen for ChatListScreen {
                         if let Some(cm) = cm {
                             if let Ok(address) = addr_str_async.parse::<ntied_transport::Address>()
                             {
-                                let handle = cm.connect_contact(address).await;
-                                let _ = handle.reject().await;
+                                cm.cancel_connect(address).await;
                                 let _ = ui_tx
                                     .send(crate::ui::UiEvent::ContactRemoved {
                                         address: addr_str_async.clone(),
@@ -2057,7 +3884,9 @@ impl Screen for ChatListScreen {
                     async move {
                         if let Some(mgr) = call_mgr {
                             if let Ok(addr) = address.parse::<ntied_transport::Address>() {
-                                let _ = mgr.start_call(addr).await;
+                                if let Err(err) = mgr.start_call(addr).await {
+                                    return ChatListMessage::CallFailed(err.to_string());
+                                }
                             }
                         }
                         ChatListMessage::Noop
@@ -2105,6 +3934,47 @@ impl Screen for ChatListScreen {
 
                 return ScreenCommand::Message(call_cmd);
             }
+            ChatListMessage::AcceptWaitingCall(ref address)
+            | ChatListMessage::RejectWaitingCall(ref address) => {
+                let call_mgr = ctx.call_manager.clone();
+                let accept = matches!(message, ChatListMessage::AcceptWaitingCall(_));
+                let address = address.clone();
+                let call_cmd = Task::perform(
+                    async move {
+                        if let Some(mgr) = call_mgr
+                            && let Ok(addr) = address.parse::<ntied_transport::Address>()
+                        {
+                            let result = if accept {
+                                mgr.accept_waiting_call(addr).await
+                            } else {
+                                mgr.reject_waiting_call(addr).await
+                            };
+                            if let Err(err) = result {
+                                tracing::warn!(?err, "Waiting call operation failed");
+                            }
+                        }
+                        ChatListMessage::Noop
+                    },
+                    |msg| msg,
+                );
+                let ui_cmd = self.update_internal(message);
+                ScreenCommand::Message(Task::batch(vec![ui_cmd, call_cmd]))
+            }
+            ChatListMessage::SwapCalls => {
+                let call_mgr = ctx.call_manager.clone();
+                let call_cmd = Task::perform(
+                    async move {
+                        if let Some(mgr) = call_mgr
+                            && let Err(err) = mgr.swap_calls().await
+                        {
+                            tracing::warn!(?err, "Failed to swap calls");
+                        }
+                        ChatListMessage::Noop
+                    },
+                    |msg| msg,
+                );
+                ScreenCommand::Message(call_cmd)
+            }
             ChatListMessage::HangupCall(ref address) => {
                 // Handle hangup call with async operation
                 let call_mgr = ctx.call_manager.clone();
@@ -2183,6 +4053,30 @@ impl Screen for ChatListScreen {
                     self.update_internal(ChatListMessage::SelectOutputDevice(device_name.clone()));
                 return ScreenCommand::Message(Task::batch(vec![ui_cmd, switch_cmd]));
             }
+            ChatListMessage::DividerDragged(_) => {
+                let cmd = self.update_internal(message);
+                ctx.window_geometry.sidebar_width = self.sidebar_width;
+                ScreenCommand::Message(cmd)
+            }
+            ChatListMessage::PlayTestTone => {
+                let device = self.selected_output_device.clone();
+                ScreenCommand::Message(Task::perform(
+                    async move {
+                        if let Err(err) = crate::audio::AudioManager::play_test_tone(
+                            device,
+                            TEST_TONE_FREQ,
+                            TEST_TONE_DURATION,
+                            TEST_TONE_VOLUME,
+                        )
+                        .await
+                        {
+                            tracing::error!(?err, "Failed to play test tone");
+                        }
+                        ChatListMessage::Noop
+                    },
+                    |msg| msg,
+                ))
+            }
             ChatListMessage::SpeakerVolumeChanged(volume) => {
                 // Handle speaker volume change with async operation
                 let call_mgr = ctx.call_manager.clone();
@@ -2224,77 +4118,37 @@ impl Screen for ChatListScreen {
 
                 // Load chat history
                 let chats = ctx.chat_manager.clone();
-                let ui_tx = ctx.ui_event_tx.clone();
                 let addr_str = addr.clone();
 
-                let load_history = Task::perform(
-                    async move {
-                        if let Some(chats) = chats {
-                            if let Ok(address) = addr_str.parse::<ntied_transport::Address>() {
-                                if let Some(handle) = chats.get_contact_chat(address).await {
-                                    let limit = 200usize;
-                                    if let Ok(messages) = handle.load_history(limit).await {
-                                        for m in messages {
-                                            let text = match m.kind {
-                                                crate::models::MessageKind::Text(s) => s,
-                                            };
-                                            if m.log_id.is_some() {
-                                                if m.incoming {
-                                                    let _ = ui_tx
-                                                        .send(crate::ui::UiEvent::NewMessage {
-                                                            id: m.id,
-                                                            address: addr_str.clone(),
-                                                            incoming: true,
-                                                            text,
-                                                        })
-                                                        .await;
-                                                } else {
-                                                    // Outgoing confirmed: ensure delivered bubble
-                                                    let _ = ui_tx
-                                                        .send(crate::ui::UiEvent::NewMessage {
-                                                            id: m.id,
-                                                            address: addr_str.clone(),
-                                                            incoming: false,
-                                                            text,
-                                                        })
-                                                        .await;
-                                                }
-                                            } else {
-                                                if m.incoming {
-                                                    // Unexpected but safe: treat as delivered incoming
-                                                    let _ = ui_tx
-                                                        .send(crate::ui::UiEvent::NewMessage {
-                                                            id: m.id,
-                                                            address: addr_str.clone(),
-                                                            incoming: true,
-                                                            text,
-                                                        })
-                                                        .await;
-                                                } else {
-                                                    // Our pending outgoing (undelivered)
-                                                    let _ = ui_tx
-                                                        .send(crate::ui::UiEvent::MessageSent {
-                                                            id: m.id,
-                                                            address: addr_str.clone(),
-                                                            text,
-                                                        })
-                                                        .await;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        ChatListMessage::Noop
-                    },
-                    |msg| msg,
-                );
+                self.history_has_more = false;
+                self.history_loading = true;
+                let load_history = Self::load_history_page(chats, addr_str, None);
 
                 // Call the internal update method and combine with history loading
                 let scroll_cmd = self.update_internal(ChatListMessage::SelectChat(addr.clone()));
                 return ScreenCommand::Message(Task::batch(vec![scroll_cmd, load_history]));
             }
+            ChatListMessage::MessagesScrolled(offset_y) => {
+                // Load older messages when the list is scrolled to the top
+                let Some(addr) = self.selected_chat.clone() else {
+                    return ScreenCommand::None;
+                };
+                if offset_y > 0.0 || !self.history_has_more || self.history_loading {
+                    return ScreenCommand::None;
+                }
+                let before_id = self
+                    .messages_by_addr
+                    .get(&addr)
+                    .and_then(|list| list.first())
+                    .map(|m| m.id);
+                if before_id.is_none() {
+                    return ScreenCommand::None;
+                }
+                self.history_loading = true;
+                let load_history =
+                    Self::load_history_page(ctx.chat_manager.clone(), addr, before_id);
+                ScreenCommand::Message(load_history)
+            }
             ChatListMessage::SendMessage => {
                 // Handle message sending with async operation
                 let chats = ctx.chat_manager.clone();
@@ -2307,38 +4161,14 @@ impl Screen for ChatListScreen {
                     }
                 });
                 let ui_tx = ctx.ui_event_tx.clone();
+                let reply_to = self.replying_to;
 
                 if let (Some(addr_str), Some(text)) = (maybe_addr, maybe_text) {
                     let trimmed = text.trim().to_string();
                     if !trimmed.is_empty() {
-                        let send_cmd = Task::perform(
-                            async move {
-                                if let Some(chats) = chats {
-                                    if let Ok(address) =
-                                        addr_str.parse::<ntied_transport::Address>()
-                                    {
-                                        if let Some(handle) = chats.get_contact_chat(address).await
-                                        {
-                                            if let Ok(message) = handle
-                                                .send_message(crate::models::MessageKind::Text(
-                                                    trimmed.clone(),
-                                                ))
-                                                .await
-                                            {
-                                                let _ = ui_tx
-                                                    .send(crate::ui::UiEvent::MessageSent {
-                                                        id: message.id,
-                                                        address: addr_str.clone(),
-                                                        text: trimmed,
-                                                    })
-                                                    .await;
-                                            }
-                                        }
-                                    }
-                                }
-                                ChatListMessage::Noop
-                            },
-                            |msg| msg,
+                        let failed_id = self.next_failed_id();
+                        let send_cmd = Self::send_text_task(
+                            chats, ui_tx, addr_str, trimmed, reply_to, failed_id,
                         );
 
                         // Clear compose text and update UI
@@ -2352,6 +4182,23 @@ impl Screen for ChatListScreen {
                 let cmd = self.update_internal(ChatListMessage::SendMessage);
                 return ScreenCommand::Message(cmd);
             }
+            ChatListMessage::RetryMessage(id) => {
+                let Some(address) = self.selected_chat.clone() else {
+                    return ScreenCommand::None;
+                };
+                let Some((text, reply_to)) = self.take_failed_message(&address, id) else {
+                    return ScreenCommand::None;
+                };
+                // Failure brings the bubble back under the same id
+                ScreenCommand::Message(Self::send_text_task(
+                    ctx.chat_manager.clone(),
+                    ctx.ui_event_tx.clone(),
+                    address,
+                    text,
+                    reply_to,
+                    id,
+                ))
+            }
             ChatListMessage::OpenSettings => {
                 let server_addr = ctx
                     .server_addr
@@ -2363,8 +4210,137 @@ impl Screen for ChatListScreen {
                 ctx.contact_manager = None;
                 ctx.chat_manager = None;
                 ctx.storage = None;
+                ctx.server_connected = false;
                 return ScreenCommand::ChangeScreen(ScreenType::Unlock);
             }
+            ChatListMessage::AssignGroup(..)
+            | ChatListMessage::CreateGroup
+            | ChatListMessage::DeleteGroup(_) => {
+                let ui_cmd = self.update_internal(message);
+                let save_cmd = Self::save_groups(ctx.storage.clone(), self.groups.groups.clone());
+                ScreenCommand::Message(Task::batch(vec![ui_cmd, save_cmd]))
+            }
+            ChatListMessage::SetArchived(ref addr, archived) => {
+                let chats = ctx.chat_manager.clone();
+                let address = addr.parse::<ntied_transport::Address>();
+                let archive_cmd = Task::perform(
+                    async move {
+                        if let (Some(chats), Ok(address)) = (chats, address)
+                            && let Err(err) = chats.set_archived(address, archived).await
+                        {
+                            tracing::error!(?err, "Failed to update archived flag");
+                        }
+                        ChatListMessage::Noop
+                    },
+                    |msg| msg,
+                );
+                let ui_cmd = self.update_internal(message);
+                ScreenCommand::Message(Task::batch(vec![ui_cmd, archive_cmd]))
+            }
+            ChatListMessage::SetMuted(ref addr, muted) => {
+                let chats = ctx.chat_manager.clone();
+                let address = addr.parse::<ntied_transport::Address>();
+                let mute_cmd = Task::perform(
+                    async move {
+                        if let (Some(chats), Ok(address)) = (chats, address)
+                            && let Err(err) = chats.set_muted(address, muted).await
+                        {
+                            tracing::error!(?err, "Failed to update muted flag");
+                        }
+                        ChatListMessage::Noop
+                    },
+                    |msg| msg,
+                );
+                let ui_cmd = self.update_internal(message);
+                ScreenCommand::Message(Task::batch(vec![ui_cmd, mute_cmd]))
+            }
+            ChatListMessage::SetAutoAnswer(ref addr, auto_answer) => {
+                let chats = ctx.chat_manager.clone();
+                let calls = ctx.call_manager.clone();
+                let address = addr.parse::<ntied_transport::Address>();
+                let auto_answer_cmd = Task::perform(
+                    async move {
+                        if let (Some(chats), Ok(address)) = (chats, address) {
+                            match chats.set_auto_answer(address, auto_answer).await {
+                                Ok(()) => {
+                                    if let Some(calls) = calls {
+                                        calls.set_auto_answer(address, auto_answer);
+                                    }
+                                }
+                                Err(err) => {
+                                    tracing::error!(?err, "Failed to update auto-answer flag");
+                                }
+                            }
+                        }
+                        ChatListMessage::Noop
+                    },
+                    |msg| msg,
+                );
+                let ui_cmd = self.update_internal(message);
+                ScreenCommand::Message(Task::batch(vec![ui_cmd, auto_answer_cmd]))
+            }
+            ChatListMessage::ShowVerifyDialog(addr) => {
+                let chats = ctx.chat_manager.clone();
+                ScreenCommand::Message(Task::perform(
+                    async move {
+                        let Some(chats) = chats else {
+                            return ChatListMessage::Noop;
+                        };
+                        let Ok(address) = addr.parse() else {
+                            return ChatListMessage::Noop;
+                        };
+                        match chats.get_contact_chat(address).await {
+                            Some(handle) => {
+                                ChatListMessage::VerifyDialogLoaded(Box::new(VerifyDialog {
+                                    address: addr,
+                                    own_fingerprint: chats.own_fingerprint(),
+                                    contact_fingerprint: handle.fingerprint(),
+                                }))
+                            }
+                            None => ChatListMessage::Noop,
+                        }
+                    },
+                    |msg| msg,
+                ))
+            }
+            ChatListMessage::SetVerified(ref addr, verified) => {
+                let chats = ctx.chat_manager.clone();
+                let address = addr.parse::<ntied_transport::Address>();
+                if !verified && let (Some(calls), Ok(address)) = (&ctx.call_manager, &address) {
+                    calls.set_auto_answer(*address, false);
+                }
+                let verify_cmd = Task::perform(
+                    async move {
+                        if let (Some(chats), Ok(address)) = (chats, address)
+                            && let Err(err) = chats.set_verified(address, verified).await
+                        {
+                            tracing::error!(?err, "Failed to update verified flag");
+                        }
+                        ChatListMessage::Noop
+                    },
+                    |msg| msg,
+                );
+                let ui_cmd = self.update_internal(message);
+                ScreenCommand::Message(Task::batch(vec![ui_cmd, verify_cmd]))
+            }
+            ChatListMessage::SetPinned(addr, pinned) => {
+                let chats = ctx.chat_manager.clone();
+                ScreenCommand::Message(Task::perform(
+                    async move {
+                        let (Some(chats), Ok(address)) = (chats, addr.parse()) else {
+                            return ChatListMessage::Noop;
+                        };
+                        match chats.set_pinned(address, pinned).await {
+                            Ok(pin_order) => ChatListMessage::PinChanged(addr, pin_order),
+                            Err(err) => {
+                                tracing::error!(?err, "Failed to update pinned flag");
+                                ChatListMessage::Noop
+                            }
+                        }
+                    },
+                    |msg| msg,
+                ))
+            }
             _ => {
                 // Call the internal update method for other messages
                 let cmd = self.update_internal(message);
@@ -2376,8 +4352,12 @@ impl Screen for ChatListScreen {
     fn handle_ui_event(
         &mut self,
         event: UiEvent,
-        _ctx: &mut AppContext,
+        ctx: &mut AppContext,
     ) -> ScreenCommand<ChatListMessage> {
+        self.groups.archived_unread_badges = ctx
+            .chat_manager
+            .as_ref()
+            .is_some_and(|chats| chats.archive_options().unread_badges);
         // Apply event to internal state
         self.apply_event(event);
         ScreenCommand::None
@@ -2389,3 +4369,67 @@ impl Screen for ChatListScreen {
 }
 
 // Helper function removed - no longer needed as we use inline styling
+
+fn history_item(message: Message) -> MessageItem {
+    let clock_skewed = message.has_clock_skew();
+    let text = message.kind.display_text();
+    // Incoming messages are always delivered, outgoing ones once confirmed
+    let status = if message.incoming || message.log_id.is_some() {
+        MessageStatus::Delivered
+    } else {
+        MessageStatus::Pending
+    };
+    MessageItem {
+        id: message.id,
+        text,
+        is_mine: !message.incoming,
+        status,
+        verified: message.verified,
+        timestamp: format_message_time(message.create_time),
+        clock_skewed,
+        reply_to: message.reply_to,
+    }
+}
+
+/// Formats a message time as "HH:MM" in the local time zone.
+fn format_message_time(time: DateTime) -> String {
+    time.0
+        .with_timezone(&chrono::Local)
+        .format("%H:%M")
+        .to_string()
+}
+
+/// Shield for verified contacts.
+fn verification_badge<'a>(
+    verified: bool,
+    size: f32,
+    theme: &Theme,
+) -> Option<Element<'a, ChatListMessage>> {
+    if !verified {
+        return None;
+    }
+    let color = colors::text_success(theme);
+    let shield = svg::Svg::new(svg::Handle::from_memory(SHIELD_ICON.as_bytes().to_vec()))
+        .width(Length::Fixed(size))
+        .height(Length::Fixed(size))
+        .style(move |_theme, _status| svg::Style { color: Some(color) });
+    Some(shield.into())
+}
+
+/// Formats a message as a "name • time: text" line for the clipboard.
+pub fn format_message_line(name: &str, time: &str, text: &str) -> String {
+    format!("{} • {}: {}", name, time, text)
+}
+
+/// Formats a byte count as B, KB or MB.
+fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.1} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{bytes} B")
+    }
+}
@@ -7,6 +7,7 @@ use iced::{Alignment, Element, Length, Task, Theme};
 use tokio::sync::{Mutex as TokioMutex, mpsc};
 
 use crate::DEFAULT_SERVER;
+use crate::audio::RingtonePlayer;
 use crate::call::CallManager;
 use crate::chat::ChatManager;
 use crate::config::ConfigManager;
@@ -216,10 +217,7 @@ impl InitScreen {
         }
     }
 
-    pub fn handle_tab_navigation(
-        &mut self,
-        reverse: bool,
-    ) -> ScreenCommand<InitMessage> {
+    pub fn handle_tab_navigation(&mut self, reverse: bool) -> ScreenCommand<InitMessage> {
         let can_submit = self.can_submit();
         if reverse {
             let mut previous = self.focus.previous();
@@ -328,6 +326,7 @@ impl Screen for InitScreen {
                         ctx.call_manager = Some(success.call_manager.clone());
                         ctx.profile = Some(success.profile.clone());
                         ctx.server_addr = Some(success.server_addr);
+                        ctx.server_token = success.server_token.clone();
                         // Initialize contacts list (usually empty for new account) and connection status
                         let ui_tx = ctx.ui_event_tx.clone();
                         let cm_for_list = ctx.chat_manager.clone();
@@ -335,8 +334,8 @@ impl Screen for InitScreen {
                         tokio::spawn(async move {
                             // Send transport connection status
                             if let Some(ref cm) = contact_mgr {
-                                let is_connected = cm.is_connected();
-                                let _ = ui_tx.send(UiEvent::TransportConnected(is_connected)).await;
+                                let status = cm.connection_status();
+                                let _ = ui_tx.send(UiEvent::ConnectionStatus(status)).await;
                             }
                             if let Some(cm) = cm_for_list {
                                 for chat_handle in cm.list_contact_chats().await {
@@ -345,6 +344,12 @@ impl Screen for InitScreen {
                                         .send(UiEvent::ContactAccepted {
                                             address: contact.address.to_string(),
                                             name: contact.local_name.unwrap_or(contact.name),
+                                            avatar: contact.avatar,
+                                            archived: contact.archived,
+                                            pin_order: contact.pinned.then_some(contact.pin_order),
+                                            verified: contact.verified,
+                                            muted: contact.muted,
+                                            auto_answer: contact.auto_answer,
                                         })
                                         .await;
                                     let _ = ui_tx
@@ -512,7 +517,7 @@ async fn init_flow(
     let cfg = ConfigManager::new(storage.clone());
     // Initialize account: generates keypair, stores private key and profile
     let (profile, private_key) = cfg
-        .init_account(name)
+        .init_account(name, &password)
         .await
         .map_err(|e| format!("Failed to initialize account: {}", e))?;
     // Validate and store server address
@@ -539,6 +544,10 @@ async fn init_flow(
         call_manager,
         profile,
         server_addr,
+        server_token: None,
+        ringtone_volume: RingtonePlayer::DEFAULT_VOLUME,
+        message_history_limit: ConfigManager::DEFAULT_HISTORY_LIMIT,
+        window_geometry: None,
     })
 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
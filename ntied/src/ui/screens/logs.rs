@@ -0,0 +1,174 @@
+use iced::widget::{Space, button, column, container, pick_list, row, scrollable, text};
+use iced::{Alignment, Element, Font, Length, Padding, Task, Theme, clipboard};
+use tracing::Level;
+
+use crate::logs::{LogBuffer, LogLine};
+use crate::ui::AppContext;
+use crate::ui::core::{Screen, ScreenCommand, ScreenType};
+use crate::ui::theme::colors;
+
+const LEVELS: [Level; 5] = [
+    Level::ERROR,
+    Level::WARN,
+    Level::INFO,
+    Level::DEBUG,
+    Level::TRACE,
+];
+
+#[derive(Clone, Debug)]
+pub enum LogsMessage {
+    LevelChanged(Level),
+    Copy,
+    Clear,
+    Back,
+}
+
+/// Tails recent log lines from a [`LogBuffer`].
+pub struct LogsScreen {
+    buffer: LogBuffer,
+    level: Level,
+    lines: Vec<LogLine>,
+}
+
+impl LogsScreen {
+    pub fn new(buffer: LogBuffer) -> Self {
+        let mut screen = Self {
+            buffer,
+            level: Level::INFO,
+            lines: Vec::new(),
+        };
+        screen.refresh();
+        screen
+    }
+
+    /// Reload lines from the buffer, called periodically by the app.
+    pub fn refresh(&mut self) {
+        self.lines = self.buffer.lines(self.level);
+    }
+
+    fn update_internal(&mut self, message: LogsMessage) -> Task<LogsMessage> {
+        match message {
+            LogsMessage::LevelChanged(level) => {
+                self.level = level;
+                self.refresh();
+                Task::none()
+            }
+            LogsMessage::Copy => {
+                let mut content = String::new();
+                for line in &self.lines {
+                    content.push_str(&line.to_string());
+                    content.push('\n');
+                }
+                clipboard::write(content)
+            }
+            LogsMessage::Clear => {
+                self.buffer.clear();
+                self.refresh();
+                Task::none()
+            }
+            LogsMessage::Back => {
+                // Handled in Screen trait implementation
+                Task::none()
+            }
+        }
+    }
+
+    pub fn view<'a>(&'a self, theme: &'a Theme) -> Element<'a, LogsMessage> {
+        let header = container(
+            row![
+                text("Logs").size(24),
+                Space::with_width(Length::Fill),
+                text("Level").size(14),
+                pick_list(LEVELS, Some(self.level), LogsMessage::LevelChanged)
+                    .text_size(14)
+                    .padding([4, 8]),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        )
+        .width(Length::Fill)
+        .padding(Padding::ZERO.bottom(16));
+
+        let lines: Element<'_, LogsMessage> = if self.lines.is_empty() {
+            text("No log lines yet")
+                .size(12)
+                .color(colors::text_secondary(theme))
+                .into()
+        } else {
+            column(self.lines.iter().map(|line| {
+                let color = match line.level {
+                    Level::ERROR => colors::text_error(theme),
+                    Level::WARN => colors::primary(theme),
+                    _ => colors::text_primary(theme),
+                };
+                text(line.to_string())
+                    .size(12)
+                    .font(Font::MONOSPACE)
+                    .color(color)
+                    .into()
+            }))
+            .spacing(2)
+            .into()
+        };
+        let log_view = container(
+            scrollable(container(lines).padding(8))
+                .anchor_bottom()
+                .height(Length::Fill)
+                .width(Length::Fill),
+        )
+        .height(Length::Fill)
+        .style(move |t: &Theme| container::Style {
+            background: Some(iced::Background::Color(colors::background_weak(t))),
+            ..Default::default()
+        });
+
+        let actions = container(
+            row![
+                button(text("Back").size(14))
+                    .on_press(LogsMessage::Back)
+                    .padding([6, 12])
+                    .style(button::secondary),
+                Space::with_width(Length::Fill),
+                button(text("Clear").size(14))
+                    .on_press(LogsMessage::Clear)
+                    .padding([6, 12])
+                    .style(button::secondary),
+                Space::with_width(8),
+                button(text("Copy").size(14))
+                    .on_press_maybe((!self.lines.is_empty()).then_some(LogsMessage::Copy))
+                    .padding([6, 12])
+                    .style(button::primary),
+            ]
+            .align_y(Alignment::Center),
+        )
+        .width(Length::Fill)
+        .padding(Padding::ZERO.top(16));
+
+        container(column![header, log_view, actions])
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(20)
+            .into()
+    }
+}
+
+impl Screen for LogsScreen {
+    type Message = LogsMessage;
+
+    fn update(&mut self, message: LogsMessage, ctx: &mut AppContext) -> ScreenCommand<LogsMessage> {
+        match message {
+            LogsMessage::Back => {
+                let server_addr = ctx
+                    .server_addr
+                    .map(|addr| addr.to_string())
+                    .unwrap_or_else(|| crate::DEFAULT_SERVER.to_string());
+                ScreenCommand::ChangeScreen(ScreenType::Settings { server_addr })
+            }
+            _ => ScreenCommand::Message(self.update_internal(message)),
+        }
+    }
+
+    fn view<'a>(&'a self, theme: &'a Theme) -> Element<'a, LogsMessage> {
+        self.view(theme)
+    }
+}
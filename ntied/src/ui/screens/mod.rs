@@ -1,9 +1,11 @@
 mod chat_list;
 mod init;
+mod logs;
 mod settings;
 mod unlock;
 
 pub use chat_list::*;
 pub use init::*;
+pub use logs::*;
 pub use settings::*;
 pub use unlock::{InitSuccess, UnlockMessage, UnlockScreen};
@@ -1,10 +1,21 @@
 use std::net::SocketAddr;
 use std::str::FromStr as _;
 
-use iced::widget::{Space, button, column, container, row, scrollable, text, text_input};
-use iced::{Alignment, Element, Length, Padding, Task, Theme};
+use iced::widget::{
+    Space, button, checkbox, column, container, image, row, scrollable, slider, text, text_input,
+};
+use iced::{Alignment, Element, Font, Length, Padding, Task, Theme, clipboard};
+use ntied_transport::{Address, ToAddress as _};
+use tracing::Level;
 
-use crate::config::ConfigManager;
+use crate::audio::{CodecType, RingtonePlayer, ShareMode, SystemAudioMode};
+use crate::call::CallManager;
+use crate::chat::{ArchiveOptions, RetentionOptions};
+use crate::config::{ConfigManager, SessionRecord};
+use crate::diagnostics::DiagnosticsBundle;
+use crate::logs::LogBuffer;
+use crate::packet::ContactProfile;
+use crate::ui::avatar::{avatar, avatar_handle};
 use crate::ui::core::{Screen, ScreenCommand, ScreenType};
 use crate::ui::theme::{ThemePreference, colors};
 use crate::ui::{AppContext, UiEvent};
@@ -12,32 +23,189 @@ use crate::ui::{AppContext, UiEvent};
 #[derive(Clone, Debug)]
 pub enum SettingsMessage {
     ServerAddressChanged(String),
+    ServerTokenChanged(String),
+    WakeRecoveryChanged(bool),
+    FocusRefreshChanged(bool),
     ThemeChanged(ThemePreference),
+    CallWaitingChanged(bool),
+    FramePacingChanged(bool),
+    FollowDefaultDeviceChanged(bool),
+    QosMarkingChanged(bool),
+    UnarchiveOnMessageChanged(bool),
+    ArchivedBadgesChanged(bool),
+    DeleteOldMessagesChanged(bool),
+    MessageMaxAgeChanged(u32),
+    MessageHistoryLimitChanged(u32),
+    InputGainChanged(f32),
+    RingtoneVolumeChanged(f32),
+    PreferredCodecChanged(CodecType),
+    ShareModeChanged(ShareMode),
+    SystemAudioChanged(Option<SystemAudioMode>),
     SaveSettings,
     CancelSettings,
     ResetToDefault,
     SaveComplete(Result<(), String>),
+    AvatarPathChanged(String),
+    SetAvatar,
+    RemoveAvatar,
+    AvatarSaved(Result<ContactProfile, String>),
+    RotateKeyRequested,
+    RotateKeyPassphraseChanged(String),
+    RotateKeyCancelled,
+    RotateKeyConfirmed,
+    RotateKeyComplete(Result<String, String>),
+    RecentSessionsLoaded(Vec<SessionRecord>),
+    Unblock(Address),
+    Unblocked(Result<Address, String>),
+    ClearSessions,
+    SessionsCleared(Result<(), String>),
+    OpenLogs,
+    DiagnosticAddressChanged(String),
+    RunDiagnostic,
+    DiagnosticComplete(Result<String, String>),
+    CopyDiagnostic,
+    CopyDiagnosticsBundle,
+    DiagnosticsBundleReady(String),
 }
 
 pub struct SettingsScreen {
     server_address: String,
     original_server_address: String,
+    // Empty if the server does not require a token
+    server_token: String,
+    original_server_token: String,
+    wake_recovery: bool,
+    original_wake_recovery: bool,
+    focus_refresh: bool,
+    original_focus_refresh: bool,
     theme: ThemePreference,
     original_theme: ThemePreference,
+    call_waiting: bool,
+    original_call_waiting: bool,
+    frame_pacing: bool,
+    original_frame_pacing: bool,
+    follow_default_device: bool,
+    original_follow_default_device: bool,
+    qos_marking: bool,
+    original_qos_marking: bool,
+    archive_options: ArchiveOptions,
+    original_archive_options: ArchiveOptions,
+    retention_options: RetentionOptions,
+    original_retention_options: RetentionOptions,
+    message_history_limit: usize,
+    original_message_history_limit: usize,
+    input_gain: f32,
+    original_input_gain: f32,
+    ringtone_volume: f32,
+    original_ringtone_volume: f32,
+    codecs: Vec<CodecType>,
+    preferred_codec: CodecType,
+    original_preferred_codec: CodecType,
+    share_mode: ShareMode,
+    original_share_mode: ShareMode,
+    system_audio: Option<SystemAudioMode>,
+    original_system_audio: Option<SystemAudioMode>,
     has_changes: bool,
     error_message: Option<String>,
+    profile_name: String,
+    avatar: Option<image::Handle>,
+    avatar_path: String,
+    avatar_message: Option<Result<String, String>>,
+    // Key rotation waits for an explicit confirmation
+    confirm_rotate_key: bool,
+    // Account password re-entered to encrypt the new key
+    rotate_key_passphrase: String,
+    rotate_key_message: Option<Result<String, String>>,
+    // Newest first
+    recent_sessions: Vec<SessionRecord>,
+    sessions_error: Option<String>,
+    blocked_addresses: Vec<Address>,
+    blocked_error: Option<String>,
+    diagnostic_address: String,
+    diagnostic_running: bool,
+    diagnostic_report: Option<Result<String, String>>,
+    diagnostics_bundle_copied: bool,
 }
 
 impl SettingsScreen {
+    /// Age limit offered when deletion of old messages is turned on.
+    const DEFAULT_MESSAGE_MAX_AGE_DAYS: u32 = 30;
+
     pub fn new(current_server: String) -> Self {
         Self {
             server_address: current_server.clone(),
             original_server_address: current_server,
+            server_token: String::new(),
+            original_server_token: String::new(),
+            wake_recovery: true,
+            original_wake_recovery: true,
+            focus_refresh: true,
+            original_focus_refresh: true,
             theme: ThemePreference::default(),
             original_theme: ThemePreference::default(),
+            call_waiting: false,
+            original_call_waiting: false,
+            frame_pacing: false,
+            original_frame_pacing: false,
+            follow_default_device: false,
+            original_follow_default_device: false,
+            qos_marking: false,
+            original_qos_marking: false,
+            archive_options: ArchiveOptions::default(),
+            original_archive_options: ArchiveOptions::default(),
+            retention_options: RetentionOptions::default(),
+            original_retention_options: RetentionOptions::default(),
+            message_history_limit: ConfigManager::DEFAULT_HISTORY_LIMIT,
+            original_message_history_limit: ConfigManager::DEFAULT_HISTORY_LIMIT,
+            input_gain: 1.0,
+            original_input_gain: 1.0,
+            ringtone_volume: RingtonePlayer::DEFAULT_VOLUME,
+            original_ringtone_volume: RingtonePlayer::DEFAULT_VOLUME,
+            codecs: CodecType::available(),
+            preferred_codec: CodecType::default(),
+            original_preferred_codec: CodecType::default(),
+            share_mode: ShareMode::default(),
+            original_share_mode: ShareMode::default(),
+            system_audio: None,
+            original_system_audio: None,
             has_changes: false,
             error_message: None,
+            profile_name: String::new(),
+            avatar: None,
+            avatar_path: String::new(),
+            avatar_message: None,
+            confirm_rotate_key: false,
+            rotate_key_passphrase: String::new(),
+            rotate_key_message: None,
+            recent_sessions: Vec::new(),
+            sessions_error: None,
+            blocked_addresses: Vec::new(),
+            blocked_error: None,
+            diagnostic_address: String::new(),
+            diagnostic_running: false,
+            diagnostic_report: None,
+            diagnostics_bundle_copied: false,
+        }
+    }
+
+    pub fn with_profile(mut self, profile: Option<&ContactProfile>) -> Self {
+        if let Some(profile) = profile {
+            self.profile_name = profile.name.clone();
+            self.avatar = profile.avatar.as_ref().and_then(|a| avatar_handle(&a.0));
         }
+        self
+    }
+
+    pub fn with_server_token(mut self, token: Option<String>) -> Self {
+        self.server_token = token.unwrap_or_default();
+        self.original_server_token = self.server_token.clone();
+        self
+    }
+
+    pub fn with_blocked_addresses(mut self, mut addresses: Vec<Address>) -> Self {
+        addresses.sort_by_key(|v| v.to_string());
+        self.blocked_addresses = addresses;
+        self
     }
 
     pub fn with_theme(mut self, theme: ThemePreference) -> Self {
@@ -46,6 +214,96 @@ impl SettingsScreen {
         self
     }
 
+    pub fn with_wake_recovery(mut self, enabled: bool) -> Self {
+        self.wake_recovery = enabled;
+        self.original_wake_recovery = enabled;
+        self
+    }
+
+    pub fn with_focus_refresh(mut self, enabled: bool) -> Self {
+        self.focus_refresh = enabled;
+        self.original_focus_refresh = enabled;
+        self
+    }
+
+    pub fn with_call_waiting(mut self, enabled: bool) -> Self {
+        self.call_waiting = enabled;
+        self.original_call_waiting = enabled;
+        self
+    }
+
+    pub fn with_frame_pacing(mut self, enabled: bool) -> Self {
+        self.frame_pacing = enabled;
+        self.original_frame_pacing = enabled;
+        self
+    }
+
+    pub fn with_follow_default_device(mut self, enabled: bool) -> Self {
+        self.follow_default_device = enabled;
+        self.original_follow_default_device = enabled;
+        self
+    }
+
+    pub fn with_qos_marking(mut self, enabled: bool) -> Self {
+        self.qos_marking = enabled;
+        self.original_qos_marking = enabled;
+        self
+    }
+
+    pub fn with_archive_options(mut self, options: ArchiveOptions) -> Self {
+        self.archive_options = options;
+        self.original_archive_options = options;
+        self
+    }
+
+    pub fn with_retention_options(mut self, options: RetentionOptions) -> Self {
+        self.retention_options = options;
+        self.original_retention_options = options;
+        self
+    }
+
+    pub fn with_message_history_limit(mut self, limit: usize) -> Self {
+        self.message_history_limit = limit;
+        self.original_message_history_limit = limit;
+        self
+    }
+
+    pub fn with_input_gain(mut self, gain: f32) -> Self {
+        self.input_gain = gain;
+        self.original_input_gain = gain;
+        self
+    }
+
+    pub fn with_ringtone_volume(mut self, volume: f32) -> Self {
+        self.ringtone_volume = volume;
+        self.original_ringtone_volume = volume;
+        self
+    }
+
+    /// Codecs available in this build and the one used for outgoing audio.
+    pub fn with_codecs(mut self, codecs: Vec<CodecType>, preferred: CodecType) -> Self {
+        self.codecs = codecs;
+        self.preferred_codec = preferred;
+        self.original_preferred_codec = preferred;
+        self
+    }
+
+    pub fn with_share_mode(mut self, mode: ShareMode) -> Self {
+        self.share_mode = mode;
+        self.original_share_mode = mode;
+        self
+    }
+
+    pub fn with_system_audio(mut self, mode: Option<SystemAudioMode>) -> Self {
+        self.system_audio = mode;
+        self.original_system_audio = mode;
+        self
+    }
+
+    pub fn codecs(&self) -> &[CodecType] {
+        &self.codecs
+    }
+
     pub fn server_address(&self) -> &str {
         &self.server_address
     }
@@ -54,25 +312,144 @@ impl SettingsScreen {
         self.has_changes
     }
 
+    fn update_has_changes(&mut self) {
+        self.has_changes = self.server_address != self.original_server_address
+            || self.server_token != self.original_server_token
+            || self.wake_recovery != self.original_wake_recovery
+            || self.focus_refresh != self.original_focus_refresh
+            || self.theme != self.original_theme
+            || self.call_waiting != self.original_call_waiting
+            || self.frame_pacing != self.original_frame_pacing
+            || self.follow_default_device != self.original_follow_default_device
+            || self.qos_marking != self.original_qos_marking
+            || self.archive_options != self.original_archive_options
+            || self.retention_options != self.original_retention_options
+            || self.message_history_limit != self.original_message_history_limit
+            || self.input_gain != self.original_input_gain
+            || self.ringtone_volume != self.original_ringtone_volume
+            || self.preferred_codec != self.original_preferred_codec
+            || self.share_mode != self.original_share_mode
+            || self.system_audio != self.original_system_audio;
+    }
+
     fn update_internal(&mut self, message: SettingsMessage) -> Task<SettingsMessage> {
         match message {
             SettingsMessage::ServerAddressChanged(value) => {
                 self.server_address = value;
-                self.has_changes = self.server_address != self.original_server_address
-                    || self.theme != self.original_theme;
+                self.update_has_changes();
                 self.error_message = self.validate_server_address();
                 Task::none()
             }
+            SettingsMessage::ServerTokenChanged(value) => {
+                self.server_token = value;
+                self.update_has_changes();
+                Task::none()
+            }
             SettingsMessage::ThemeChanged(new_theme) => {
                 self.theme = new_theme;
-                self.has_changes = self.server_address != self.original_server_address
-                    || self.theme != self.original_theme;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::WakeRecoveryChanged(enabled) => {
+                self.wake_recovery = enabled;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::FocusRefreshChanged(enabled) => {
+                self.focus_refresh = enabled;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::CallWaitingChanged(enabled) => {
+                self.call_waiting = enabled;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::FramePacingChanged(enabled) => {
+                self.frame_pacing = enabled;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::FollowDefaultDeviceChanged(enabled) => {
+                self.follow_default_device = enabled;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::QosMarkingChanged(enabled) => {
+                self.qos_marking = enabled;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::UnarchiveOnMessageChanged(enabled) => {
+                self.archive_options.unarchive_on_message = enabled;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::ArchivedBadgesChanged(enabled) => {
+                self.archive_options.unread_badges = enabled;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::DeleteOldMessagesChanged(enabled) => {
+                self.retention_options.max_age_days =
+                    enabled.then_some(Self::DEFAULT_MESSAGE_MAX_AGE_DAYS);
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::MessageMaxAgeChanged(days) => {
+                self.retention_options.max_age_days = Some(days);
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::MessageHistoryLimitChanged(limit) => {
+                self.message_history_limit = limit as usize;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::InputGainChanged(gain) => {
+                self.input_gain = gain;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::RingtoneVolumeChanged(volume) => {
+                self.ringtone_volume = volume;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::PreferredCodecChanged(codec) => {
+                self.preferred_codec = codec;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::ShareModeChanged(mode) => {
+                self.share_mode = mode;
+                self.update_has_changes();
+                Task::none()
+            }
+            SettingsMessage::SystemAudioChanged(mode) => {
+                self.system_audio = mode;
+                self.update_has_changes();
                 Task::none()
             }
             SettingsMessage::SaveSettings => {
                 if self.validate_server_address().is_none() {
                     self.original_server_address = self.server_address.clone();
+                    self.original_server_token = self.server_token.clone();
+                    self.original_wake_recovery = self.wake_recovery;
+                    self.original_focus_refresh = self.focus_refresh;
                     self.original_theme = self.theme;
+                    self.original_call_waiting = self.call_waiting;
+                    self.original_frame_pacing = self.frame_pacing;
+                    self.original_follow_default_device = self.follow_default_device;
+                    self.original_qos_marking = self.qos_marking;
+                    self.original_archive_options = self.archive_options;
+                    self.original_retention_options = self.retention_options;
+                    self.original_message_history_limit = self.message_history_limit;
+                    self.original_input_gain = self.input_gain;
+                    self.original_ringtone_volume = self.ringtone_volume;
+                    self.original_preferred_codec = self.preferred_codec;
+                    self.original_share_mode = self.share_mode;
+                    self.original_system_audio = self.system_audio;
                     self.has_changes = false;
                     // Parent will handle actual saving
                 }
@@ -81,7 +458,22 @@ impl SettingsScreen {
             SettingsMessage::CancelSettings => {
                 // Revert to original
                 self.server_address = self.original_server_address.clone();
+                self.server_token = self.original_server_token.clone();
+                self.wake_recovery = self.original_wake_recovery;
+                self.focus_refresh = self.original_focus_refresh;
                 self.theme = self.original_theme;
+                self.call_waiting = self.original_call_waiting;
+                self.frame_pacing = self.original_frame_pacing;
+                self.follow_default_device = self.original_follow_default_device;
+                self.qos_marking = self.original_qos_marking;
+                self.archive_options = self.original_archive_options;
+                self.retention_options = self.original_retention_options;
+                self.message_history_limit = self.original_message_history_limit;
+                self.input_gain = self.original_input_gain;
+                self.ringtone_volume = self.original_ringtone_volume;
+                self.preferred_codec = self.original_preferred_codec;
+                self.share_mode = self.original_share_mode;
+                self.system_audio = self.original_system_audio;
                 self.has_changes = false;
                 self.error_message = None;
                 Task::none()
@@ -90,11 +482,137 @@ impl SettingsScreen {
                 // Handled in Screen trait implementation
                 Task::none()
             }
+            SettingsMessage::AvatarPathChanged(value) => {
+                self.avatar_path = value;
+                self.avatar_message = None;
+                Task::none()
+            }
+            SettingsMessage::SetAvatar | SettingsMessage::RemoveAvatar => {
+                // Handled in Screen trait implementation
+                Task::none()
+            }
+            SettingsMessage::AvatarSaved(result) => {
+                match result {
+                    Ok(profile) => {
+                        self.avatar = profile.avatar.as_ref().and_then(|a| avatar_handle(&a.0));
+                        self.avatar_path.clear();
+                        self.avatar_message = Some(Ok("Avatar saved".to_string()));
+                    }
+                    Err(error) => self.avatar_message = Some(Err(error)),
+                }
+                Task::none()
+            }
+            SettingsMessage::RotateKeyRequested => {
+                self.confirm_rotate_key = true;
+                self.rotate_key_message = None;
+                Task::none()
+            }
+            SettingsMessage::RotateKeyPassphraseChanged(value) => {
+                self.rotate_key_passphrase = value;
+                self.rotate_key_message = None;
+                Task::none()
+            }
+            SettingsMessage::RotateKeyCancelled => {
+                self.confirm_rotate_key = false;
+                self.rotate_key_passphrase.clear();
+                Task::none()
+            }
+            SettingsMessage::RotateKeyConfirmed => {
+                // Handled in Screen trait implementation
+                Task::none()
+            }
+            SettingsMessage::RotateKeyComplete(result) => {
+                // Keep the dialog open to retry a mistyped password
+                self.confirm_rotate_key = result.is_err();
+                self.rotate_key_passphrase.clear();
+                self.rotate_key_message = Some(result.map(|address| {
+                    format!(
+                        "New address: {}. Restart the application to connect with the new identity.",
+                        address
+                    )
+                }));
+                Task::none()
+            }
+            SettingsMessage::RecentSessionsLoaded(sessions) => {
+                self.recent_sessions = sessions;
+                Task::none()
+            }
+            SettingsMessage::Unblock(_) => {
+                // Handled in Screen trait implementation
+                Task::none()
+            }
+            SettingsMessage::Unblocked(result) => {
+                match result {
+                    Ok(address) => {
+                        self.blocked_addresses.retain(|v| *v != address);
+                        self.blocked_error = None;
+                    }
+                    Err(error) => self.blocked_error = Some(error),
+                }
+                Task::none()
+            }
+            SettingsMessage::ClearSessions => {
+                // Handled in Screen trait implementation
+                Task::none()
+            }
+            SettingsMessage::SessionsCleared(result) => {
+                match result {
+                    Ok(()) => {
+                        self.recent_sessions.clear();
+                        self.sessions_error = None;
+                    }
+                    Err(error) => self.sessions_error = Some(error),
+                }
+                Task::none()
+            }
+            SettingsMessage::OpenLogs => {
+                // Handled in Screen trait implementation
+                Task::none()
+            }
+            SettingsMessage::DiagnosticAddressChanged(value) => {
+                self.diagnostic_address = value;
+                Task::none()
+            }
+            SettingsMessage::RunDiagnostic => {
+                // Handled in Screen trait implementation
+                Task::none()
+            }
+            SettingsMessage::DiagnosticComplete(result) => {
+                self.diagnostic_running = false;
+                self.diagnostic_report = Some(result);
+                Task::none()
+            }
+            SettingsMessage::CopyDiagnostic => match &self.diagnostic_report {
+                Some(Ok(report)) => clipboard::write(report.clone()),
+                _ => Task::none(),
+            },
+            SettingsMessage::CopyDiagnosticsBundle => {
+                // Handled in Screen trait implementation
+                Task::none()
+            }
+            SettingsMessage::DiagnosticsBundleReady(bundle) => {
+                self.diagnostics_bundle_copied = true;
+                clipboard::write(bundle)
+            }
             SettingsMessage::ResetToDefault => {
                 self.server_address = crate::DEFAULT_SERVER.to_string();
+                self.server_token.clear();
+                self.wake_recovery = true;
+                self.focus_refresh = true;
                 self.theme = ThemePreference::default();
-                self.has_changes = self.server_address != self.original_server_address
-                    || self.theme != self.original_theme;
+                self.call_waiting = false;
+                self.frame_pacing = false;
+                self.follow_default_device = false;
+                self.qos_marking = false;
+                self.archive_options = ArchiveOptions::default();
+                self.retention_options = RetentionOptions::default();
+                self.message_history_limit = ConfigManager::DEFAULT_HISTORY_LIMIT;
+                self.input_gain = 1.0;
+                self.ringtone_volume = RingtonePlayer::DEFAULT_VOLUME;
+                self.preferred_codec = CodecType::default();
+                self.share_mode = ShareMode::default();
+                self.system_audio = None;
+                self.update_has_changes();
                 self.error_message = self.validate_server_address();
                 Task::none()
             }
@@ -131,6 +649,185 @@ impl SettingsScreen {
         )
         .width(Length::Fill)
         .padding(Padding::ZERO.bottom(16));
+        // Profile section
+        let avatar_status: Element<'_, SettingsMessage> = match &self.avatar_message {
+            Some(Ok(info)) => text(info)
+                .size(12)
+                .color(colors::text_secondary(theme))
+                .into(),
+            Some(Err(error)) => text(error).size(12).color(colors::text_error(theme)).into(),
+            None => Space::with_height(0).into(),
+        };
+        let profile_section = container(
+            column![
+                text("Profile").size(18),
+                Space::with_height(12),
+                row![
+                    avatar(&self.profile_name, self.avatar.as_ref(), 64.0),
+                    column![
+                        text("Avatar Image").size(14),
+                        text_input("Path to a PNG or JPEG file", &self.avatar_path)
+                            .on_input(SettingsMessage::AvatarPathChanged)
+                            .padding(10)
+                            .size(14)
+                            .width(Length::Fixed(300.0)),
+                        row![
+                            button(text("Set Avatar").size(14))
+                                .on_press_maybe(
+                                    (!self.avatar_path.trim().is_empty())
+                                        .then_some(SettingsMessage::SetAvatar)
+                                )
+                                .padding([6, 12])
+                                .style(button::primary),
+                            button(text("Remove").size(14))
+                                .on_press_maybe(
+                                    self.avatar
+                                        .is_some()
+                                        .then_some(SettingsMessage::RemoveAvatar)
+                                )
+                                .padding([6, 12])
+                                .style(button::secondary),
+                        ]
+                        .spacing(8),
+                        avatar_status,
+                    ]
+                    .spacing(6),
+                ]
+                .spacing(16)
+                .align_y(Alignment::Center),
+            ]
+            .spacing(4),
+        )
+        .padding(Padding::from([16, 0]))
+        .width(Length::Fill);
+
+        // Security section
+        let rotate_key_status: Element<'_, SettingsMessage> = match &self.rotate_key_message {
+            Some(Ok(info)) => text(info)
+                .size(12)
+                .color(colors::text_secondary(theme))
+                .into(),
+            Some(Err(error)) => text(error).size(12).color(colors::text_error(theme)).into(),
+            None => Space::with_height(0).into(),
+        };
+        let rotate_key_controls: Element<'_, SettingsMessage> = if self.confirm_rotate_key {
+            column![
+                text("Your address will change. Contacts are notified with a message signed by the current key.")
+                    .size(12)
+                    .color(colors::text_error(theme)),
+                text_input("Account password", &self.rotate_key_passphrase)
+                    .on_input(SettingsMessage::RotateKeyPassphraseChanged)
+                    .secure(true)
+                    .padding(10)
+                    .size(14)
+                    .width(Length::Fixed(300.0)),
+                row![
+                    button(text("Confirm").size(14))
+                        .on_press_maybe(
+                            (!self.rotate_key_passphrase.is_empty())
+                                .then_some(SettingsMessage::RotateKeyConfirmed)
+                        )
+                        .padding([6, 12])
+                        .style(button::danger),
+                    button(text("Cancel").size(14))
+                        .on_press(SettingsMessage::RotateKeyCancelled)
+                        .padding([6, 12])
+                        .style(button::secondary),
+                ]
+                .spacing(8),
+            ]
+            .spacing(6)
+            .into()
+        } else {
+            button(text("Rotate Identity Key").size(14))
+                .on_press(SettingsMessage::RotateKeyRequested)
+                .padding([6, 12])
+                .style(button::secondary)
+                .into()
+        };
+        let sessions_list: Element<'_, SettingsMessage> = if self.recent_sessions.is_empty() {
+            text("No sessions recorded")
+                .size(12)
+                .color(colors::text_secondary(theme))
+                .into()
+        } else {
+            let mut sessions = column![].spacing(2);
+            for session in &self.recent_sessions {
+                sessions = sessions.push(text(format_session(session)).size(12));
+            }
+            column![
+                sessions,
+                button(text("Clear Sessions").size(14))
+                    .on_press(SettingsMessage::ClearSessions)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ]
+            .spacing(6)
+            .into()
+        };
+        let blocked_list: Element<'_, SettingsMessage> = if self.blocked_addresses.is_empty() {
+            text("No blocked addresses")
+                .size(12)
+                .color(colors::text_secondary(theme))
+                .into()
+        } else {
+            let mut blocked = column![].spacing(4);
+            for address in &self.blocked_addresses {
+                blocked = blocked.push(
+                    row![
+                        text(address.to_string()).size(12).font(Font::MONOSPACE),
+                        button(text("Unblock").size(12))
+                            .on_press(SettingsMessage::Unblock(*address))
+                            .padding([4, 10])
+                            .style(button::secondary),
+                    ]
+                    .spacing(8)
+                    .align_y(Alignment::Center),
+                );
+            }
+            blocked.into()
+        };
+        let blocked_status: Element<'_, SettingsMessage> = match &self.blocked_error {
+            Some(error) => text(error).size(12).color(colors::text_error(theme)).into(),
+            None => Space::with_height(0).into(),
+        };
+        let sessions_status: Element<'_, SettingsMessage> = match &self.sessions_error {
+            Some(error) => text(error).size(12).color(colors::text_error(theme)).into(),
+            None => Space::with_height(0).into(),
+        };
+        let security_section = container(
+            column![
+                Space::with_height(24),
+                text("Security").size(18),
+                Space::with_height(12),
+                text("Generate a new identity key and archive the current one")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(4),
+                rotate_key_controls,
+                rotate_key_status,
+                Space::with_height(12),
+                text("Recent sessions").size(14),
+                text("Connections of this profile to the server. Rotate the identity key if you do not recognize one.")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(4),
+                sessions_list,
+                sessions_status,
+                Space::with_height(12),
+                text("Blocked addresses").size(14),
+                text("Contact requests and connections from these addresses are dropped.")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(4),
+                blocked_list,
+                blocked_status,
+            ]
+            .spacing(4),
+        )
+        .padding(Padding::from([16, 0]))
+        .width(Length::Fill);
+
         // Server section
         let server_section = container(
             column![
@@ -152,9 +849,34 @@ impl SettingsScreen {
                     Element::from(Space::with_height(0))
                 },
                 Space::with_height(8),
+                text("Access Token").size(14),
+                Space::with_height(4),
+                text_input("Required by private servers only", &self.server_token)
+                    .on_input(SettingsMessage::ServerTokenChanged)
+                    .secure(true)
+                    .padding(10)
+                    .size(14)
+                    .width(Length::Fixed(300.0)),
+                Space::with_height(8),
                 text("Default server is used for initial connection")
                     .size(12)
                     .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                checkbox("Reconnect after sleep", self.wake_recovery)
+                    .on_toggle(SettingsMessage::WakeRecoveryChanged)
+                    .size(16)
+                    .text_size(14),
+                text("Renew the server and contact connections when the computer wakes up")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                checkbox("Refresh contacts on focus", self.focus_refresh)
+                    .on_toggle(SettingsMessage::FocusRefreshChanged)
+                    .size(16)
+                    .text_size(14),
+                text("Check contact connections when you return to the window")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
             ]
             .spacing(4),
         )
@@ -209,6 +931,319 @@ impl SettingsScreen {
         .padding(Padding::from([16, 0]))
         .width(Length::Fill);
 
+        // Calls section
+        let calls_section = container(
+            column![
+                Space::with_height(24),
+                text("Calls").size(18),
+                Space::with_height(12),
+                checkbox("Call waiting", self.call_waiting)
+                    .on_toggle(SettingsMessage::CallWaitingChanged)
+                    .size(16)
+                    .text_size(14),
+                text("Notify about a second incoming call instead of rejecting it as busy")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                checkbox("Smooth microphone timing", self.frame_pacing)
+                    .on_toggle(SettingsMessage::FramePacingChanged)
+                    .size(16)
+                    .text_size(14),
+                text("Send audio at a steady pace when the microphone delivers it in bursts")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                checkbox("Follow system default device", self.follow_default_device)
+                    .on_toggle(SettingsMessage::FollowDefaultDeviceChanged)
+                    .size(16)
+                    .text_size(14),
+                text("Switch a call to a newly plugged in default device instead of asking")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                checkbox("Prioritize call traffic", self.qos_marking)
+                    .on_toggle(SettingsMessage::QosMarkingChanged)
+                    .size(16)
+                    .text_size(14),
+                text("Mark packets during calls as low-latency for networks that honor it")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+            ]
+            .spacing(4),
+        )
+        .padding(Padding::from([16, 0]))
+        .width(Length::Fill);
+
+        // Archived chats section
+        let chats_section = container(
+            column![
+                Space::with_height(24),
+                text("Archived chats").size(18),
+                Space::with_height(12),
+                checkbox(
+                    "Unarchive on new message",
+                    self.archive_options.unarchive_on_message
+                )
+                .on_toggle(SettingsMessage::UnarchiveOnMessageChanged)
+                .size(16)
+                .text_size(14),
+                checkbox(
+                    "Unread badges for archived chats",
+                    self.archive_options.unread_badges
+                )
+                .on_toggle(SettingsMessage::ArchivedBadgesChanged)
+                .size(16)
+                .text_size(14),
+            ]
+            .spacing(4),
+        )
+        .padding(Padding::from([16, 0]))
+        .width(Length::Fill);
+
+        // Message history section
+        let mut history_column = column![
+            Space::with_height(24),
+            text("Message history").size(18),
+            Space::with_height(12),
+            checkbox(
+                "Delete old messages",
+                self.retention_options.max_age_days.is_some()
+            )
+            .on_toggle(SettingsMessage::DeleteOldMessagesChanged)
+            .size(16)
+            .text_size(14),
+        ]
+        .spacing(4);
+        if let Some(days) = self.retention_options.max_age_days {
+            history_column = history_column
+                .push(text(format!("Keep messages for {} days", days)).size(14))
+                .push(slider(1..=365, days, SettingsMessage::MessageMaxAgeChanged));
+        }
+        let history_section = container(
+            history_column
+                .push(
+                    text("The latest message of each chat is kept")
+                        .size(12)
+                        .color(colors::text_secondary(theme)),
+                )
+                .push(Space::with_height(8))
+                .push(
+                    text(format!(
+                        "Messages kept in memory per chat: {}",
+                        self.message_history_limit
+                    ))
+                    .size(14),
+                )
+                .push(
+                    slider(
+                        ConfigManager::MIN_HISTORY_LIMIT as u32
+                            ..=ConfigManager::MAX_HISTORY_LIMIT as u32,
+                        self.message_history_limit as u32,
+                        SettingsMessage::MessageHistoryLimitChanged,
+                    )
+                    .step(100u32),
+                )
+                .push(
+                    text("Older messages are loaded again when scrolling up")
+                        .size(12)
+                        .color(colors::text_secondary(theme)),
+                ),
+        )
+        .padding(Padding::from([16, 0]))
+        .width(Length::Fill);
+
+        let mut codecs_column = column![
+            Space::with_height(8),
+            text("Codec of outgoing audio").size(14),
+        ]
+        .spacing(4);
+        for &codec in &self.codecs {
+            let marker = if codec == self.preferred_codec {
+                "●"
+            } else {
+                "○"
+            };
+            codecs_column = codecs_column.push(
+                button(
+                    text(format!(
+                        "{} {}, about {} kbps",
+                        marker,
+                        codec.name(),
+                        codec.typical_bitrate()
+                    ))
+                    .size(14),
+                )
+                .on_press(SettingsMessage::PreferredCodecChanged(codec))
+                .padding([6, 12])
+                .style(if codec == self.preferred_codec {
+                    button::primary
+                } else {
+                    button::secondary
+                }),
+            );
+        }
+        codecs_column = codecs_column.push(
+            text("Codecs available in this build, the codec of a call is shown next to its timer")
+                .size(12)
+                .color(colors::text_secondary(theme)),
+        );
+        let audio_section = container(
+            column![
+                Space::with_height(24),
+                text("Audio").size(18),
+                Space::with_height(12),
+                text(format!("Microphone gain: {:.0}%", self.input_gain * 100.0)).size(14),
+                slider(
+                    0.0..=CallManager::MAX_INPUT_GAIN,
+                    self.input_gain,
+                    SettingsMessage::InputGainChanged
+                )
+                .step(0.05f32),
+                text("Applied on top of the microphone volume of each call")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                text(format!("Ringtone volume: {:.0}%", self.ringtone_volume * 100.0)).size(14),
+                slider(
+                    0.0..=RingtonePlayer::MAX_VOLUME,
+                    self.ringtone_volume,
+                    SettingsMessage::RingtoneVolumeChanged
+                )
+                .step(0.05f32),
+                text("Volume of incoming calls ringing, the volume of calls is not affected")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(8),
+                checkbox(
+                    "Exclusive device access",
+                    self.share_mode == ShareMode::Exclusive
+                )
+                .on_toggle(|exclusive| SettingsMessage::ShareModeChanged(if exclusive {
+                    ShareMode::Exclusive
+                } else {
+                    ShareMode::Shared
+                }))
+                .size(16)
+                .text_size(14),
+                text("Lower latency, other applications cannot use the devices during calls. Shared access is used where exclusive one is not available")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                codecs_column,
+            ]
+            .spacing(4),
+        )
+        .padding(Padding::from([16, 0]))
+        .width(Length::Fill);
+
+        // Images and screen sharing section
+        let system_audio_replace: Element<'_, SettingsMessage> = match self.system_audio {
+            Some(mode) => checkbox("Mute microphone", mode == SystemAudioMode::Replace)
+                .on_toggle(|replace| {
+                    SettingsMessage::SystemAudioChanged(Some(if replace {
+                        SystemAudioMode::Replace
+                    } else {
+                        SystemAudioMode::Mix
+                    }))
+                })
+                .size(16)
+                .text_size(14)
+                .into(),
+            None => Space::with_height(0).into(),
+        };
+        let screen_share_section = container(
+            column![
+                Space::with_height(24),
+                text("Screen sharing").size(18),
+                Space::with_height(12),
+                checkbox("Send system audio", self.system_audio.is_some())
+                    .on_toggle(|enabled| SettingsMessage::SystemAudioChanged(
+                        enabled.then_some(SystemAudioMode::default())
+                    ))
+                    .size(16)
+                    .text_size(14),
+                system_audio_replace,
+                text("Others hear what your applications play while you share the screen, if the system provides a loopback device")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+            ]
+            .spacing(4),
+        )
+        .padding(Padding::from([16, 0]))
+        .width(Length::Fill);
+
+        let diagnostic_report: Element<'_, SettingsMessage> = match &self.diagnostic_report {
+            _ if self.diagnostic_running => text("Running diagnostic...")
+                .size(12)
+                .color(colors::text_secondary(theme))
+                .into(),
+            Some(Ok(report)) => column![
+                text(report).size(12).font(Font::MONOSPACE),
+                button(text("Copy Report").size(14))
+                    .on_press(SettingsMessage::CopyDiagnostic)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ]
+            .spacing(6)
+            .into(),
+            Some(Err(error)) => text(error).size(12).color(colors::text_error(theme)).into(),
+            None => Space::with_height(0).into(),
+        };
+        let diagnostics_section = container(
+            column![
+                Space::with_height(24),
+                text("Diagnostics").size(18),
+                Space::with_height(12),
+                text("Recent log lines to copy into a bug report")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(4),
+                row![
+                    button(text("View Logs").size(14))
+                        .on_press(SettingsMessage::OpenLogs)
+                        .padding([6, 12])
+                        .style(button::secondary),
+                    button(text("Copy Diagnostics").size(14))
+                        .on_press(SettingsMessage::CopyDiagnosticsBundle)
+                        .padding([6, 12])
+                        .style(button::secondary),
+                    text(if self.diagnostics_bundle_copied {
+                        "Copied without keys and messages"
+                    } else {
+                        ""
+                    })
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+                Space::with_height(12),
+                text("Connectivity check").size(14),
+                text("Tries to connect to an address and reports the discovery and hole punching steps")
+                    .size(12)
+                    .color(colors::text_secondary(theme)),
+                Space::with_height(4),
+                row![
+                    text_input("Peer address", &self.diagnostic_address)
+                        .on_input(SettingsMessage::DiagnosticAddressChanged)
+                        .padding(8)
+                        .size(14),
+                    button(text("Run Diagnostic").size(14))
+                        .on_press_maybe(
+                            (!self.diagnostic_running && !self.diagnostic_address.trim().is_empty())
+                                .then_some(SettingsMessage::RunDiagnostic)
+                        )
+                        .padding([6, 12])
+                        .style(button::secondary),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+                diagnostic_report,
+            ]
+            .spacing(4),
+        )
+        .padding(Padding::from([16, 0]))
+        .width(Length::Fill);
+
         // Future settings sections placeholder
         let future_section = container(column![
             Space::with_height(24),
@@ -252,8 +1287,23 @@ impl SettingsScreen {
         .padding(Padding::ZERO.top(16));
         let content = column![
             header,
-            scrollable(column![server_section, appearance_section, future_section,].spacing(0))
-                .height(Length::Fill),
+            scrollable(
+                column![
+                    profile_section,
+                    server_section,
+                    appearance_section,
+                    calls_section,
+                    audio_section,
+                    chats_section,
+                    history_section,
+                    screen_share_section,
+                    security_section,
+                    diagnostics_section,
+                    future_section,
+                ]
+                .spacing(0)
+            )
+            .height(Length::Fill),
             actions,
         ]
         .spacing(0);
@@ -282,6 +1332,280 @@ impl Screen for SettingsScreen {
                     // Update theme in context
                     ctx.theme = new_theme;
 
+                    // Apply and persist call waiting
+                    if self.call_waiting != self.original_call_waiting {
+                        let call_waiting = self.call_waiting;
+                        self.original_call_waiting = call_waiting;
+                        if let Some(ref call_mgr) = ctx.call_manager {
+                            call_mgr.set_call_waiting(call_waiting);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_call_waiting(call_waiting).await {
+                                    tracing::error!("Failed to save call waiting: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist wake recovery
+                    if self.wake_recovery != self.original_wake_recovery {
+                        let wake_recovery = self.wake_recovery;
+                        self.original_wake_recovery = wake_recovery;
+                        if let Some(ref contact_mgr) = ctx.contact_manager {
+                            contact_mgr.set_wake_recovery(wake_recovery);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_wake_recovery(wake_recovery).await
+                                {
+                                    tracing::error!("Failed to save wake recovery: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist focus refresh
+                    if self.focus_refresh != self.original_focus_refresh {
+                        let focus_refresh = self.focus_refresh;
+                        self.original_focus_refresh = focus_refresh;
+                        if let Some(ref contact_mgr) = ctx.contact_manager {
+                            contact_mgr.set_focus_refresh(focus_refresh);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_focus_refresh(focus_refresh).await
+                                {
+                                    tracing::error!("Failed to save focus refresh: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist frame pacing
+                    if self.frame_pacing != self.original_frame_pacing {
+                        let frame_pacing = self.frame_pacing;
+                        self.original_frame_pacing = frame_pacing;
+                        if let Some(ref call_mgr) = ctx.call_manager {
+                            call_mgr.set_frame_pacing(frame_pacing);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_frame_pacing(frame_pacing).await {
+                                    tracing::error!("Failed to save frame pacing: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist following of the default device
+                    if self.follow_default_device != self.original_follow_default_device {
+                        let follow = self.follow_default_device;
+                        self.original_follow_default_device = follow;
+                        if let Some(ref call_mgr) = ctx.call_manager {
+                            call_mgr.set_follow_default_device(follow);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_follow_default_device(follow).await
+                                {
+                                    tracing::error!(
+                                        "Failed to save follow default device: {}",
+                                        err
+                                    );
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist QoS marking
+                    if self.qos_marking != self.original_qos_marking {
+                        let qos_marking = self.qos_marking;
+                        self.original_qos_marking = qos_marking;
+                        if let Some(ref call_mgr) = ctx.call_manager {
+                            let call_mgr = call_mgr.clone();
+                            tokio::spawn(
+                                async move { call_mgr.set_qos_marking(qos_marking).await },
+                            );
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_qos_marking(qos_marking).await {
+                                    tracing::error!("Failed to save QoS marking: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist archive options
+                    if self.archive_options != self.original_archive_options {
+                        let options = self.archive_options;
+                        self.original_archive_options = options;
+                        if let Some(ref chat_mgr) = ctx.chat_manager {
+                            chat_mgr.set_archive_options(options);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_archive_options(options).await {
+                                    tracing::error!("Failed to save archive options: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist retention options
+                    if self.retention_options != self.original_retention_options {
+                        let options = self.retention_options;
+                        self.original_retention_options = options;
+                        if let Some(ref chat_mgr) = ctx.chat_manager {
+                            chat_mgr.set_retention_options(options);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_retention_options(options).await {
+                                    tracing::error!("Failed to save retention options: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist the in-memory history limit
+                    if self.message_history_limit != self.original_message_history_limit {
+                        let limit = self.message_history_limit;
+                        self.original_message_history_limit = limit;
+                        ctx.message_history_limit = limit;
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_message_history_limit(limit).await
+                                {
+                                    tracing::error!(
+                                        "Failed to save message history limit: {}",
+                                        err
+                                    );
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist microphone gain
+                    if self.input_gain != self.original_input_gain {
+                        let gain = self.input_gain;
+                        self.original_input_gain = gain;
+                        if let Some(ref call_mgr) = ctx.call_manager {
+                            let call_mgr = call_mgr.clone();
+                            tokio::spawn(async move { call_mgr.set_input_gain(gain).await });
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_input_gain(gain).await {
+                                    tracing::error!("Failed to save input gain: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist ringtone volume
+                    if self.ringtone_volume != self.original_ringtone_volume {
+                        let volume = self.ringtone_volume;
+                        self.original_ringtone_volume = volume;
+                        let ringtone = ctx.ringtone_player.clone();
+                        tokio::spawn(async move { ringtone.lock().await.set_volume(volume) });
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_ringtone_volume(volume).await {
+                                    tracing::error!("Failed to save ringtone volume: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist the preferred codec
+                    if self.preferred_codec != self.original_preferred_codec {
+                        let codec = self.preferred_codec;
+                        self.original_preferred_codec = codec;
+                        if let Some(ref call_mgr) = ctx.call_manager {
+                            call_mgr.set_preferred_codec(codec);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_preferred_codec(codec).await {
+                                    tracing::error!("Failed to save preferred codec: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist the audio share mode
+                    if self.share_mode != self.original_share_mode {
+                        let mode = self.share_mode;
+                        self.original_share_mode = mode;
+                        if let Some(ref call_mgr) = ctx.call_manager {
+                            call_mgr.set_share_mode(mode);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_audio_share_mode(mode).await {
+                                    tracing::error!("Failed to save audio share mode: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist the system audio of screen share
+                    if self.system_audio != self.original_system_audio {
+                        let mode = self.system_audio;
+                        self.original_system_audio = mode;
+                        if let Some(ref call_mgr) = ctx.call_manager {
+                            call_mgr.set_system_audio(mode);
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_system_audio(mode).await {
+                                    tracing::error!("Failed to save system audio mode: {}", err);
+                                }
+                            });
+                        }
+                    }
+
+                    // Apply and persist the server token
+                    if self.server_token != self.original_server_token {
+                        let token = Some(self.server_token.trim().to_string())
+                            .filter(|token| !token.is_empty());
+                        self.original_server_token = self.server_token.clone();
+                        ctx.server_token = token.clone();
+                        if let Some(ref contact_mgr) = ctx.contact_manager {
+                            let cm = contact_mgr.clone();
+                            let token = token.clone();
+                            tokio::spawn(async move {
+                                if let Err(err) = cm.change_server_token(token).await {
+                                    tracing::error!("Failed to update server token: {}", err);
+                                }
+                            });
+                        }
+                        if let Some(ref storage) = ctx.storage {
+                            let config_mgr = ConfigManager::new(storage.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = config_mgr.set_server_token(token).await {
+                                    tracing::error!("Failed to save server token: {}", err);
+                                }
+                            });
+                        }
+                    }
+
                     // Parse and validate the address
                     if let Ok(addr) = std::net::SocketAddr::from_str(&new_server) {
                         // Check if server address actually changed
@@ -335,10 +1659,10 @@ impl Screen for SettingsScreen {
                 } else {
                     // Send updated connection status
                     if let Some(ref contact_mgr) = ctx.contact_manager {
-                        let is_connected = contact_mgr.is_connected();
+                        let status = contact_mgr.connection_status();
                         let ui_tx = ctx.ui_event_tx.clone();
                         tokio::spawn(async move {
-                            let _ = ui_tx.send(UiEvent::TransportConnected(is_connected)).await;
+                            let _ = ui_tx.send(UiEvent::ConnectionStatus(status)).await;
                         });
                     }
                     // Return to chat screen after successful save
@@ -357,18 +1681,197 @@ impl Screen for SettingsScreen {
                 }
                 ScreenCommand::None
             }
+            SettingsMessage::SetAvatar | SettingsMessage::RemoveAvatar => {
+                let Some(ref storage) = ctx.storage else {
+                    return ScreenCommand::None;
+                };
+                let config_mgr = ConfigManager::new(storage.clone());
+                let path = (matches!(message, SettingsMessage::SetAvatar))
+                    .then(|| self.avatar_path.trim().to_string());
+                let cmd = Task::perform(
+                    async move {
+                        let image = match path {
+                            Some(path) => Some(
+                                tokio::fs::read(&path)
+                                    .await
+                                    .map_err(|e| format!("Cannot read '{}': {}", path, e))?,
+                            ),
+                            None => None,
+                        };
+                        config_mgr
+                            .set_avatar(image)
+                            .await
+                            .map_err(|e| format!("Failed to save avatar: {}", e))
+                    },
+                    SettingsMessage::AvatarSaved,
+                );
+                ScreenCommand::Message(cmd)
+            }
+            SettingsMessage::AvatarSaved(ref result) => {
+                if let Ok(profile) = result {
+                    ctx.profile = Some(profile.clone());
+                    // Let contacts know about the new avatar
+                    if let Some(ref contact_mgr) = ctx.contact_manager {
+                        let cm = contact_mgr.clone();
+                        let profile = profile.clone();
+                        tokio::spawn(async move {
+                            cm.update_own_profile(profile).await;
+                        });
+                    }
+                }
+                ScreenCommand::Message(self.update_internal(message))
+            }
+            SettingsMessage::RotateKeyConfirmed => {
+                let Some(ref storage) = ctx.storage else {
+                    return ScreenCommand::None;
+                };
+                let config_mgr = ConfigManager::new(storage.clone());
+                let contact_mgr = ctx.contact_manager.clone();
+                let passphrase = self.rotate_key_passphrase.clone();
+                let cmd = Task::perform(
+                    async move {
+                        let (private_key, packet) = config_mgr
+                            .rotate_private_key(&passphrase)
+                            .await
+                            .map_err(|e| format!("Failed to rotate key: {}", e))?;
+                        if let Some(cm) = contact_mgr {
+                            cm.announce_key_rotation(packet).await;
+                            // Offline contacts get the announcement when they connect
+                            if let Err(err) = config_mgr
+                                .set_pending_key_rotations(&cm.pending_key_rotations())
+                                .await
+                            {
+                                tracing::warn!(?err, "Failed to save pending key rotations");
+                            }
+                        }
+                        private_key
+                            .public_key()
+                            .to_address()
+                            .map(|a| a.to_string())
+                            .map_err(|e| format!("Invalid address: {}", e))
+                    },
+                    SettingsMessage::RotateKeyComplete,
+                );
+                ScreenCommand::Message(cmd)
+            }
+            SettingsMessage::Unblock(address) => {
+                let (Some(storage), Some(contact_mgr)) =
+                    (ctx.storage.clone(), ctx.contact_manager.clone())
+                else {
+                    return ScreenCommand::None;
+                };
+                let cmd = Task::perform(
+                    async move {
+                        contact_mgr.unblock_address(address);
+                        ConfigManager::new(storage)
+                            .set_blocked_addresses(&contact_mgr.blocked_addresses())
+                            .await
+                            .map(|_| address)
+                            .map_err(|e| format!("Failed to unblock: {}", e))
+                    },
+                    SettingsMessage::Unblocked,
+                );
+                ScreenCommand::Message(cmd)
+            }
+            SettingsMessage::ClearSessions => {
+                let Some(ref storage) = ctx.storage else {
+                    return ScreenCommand::None;
+                };
+                let config_mgr = ConfigManager::new(storage.clone());
+                let cmd = Task::perform(
+                    async move {
+                        config_mgr
+                            .clear_recent_sessions()
+                            .await
+                            .map_err(|e| format!("Failed to clear sessions: {}", e))
+                    },
+                    SettingsMessage::SessionsCleared,
+                );
+                ScreenCommand::Message(cmd)
+            }
+            SettingsMessage::OpenLogs => ScreenCommand::ChangeScreen(ScreenType::Logs),
+            SettingsMessage::CopyDiagnosticsBundle => {
+                let connection = ctx
+                    .contact_manager
+                    .as_ref()
+                    .map(|v| v.connection_status())
+                    .unwrap_or_default();
+                let mut bundle = DiagnosticsBundle::new(connection);
+                bundle.connectivity = self.diagnostic_report.clone().and_then(Result::ok);
+                bundle.logs = LogBuffer::global().lines(Level::DEBUG);
+                bundle.secrets = [
+                    self.server_token.clone(),
+                    self.original_server_token.clone(),
+                ]
+                .into_iter()
+                .chain(ctx.server_token.clone())
+                .collect();
+                let call_manager = ctx.call_manager.clone();
+                let cmd = Task::perform(
+                    async move {
+                        if let Some(call_manager) = call_manager {
+                            bundle.codecs = call_manager.codec_capabilities().await.codecs;
+                            bundle.call_stats = call_manager.decoder_stats().await;
+                        } else {
+                            bundle.codecs = CodecType::available();
+                        }
+                        bundle.to_string()
+                    },
+                    SettingsMessage::DiagnosticsBundleReady,
+                );
+                ScreenCommand::Message(cmd)
+            }
+            SettingsMessage::RunDiagnostic => {
+                let address = match Address::from_str(self.diagnostic_address.trim()) {
+                    Ok(v) => v,
+                    Err(err) => {
+                        self.diagnostic_report = Some(Err(format!("Invalid address: {}", err)));
+                        return ScreenCommand::None;
+                    }
+                };
+                let Some(contact_mgr) = ctx.contact_manager.clone() else {
+                    self.diagnostic_report = Some(Err("Not connected".to_string()));
+                    return ScreenCommand::None;
+                };
+                self.diagnostic_running = true;
+                let cmd = Task::perform(
+                    async move {
+                        contact_mgr
+                            .diagnose(address)
+                            .await
+                            .map(|report| report.to_string())
+                            .map_err(|e| format!("Diagnostic failed: {}", e))
+                    },
+                    SettingsMessage::DiagnosticComplete,
+                );
+                ScreenCommand::Message(cmd)
+            }
             SettingsMessage::CancelSettings => {
                 // Reset and return to chat screen
                 self.server_address = self.original_server_address.clone();
+                self.server_token = self.original_server_token.clone();
+                self.wake_recovery = self.original_wake_recovery;
+                self.focus_refresh = self.original_focus_refresh;
                 self.theme = self.original_theme;
+                self.call_waiting = self.original_call_waiting;
+                self.frame_pacing = self.original_frame_pacing;
+                self.follow_default_device = self.original_follow_default_device;
+                self.qos_marking = self.original_qos_marking;
+                self.archive_options = self.original_archive_options;
+                self.message_history_limit = self.original_message_history_limit;
+                self.input_gain = self.original_input_gain;
+                self.ringtone_volume = self.original_ringtone_volume;
+                self.preferred_codec = self.original_preferred_codec;
+                self.share_mode = self.original_share_mode;
+                self.system_audio = self.original_system_audio;
                 self.has_changes = false;
                 self.error_message = None;
                 // Send updated connection status
                 if let Some(ref contact_mgr) = ctx.contact_manager {
-                    let is_connected = contact_mgr.is_connected();
+                    let status = contact_mgr.connection_status();
                     let ui_tx = ctx.ui_event_tx.clone();
                     tokio::spawn(async move {
-                        let _ = ui_tx.send(UiEvent::TransportConnected(is_connected)).await;
+                        let _ = ui_tx.send(UiEvent::ConnectionStatus(status)).await;
                     });
                 }
                 if let Some(ref profile) = ctx.profile {
@@ -397,3 +1900,17 @@ impl Screen for SettingsScreen {
         self.view(theme)
     }
 }
+
+/// Formats a session as "YYYY-MM-DD HH:MM server, from public address" in
+/// the local time zone.
+fn format_session(session: &SessionRecord) -> String {
+    let time = session
+        .connect_time
+        .0
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    match session.public_addr {
+        Some(public_addr) => format!("{time} {}, from {public_addr}", session.server_addr),
+        None => format!("{time} {}", session.server_addr),
+    }
+}
@@ -4,9 +4,10 @@ use iced::widget::{Space, button, column, container, row, text, text_input};
 use iced::{Alignment, Element, Length, Task, Theme};
 use tokio::sync::{Mutex as TokioMutex, mpsc};
 
+use crate::audio::RingtonePlayer;
 use crate::call::CallManager;
 use crate::chat::ChatManager;
-use crate::config::ConfigManager;
+use crate::config::{ConfigManager, WindowGeometry};
 use crate::contact::ContactManager;
 use crate::packet::ContactProfile;
 use crate::storage::Storage;
@@ -45,6 +46,11 @@ pub struct InitSuccess {
     pub call_manager: Arc<CallManager>,
     pub profile: ContactProfile,
     pub server_addr: std::net::SocketAddr,
+    pub server_token: Option<String>,
+    pub ringtone_volume: f32,
+    pub message_history_limit: usize,
+    // Saved geometry to restore, `None` for a new account
+    pub window_geometry: Option<WindowGeometry>,
 }
 
 impl std::fmt::Debug for InitSuccess {
@@ -56,6 +62,13 @@ impl std::fmt::Debug for InitSuccess {
             .field("call_manager", &"Arc<CallManager>")
             .field("profile", &self.profile)
             .field("server_addr", &self.server_addr)
+            .field(
+                "server_token",
+                &self.server_token.as_ref().map(|_| "<token>"),
+            )
+            .field("ringtone_volume", &self.ringtone_volume)
+            .field("message_history_limit", &self.message_history_limit)
+            .field("window_geometry", &self.window_geometry)
             .finish()
     }
 }
@@ -145,23 +158,39 @@ impl Screen for UnlockScreen {
                         ctx.call_manager = Some(success.call_manager.clone());
                         ctx.profile = Some(success.profile.clone());
                         ctx.server_addr = Some(success.server_addr);
+                        ctx.server_token = success.server_token.clone();
+                        ctx.pending_window_geometry = success.window_geometry;
+                        ctx.message_history_limit = success.message_history_limit;
+                        let ringtone = ctx.ringtone_player.clone();
+                        let ringtone_volume = success.ringtone_volume;
+                        tokio::spawn(async move {
+                            ringtone.lock().await.set_volume(ringtone_volume);
+                        });
                         // Initialize contacts list and connection status
                         let ui_tx = ctx.ui_event_tx.clone();
                         let cm_for_list = ctx.chat_manager.clone();
                         let contact_mgr = ctx.contact_manager.clone();
+                        let calls = success.call_manager.clone();
                         tokio::spawn(async move {
                             // Send transport connection status
                             if let Some(ref cm) = contact_mgr {
-                                let is_connected = cm.is_connected();
-                                let _ = ui_tx.send(UiEvent::TransportConnected(is_connected)).await;
+                                let status = cm.connection_status();
+                                let _ = ui_tx.send(UiEvent::ConnectionStatus(status)).await;
                             }
                             if let Some(cm) = cm_for_list {
                                 for chat_handle in cm.list_contact_chats().await {
                                     let contact = chat_handle.contact();
+                                    calls.set_auto_answer(contact.address, contact.auto_answer);
                                     let _ = ui_tx
                                         .send(UiEvent::ContactAccepted {
                                             address: contact.address.to_string(),
                                             name: contact.local_name.unwrap_or(contact.name),
+                                            avatar: contact.avatar,
+                                            archived: contact.archived,
+                                            pin_order: contact.pinned.then_some(contact.pin_order),
+                                            verified: contact.verified,
+                                            muted: contact.muted,
+                                            auto_answer: contact.auto_answer,
                                         })
                                         .await;
                                     let _ = ui_tx
@@ -265,18 +294,58 @@ async fn unlock_flow(
     let cfg = ConfigManager::new(storage.clone());
     let profile = cfg.get_profile().await.map_err(|v| v.to_string())?;
     let server_addr = cfg.get_server_addr().await.map_err(|v| v.to_string())?;
-    let private_key = cfg.get_private_key().await.map_err(|v| v.to_string())?;
+    let server_token = cfg.get_server_token().await.map_err(|v| v.to_string())?;
+    let private_key = cfg
+        .get_private_key(&password)
+        .await
+        .map_err(|v| v.to_string())?;
     let listener = Arc::new(UiEventListener::new(ui_event_tx.clone()));
     let contact_manager = Arc::new(
-        ContactManager::with_listener(server_addr, private_key, profile.clone(), listener.clone())
-            .await,
+        ContactManager::with_server_token(
+            server_addr,
+            server_token.clone(),
+            private_key,
+            profile.clone(),
+            listener.clone(),
+        )
+        .await,
     );
+    contact_manager.set_wake_recovery(cfg.get_wake_recovery().await.unwrap_or(true));
+    contact_manager.set_focus_refresh(cfg.get_focus_refresh().await.unwrap_or(true));
+    contact_manager.set_auto_accept_keys(cfg.get_auto_accept_keys().await.unwrap_or_default());
+    contact_manager.set_blocked_addresses(cfg.get_blocked_addresses().await.unwrap_or_default());
+    contact_manager
+        .set_pending_key_rotations(cfg.get_pending_key_rotations().await.unwrap_or_default());
     let chat_manager = Arc::new(
         ChatManager::with_listener(storage.clone(), contact_manager.clone(), listener.clone())
             .await
             .map_err(|e| format!("ChatManager init failed: {}", e))?,
     );
+    chat_manager.set_archive_options(cfg.get_archive_options().await.unwrap_or_default());
+    chat_manager.set_retention_options(cfg.get_retention_options().await.unwrap_or_default());
     let call_manager = CallManager::with_listener(contact_manager.clone(), listener.clone());
+    call_manager.set_call_waiting(cfg.get_call_waiting().await.unwrap_or_default());
+    call_manager.set_frame_pacing(cfg.get_frame_pacing().await.unwrap_or_default());
+    call_manager
+        .set_follow_default_device(cfg.get_follow_default_device().await.unwrap_or_default());
+    call_manager
+        .set_qos_marking(cfg.get_qos_marking().await.unwrap_or_default())
+        .await;
+    call_manager.set_preferred_codec(cfg.get_preferred_codec().await.unwrap_or_default());
+    call_manager.set_share_mode(cfg.get_audio_share_mode().await.unwrap_or_default());
+    call_manager.set_system_audio(cfg.get_system_audio().await.unwrap_or_default());
+    call_manager
+        .set_input_gain(cfg.get_input_gain().await.unwrap_or(1.0))
+        .await;
+    let ringtone_volume = cfg
+        .get_ringtone_volume()
+        .await
+        .unwrap_or(RingtonePlayer::DEFAULT_VOLUME);
+    let message_history_limit = cfg
+        .get_message_history_limit()
+        .await
+        .unwrap_or(ConfigManager::DEFAULT_HISTORY_LIMIT);
+    let window_geometry = cfg.get_window_geometry().await.unwrap_or_default();
     Ok(InitSuccess {
         storage,
         contact_manager,
@@ -284,5 +353,9 @@ async fn unlock_flow(
         call_manager,
         profile,
         server_addr,
+        server_token,
+        ringtone_volume,
+        message_history_limit,
+        window_geometry,
     })
 }
@@ -106,6 +106,32 @@ pub mod styles {
         }
     }
 
+    /// Style for connection status indicator (reconnecting)
+    pub fn status_reconnecting(theme: &Theme) -> container::Style {
+        let color = colors::text_warning(theme);
+        container::Style {
+            background: Some(iced::Background::Color(color)),
+            border: iced::Border {
+                color,
+                width: 2.0,
+                radius: 6.0.into(),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Style for a small round indicator of the given color
+    pub fn indicator_dot(color: Color) -> container::Style {
+        container::Style {
+            background: Some(iced::Background::Color(color)),
+            border: iced::Border {
+                radius: 4.0.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
     /// Style for connection status indicator (disconnected)
     pub fn status_disconnected(theme: &Theme) -> container::Style {
         let palette = theme.extended_palette();
@@ -401,6 +427,10 @@ pub mod colors {
         theme.extended_palette().success.strong.color
     }
 
+    pub fn text_warning(_theme: &Theme) -> Color {
+        Color::from_rgb8(0xE0, 0xA0, 0x20)
+    }
+
     pub fn background_base(theme: &Theme) -> Color {
         theme.extended_palette().background.base.color
     }
@@ -473,4 +503,13 @@ pub mod colors {
     pub fn message_pending_border(theme: &Theme) -> Color {
         theme.extended_palette().background.strong.color
     }
+
+    /// Message bubble colors - outgoing failed to send
+    pub fn message_failed_bg(theme: &Theme) -> Color {
+        theme.extended_palette().danger.weak.color
+    }
+
+    pub fn message_failed_border(theme: &Theme) -> Color {
+        theme.extended_palette().danger.base.color
+    }
 }
@@ -0,0 +1,86 @@
+use ntied::audio::{
+    AdpcmDecoder, AdpcmEncoder, AdpcmVariant, AudioDecoder, AudioEncoder, CodecParams, CodecType,
+    create_decoder, create_encoder,
+};
+
+const VARIANTS: [AdpcmVariant; 2] = [AdpcmVariant::Ima, AdpcmVariant::Microsoft];
+
+fn sine_frame(frame: usize, channels: usize) -> Vec<f32> {
+    (0..960 * channels)
+        .map(|i| {
+            let t = (frame * 960 + i / channels) as f32 / 48000.0;
+            (2.0 * std::f32::consts::PI * 440.0 * t).sin() * 0.5
+        })
+        .collect()
+}
+
+/// Mean squared error of decoded frames, `None` if the decoder fails.
+fn roundtrip_error(encode: AdpcmVariant, decode: AdpcmVariant, channels: u16) -> Option<f32> {
+    let mut encoder = AdpcmEncoder::new(channels, encode).unwrap();
+    let mut decoder = AdpcmDecoder::new(channels, decode).unwrap();
+    let mut error = 0.0;
+    let mut count = 0;
+    for frame in 0..5 {
+        let samples = sine_frame(frame, channels as usize);
+        let decoded = decoder.decode(&encoder.encode(&samples).unwrap()).ok()?;
+        if decoded.len() != samples.len() {
+            return None;
+        }
+        error += samples
+            .iter()
+            .zip(&decoded)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>();
+        count += samples.len();
+    }
+    Some(error / count as f32)
+}
+
+#[test]
+fn test_matched_variants_reconstruct() {
+    for variant in VARIANTS {
+        for channels in [1, 2] {
+            let error = roundtrip_error(variant, variant, channels).unwrap();
+            assert!(error < 1e-3, "{variant:?}/{channels}ch error {error}");
+        }
+    }
+}
+
+#[test]
+fn test_mismatched_variants_are_detected() {
+    for (encode, decode) in [
+        (AdpcmVariant::Ima, AdpcmVariant::Microsoft),
+        (AdpcmVariant::Microsoft, AdpcmVariant::Ima),
+    ] {
+        for channels in [1, 2] {
+            // Either the frame cannot be decoded or the audio is garbage
+            if let Some(error) = roundtrip_error(encode, decode, channels) {
+                assert!(error > 1e-2, "{encode:?}->{decode:?} error {error}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_variant_is_threaded_through_params() {
+    let params = CodecParams::builder(CodecType::ADPCM)
+        .adpcm_variant(AdpcmVariant::Microsoft)
+        .build()
+        .unwrap();
+    assert_eq!(CodecParams::adpcm().adpcm_variant, AdpcmVariant::Ima);
+    let mut encoder = create_encoder(CodecType::ADPCM, &params).unwrap();
+    let mut decoder = create_decoder(CodecType::ADPCM, &params).unwrap();
+    let samples = sine_frame(0, 1);
+    let encoded = encoder.encode(&samples).unwrap();
+    let mut ms_decoder = AdpcmDecoder::new(1, AdpcmVariant::Microsoft).unwrap();
+    assert_eq!(
+        decoder.decode(&encoded).unwrap(),
+        ms_decoder.decode(&encoded).unwrap()
+    );
+    assert!(
+        CodecParams::builder(CodecType::Raw)
+            .adpcm_variant(AdpcmVariant::Microsoft)
+            .build()
+            .is_err()
+    );
+}
@@ -0,0 +1,27 @@
+#![cfg(not(feature = "audio"))]
+
+use ntied::audio::{AudioDisabled, AudioHost as _, AudioManager, CpalHost};
+use ntied::call::CallEndReason;
+
+#[test]
+fn test_audio_is_not_supported() {
+    assert!(!AudioManager::is_supported());
+}
+
+#[tokio::test]
+async fn test_device_lists_report_disabled_audio() {
+    let err = AudioManager::list_input_devices().await.unwrap_err();
+    assert!(err.is::<AudioDisabled>());
+    let err = AudioManager::list_output_devices().await.unwrap_err();
+    assert!(err.is::<AudioDisabled>());
+    assert!(CpalHost.default_input_device().is_none());
+    assert!(CpalHost.default_output_device().is_none());
+}
+
+#[test]
+fn test_call_end_reason_explains_disabled_audio() {
+    assert_eq!(
+        CallEndReason::AudioDisabled.to_string(),
+        "Audio is not supported by this build, calls are disabled"
+    );
+}
@@ -0,0 +1,91 @@
+use std::io::Cursor;
+
+use image::{ImageFormat, Rgba, RgbaImage};
+use ntied::avatar::{
+    AVATAR_SIZE, MAX_AVATAR_BYTES, circle_avatar, normalize_avatar, sanitize_avatar,
+};
+
+fn encode_png(image: &RgbaImage) -> Vec<u8> {
+    let mut data = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+        .expect("failed to encode png");
+    data
+}
+
+fn noise_png(width: u32, height: u32) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    let image = RgbaImage::from_fn(width, height, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let [r, g, b, _] = state.to_le_bytes();
+        Rgba([r, g, b, 255])
+    });
+    encode_png(&image)
+}
+
+#[test]
+fn test_normalize_avatar_downscales_to_square() {
+    let data = encode_png(&RgbaImage::from_pixel(640, 480, Rgba([200, 10, 10, 255])));
+    let avatar = normalize_avatar(&data).expect("normalize failed");
+    assert!(avatar.len() <= MAX_AVATAR_BYTES);
+    let image = image::load_from_memory(&avatar).expect("decode failed");
+    assert_eq!((image.width(), image.height()), (AVATAR_SIZE, AVATAR_SIZE));
+}
+
+#[test]
+fn test_normalize_avatar_keeps_small_image_size() {
+    let data = encode_png(&RgbaImage::from_pixel(32, 48, Rgba([0, 0, 255, 255])));
+    let avatar = normalize_avatar(&data).expect("normalize failed");
+    let image = image::load_from_memory(&avatar).expect("decode failed");
+    assert_eq!((image.width(), image.height()), (32, 32));
+}
+
+#[test]
+fn test_normalize_avatar_rejects_invalid_data() {
+    assert!(normalize_avatar(b"not an image").is_err());
+}
+
+#[test]
+fn test_sanitize_avatar_rejects_oversized_data() {
+    let data = noise_png(256, 256);
+    assert!(data.len() > MAX_AVATAR_BYTES);
+    assert!(sanitize_avatar(&data).is_none());
+}
+
+#[test]
+fn test_sanitize_avatar_rejects_large_dimensions() {
+    // Solid color compresses well, so the encoded size stays below the limit.
+    let data = encode_png(&RgbaImage::from_pixel(1024, 1024, Rgba([1, 2, 3, 255])));
+    assert!(data.len() <= MAX_AVATAR_BYTES);
+    assert!(sanitize_avatar(&data).is_none());
+    let data = encode_png(&RgbaImage::from_pixel(
+        AVATAR_SIZE + 1,
+        8,
+        Rgba([1, 2, 3, 255]),
+    ));
+    assert!(sanitize_avatar(&data).is_none());
+}
+
+#[test]
+fn test_sanitize_avatar_keeps_normalized_avatar() {
+    let data = encode_png(&RgbaImage::from_pixel(640, 480, Rgba([1, 2, 3, 255])));
+    let avatar = normalize_avatar(&data).expect("normalize failed");
+    let sanitized = sanitize_avatar(&avatar).expect("avatar dropped");
+    let image = image::load_from_memory(&sanitized).expect("decode failed");
+    assert_eq!((image.width(), image.height()), (AVATAR_SIZE, AVATAR_SIZE));
+    // Smaller avatars are cropped to a square
+    let data = encode_png(&RgbaImage::from_pixel(48, 32, Rgba([1, 2, 3, 255])));
+    let sanitized = sanitize_avatar(&data).expect("avatar dropped");
+    let image = image::load_from_memory(&sanitized).expect("decode failed");
+    assert_eq!((image.width(), image.height()), (32, 32));
+}
+
+#[test]
+fn test_circle_avatar_clears_corners() {
+    let data = encode_png(&RgbaImage::from_pixel(64, 64, Rgba([9, 9, 9, 255])));
+    let image = circle_avatar(&data).expect("circle failed");
+    assert_eq!(image.get_pixel(0, 0)[3], 0);
+    assert_eq!(image.get_pixel(32, 32)[3], 255);
+}
@@ -0,0 +1,945 @@
+#![cfg(feature = "audio")]
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ntied::audio::{
+    AdpcmEncoder, AdpcmVariant, AudioConfig, AudioEncoder, AudioHost, CodecParams, CodecType,
+    ContentType, Decoder, DeviceChange, DeviceType, NoAudioDevice,
+};
+use ntied::call::{
+    AudioDirection, CallEndReason, CallHandle, CallListener, CallManager, CallMode, CallQuality,
+    CallState, DefaultDeviceAction, OneWayAudioDetector, OneWayAudioEvent, ReconnectEvent,
+    ReconnectGrace, play_call_audio,
+};
+use ntied::contact::{ContactManager, ContactStatus, Usage};
+use ntied::packet::{AudioDataPacket, ContactProfile};
+use ntied_crypto::PrivateKey;
+use ntied_server::Server;
+use ntied_transport::{Address, ToAddress, Transport};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use uuid::Uuid;
+
+async fn start_server() -> (SocketAddr, JoinHandle<()>) {
+    let server = Server::new("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let _ = server.run().await;
+    });
+    (server_addr, handle)
+}
+
+async fn wait_until<F>(mut f: F, tries: usize, delay: Duration) -> bool
+where
+    F: FnMut() -> bool,
+{
+    for _ in 0..tries {
+        if f() {
+            return true;
+        }
+        sleep(delay).await;
+    }
+    false
+}
+
+async fn new_manager(server_addr: SocketAddr, name: &str) -> (Address, Arc<ContactManager>) {
+    let key = PrivateKey::generate().unwrap();
+    let address = key.public_key().to_address().unwrap();
+    let manager = ContactManager::new(
+        server_addr,
+        key,
+        ContactProfile {
+            name: name.to_string(),
+            avatar: None,
+        },
+    )
+    .await;
+    (address, Arc::new(manager))
+}
+
+/// Makes `from` send a contact request to `to` and `to` accept it.
+async fn befriend(from: &ContactManager, to: &ContactManager) {
+    let outgoing = from.connect_contact(to.get_own_address()).await;
+    let address = timeout(Duration::from_secs(5), to.on_incoming_address())
+        .await
+        .expect("Timed out waiting for incoming contact")
+        .unwrap();
+    let incoming = to.connect_contact(address).await;
+    assert!(
+        wait_until(
+            || incoming.profile().is_some(),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    incoming.accept().await.unwrap();
+    assert!(
+        wait_until(
+            || outgoing.status() == ContactStatus::Accepted && outgoing.is_connected(),
+            50,
+            Duration::from_millis(100),
+        )
+        .await
+    );
+}
+
+#[derive(Default)]
+struct CallEvents {
+    events: Mutex<Vec<(String, Address)>>,
+    end_reasons: Mutex<Vec<(CallEndReason, Address)>>,
+}
+
+impl CallEvents {
+    fn push(&self, event: &str, address: Address) {
+        self.events
+            .lock()
+            .unwrap()
+            .push((event.to_string(), address));
+    }
+
+    fn has(&self, event: &str, address: Address) -> bool {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(e, a)| e == event && *a == address)
+    }
+
+    fn count(&self, event: &str, address: Address) -> usize {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(e, a)| e == event && *a == address)
+            .count()
+    }
+
+    fn end_reason(&self, address: Address) -> Option<CallEndReason> {
+        self.end_reasons
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(_, a)| *a == address)
+            .map(|(r, _)| r.clone())
+    }
+}
+
+#[async_trait]
+impl CallListener for CallEvents {
+    async fn on_incoming_call(&self, address: Address) {
+        self.push("incoming", address);
+    }
+    async fn on_outgoing_call(&self, _address: Address) {}
+    async fn on_call_accepted(&self, _address: Address) {}
+    async fn on_call_rejected(&self, address: Address) {
+        self.push("rejected", address);
+    }
+    async fn on_call_connected(&self, address: Address) {
+        self.push("connected", address);
+    }
+    async fn on_call_ended(&self, address: Address, reason: CallEndReason) {
+        self.push("ended", address);
+        self.end_reasons.lock().unwrap().push((reason, address));
+    }
+    async fn on_call_state_changed(&self, _address: Address, _state: &str) {}
+    async fn on_audio_data_received(&self, _address: Address, _data: Vec<u8>) {}
+    async fn on_video_frame_received(&self, _address: Address, _frame: Vec<u8>) {}
+    async fn on_call_waiting(&self, address: Address) {
+        self.push("waiting", address);
+    }
+    async fn on_call_held(&self, address: Address) {
+        self.push("held", address);
+    }
+    async fn on_call_resumed(&self, _address: Address) {}
+    async fn on_call_waiting_ended(&self, _address: Address, _reason: CallEndReason) {}
+    async fn on_speaking_while_muted(&self, _address: Address) {}
+    async fn on_call_summary(&self, _address: Address, _usage: Usage) {}
+    async fn on_call_codec(&self, address: Address, _codec: CodecType) {
+        self.push("codec", address);
+    }
+    async fn on_call_quality(&self, _address: Address, _quality: CallQuality) {}
+    async fn on_one_way_audio_detected(&self, _address: Address, _direction: AudioDirection) {}
+    async fn on_one_way_audio_resolved(&self, _address: Address) {}
+    async fn on_default_device_changed(
+        &self,
+        address: Address,
+        _device_type: DeviceType,
+        action: DefaultDeviceAction,
+    ) {
+        match action {
+            DefaultDeviceAction::Switch(_) => self.push("device_switched", address),
+            DefaultDeviceAction::Offer(_) => self.push("device_offered", address),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_second_call_is_waiting() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    let (carol_addr, carol) = new_manager(server_addr, "Carol").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+    befriend(&carol, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let carol_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    let carol_calls = CallManager::with_listener(carol.clone(), carol_events.clone());
+    bob_calls.set_call_waiting(true);
+    // Let call managers start polling contacts
+    sleep(Duration::from_millis(1500)).await;
+
+    // Alice calls Bob and Bob answers
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Bob did not receive Alice's call"
+    );
+    bob_calls.accept_call(alice_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || alice_events.has("connected", bob_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Alice's call was not connected"
+    );
+
+    // Carol calls Bob while he is talking to Alice
+    carol_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("waiting", carol_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Bob was not notified about the waiting call"
+    );
+    sleep(Duration::from_millis(300)).await;
+    assert!(!carol_events.has("rejected", bob_addr));
+    assert!(!bob_events.has("incoming", carol_addr));
+    let current = bob_calls.get_current_call().await.unwrap();
+    assert_eq!(current.peer_address(), alice_addr);
+    assert_eq!(current.get_state().await, CallState::Connected);
+    let waiting = bob_calls.get_secondary_call().await.unwrap();
+    assert_eq!(waiting.peer_address(), carol_addr);
+    assert_eq!(waiting.get_state().await, CallState::Ringing);
+
+    // Bob holds Alice and answers Carol
+    bob_calls.accept_waiting_call(carol_addr).await.unwrap();
+    assert!(bob_events.has("held", alice_addr));
+    let current = bob_calls.get_current_call().await.unwrap();
+    assert_eq!(current.peer_address(), carol_addr);
+    assert_eq!(current.get_state().await, CallState::Connected);
+    let held = bob_calls.get_secondary_call().await.unwrap();
+    assert_eq!(held.peer_address(), alice_addr);
+    assert_eq!(held.get_state().await, CallState::OnHold);
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_second_call_is_rejected_without_call_waiting() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    let (carol_addr, carol) = new_manager(server_addr, "Carol").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+    befriend(&carol, &bob).await;
+
+    let bob_events = Arc::new(CallEvents::default());
+    let carol_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::new(alice.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    let carol_calls = CallManager::with_listener(carol.clone(), carol_events.clone());
+    sleep(Duration::from_millis(1500)).await;
+
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    bob_calls.accept_call(alice_addr).await.unwrap();
+
+    carol_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || carol_events.has("rejected", bob_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Carol's call was not rejected as busy"
+    );
+    assert_eq!(
+        carol_events.end_reason(bob_addr),
+        Some(CallEndReason::Rejected)
+    );
+    assert!(!bob_events.has("waiting", carol_addr));
+    assert!(bob_calls.get_secondary_call().await.is_none());
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_rejected_call_end_reason() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    sleep(Duration::from_millis(1500)).await;
+
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    bob_calls.reject_call(alice_addr).await.unwrap();
+    assert_eq!(
+        bob_events.end_reason(alice_addr),
+        Some(CallEndReason::Rejected)
+    );
+    assert!(
+        wait_until(
+            || alice_events.has("ended", bob_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    assert_eq!(
+        alice_events.end_reason(bob_addr),
+        Some(CallEndReason::Rejected)
+    );
+    assert_eq!(CallEndReason::Rejected.to_string(), "Call rejected");
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_codec_mismatch_ends_call() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    assert!(!alice_calls.set_codecs(&[]));
+    assert!(alice_calls.set_codecs(&[CodecType::ADPCM]));
+    assert!(bob_calls.set_codecs(&[CodecType::Raw]));
+    sleep(Duration::from_millis(1500)).await;
+
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || alice_events.has("ended", bob_addr) && bob_events.has("ended", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await,
+        "The call with disjoint codecs was not ended"
+    );
+    assert_eq!(
+        bob_events.end_reason(alice_addr),
+        Some(CallEndReason::CodecMismatch {
+            local: vec![CodecType::Raw],
+            remote: vec![CodecType::ADPCM],
+        })
+    );
+    let reason = alice_events.end_reason(bob_addr).unwrap();
+    assert_eq!(
+        reason,
+        CallEndReason::CodecMismatch {
+            local: vec![CodecType::ADPCM],
+            remote: vec![CodecType::Raw],
+        }
+    );
+    assert_eq!(
+        reason.to_string(),
+        "No common audio codec, supported here: ADPCM, by the peer: Raw PCM"
+    );
+    assert!(alice_calls.get_current_call().await.is_none());
+    assert!(bob_calls.get_current_call().await.is_none());
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_auto_answer_trusted_contact() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    let (carol_addr, carol) = new_manager(server_addr, "Carol").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+    befriend(&carol, &bob).await;
+
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::new(alice.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    let carol_calls = CallManager::new(carol.clone());
+    bob_calls.set_auto_answer(alice_addr, true);
+    bob_calls.set_auto_answer_delay(Duration::from_millis(200));
+    sleep(Duration::from_millis(1500)).await;
+
+    // Carol is not trusted, her call keeps ringing
+    carol_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", carol_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    sleep(Duration::from_millis(600)).await;
+    let call = bob_calls.get_current_call().await.unwrap();
+    assert_eq!(call.get_state().await, CallState::Ringing);
+    bob_calls.reject_call(carol_addr).await.unwrap();
+
+    // Alice's call is answered without a manual accept
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("connected", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Alice's call was not auto-answered"
+    );
+    let call = bob_calls.get_current_call().await.unwrap();
+    assert_eq!(call.peer_address(), alice_addr);
+    assert_eq!(call.get_state().await, CallState::Connected);
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_shutdown_ends_call_and_unregisters() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    sleep(Duration::from_millis(1500)).await;
+
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    bob_calls.accept_call(alice_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || alice_events.has("connected", bob_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+
+    ntied::ui::shutdown(Some(alice_calls.clone()), None, Some(alice.clone())).await;
+    assert_eq!(
+        alice_events.end_reason(bob_addr),
+        Some(CallEndReason::LocalHangup)
+    );
+    assert!(alice_calls.get_current_call().await.is_none());
+    assert!(!alice.is_connected());
+    assert!(
+        wait_until(
+            || bob_events.has("ended", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Bob was not notified about the ended call"
+    );
+    assert_eq!(
+        bob_events.end_reason(alice_addr),
+        Some(CallEndReason::RemoteHangup)
+    );
+    // The server no longer knows Alice
+    let carol_key = PrivateKey::generate().unwrap();
+    let carol_addr = carol_key.public_key().to_address().unwrap();
+    let carol = Transport::bind("127.0.0.1:0", carol_addr, carol_key, server_addr)
+        .await
+        .unwrap();
+    sleep(Duration::from_millis(200)).await;
+    assert!(carol.connect(alice_addr).await.is_err());
+    server_handle.abort();
+}
+
+#[test]
+fn test_reconnect_grace() {
+    let mut grace = ReconnectGrace::new(Duration::from_secs(5));
+    let call_id = Uuid::now_v7();
+    let start = Instant::now();
+    assert_eq!(grace.update(call_id, true, start), ReconnectEvent::None);
+    assert_eq!(
+        grace.update(call_id, false, start),
+        ReconnectEvent::Interrupted
+    );
+    let later = start + Duration::from_secs(3);
+    assert_eq!(grace.update(call_id, false, later), ReconnectEvent::None);
+    assert_eq!(grace.update(call_id, true, later), ReconnectEvent::Resumed);
+    assert!(!grace.is_interrupted());
+
+    assert_eq!(
+        grace.update(call_id, false, later),
+        ReconnectEvent::Interrupted
+    );
+    let expired = later + Duration::from_secs(6);
+    assert_eq!(
+        grace.update(call_id, false, expired),
+        ReconnectEvent::Expired
+    );
+    // Another call does not inherit the interruption
+    assert_eq!(
+        grace.update(call_id, false, expired),
+        ReconnectEvent::Interrupted
+    );
+    assert_eq!(
+        grace.update(Uuid::now_v7(), true, expired),
+        ReconnectEvent::None
+    );
+}
+
+#[test]
+fn test_one_way_audio_is_detected() {
+    let mut detector = OneWayAudioDetector::new(Duration::from_secs(5));
+    let call_id = Uuid::now_v7();
+    let start = Instant::now();
+    let usage = |bytes_sent, bytes_received| Usage {
+        bytes_sent,
+        bytes_received,
+    };
+    assert_eq!(detector.update(call_id, usage(100, 100), start), None);
+    // Nothing is sent while incoming audio keeps flowing
+    let mut event = None;
+    for second in 1..=5 {
+        let now = start + Duration::from_secs(second);
+        event = detector.update(call_id, usage(100, 100 + second * 200), now);
+        if second < 5 {
+            assert_eq!(event, None);
+        }
+    }
+    assert_eq!(
+        event,
+        Some(OneWayAudioEvent::Detected(AudioDirection::Outbound))
+    );
+    // Reported once
+    let now = start + Duration::from_secs(6);
+    assert_eq!(detector.update(call_id, usage(100, 1400), now), None);
+    let now = start + Duration::from_secs(7);
+    assert_eq!(
+        detector.update(call_id, usage(300, 1600), now),
+        Some(OneWayAudioEvent::Resolved)
+    );
+    // Silence in both directions is not one-way audio
+    let now = start + Duration::from_secs(20);
+    assert_eq!(detector.update(call_id, usage(300, 1600), now), None);
+}
+
+#[tokio::test]
+async fn test_call_survives_reconnect() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    alice_calls.set_reconnect_grace(Duration::from_secs(10));
+    sleep(Duration::from_millis(1500)).await;
+
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    bob_calls.accept_call(alice_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || alice_events.has("connected", bob_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    let call = alice_calls.get_current_call().await.unwrap();
+    let call_id = call.call_id();
+
+    // Drop the connection with Bob, it is re-established within the grace window
+    let contact = call.contact_handle();
+    contact.reconnect().await.unwrap();
+    assert!(
+        wait_until(|| contact.is_connected(), 100, Duration::from_millis(100)).await,
+        "Alice did not reconnect to Bob"
+    );
+    // Let the call managers notice the reconnect
+    sleep(Duration::from_millis(2500)).await;
+
+    for (calls, events, peer) in [
+        (&alice_calls, &alice_events, bob_addr),
+        (&bob_calls, &bob_events, alice_addr),
+    ] {
+        let current = calls.get_current_call().await.unwrap();
+        assert_eq!(current.call_id(), call_id);
+        assert_eq!(current.get_state().await, CallState::Connected);
+        assert!(!events.has("ended", peer));
+    }
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_input_gain_survives_call_teardown() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    sleep(Duration::from_millis(1500)).await;
+    alice_calls.set_input_gain(1.5).await;
+    alice_calls.set_input_gain(100.0).await;
+    assert_eq!(alice_calls.input_gain(), CallManager::MAX_INPUT_GAIN);
+    alice_calls.set_input_gain(1.5).await;
+
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    bob_calls.accept_call(alice_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || alice_events.has("connected", bob_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    // Audio devices may be missing, the per-call volume exists only with them
+    let _ = alice_calls.set_capture_volume(0.5).await;
+    alice_calls.end_call(bob_addr).await.unwrap();
+    assert_eq!(alice_calls.input_gain(), 1.5);
+    // The per-call volume is dropped with the call audio
+    assert!(alice_calls.get_capture_volume().await.is_err());
+    server_handle.abort();
+}
+
+/// Host of a machine without any audio hardware.
+struct NoDevicesHost;
+
+impl AudioHost for NoDevicesHost {
+    fn input_devices(&self) -> anyhow::Result<Vec<cpal::Device>> {
+        Ok(Vec::new())
+    }
+
+    fn output_devices(&self) -> anyhow::Result<Vec<cpal::Device>> {
+        Ok(Vec::new())
+    }
+
+    fn default_input_device(&self) -> Option<cpal::Device> {
+        None
+    }
+
+    fn default_output_device(&self) -> Option<cpal::Device> {
+        None
+    }
+}
+
+#[tokio::test]
+async fn test_start_call_without_audio_devices() {
+    let (server_addr, server_handle) = start_server().await;
+    let (_, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let _bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    alice_calls.set_audio_host(Arc::new(NoDevicesHost));
+
+    let err = alice_calls.start_call(bob_addr).await.err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<NoAudioDevice>(),
+        Some(&NoAudioDevice(DeviceType::Input))
+    );
+    let reason = alice_events.end_reason(bob_addr).unwrap();
+    assert_eq!(reason, CallEndReason::NoAudioDevice(DeviceType::Input));
+    assert_eq!(reason.to_string(), "No audio input device available");
+    assert!(alice_calls.get_current_call().await.is_none());
+    // The peer is not rung
+    sleep(Duration::from_millis(500)).await;
+    assert!(bob_events.events.lock().unwrap().is_empty());
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_call_connects_on_first_played_frame() {
+    let (server_addr, server_handle) = start_server().await;
+    let (_, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, _bob) = new_manager(server_addr, "Bob").await;
+    let contact = alice.connect_contact(bob_addr).await;
+    let call = CallHandle::new(
+        Uuid::now_v7(),
+        bob_addr,
+        false,
+        contact,
+        Arc::new(CallEvents::default()),
+    );
+    call.set_state(CallState::ConnectingAudio).await;
+    assert!(call.get_state().await.is_established());
+
+    let decoder = Arc::new(Decoder::new(AudioConfig::new(48000, 1), CodecType::ADPCM));
+    let (frame_tx, mut frame_rx) = mpsc::unbounded_channel();
+    let playback = tokio::spawn({
+        let decoder = decoder.clone();
+        let call = call.clone();
+        async move {
+            play_call_audio(&decoder, &call, |frame| {
+                let frame_tx = frame_tx.clone();
+                async move { frame_tx.send(frame).map_err(Into::into) }
+            })
+            .await
+        }
+    });
+    // Nothing was received from the peer yet
+    sleep(Duration::from_millis(200)).await;
+    assert_eq!(call.get_state().await, CallState::ConnectingAudio);
+
+    let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+    for sequence in 0..3 {
+        let samples = vec![0.1; 960];
+        decoder
+            .send_packet(AudioDataPacket {
+                call_id: call.call_id(),
+                sequence,
+                timestamp: sequence as u64 * 20_000,
+                codec: CodecType::ADPCM,
+                channels: 1,
+                data: encoder.encode(&samples).unwrap(),
+                content: ContentType::Speech,
+            })
+            .await
+            .unwrap();
+    }
+    timeout(Duration::from_secs(2), frame_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    // The state advances right after the frame was handed to the output
+    for _ in 0..50 {
+        if call.get_state().await == CallState::Connected {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(call.get_state().await, CallState::Connected);
+    playback.abort();
+    server_handle.abort();
+}
+
+#[test]
+fn test_call_mode_codec_params() {
+    assert_eq!(CallMode::default(), CallMode::Voice);
+    assert_eq!(CallMode::Voice.codec_params(), CodecParams::voice());
+    assert_eq!(CallMode::Presentation.codec_params(), CodecParams::music());
+    let voice = CodecParams::voice();
+    let music = CodecParams::music();
+    assert_eq!(voice.channels, 1);
+    assert_eq!(music.channels, 2);
+    assert!(music.bitrate > voice.bitrate);
+    voice.validate(CodecType::ADPCM).unwrap();
+    music.validate(CodecType::ADPCM).unwrap();
+}
+
+#[tokio::test]
+async fn test_call_mode_selects_codec_params() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    sleep(Duration::from_millis(1500)).await;
+    assert!(alice_calls.codec_params().await.is_none());
+
+    let call = alice_calls
+        .start_call_with_mode(bob_addr, CallMode::Presentation)
+        .await
+        .unwrap();
+    assert_eq!(call.mode(), CallMode::Presentation);
+    assert_eq!(alice_calls.codec_params().await, Some(CodecParams::music()));
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    bob_calls.accept_call(alice_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || alice_events.has("connected", bob_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    // The answering side keeps its own, voice, parameters
+    assert_eq!(bob_calls.codec_params().await, Some(CodecParams::voice()));
+
+    // Leaving the presentation renegotiates the codec with the peer
+    assert!(
+        wait_until(
+            || bob_events.has("codec", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    let codec_events = bob_events.count("codec", alice_addr);
+    alice_calls.set_call_mode(CallMode::Voice).await.unwrap();
+    assert_eq!(alice_calls.call_mode().await, Some(CallMode::Voice));
+    assert_eq!(alice_calls.codec_params().await, Some(CodecParams::voice()));
+    assert!(
+        wait_until(
+            || bob_events.count("codec", alice_addr) > codec_events,
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    alice_calls.end_call(bob_addr).await.unwrap();
+
+    let call = alice_calls.start_call(bob_addr).await.unwrap();
+    assert_eq!(call.mode(), CallMode::Voice);
+    assert_eq!(alice_calls.codec_params().await, Some(CodecParams::voice()));
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_default_device_change_is_offered() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    befriend(&alice, &bob).await;
+
+    let alice_events = Arc::new(CallEvents::default());
+    let bob_events = Arc::new(CallEvents::default());
+    let alice_calls = CallManager::with_listener(alice.clone(), alice_events.clone());
+    let bob_calls = CallManager::with_listener(bob.clone(), bob_events.clone());
+    let change = DeviceChange::DefaultChanged(DeviceType::Output, Some("Headset".to_string()));
+    // Without a call there is nothing to switch
+    assert_eq!(
+        alice_calls.handle_device_change(&change).await.unwrap(),
+        None
+    );
+    sleep(Duration::from_millis(1500)).await;
+
+    alice_calls.start_call(bob_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || bob_events.has("incoming", alice_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    bob_calls.accept_call(alice_addr).await.unwrap();
+    assert!(
+        wait_until(
+            || alice_events.has("connected", bob_addr),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    if alice_calls.get_capture_volume().await.is_err() {
+        println!("No audio devices, skipping test");
+        alice_calls.end_call(bob_addr).await.unwrap();
+        server_handle.abort();
+        return;
+    }
+    // Other changes do not affect the call
+    let removed = DeviceChange::DefaultChanged(DeviceType::Output, None);
+    assert_eq!(
+        alice_calls.handle_device_change(&removed).await.unwrap(),
+        None
+    );
+    assert!(!alice_calls.is_follow_default_device_enabled());
+    assert_eq!(
+        alice_calls.handle_device_change(&change).await.unwrap(),
+        Some(DefaultDeviceAction::Offer("Headset".to_string()))
+    );
+    assert!(alice_events.has("device_offered", bob_addr));
+    assert!(!alice_events.has("device_switched", bob_addr));
+    alice_calls.end_call(bob_addr).await.unwrap();
+    server_handle.abort();
+}
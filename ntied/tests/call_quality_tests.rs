@@ -0,0 +1,44 @@
+use ntied::audio::NetworkQuality;
+use ntied::call::{CallQuality, LossEstimator};
+
+fn bucket(packet_loss: f32, rtt: f32) -> CallQuality {
+    CallQuality::from_network(&NetworkQuality {
+        packet_loss,
+        rtt,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_quality_buckets() {
+    assert_eq!(bucket(0.0, 50.0), CallQuality::Good);
+    assert_eq!(bucket(1.5, 120.0), CallQuality::Good);
+    assert_eq!(bucket(3.0, 50.0), CallQuality::Fair);
+    assert_eq!(bucket(0.0, 250.0), CallQuality::Fair);
+    assert_eq!(bucket(10.0, 50.0), CallQuality::Poor);
+    assert_eq!(bucket(0.0, 500.0), CallQuality::Poor);
+    assert_eq!(bucket(3.0, 450.0), CallQuality::Poor);
+}
+
+#[test]
+fn test_jitter_lowers_quality() {
+    let quality = NetworkQuality {
+        jitter: 50.0,
+        ..Default::default()
+    };
+    assert_eq!(CallQuality::from_network(&quality), CallQuality::Fair);
+}
+
+#[test]
+fn test_loss_estimator() {
+    let mut loss = LossEstimator::default();
+    assert_eq!(loss.take_loss(), None);
+    for sequence in (0..100).filter(|s| s % 10 != 5) {
+        loss.record(sequence);
+    }
+    assert_eq!(loss.take_loss(), Some(10.0));
+    for sequence in 100..150 {
+        loss.record(sequence);
+    }
+    assert_eq!(loss.take_loss(), Some(0.0));
+}
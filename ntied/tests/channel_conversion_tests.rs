@@ -0,0 +1,31 @@
+use ntied::audio::convert_channels;
+
+#[test]
+fn test_mono_to_mono() {
+    let mono = vec![0.5, -0.25, 0.1];
+    assert_eq!(convert_channels(&mono, 1, 1), mono);
+}
+
+#[test]
+fn test_stereo_to_stereo() {
+    let stereo = vec![0.5, -0.5, 0.3, -0.3];
+    assert_eq!(convert_channels(&stereo, 2, 2), stereo);
+}
+
+#[test]
+fn test_mono_to_stereo() {
+    let mono = vec![0.5, 0.3, 0.1];
+    let stereo = convert_channels(&mono, 1, 2);
+    assert_eq!(stereo, vec![0.5, 0.5, 0.3, 0.3, 0.1, 0.1]);
+}
+
+#[test]
+fn test_stereo_to_mono() {
+    // Left and right differ, so dropping either channel would be noticed
+    let stereo = vec![0.5, 0.1, 0.0, -0.4, 1.0, 1.0];
+    let mono = convert_channels(&stereo, 2, 1);
+    assert_eq!(mono.len(), 3);
+    for (actual, expected) in mono.iter().zip([0.3, -0.2, 1.0]) {
+        assert!((actual - expected).abs() < 1e-6);
+    }
+}
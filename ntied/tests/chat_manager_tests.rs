@@ -2,13 +2,19 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use ntied::chat::{ChatListener, ChatManager};
-use ntied::contact::{ContactManager, ContactStatus};
-use ntied::models::{Message, MessageKind};
-use ntied::packet::ContactProfile;
-use ntied::storage::Storage;
-
-use ntied_crypto::PrivateKey;
+use ntied::chat::{ChatListener, ChatManager, PendingSession, RatchetSession};
+use ntied::config::ConfigManager;
+use ntied::contact::{ContactManager, ContactStatus, Features};
+use ntied::models::{Contact, Message, MessageKind};
+use ntied::packet::{
+    ChatMessageKind, ChatMessagePacket, ChatPacket, ContactProfile, Packet, RatchetHeader,
+    SealedChatPacket,
+};
+use ntied::storage::{MessageStore, SqliteStore, Storage};
+use ntied::ui::UiEvent;
+use ntied::ui::screens::{ChatListScreen, MessageStatus};
+
+use ntied_crypto::{PrivateKey, SignatureScheme, X25519Key};
 use ntied_server::Server;
 use ntied_transport::{Address, ToAddress};
 
@@ -78,6 +84,7 @@ async fn test_chat_manager_creates_tables_and_starts_empty() {
             key_a,
             ContactProfile {
                 name: "Alice".into(),
+                avatar: None,
             },
         )
         .await,
@@ -140,6 +147,7 @@ async fn test_add_contact_chat_persists_and_reload() {
             key_a,
             ContactProfile {
                 name: "Alice".into(),
+                avatar: None,
             },
         )
         .await,
@@ -186,6 +194,110 @@ async fn test_add_contact_chat_persists_and_reload() {
     server_handle.abort();
 }
 
+#[tokio::test]
+async fn test_archived_chat_keeps_history() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir, storage) = open_temp_storage().await;
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            PrivateKey::generate().unwrap(),
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let chats = ChatManager::new(storage.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager::new failed");
+    let handle = chats
+        .add_contact_chat(addr_b, key_b.public_key(), "Bob".into(), None)
+        .await
+        .expect("add_contact_chat failed");
+    assert!(!handle.is_archived());
+    handle
+        .send_message(MessageKind::Text("hello".into()))
+        .await
+        .expect("send_message failed");
+
+    chats.set_archived(addr_b, true).await.unwrap();
+    assert!(handle.is_archived());
+
+    // The flag and the history survive a reload.
+    drop(chats);
+    let chats = ChatManager::new(storage.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager reload failed");
+    let handle = chats.get_contact_chat(addr_b).await.unwrap();
+    assert!(handle.is_archived());
+    let history = handle.load_history(10).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].kind.content(), "hello");
+
+    chats.set_archived(addr_b, false).await.unwrap();
+    assert!(!handle.is_archived());
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_key_change_of_verified_contact_drops_verification() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir, storage) = open_temp_storage().await;
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            PrivateKey::generate().unwrap(),
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let chats = ChatManager::new(storage.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager::new failed");
+    let handle = chats
+        .add_contact_chat(addr_b, key_b.public_key(), "Bob".into(), None)
+        .await
+        .expect("add_contact_chat failed");
+    assert_eq!(handle.fingerprint(), key_b.public_key().fingerprint());
+    assert_ne!(handle.fingerprint(), chats.own_fingerprint());
+    // Only verified contacts can be auto-answered
+    assert!(chats.set_auto_answer(addr_b, true).await.is_err());
+    chats.set_verified(addr_b, true).await.unwrap();
+    assert!(handle.contact().verified);
+    chats.set_auto_answer(addr_b, true).await.unwrap();
+    assert!(handle.is_auto_answer());
+
+    let new_key = PrivateKey::generate().unwrap().public_key();
+    let new_addr = new_key.to_address().unwrap();
+    let handle = chats.rotate_contact_key(addr_b, new_key).await.unwrap();
+    let contact = handle.contact();
+    assert!(!contact.verified);
+    assert!(!contact.auto_answer);
+    assert_ne!(handle.fingerprint(), key_b.public_key().fingerprint());
+
+    // The new key stays unverified after a reload until it is verified again.
+    drop(chats);
+    let chats = ChatManager::new(storage.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager reload failed");
+    let handle = chats.get_contact_chat(new_addr).await.unwrap();
+    assert!(!handle.contact().verified);
+    chats.set_verified(new_addr, true).await.unwrap();
+    assert!(handle.contact().verified);
+    server_handle.abort();
+}
+
 #[tokio::test]
 async fn test_remove_contact_chat_removes_from_db_and_cache() {
     init_tracing();
@@ -206,6 +318,7 @@ async fn test_remove_contact_chat_removes_from_db_and_cache() {
             key_a,
             ContactProfile {
                 name: "Alice".into(),
+                avatar: None,
             },
         )
         .await,
@@ -275,12 +388,21 @@ async fn test_one_way_message_delivery() {
             key_a,
             ContactProfile {
                 name: "Alice".into(),
+                avatar: None,
             },
         )
         .await,
     );
     let mgr_b = Arc::new(
-        ContactManager::new(server_addr, key_b, ContactProfile { name: "Bob".into() }).await,
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
     );
 
     // Give transports time to register
@@ -341,8 +463,96 @@ async fn test_one_way_message_delivery() {
     assert!(msg.incoming, "B should see incoming message");
     match msg.kind {
         MessageKind::Text(s) => assert_eq!(s, "hello-from-A"),
+        kind => panic!("Unexpected message kind: {kind:?}"),
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_long_text_is_split_and_delivered() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir_a, storage_a) = open_temp_storage().await;
+    let (_dir_b, storage_b) = open_temp_storage().await;
+    let key_a = PrivateKey::generate().unwrap();
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_a = key_a.public_key().to_address().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let pub_a = key_a.public_key().clone();
+    let pub_b = key_b.public_key().clone();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_a,
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let mgr_b = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    sleep(Duration::from_millis(300)).await;
+    let a_outgoing = mgr_a.connect_contact(addr_b).await;
+    let incoming_addr = timeout(Duration::from_secs(10), mgr_b.on_incoming_address())
+        .await
+        .expect("timeout waiting for incoming at B")
+        .expect("incoming channel closed");
+    let b_incoming = mgr_b.connect_contact(incoming_addr).await;
+    b_incoming.accept().await.expect("B accept failed");
+    let mut tries = 100;
+    while tries > 0 && a_outgoing.status() != ContactStatus::Accepted {
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
     }
+    assert!(tries > 0, "contacts did not reach Accepted");
+    let chats_a = ChatManager::new(storage_a.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager A init failed");
+    let chats_b = ChatManager::new(storage_b.clone(), mgr_b.clone())
+        .await
+        .expect("ChatManager B init failed");
+    let a_handle = chats_a
+        .add_contact_chat(addr_b, pub_b, "Bob".into(), None)
+        .await
+        .expect("A add_contact_chat failed");
+    let b_handle = chats_b
+        .add_contact_chat(addr_a, pub_a, "Alice".into(), None)
+        .await
+        .expect("B add_contact_chat failed");
 
+    // A single message over the limit is refused instead of being lost
+    let text = "lorem ipsum ".repeat(MessageKind::MAX_TEXT_LEN / 5);
+    assert!(
+        a_handle
+            .send_message(MessageKind::Text(text.clone()))
+            .await
+            .is_err()
+    );
+    let sent = a_handle.send_text(&text, None).await.unwrap();
+    assert_eq!(sent.len(), 3);
+    let mut received = String::new();
+    for _ in 0..sent.len() {
+        let msg = timeout(Duration::from_secs(5), b_handle.recv_message())
+            .await
+            .expect("timeout waiting for B to receive message")
+            .expect("B recv_message failed");
+        let part = msg.kind.display_text();
+        assert!(part.len() <= MessageKind::MAX_TEXT_LEN);
+        received.push_str(&part);
+    }
+    assert_eq!(received, text);
     server_handle.abort();
 }
 
@@ -354,18 +564,18 @@ struct TestListener {
 #[async_trait::async_trait]
 impl ChatListener for TestListener {
     async fn on_incoming_message(&self, _address: Address, message: Message) {
-        let text = match message.kind {
-            MessageKind::Text(s) => s,
-        };
+        let text = message.kind.display_text();
         let _ = self.tx.send((true, text));
     }
 
+    async fn on_message_notification(&self, _address: Address, _message: Message) {}
+
     async fn on_outgoing_message(&self, _address: Address, message: Message) {
-        let text = match message.kind {
-            MessageKind::Text(s) => s,
-        };
+        let text = message.kind.display_text();
         let _ = self.tx.send((false, text));
     }
+
+    async fn on_contact_updated(&self, _address: Address, _contact: Contact) {}
 }
 
 #[tokio::test]
@@ -391,12 +601,21 @@ async fn test_chat_listener_emits_events() {
             key_a,
             ContactProfile {
                 name: "Alice".into(),
+                avatar: None,
             },
         )
         .await,
     );
     let mgr_b = Arc::new(
-        ContactManager::new(server_addr, key_b, ContactProfile { name: "Bob".into() }).await,
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
     );
 
     // Give transports time to register
@@ -474,6 +693,16 @@ async fn test_chat_listener_emits_events() {
 
 #[tokio::test]
 async fn test_bidirectional_message_delivery() {
+    bidirectional_message_delivery(SignatureScheme::P256, SignatureScheme::P256).await;
+}
+
+#[tokio::test]
+async fn test_bidirectional_message_delivery_between_schemes() {
+    bidirectional_message_delivery(SignatureScheme::P256, SignatureScheme::Ed25519).await;
+    bidirectional_message_delivery(SignatureScheme::Ed25519, SignatureScheme::P256).await;
+}
+
+async fn bidirectional_message_delivery(scheme_a: SignatureScheme, scheme_b: SignatureScheme) {
     init_tracing();
     let (server_addr, server_handle) = start_server().await;
 
@@ -482,8 +711,8 @@ async fn test_bidirectional_message_delivery() {
     let (_dir_b, storage_b) = open_temp_storage().await;
 
     // Two identities
-    let key_a = PrivateKey::generate().unwrap();
-    let key_b = PrivateKey::generate().unwrap();
+    let key_a = PrivateKey::generate_with_scheme(scheme_a).unwrap();
+    let key_b = PrivateKey::generate_with_scheme(scheme_b).unwrap();
     let addr_a = key_a.public_key().to_address().unwrap();
     let addr_b = key_b.public_key().to_address().unwrap();
     let pub_a = key_a.public_key().clone();
@@ -496,12 +725,21 @@ async fn test_bidirectional_message_delivery() {
             key_a,
             ContactProfile {
                 name: "Alice".into(),
+                avatar: None,
             },
         )
         .await,
     );
     let mgr_b = Arc::new(
-        ContactManager::new(server_addr, key_b, ContactProfile { name: "Bob".into() }).await,
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
     );
 
     // Give transports time to register
@@ -559,6 +797,7 @@ async fn test_bidirectional_message_delivery() {
     assert!(msg_b.incoming, "B should see incoming");
     match msg_b.kind {
         MessageKind::Text(s) => assert_eq!(s, "ping"),
+        kind => panic!("Unexpected message kind: {kind:?}"),
     }
 
     // Send B->A
@@ -573,7 +812,1054 @@ async fn test_bidirectional_message_delivery() {
     assert!(msg_a.incoming, "A should see incoming");
     match msg_a.kind {
         MessageKind::Text(s) => assert_eq!(s, "pong"),
+        kind => panic!("Unexpected message kind: {kind:?}"),
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_contact_without_signed_messages_gets_plain_messages() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir_a, storage_a) = open_temp_storage().await;
+    let (_dir_b, storage_b) = open_temp_storage().await;
+
+    let key_a = PrivateKey::generate().unwrap();
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_a = key_a.public_key().to_address().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let pub_a = key_a.public_key().clone();
+    let pub_b = key_b.public_key().clone();
+
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_a,
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let mgr_b = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    // Bob runs a client released before signed messages
+    mgr_b.set_features(Features::from_bits(
+        Features::all().bits() & !Features::SIGNED_MESSAGE.bits(),
+    ));
+    sleep(Duration::from_millis(300)).await;
+
+    let a_outgoing = mgr_a.connect_contact(addr_b).await;
+    let incoming_addr = timeout(Duration::from_secs(10), mgr_b.on_incoming_address())
+        .await
+        .expect("timeout waiting for incoming at B")
+        .expect("incoming channel closed");
+    let b_incoming = mgr_b.connect_contact(incoming_addr).await;
+    b_incoming.accept().await.expect("B accept failed");
+    let mut tries = 100;
+    while tries > 0 {
+        if a_outgoing.status() == ContactStatus::Accepted
+            && b_incoming.status() == ContactStatus::Accepted
+        {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
     }
+    assert!(tries > 0, "contacts did not reach Accepted");
+    assert!(!a_outgoing.features().contains(Features::SIGNED_MESSAGE));
+
+    let chats_a = ChatManager::new(storage_a.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager A init failed");
+    let chats_b = ChatManager::new(storage_b.clone(), mgr_b.clone())
+        .await
+        .expect("ChatManager B init failed");
+    let a_handle = chats_a
+        .add_contact_chat(addr_b, pub_b, "Bob".into(), None)
+        .await
+        .expect("A add_contact_chat failed");
+    let b_handle = chats_b
+        .add_contact_chat(addr_a, pub_a, "Alice".into(), None)
+        .await
+        .expect("B add_contact_chat failed");
+
+    a_handle
+        .send_message(MessageKind::Text("ping".into()))
+        .await
+        .expect("A->B send failed");
+    let ping = timeout(Duration::from_secs(5), b_handle.recv_message())
+        .await
+        .expect("timeout waiting B recv")
+        .expect("B recv failed");
+    assert_eq!(ping.kind.content(), "ping");
+    assert!(ping.signature.is_none());
+    // Nothing vouches for the sender, the message is flagged
+    assert!(!ping.verified);
+
+    // The reply is left out of the plain message
+    b_handle
+        .send_reply(MessageKind::Text("pong".into()), ping.id)
+        .await
+        .expect("B->A send failed");
+    let pong = timeout(Duration::from_secs(5), a_handle.recv_message())
+        .await
+        .expect("timeout waiting A recv")
+        .expect("A recv failed");
+    assert_eq!(pong.kind.content(), "pong");
+    assert!(pong.signature.is_none());
+    assert!(pong.reply_to.is_none());
+    assert!(!pong.verified);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_profile_update_keeps_local_name() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+
+    let (_dir_a, storage_a) = open_temp_storage().await;
+
+    // Two identities
+    let key_a = PrivateKey::generate().unwrap();
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_a = key_a.public_key().to_address().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let pub_b = key_b.public_key().clone();
 
+    // Managers
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_a,
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let mgr_b = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+
+    // Give transports time to register
+    sleep(Duration::from_millis(300)).await;
+
+    // Perform explicit handshake via ContactManager
+    let a_outgoing = mgr_a.connect_contact(addr_b).await;
+    let incoming_addr = timeout(Duration::from_secs(10), mgr_b.on_incoming_address())
+        .await
+        .expect("timeout waiting for incoming at B")
+        .expect("incoming channel closed");
+    assert_eq!(incoming_addr, addr_a);
+    let b_incoming = mgr_b.connect_contact(incoming_addr).await;
+    b_incoming.accept().await.expect("B accept failed");
+
+    // Wait until both Accepted at contact level
+    let mut tries = 100;
+    while tries > 0 {
+        if a_outgoing.status() == ContactStatus::Accepted
+            && b_incoming.status() == ContactStatus::Accepted
+        {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "contacts did not reach Accepted");
+
+    // A knows B under a local alias
+    let chats_a = ChatManager::new(storage_a.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager A init failed");
+    let a_handle = chats_a
+        .add_contact_chat(addr_b, pub_b, "Bob".into(), Some("Bobby".into()))
+        .await
+        .expect("A add_contact_chat failed");
+
+    // B renames itself
+    mgr_b
+        .update_own_profile(ContactProfile {
+            name: "Robert".into(),
+            avatar: None,
+        })
+        .await;
+
+    let mut tries = 100;
+    while tries > 0 && a_handle.contact().name != "Robert" {
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "profile update was not received");
+    assert_eq!(a_handle.contact().local_name.as_deref(), Some("Bobby"));
+
+    // The new name is persisted, the alias is untouched
+    let count = scalar_i64(
+        &storage_a,
+        "SELECT COUNT(*) FROM contact WHERE name = ?1 AND local_name = ?2",
+        vec![
+            Value::Text("Robert".to_string()),
+            Value::Text("Bobby".to_string()),
+        ],
+    )
+    .await;
+    assert_eq!(count, 1);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_duplicate_message_is_stored_once() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir_a, storage_a) = open_temp_storage().await;
+    let (_dir_b, storage_b) = open_temp_storage().await;
+
+    let key_a = PrivateKey::generate().unwrap();
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_a = key_a.public_key().to_address().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let pub_a = key_a.public_key().clone();
+    let pub_b = key_b.public_key().clone();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_a.clone(),
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let mgr_b = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    sleep(Duration::from_millis(300)).await;
+
+    let a_outgoing = mgr_a.connect_contact(addr_b).await;
+    let incoming_addr = timeout(Duration::from_secs(10), mgr_b.on_incoming_address())
+        .await
+        .expect("timeout waiting for incoming at B")
+        .expect("incoming channel closed");
+    let b_incoming = mgr_b.connect_contact(incoming_addr).await;
+    b_incoming.accept().await.expect("B accept failed");
+    let mut tries = 100;
+    while tries > 0
+        && (a_outgoing.status() != ContactStatus::Accepted
+            || b_incoming.status() != ContactStatus::Accepted)
+    {
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "contacts did not reach Accepted");
+
+    let chats_a = ChatManager::new(storage_a.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager A init failed");
+    let chats_b = ChatManager::new(storage_b.clone(), mgr_b.clone())
+        .await
+        .expect("ChatManager B init failed");
+    let a_handle = chats_a
+        .add_contact_chat(addr_b, pub_b, "Bob".into(), None)
+        .await
+        .expect("A add_contact_chat failed");
+    let b_handle = chats_b
+        .add_contact_chat(addr_a, pub_a, "Alice".into(), None)
+        .await
+        .expect("B add_contact_chat failed");
+
+    let sent = a_handle
+        .send_message(MessageKind::Text("once".into()))
+        .await
+        .expect("send_message failed");
+    let received = timeout(Duration::from_secs(5), b_handle.recv_message())
+        .await
+        .expect("timeout waiting for B to receive message")
+        .expect("B recv_message failed");
+    assert_eq!(received.message_id, sent.message_id);
+
+    // Retransmit the same message, also with a log_id that moved on meanwhile
+    for log_id in [1, 2] {
+        let packet = ChatMessagePacket::new(
+            sent.message_id,
+            log_id,
+            ChatMessageKind::Text("once".into()),
+            None,
+            &key_a,
+        );
+        a_handle
+            .contact_handle()
+            .send_chat_packet(ChatPacket::SignedMessage(packet))
+            .await
+            .expect("send_chat_packet failed");
+    }
+    sleep(Duration::from_millis(500)).await;
+    let count = scalar_i64(
+        &storage_b,
+        "SELECT COUNT(*) FROM \"message\" WHERE \"message_id\" = ?1",
+        vec![Value::Text(sent.message_id.to_string())],
+    )
+    .await;
+    assert_eq!(count, 1);
+
+    // The duplicate does not advance the log of the receiver
+    a_handle
+        .send_message(MessageKind::Text("next".into()))
+        .await
+        .expect("send_message failed");
+    let next = timeout(Duration::from_secs(5), b_handle.recv_message())
+        .await
+        .expect("timeout waiting for B to receive next message")
+        .expect("B recv_message failed");
+    assert_eq!(next.log_id, Some(2));
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_forged_message_is_rejected() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir_a, storage_a) = open_temp_storage().await;
+    let (_dir_b, storage_b) = open_temp_storage().await;
+
+    let key_a = PrivateKey::generate().unwrap();
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_a = key_a.public_key().to_address().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let pub_a = key_a.public_key().clone();
+    let pub_b = key_b.public_key().clone();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_a.clone(),
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let mgr_b = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    // Plain messages reach the signature check without a session
+    mgr_b.set_features(Features::from_bits(
+        Features::all().bits() & !Features::MESSAGE_RATCHET.bits(),
+    ));
+    sleep(Duration::from_millis(300)).await;
+
+    let a_outgoing = mgr_a.connect_contact(addr_b).await;
+    let incoming_addr = timeout(Duration::from_secs(10), mgr_b.on_incoming_address())
+        .await
+        .expect("timeout waiting for incoming at B")
+        .expect("incoming channel closed");
+    let b_incoming = mgr_b.connect_contact(incoming_addr).await;
+    b_incoming.accept().await.expect("B accept failed");
+    let mut tries = 100;
+    while tries > 0
+        && (a_outgoing.status() != ContactStatus::Accepted
+            || b_incoming.status() != ContactStatus::Accepted)
+    {
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "contacts did not reach Accepted");
+
+    let chats_a = ChatManager::new(storage_a.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager A init failed");
+    let chats_b = ChatManager::new(storage_b.clone(), mgr_b.clone())
+        .await
+        .expect("ChatManager B init failed");
+    let a_handle = chats_a
+        .add_contact_chat(addr_b, pub_b, "Bob".into(), None)
+        .await
+        .expect("A add_contact_chat failed");
+    let b_handle = chats_b
+        .add_contact_chat(addr_a, pub_a.clone(), "Alice".into(), None)
+        .await
+        .expect("B add_contact_chat failed");
+
+    // Signed by a key other than the one pinned for Alice
+    let forger = PrivateKey::generate().unwrap();
+    let forged = ChatMessagePacket::new(
+        uuid::Uuid::now_v7(),
+        1,
+        ChatMessageKind::Text("forged".into()),
+        None,
+        &forger,
+    );
+    a_handle
+        .contact_handle()
+        .send_chat_packet(ChatPacket::SignedMessage(forged.clone()))
+        .await
+        .expect("send_chat_packet failed");
+    sleep(Duration::from_millis(500)).await;
+    let count = scalar_i64(
+        &storage_b,
+        "SELECT COUNT(*) FROM \"message\" WHERE \"message_id\" = ?1",
+        vec![Value::Text(forged.message_id.to_string())],
+    )
+    .await;
+    assert_eq!(count, 0);
+
+    // Signed by Alice but tampered on the way
+    let mut tampered = ChatMessagePacket::new(
+        uuid::Uuid::now_v7(),
+        1,
+        ChatMessageKind::Text("tampered".into()),
+        None,
+        &key_a,
+    );
+    let last = tampered.signature.len() - 1;
+    tampered.signature[last] ^= 0x01;
+    a_handle
+        .contact_handle()
+        .send_chat_packet(ChatPacket::SignedMessage(tampered.clone()))
+        .await
+        .expect("send_chat_packet failed");
+    sleep(Duration::from_millis(500)).await;
+    let count = scalar_i64(
+        &storage_b,
+        "SELECT COUNT(*) FROM \"message\" WHERE \"message_id\" = ?1",
+        vec![Value::Text(tampered.message_id.to_string())],
+    )
+    .await;
+    assert_eq!(count, 0);
+
+    // The genuine message takes the log position and keeps its signature
+    let sent = a_handle
+        .send_message(MessageKind::Text("genuine".into()))
+        .await
+        .expect("send_message failed");
+    let received = timeout(Duration::from_secs(5), b_handle.recv_message())
+        .await
+        .expect("timeout waiting for B to receive message")
+        .expect("B recv_message failed");
+    assert_eq!(received.message_id, sent.message_id);
+    assert_eq!(received.log_id, Some(1));
+    assert!(received.verified);
+    let signature = received.signature.expect("signature is not stored");
+    let packet = ChatMessagePacket {
+        message_id: sent.message_id,
+        log_id: 1,
+        kind: ChatMessageKind::Text("genuine".into()),
+        reply_to: None,
+        signature,
+    };
+    assert!(packet.verify(&pub_a));
+    assert!(sent.signature.is_none());
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_batched_messages_share_one_packet() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir_a, storage_a) = open_temp_storage().await;
+    let (_dir_b, storage_b) = open_temp_storage().await;
+
+    let key_a = PrivateKey::generate().unwrap();
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_a = key_a.public_key().to_address().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let pub_a = key_a.public_key().clone();
+    let pub_b = key_b.public_key().clone();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_a,
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let mgr_b = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    sleep(Duration::from_millis(300)).await;
+
+    let a_outgoing = mgr_a.connect_contact(addr_b).await;
+    let incoming_addr = timeout(Duration::from_secs(10), mgr_b.on_incoming_address())
+        .await
+        .expect("timeout waiting for incoming at B")
+        .expect("incoming channel closed");
+    let b_incoming = mgr_b.connect_contact(incoming_addr).await;
+    b_incoming.accept().await.expect("B accept failed");
+    let mut tries = 100;
+    while tries > 0
+        && (a_outgoing.status() != ContactStatus::Accepted
+            || b_incoming.status() != ContactStatus::Accepted
+            || !a_outgoing.features().contains(Features::MESSAGE_BATCH))
+    {
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "contacts did not negotiate batches");
+
+    let chats_a = ChatManager::new(storage_a.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager A init failed");
+    let chats_b = ChatManager::new(storage_b.clone(), mgr_b.clone())
+        .await
+        .expect("ChatManager B init failed");
+    let a_handle = chats_a
+        .add_contact_chat(addr_b, pub_b, "Bob".into(), None)
+        .await
+        .expect("A add_contact_chat failed");
+    let b_handle = chats_b
+        .add_contact_chat(addr_a, pub_a.clone(), "Alice".into(), None)
+        .await
+        .expect("B add_contact_chat failed");
+
+    // The first message starts the end-to-end session
+    a_handle
+        .send_message(MessageKind::Text("hello".into()))
+        .await
+        .expect("send_message failed");
+    timeout(Duration::from_secs(5), b_handle.recv_message())
+        .await
+        .expect("timeout waiting for B to receive message")
+        .expect("B recv_message failed");
+    let mut tries = 100;
+    while tries > 0 {
+        let history = a_handle.load_history(10).await.unwrap();
+        if history.iter().all(|v| v.log_id.is_some()) {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "first message was not acked");
+
+    let before = a_outgoing.usage();
+    let sent = a_handle
+        .send_batch(vec![
+            MessageKind::Text("one".into()),
+            MessageKind::Text("two".into()),
+            MessageKind::Text("three".into()),
+        ])
+        .await
+        .expect("send_batch failed");
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let message = timeout(Duration::from_secs(5), b_handle.recv_message())
+            .await
+            .expect("timeout waiting for B to receive message")
+            .expect("B recv_message failed");
+        received.push(message);
+    }
+    // Stored individually with their own ids and in order
+    for (i, (sent, received)) in sent.iter().zip(&received).enumerate() {
+        assert_eq!(received.message_id, sent.message_id);
+        assert_eq!(received.kind.content(), sent.kind.content());
+        assert_eq!(received.log_id, Some(i as u64 + 2));
+    }
+
+    // Exactly one sealed packet carrying all three messages was sent
+    let packets = received
+        .iter()
+        .map(|v| {
+            ChatPacket::SignedMessage(ChatMessagePacket {
+                message_id: v.message_id,
+                log_id: v.log_id.unwrap(),
+                kind: ChatMessageKind::Text(v.kind.content().to_string()),
+                reply_to: None,
+                signature: v.signature.clone().expect("signature is not stored"),
+            })
+        })
+        .collect();
+    let batch = bincode::serialize(&ChatPacket::Batch(packets)).unwrap();
+    // Ratchet keys of the session are X25519 keys
+    let key_len = X25519Key::generate().public_key().len();
+    let sealed = SealedChatPacket {
+        session: vec![0; key_len],
+        header: RatchetHeader {
+            ratchet_key: vec![0; key_len],
+            prev_count: 0,
+            count: 0,
+        },
+        ciphertext: vec![0; batch.len() + 16],
+    };
+    let sealed = bincode::serialize(&Packet::Chat(ChatPacket::Sealed(sealed))).unwrap();
+    assert_eq!(
+        a_outgoing.usage().since(before).bytes_sent,
+        sealed.len() as u64
+    );
+
+    // The batch is acked as a whole
+    let mut tries = 100;
+    while tries > 0 {
+        let history = a_handle.load_history(10).await.unwrap();
+        if history.iter().all(|v| v.log_id.is_some()) {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "batch was not acked");
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_unknown_chat_session_is_replaced() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir_a, storage_a) = open_temp_storage().await;
+    let (_dir_b, storage_b) = open_temp_storage().await;
+
+    let key_a = PrivateKey::generate().unwrap();
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_a = key_a.public_key().to_address().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let pub_a = key_a.public_key().clone();
+    let pub_b = key_b.public_key().clone();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_a.clone(),
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let mgr_b = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_b.clone(),
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    sleep(Duration::from_millis(300)).await;
+
+    let a_outgoing = mgr_a.connect_contact(addr_b).await;
+    let incoming_addr = timeout(Duration::from_secs(10), mgr_b.on_incoming_address())
+        .await
+        .expect("timeout waiting for incoming at B")
+        .expect("incoming channel closed");
+    let b_incoming = mgr_b.connect_contact(incoming_addr).await;
+    b_incoming.accept().await.expect("B accept failed");
+    let mut tries = 100;
+    while tries > 0
+        && (a_outgoing.status() != ContactStatus::Accepted
+            || b_incoming.status() != ContactStatus::Accepted
+            || !a_outgoing.features().contains(Features::MESSAGE_RATCHET))
+    {
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "contacts did not negotiate sessions");
+
+    let chats_a = ChatManager::new(storage_a.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager A init failed");
+    let chats_b = ChatManager::new(storage_b.clone(), mgr_b.clone())
+        .await
+        .expect("ChatManager B init failed");
+    let a_handle = chats_a
+        .add_contact_chat(addr_b, pub_b, "Bob".into(), None)
+        .await
+        .expect("A add_contact_chat failed");
+    let b_handle = chats_b
+        .add_contact_chat(addr_a, pub_a.clone(), "Alice".into(), None)
+        .await
+        .expect("B add_contact_chat failed");
+    a_handle
+        .send_message(MessageKind::Text("hello".into()))
+        .await
+        .expect("send_message failed");
+    timeout(Duration::from_secs(5), b_handle.recv_message())
+        .await
+        .expect("timeout waiting for B to receive message")
+        .expect("B recv_message failed");
+
+    // Bob restarts with a session Alice does not know, e.g. a stale backup
+    let contact_id = b_handle.contact().id;
+    drop(b_handle);
+    drop(chats_b);
+    sleep(Duration::from_millis(100)).await;
+    let pending = PendingSession::new(&key_a);
+    let stale = RatchetSession::accept(&key_b, &pub_a, &pending.init_packet()).unwrap();
+    SqliteStore::new(storage_b.clone())
+        .set_chat_session(contact_id, Some(stale.to_bytes().unwrap()))
+        .await
+        .unwrap();
+    let chats_b = ChatManager::new(storage_b.clone(), mgr_b.clone())
+        .await
+        .expect("ChatManager B reload failed");
+    let b_handle = chats_b.get_contact_chat(addr_a).await.unwrap();
+
+    // Alice answers the unknown session with a new one and gets the message
+    b_handle
+        .send_message(MessageKind::Text("after restart".into()))
+        .await
+        .expect("send_message failed");
+    let message = timeout(Duration::from_secs(10), a_handle.recv_message())
+        .await
+        .expect("timeout waiting for A to receive message")
+        .expect("A recv_message failed");
+    assert_eq!(message.kind.content(), "after restart");
+    a_handle
+        .send_message(MessageKind::Text("reply".into()))
+        .await
+        .expect("send_message failed");
+    let message = timeout(Duration::from_secs(5), b_handle.recv_message())
+        .await
+        .expect("timeout waiting for B to receive message")
+        .expect("B recv_message failed");
+    assert_eq!(message.kind.content(), "reply");
+
+    server_handle.abort();
+}
+
+/// Records incoming messages and notifications as "message: text" and
+/// "notification: text".
+struct NotifyListener {
+    tx: tokio::sync::mpsc::UnboundedSender<String>,
+}
+
+#[async_trait::async_trait]
+impl ChatListener for NotifyListener {
+    async fn on_incoming_message(&self, _address: Address, message: Message) {
+        let text = message.kind.display_text();
+        let _ = self.tx.send(format!("message: {text}"));
+    }
+
+    async fn on_message_notification(&self, _address: Address, message: Message) {
+        let text = message.kind.display_text();
+        let _ = self.tx.send(format!("notification: {text}"));
+    }
+
+    async fn on_outgoing_message(&self, _address: Address, _message: Message) {}
+
+    async fn on_contact_updated(&self, _address: Address, _contact: Contact) {}
+}
+
+#[tokio::test]
+async fn test_muted_contact_skips_notification() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir_a, storage_a) = open_temp_storage().await;
+    let (_dir_b, storage_b) = open_temp_storage().await;
+
+    let key_a = PrivateKey::generate().unwrap();
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_a = key_a.public_key().to_address().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let pub_a = key_a.public_key().clone();
+    let pub_b = key_b.public_key().clone();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_a,
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let mgr_b = Arc::new(
+        ContactManager::new(
+            server_addr,
+            key_b,
+            ContactProfile {
+                name: "Bob".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    sleep(Duration::from_millis(300)).await;
+
+    let a_outgoing = mgr_a.connect_contact(addr_b).await;
+    let incoming_addr = timeout(Duration::from_secs(10), mgr_b.on_incoming_address())
+        .await
+        .expect("timeout waiting for incoming at B")
+        .expect("incoming channel closed");
+    let b_incoming = mgr_b.connect_contact(incoming_addr).await;
+    b_incoming.accept().await.expect("B accept failed");
+    let mut tries = 100;
+    while tries > 0
+        && (a_outgoing.status() != ContactStatus::Accepted
+            || b_incoming.status() != ContactStatus::Accepted)
+    {
+        sleep(Duration::from_millis(50)).await;
+        tries -= 1;
+    }
+    assert!(tries > 0, "contacts did not reach Accepted");
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let chats_a = ChatManager::new(storage_a.clone(), mgr_a.clone())
+        .await
+        .expect("ChatManager A init failed");
+    let chats_b = ChatManager::with_listener(
+        storage_b.clone(),
+        mgr_b.clone(),
+        Arc::new(NotifyListener { tx }),
+    )
+    .await
+    .expect("ChatManager B init failed");
+    let a_handle = chats_a
+        .add_contact_chat(addr_b, pub_b, "Bob".into(), None)
+        .await
+        .expect("A add_contact_chat failed");
+    chats_b
+        .add_contact_chat(addr_a, pub_a, "Alice".into(), None)
+        .await
+        .expect("B add_contact_chat failed");
+    chats_b.set_muted(addr_a, true).await.unwrap();
+    assert!(chats_b.is_muted(addr_a).await);
+
+    // The chat of a muted contact is updated without a notification
+    a_handle
+        .send_message(MessageKind::Text("quiet".into()))
+        .await
+        .expect("send_message failed");
+    let event = timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timeout waiting for B listener")
+        .unwrap();
+    assert_eq!(event, "message: quiet");
+    let unread = scalar_i64(
+        &storage_b,
+        "SELECT COUNT(*) FROM \"message\" WHERE \"incoming\" = 1 AND \"read_time\" IS NULL",
+        vec![],
+    )
+    .await;
+    assert_eq!(unread, 1);
+
+    // The flag survives reload, unmuted contacts notify again
+    drop(chats_b);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let chats_b = ChatManager::with_listener(
+        storage_b.clone(),
+        mgr_b.clone(),
+        Arc::new(NotifyListener { tx }),
+    )
+    .await
+    .expect("ChatManager B reload failed");
+    assert!(chats_b.is_muted(addr_a).await);
+    chats_b.set_muted(addr_a, false).await.unwrap();
+    a_handle
+        .send_message(MessageKind::Text("loud".into()))
+        .await
+        .expect("send_message failed");
+    for expected in ["message: loud", "notification: loud"] {
+        let event = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timeout waiting for B listener")
+            .unwrap();
+        assert_eq!(event, expected);
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_failed_message_is_retried() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir, storage) = open_temp_storage().await;
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            PrivateKey::generate().unwrap(),
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let chats = Arc::new(
+        ChatManager::new(storage.clone(), mgr_a.clone())
+            .await
+            .expect("ChatManager::new failed"),
+    );
+    let address = addr_b.to_string();
+    let mut screen = ChatListScreen::new(None);
+
+    // There is no chat with Bob yet, so sending fails
+    let events = ChatListScreen::send_text(
+        Some(chats.clone()),
+        address.clone(),
+        "hello".into(),
+        None,
+        -1,
+    )
+    .await;
+    assert!(matches!(
+        events.as_slice(),
+        [UiEvent::MessageFailed { id: -1, .. }]
+    ));
+    for event in events {
+        screen.apply_event(event);
+    }
+    assert!(matches!(
+        screen.message_status(&address, -1),
+        Some(MessageStatus::Failed(_))
+    ));
+
+    // Retry sends the same text again once the chat exists
+    chats
+        .add_contact_chat(addr_b, key_b.public_key(), "Bob".into(), None)
+        .await
+        .expect("add_contact_chat failed");
+    let (text, reply_to) = screen.take_failed_message(&address, -1).unwrap();
+    assert_eq!(screen.message_status(&address, -1), None);
+    let events =
+        ChatListScreen::send_text(Some(chats.clone()), address.clone(), text, reply_to, -1).await;
+    let [UiEvent::MessageSent { id, text, .. }] = events.as_slice() else {
+        panic!("expected a sent message, got {events:?}");
+    };
+    assert_eq!(text, "hello");
+    let id = *id;
+    for event in events {
+        screen.apply_event(event);
+    }
+    assert_eq!(
+        screen.message_status(&address, id),
+        Some(MessageStatus::Pending)
+    );
+    let history = chats
+        .get_contact_chat(addr_b)
+        .await
+        .unwrap()
+        .load_history(10)
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].kind.content(), "hello");
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_history_over_limit_is_trimmed_in_memory() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let (_dir, storage) = open_temp_storage().await;
+    let key_b = PrivateKey::generate().unwrap();
+    let addr_b = key_b.public_key().to_address().unwrap();
+    let mgr_a = Arc::new(
+        ContactManager::new(
+            server_addr,
+            PrivateKey::generate().unwrap(),
+            ContactProfile {
+                name: "Alice".into(),
+                avatar: None,
+            },
+        )
+        .await,
+    );
+    let chats = Arc::new(
+        ChatManager::new(storage.clone(), mgr_a.clone())
+            .await
+            .expect("ChatManager::new failed"),
+    );
+    chats
+        .add_contact_chat(addr_b, key_b.public_key(), "Bob".into(), None)
+        .await
+        .expect("add_contact_chat failed");
+    let address = addr_b.to_string();
+    let limit = ConfigManager::MIN_HISTORY_LIMIT;
+    let mut screen = ChatListScreen::new(None);
+    screen.set_history_limit(limit);
+
+    let mut ids = Vec::new();
+    for i in 0..limit + 2 {
+        let events = ChatListScreen::send_text(
+            Some(chats.clone()),
+            address.clone(),
+            format!("message {i}"),
+            None,
+            -1,
+        )
+        .await;
+        let [UiEvent::MessageSent { id, .. }] = events.as_slice() else {
+            panic!("expected a sent message, got {events:?}");
+        };
+        ids.push(*id);
+        for event in events {
+            screen.apply_event(event);
+        }
+    }
+
+    // The oldest messages are dropped from memory only
+    assert_eq!(screen.message_status(&address, ids[0]), None);
+    assert_eq!(screen.message_status(&address, ids[1]), None);
+    for &id in &ids[2..] {
+        assert_eq!(
+            screen.message_status(&address, id),
+            Some(MessageStatus::Pending)
+        );
+    }
+    let history = chats
+        .get_contact_chat(addr_b)
+        .await
+        .unwrap()
+        .load_history(limit * 2)
+        .await
+        .unwrap();
+    assert_eq!(history.len(), limit + 2);
     server_handle.abort();
 }
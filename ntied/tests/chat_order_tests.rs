@@ -0,0 +1,40 @@
+use ntied::chat::ChatOrder;
+
+fn chat(pin_order: Option<i64>, last_activity: Option<i64>) -> ChatOrder {
+    ChatOrder {
+        pin_order,
+        last_activity,
+    }
+}
+
+#[test]
+fn test_pinned_chats_come_first() {
+    let mut chats = [
+        ("idle", chat(None, None)),
+        ("old", chat(None, Some(3))),
+        ("pinned second", chat(Some(1), None)),
+        ("recent", chat(None, Some(10))),
+        ("pinned first", chat(Some(0), Some(1))),
+        ("also idle", chat(None, None)),
+    ];
+    chats.sort_by_key(|(_, order)| *order);
+    let names: Vec<_> = chats.iter().map(|(name, _)| *name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "pinned first",
+            "pinned second",
+            "recent",
+            "old",
+            "idle",
+            "also idle",
+        ]
+    );
+}
+
+#[test]
+fn test_pin_order_beats_activity() {
+    assert!(chat(Some(5), None) < chat(None, Some(100)));
+    assert!(chat(Some(0), None) < chat(Some(1), Some(100)));
+    assert!(chat(None, Some(2)) < chat(None, Some(1)));
+}
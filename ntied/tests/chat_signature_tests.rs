@@ -0,0 +1,91 @@
+use ntied::packet::{ChatMessageKind, ChatMessagePacket};
+use ntied_crypto::PrivateKey;
+use uuid::Uuid;
+
+fn signed_packet(key: &PrivateKey, text: &str) -> ChatMessagePacket {
+    ChatMessagePacket::new(
+        Uuid::now_v7(),
+        1,
+        ChatMessageKind::Text(text.to_string()),
+        None,
+        key,
+    )
+}
+
+#[test]
+fn test_valid_signature_is_verified() {
+    let key = PrivateKey::generate().unwrap();
+    let packet = signed_packet(&key, "hello");
+    assert!(packet.verify(&key.public_key()));
+    // Resending the message with another log_id keeps the signature valid
+    let mut resent = packet.clone();
+    resent.log_id = 5;
+    assert!(resent.verify(&key.public_key()));
+}
+
+#[test]
+fn test_tampered_signature_is_not_verified() {
+    let key = PrivateKey::generate().unwrap();
+    let mut packet = signed_packet(&key, "hello");
+    let last = packet.signature.len() - 1;
+    packet.signature[last] ^= 0x01;
+    assert!(!packet.verify(&key.public_key()));
+    packet.signature.clear();
+    assert!(!packet.verify(&key.public_key()));
+}
+
+#[test]
+fn test_tampered_message_is_not_verified() {
+    let key = PrivateKey::generate().unwrap();
+    let mut packet = signed_packet(&key, "hello");
+    packet.kind = ChatMessageKind::Text("goodbye".to_string());
+    assert!(!packet.verify(&key.public_key()));
+    let mut packet = signed_packet(&key, "hello");
+    packet.message_id = Uuid::now_v7();
+    assert!(!packet.verify(&key.public_key()));
+    let mut packet = signed_packet(&key, "hello");
+    packet.reply_to = Some(Uuid::now_v7());
+    assert!(!packet.verify(&key.public_key()));
+}
+
+#[test]
+fn test_signature_of_other_key_is_not_verified() {
+    let key = PrivateKey::generate().unwrap();
+    let other_key = PrivateKey::generate().unwrap();
+    let packet = signed_packet(&other_key, "hello");
+    assert!(!packet.verify(&key.public_key()));
+}
+
+#[test]
+fn test_reply_is_signed() {
+    let key = PrivateKey::generate().unwrap();
+    let reply_to = Uuid::now_v7();
+    let mut packet = ChatMessagePacket::new(
+        Uuid::now_v7(),
+        1,
+        ChatMessageKind::Text("answer".to_string()),
+        Some(reply_to),
+        &key,
+    );
+    assert!(packet.verify(&key.public_key()));
+    packet.reply_to = None;
+    assert!(!packet.verify(&key.public_key()));
+}
+
+#[test]
+fn test_reply_cannot_be_moved_into_text() {
+    let key = PrivateKey::generate().unwrap();
+    let reply_to = Uuid::from_bytes(*b"0123456789abcdef");
+    let packet = ChatMessagePacket::new(
+        Uuid::now_v7(),
+        1,
+        ChatMessageKind::Text("hello".to_string()),
+        Some(reply_to),
+        &key,
+    );
+    assert!(packet.verify(&key.public_key()));
+    let mut forged = packet.clone();
+    forged.kind = ChatMessageKind::Text("helloreply0123456789abcdef".to_string());
+    forged.reply_to = None;
+    assert!(!forged.verify(&key.public_key()));
+}
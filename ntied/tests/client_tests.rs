@@ -0,0 +1,144 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use ntied::client::Client;
+use ntied::config::ConfigManager;
+use ntied::contact::{ConnectionStatus, ContactStatus};
+use ntied::ui::UiEvent;
+use ntied_server::Server;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+
+async fn start_server() -> (SocketAddr, JoinHandle<()>) {
+    let server = Server::new("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let _ = server.run().await;
+    });
+    (server_addr, handle)
+}
+
+/// Reads events until one matches, returns it.
+async fn wait_event(client: &Client, mut matches: impl FnMut(&UiEvent) -> bool) -> UiEvent {
+    timeout(Duration::from_secs(10), async {
+        loop {
+            let event = client.next_event().await.expect("Event stream closed");
+            if matches(&event) {
+                return event;
+            }
+        }
+    })
+    .await
+    .expect("Timeout waiting for event")
+}
+
+#[tokio::test]
+async fn test_client_message_events() {
+    let (server_addr, server_handle) = start_server().await;
+    let dir = tempfile::tempdir().unwrap();
+    let alice = Client::create(
+        &dir.path().join("alice"),
+        "test-pass",
+        "Alice".to_string(),
+        server_addr,
+    )
+    .await
+    .unwrap();
+    let bob = Client::create(
+        &dir.path().join("bob"),
+        "test-pass",
+        "Bob".to_string(),
+        server_addr,
+    )
+    .await
+    .unwrap();
+    let (addr_a, addr_b) = (alice.own_address(), bob.own_address());
+    sleep(Duration::from_millis(300)).await;
+
+    alice.request_contact(addr_b).await;
+    let event = wait_event(&bob, |v| matches!(v, UiEvent::IncomingRequest { .. })).await;
+    let UiEvent::IncomingRequest { name, address, .. } = event else {
+        unreachable!();
+    };
+    assert_eq!((name.as_str(), address), ("Alice", addr_a.to_string()));
+    bob.accept_contact(addr_a).await.unwrap();
+    // The chat on the requesting side is created with the event
+    wait_event(&alice, |v| matches!(v, UiEvent::ContactAccepted { .. })).await;
+    assert!(
+        alice
+            .chat_manager()
+            .get_contact_chat(addr_b)
+            .await
+            .is_some()
+    );
+    let contact = alice.contact_manager().connect_contact(addr_b).await;
+    assert_eq!(contact.status(), ContactStatus::Accepted);
+
+    let messages = alice
+        .send_message(addr_b, "hello-client".to_string())
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    let message = &messages[0];
+    let event = wait_event(&bob, |v| matches!(v, UiEvent::NewMessage { .. })).await;
+    let UiEvent::NewMessage {
+        address,
+        text,
+        incoming,
+        ..
+    } = event
+    else {
+        unreachable!();
+    };
+    assert_eq!(address, addr_a.to_string());
+    assert_eq!(text, "hello-client");
+    assert!(incoming);
+    let event = wait_event(&alice, |v| matches!(v, UiEvent::MessageDelivered { .. })).await;
+    let UiEvent::MessageDelivered { id, address } = event else {
+        unreachable!();
+    };
+    assert_eq!((id, address), (message.id, addr_b.to_string()));
+
+    // Profile is unlocked again with the same identity
+    alice.shutdown().await.unwrap();
+    drop(alice);
+    let alice = Client::open(&dir.path().join("alice"), "test-pass")
+        .await
+        .unwrap();
+    assert_eq!(alice.own_address(), addr_a);
+    assert!(
+        alice
+            .chat_manager()
+            .get_contact_chat(addr_b)
+            .await
+            .is_some()
+    );
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_client_records_sessions() {
+    let (server_addr, server_handle) = start_server().await;
+    let dir = tempfile::tempdir().unwrap();
+    let client = Client::create(
+        &dir.path().join("alice"),
+        "test-pass",
+        "Alice".to_string(),
+        server_addr,
+    )
+    .await
+    .unwrap();
+    wait_event(&client, |v| {
+        matches!(v, UiEvent::ConnectionStatus(ConnectionStatus::Connected))
+    })
+    .await;
+
+    let config = ConfigManager::new(client.storage().clone());
+    let sessions = config.recent_sessions().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].server_addr, server_addr);
+    config.clear_recent_sessions().await.unwrap();
+    assert!(config.recent_sessions().await.unwrap().is_empty());
+    client.shutdown().await.unwrap();
+    server_handle.abort();
+}
@@ -0,0 +1,112 @@
+use ntied::audio::{
+    CodecCapabilities, CodecManager, CodecParams, CodecType, create_decoder, create_encoder,
+};
+
+#[test]
+fn test_builder_rejects_invalid_params() {
+    for codec in [CodecType::ADPCM, CodecType::Raw] {
+        assert!(CodecParams::builder(codec).channels(0).build().is_err());
+        assert!(CodecParams::builder(codec).channels(3).build().is_err());
+        assert!(
+            CodecParams::builder(codec)
+                .sample_rate(16000)
+                .build()
+                .is_err()
+        );
+        assert!(CodecParams::builder(codec).fec(true).build().is_err());
+        assert!(CodecParams::builder(codec).dtx(true).build().is_err());
+        assert!(CodecParams::builder(codec).complexity(11).build().is_err());
+        assert!(
+            CodecParams::builder(codec)
+                .expected_packet_loss(101)
+                .build()
+                .is_err()
+        );
+        let max_bitrate = codec.max_bitrate();
+        assert!(
+            CodecParams::builder(codec)
+                .bitrate(max_bitrate + 1)
+                .build()
+                .is_err()
+        );
+        // Stereo doubles the allowed bitrate
+        assert!(
+            CodecParams::builder(codec)
+                .channels(2)
+                .bitrate(max_bitrate + 1)
+                .build()
+                .is_ok()
+        );
+    }
+}
+
+#[test]
+fn test_builder_params_construct_codecs() {
+    for codec in [CodecType::ADPCM, CodecType::Raw] {
+        for channels in [1, 2] {
+            let params = CodecParams::builder(codec)
+                .channels(channels)
+                .bitrate(codec.max_bitrate())
+                .complexity(5)
+                .build()
+                .unwrap();
+            assert_eq!(params.channels, channels);
+            let mut encoder = create_encoder(codec, &params).unwrap();
+            let mut decoder = create_decoder(codec, &params).unwrap();
+            assert_eq!(encoder.codec_type(), codec);
+            let samples = vec![0.25; 960 * channels as usize];
+            let data = encoder.encode(&samples).unwrap();
+            let decoded = decoder.decode(&data).unwrap();
+            assert_eq!(decoded.len(), samples.len());
+        }
+    }
+}
+
+#[test]
+fn test_factories_reject_invalid_params() {
+    let mut params = CodecParams::raw_mono();
+    params.channels = 4;
+    assert!(create_encoder(CodecType::Raw, &params).is_err());
+    assert!(create_decoder(CodecType::Raw, &params).is_err());
+    assert!(create_encoder(CodecType::ADPCM, &CodecParams::adpcm()).is_ok());
+}
+
+#[tokio::test]
+async fn test_capabilities_list_compiled_codecs() {
+    let codecs = CodecType::available();
+    assert_eq!(codecs, vec![CodecType::ADPCM, CodecType::Raw]);
+    let manager = CodecManager::new();
+    assert_eq!(manager.capabilities().await.codecs, codecs);
+    assert_eq!(manager.preferred_codec(), CodecType::ADPCM);
+    for codec in codecs {
+        let params = CodecParams::preset(codec);
+        assert!(create_encoder(codec, &params).is_ok());
+        assert!(create_decoder(codec, &params).is_ok());
+    }
+}
+
+#[tokio::test]
+async fn test_preferred_codec_is_offered_first() {
+    let manager = CodecManager::new();
+    assert!(manager.set_preferred_codec(CodecType::Raw));
+    assert_eq!(manager.preferred_codec(), CodecType::Raw);
+    assert_eq!(
+        manager.capabilities().await.codecs,
+        vec![CodecType::Raw, CodecType::ADPCM]
+    );
+    assert_eq!(manager.create_offer().codec, CodecType::Raw);
+    // The answer falls back to a codec the peer supports
+    let peer = CodecCapabilities {
+        codecs: vec![CodecType::ADPCM],
+        ..CodecCapabilities::default()
+    };
+    assert_eq!(
+        manager.create_answer(&peer).unwrap().codec,
+        CodecType::ADPCM
+    );
+    let peer = CodecCapabilities {
+        codecs: Vec::new(),
+        ..CodecCapabilities::default()
+    };
+    assert!(manager.create_answer(&peer).is_err());
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use ntied::audio::{
+    AdaptiveCodecManager, AudioConfig, AudioFrame, CodecNegotiator, CodecParams, CodecStats,
+    CodecType, Encoder, create_decoder, create_encoder,
+};
+use tokio::time::timeout;
+
+/// 20 ms of a 48 kHz mono tone.
+fn frame() -> Vec<f32> {
+    (0..960)
+        .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin() * 0.5)
+        .collect()
+}
+
+#[test]
+fn test_encode_time_is_averaged_and_reset() {
+    for codec in [CodecType::ADPCM, CodecType::Raw] {
+        let params = CodecParams::builder(codec).channels(1).build().unwrap();
+        let mut encoder = create_encoder(codec, &params).unwrap();
+        for _ in 0..10 {
+            encoder.encode(&frame()).unwrap();
+        }
+        let stats = encoder.stats();
+        assert_eq!(stats.frames_encoded, 10);
+        assert!(stats.bytes_encoded > 0);
+        assert!(stats.avg_encode_time_us > 0.0, "{codec:?}");
+
+        encoder.reset().unwrap();
+        let stats = encoder.stats();
+        assert_eq!(stats.frames_encoded, 0);
+        assert_eq!(stats.avg_encode_time_us, 0.0);
+    }
+}
+
+#[test]
+fn test_decode_time_is_averaged_and_reset() {
+    let params = CodecParams::builder(CodecType::ADPCM)
+        .channels(1)
+        .build()
+        .unwrap();
+    let mut encoder = create_encoder(CodecType::ADPCM, &params).unwrap();
+    let mut decoder = create_decoder(CodecType::ADPCM, &params).unwrap();
+    for _ in 0..10 {
+        decoder.decode(&encoder.encode(&frame()).unwrap()).unwrap();
+    }
+    let stats = decoder.stats();
+    assert_eq!(stats.frames_decoded, 10);
+    assert!(stats.avg_decode_time_us > 0.0);
+
+    decoder.reset().unwrap();
+    let stats = decoder.stats();
+    assert_eq!(stats.frames_decoded, 0);
+    assert_eq!(stats.avg_decode_time_us, 0.0);
+}
+
+#[tokio::test]
+async fn test_encoder_reports_encode_time() {
+    let encoder = Encoder::new(AudioConfig::new(48000, 1), CodecType::ADPCM);
+    assert_eq!(encoder.stats().avg_encode_time, Duration::ZERO);
+    for _ in 0..3 {
+        encoder
+            .send_frame(AudioFrame {
+                samples: frame(),
+                sample_rate: 48000,
+                channels: 1,
+                timestamp: std::time::Instant::now(),
+            })
+            .await
+            .unwrap();
+        timeout(Duration::from_secs(1), encoder.recv_packet())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+    assert!(encoder.stats().avg_encode_time > Duration::ZERO);
+}
+
+#[test]
+fn test_expensive_codec_is_detected() {
+    let mut adaptive = AdaptiveCodecManager::new(CodecNegotiator::default());
+    assert_eq!(adaptive.codec_cost(CodecType::ADPCM), None);
+    assert!(!adaptive.is_too_expensive(CodecType::ADPCM));
+
+    let stats = |encode, decode| CodecStats {
+        frames_encoded: 1,
+        frames_decoded: 1,
+        avg_encode_time_us: encode,
+        avg_decode_time_us: decode,
+        ..CodecStats::default()
+    };
+    adaptive.update_codec_stats(CodecType::ADPCM, &stats(50.0, 30.0));
+    assert_eq!(adaptive.codec_cost(CodecType::ADPCM), Some(80.0));
+    assert!(!adaptive.is_too_expensive(CodecType::ADPCM));
+
+    adaptive.update_codec_stats(CodecType::Raw, &stats(4000.0, 2000.0));
+    assert!(adaptive.is_too_expensive(CodecType::Raw));
+}
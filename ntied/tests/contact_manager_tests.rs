@@ -1,12 +1,25 @@
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use ntied::contact::{ContactManager, ContactStatus};
-use ntied::packet::ContactProfile;
-use ntied_crypto::PrivateKey;
+use async_trait::async_trait;
+use ntied::contact::{
+    ConnectionStatus, ContactListener, ContactManager, ContactStatus, Features, FocusTracker,
+    PROTOCOL_VERSION, PresenceSchedule, RequestThrottle, RequestVerdict, WakeDetector,
+    sanitize_intro,
+};
+use ntied::packet::{
+    ContactHelloPacket, ContactKeyRotationPacket, ContactPacket, ContactProfile,
+    ContactRequestPacket, Packet,
+};
+use ntied::ui::screens::ChatListScreen;
+use ntied::ui::{UiEvent, UiEventListener};
+use ntied_crypto::{PrivateKey, PublicKey};
 use ntied_server::Server;
-use ntied_transport::ToAddress;
+use ntied_transport::{Address, ToAddress, Transport};
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tokio::time::{sleep, timeout};
 
@@ -52,6 +65,7 @@ async fn test_accept_contact() {
         alice_key,
         ContactProfile {
             name: "Alice".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -60,6 +74,7 @@ async fn test_accept_contact() {
         bob_key,
         ContactProfile {
             name: "Bob".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -135,6 +150,7 @@ async fn test_reject_contact() {
         a_key,
         ContactProfile {
             name: "A".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -143,6 +159,7 @@ async fn test_reject_contact() {
         b_key,
         ContactProfile {
             name: "B".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -201,6 +218,7 @@ async fn test_simultaneous_connect_and_accept_paths() {
         left_key,
         ContactProfile {
             name: "Left".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -210,6 +228,7 @@ async fn test_simultaneous_connect_and_accept_paths() {
             right_key,
             ContactProfile {
                 name: "Right".to_string(),
+                avatar: None,
             },
         )
         .await,
@@ -269,6 +288,7 @@ async fn test_preknown_contact_auto_handshake() {
         a_key,
         ContactProfile {
             name: "Alice".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -277,6 +297,7 @@ async fn test_preknown_contact_auto_handshake() {
         b_key,
         ContactProfile {
             name: "Bob".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -287,6 +308,7 @@ async fn test_preknown_contact_auto_handshake() {
     // reply to A's Request with Accept, and both converge to Accepted.
     let b_known_profile = ContactProfile {
         name: "AliceKnown".to_string(),
+        avatar: None,
     };
     let b_known_handle = b_mgr
         .add_contact(a_addr, a_pub, b_known_profile.clone())
@@ -338,6 +360,7 @@ async fn test_both_outgoing_remain_pending() {
         a_key,
         ContactProfile {
             name: "A".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -346,6 +369,7 @@ async fn test_both_outgoing_remain_pending() {
         b_key,
         ContactProfile {
             name: "B".to_string(),
+            avatar: None,
         },
     )
     .await;
@@ -364,3 +388,1010 @@ async fn test_both_outgoing_remain_pending() {
     assert!(!matches!(b_outgoing.status(), ContactStatus::Accepted));
     server_handle.abort();
 }
+
+#[derive(Default)]
+struct RequestCounter {
+    incoming: AtomicUsize,
+    repeated: AtomicUsize,
+    key_rotated: std::sync::Mutex<Vec<(Address, Address)>>,
+    key_rotations_delivered: AtomicUsize,
+}
+
+#[async_trait]
+impl ContactListener for RequestCounter {
+    async fn on_server_connected(&self) {}
+
+    async fn on_server_reconnecting(&self) {}
+
+    async fn on_server_disconnected(&self) {}
+
+    async fn on_contact_connected(&self, _address: Address) {}
+
+    async fn on_contact_disconnected(&self, _address: Address) {}
+
+    async fn on_contact_incoming(
+        &self,
+        _address: Address,
+        _profile: ContactProfile,
+        _intro: Option<String>,
+    ) {
+        self.incoming.fetch_add(1, Ordering::SeqCst);
+    }
+
+    async fn on_contact_incoming_repeated(
+        &self,
+        _address: Address,
+        _profile: ContactProfile,
+        _intro: Option<String>,
+        _count: u32,
+    ) {
+        self.repeated.fetch_add(1, Ordering::SeqCst);
+    }
+
+    async fn on_contact_accepted(&self, _address: Address, _profile: ContactProfile) {}
+
+    async fn on_contact_rejected(&self, _address: Address) {}
+
+    async fn on_contact_key_rotated(&self, address: Address, public_key: PublicKey) {
+        let new_address = public_key.to_address().unwrap();
+        self.key_rotated
+            .lock()
+            .unwrap()
+            .push((address, new_address));
+    }
+
+    async fn on_key_rotation_delivered(&self, _address: Address) {
+        self.key_rotations_delivered.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn test_key_rotation_is_delivered_from_new_identity() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let old_key = PrivateKey::generate().unwrap();
+    let new_key = PrivateKey::generate().unwrap();
+    let bob_key = PrivateKey::generate().unwrap();
+    let old_addr = old_key.public_key().to_address().unwrap();
+    let new_addr = new_key.public_key().to_address().unwrap();
+    let bob_addr = bob_key.public_key().to_address().unwrap();
+    let profile = |name: &str| ContactProfile {
+        name: name.to_string(),
+        avatar: None,
+    };
+    let alice_counter = Arc::new(RequestCounter::default());
+    let bob_counter = Arc::new(RequestCounter::default());
+    // Alice rotated her key while Bob was offline and restarted since
+    let alice = ContactManager::with_listener(
+        server_addr,
+        new_key.clone(),
+        profile("Alice"),
+        alice_counter.clone(),
+    )
+    .await;
+    let packet = ContactKeyRotationPacket::new(&old_key, &new_key.public_key()).unwrap();
+    alice.set_pending_key_rotations(HashMap::from([(bob_addr, packet)]));
+    alice
+        .add_contact(bob_addr, bob_key.public_key(), profile("Bob"))
+        .await;
+    let bob = ContactManager::with_listener(
+        server_addr,
+        bob_key.clone(),
+        profile("Bob"),
+        bob_counter.clone(),
+    )
+    .await;
+    bob.add_contact(old_addr, old_key.public_key(), profile("Alice"))
+        .await;
+
+    let rotated = wait_until(
+        || !bob_counter.key_rotated.lock().unwrap().is_empty(),
+        100,
+        Duration::from_millis(100),
+    )
+    .await;
+    assert!(rotated, "Bob did not receive the key rotation");
+    assert_eq!(
+        *bob_counter.key_rotated.lock().unwrap(),
+        vec![(old_addr, new_addr)]
+    );
+    // The acknowledgement reaches Alice after Bob handles the rotation
+    let delivered = wait_until(
+        || alice_counter.key_rotations_delivered.load(Ordering::SeqCst) > 0,
+        100,
+        Duration::from_millis(100),
+    )
+    .await;
+    assert!(delivered, "Key rotation was not acknowledged");
+    assert_eq!(
+        alice_counter.key_rotations_delivered.load(Ordering::SeqCst),
+        1
+    );
+    assert!(alice.pending_key_rotations().is_empty());
+    // The announcing connection is not a contact request
+    assert_eq!(bob_counter.incoming.load(Ordering::SeqCst), 0);
+    // Adding the rotated contact replaces the pending connection
+    let handle = bob
+        .add_rotated_contact(new_addr, new_key.public_key(), profile("Alice"))
+        .await;
+    assert_eq!(handle.status(), ContactStatus::Accepted);
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_key_rotation_is_resent_until_acknowledged() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let old_key = PrivateKey::generate().unwrap();
+    let new_key = PrivateKey::generate().unwrap();
+    let bob_key = PrivateKey::generate().unwrap();
+    let old_addr = old_key.public_key().to_address().unwrap();
+    let new_addr = new_key.public_key().to_address().unwrap();
+    let bob_addr = bob_key.public_key().to_address().unwrap();
+    let profile = |name: &str| ContactProfile {
+        name: name.to_string(),
+        avatar: None,
+    };
+    // The first announcement reaches a transport that drops it, as a
+    // packet lost on the way
+    let lossy = Transport::bind("127.0.0.1:0", bob_addr, bob_key.clone(), server_addr)
+        .await
+        .unwrap();
+    let alice_counter = Arc::new(RequestCounter::default());
+    let alice = ContactManager::with_listener(
+        server_addr,
+        new_key.clone(),
+        profile("Alice"),
+        alice_counter.clone(),
+    )
+    .await;
+    let packet = ContactKeyRotationPacket::new(&old_key, &new_key.public_key()).unwrap();
+    alice.set_pending_key_rotations(HashMap::from([(bob_addr, packet)]));
+    let to_bob = alice
+        .add_contact(bob_addr, bob_key.public_key(), profile("Bob"))
+        .await;
+    let connection = timeout(Duration::from_secs(10), lossy.accept())
+        .await
+        .expect("Timed out waiting for Alice to connect to Bob")
+        .unwrap();
+    loop {
+        let data = timeout(Duration::from_secs(5), connection.recv())
+            .await
+            .expect("Timed out waiting for the key rotation")
+            .unwrap();
+        if matches!(
+            Packet::decode(&data),
+            Ok(Packet::Contact(ContactPacket::KeyRotation(_)))
+        ) {
+            break;
+        }
+    }
+    drop(connection);
+    drop(lossy);
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(
+        alice_counter.key_rotations_delivered.load(Ordering::SeqCst),
+        0
+    );
+    assert!(alice.pending_key_rotations().contains_key(&bob_addr));
+
+    // Bob comes back, the announcement is sent again on the new connection
+    let bob_counter = Arc::new(RequestCounter::default());
+    let bob = ContactManager::with_listener(
+        server_addr,
+        bob_key.clone(),
+        profile("Bob"),
+        bob_counter.clone(),
+    )
+    .await;
+    bob.add_contact(old_addr, old_key.public_key(), profile("Alice"))
+        .await;
+    sleep(Duration::from_millis(300)).await;
+    to_bob.reconnect().await.unwrap();
+    let delivered = wait_until(
+        || alice.pending_key_rotations().is_empty(),
+        150,
+        Duration::from_millis(100),
+    )
+    .await;
+    assert!(delivered, "Key rotation was not acknowledged");
+    assert_eq!(
+        *bob_counter.key_rotated.lock().unwrap(),
+        vec![(old_addr, new_addr)]
+    );
+    assert_eq!(
+        alice_counter.key_rotations_delivered.load(Ordering::SeqCst),
+        1
+    );
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_request_burst_is_coalesced() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let counter = Arc::new(RequestCounter::default());
+    let bob_key = PrivateKey::generate().unwrap();
+    let bob_addr = bob_key.public_key().to_address().unwrap();
+    let bob = ContactManager::with_listener(
+        server_addr,
+        bob_key,
+        ContactProfile {
+            name: "Bob".to_string(),
+            avatar: None,
+        },
+        counter.clone(),
+    )
+    .await;
+    // Hostile peer talks to the transport directly to flood requests
+    let mallory_key = PrivateKey::generate().unwrap();
+    let mallory_addr = mallory_key.public_key().to_address().unwrap();
+    let mallory = Transport::bind("127.0.0.1:0", mallory_addr, mallory_key, server_addr)
+        .await
+        .unwrap();
+    sleep(Duration::from_millis(400)).await;
+    let connection = mallory.connect(bob_addr).await.unwrap();
+    let request = bincode::serialize(&Packet::Contact(ContactPacket::Request(
+        ContactRequestPacket {
+            profile: ContactProfile {
+                name: "Mallory".to_string(),
+                avatar: None,
+            },
+            intro: None,
+        },
+    )))
+    .unwrap();
+    for _ in 0..20 {
+        connection.send(request.clone()).await.unwrap();
+    }
+    let incoming_address = timeout(Duration::from_secs(5), bob.on_incoming_address())
+        .await
+        .expect("Timed out waiting for incoming contact")
+        .unwrap();
+    assert_eq!(incoming_address, mallory_addr);
+    let notified = wait_until(
+        || counter.incoming.load(Ordering::SeqCst) > 0,
+        50,
+        Duration::from_millis(100),
+    )
+    .await;
+    assert!(notified, "Bob did not receive the request");
+    sleep(Duration::from_millis(500)).await;
+    // A single pending entry for the whole burst
+    assert_eq!(counter.incoming.load(Ordering::SeqCst), 1);
+    assert_eq!(counter.repeated.load(Ordering::SeqCst), 0);
+    assert_eq!(bob.list_contacts().await.len(), 1);
+    // Blocked address is forgotten and its connections are dropped
+    assert!(bob.block_address(mallory_addr).await.is_some());
+    assert!(bob.is_blocked(mallory_addr));
+    assert!(bob.list_contacts().await.is_empty());
+    server_handle.abort();
+}
+
+#[test]
+fn test_request_throttle_window() {
+    let address = PrivateKey::generate()
+        .unwrap()
+        .public_key()
+        .to_address()
+        .unwrap();
+    let mut throttle = RequestThrottle::new(Duration::from_secs(10));
+    let now = Instant::now();
+    assert_eq!(throttle.check(address, now), RequestVerdict::Notify);
+    assert_eq!(
+        throttle.check(address, now + Duration::from_secs(1)),
+        RequestVerdict::Coalesce
+    );
+    assert_eq!(
+        throttle.check(address, now + Duration::from_secs(5)),
+        RequestVerdict::Coalesce
+    );
+    assert_eq!(
+        throttle.check(address, now + Duration::from_secs(11)),
+        RequestVerdict::Repeat { count: 4 }
+    );
+    assert_eq!(
+        throttle.check(address, now + Duration::from_secs(12)),
+        RequestVerdict::Coalesce
+    );
+    throttle.block(address);
+    assert_eq!(
+        throttle.check(address, now + Duration::from_secs(30)),
+        RequestVerdict::Drop
+    );
+    assert!(throttle.unblock(&address));
+    assert_eq!(
+        throttle.check(address, now + Duration::from_secs(31)),
+        RequestVerdict::Notify
+    );
+}
+
+#[test]
+fn test_request_throttle_forgets_idle_requests() {
+    let addresses: Vec<_> = (0..2)
+        .map(|_| {
+            PrivateKey::generate()
+                .unwrap()
+                .public_key()
+                .to_address()
+                .unwrap()
+        })
+        .collect();
+    let mut throttle = RequestThrottle::new(Duration::from_secs(10));
+    let now = Instant::now();
+    assert_eq!(throttle.check(addresses[0], now), RequestVerdict::Notify);
+    assert_eq!(
+        throttle.check(addresses[1], now + Duration::from_secs(50)),
+        RequestVerdict::Notify
+    );
+    assert_eq!(throttle.pending_len(), 2);
+    assert_eq!(
+        throttle.check(addresses[1], now + Duration::from_secs(101)),
+        RequestVerdict::Repeat { count: 2 }
+    );
+    assert_eq!(throttle.pending_len(), 1);
+    // Blocked addresses are kept regardless of activity
+    throttle.block(addresses[0]);
+    assert_eq!(
+        throttle.check(addresses[1], now + Duration::from_secs(1000)),
+        RequestVerdict::Notify
+    );
+    assert_eq!(throttle.blocked(), vec![addresses[0]]);
+}
+
+#[tokio::test]
+async fn test_presence_is_announced_to_stored_contacts() {
+    let (server_addr, server_handle) = start_server().await;
+    let keys: Vec<_> = (0..3).map(|_| PrivateKey::generate().unwrap()).collect();
+    let addrs: Vec<_> = keys
+        .iter()
+        .map(|key| key.public_key().to_address().unwrap())
+        .collect();
+    let (alice_addr, bob_addr, carol_addr) = (addrs[0], addrs[1], addrs[2]);
+    let profile = |name: &str| ContactProfile {
+        name: name.to_string(),
+        avatar: None,
+    };
+    let alice = ContactManager::new(server_addr, keys[0].clone(), profile("Alice")).await;
+    let bob = ContactManager::new(server_addr, keys[1].clone(), profile("Bob")).await;
+    // Stored contacts are added before the server connection is established,
+    // Carol never comes online
+    let to_bob = alice
+        .add_contact(bob_addr, keys[1].public_key(), profile("Bob"))
+        .await;
+    alice
+        .add_contact(carol_addr, keys[2].public_key(), profile("Carol"))
+        .await;
+    let to_alice = bob
+        .add_contact(alice_addr, keys[0].public_key(), profile("Alice"))
+        .await;
+
+    // Much faster than the reconnect timeout of contact handles
+    assert!(
+        wait_until(
+            || to_bob.is_connected() && to_alice.is_connected(),
+            30,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Contacts did not see each other online"
+    );
+    sleep(Duration::from_secs(4)).await;
+    // The side announcing first connects both, the other one may skip it
+    let alice_to_bob = alice.presence_attempts(bob_addr);
+    let bob_to_alice = bob.presence_attempts(alice_addr);
+    assert!(alice_to_bob <= 1 && bob_to_alice <= 1);
+    assert!(alice_to_bob + bob_to_alice >= 1);
+    // Unreachable contacts are not retried within the backoff
+    assert_eq!(alice.presence_attempts(carol_addr), 1);
+    server_handle.abort();
+}
+
+#[test]
+fn test_presence_backoff() {
+    let address = PrivateKey::generate()
+        .unwrap()
+        .public_key()
+        .to_address()
+        .unwrap();
+    let mut presence = PresenceSchedule::new(
+        Duration::from_millis(10),
+        Duration::from_secs(30),
+        Duration::from_secs(100),
+    );
+    let now = Instant::now();
+    assert!(presence.try_announce(address, now));
+    presence.record_failure(address, now);
+    assert!(!presence.try_announce(address, now + Duration::from_secs(29)));
+    assert!(presence.try_announce(address, now + Duration::from_secs(30)));
+    // The backoff doubles up to the maximum
+    let now = now + Duration::from_secs(30);
+    presence.record_failure(address, now);
+    assert!(!presence.try_announce(address, now + Duration::from_secs(59)));
+    assert!(presence.try_announce(address, now + Duration::from_secs(60)));
+    let now = now + Duration::from_secs(60);
+    presence.record_failure(address, now);
+    assert!(!presence.try_announce(address, now + Duration::from_secs(99)));
+    assert!(presence.try_announce(address, now + Duration::from_secs(100)));
+    presence.record_success(address);
+    assert!(presence.try_announce(address, now + Duration::from_secs(100)));
+    assert_eq!(presence.attempts(address), 5);
+}
+
+#[tokio::test]
+async fn test_features_are_negotiated() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let alice = ContactManager::new(
+        server_addr,
+        PrivateKey::generate().unwrap(),
+        ContactProfile {
+            name: "Alice".to_string(),
+            avatar: None,
+        },
+    )
+    .await;
+    let bob = ContactManager::new(
+        server_addr,
+        PrivateKey::generate().unwrap(),
+        ContactProfile {
+            name: "Bob".to_string(),
+            avatar: None,
+        },
+    )
+    .await;
+    assert_eq!(alice.features(), Features::all());
+    alice.set_features(Features::FEC.union(Features::VIDEO));
+    bob.set_features(Features::FEC.union(Features::ADPCM_MICROSOFT));
+    sleep(Duration::from_millis(400)).await;
+    let alice_to_bob = alice.connect_contact(bob.get_own_address()).await;
+    let address = timeout(Duration::from_secs(5), bob.on_incoming_address())
+        .await
+        .expect("Timed out waiting for incoming contact")
+        .unwrap();
+    let bob_to_alice = bob.connect_contact(address).await;
+    assert!(
+        wait_until(
+            || bob_to_alice.profile().is_some(),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    assert!(bob_to_alice.negotiated().is_none());
+    bob_to_alice.accept().await.unwrap();
+    assert!(
+        wait_until(
+            || alice_to_bob.negotiated().is_some() && bob_to_alice.negotiated().is_some(),
+            50,
+            Duration::from_millis(100),
+        )
+        .await,
+        "Features were not negotiated"
+    );
+    for handle in [&alice_to_bob, &bob_to_alice] {
+        let negotiated = handle.negotiated().unwrap();
+        assert_eq!(negotiated.version, PROTOCOL_VERSION);
+        assert_eq!(negotiated.features, Features::FEC);
+        assert!(handle.features().contains(Features::FEC));
+        assert!(!handle.features().contains(Features::VIDEO));
+    }
+    server_handle.abort();
+}
+
+#[derive(Default)]
+struct StatusRecorder {
+    statuses: std::sync::Mutex<Vec<ConnectionStatus>>,
+    // Contact address with whether it was connected or disconnected
+    contacts: std::sync::Mutex<Vec<(Address, bool)>>,
+}
+
+impl StatusRecorder {
+    fn push(&self, status: ConnectionStatus) {
+        self.statuses.lock().unwrap().push(status);
+    }
+
+    fn statuses(&self) -> Vec<ConnectionStatus> {
+        self.statuses.lock().unwrap().clone()
+    }
+
+    fn take_contacts(&self) -> Vec<(Address, bool)> {
+        std::mem::take(&mut *self.contacts.lock().unwrap())
+    }
+}
+
+#[async_trait]
+impl ContactListener for StatusRecorder {
+    async fn on_server_connected(&self) {
+        self.push(ConnectionStatus::Connected);
+    }
+
+    async fn on_server_reconnecting(&self) {
+        self.push(ConnectionStatus::Reconnecting);
+    }
+
+    async fn on_server_disconnected(&self) {
+        self.push(ConnectionStatus::Disconnected);
+    }
+
+    async fn on_contact_connected(&self, address: Address) {
+        self.contacts.lock().unwrap().push((address, true));
+    }
+
+    async fn on_contact_disconnected(&self, address: Address) {
+        self.contacts.lock().unwrap().push((address, false));
+    }
+
+    async fn on_contact_incoming(
+        &self,
+        _address: Address,
+        _profile: ContactProfile,
+        _intro: Option<String>,
+    ) {
+    }
+
+    async fn on_contact_incoming_repeated(
+        &self,
+        _address: Address,
+        _profile: ContactProfile,
+        _intro: Option<String>,
+        _count: u32,
+    ) {
+    }
+
+    async fn on_contact_accepted(&self, _address: Address, _profile: ContactProfile) {}
+
+    async fn on_contact_rejected(&self, _address: Address) {}
+
+    async fn on_contact_key_rotated(&self, _address: Address, _public_key: PublicKey) {}
+
+    async fn on_key_rotation_delivered(&self, _address: Address) {}
+}
+
+#[tokio::test]
+async fn test_connection_status_reconnecting() {
+    init_tracing();
+    // Private server rejects the registration at once, so the manager
+    // keeps retrying until the token is configured.
+    let server = Server::new("127.0.0.1:0")
+        .await
+        .unwrap()
+        .with_token(Some("secret".to_string()));
+    let server_addr = server.local_addr().unwrap();
+    let server_handle = tokio::spawn(async move {
+        let _ = server.run().await;
+    });
+    let recorder = Arc::new(StatusRecorder::default());
+    let manager = ContactManager::with_listener(
+        server_addr,
+        PrivateKey::generate().unwrap(),
+        ContactProfile {
+            name: "Alice".to_string(),
+            avatar: None,
+        },
+        recorder.clone(),
+    )
+    .await;
+    sleep(Duration::from_millis(400)).await;
+    assert_eq!(manager.connection_status(), ConnectionStatus::Reconnecting);
+    assert!(!manager.is_connected());
+    // Failed attempts during backoff are reported once
+    sleep(Duration::from_millis(1500)).await;
+    assert_eq!(recorder.statuses(), vec![ConnectionStatus::Reconnecting]);
+    manager
+        .change_server_token(Some("secret".to_string()))
+        .await
+        .unwrap();
+    timeout(Duration::from_secs(5), async {
+        while !manager.is_connected() {
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("Manager should connect with the token");
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(manager.connection_status(), ConnectionStatus::Connected);
+    assert_eq!(
+        recorder.statuses(),
+        vec![ConnectionStatus::Reconnecting, ConnectionStatus::Connected]
+    );
+    server_handle.abort();
+}
+
+#[test]
+fn test_wake_detector() {
+    let mut wake = WakeDetector::new(Duration::from_secs(5), Duration::from_secs(10));
+    let now = Instant::now();
+    assert!(!wake.tick(now));
+    assert!(!wake.tick(now + Duration::from_secs(5)));
+    // A late timer is not a wake
+    assert!(!wake.tick(now + Duration::from_secs(20)));
+    assert!(wake.tick(now + Duration::from_secs(36)));
+    wake.reset();
+    assert!(!wake.tick(now + Duration::from_secs(100)));
+}
+
+#[tokio::test]
+async fn test_wake_from_sleep_reregisters() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let recorder = Arc::new(StatusRecorder::default());
+    let manager = ContactManager::with_listener(
+        server_addr,
+        PrivateKey::generate().unwrap(),
+        ContactProfile {
+            name: "Alice".to_string(),
+            avatar: None,
+        },
+        recorder.clone(),
+    )
+    .await;
+    assert!(wait_until(|| manager.is_connected(), 50, Duration::from_millis(50)).await);
+    assert!(manager.is_wake_recovery_enabled());
+    // Jump the clock as if the laptop slept, the connection is renewed
+    tokio::time::pause();
+    tokio::time::advance(Duration::from_secs(20)).await;
+    tokio::time::resume();
+    let reconnected = wait_until(
+        || recorder.statuses().len() >= 3 && manager.is_connected(),
+        100,
+        Duration::from_millis(50),
+    )
+    .await;
+    assert!(reconnected, "statuses: {:?}", recorder.statuses());
+    assert_eq!(
+        recorder.statuses(),
+        vec![
+            ConnectionStatus::Connected,
+            ConnectionStatus::Reconnecting,
+            ConnectionStatus::Connected,
+        ]
+    );
+    // Without recovery the same jump keeps the connection
+    manager.set_wake_recovery(false);
+    tokio::time::pause();
+    tokio::time::advance(Duration::from_secs(20)).await;
+    tokio::time::resume();
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(recorder.statuses().len(), 3);
+    assert!(manager.is_connected());
+    server_handle.abort();
+}
+
+#[test]
+fn test_focus_tracker() {
+    let mut focus = FocusTracker::new(Duration::from_secs(30));
+    let now = Instant::now();
+    // Focus without losing it first is not a return
+    assert!(!focus.focus(now));
+    focus.unfocus(now);
+    assert!(!focus.focus(now + Duration::from_secs(10)));
+    focus.unfocus(now + Duration::from_secs(20));
+    // Repeated unfocus events keep the first time
+    focus.unfocus(now + Duration::from_secs(40));
+    assert!(focus.focus(now + Duration::from_secs(50)));
+    assert!(!focus.focus(now + Duration::from_secs(100)));
+}
+
+#[tokio::test]
+async fn test_refresh_contacts_closes_stale_connections() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let keys: Vec<_> = (0..3).map(|_| PrivateKey::generate().unwrap()).collect();
+    let addrs: Vec<_> = keys
+        .iter()
+        .map(|key| key.public_key().to_address().unwrap())
+        .collect();
+    let (alice_addr, bob_addr, dave_addr) = (addrs[0], addrs[1], addrs[2]);
+    let profile = |name: &str| ContactProfile {
+        name: name.to_string(),
+        avatar: None,
+    };
+    let recorder = Arc::new(StatusRecorder::default());
+    let alice = ContactManager::with_listener(
+        server_addr,
+        keys[0].clone(),
+        profile("Alice"),
+        recorder.clone(),
+    )
+    .await;
+    let bob = ContactManager::new(server_addr, keys[1].clone(), profile("Bob")).await;
+    // Dave keeps the connection open but stopped answering, as a peer
+    // whose app hangs while its transport is alive
+    let dave = Transport::bind("127.0.0.1:0", dave_addr, keys[2].clone(), server_addr)
+        .await
+        .unwrap();
+    let to_bob = alice
+        .add_contact(bob_addr, keys[1].public_key(), profile("Bob"))
+        .await;
+    let to_dave = alice
+        .add_contact(dave_addr, keys[2].public_key(), profile("Dave"))
+        .await;
+    bob.add_contact(alice_addr, keys[0].public_key(), profile("Alice"))
+        .await;
+    let connection = timeout(Duration::from_secs(10), dave.accept())
+        .await
+        .expect("Timed out waiting for Alice to connect to Dave")
+        .unwrap();
+    let hello = bincode::serialize(&Packet::Contact(ContactPacket::Hello(ContactHelloPacket {
+        version: PROTOCOL_VERSION,
+        features: Features::all().bits(),
+    })))
+    .unwrap();
+    connection.send(hello).await.unwrap();
+    assert!(
+        wait_until(
+            || to_bob.is_connected() && to_dave.is_connected() && to_dave.negotiated().is_some(),
+            100,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Contacts did not come online"
+    );
+    assert!(alice.is_focus_refresh_enabled());
+    recorder.take_contacts();
+    alice.refresh_contacts().await;
+    // Dave does not answer the ping and is shown offline, Bob stays online
+    assert!(
+        wait_until(|| !to_dave.is_connected(), 50, Duration::from_millis(100)).await,
+        "Stale connection was not closed"
+    );
+    // Bob may reconnect when the ping lands on the connection dropped after
+    // both sides connected at once, but he answers and stays online
+    assert!(wait_until(|| to_bob.is_connected(), 50, Duration::from_millis(100)).await);
+    let dave_statuses: Vec<_> = recorder
+        .take_contacts()
+        .into_iter()
+        .filter(|(address, _)| *address == dave_addr)
+        .collect();
+    assert_eq!(dave_statuses, vec![(dave_addr, false)]);
+    // The connection is established again
+    let reconnected = timeout(Duration::from_secs(15), dave.accept()).await;
+    assert!(reconnected.is_ok_and(|v| v.is_ok()));
+    drop(connection);
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_cancel_connect() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    // Bob is registered but never accepts, the handshake stays in flight.
+    let bob_key = PrivateKey::generate().unwrap();
+    let bob_addr = bob_key.public_key().to_address().unwrap();
+    let _bob = Transport::bind("127.0.0.1:0", bob_addr, bob_key, server_addr)
+        .await
+        .unwrap();
+    let alice = ContactManager::new(
+        server_addr,
+        PrivateKey::generate().unwrap(),
+        ContactProfile {
+            name: "Alice".to_string(),
+            avatar: None,
+        },
+    )
+    .await;
+    assert!(wait_until(|| alice.is_connected(), 50, Duration::from_millis(50)).await);
+    let handle = alice.connect_contact(bob_addr).await;
+    sleep(Duration::from_millis(300)).await;
+    assert!(handle.is_running());
+    assert!(!handle.is_connected());
+    assert!(alice.cancel_connect(bob_addr).await);
+    assert!(wait_until(|| !handle.is_running(), 20, Duration::from_millis(10)).await);
+    assert!(!handle.is_connected());
+    assert!(alice.list_contacts().await.is_empty());
+    // Nothing is left to cancel.
+    assert!(!alice.cancel_connect(bob_addr).await);
+    server_handle.abort();
+}
+
+#[test]
+fn test_sanitize_intro() {
+    assert_eq!(
+        sanitize_intro("  Hi,\tit's Alice\r\nfrom   work\u{7}  ").as_deref(),
+        Some("Hi, it's Alice from work")
+    );
+    assert_eq!(sanitize_intro(" \n\t "), None);
+    let long = sanitize_intro(&"a".repeat(1000)).unwrap();
+    assert_eq!(long.chars().count(), ContactRequestPacket::MAX_INTRO_LEN);
+}
+
+#[tokio::test]
+async fn test_contact_request_intro() {
+    let (server_addr, server_handle) = start_server().await;
+    let alice_key = PrivateKey::generate().unwrap();
+    let alice_addr = alice_key.public_key().to_address().unwrap();
+    let bob_key = PrivateKey::generate().unwrap();
+    let bob_addr = bob_key.public_key().to_address().unwrap();
+    let alice = ContactManager::new(
+        server_addr,
+        alice_key,
+        ContactProfile {
+            name: "Alice".to_string(),
+            avatar: None,
+        },
+    )
+    .await;
+    let (event_tx, mut event_rx) = mpsc::channel(100);
+    let bob = ContactManager::with_listener(
+        server_addr,
+        bob_key,
+        ContactProfile {
+            name: "Bob".to_string(),
+            avatar: None,
+        },
+        Arc::new(UiEventListener::new(event_tx)),
+    )
+    .await;
+    sleep(Duration::from_millis(400)).await;
+
+    let alice_to_bob = alice
+        .connect_contact_with_intro(bob_addr, Some("Hi, it's Alice\nfrom work"))
+        .await;
+    assert_eq!(
+        alice_to_bob.intro().as_deref(),
+        Some("Hi, it's Alice from work")
+    );
+    let event = timeout(Duration::from_secs(5), async {
+        loop {
+            match event_rx.recv().await.unwrap() {
+                event @ UiEvent::IncomingRequest { .. } => return event,
+                _ => continue,
+            }
+        }
+    })
+    .await
+    .expect("Timed out waiting for the incoming request");
+    let mut screen = ChatListScreen::new(None);
+    screen.apply_event(event);
+    assert_eq!(
+        screen.incoming_request_intro(&alice_addr.to_string()),
+        Some("Hi, it's Alice from work")
+    );
+
+    let bob_incoming = bob.connect_contact(alice_addr).await;
+    assert_eq!(
+        bob_incoming.intro().as_deref(),
+        Some("Hi, it's Alice from work")
+    );
+    bob_incoming.accept().await.unwrap();
+    assert!(
+        wait_until(
+            || alice_to_bob.status() == ContactStatus::Accepted,
+            50,
+            Duration::from_millis(100),
+        )
+        .await
+    );
+    // The note is kept only while the request is pending
+    assert!(
+        wait_until(
+            || alice_to_bob.intro().is_none() && bob_incoming.intro().is_none(),
+            50,
+            Duration::from_millis(100),
+        )
+        .await
+    );
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_raw_packets_are_delivered_to_subscribers() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let alice = ContactManager::new(
+        server_addr,
+        PrivateKey::generate().unwrap(),
+        ContactProfile {
+            name: "Alice".to_string(),
+            avatar: None,
+        },
+    )
+    .await;
+    let bob = ContactManager::new(
+        server_addr,
+        PrivateKey::generate().unwrap(),
+        ContactProfile {
+            name: "Bob".to_string(),
+            avatar: None,
+        },
+    )
+    .await;
+    sleep(Duration::from_millis(400)).await;
+    let alice_to_bob = alice.connect_contact(bob.get_own_address()).await;
+    let address = timeout(Duration::from_secs(5), bob.on_incoming_address())
+        .await
+        .expect("Timed out waiting for incoming contact")
+        .unwrap();
+    let bob_to_alice = bob.connect_contact(address).await;
+    assert!(
+        wait_until(
+            || bob_to_alice.profile().is_some(),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    bob_to_alice.accept().await.unwrap();
+    assert!(
+        wait_until(
+            || alice_to_bob.negotiated().is_some() && bob_to_alice.negotiated().is_some(),
+            50,
+            Duration::from_millis(100),
+        )
+        .await,
+        "Contacts were not connected"
+    );
+    assert!(alice_to_bob.features().contains(Features::APP_PACKETS));
+
+    let mut raw = bob_to_alice.subscribe_raw();
+    alice_to_bob
+        .send_raw(b"custom-payload".to_vec())
+        .await
+        .unwrap();
+    let data = timeout(Duration::from_secs(5), raw.recv())
+        .await
+        .expect("Timed out waiting for raw packet")
+        .unwrap();
+    assert_eq!(data, b"custom-payload");
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_allowlisted_key_is_auto_accepted() {
+    init_tracing();
+    let (server_addr, server_handle) = start_server().await;
+    let alice_key = PrivateKey::generate().unwrap();
+    let alice_public_key = alice_key.public_key();
+    let profile = |name: &str| ContactProfile {
+        name: name.to_string(),
+        avatar: None,
+    };
+    let alice = ContactManager::new(server_addr, alice_key, profile("Alice")).await;
+    let carol_key = PrivateKey::generate().unwrap();
+    let carol_public_key = carol_key.public_key();
+    let carol = ContactManager::new(server_addr, carol_key, profile("Carol")).await;
+    let bob =
+        ContactManager::new(server_addr, PrivateKey::generate().unwrap(), profile("Bob")).await;
+    bob.set_auto_accept_keys(vec![alice_public_key.clone()]);
+    assert!(bob.is_auto_accepted(&alice_public_key));
+    assert!(!bob.is_auto_accepted(&carol_public_key));
+    sleep(Duration::from_millis(400)).await;
+
+    let alice_to_bob = alice.connect_contact(bob.get_own_address()).await;
+    let address = timeout(Duration::from_secs(5), bob.on_incoming_address())
+        .await
+        .expect("Timed out waiting for incoming contact")
+        .unwrap();
+    assert_eq!(address, alice.get_own_address());
+    let bob_to_alice = bob.connect_contact(address).await;
+    assert!(
+        wait_until(
+            || bob_to_alice.status() == ContactStatus::Accepted
+                && alice_to_bob.status() == ContactStatus::Accepted,
+            50,
+            Duration::from_millis(100),
+        )
+        .await,
+        "Allowlisted request was not accepted"
+    );
+
+    carol.connect_contact(bob.get_own_address()).await;
+    let address = timeout(Duration::from_secs(5), bob.on_incoming_address())
+        .await
+        .expect("Timed out waiting for incoming contact")
+        .unwrap();
+    assert_eq!(address, carol.get_own_address());
+    let bob_to_carol = bob.connect_contact(address).await;
+    assert!(
+        wait_until(
+            || bob_to_carol.profile().is_some(),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    sleep(Duration::from_millis(200)).await;
+    assert_eq!(bob_to_carol.status(), ContactStatus::PendingIncoming);
+    server_handle.abort();
+}
@@ -0,0 +1,63 @@
+use std::f32::consts::PI;
+
+use ntied::audio::{ContentClassifier, ContentType};
+
+const SAMPLE_RATE: f32 = 48000.0;
+const FRAME_SAMPLES: usize = 960; // 20ms at 48kHz
+
+/// Classifies `seconds` of a mono signal frame by frame, returns the last result.
+fn classify(signal: impl Fn(f32) -> f32, seconds: usize) -> ContentType {
+    let mut classifier = ContentClassifier::default();
+    let mut content = ContentType::Speech;
+    for frame in 0..seconds * 50 {
+        let samples: Vec<f32> = (0..FRAME_SAMPLES)
+            .map(|i| signal((frame * FRAME_SAMPLES + i) as f32 / SAMPLE_RATE))
+            .collect();
+        content = classifier.classify(&samples, 1);
+    }
+    content
+}
+
+#[test]
+fn test_tonal_music_is_classified_as_music() {
+    // A major chord held steady
+    let chord = |t: f32| {
+        [220.0, 277.18, 329.63]
+            .iter()
+            .map(|f| (2.0 * PI * f * t).sin() * 0.2)
+            .sum::<f32>()
+    };
+    assert_eq!(classify(chord, 2), ContentType::Music);
+}
+
+#[test]
+fn test_speech_like_input_is_classified_as_speech() {
+    // Harmonic buzz with a gliding pitch, cut into syllables with pauses
+    let speech = |t: f32| {
+        let pitch = 120.0 + 20.0 * (2.0 * PI * 0.7 * t).sin();
+        let buzz = (1..=10)
+            .map(|h| (2.0 * PI * pitch * h as f32 * t).sin() / h as f32)
+            .sum::<f32>();
+        let syllables = (2.0 * PI * 4.0 * t).sin().max(0.0).powi(2);
+        buzz * syllables * 0.2
+    };
+    assert_eq!(classify(speech, 2), ContentType::Speech);
+}
+
+#[test]
+fn test_content_is_speech_until_window_is_full() {
+    let mut classifier = ContentClassifier::new(10);
+    let tone: Vec<f32> = (0..FRAME_SAMPLES)
+        .map(|i| (2.0 * PI * 440.0 * i as f32 / SAMPLE_RATE).sin() * 0.3)
+        .collect();
+    for _ in 0..9 {
+        assert_eq!(classifier.classify(&tone, 1), ContentType::Speech);
+    }
+    assert_eq!(classifier.classify(&tone, 1), ContentType::Music);
+    // Silence is not music
+    let silence = vec![0.0; FRAME_SAMPLES];
+    for _ in 0..10 {
+        classifier.classify(&silence, 1);
+    }
+    assert_eq!(classifier.current(), ContentType::Speech);
+}
@@ -0,0 +1,30 @@
+use ntied::ui::qr::qr_svg;
+use ntied::ui::screens::CopyConfirmation;
+
+#[test]
+fn test_copy_confirmation_clears() {
+    let mut copied = CopyConfirmation::default();
+    assert!(!copied.is_visible());
+    let generation = copied.show();
+    assert!(copied.is_visible());
+    copied.clear(generation);
+    assert!(!copied.is_visible());
+}
+
+#[test]
+fn test_stale_clear_is_ignored() {
+    let mut copied = CopyConfirmation::default();
+    let first = copied.show();
+    let second = copied.show();
+    copied.clear(first);
+    assert!(copied.is_visible());
+    copied.clear(second);
+    assert!(!copied.is_visible());
+}
+
+#[test]
+fn test_address_qr_code() {
+    let svg = qr_svg("f3c1a8e4b6d2f0e9c7a5b3d1f8e6c4a2b0d9f7e5").unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("<path d=\"M"));
+}
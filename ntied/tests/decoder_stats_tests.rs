@@ -0,0 +1,218 @@
+use ntied::audio::{
+    AdpcmEncoder, AdpcmVariant, AudioConfig, AudioEncoder, CodecType, ContentType, Decoder,
+    DecoderConfig, DecoderStats, PlayoutOrder, RawEncoder,
+};
+use ntied::packet::AudioDataPacket;
+use tokio::time::{Duration, timeout};
+use uuid::Uuid;
+
+const FRAME_SAMPLES: usize = 960; // 20ms at 48kHz
+
+fn audio_packet(encoder: &mut AdpcmEncoder, sequence: u32) -> AudioDataPacket {
+    let samples: Vec<f32> = (0..FRAME_SAMPLES)
+        .map(|i| ((sequence as usize * FRAME_SAMPLES + i) as f32 * 0.05).sin() * 0.3)
+        .collect();
+    AudioDataPacket {
+        call_id: Uuid::nil(),
+        sequence,
+        timestamp: sequence as u64 * 20_000,
+        codec: CodecType::ADPCM,
+        channels: 1,
+        data: encoder.encode(&samples).unwrap(),
+        content: ContentType::Speech,
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_decoder_counts_decoded_and_concealed_frames() {
+    let decoder = Decoder::new(AudioConfig::new(48000, 1), CodecType::ADPCM);
+    let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+    // Packet 6 is lost
+    let sequences: Vec<u32> = (0..10).filter(|&s| s != 6).collect();
+    for &sequence in &sequences {
+        decoder
+            .send_packet(audio_packet(&mut encoder, sequence))
+            .await
+            .unwrap();
+    }
+    // 9 decoded frames, 1 concealed gap and 3 concealed frames after the stream ends
+    for _ in 0..13 {
+        decoder.recv_frame().await.unwrap();
+    }
+    let stats = decoder.stats();
+    assert_eq!(
+        stats,
+        DecoderStats {
+            sent_packets: 9,
+            received_frames: 13,
+            sent_bytes: stats.sent_bytes,
+            received_bytes: 13 * FRAME_SAMPLES as u64 * 4,
+            decoded_frames: 9,
+            plc_frames: 4,
+            underruns: 3,
+            avg_decode_time: stats.avg_decode_time,
+        }
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_decoder_mutes_long_outage() {
+    let config = DecoderConfig {
+        max_consecutive_plc: 3,
+        plc_fade_rate: 0.2,
+        ..DecoderConfig::default()
+    };
+    let decoder = Decoder::with_config(AudioConfig::new(48000, 1), CodecType::ADPCM, config);
+    let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+    for sequence in 0..5 {
+        decoder
+            .send_packet(audio_packet(&mut encoder, sequence))
+            .await
+            .unwrap();
+    }
+    for _ in 0..5 {
+        decoder.recv_frame().await.unwrap();
+    }
+    let energy = |samples: &[f32]| samples.iter().map(|s| s * s).sum::<f32>();
+    // The stream stops, concealment fades out
+    let mut last = f32::MAX;
+    for _ in 0..3 {
+        let frame = decoder.recv_frame().await.unwrap();
+        let frame_energy = energy(&frame.samples);
+        assert!(frame_energy > 0.0);
+        assert!(frame_energy < last);
+        last = frame_energy;
+    }
+    // Beyond the limit nothing is synthesized
+    for _ in 0..10 {
+        let frame = decoder.recv_frame().await.unwrap();
+        assert_eq!(energy(&frame.samples), 0.0);
+    }
+    assert_eq!(decoder.stats().plc_frames, 13);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_decoder_prebuffers_at_start() {
+    let config = DecoderConfig {
+        prebuffer_frames: 5,
+        ..DecoderConfig::default()
+    };
+    let decoder = Decoder::with_config(AudioConfig::new(48000, 1), CodecType::ADPCM, config);
+    let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+    for sequence in 0..3 {
+        decoder
+            .send_packet(audio_packet(&mut encoder, sequence))
+            .await
+            .unwrap();
+    }
+    // Below the threshold playback is withheld, nothing is concealed
+    assert!(
+        timeout(Duration::from_millis(100), decoder.recv_frame())
+            .await
+            .is_err()
+    );
+    let stats = decoder.stats();
+    assert_eq!(stats.received_frames, 0);
+    assert_eq!(stats.plc_frames, 0);
+    assert_eq!(stats.underruns, 0);
+    for sequence in 3..5 {
+        decoder
+            .send_packet(audio_packet(&mut encoder, sequence))
+            .await
+            .unwrap();
+    }
+    // The threshold is reached, the buffered frames flow
+    for _ in 0..5 {
+        decoder.recv_frame().await.unwrap();
+    }
+    let stats = decoder.stats();
+    assert_eq!(stats.decoded_frames, 5);
+    assert_eq!(stats.plc_frames, 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_decoder_prebuffer_gives_up_waiting() {
+    let config = DecoderConfig {
+        prebuffer_frames: 5,
+        ..DecoderConfig::default()
+    };
+    let decoder = Decoder::with_config(AudioConfig::new(48000, 1), CodecType::ADPCM, config);
+    let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+    decoder
+        .send_packet(audio_packet(&mut encoder, 0))
+        .await
+        .unwrap();
+    // A sparse stream starts playing after twice the prebuffer duration
+    timeout(Duration::from_millis(300), decoder.recv_frame())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(decoder.stats().decoded_frames, 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_decoder_follows_codec_of_packets() {
+    let decoder = Decoder::new(AudioConfig::new(48000, 1), CodecType::ADPCM);
+    let mut encoder = RawEncoder::new(1).unwrap();
+    for sequence in 0..5 {
+        let samples = vec![0.25; FRAME_SAMPLES];
+        let packet = AudioDataPacket {
+            call_id: Uuid::nil(),
+            sequence,
+            timestamp: sequence as u64 * 20_000,
+            codec: CodecType::Raw,
+            channels: 1,
+            data: encoder.encode(&samples).unwrap(),
+            content: ContentType::Speech,
+        };
+        decoder.send_packet(packet).await.unwrap();
+    }
+    for _ in 0..5 {
+        let frame = decoder.recv_frame().await.unwrap();
+        assert!(frame.samples.iter().all(|s| (s - 0.25).abs() < 0.01));
+    }
+    assert_eq!(decoder.stats().decoded_frames, 5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_decoder_plays_packets_in_timestamp_order() {
+    let config = DecoderConfig {
+        playout_order: PlayoutOrder::Timestamp,
+        ..DecoderConfig::default()
+    };
+    let decoder = Decoder::with_config(AudioConfig::new(48000, 1), CodecType::Raw, config);
+    let mut encoder = RawEncoder::new(1).unwrap();
+    // The sender paused for two frames before the last packet
+    let timestamps = [0, 20_000, 40_000, 60_000, 120_000];
+    // Packets arrive out of order
+    for index in [2, 0, 3, 1, 4] {
+        let samples = vec![0.1 * (index + 1) as f32; FRAME_SAMPLES];
+        let packet = AudioDataPacket {
+            call_id: Uuid::nil(),
+            sequence: index as u32,
+            timestamp: 1_000_000 + timestamps[index],
+            codec: CodecType::Raw,
+            channels: 1,
+            data: encoder.encode(&samples).unwrap(),
+            content: ContentType::Speech,
+        };
+        decoder.send_packet(packet).await.unwrap();
+    }
+    let mut levels = Vec::new();
+    for _ in 0..7 {
+        let frame = decoder.recv_frame().await.unwrap();
+        levels.push(frame.samples[0]);
+    }
+    for (slot, level) in [(0, 0.1), (1, 0.2), (2, 0.3), (3, 0.4), (6, 0.5)] {
+        assert!(
+            (levels[slot] - level).abs() < 0.01,
+            "Unexpected level {} in slot {}",
+            levels[slot],
+            slot
+        );
+    }
+    let stats = decoder.stats();
+    assert_eq!(stats.decoded_frames, 5);
+    // The pause is kept instead of playing the last packet early
+    assert_eq!(stats.plc_frames, 2);
+}
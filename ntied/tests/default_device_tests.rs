@@ -0,0 +1,71 @@
+use ntied::audio::DeviceType;
+use ntied::call::DefaultDeviceAction;
+use ntied::ui::UiEvent;
+use ntied::ui::screens::ChatListScreen;
+
+#[test]
+fn test_default_device_action() {
+    // A call on the old default is offered the new one
+    assert_eq!(
+        DefaultDeviceAction::decide(None, Some("Headset"), false),
+        Some(DefaultDeviceAction::Offer("Headset".to_string()))
+    );
+    assert_eq!(
+        DefaultDeviceAction::decide(Some("Speakers"), Some("Headset"), true),
+        Some(DefaultDeviceAction::Switch("Headset".to_string()))
+    );
+    // The call already uses the device
+    assert_eq!(
+        DefaultDeviceAction::decide(Some("Headset"), Some("Headset"), true),
+        None
+    );
+    // No default device is left
+    assert_eq!(DefaultDeviceAction::decide(None, None, true), None);
+}
+
+fn screen_in_call(address: &str) -> ChatListScreen {
+    let mut screen = ChatListScreen::new(None);
+    screen.restore_call_state(
+        Some(address.to_string()),
+        Some("Bob".to_string()),
+        Some("connected".to_string()),
+        None,
+        None,
+    );
+    screen
+}
+
+#[test]
+fn test_default_device_offer_is_shown() {
+    let mut screen = screen_in_call("bob");
+    screen.apply_event(UiEvent::DefaultDeviceChanged {
+        address: "carol".to_string(),
+        device_type: DeviceType::Output,
+        action: DefaultDeviceAction::Offer("Headset".to_string()),
+    });
+    assert_eq!(screen.device_offer(), None);
+
+    screen.apply_event(UiEvent::DefaultDeviceChanged {
+        address: "bob".to_string(),
+        device_type: DeviceType::Output,
+        action: DefaultDeviceAction::Offer("Headset".to_string()),
+    });
+    assert_eq!(screen.device_offer(), Some((DeviceType::Output, "Headset")));
+}
+
+#[test]
+fn test_default_device_switch_updates_selection() {
+    let mut screen = screen_in_call("bob");
+    screen.apply_event(UiEvent::DefaultDeviceChanged {
+        address: "bob".to_string(),
+        device_type: DeviceType::Output,
+        action: DefaultDeviceAction::Offer("Headset".to_string()),
+    });
+    screen.apply_event(UiEvent::DefaultDeviceChanged {
+        address: "bob".to_string(),
+        device_type: DeviceType::Output,
+        action: DefaultDeviceAction::Switch("Headset".to_string()),
+    });
+    assert_eq!(screen.device_offer(), None);
+    assert_eq!(screen.selected_output_device(), Some("Headset"));
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "audio")]
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ntied::audio::{AudioDevice, AudioHost, AudioManager, DeviceChange, DeviceType};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+/// Host with a device list edited by the test.
+#[derive(Default)]
+struct MockHost {
+    devices: Mutex<Vec<AudioDevice>>,
+}
+
+impl MockHost {
+    fn add(&self, name: &str, device_type: DeviceType, is_default: bool) {
+        self.devices
+            .lock()
+            .unwrap()
+            .push(device(name, device_type, is_default));
+    }
+
+    fn remove(&self, name: &str) {
+        self.devices.lock().unwrap().retain(|d| d.name != name);
+    }
+
+    fn set_default(&self, name: &str, device_type: DeviceType) {
+        for d in self.devices.lock().unwrap().iter_mut() {
+            if d.device_type == device_type {
+                d.is_default = d.name == name;
+            }
+        }
+    }
+}
+
+impl AudioHost for MockHost {
+    fn input_devices(&self) -> anyhow::Result<Vec<cpal::Device>> {
+        Ok(Vec::new())
+    }
+
+    fn output_devices(&self) -> anyhow::Result<Vec<cpal::Device>> {
+        Ok(Vec::new())
+    }
+
+    fn default_input_device(&self) -> Option<cpal::Device> {
+        None
+    }
+
+    fn default_output_device(&self) -> Option<cpal::Device> {
+        None
+    }
+
+    fn list_devices(&self, device_type: DeviceType) -> anyhow::Result<Vec<AudioDevice>> {
+        let devices = self.devices.lock().unwrap();
+        Ok(devices
+            .iter()
+            .filter(|d| d.device_type == device_type)
+            .cloned()
+            .collect())
+    }
+}
+
+fn device(name: &str, device_type: DeviceType, is_default: bool) -> AudioDevice {
+    AudioDevice {
+        name: name.to_string(),
+        is_default,
+        device_type,
+    }
+}
+
+async fn next_change(rx: &mut mpsc::Receiver<DeviceChange>) -> DeviceChange {
+    timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timeout waiting for device change")
+        .expect("subscription closed")
+}
+
+#[tokio::test]
+async fn test_device_changes_are_delivered() {
+    let host = Arc::new(MockHost::default());
+    host.add("Built-in Microphone", DeviceType::Input, true);
+    host.add("Speakers", DeviceType::Output, true);
+    let mut rx =
+        AudioManager::subscribe_device_changes_from(host.clone(), Duration::from_millis(10))
+            .await
+            .unwrap();
+
+    // Devices present at subscription are not reported
+    host.add("USB Headset", DeviceType::Input, false);
+    assert_eq!(
+        next_change(&mut rx).await,
+        DeviceChange::Added(device("USB Headset", DeviceType::Input, false))
+    );
+
+    host.remove("Speakers");
+    assert_eq!(
+        next_change(&mut rx).await,
+        DeviceChange::Removed(device("Speakers", DeviceType::Output, true))
+    );
+    assert_eq!(
+        next_change(&mut rx).await,
+        DeviceChange::DefaultChanged(DeviceType::Output, None)
+    );
+
+    // The headset becomes the default microphone
+    host.set_default("USB Headset", DeviceType::Input);
+    assert_eq!(
+        next_change(&mut rx).await,
+        DeviceChange::DefaultChanged(DeviceType::Input, Some("USB Headset".to_string()))
+    );
+
+    // Nothing else is reported while devices stay the same
+    assert!(
+        timeout(Duration::from_millis(100), rx.recv())
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,70 @@
+use ntied::audio::{CodecType, DecoderStats};
+use ntied::contact::ConnectionStatus;
+use ntied::diagnostics::DiagnosticsBundle;
+use ntied::logs::LogBuffer;
+use ntied_crypto::PrivateKey;
+use ntied_transport::ToAddress as _;
+use tracing::Level;
+use tracing_subscriber::prelude::*;
+
+#[test]
+fn test_bundle_has_sections_without_sensitive_fields() {
+    let key = PrivateKey::generate().unwrap();
+    let pem = key.to_pem().unwrap();
+    let address = key.public_key().to_address().unwrap().to_string();
+    let token = "server-token-1234";
+
+    let buffer = LogBuffer::new(10);
+    let subscriber = tracing_subscriber::registry().with(buffer.layer());
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!("Connected to {}", address);
+        tracing::debug!(token, "Registering on the server");
+        tracing::warn!("Loaded key\n{}", pem);
+    });
+
+    let mut bundle = DiagnosticsBundle::new(ConnectionStatus::Connected);
+    bundle.connectivity = Some(format!("Target: {address}\nResult: connected"));
+    bundle.codecs = vec![CodecType::ADPCM, CodecType::Raw];
+    bundle.call_stats = Some(DecoderStats {
+        decoded_frames: 100,
+        plc_frames: 3,
+        ..DecoderStats::default()
+    });
+    bundle.logs = buffer.lines(Level::TRACE);
+    bundle.secrets = vec![token.to_string()];
+    let text = bundle.to_string();
+
+    for section in [
+        "== Version ==",
+        "== Connection ==",
+        "== Connectivity ==",
+        "== Codecs ==",
+        "== Call ==",
+        "== Logs ==",
+    ] {
+        assert!(text.contains(section), "Missing section {section}");
+    }
+    assert!(text.contains(env!("CARGO_PKG_VERSION")));
+    assert!(text.contains("Server: Connected"));
+    assert!(text.contains("Result: connected"));
+    assert!(text.contains("ADPCM, Raw PCM"));
+    assert!(text.contains("Concealed frames: 3"));
+    assert!(text.contains("Registering on the server"));
+    // Addresses are shortened, the key and the token are removed
+    assert!(text.contains(&format!("Connected to {}...", &address[..6])));
+    assert!(!text.contains(&address));
+    assert!(!text.contains("PRIVATE KEY"));
+    for line in pem.lines().filter(|v| !v.starts_with("-----")) {
+        assert!(!text.contains(line));
+    }
+    assert!(!text.contains(token));
+    assert!(text.contains("[redacted]"));
+}
+
+#[test]
+fn test_bundle_without_call() {
+    let text = DiagnosticsBundle::new(ConnectionStatus::Disconnected).to_string();
+    assert!(text.contains("Server: Disconnected"));
+    assert!(text.contains("Not run"));
+    assert!(text.contains("No active call"));
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+use ntied::audio::{AudioFrame, FramePacer};
+
+const FRAME: Duration = Duration::from_millis(20);
+
+fn frame(timestamp: Instant) -> AudioFrame {
+    AudioFrame {
+        samples: vec![0.0; 960 * 2],
+        sample_rate: 48000,
+        channels: 2,
+        timestamp,
+    }
+}
+
+#[test]
+fn test_audio_frame_duration() {
+    assert_eq!(frame(Instant::now()).duration(), FRAME);
+}
+
+#[test]
+fn test_bursty_frames_are_paced() {
+    let mut pacer = FramePacer::default();
+    let start = Instant::now();
+    // Bursts of three frames every 60 ms with a little arrival jitter
+    let mut releases = Vec::new();
+    for burst in 0..10u64 {
+        let arrival = start + Duration::from_millis(burst * 60 + burst % 3);
+        for _ in 0..3 {
+            releases.push(pacer.schedule(FRAME, arrival));
+        }
+    }
+    for pair in releases.windows(2) {
+        let interval = pair[1] - pair[0];
+        assert!(
+            interval >= FRAME && interval <= FRAME + Duration::from_millis(3),
+            "irregular interval {interval:?}"
+        );
+    }
+}
+
+#[test]
+fn test_pacer_bounds_delay() {
+    let mut pacer = FramePacer::new(Duration::from_millis(40));
+    let now = Instant::now();
+    let releases: Vec<_> = (0..5).map(|_| pacer.schedule(FRAME, now)).collect();
+    assert_eq!(releases[1] - now, FRAME);
+    assert_eq!(releases[2] - now, FRAME * 2);
+    // Schedule restarts instead of delaying the burst further
+    assert_eq!(releases[3], now);
+    // Late frames are released at once
+    let late = now + Duration::from_secs(1);
+    assert_eq!(pacer.schedule(FRAME, late), late);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_pacer_waits_between_frames() {
+    let mut pacer = FramePacer::default();
+    let start = tokio::time::Instant::now();
+    for _ in 0..3 {
+        pacer.pace(&frame(Instant::now())).await;
+    }
+    let elapsed = start.elapsed();
+    assert!(elapsed >= FRAME * 2 && elapsed < FRAME * 3, "{elapsed:?}");
+}
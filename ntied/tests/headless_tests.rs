@@ -0,0 +1,155 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ntied::client::Client;
+use ntied::contact::ContactStatus;
+use ntied::headless::{Headless, HeadlessCommand};
+use ntied::models::MessageKind;
+use ntied_crypto::PrivateKey;
+use ntied_server::Server;
+use ntied_transport::ToAddress;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+
+async fn start_server() -> (SocketAddr, JoinHandle<()>) {
+    let server = Server::new("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let _ = server.run().await;
+    });
+    (server_addr, handle)
+}
+
+async fn start_headless(dir: &tempfile::TempDir, name: &str, server_addr: SocketAddr) -> Headless {
+    let client = Client::create(
+        &dir.path().join(name),
+        "test-pass",
+        name.to_string(),
+        server_addr,
+    )
+    .await
+    .unwrap();
+    Headless::new(Arc::new(client))
+}
+
+async fn run(headless: &Headless, input: &str) -> String {
+    let mut output = Vec::new();
+    headless.run(input.as_bytes(), &mut output).await.unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_headless_command_parse() {
+    let address = PrivateKey::generate()
+        .unwrap()
+        .public_key()
+        .to_address()
+        .unwrap();
+    assert_eq!(
+        HeadlessCommand::parse(&format!("send {address}  hello there ")).unwrap(),
+        HeadlessCommand::Send {
+            address,
+            text: "hello there".to_string(),
+        }
+    );
+    assert_eq!(
+        HeadlessCommand::parse(&format!("voice {address} /tmp/note.wav")).unwrap(),
+        HeadlessCommand::Voice {
+            address,
+            path: "/tmp/note.wav".into(),
+        }
+    );
+    assert_eq!(
+        HeadlessCommand::parse(&format!("answer {address}")).unwrap(),
+        HeadlessCommand::Answer { address }
+    );
+    assert_eq!(
+        HeadlessCommand::parse(&format!("diagnose {address}")).unwrap(),
+        HeadlessCommand::Diagnose { address }
+    );
+    assert_eq!(
+        HeadlessCommand::parse(" quit ").unwrap(),
+        HeadlessCommand::Quit
+    );
+    assert!(HeadlessCommand::parse(&format!("send {address}")).is_err());
+    assert!(HeadlessCommand::parse(&format!("voice {address}")).is_err());
+    assert!(HeadlessCommand::parse("accept nope").is_err());
+    assert!(HeadlessCommand::parse("dance").is_err());
+}
+
+#[tokio::test]
+async fn test_headless_send_message() {
+    let (server_addr, server_handle) = start_server().await;
+    let dir = tempfile::tempdir().unwrap();
+    let alice = start_headless(&dir, "Alice", server_addr).await;
+    let bob = start_headless(&dir, "Bob", server_addr).await;
+    let addr_a = alice.client().own_address();
+    let addr_b = bob.client().own_address();
+    sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(run(&alice, &format!("request {addr_b}\n")).await, "ok\n");
+    timeout(Duration::from_secs(10), async {
+        loop {
+            let contacts = bob.client().contact_manager().list_contacts().await;
+            if contacts.iter().any(|v| v.address() == addr_a) {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        run(&bob, &format!("accept {addr_a}\nquit\ncontacts\n")).await,
+        "ok\n"
+    );
+    // Alice gets the chat once the acceptance event is handled
+    timeout(Duration::from_secs(5), async {
+        while run(&alice, "contacts\n").await != format!("ok {addr_b}\n") {
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
+    let contact = alice
+        .client()
+        .contact_manager()
+        .connect_contact(addr_b)
+        .await;
+    assert_eq!(contact.status(), ContactStatus::Accepted);
+
+    let reply = alice
+        .execute(HeadlessCommand::parse(&format!("send {addr_b} hello-headless")).unwrap())
+        .await
+        .unwrap();
+    let chat_b = bob
+        .client()
+        .chat_manager()
+        .get_contact_chat(addr_a)
+        .await
+        .unwrap();
+    let message = timeout(Duration::from_secs(5), chat_b.recv_message())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(message.incoming);
+    assert_eq!(message.message_id.to_string(), reply);
+    match message.kind {
+        MessageKind::Text(text) => assert_eq!(text, "hello-headless"),
+        kind => panic!("Unexpected message kind: {kind:?}"),
+    }
+    // Unknown chats are reported without stopping the loop
+    let unknown = PrivateKey::generate()
+        .unwrap()
+        .public_key()
+        .to_address()
+        .unwrap();
+    assert_eq!(
+        run(&alice, &format!("send {unknown} hi\ncontacts\n")).await,
+        format!("error No chat with {unknown}\nok {addr_b}\n")
+    );
+    alice.shutdown().await.unwrap();
+    bob.shutdown().await.unwrap();
+    server_handle.abort();
+}
@@ -0,0 +1,37 @@
+use ntied::audio::{DEFAULT_LIMITER_THRESHOLD, soft_clip};
+
+const THRESHOLD: f32 = DEFAULT_LIMITER_THRESHOLD;
+
+#[test]
+fn test_quiet_samples_pass_unchanged() {
+    for sample in [-THRESHOLD, -0.5, 0.0, 0.25, THRESHOLD] {
+        assert_eq!(soft_clip(sample, THRESHOLD), sample);
+    }
+}
+
+#[test]
+fn test_loud_samples_are_limited() {
+    for sample in [0.9, 1.0, 1.5, 2.0, 10.0, 1000.0] {
+        let limited = soft_clip(sample, THRESHOLD);
+        assert!(
+            limited > THRESHOLD && limited <= 1.0,
+            "{sample} -> {limited}"
+        );
+        assert_eq!(soft_clip(-sample, THRESHOLD), -limited);
+    }
+}
+
+#[test]
+fn test_curve_is_monotonic_and_continuous() {
+    let step = 0.001;
+    let mut previous = soft_clip(-4.0, THRESHOLD);
+    let mut sample = -4.0 + step;
+    while sample <= 4.0 {
+        let limited = soft_clip(sample, THRESHOLD);
+        assert!(limited >= previous, "Not monotonic at {sample}");
+        // Slope never exceeds 1, so neighbouring outputs stay close
+        assert!(limited - previous <= step * 1.01, "Jump at {sample}");
+        previous = limited;
+        sample += step;
+    }
+}
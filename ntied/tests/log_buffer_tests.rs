@@ -0,0 +1,49 @@
+use ntied::logs::LogBuffer;
+use tracing::Level;
+use tracing_subscriber::prelude::*;
+
+fn messages(buffer: &LogBuffer, level: Level) -> Vec<String> {
+    buffer
+        .lines(level)
+        .into_iter()
+        .map(|line| line.message)
+        .collect()
+}
+
+#[test]
+fn test_buffer_keeps_last_lines() {
+    let buffer = LogBuffer::new(3);
+    let subscriber = tracing_subscriber::registry().with(buffer.layer());
+    tracing::subscriber::with_default(subscriber, || {
+        for i in 0..5 {
+            tracing::info!("line {}", i);
+        }
+    });
+    assert_eq!(buffer.len(), 3);
+    assert_eq!(
+        messages(&buffer, Level::TRACE),
+        vec!["line 2", "line 3", "line 4"]
+    );
+    buffer.clear();
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_buffer_filters_by_level() {
+    let buffer = LogBuffer::new(10);
+    let subscriber = tracing_subscriber::registry().with(buffer.layer());
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::error!("error");
+        tracing::warn!("warn");
+        tracing::info!(peer = "alice", "info");
+        tracing::debug!("debug");
+    });
+    assert_eq!(messages(&buffer, Level::WARN), vec!["error", "warn"]);
+    assert_eq!(
+        messages(&buffer, Level::TRACE),
+        vec!["error", "warn", "info peer=alice", "debug"]
+    );
+    let line = &buffer.lines(Level::ERROR)[0];
+    assert_eq!(line.target, module_path!());
+    assert!(line.to_string().contains("ERROR"));
+}
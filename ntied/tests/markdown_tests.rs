@@ -0,0 +1,113 @@
+use ntied::ui::markdown::{Span, SpanStyle, parse_inline};
+
+const PLAIN: SpanStyle = SpanStyle {
+    bold: false,
+    italic: false,
+    code: false,
+};
+const BOLD: SpanStyle = SpanStyle {
+    bold: true,
+    ..PLAIN
+};
+const ITALIC: SpanStyle = SpanStyle {
+    italic: true,
+    ..PLAIN
+};
+const CODE: SpanStyle = SpanStyle {
+    code: true,
+    ..PLAIN
+};
+
+fn spans(items: &[(&str, SpanStyle)]) -> Vec<Span> {
+    items
+        .iter()
+        .map(|(text, style)| Span {
+            text: text.to_string(),
+            style: *style,
+        })
+        .collect()
+}
+
+#[test]
+fn test_plain_text() {
+    assert_eq!(parse_inline("hello"), spans(&[("hello", PLAIN)]));
+    assert!(parse_inline("").is_empty());
+}
+
+#[test]
+fn test_basic_spans() {
+    assert_eq!(
+        parse_inline("a *bold* _italic_ `code`"),
+        spans(&[
+            ("a ", PLAIN),
+            ("bold", BOLD),
+            (" ", PLAIN),
+            ("italic", ITALIC),
+            (" ", PLAIN),
+            ("code", CODE),
+        ])
+    );
+}
+
+#[test]
+fn test_nested_spans() {
+    assert_eq!(
+        parse_inline("*bold _both_ `x`*"),
+        spans(&[
+            ("bold ", BOLD),
+            (
+                "both",
+                SpanStyle {
+                    italic: true,
+                    ..BOLD
+                }
+            ),
+            (" ", BOLD),
+            ("x", SpanStyle { code: true, ..BOLD }),
+        ])
+    );
+    // Code is not formatted further
+    assert_eq!(parse_inline("`*a* _b_`"), spans(&[("*a* _b_", CODE)]));
+}
+
+#[test]
+fn test_malformed_markers_are_literal() {
+    for text in [
+        "*unclosed",
+        "closed*",
+        "* spaced*",
+        "*spaced *",
+        "**",
+        "``",
+        "2 * 3 * 4",
+        "snake_case_name",
+        "`open",
+    ] {
+        assert_eq!(parse_inline(text), spans(&[(text, PLAIN)]), "{text}");
+    }
+    // Crossing spans close the outer one, the inner marker stays literal
+    assert_eq!(
+        parse_inline("*a _b* c_"),
+        spans(&[("a _b", BOLD), (" c_", PLAIN)])
+    );
+    // Markers inside code do not close spans
+    assert_eq!(
+        parse_inline("*a `b*` c*"),
+        spans(&[
+            ("a ", BOLD),
+            ("b*", SpanStyle { code: true, ..BOLD }),
+            (" c", BOLD)
+        ])
+    );
+}
+
+#[test]
+fn test_escaped_markers() {
+    assert_eq!(
+        parse_inline(r"\*not bold\* a\\b"),
+        spans(&[(r"*not bold* a\b", PLAIN)])
+    );
+    assert_eq!(parse_inline(r"\`x\`"), spans(&[("`x`", PLAIN)]));
+    // Other backslashes are kept as typed
+    assert_eq!(parse_inline(r"C:\dir"), spans(&[(r"C:\dir", PLAIN)]));
+}
@@ -0,0 +1,14 @@
+use ntied::ui::screens::format_message_line;
+
+#[test]
+fn test_format_message_line() {
+    assert_eq!(
+        format_message_line("Alice", "12:34", "hello"),
+        "Alice • 12:34: hello"
+    );
+    // Message text is copied as is
+    assert_eq!(
+        format_message_line("Bob", "09:05", "a: b\nc"),
+        "Bob • 09:05: a: b\nc"
+    );
+}
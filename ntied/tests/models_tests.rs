@@ -16,6 +16,13 @@ fn test_contact_values_roundtrip() {
         public_key: public_key.clone(),
         local_name: Some("Local Name".to_string()),
         name: "Remote Name".to_string(),
+        avatar: None,
+        archived: false,
+        pinned: false,
+        pin_order: 0,
+        verified: false,
+        muted: false,
+        auto_answer: false,
         create_time: DateTime::now(),
     };
     let columns = Contact::columns();
@@ -77,6 +84,13 @@ fn test_contact_values_roundtrip_with_nones() {
         public_key: public_key.clone(),
         local_name: None,
         name: "1".into(),
+        avatar: None,
+        archived: false,
+        pinned: false,
+        pin_order: 0,
+        verified: false,
+        muted: false,
+        auto_answer: false,
         create_time: DateTime::now(),
     };
     let columns = Contact::columns();
@@ -148,6 +162,9 @@ fn test_message_values_serialization() {
         create_time: DateTime::now(),
         receive_time: Some(DateTime::now()),
         read_time: None,
+        verified: true,
+        signature: Some(vec![1, 2, 3]),
+        reply_to: None,
     };
     let columns = Message::columns();
     // Act
@@ -193,6 +210,10 @@ fn test_message_values_serialization() {
         Value::Null => {}
         v => panic!("read_time should be Null, got {:?}", v),
     }
+    match columns.get_value(&values, "signature").unwrap() {
+        Value::Blob(b) => assert_eq!(b, &vec![1, 2, 3]),
+        v => panic!("signature should be Blob, got {:?}", v),
+    }
 }
 
 #[test]
@@ -208,6 +229,9 @@ fn test_message_values_roundtrip_variants() {
         create_time: DateTime::now(),
         receive_time: Some(DateTime::now()),
         read_time: Some(DateTime::now()),
+        verified: true,
+        signature: None,
+        reply_to: None,
     };
     let columns = Message::columns();
     let values1 = msg1.values(columns);
@@ -220,6 +244,7 @@ fn test_message_values_roundtrip_variants() {
     assert_eq!(decoded1.incoming, msg1.incoming);
     match decoded1.kind {
         MessageKind::Text(s) => assert_eq!(s, "payload-1"),
+        kind => panic!("Unexpected message kind: {kind:?}"),
     }
     assert_eq!(
         decoded1.create_time.0.timestamp_micros(),
@@ -244,6 +269,9 @@ fn test_message_values_roundtrip_variants() {
         create_time: DateTime::now(),
         receive_time: None,
         read_time: None,
+        verified: false,
+        signature: None,
+        reply_to: None,
     };
     let values2 = msg2.values(columns);
     let decoded2 =
@@ -255,6 +283,7 @@ fn test_message_values_roundtrip_variants() {
     assert_eq!(decoded2.incoming, msg2.incoming);
     match decoded2.kind {
         MessageKind::Text(s) => assert_eq!(s, "payload-2"),
+        kind => panic!("Unexpected message kind: {kind:?}"),
     }
     assert_eq!(
         decoded2.create_time.0.timestamp_micros(),
@@ -262,4 +291,72 @@ fn test_message_values_roundtrip_variants() {
     );
     assert!(decoded2.receive_time.is_none());
     assert!(decoded2.read_time.is_none());
+    assert!(!decoded2.verified);
+}
+
+#[test]
+fn test_message_clock_skew() {
+    let message_id = Uuid::now_v7();
+    let sent = DateTime::from_uuid(&message_id).expect("v7 ids carry a timestamp");
+    assert!((DateTime::now().0 - sent.0).abs() < chrono::TimeDelta::seconds(1));
+    assert!(DateTime::from_uuid(&Uuid::nil()).is_none());
+
+    let mut message = Message {
+        id: 1,
+        contact_id: 1,
+        message_id,
+        log_id: Some(1),
+        incoming: true,
+        kind: MessageKind::Text("hello".to_string()),
+        create_time: sent,
+        receive_time: Some(sent),
+        read_time: None,
+        verified: true,
+        signature: None,
+        reply_to: None,
+    };
+    assert_eq!(message.clock_skew(), Some(chrono::TimeDelta::zero()));
+    assert!(!message.has_clock_skew());
+    // Sender clock a day ahead
+    message.create_time = DateTime(sent.0 + chrono::TimeDelta::days(1));
+    assert!(message.has_clock_skew());
+    // Sender clock behind
+    message.create_time = DateTime(sent.0 - chrono::TimeDelta::hours(1));
+    assert!(message.has_clock_skew());
+    // Outgoing messages use the local clock
+    message.incoming = false;
+    assert_eq!(message.clock_skew(), None);
+}
+
+#[test]
+fn test_split_text() {
+    assert_eq!(MessageKind::split_text("hello"), vec!["hello"]);
+    assert_eq!(MessageKind::split_text(""), vec![""]);
+    // Long lines are split after a space
+    let text = "word ".repeat(MessageKind::MAX_TEXT_LEN / 2);
+    let parts = MessageKind::split_text(&text);
+    assert_eq!(parts.len(), 3);
+    assert_eq!(parts.concat(), text);
+    for part in &parts {
+        assert!(part.len() <= MessageKind::MAX_TEXT_LEN);
+        assert!(part.ends_with(' '));
+    }
+    // Text without spaces is split between characters
+    let text = "ж".repeat(MessageKind::MAX_TEXT_LEN);
+    let parts = MessageKind::split_text(&text);
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts.concat(), text);
+    assert!(parts.iter().all(|v| v.len() <= MessageKind::MAX_TEXT_LEN));
+    // Multi-byte whitespace near the cut is kept whole
+    for space in ['\u{a0}', '\u{3000}'] {
+        let text = format!(
+            "{}{space}{}",
+            "a".repeat(MessageKind::MAX_TEXT_LEN - 10),
+            "b".repeat(100)
+        );
+        let parts = MessageKind::split_text(&text);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts.concat(), text);
+        assert!(parts[0].ends_with(space));
+    }
 }
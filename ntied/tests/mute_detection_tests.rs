@@ -0,0 +1,81 @@
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+use ntied::audio::{AudioFrame, MutedSpeechDetector, rms_level};
+
+const SAMPLE_RATE: u32 = 48000;
+const FRAME_SAMPLES: usize = 960; // 20ms
+
+fn frame(amplitude: f32, timestamp: Instant) -> AudioFrame {
+    let samples = (0..FRAME_SAMPLES)
+        .map(|i| amplitude * (2.0 * PI * 220.0 * i as f32 / SAMPLE_RATE as f32).sin())
+        .collect();
+    AudioFrame {
+        samples,
+        sample_rate: SAMPLE_RATE,
+        channels: 1,
+        timestamp,
+    }
+}
+
+/// Feeds `count` consecutive frames and returns number of reports.
+fn feed(detector: &mut MutedSpeechDetector, amplitude: f32, start: Instant, count: usize) -> usize {
+    (0..count)
+        .filter(|i| {
+            detector.process(&frame(
+                amplitude,
+                start + Duration::from_millis(20 * *i as u64),
+            ))
+        })
+        .count()
+}
+
+#[test]
+fn test_rms_level() {
+    assert_eq!(rms_level(&[]), 0.0);
+    assert_eq!(rms_level(&[0.0; 16]), 0.0);
+    let level = rms_level(&frame(0.5, Instant::now()).samples);
+    assert!((level - 0.5 / 2f32.sqrt()).abs() < 0.01);
+}
+
+#[test]
+fn test_voice_while_muted_is_reported() {
+    let mut detector = MutedSpeechDetector::new();
+    // One second of speech
+    assert_eq!(feed(&mut detector, 0.3, Instant::now(), 50), 1);
+}
+
+#[test]
+fn test_silence_while_muted_is_not_reported() {
+    let mut detector = MutedSpeechDetector::new();
+    let start = Instant::now();
+    assert_eq!(feed(&mut detector, 0.0, start, 500), 0);
+    // Background noise stays below the threshold
+    assert_eq!(feed(&mut detector, 0.005, start, 500), 0);
+}
+
+#[test]
+fn test_short_click_is_not_reported() {
+    let mut detector = MutedSpeechDetector::new();
+    let start = Instant::now();
+    assert_eq!(feed(&mut detector, 0.5, start, 5), 0);
+    assert_eq!(
+        feed(&mut detector, 0.0, start + Duration::from_millis(100), 50),
+        0
+    );
+    assert_eq!(
+        feed(&mut detector, 0.5, start + Duration::from_millis(1100), 5),
+        0
+    );
+}
+
+#[test]
+fn test_report_is_throttled() {
+    let mut detector = MutedSpeechDetector::new();
+    let start = Instant::now();
+    // Five seconds of continuous speech are reported once
+    assert_eq!(feed(&mut detector, 0.3, start, 250), 1);
+    // Speech after the cooldown is reported again
+    let later = start + MutedSpeechDetector::DEFAULT_COOLDOWN + Duration::from_secs(1);
+    assert_eq!(feed(&mut detector, 0.3, later, 50), 1);
+}
@@ -0,0 +1,41 @@
+use ntied::packet::{CallEndPacket, CallPacket, Packet, UnknownCallPacket};
+use uuid::Uuid;
+
+fn end_packet_bytes() -> Vec<u8> {
+    let packet = Packet::Call(CallPacket::End(CallEndPacket {
+        call_id: Uuid::now_v7(),
+    }));
+    bincode::serialize(&packet).unwrap()
+}
+
+#[test]
+fn test_unknown_call_packet_is_decoded() {
+    let mut bytes = end_packet_bytes();
+    bytes[4..8].copy_from_slice(&42u32.to_le_bytes());
+    match Packet::decode(&bytes).unwrap() {
+        Packet::Call(CallPacket::Unknown(packet)) => assert_eq!(
+            packet,
+            UnknownCallPacket {
+                tag: 42,
+                data: bytes[8..].to_vec(),
+            }
+        ),
+        packet => panic!("Unexpected packet: {:?}", packet),
+    }
+}
+
+#[test]
+fn test_known_call_packet_is_decoded() {
+    let bytes = end_packet_bytes();
+    assert!(matches!(
+        Packet::decode(&bytes).unwrap(),
+        Packet::Call(CallPacket::End(_))
+    ));
+}
+
+#[test]
+fn test_unknown_packet_kind_is_rejected() {
+    let mut bytes = end_packet_bytes();
+    bytes[0..4].copy_from_slice(&7u32.to_le_bytes());
+    assert!(Packet::decode(&bytes).is_err());
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "audio")]
+
 use cpal::traits::HostTrait;
 use ntied::audio::{AudioFrame, PlaybackStream};
 use std::time::{Duration, Instant};
@@ -0,0 +1,214 @@
+use ntied::chat::{PendingSession, RatchetSession};
+use ntied_crypto::{PrivateKey, SignatureScheme};
+
+struct Peers {
+    alice: RatchetSession,
+    bob: RatchetSession,
+}
+
+fn establish() -> (PrivateKey, PrivateKey, Peers) {
+    establish_with_scheme(SignatureScheme::P256)
+}
+
+fn establish_with_scheme(scheme: SignatureScheme) -> (PrivateKey, PrivateKey, Peers) {
+    establish_with_schemes(scheme, scheme)
+}
+
+fn establish_with_schemes(
+    alice_scheme: SignatureScheme,
+    bob_scheme: SignatureScheme,
+) -> (PrivateKey, PrivateKey, Peers) {
+    let alice_key = PrivateKey::generate_with_scheme(alice_scheme).unwrap();
+    let bob_key = PrivateKey::generate_with_scheme(bob_scheme).unwrap();
+    let pending = PendingSession::new(&alice_key);
+    let bob =
+        RatchetSession::accept(&bob_key, &alice_key.public_key(), &pending.init_packet()).unwrap();
+    let alice = pending
+        .complete(
+            &alice_key,
+            &bob_key.public_key(),
+            &bob.accept_packet(&bob_key),
+        )
+        .unwrap();
+    (alice_key, bob_key, Peers { alice, bob })
+}
+
+#[test]
+fn test_round_trip_with_ratchet_steps() {
+    let (_, _, mut peers) = establish();
+    assert_eq!(peers.alice.id(), peers.bob.id());
+    for round in 0..3u8 {
+        let (header, ciphertext) = peers.alice.encrypt(&[round, 1]).unwrap();
+        assert_eq!(peers.bob.decrypt(&header, &ciphertext).unwrap(), [round, 1]);
+        let (header, ciphertext) = peers.bob.encrypt(&[round, 2]).unwrap();
+        assert_eq!(
+            peers.alice.decrypt(&header, &ciphertext).unwrap(),
+            [round, 2]
+        );
+    }
+}
+
+#[test]
+fn test_ed25519_identities() {
+    let (_, _, mut peers) = establish_with_scheme(SignatureScheme::Ed25519);
+    for round in 0..3u8 {
+        let (header, ciphertext) = peers.alice.encrypt(&[round, 1]).unwrap();
+        assert_eq!(peers.bob.decrypt(&header, &ciphertext).unwrap(), [round, 1]);
+        let (header, ciphertext) = peers.bob.encrypt(&[round, 2]).unwrap();
+        assert_eq!(
+            peers.alice.decrypt(&header, &ciphertext).unwrap(),
+            [round, 2]
+        );
+    }
+    let mut bob = RatchetSession::from_bytes(&peers.bob.to_bytes().unwrap()).unwrap();
+    let (header, ciphertext) = peers.alice.encrypt(b"hello").unwrap();
+    assert_eq!(bob.decrypt(&header, &ciphertext).unwrap(), b"hello");
+}
+
+#[test]
+fn test_identities_of_different_schemes() {
+    let alice_key = PrivateKey::generate_with_scheme(SignatureScheme::P256).unwrap();
+    let bob_key = PrivateKey::generate_with_scheme(SignatureScheme::Ed25519).unwrap();
+    assert!(!RatchetSession::is_supported(
+        &alice_key,
+        &bob_key.public_key()
+    ));
+    let pending = PendingSession::new(&alice_key);
+    assert!(
+        RatchetSession::accept(&bob_key, &alice_key.public_key(), &pending.init_packet()).is_err()
+    );
+}
+
+#[test]
+fn test_out_of_order_delivery() {
+    let (_, _, mut peers) = establish();
+    let first = peers.bob.encrypt(b"first").unwrap();
+    let second = peers.bob.encrypt(b"second").unwrap();
+    // Alice replies before receiving anything, the next chain of Bob
+    // must still open after the skipped packets
+    let reply = peers.alice.encrypt(b"reply").unwrap();
+    peers.bob.decrypt(&reply.0, &reply.1).unwrap();
+    let third = peers.bob.encrypt(b"third").unwrap();
+    assert_eq!(peers.alice.decrypt(&third.0, &third.1).unwrap(), b"third");
+    assert_eq!(
+        peers.alice.decrypt(&second.0, &second.1).unwrap(),
+        b"second"
+    );
+    assert_eq!(peers.alice.decrypt(&first.0, &first.1).unwrap(), b"first");
+    // Replayed packets do not open twice
+    assert!(peers.alice.decrypt(&first.0, &first.1).is_err());
+}
+
+#[test]
+fn test_later_state_does_not_open_earlier_packets() {
+    let (_, _, mut peers) = establish();
+    let (header, ciphertext) = peers.alice.encrypt(b"secret").unwrap();
+    peers.bob.decrypt(&header, &ciphertext).unwrap();
+    assert!(peers.bob.clone().decrypt(&header, &ciphertext).is_err());
+    let mut restored = RatchetSession::from_bytes(&peers.bob.to_bytes().unwrap()).unwrap();
+    assert!(restored.decrypt(&header, &ciphertext).is_err());
+}
+
+#[test]
+fn test_leaked_state_recovers_after_round_trip() {
+    let (_, _, mut peers) = establish();
+    let (header, ciphertext) = peers.alice.encrypt(b"before").unwrap();
+    peers.bob.decrypt(&header, &ciphertext).unwrap();
+    let mut leaked = peers.bob.clone();
+    // The leaked state follows the conversation until Bob picks a new
+    // ratchet key unknown to it
+    let (header, ciphertext) = peers.bob.encrypt(b"reply").unwrap();
+    peers.alice.decrypt(&header, &ciphertext).unwrap();
+    let (header, ciphertext) = peers.alice.encrypt(b"leaked").unwrap();
+    assert_eq!(leaked.decrypt(&header, &ciphertext).unwrap(), b"leaked");
+    peers.bob.decrypt(&header, &ciphertext).unwrap();
+    let (header, ciphertext) = peers.bob.encrypt(b"reply").unwrap();
+    peers.alice.decrypt(&header, &ciphertext).unwrap();
+    let (header, ciphertext) = peers.alice.encrypt(b"after").unwrap();
+    assert!(leaked.decrypt(&header, &ciphertext).is_err());
+    assert_eq!(peers.bob.decrypt(&header, &ciphertext).unwrap(), b"after");
+}
+
+#[test]
+fn test_restored_session_continues() {
+    let (_, _, mut peers) = establish();
+    let mut bob = RatchetSession::from_bytes(&peers.bob.to_bytes().unwrap()).unwrap();
+    let (header, ciphertext) = peers.alice.encrypt(b"hello").unwrap();
+    assert_eq!(bob.decrypt(&header, &ciphertext).unwrap(), b"hello");
+}
+
+#[test]
+fn test_tampered_packet_keeps_session() {
+    let (_, _, mut peers) = establish();
+    let (mut header, ciphertext) = peers.alice.encrypt(b"hello").unwrap();
+    header.count += 1;
+    assert!(peers.bob.decrypt(&header, &ciphertext).is_err());
+    header.count -= 1;
+    let mut tampered = ciphertext.clone();
+    tampered[0] ^= 1;
+    assert!(peers.bob.decrypt(&header, &tampered).is_err());
+    assert_eq!(peers.bob.decrypt(&header, &ciphertext).unwrap(), b"hello");
+}
+
+#[test]
+fn test_wrong_identity_does_not_open() {
+    let (alice_key, _, mut peers) = establish();
+    let mallory_key = PrivateKey::generate().unwrap();
+    let pending = PendingSession::new(&alice_key);
+    let mut mallory = RatchetSession::accept(
+        &mallory_key,
+        &alice_key.public_key(),
+        &pending.init_packet(),
+    )
+    .unwrap();
+    let (header, ciphertext) = peers.alice.encrypt(b"hello").unwrap();
+    assert!(mallory.decrypt(&header, &ciphertext).is_err());
+}
+
+#[test]
+fn test_forged_init_is_rejected() {
+    let alice_key = PrivateKey::generate().unwrap();
+    let bob_key = PrivateKey::generate().unwrap();
+    let mallory_key = PrivateKey::generate().unwrap();
+    let pending = PendingSession::new(&mallory_key);
+    assert!(
+        RatchetSession::accept(&bob_key, &alice_key.public_key(), &pending.init_packet()).is_err()
+    );
+}
+
+#[test]
+fn test_forged_accept_is_rejected() {
+    let alice_key = PrivateKey::generate().unwrap();
+    let bob_key = PrivateKey::generate().unwrap();
+    let mallory_key = PrivateKey::generate().unwrap();
+    let pending = PendingSession::new(&alice_key);
+    // Mallory answers the init of Alice in the name of Bob
+    let mallory = RatchetSession::accept(
+        &mallory_key,
+        &alice_key.public_key(),
+        &pending.init_packet(),
+    )
+    .unwrap();
+    let forged = mallory.accept_packet(&mallory_key);
+    assert!(
+        pending
+            .complete(&alice_key, &bob_key.public_key(), &forged)
+            .is_err()
+    );
+    // The ratchet key of a genuine accept can not be replaced
+    let bob =
+        RatchetSession::accept(&bob_key, &alice_key.public_key(), &pending.init_packet()).unwrap();
+    let mut tampered = bob.accept_packet(&bob_key);
+    tampered.ratchet_key = forged.ratchet_key;
+    assert!(
+        pending
+            .complete(&alice_key, &bob_key.public_key(), &tampered)
+            .is_err()
+    );
+    let genuine = bob.accept_packet(&bob_key);
+    assert!(
+        pending
+            .complete(&alice_key, &bob_key.public_key(), &genuine)
+            .is_ok()
+    );
+}
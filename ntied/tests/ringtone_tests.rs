@@ -1,4 +1,4 @@
-use ntied::audio::RingtonePlayer;
+use ntied::audio::{RingtonePlayer, RingtoneSignal};
 use std::time::Duration;
 
 #[tokio::test]
@@ -109,3 +109,35 @@ async fn test_ringtone_player_async_usage() {
         assert!(!p.is_playing(), "Player should not be playing after stop");
     }
 }
+
+#[test]
+fn test_ringtone_signal_applies_volume() {
+    let samples = |volume: f32| {
+        let mut signal = RingtoneSignal::new(48000);
+        let mut data = vec![0.0; 9600];
+        signal.fill(&mut data, 2, volume);
+        data
+    };
+    let normal = samples(1.0);
+    let loud = samples(2.5);
+    assert!(normal.iter().any(|v| v.abs() > 0.01));
+    for (normal, loud) in normal.iter().zip(&loud) {
+        assert!((normal * 2.5 - loud).abs() < 1e-5);
+    }
+    assert!(loud.iter().all(|v| v.abs() <= 1.0));
+    assert!(samples(0.0).iter().all(|v| *v == 0.0));
+    // Both channels of a frame get the same value
+    assert!(normal.chunks(2).all(|v| v[0] == v[1]));
+}
+
+#[test]
+fn test_ringtone_volume_is_clamped() {
+    let player = RingtonePlayer::new();
+    assert_eq!(player.volume(), RingtonePlayer::DEFAULT_VOLUME);
+    player.set_volume(2.0);
+    assert_eq!(player.volume(), 2.0);
+    player.set_volume(10.0);
+    assert_eq!(player.volume(), RingtonePlayer::MAX_VOLUME);
+    player.set_volume(-1.0);
+    assert_eq!(player.volume(), 0.0);
+}
@@ -0,0 +1,52 @@
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use ntied::audio::{ExclusiveModeUnavailable, ShareMode, open_with_fallback};
+
+#[tokio::test]
+async fn test_mode_is_passed_to_open() {
+    for mode in [ShareMode::Shared, ShareMode::Exclusive] {
+        let requested = Mutex::new(Vec::new());
+        let opened = open_with_fallback(mode, |mode| {
+            requested.lock().unwrap().push(mode);
+            async move { Ok(mode) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(opened, mode);
+        assert_eq!(*requested.lock().unwrap(), vec![mode]);
+    }
+}
+
+#[tokio::test]
+async fn test_exclusive_failure_falls_back_to_shared() {
+    let requested = Mutex::new(Vec::new());
+    let opened = open_with_fallback(ShareMode::Exclusive, |mode| {
+        requested.lock().unwrap().push(mode);
+        async move {
+            match mode {
+                ShareMode::Exclusive => Err(ExclusiveModeUnavailable.into()),
+                ShareMode::Shared => Ok(mode),
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(opened, ShareMode::Shared);
+    assert_eq!(
+        *requested.lock().unwrap(),
+        vec![ShareMode::Exclusive, ShareMode::Shared]
+    );
+}
+
+#[tokio::test]
+async fn test_shared_failure_is_returned() {
+    let requested = Mutex::new(Vec::new());
+    let result: anyhow::Result<()> = open_with_fallback(ShareMode::Shared, |mode| {
+        requested.lock().unwrap().push(mode);
+        async { Err(anyhow!("Device is busy")) }
+    })
+    .await;
+    assert_eq!(result.unwrap_err().to_string(), "Device is busy");
+    assert_eq!(*requested.lock().unwrap(), vec![ShareMode::Shared]);
+}
@@ -0,0 +1,471 @@
+use std::sync::Arc;
+
+use ntied::chat::RetentionOptions;
+use ntied::models::{Contact, DateTime, Message, MessageKind};
+use ntied::storage::{ConfigStore, MemoryStore, MessageStore, SqliteStore, Storage};
+use ntied_crypto::PrivateKey;
+use ntied_transport::ToAddress;
+use tokio::sync::Mutex as TokioMutex;
+use uuid::Uuid;
+
+async fn sqlite_store() -> (tempfile::TempDir, Arc<SqliteStore>) {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let storage = Storage::create(dir.path(), "test-pass")
+        .await
+        .expect("failed to create storage");
+    let store = SqliteStore::new(Arc::new(TokioMutex::new(storage)));
+    store
+        .create_tables()
+        .await
+        .expect("failed to create tables");
+    (dir, Arc::new(store))
+}
+
+fn new_contact(name: &str) -> Contact {
+    let public_key = PrivateKey::generate().unwrap().public_key();
+    Contact {
+        id: 0,
+        address: public_key.to_address().unwrap(),
+        public_key,
+        local_name: None,
+        name: name.to_string(),
+        avatar: None,
+        archived: false,
+        pinned: false,
+        pin_order: 0,
+        verified: false,
+        muted: false,
+        auto_answer: false,
+        create_time: DateTime::now(),
+    }
+}
+
+fn new_message(contact_id: i64, log_id: Option<u64>, incoming: bool, text: &str) -> Message {
+    Message {
+        id: 0,
+        contact_id,
+        message_id: Uuid::now_v7(),
+        log_id,
+        incoming,
+        kind: MessageKind::Text(text.to_string()),
+        create_time: DateTime::now(),
+        receive_time: None,
+        read_time: None,
+        verified: true,
+        signature: None,
+        reply_to: None,
+    }
+}
+
+fn texts(messages: &[Message]) -> Vec<String> {
+    messages.iter().map(|v| v.kind.content()).collect()
+}
+
+async fn check_config(store: Arc<dyn ConfigStore>) {
+    assert_eq!(store.get_config("missing").await.unwrap(), None);
+    store.set_config("key", "value1".into()).await.unwrap();
+    assert_eq!(
+        store.get_config("key").await.unwrap().as_deref(),
+        Some("value1")
+    );
+    store.set_config("key", "value2".into()).await.unwrap();
+    assert_eq!(
+        store.get_config("key").await.unwrap().as_deref(),
+        Some("value2")
+    );
+}
+
+async fn check_contacts(store: Arc<dyn MessageStore>) {
+    assert!(store.get_contacts().await.unwrap().is_empty());
+    let alice = store.create_contact(new_contact("Alice")).await.unwrap();
+    let mut bob = new_contact("Bob");
+    bob.avatar = Some(vec![1, 2, 3]);
+    let bob = store.create_contact(bob).await.unwrap();
+    assert_ne!(alice.id, 0);
+    assert_ne!(alice.id, bob.id);
+    // Addresses are unique.
+    let mut duplicate = new_contact("Alice 2");
+    duplicate.address = alice.address;
+    assert!(store.create_contact(duplicate).await.is_err());
+    let contacts = store.get_contacts().await.unwrap();
+    let names: Vec<_> = contacts.iter().map(|v| v.name.clone()).collect();
+    assert_eq!(names, vec!["Alice", "Bob"]);
+    assert_eq!(contacts[0].avatar, None);
+    assert_eq!(contacts[1].avatar, Some(vec![1, 2, 3]));
+    // Updates keep the id and the address.
+    let mut renamed = bob.clone();
+    renamed.name = "Robert".into();
+    renamed.local_name = Some("Bobby".into());
+    renamed.avatar = None;
+    renamed.pinned = true;
+    renamed.pin_order = 3;
+    store.update_contact(renamed).await.unwrap();
+    let contacts = store.get_contacts().await.unwrap();
+    assert_eq!(contacts[1].id, bob.id);
+    assert_eq!(contacts[1].address, bob.address);
+    assert_eq!(contacts[1].name, "Robert");
+    assert_eq!(contacts[1].local_name.as_deref(), Some("Bobby"));
+    assert_eq!(contacts[1].avatar, None);
+    assert!(contacts[1].pinned);
+    assert_eq!(contacts[1].pin_order, 3);
+    // Key rotation moves the contact to a new address.
+    let mut rotated = contacts[1].clone();
+    rotated.public_key = PrivateKey::generate().unwrap().public_key();
+    rotated.address = rotated.public_key.to_address().unwrap();
+    store.update_contact(rotated.clone()).await.unwrap();
+    let contacts = store.get_contacts().await.unwrap();
+    assert_eq!(contacts[1].id, bob.id);
+    assert_eq!(contacts[1].address, rotated.address);
+    assert_eq!(
+        contacts[1].public_key.to_bytes().unwrap(),
+        rotated.public_key.to_bytes().unwrap()
+    );
+    let mut duplicate = contacts[1].clone();
+    duplicate.address = alice.address;
+    assert!(store.update_contact(duplicate).await.is_err());
+    // Deleting a contact removes its messages.
+    let message = store
+        .create_message(new_message(alice.id, Some(1), true, "hi"))
+        .await
+        .unwrap();
+    store.delete_contact(alice.id).await.unwrap();
+    assert!(store.delete_contact(alice.id).await.is_err());
+    assert!(
+        store
+            .get_message(message.message_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(store.get_contacts().await.unwrap().len(), 1);
+}
+
+async fn check_messages(store: Arc<dyn MessageStore>) {
+    let contact = store.create_contact(new_contact("Alice")).await.unwrap();
+    let message = store
+        .create_message(new_message(contact.id, None, false, "hello"))
+        .await
+        .unwrap();
+    assert_ne!(message.id, 0);
+    // Message ids are unique.
+    let mut duplicate = new_message(contact.id, None, false, "again");
+    duplicate.message_id = message.message_id;
+    assert!(store.create_message(duplicate).await.is_err());
+    assert_eq!(
+        store.get_pending_message_ids(contact.id).await.unwrap(),
+        vec![message.message_id]
+    );
+    assert_eq!(store.get_head_log_id(contact.id).await.unwrap(), None);
+    // Confirm the message.
+    let mut confirmed = message.clone();
+    confirmed.log_id = Some(1);
+    confirmed.receive_time = Some(DateTime::now());
+    store.update_message(confirmed).await.unwrap();
+    let stored = store
+        .get_message(message.message_id)
+        .await
+        .unwrap()
+        .expect("message not found");
+    assert_eq!(stored.log_id, Some(1));
+    assert!(stored.receive_time.is_some());
+    assert!(
+        store
+            .get_pending_message_ids(contact.id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(store.get_head_log_id(contact.id).await.unwrap(), Some(1));
+    // Updating an unknown message fails.
+    let mut unknown = new_message(contact.id, Some(2), false, "unknown");
+    unknown.id = 1000;
+    assert!(store.update_message(unknown).await.is_err());
+    // Verification result of incoming messages is kept.
+    let mut unsigned = new_message(contact.id, Some(2), true, "unsigned");
+    unsigned.verified = false;
+    let unsigned = store.create_message(unsigned).await.unwrap();
+    let stored = store
+        .get_message(unsigned.message_id)
+        .await
+        .unwrap()
+        .expect("message not found");
+    assert!(!stored.verified);
+    // Flushed messages stay readable.
+    store.flush().await.unwrap();
+    assert!(
+        store
+            .get_message(unsigned.message_id)
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+async fn check_history(store: Arc<dyn MessageStore>) {
+    let alice = store.create_contact(new_contact("Alice")).await.unwrap();
+    let bob = store.create_contact(new_contact("Bob")).await.unwrap();
+    // Pending message created first is still shown last.
+    store
+        .create_message(new_message(alice.id, None, false, "pending"))
+        .await
+        .unwrap();
+    for (log_id, text) in [(2, "second"), (1, "first"), (3, "third")] {
+        store
+            .create_message(new_message(alice.id, Some(log_id), true, text))
+            .await
+            .unwrap();
+    }
+    store
+        .create_message(new_message(bob.id, Some(1), true, "other"))
+        .await
+        .unwrap();
+    let history = store.load_history(alice.id, 10).await.unwrap();
+    assert_eq!(texts(&history), vec!["first", "second", "third", "pending"]);
+    let history = store.load_history(alice.id, 2).await.unwrap();
+    assert_eq!(texts(&history), vec!["third", "pending"]);
+    let history = store.load_history(bob.id, 10).await.unwrap();
+    assert_eq!(texts(&history), vec!["other"]);
+    assert_eq!(store.get_head_log_id(alice.id).await.unwrap(), Some(3));
+}
+
+async fn check_history_clock_skew(store: Arc<dyn MessageStore>) {
+    let contact = store.create_contact(new_contact("Alice")).await.unwrap();
+    let now = DateTime::now();
+    // The sender clock is a year ahead for the first received message
+    for (log_id, text, days) in [(1, "first", 365), (2, "second", 0), (3, "third", -1)] {
+        let mut message = new_message(contact.id, Some(log_id), true, text);
+        message.create_time = DateTime(now.0 + chrono::Duration::days(days));
+        message.receive_time = Some(now);
+        store.create_message(message).await.unwrap();
+    }
+    let history = store.load_history(contact.id, 10).await.unwrap();
+    assert_eq!(texts(&history), vec!["first", "second", "third"]);
+    assert!(history[0].has_clock_skew());
+    assert!(!history[1].has_clock_skew());
+}
+
+async fn check_history_pages(store: Arc<dyn MessageStore>) {
+    let contact = store.create_contact(new_contact("Alice")).await.unwrap();
+    let mut expected = Vec::new();
+    // 490 confirmed messages followed by 10 pending ones.
+    for i in 0..500u64 {
+        let log_id = if i < 490 { Some(i + 1) } else { None };
+        let message = store
+            .create_message(new_message(contact.id, log_id, i % 2 == 0, &i.to_string()))
+            .await
+            .unwrap();
+        expected.push(message.id);
+    }
+    let mut loaded = Vec::new();
+    let mut before_id = None;
+    loop {
+        let page = store
+            .load_history_before(contact.id, before_id, 37)
+            .await
+            .unwrap();
+        assert!(page.messages.len() <= 37);
+        assert!(!page.messages.is_empty());
+        let ids: Vec<_> = page.messages.iter().map(|v| v.id).collect();
+        // Each page must be contiguous and end right before the previous one.
+        let end = expected.len() - loaded.len();
+        assert_eq!(ids, expected[end - ids.len()..end]);
+        loaded.splice(0..0, ids);
+        if !page.has_more {
+            break;
+        }
+        before_id = loaded.first().copied();
+    }
+    assert_eq!(loaded, expected);
+    // Unknown cursor is an error.
+    assert!(
+        store
+            .load_history_before(contact.id, Some(100_000), 10)
+            .await
+            .is_err()
+    );
+}
+
+async fn check_replies(store: Arc<dyn MessageStore>) {
+    let contact = store.create_contact(new_contact("Alice")).await.unwrap();
+    let original = store
+        .create_message(new_message(contact.id, Some(1), true, "question"))
+        .await
+        .unwrap();
+    let mut reply = new_message(contact.id, None, false, "answer");
+    reply.reply_to = Some(original.id);
+    let reply = store.create_message(reply).await.unwrap();
+    let stored = store
+        .get_message(reply.message_id)
+        .await
+        .unwrap()
+        .expect("message not found");
+    assert_eq!(stored.reply_to, Some(original.id));
+    let history = store.load_history(contact.id, 10).await.unwrap();
+    assert_eq!(history[1].reply_to, Some(original.id));
+    assert_eq!(history[0].reply_to, None);
+    // The referenced message is found by its local id.
+    let quoted = store
+        .get_message_by_id(stored.reply_to.unwrap())
+        .await
+        .unwrap()
+        .expect("replied message not found");
+    assert_eq!(quoted.message_id, original.message_id);
+    assert_eq!(quoted.kind.content(), "question");
+    assert!(store.get_message_by_id(1000).await.unwrap().is_none());
+}
+
+async fn check_retention(store: Arc<dyn MessageStore>) {
+    let alice = store.create_contact(new_contact("Alice")).await.unwrap();
+    let bob = store.create_contact(new_contact("Bob")).await.unwrap();
+    let now = DateTime::now();
+    let days_ago = |days| DateTime(now.0 - chrono::Duration::days(days));
+    let create = |contact_id, log_id, text, age| {
+        let mut message = new_message(contact_id, log_id, true, text);
+        message.create_time = days_ago(age);
+        store.create_message(message)
+    };
+    create(alice.id, Some(1), "old 1", 40).await.unwrap();
+    create(alice.id, Some(2), "old 2", 31).await.unwrap();
+    create(alice.id, Some(3), "new", 29).await.unwrap();
+    create(bob.id, Some(1), "head", 40).await.unwrap();
+    let mut pending = new_message(bob.id, None, false, "pending");
+    pending.create_time = days_ago(40);
+    store.create_message(pending).await.unwrap();
+
+    let options = RetentionOptions {
+        max_age_days: Some(30),
+    };
+    let time = options.expire_before(now).unwrap();
+    assert_eq!(store.delete_messages_before(time).await.unwrap(), 2);
+    let history = store.load_history(alice.id, 10).await.unwrap();
+    assert_eq!(texts(&history), vec!["new"]);
+    // The head of the log and pending messages are kept
+    let history = store.load_history(bob.id, 10).await.unwrap();
+    assert_eq!(texts(&history), vec!["head", "pending"]);
+    assert_eq!(store.get_head_log_id(bob.id).await.unwrap(), Some(1));
+    assert_eq!(store.delete_messages_before(time).await.unwrap(), 0);
+    assert!(RetentionOptions::default().expire_before(now).is_none());
+}
+
+async fn check_chat_sessions(store: Arc<dyn MessageStore>) {
+    let alice = store.create_contact(new_contact("Alice")).await.unwrap();
+    let bob = store.create_contact(new_contact("Bob")).await.unwrap();
+    assert_eq!(store.get_chat_session(alice.id).await.unwrap(), None);
+    store
+        .set_chat_session(alice.id, Some(vec![1, 2]))
+        .await
+        .unwrap();
+    store
+        .set_chat_session(alice.id, Some(vec![3]))
+        .await
+        .unwrap();
+    store.set_chat_session(bob.id, Some(vec![4])).await.unwrap();
+    assert_eq!(
+        store.get_chat_session(alice.id).await.unwrap(),
+        Some(vec![3])
+    );
+    store.set_chat_session(alice.id, None).await.unwrap();
+    assert_eq!(store.get_chat_session(alice.id).await.unwrap(), None);
+    // Sessions are deleted with the contact
+    store.delete_contact(bob.id).await.unwrap();
+    assert_eq!(store.get_chat_session(bob.id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_memory_config() {
+    check_config(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_config() {
+    let (_dir, store) = sqlite_store().await;
+    check_config(store).await;
+}
+
+#[tokio::test]
+async fn test_memory_contacts() {
+    check_contacts(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_contacts() {
+    let (_dir, store) = sqlite_store().await;
+    check_contacts(store).await;
+}
+
+#[tokio::test]
+async fn test_memory_messages() {
+    check_messages(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_messages() {
+    let (_dir, store) = sqlite_store().await;
+    check_messages(store).await;
+}
+
+#[tokio::test]
+async fn test_memory_history() {
+    check_history(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_history() {
+    let (_dir, store) = sqlite_store().await;
+    check_history(store).await;
+}
+
+#[tokio::test]
+async fn test_memory_history_clock_skew() {
+    check_history_clock_skew(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_history_clock_skew() {
+    let (_dir, store) = sqlite_store().await;
+    check_history_clock_skew(store).await;
+}
+
+#[tokio::test]
+async fn test_memory_history_pages() {
+    check_history_pages(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_history_pages() {
+    let (_dir, store) = sqlite_store().await;
+    check_history_pages(store).await;
+}
+
+#[tokio::test]
+async fn test_memory_replies() {
+    check_replies(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_replies() {
+    let (_dir, store) = sqlite_store().await;
+    check_replies(store).await;
+}
+
+#[tokio::test]
+async fn test_memory_retention() {
+    check_retention(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_retention() {
+    let (_dir, store) = sqlite_store().await;
+    check_retention(store).await;
+}
+
+#[tokio::test]
+async fn test_memory_chat_sessions() {
+    check_chat_sessions(Arc::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_chat_sessions() {
+    let (_dir, store) = sqlite_store().await;
+    check_chat_sessions(store).await;
+}
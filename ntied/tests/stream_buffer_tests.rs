@@ -0,0 +1,36 @@
+use ntied::audio::StreamBuffer;
+
+const FRAME_SAMPLES: usize = 960; // 20ms at 48kHz
+
+#[test]
+fn test_pushed_frames_are_buffered() {
+    let buffer = StreamBuffer::new(48000, 1);
+    assert_eq!(buffer.buffered_frames(), 0);
+    assert_eq!(buffer.estimated_latency_ms(), 0.0);
+    for count in 1..=5 {
+        buffer.push_frame(FRAME_SAMPLES);
+        assert_eq!(buffer.buffered_frames(), count);
+    }
+    assert_eq!(buffer.estimated_latency_ms(), 100.0);
+    buffer.pop_frame(FRAME_SAMPLES);
+    assert_eq!(buffer.buffered_frames(), 4);
+    assert_eq!(buffer.estimated_latency_ms(), 80.0);
+}
+
+#[test]
+fn test_unqueued_samples_count_towards_latency() {
+    let buffer = StreamBuffer::new(48000, 2);
+    buffer.push_frame(FRAME_SAMPLES * 2);
+    buffer.push_frame(FRAME_SAMPLES * 2);
+    buffer.unqueue_frame();
+    assert_eq!(buffer.buffered_frames(), 1);
+    assert_eq!(buffer.estimated_latency_ms(), 40.0);
+    buffer.consume_samples(FRAME_SAMPLES * 2);
+    assert_eq!(buffer.estimated_latency_ms(), 20.0);
+    // Counters never go below zero
+    buffer.consume_samples(FRAME_SAMPLES * 10);
+    buffer.pop_frame(FRAME_SAMPLES);
+    buffer.pop_frame(FRAME_SAMPLES);
+    assert_eq!(buffer.buffered_frames(), 0);
+    assert_eq!(buffer.buffered_samples(), 0);
+}
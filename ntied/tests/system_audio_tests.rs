@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ntied::audio::{
+    AudioConfig, AudioFrame, CodecParams, CodecType, Encoder, LoopbackSource, SystemAudioMixer,
+    SystemAudioMode, VadParams, forward_system_audio, is_loopback_device_name,
+};
+use tokio::time::timeout;
+
+/// Loopback source playing prepared frames.
+struct MockLoopback {
+    config: AudioConfig,
+    frames: VecDeque<AudioFrame>,
+}
+
+#[async_trait]
+impl LoopbackSource for MockLoopback {
+    fn config(&self) -> AudioConfig {
+        self.config
+    }
+
+    async fn recv(&mut self) -> Option<AudioFrame> {
+        self.frames.pop_front()
+    }
+}
+
+fn audio_frame(samples: Vec<f32>, config: AudioConfig) -> AudioFrame {
+    AudioFrame {
+        samples,
+        sample_rate: config.sample_rate,
+        channels: config.channels,
+        timestamp: Instant::now(),
+    }
+}
+
+/// 20 ms of a 440 Hz tone.
+fn tone(config: AudioConfig) -> Vec<f32> {
+    let count = config.sample_rate as usize / 50;
+    (0..count)
+        .flat_map(|i| {
+            let t = i as f32 / config.sample_rate as f32;
+            let sample = (2.0 * std::f32::consts::PI * 440.0 * t).sin() * 0.5;
+            std::iter::repeat_n(sample, config.channels as usize)
+        })
+        .collect()
+}
+
+#[test]
+fn test_loopback_device_names() {
+    assert!(is_loopback_device_name(
+        "Monitor of Built-in Audio Analog Stereo"
+    ));
+    assert!(is_loopback_device_name("Stereo Mix (Realtek Audio)"));
+    assert!(!is_loopback_device_name("Built-in Microphone"));
+}
+
+#[test]
+fn test_mixer_mixes_or_replaces_microphone() {
+    let config = AudioConfig::new(48000, 1);
+    for (mode, expected) in [
+        (SystemAudioMode::Mix, 0.5),
+        (SystemAudioMode::Replace, 0.25),
+    ] {
+        let mut mixer = SystemAudioMixer::new(mode, config, config).unwrap();
+        mixer.push(&audio_frame(vec![0.25; 960], config));
+        let mut frame = audio_frame(vec![0.25; 960], config);
+        mixer.mix(&mut frame);
+        assert!(frame.samples.iter().all(|v| *v == expected), "{mode:?}");
+        // Nothing is queued, the microphone is left or silenced
+        let mut frame = audio_frame(vec![0.25; 960], config);
+        mixer.mix(&mut frame);
+        let expected = if mode == SystemAudioMode::Mix {
+            0.25
+        } else {
+            0.0
+        };
+        assert!(frame.samples.iter().all(|v| *v == expected), "{mode:?}");
+    }
+}
+
+#[test]
+fn test_mixer_converts_system_audio_format() {
+    let source = AudioConfig::new(44100, 2);
+    let target = AudioConfig::new(48000, 1);
+    let mut mixer = SystemAudioMixer::new(SystemAudioMode::Replace, source, target).unwrap();
+    for _ in 0..5 {
+        mixer.push(&audio_frame(tone(source), source));
+    }
+    let mut frame = audio_frame(vec![0.0; 960], target);
+    mixer.mix(&mut frame);
+    assert_eq!(frame.samples.len(), 960);
+    assert!(frame.samples.iter().any(|v| v.abs() > 0.1));
+}
+
+/// Packets encoded from silent microphone frames with the system audio of
+/// `source` mixed in.
+async fn encode_with_system_audio(source: Option<MockLoopback>) -> usize {
+    let config = AudioConfig::new(48000, 1);
+    let encoder = Encoder::with_vad(
+        config,
+        CodecType::ADPCM,
+        CodecParams::music(),
+        Some(VadParams {
+            hangover_frames: 0,
+            ..VadParams::default()
+        }),
+    );
+    let mixer = match source {
+        Some(source) => {
+            let mixer = SystemAudioMixer::new(SystemAudioMode::Mix, source.config, config).unwrap();
+            let mixer = Arc::new(Mutex::new(mixer));
+            // The mock source ends after its frames are queued
+            forward_system_audio(Box::new(source), mixer.clone()).await;
+            Some(mixer)
+        }
+        None => None,
+    };
+    for _ in 0..5 {
+        let mut frame = audio_frame(vec![0.0; 960], config);
+        if let Some(mixer) = &mixer {
+            mixer.lock().unwrap().mix(&mut frame);
+        }
+        encoder.send_frame(frame).await.unwrap();
+    }
+    let mut packets = 0;
+    while let Ok(Some(_)) = timeout(Duration::from_millis(300), encoder.recv_packet()).await {
+        packets += 1;
+    }
+    packets
+}
+
+#[tokio::test]
+async fn test_system_audio_flows_into_encoder() {
+    assert_eq!(encode_with_system_audio(None).await, 0);
+    let config = AudioConfig::new(48000, 2);
+    let source = MockLoopback {
+        config,
+        frames: (0..5).map(|_| audio_frame(tone(config), config)).collect(),
+    };
+    assert_eq!(encode_with_system_audio(Some(source)).await, 5);
+}
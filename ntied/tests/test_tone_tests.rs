@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use ntied::audio::test_tone_samples;
+
+const SAMPLE_RATE: u32 = 48000;
+
+#[test]
+fn test_tone_has_requested_frequency_and_amplitude() {
+    let samples = test_tone_samples(440.0, Duration::from_secs(1), 0.5, SAMPLE_RATE);
+    assert_eq!(samples.len(), SAMPLE_RATE as usize);
+
+    // Peak equals the volume, fades only shorten the ends
+    let peak = samples.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+    assert!((peak - 0.5).abs() < 0.01, "peak {peak}");
+
+    // A sine crosses zero upwards once per period
+    let crossings = samples
+        .windows(2)
+        .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+        .count();
+    assert!((439..=441).contains(&crossings), "crossings {crossings}");
+
+    // No clicks at the ends
+    assert!(samples[0].abs() < 0.01);
+    assert!(samples[samples.len() - 1].abs() < 0.01);
+}
+
+#[test]
+fn test_tone_volume_is_clamped() {
+    let samples = test_tone_samples(1000.0, Duration::from_millis(100), 3.0, SAMPLE_RATE);
+    let peak = samples.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+    assert!(peak <= 1.0);
+    assert!(test_tone_samples(1000.0, Duration::ZERO, 1.0, SAMPLE_RATE).is_empty());
+}
@@ -0,0 +1,165 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ntied::audio::{AdpcmEncoder, AdpcmVariant, AudioEncoder, CodecType, ContentType};
+use ntied::call::CallManager;
+use ntied::contact::{ContactHandle, ContactManager, ContactStatus, Usage};
+use ntied::packet::{AudioDataPacket, CallPacket, ContactProfile, Packet};
+use ntied_crypto::PrivateKey;
+use ntied_server::Server;
+use ntied_transport::{Address, ToAddress};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use uuid::Uuid;
+
+const PACKET_COUNT: u32 = 50;
+const FRAME_SAMPLES: usize = 320; // 20ms at 16kHz
+
+async fn start_server() -> (SocketAddr, JoinHandle<()>) {
+    let server = Server::new("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let _ = server.run().await;
+    });
+    (server_addr, handle)
+}
+
+async fn wait_until<F>(mut f: F, tries: usize, delay: Duration) -> bool
+where
+    F: FnMut() -> bool,
+{
+    for _ in 0..tries {
+        if f() {
+            return true;
+        }
+        sleep(delay).await;
+    }
+    false
+}
+
+async fn new_manager(server_addr: SocketAddr, name: &str) -> (Address, Arc<ContactManager>) {
+    let key = PrivateKey::generate().unwrap();
+    let address = key.public_key().to_address().unwrap();
+    let manager = ContactManager::new(
+        server_addr,
+        key,
+        ContactProfile {
+            name: name.to_string(),
+            avatar: None,
+        },
+    )
+    .await;
+    (address, Arc::new(manager))
+}
+
+/// Makes `from` send a contact request to `to` and `to` accept it.
+async fn befriend(from: &ContactManager, to: &ContactManager) -> (ContactHandle, ContactHandle) {
+    let outgoing = from.connect_contact(to.get_own_address()).await;
+    let address = timeout(Duration::from_secs(5), to.on_incoming_address())
+        .await
+        .expect("Timed out waiting for incoming contact")
+        .unwrap();
+    let incoming = to.connect_contact(address).await;
+    assert!(
+        wait_until(
+            || incoming.profile().is_some(),
+            50,
+            Duration::from_millis(100)
+        )
+        .await
+    );
+    incoming.accept().await.unwrap();
+    assert!(
+        wait_until(
+            || outgoing.status() == ContactStatus::Accepted
+                && outgoing.is_connected()
+                && incoming.is_connected(),
+            50,
+            Duration::from_millis(100),
+        )
+        .await
+    );
+    (outgoing, incoming)
+}
+
+fn encoded_audio_packets() -> Vec<CallPacket> {
+    let mut encoder = AdpcmEncoder::new(1, AdpcmVariant::Ima).unwrap();
+    let call_id = Uuid::now_v7();
+    (0..PACKET_COUNT)
+        .map(|sequence| {
+            let samples: Vec<f32> = (0..FRAME_SAMPLES)
+                .map(|i| ((sequence as usize * FRAME_SAMPLES + i) as f32 * 0.05).sin() * 0.3)
+                .collect();
+            CallPacket::AudioData(AudioDataPacket {
+                call_id,
+                sequence,
+                timestamp: sequence as u64 * 20_000,
+                codec: CodecType::ADPCM,
+                channels: 1,
+                data: encoder.encode(&samples).unwrap(),
+                content: ContentType::Speech,
+            })
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_audio_packets_are_counted() {
+    let (server_addr, server_handle) = start_server().await;
+    let (alice_addr, alice) = new_manager(server_addr, "Alice").await;
+    let (bob_addr, bob) = new_manager(server_addr, "Bob").await;
+    sleep(Duration::from_millis(400)).await;
+    let (to_bob, from_alice) = befriend(&alice, &bob).await;
+    let alice_before = alice.usage(bob_addr).await.unwrap();
+    let bob_before = bob.usage(alice_addr).await.unwrap();
+    assert!(alice_before.bytes_sent > 0);
+    assert_eq!(to_bob.call_usage(), Usage::default());
+
+    let packets = encoded_audio_packets();
+    let expected: u64 = packets
+        .iter()
+        .map(|p| bincode::serialize(&Packet::Call(p.clone())).unwrap().len() as u64)
+        .sum();
+    for packet in packets {
+        to_bob.send_call_packet(packet).await.unwrap();
+    }
+    assert!(
+        wait_until(
+            || from_alice.call_usage().bytes_received >= expected,
+            50,
+            Duration::from_millis(100)
+        )
+        .await,
+        "Bob did not receive all audio packets"
+    );
+    assert_eq!(
+        to_bob.call_usage(),
+        Usage {
+            bytes_sent: expected,
+            bytes_received: 0,
+        }
+    );
+    assert_eq!(
+        from_alice.call_usage(),
+        Usage {
+            bytes_sent: 0,
+            bytes_received: expected,
+        }
+    );
+    let alice_after = alice.usage(bob_addr).await.unwrap();
+    let bob_after = bob.usage(alice_addr).await.unwrap();
+    assert_eq!(alice_after.since(alice_before).bytes_sent, expected);
+    assert_eq!(bob_after.since(bob_before).bytes_received, expected);
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_call_bytes_without_call() {
+    let (server_addr, server_handle) = start_server().await;
+    let (_, alice) = new_manager(server_addr, "Alice").await;
+    let calls = CallManager::new(alice.clone());
+    assert!(calls.call_bytes().await.is_none());
+    assert!(alice.usage(alice.get_own_address()).await.is_none());
+    server_handle.abort();
+}
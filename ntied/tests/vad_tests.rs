@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+use ntied::audio::{
+    AudioConfig, AudioFrame, CodecParams, CodecType, Encoder, VadParams, VoiceActivityDetector,
+};
+use tokio::time::timeout;
+
+/// 20 ms of a 48 kHz mono tone with the given amplitude.
+fn frame(amplitude: f32) -> Vec<f32> {
+    (0..960)
+        .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin() * amplitude)
+        .collect()
+}
+
+#[test]
+fn test_speech_onset_is_not_truncated() {
+    let mut vad = VoiceActivityDetector::default();
+    for _ in 0..10 {
+        assert!(!vad.is_active(&frame(0.0)));
+    }
+    assert!(vad.is_active(&frame(0.3)));
+}
+
+#[test]
+fn test_silence_closes_after_hangover() {
+    let params = VadParams {
+        energy_threshold: 0.05,
+        hangover_frames: 4,
+    };
+    let mut vad = VoiceActivityDetector::new(params);
+    assert!(vad.is_active(&frame(0.3)));
+    for _ in 0..params.hangover_frames {
+        assert!(vad.is_active(&frame(0.01)));
+    }
+    assert!(!vad.is_active(&frame(0.01)));
+    // Speech in the hangover restarts it
+    assert!(vad.is_active(&frame(0.3)));
+    assert!(vad.is_active(&frame(0.0)));
+}
+
+#[test]
+fn test_energy_threshold_is_configurable() {
+    let quiet = frame(0.02);
+    assert!(VoiceActivityDetector::default().is_active(&quiet));
+    let mut vad = VoiceActivityDetector::new(VadParams {
+        energy_threshold: 0.1,
+        ..VadParams::default()
+    });
+    assert!(!vad.is_active(&quiet));
+}
+
+#[tokio::test]
+async fn test_encoder_skips_silent_frames() {
+    let vad = VadParams {
+        hangover_frames: 2,
+        ..VadParams::default()
+    };
+    let encoder = Encoder::with_vad(
+        AudioConfig::new(48000, 1),
+        CodecType::ADPCM,
+        CodecParams::voice(),
+        Some(vad),
+    );
+    let amplitudes = [0.0, 0.0, 0.3, 0.3, 0.0, 0.0, 0.0, 0.0];
+    for amplitude in amplitudes {
+        let frame = AudioFrame {
+            samples: frame(amplitude),
+            sample_rate: 48000,
+            channels: 1,
+            timestamp: Instant::now(),
+        };
+        encoder.send_frame(frame).await.unwrap();
+    }
+    // Two voiced frames and two frames of hangover
+    let mut sequences = Vec::new();
+    while let Ok(Some(packet)) = timeout(Duration::from_millis(500), encoder.recv_packet()).await {
+        sequences.push(packet.sequence);
+    }
+    assert_eq!(sequences, [0, 1, 2, 3]);
+    assert_eq!(encoder.stats().dtx_frames, 4);
+}
@@ -0,0 +1,87 @@
+use std::io::Cursor;
+use std::time::Duration;
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use ntied::audio::{AudioConfig, CodecType, MAX_VOICE_DURATION, encode_voice, read_wav};
+use ntied::models::{MessageKind, VoiceMessage};
+
+/// WAV file with a 440 Hz tone of `frames` samples per channel.
+fn wav(sample_rate: u32, channels: u16, frames: u32) -> Vec<u8> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut data = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut data, spec).unwrap();
+    for i in 0..frames {
+        let t = i as f32 / sample_rate as f32;
+        let sample = (2.0 * std::f32::consts::PI * 440.0 * t).sin() * 0.5;
+        for _ in 0..channels {
+            writer
+                .write_sample((sample * i16::MAX as f32) as i16)
+                .unwrap();
+        }
+    }
+    writer.finalize().unwrap();
+    data.into_inner()
+}
+
+#[test]
+fn test_wav_is_resampled_and_encoded() {
+    let (config, samples) = read_wav(Cursor::new(wav(44100, 1, 44100))).unwrap();
+    assert_eq!(config, AudioConfig::new(44100, 1));
+    assert_eq!(samples.len(), 44100);
+
+    let parts = encode_voice(config, &samples).unwrap();
+    assert_eq!(parts.len(), 1);
+    let voice = &parts[0];
+    assert_eq!(voice.codec, CodecType::ADPCM);
+    assert_eq!(voice.sample_rate, 48000);
+    assert!(
+        voice.duration_ms.abs_diff(1000) <= 20,
+        "{}",
+        voice.duration_ms
+    );
+    assert_eq!(voice.frames.len(), 50);
+    voice.validate().unwrap();
+}
+
+#[test]
+fn test_long_voice_is_split_into_messages() {
+    let (config, samples) = read_wav(Cursor::new(wav(48000, 2, 48000 * 5))).unwrap();
+    let parts = encode_voice(config, &samples).unwrap();
+    assert!(parts.len() > 1);
+    for voice in &parts {
+        voice.validate().unwrap();
+        assert!(voice.data_len() <= VoiceMessage::MAX_DATA_LEN);
+    }
+    let duration: Duration = parts.iter().map(VoiceMessage::duration).sum();
+    assert_eq!(duration, Duration::from_secs(5));
+}
+
+#[test]
+fn test_invalid_wav_is_rejected() {
+    assert!(read_wav(Cursor::new(b"not a wav".to_vec())).is_err());
+    assert!(read_wav(Cursor::new(wav(44100, 3, 100))).is_err());
+    assert!(read_wav(Cursor::new(wav(4000, 1, 100))).is_err());
+    assert!(read_wav(Cursor::new(wav(44100, 1, 0))).is_err());
+    let frames = 8000 * (MAX_VOICE_DURATION.as_secs() as u32 + 1);
+    assert!(read_wav(Cursor::new(wav(8000, 1, frames))).is_err());
+}
+
+#[test]
+fn test_voice_message_kind_round_trip() {
+    let (config, samples) = read_wav(Cursor::new(wav(16000, 1, 8000))).unwrap();
+    let voice = encode_voice(config, &samples).unwrap().remove(0);
+    let kind = MessageKind::Voice(voice);
+    assert_eq!(kind.display_text(), "Voice message (0.5 s)");
+    let MessageKind::Voice(parsed) =
+        MessageKind::parse(kind.name().to_string(), kind.content()).unwrap()
+    else {
+        panic!("Unexpected message kind");
+    };
+    assert_eq!(parsed.duration_ms, 500);
+    assert_eq!(parsed.frames.len(), 25);
+}
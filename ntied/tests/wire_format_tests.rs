@@ -0,0 +1,312 @@
+use ntied::audio::{
+    AdpcmVariant, CodecCapabilities, CodecParams, CodecType, ContentType, NegotiatedCodec,
+};
+use ntied::models::Base64;
+use ntied::packet::{
+    AudioDataPacket, CallAcceptPacket, CallEndPacket, CallPacket, CallRejectPacket,
+    CallStartPacket, ChatConflictPacket, ChatMessageAckPacket, ChatMessageKind, ChatMessagePacket,
+    ChatPacket, CodecAnswerPacket, CodecOfferPacket, ContactAcceptPacket, ContactHelloPacket,
+    ContactKeyRotationPacket, ContactPacket, ContactProfile, ContactProfileUpdatePacket,
+    ContactRejectPacket, ContactRequestPacket, Packet, VideoDataPacket,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+/// Checks that `value` survives encoding and decoding, returns its encoding.
+fn assert_roundtrip<T: Serialize + DeserializeOwned>(value: &T) -> Vec<u8> {
+    let bytes = bincode::serialize(value).unwrap();
+    let decoded: T = bincode::deserialize(&bytes).expect("Failed to decode encoded value");
+    assert_eq!(
+        bincode::serialize(&decoded).unwrap(),
+        bytes,
+        "Encoding changed after round trip"
+    );
+    bytes
+}
+
+/// Checks `packet` against an encoding captured from a previous version.
+fn assert_golden(packet: Packet, golden: &str) {
+    let bytes = assert_roundtrip(&packet);
+    assert_eq!(hex::encode(&bytes), golden, "Wire format changed");
+    let decoded = Packet::decode(&hex::decode(golden).unwrap()).expect("Failed to decode golden");
+    assert_eq!(bincode::serialize(&decoded).unwrap(), bytes);
+}
+
+fn id(value: u128) -> Uuid {
+    Uuid::from_u128(value)
+}
+
+fn profile() -> ContactProfile {
+    ContactProfile {
+        name: "Alice".to_string(),
+        avatar: Some(Base64(vec![0x89, 0x50])),
+    }
+}
+
+fn codec() -> NegotiatedCodec {
+    NegotiatedCodec {
+        codec: CodecType::ADPCM,
+        params: CodecParams {
+            sample_rate: 48000,
+            channels: 1,
+            bitrate: 32000,
+            fec: false,
+            dtx: false,
+            expected_packet_loss: 5,
+            complexity: 10,
+            adpcm_variant: AdpcmVariant::Ima,
+        },
+        is_offerer: true,
+    }
+}
+
+#[test]
+fn test_golden_contact_packets() {
+    let contact = |packet| Packet::Contact(packet);
+    assert_golden(
+        contact(ContactPacket::Request(ContactRequestPacket {
+            profile: profile(),
+            intro: None,
+        })),
+        "00000000000000000500000000000000416c6963650104000000000000006956413d",
+    );
+    assert_golden(
+        contact(ContactPacket::Accept(ContactAcceptPacket {
+            profile: profile(),
+        })),
+        "00000000010000000500000000000000416c6963650104000000000000006956413d",
+    );
+    assert_golden(
+        contact(ContactPacket::Reject(ContactRejectPacket {})),
+        "0000000002000000",
+    );
+    assert_golden(
+        contact(ContactPacket::ProfileUpdate(ContactProfileUpdatePacket {
+            profile: ContactProfile {
+                name: "Bob".to_string(),
+                avatar: None,
+            },
+        })),
+        "00000000030000000300000000000000426f6200",
+    );
+    assert_golden(
+        contact(ContactPacket::KeyRotation(ContactKeyRotationPacket {
+            public_key: vec![1, 2, 3],
+            signature: vec![4, 5],
+            previous_key: None,
+        })),
+        "0000000004000000030000000000000001020302000000000000000405",
+    );
+    assert_golden(
+        contact(ContactPacket::Hello(ContactHelloPacket {
+            version: 1,
+            features: 3,
+        })),
+        "00000000050000000100000003000000",
+    );
+}
+
+#[test]
+fn test_golden_chat_packets() {
+    // Plain messages keep the layout of clients before signed messages
+    assert_golden(
+        Packet::Chat(ChatPacket::Message(ChatMessagePacket {
+            message_id: id(1),
+            log_id: 2,
+            kind: ChatMessageKind::Text("hi".to_string()),
+            reply_to: None,
+            signature: Vec::new(),
+        })),
+        "010000000000000010000000000000000000000000000000000000000000000102000000000000000000000002000000000000006869",
+    );
+    assert_golden(
+        Packet::Chat(ChatPacket::SignedMessage(ChatMessagePacket {
+            message_id: id(1),
+            log_id: 2,
+            kind: ChatMessageKind::Text("hi".to_string()),
+            reply_to: Some(id(3)),
+            signature: vec![4, 5],
+        })),
+        "0100000007000000100000000000000000000000000000000000000000000001020000000000000000000000020000000000000068690110000000000000000000000000000000000000000000000302000000000000000405",
+    );
+    assert_golden(
+        Packet::Chat(ChatPacket::MessageAck(ChatMessageAckPacket {
+            message_id: id(1),
+            log_id: 2,
+        })),
+        "01000000010000001000000000000000000000000000000000000000000000010200000000000000",
+    );
+    assert_golden(
+        Packet::Chat(ChatPacket::Conflict(ChatConflictPacket {
+            message_id: id(1),
+        })),
+        "0100000002000000100000000000000000000000000000000000000000000001",
+    );
+}
+
+#[test]
+fn test_golden_call_packets() {
+    let call = |packet| Packet::Call(packet);
+    assert_golden(
+        call(CallPacket::Start(CallStartPacket { call_id: id(1) })),
+        "0200000000000000100000000000000000000000000000000000000000000001",
+    );
+    assert_golden(
+        call(CallPacket::Accept(CallAcceptPacket { call_id: id(1) })),
+        "0200000001000000100000000000000000000000000000000000000000000001",
+    );
+    assert_golden(
+        call(CallPacket::Reject(CallRejectPacket { call_id: id(1) })),
+        "0200000002000000100000000000000000000000000000000000000000000001",
+    );
+    assert_golden(
+        call(CallPacket::End(CallEndPacket { call_id: id(1) })),
+        "0200000003000000100000000000000000000000000000000000000000000001",
+    );
+    assert_golden(
+        call(CallPacket::AudioData(AudioDataPacket {
+            call_id: id(1),
+            sequence: 2,
+            timestamp: 3,
+            codec: CodecType::ADPCM,
+            channels: 1,
+            data: vec![4, 5, 6],
+            content: ContentType::Music,
+        })),
+        "0200000004000000100000000000000000000000000000000000000000000001020000000300000000000000010000000100030000000000000004050601000000",
+    );
+    assert_golden(
+        call(CallPacket::VideoData(VideoDataPacket {
+            call_id: id(1),
+            timestamp: 2,
+            frame: vec![3, 4],
+        })),
+        "0200000005000000100000000000000000000000000000000000000000000001020000000000000002000000000000000304",
+    );
+    assert_golden(
+        call(CallPacket::CodecOffer(CodecOfferPacket {
+            call_id: id(1),
+            capabilities: CodecCapabilities {
+                codecs: vec![CodecType::ADPCM, CodecType::Raw],
+                sample_rates: vec![48000],
+                max_channels: 2,
+                max_bitrate: 510000,
+                supports_fec: false,
+                supports_dtx: false,
+            },
+            preferred_codec: codec(),
+        })),
+        "020000000600000010000000000000000000000000000000000000000000000102000000000000000100000000000000010000000000000080bb0000020030c8070000000100000080bb00000100007d00000000050a0000000001",
+    );
+    assert_golden(
+        call(CallPacket::CodecAnswer(CodecAnswerPacket {
+            call_id: id(1),
+            negotiated_codec: codec(),
+        })),
+        "02000000070000001000000000000000000000000000000000000000000000010100000080bb00000100007d00000000050a0000000001",
+    );
+}
+
+#[test]
+fn test_roundtrip_edge_values() {
+    assert_roundtrip(&Packet::Chat(ChatPacket::Message(ChatMessagePacket {
+        message_id: Uuid::max(),
+        log_id: u64::MAX,
+        kind: ChatMessageKind::Text("Привет, 👋".repeat(100)),
+        reply_to: None,
+        signature: Vec::new(),
+    })));
+    assert_roundtrip(&Packet::Call(CallPacket::AudioData(AudioDataPacket {
+        call_id: Uuid::nil(),
+        sequence: u32::MAX,
+        timestamp: u64::MAX,
+        codec: CodecType::Raw,
+        channels: 2,
+        data: vec![0xff; 1920],
+        content: ContentType::Speech,
+    })));
+    assert_roundtrip(&Packet::Contact(ContactPacket::Request(
+        ContactRequestPacket {
+            profile: ContactProfile {
+                name: String::new(),
+                avatar: Some(Base64(Vec::new())),
+            },
+            intro: None,
+        },
+    )));
+}
+
+#[test]
+fn test_contact_request_intro_is_compatible() {
+    let request = |intro: Option<&str>| {
+        Packet::Contact(ContactPacket::Request(ContactRequestPacket {
+            profile: profile(),
+            intro: intro.map(str::to_string),
+        }))
+    };
+    let legacy = assert_roundtrip(&request(None));
+    let bytes = assert_roundtrip(&request(Some("Hi, it's Alice")));
+    // Older clients read the request and ignore the appended note
+    assert!(bytes.starts_with(&legacy));
+    assert!(bytes.len() > legacy.len());
+    match Packet::decode(&bytes).unwrap() {
+        Packet::Contact(ContactPacket::Request(packet)) => {
+            assert_eq!(packet.intro.as_deref(), Some("Hi, it's Alice"));
+        }
+        packet => panic!("Unexpected packet: {packet:?}"),
+    }
+    match Packet::decode(&legacy).unwrap() {
+        Packet::Contact(ContactPacket::Request(packet)) => assert_eq!(packet.intro, None),
+        packet => panic!("Unexpected packet: {packet:?}"),
+    }
+}
+
+#[test]
+fn test_profile_without_avatar_is_compatible() {
+    // Profiles of clients without avatars end after the name
+    let legacy = "00000000030000000300000000000000426f62";
+    match Packet::decode(&hex::decode(legacy).unwrap()).unwrap() {
+        Packet::Contact(ContactPacket::ProfileUpdate(packet)) => {
+            assert_eq!(packet.profile.name, "Bob");
+            assert!(packet.profile.avatar.is_none());
+        }
+        packet => panic!("Unexpected packet: {packet:?}"),
+    }
+    let legacy = "00000000000000000500000000000000416c696365";
+    match Packet::decode(&hex::decode(legacy).unwrap()).unwrap() {
+        Packet::Contact(ContactPacket::Request(packet)) => {
+            assert_eq!(packet.profile.name, "Alice");
+            assert!(packet.profile.avatar.is_none());
+            assert_eq!(packet.intro, None);
+        }
+        packet => panic!("Unexpected packet: {packet:?}"),
+    }
+}
+
+#[test]
+fn test_key_rotation_previous_key_is_compatible() {
+    let rotation = |previous_key| {
+        Packet::Contact(ContactPacket::KeyRotation(ContactKeyRotationPacket {
+            public_key: vec![1, 2, 3],
+            signature: vec![4, 5],
+            previous_key,
+        }))
+    };
+    let legacy = assert_roundtrip(&rotation(None));
+    let bytes = assert_roundtrip(&rotation(Some(vec![6, 7])));
+    // Older clients read the announcement and ignore the old key
+    assert!(bytes.starts_with(&legacy));
+    match Packet::decode(&bytes).unwrap() {
+        Packet::Contact(ContactPacket::KeyRotation(packet)) => {
+            assert_eq!(packet.previous_key, Some(vec![6, 7]));
+        }
+        packet => panic!("Unexpected packet: {packet:?}"),
+    }
+    match Packet::decode(&legacy).unwrap() {
+        Packet::Contact(ContactPacket::KeyRotation(packet)) => {
+            assert_eq!(packet.previous_key, None)
+        }
+        packet => panic!("Unexpected packet: {packet:?}"),
+    }
+}